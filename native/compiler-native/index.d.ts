@@ -13,6 +13,14 @@ export interface RuntimeCode {
   bundle: string
   npmImports: Array<ScriptImport>
   errors: Array<string>
+  /** Non-fatal diagnostics from expressions (e.g. an event handler mutating a prop) */
+  warnings: Array<string>
+  /** Console calls and `debugger` statements removed from the script and expressions */
+  strippedDebugStatements: number
+  /** Lazy handler chunk, when a `zen:lazy` handler's imports were split off */
+  chunks: Array<BundleChunk>
+  /** `Z-ERR-ES-TARGET` for syntax that could not be lowered to `target_es` */
+  esTargetErrors: Array<string>
 }
 export interface ScriptImport {
   source: string
@@ -25,6 +33,83 @@ export declare function generateCodegenIntent(): string
  * Manifest export for the bundler's capability-based chunking.
  * This is the Compiler → Bundler handshake contract.
  */
+/** Additions to and overrides of the built-in tables for one compile */
+export interface HtmlExtensions {
+  /** Lowercase SVG attribute name → the case to restore (e.g. `"transformorigin": "transformOrigin"`) */
+  extraSvgAttrCase: Record<string, string>
+  /** More SVG elements whose attributes are case-corrected */
+  extraSvgTags: Array<string>
+  /** More elements written self-closing when they have no children */
+  extraVoidElements: Array<string>
+  /** Built-in void elements written with a closing tag instead */
+  nonVoidOverrides: Array<string>
+}
+/**
+ * The built-in tables for the JS side: `svgAttrCase`, `svgTags` and
+ * `voidElements`, sorted
+ */
+export declare function getDefaultHtmlTablesNative(): any
+export interface I18nOptions {
+  /** Collect static message keys into `CompileResult.i18n_keys` */
+  extract: boolean
+  /** Messages for the build locale; matching single-argument calls become string literals */
+  inlineLocale?: Record<string, string>
+}
+/** What `strip_debug` removes. The default removes nothing. */
+export interface StripDebug {
+  /** Remove `console.<method>(...)` calls */
+  console: boolean
+  /** Remove `debugger` statements */
+  debugger: boolean
+  /** Console methods to keep (e.g. `["error", "warn"]`) */
+  keep: Array<string>
+  /** Remove `zenAssert(...)` calls */
+  asserts: boolean
+}
+/** Hydration payload budgets. Each exceeded limit is reported as an error. */
+export interface Budgets {
+  maxExpressions?: number
+  maxBundleBytes?: number
+  maxStateKeys?: number
+  maxBindings?: number
+}
+/** Thresholds for one kind of expression; unset fields use the kind's default */
+export interface ComplexityThresholds {
+  maxLength?: number
+  maxNodes?: number
+  maxDepth?: number
+  maxDeps?: number
+}
+/** Expression complexity thresholds by kind (see module docs) */
+export interface ComplexityLimits {
+  /** Render expressions outside loops */
+  render?: ComplexityThresholds
+  /** Render expressions in a loop body */
+  loopBody?: ComplexityThresholds
+  /** Event handlers */
+  handler?: ComplexityThresholds
+}
+/**
+ * Measured hydration payload of a page, always reported in the manifest.
+ * Taken at finalize time, i.e. after transform has produced the final bindings.
+ */
+export interface PayloadMetrics {
+  expressions: number
+  bundleBytes: number
+  stateKeys: number
+  bindings: number
+  /** Expressions inside `zen:static` subtrees (evaluated once, never updated) */
+  frozenExpressions: number
+}
+/** A component tag and the registry entry it resolved to */
+export interface ComponentUsage {
+  /** Tag as written in the template (e.g. `ui.Button`) */
+  tag: string
+  /** Registry key it resolved to */
+  component: string
+  /** Source path of the resolved component */
+  path: string
+}
 export interface ZenManifestExport {
   /** Entry point path */
   entry: string
@@ -38,6 +123,11 @@ export interface ZenManifestExport {
   isStatic: boolean
   /** CSS classes used by this page (for pruning) */
   cssClasses: Array<string>
+  /**
+   * A `class` expression renders names not known at compile time, so
+   * `css_classes` may be incomplete and no CSS should be pruned
+   */
+  hasDynamicClasses: boolean
   /** Required runtime capabilities (as strings for JS interop) */
   requiredCapabilities: Array<string>
   /** Compiled script content (author code) */
@@ -50,13 +140,149 @@ export interface ZenManifestExport {
   styles: string
   /** NPM imports */
   npmImports: string
+  /** Measured hydration payload (expressions, bundle size, state keys, bindings) */
+  metrics: PayloadMetrics
+  /** Per component: declared props never received in this compilation */
+  unusedProps: Record<string, Array<string>>
+  /** Resolved component for each tag usage (tag, registry key, source path) */
+  componentUsages: Array<ComponentUsage>
+  /** Component usages inlined as plain markup, without scope registration */
+  inlinedComponents: number
+  /** Console calls and `debugger` statements removed by `strip_debug` */
+  strippedDebugStatements: number
+  /** Per component: props passed conditionally (`name?={expr}`) with a dynamic value */
+  conditionalProps: Record<string, Array<string>>
+  /** Per component: static props passed as number/boolean literals (`"pageSize: number"`) */
+  coercedProps: Record<string, Array<string>>
+  /** Per component: events emitted via `zenDispatch()` (handled with `on:name`) */
+  componentEvents: Record<string, Array<string>>
+  /** Declared props (page props, or the component's own props in component mode) */
+  props: Array<string>
+  /** Slot names a standalone component exposes (`default` for the unnamed slot) */
+  slots: Array<string>
+  /** Expression ids of `zen:action` handlers on progressively enhanced forms */
+  enhancedForms: Array<string>
+  /** Hydrated islands with their size and dependencies (islands mode only) */
+  islands: Array<IslandManifest>
+  /** Modules emitted next to `bundle` and imported by it on demand (see `lazy.rs`) */
+  chunks: Array<BundleChunk>
+  /** `zen:if-env` / `zen:env` regions left out for this build's environment */
+  strippedEnvRegions: Array<StrippedEnvRegion>
+  /** When each instance with a `client:*` loading directive hydrates */
+  hydration: Array<InstanceHydration>
+  /** Complexity of every expression, whether or not it exceeded a threshold */
+  expressionMetrics: Array<ExpressionMetrics>
+  /** Routes the page navigates to, for prefetching (see `navigation.rs`) */
+  navigation: Navigation
+  /** Repeated inline SVGs moved into the page's sprite (see `svg_sprite.rs`) */
+  svgSprite: SvgSprite
+  /** Item keys of loops over statically known objects (see `loop_shapes.rs`) */
+  loopItemKeys: Array<LoopItemKeys>
+  /** Class names the compiler generated, by purpose (see `class_names.rs`) */
+  generatedClasses: Record<string, Array<string>>
+}
+/** Manifest entry: the item keys of a loop over a statically known list */
+export interface LoopItemKeys {
+  /** Id of the loop source expression */
+  source: string
+  /** The loop source as written (`LoopContext.map_source` in the body) */
+  expression: string
+  /** The item variable */
+  item: string
+  /** Keys present on at least one item, sorted */
+  keys: Array<string>
+}
+/** Manifest entry: what the sprite replaced */
+export interface SvgSprite {
+  /** Symbols in the sprite */
+  symbols: number
+  /** Occurrences rendered as `<use>` */
+  uses: number
+  /** HTML bytes saved, net of the sprite itself */
+  bytesSaved: number
+}
+/** Manifest entry: where the page navigates */
+export interface Navigation {
+  /** Static routes of `zenLink()` calls and internal links, sorted */
+  targets: Array<string>
+  /** Source of each `zenLink()` argument only known at runtime, sorted */
+  dynamic: Array<string>
+}
+/** Measured complexity of one expression, always reported in the manifest */
+export interface ExpressionMetrics {
+  id: string
+  /** `render`, `loop` or `handler` */
+  kind: string
+  length: number
+  nodes: number
+  depth: number
+  deps: number
+}
+/** Manifest entry for an instance whose hydration a loading directive decides */
+export interface InstanceHydration {
+  /** `instN` of the instance */
+  instance: string
+  /** Registry key of the component */
+  component: string
+  /** `load`, `visible` or `idle`; inherited from `group` when nested in it */
+  strategy: string
+  /** Outermost instance whose trigger hydrates this one (itself unless nested) */
+  group: string
+}
+/** A region left out of this build */
+export interface StrippedEnvRegion {
+  /** The environments the region is for, as written (`staging preview`) */
+  env: string
+  line: number
+  column: number
+}
+/** An extra module emitted next to the main bundle */
+export interface BundleChunk {
+  /** File name, imported relative to the main bundle (`./about.lazy.js`) */
+  name: string
+  code: string
+}
+/** Manifest entry for one island */
+export interface IslandManifest {
+  id: string
+  expressions: number
+  /** Bytes of the island's init block plus its expression sources */
+  bytes: number
+  /** Page state keys and component scopes the island reads */
+  deps: Array<string>
 }
 export interface FinalizedOutput {
   html: string
   hasErrors: boolean
   errors: Array<string>
+  /** Non-fatal diagnostics (e.g. a11y warnings) that do not block output */
+  warnings: Array<string>
   /** Manifest for bundler's capability-based chunking */
   manifest?: ZenManifestExport
+  /** Critical images and fonts (also injected into `<head>` when the document has one) */
+  preloadHints: Array<PreloadHint>
+}
+export interface PreloadHint {
+  href: string
+  /** Value of the link's `as` attribute (`image` or `font`) */
+  asType: string
+  media?: string
+}
+export interface StyleChunk {
+  /**
+   * Component file stem for shared chunks (`.global` for its global blocks),
+   * page file stem otherwise (`-2`, `-3`, ... when a shared chunk splits the page's)
+   */
+  name: string
+  /** First 8 hex characters of the SHA-256 of `css` */
+  hash: string
+  css: string
+  /** Source component paths of the styles in the chunk (the page's own have none) */
+  components: Array<string>
+  /** Emit once for the site and reference it from every page */
+  shared: boolean
+  /** Position in the cascade: chunks apply in ascending order */
+  order: number
 }
 /**
  * Full Zenith compilation entry point - the "One True Syscall"
@@ -70,8 +296,91 @@ export interface ParseFullOptions {
   components?: any
   layout?: any
   props?: any
+  /** Optional result sections to keep ("ir", "manifest"). All are included when absent. */
+  include?: Array<string>
+  /** Hydration payload budgets; exceeded limits fail the compile */
+  budgets?: Budgets
+  /**
+   * Expression complexity thresholds per kind; unset ones use the defaults
+   * (see `expr_complexity.rs`)
+   */
+  expressionComplexity?: ComplexityLimits
+  /** Translation key extraction and build-time locale inlining */
+  i18n?: I18nOptions
+  /** Filters available at runtime for pipe syntax (`{value | name}`) */
+  filters?: Array<string>
+  /** Additions to the SVG attribute case, SVG element and void element tables */
+  htmlExtensions?: HtmlExtensions
+  /** Register <head> text expressions for post-hydration updates */
+  reactiveHead?: boolean
+  /** Emit CRLF line endings in the HTML when the source used them */
+  preserveCrlf?: boolean
+  /** Maximum `<link rel="preload">` hints (default 4, 0 disables them) */
+  preloadLimit?: number
+  /** Pass untyped static props that are exactly `true`/`false` or an integer as literals */
+  coerceStaticProps?: boolean
+  /** Lower `.map()`, ternary, and `&&` markup expressions to template fragments */
+  astClassifier?: boolean
+  /** Hydrate only `zen:island` subtrees and interactive components */
+  islands?: boolean
+  /** Inline component usages that need nothing at runtime as plain markup */
+  inlineComponents?: boolean
+  /** Content for a slot the component does not declare is an error, not a warning */
+  strictSlots?: boolean
+  /** Unknown IR fields are errors instead of being carried through (debugging) */
+  strictIr?: boolean
+  /** Invalid component registry entries fail the compile instead of staying unresolved */
+  strictRegistry?: boolean
+  /** Production output: compact HTML and bundle */
+  minify?: boolean
+  /** Remove `console.*` calls and `debugger` statements from the bundle */
+  stripDebug?: StripDebug
+  /** Extra or overriding `@zen-screen` breakpoints (name → media condition or min-width) */
+  breakpoints?: Record<string, string>
+  /** Bundle hydrates inside an iframe `srcdoc` preview */
+  embedMode?: boolean
+  /** Add `<link rel="modulepreload">` for the runtime and npm imports */
+  modulepreload?: boolean
+  /** Import specifier → URL used for modulepreload links */
+  importUrlMap?: Record<string, string>
+  /** Add `<link rel="prefetch">` for the assets of the routes the page navigates to */
+  prefetchLinks?: boolean
+  /** Route → asset URL used for prefetch links */
+  prefetchRoutes?: Record<string, string>
+  /** Share repeated inline SVGs through one hidden sprite */
+  svgSprite?: boolean
+  /** Build environment for `zen:if-env` / `zen:env` regions */
+  env?: string
+  /** Syntax level of the bundle: `es2017`, `es2020` or `esnext` (default) */
+  targetEs?: string
+  /** Warn for template classes without a style rule and for unused class rules */
+  warnUnstyledClasses?: boolean
+  /** Regex of class names the unstyled-class check skips (e.g. `^js-`) */
+  unstyledClassIgnore?: string
+  /** Warn for member access on state that may start undefined or null */
+  strictNullish?: boolean
+  /** Deepest markup nesting accepted before `Z-ERR-MAX-DEPTH` (default 5000) */
+  maxDepth?: number
+  /** Re-check the compiled output against the ground-truth rules (default: debug builds) */
+  verifyInvariants?: boolean
+  /** Scope component styles: `"class-suffix"`, `"where-wrapper"` or `"attribute-selector"` */
+  cssScopeStrategy?: string
+  /** Prefix of the class names the compiler generates (default `zen-`) */
+  classPrefix?: string
+  /** Component paths whose styles go into their own shared chunk */
+  sharedComponents?: Array<string>
 }
 export declare function parseFullZenNative(source: string, filePath: string, optionsJson: string): any
+/**
+ * `parse_full_zen_native` over MessagePack: versioned `ParseFullOptions` in,
+ * versioned result out
+ */
+export declare function parseFullZenMsgpack(source: string, filePath: string, optionsMsgpack: Buffer): Buffer
+export declare function parseFullZenBatchMsgpack(requestMsgpack: Buffer): Buffer
+/** JSON Schemas of the boundary payloads by type name (builds with the `schema` feature only) */
+export declare function getJsonSchemasNative(): Record<string, any>
+/** Editor-facing parse entry point (template node tree and expressions only) */
+export declare function parseZenTemplateNative(source: string, filePath: string): string
 export interface Binding {
   id: string
   type: string
@@ -79,12 +388,36 @@ export interface Binding {
   expression: string
   location?: SourceLocation
   loopContext?: LoopContext
+  /**
+   * The element also has a static value for this attribute (duplicate `class`/`style`):
+   * the runtime appends the dynamic value to `static_prefix` instead of replacing it
+   */
+  mergeWithStatic: boolean
+  staticPrefix?: string
+  /** Component instance the expression was promoted from */
+  origin?: ExpressionOrigin
+  /** `"aria"` or `"data"`: the value is stringified before it is set (see `attr_coerce.rs`) */
+  coerce?: string
+  /**
+   * Loop over a long static list: only the first `chunk_size` items are
+   * server-rendered, the rest are in the loop's `application/zen-data` block
+   */
+  virtualized: boolean
+  chunkSize?: number
+  /** Boolean state held `true` while this event handler runs (`zen:pending`) */
+  pending?: string
+  /**
+   * Text inside an `aria-live` region: the text after the marker is server-rendered
+   * and updated in place (see `live_regions.rs`)
+   */
+  liveRegion: boolean
 }
 export interface TransformOutput {
   html: string
   bindings: Array<Binding>
 }
 export declare function transformTemplateNative(nodesJson: string, expressionsJson: string): TransformOutput
+export declare function transformNodesNative(nodesJson: string, expressionsJson: string, scopeJson?: string | undefined | null): string
 export interface CompilerError {
   code: string
   errorType: string
@@ -100,6 +433,20 @@ export interface SourceLocation {
   line: number
   column: number
 }
+/**
+ * Where a promoted component expression came from (component resolution gives
+ * each instance its own copy, e.g. `expr_3` of `Card.zen` becomes `expr_3_inst7`)
+ */
+export interface ExpressionOrigin {
+  componentPath: string
+  componentName: string
+  /** `instN`, the key of the instance's scope */
+  instanceId: string
+  /** Expression id within the component's own template */
+  originalId: string
+  /** Location within the component file */
+  originalLocation: SourceLocation
+}
 export interface LoopContext {
   variables: Array<string>
   mapSource?: string
@@ -310,10 +310,16 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { generateCodegenIntent, ResolutionContext, parseFullZenNative, transformTemplateNative, compileBridge } = nativeBinding
+const { generateCodegenIntent, getDefaultHtmlTablesNative, ResolutionContext, parseFullZenNative, parseFullZenMsgpack, parseFullZenBatchMsgpack, getJsonSchemasNative, parseZenTemplateNative, transformTemplateNative, transformNodesNative, compileBridge } = nativeBinding
 
 module.exports.generateCodegenIntent = generateCodegenIntent
+module.exports.getDefaultHtmlTablesNative = getDefaultHtmlTablesNative
 module.exports.ResolutionContext = ResolutionContext
 module.exports.parseFullZenNative = parseFullZenNative
+module.exports.parseFullZenMsgpack = parseFullZenMsgpack
+module.exports.parseFullZenBatchMsgpack = parseFullZenBatchMsgpack
+module.exports.getJsonSchemasNative = getJsonSchemasNative
+module.exports.parseZenTemplateNative = parseZenTemplateNative
 module.exports.transformTemplateNative = transformTemplateNative
+module.exports.transformNodesNative = transformNodesNative
 module.exports.compileBridge = compileBridge
@@ -0,0 +1,307 @@
+//! ARIA and Data Attribute Coercion
+//!
+//! Dynamic `aria-*` and `data-*` attributes always hold strings, whatever the
+//! expression evaluates to. Their bindings carry a coercion marker and the
+//! generated wrapper converts the value before the runtime sets it:
+//!
+//! - `aria-*`: `String(v)`, so booleans become the literal `"true"` / `"false"`
+//!   assistive technology expects (`aria-hidden={false}` stays `"false"`; these
+//!   names are never treated as boolean attributes that are dropped when false)
+//! - `data-*`: primitives through `String(v)`, objects and arrays through
+//!   `JSON.stringify`, with `Z-WARN-DATA-ATTR-OBJECT` when the value is known to be
+//!   one at compile time
+//!
+//! `null` and `undefined` remove the attribute. Values that fold statically
+//! (literals and initial state) are rendered into the SSR HTML by the same rules.
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{ArrayExpressionElement, Expression, ObjectPropertyKind, PropertyKey};
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+use std::collections::HashMap;
+
+use crate::validate::{AttributeValue, TemplateNode, ZenIR};
+
+/// How a dynamic attribute value is converted to its string form
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AttrCoercion {
+    Aria,
+    Data,
+}
+
+impl AttrCoercion {
+    /// Coercion for an attribute name; `data-zen-*` markers are the compiler's own
+    pub(crate) fn for_attribute(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name.starts_with("aria-") {
+            Some(Self::Aria)
+        } else if name.starts_with("data-") && !name.starts_with("data-zen-") {
+            Some(Self::Data)
+        } else {
+            None
+        }
+    }
+
+    /// Marker recorded on the binding (`Binding.coerce`)
+    pub(crate) fn marker(self) -> &'static str {
+        match self {
+            Self::Aria => "aria",
+            Self::Data => "data",
+        }
+    }
+
+    /// JS function applied to the expression's value in the generated wrapper
+    pub(crate) fn js_wrapper(self) -> &'static str {
+        match self {
+            Self::Aria => "((v) => v == null ? v : String(v))",
+            Self::Data => {
+                "((v) => v == null ? v : typeof v === \"object\" ? JSON.stringify(v) : String(v))"
+            }
+        }
+    }
+
+    /// SSR value of the attribute from statically known values; `None` renders nothing
+    /// (unknown at compile time, or nullish)
+    pub(crate) fn fold(
+        self,
+        code: &str,
+        initial_values: &HashMap<String, String>,
+        initial_objects: &HashMap<String, String>,
+    ) -> Option<String> {
+        let code = code.trim();
+        if let Some(json) = literal_json(code).or_else(|| initial_objects.get(code).cloned()) {
+            // `String({...})` is "[object Object]": nothing worth rendering for aria
+            return (self == Self::Data).then_some(json);
+        }
+        match crate::static_eval::static_eval(code, initial_values)?.as_str() {
+            "null" | "undefined" => None,
+            value => Some(value.to_string()),
+        }
+    }
+}
+
+/// JSON of every state initialized with an object or array literal
+pub(crate) fn initial_object_values(
+    all_states: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    all_states
+        .iter()
+        .filter_map(|(name, init)| literal_json(init).map(|json| (name.clone(), json)))
+        .collect()
+}
+
+/// `Z-WARN-DATA-ATTR-OBJECT` for data attributes bound to a known object or array
+pub(crate) fn check_data_attribute_objects(ir: &ZenIR) -> Vec<String> {
+    let objects = initial_object_values(&ir.all_states);
+    let mut warnings = Vec::new();
+    collect_object_warnings(&ir.template.nodes, ir, &objects, &mut warnings);
+    warnings
+}
+
+fn collect_object_warnings(
+    nodes: &[TemplateNode],
+    ir: &ZenIR,
+    objects: &HashMap<String, String>,
+    warnings: &mut Vec<String>,
+) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                for attr in &el.attributes {
+                    let AttributeValue::Dynamic(expr) = &attr.value else {
+                        continue;
+                    };
+                    if AttrCoercion::for_attribute(&attr.name) != Some(AttrCoercion::Data) {
+                        continue;
+                    }
+                    let code = expr.code.trim();
+                    if literal_json(code).is_some() || objects.contains_key(code) {
+                        warnings.push(format!(
+                            "Z-WARN-DATA-ATTR-OBJECT: `{}={{{}}}` on <{}> in {} is an object; it is serialized with JSON.stringify. Data attributes hold strings, which is rarely what an object is meant for: pass an id or other primitive, or use `prop:` to hand the object to a DOM property.",
+                            attr.name, code, el.tag, ir.file_path
+                        ));
+                    }
+                }
+                collect_object_warnings(&el.children, ir, objects, warnings);
+            }
+            TemplateNode::Component(c) => {
+                collect_object_warnings(&c.children, ir, objects, warnings)
+            }
+            TemplateNode::ConditionalFragment(cf) => {
+                collect_object_warnings(&cf.consequent, ir, objects, warnings);
+                collect_object_warnings(&cf.alternate, ir, objects, warnings);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    collect_object_warnings(nodes, ir, objects, warnings);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => {
+                collect_object_warnings(&of.fragment, ir, objects, warnings)
+            }
+            TemplateNode::LoopFragment(lf) => {
+                collect_object_warnings(&lf.body, ir, objects, warnings)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `JSON.stringify` of an object or array literal made only of literals
+pub(crate) fn literal_json(code: &str) -> Option<String> {
+    let code = code.trim();
+    if !code.starts_with('{') && !code.starts_with('[') {
+        return None;
+    }
+    let allocator = Allocator::default();
+    let expr = Parser::new(&allocator, code, SourceType::default())
+        .parse_expression()
+        .ok()?;
+    if !matches!(
+        expr.without_parentheses(),
+        Expression::ObjectExpression(_) | Expression::ArrayExpression(_)
+    ) {
+        return None;
+    }
+    json_text(&expr)
+}
+
+/// Written out by hand: a `serde_json::Map` would reorder the keys, and
+/// `JSON.stringify` keeps them in source order
+fn json_text(expr: &Expression) -> Option<String> {
+    Some(match expr.without_parentheses() {
+        Expression::StringLiteral(lit) => serde_json::to_string(lit.value.as_str()).ok()?,
+        Expression::NumericLiteral(lit) if lit.value.is_finite() => {
+            if lit.value.fract() == 0.0 && lit.value.abs() < 1e15 {
+                (lit.value as i64).to_string()
+            } else {
+                lit.value.to_string()
+            }
+        }
+        Expression::BooleanLiteral(lit) => lit.value.to_string(),
+        Expression::NullLiteral(_) => "null".to_string(),
+        Expression::ArrayExpression(array) => {
+            let elements = array
+                .elements
+                .iter()
+                .map(|element| match element {
+                    ArrayExpressionElement::SpreadElement(_) => None,
+                    ArrayExpressionElement::Elision(_) => Some("null".to_string()),
+                    other => json_text(other.as_expression()?),
+                })
+                .collect::<Option<Vec<_>>>()?;
+            format!("[{}]", elements.join(","))
+        }
+        Expression::ObjectExpression(object) => {
+            let mut entries: Vec<(String, String)> = Vec::new();
+            for property in &object.properties {
+                let ObjectPropertyKind::ObjectProperty(prop) = property else {
+                    return None;
+                };
+                let key = match &prop.key {
+                    PropertyKey::StaticIdentifier(ident) => ident.name.to_string(),
+                    PropertyKey::StringLiteral(lit) => lit.value.to_string(),
+                    _ => return None,
+                };
+                let value = json_text(&prop.value)?;
+                // A repeated key keeps its first position and its last value
+                match entries.iter_mut().find(|(k, _)| *k == key) {
+                    Some(entry) => entry.1 = value,
+                    None => entries.push((key, value)),
+                }
+            }
+            let entries: Vec<String> = entries
+                .into_iter()
+                .map(|(k, v)| Some(format!("{}:{}", serde_json::to_string(&k).ok()?, v)))
+                .collect::<Option<_>>()?;
+            format!("{{{}}}", entries.join(","))
+        }
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileResult};
+
+    fn compile(source: &str) -> CompileResult {
+        compile_zen_internal(source, "page.zen", Default::default()).unwrap()
+    }
+
+    #[test]
+    fn test_aria_boolean_state_folds_to_string() {
+        let result = compile(
+            "<script>\nstate isOpen = false\n</script>\n<button aria-expanded={isOpen}>Menu</button>",
+        );
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(
+            result.html.contains("aria-expanded=\"false\""),
+            "{}",
+            result.html
+        );
+
+        let binding = &result.bindings[0];
+        assert_eq!(binding.target, "aria-expanded");
+        assert_eq!(binding.coerce.as_deref(), Some("aria"));
+        let bundle = &result.manifest.unwrap().bundle;
+        assert!(
+            bundle.contains("((v) => v == null ? v : String(v))(_expr_"),
+            "{}",
+            bundle
+        );
+    }
+
+    #[test]
+    fn test_data_attribute_object_warns_and_serializes() {
+        let result = compile(
+            "<script>\nstate meta = { id: 7, tags: ['a', 'b'] }\nstate count = 3\n</script>\n<div data-meta={meta} data-count={count}></div>",
+        );
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(
+            result.html.contains(
+                r#"data-meta="{&quot;id&quot;:7,&quot;tags&quot;:[&quot;a&quot;,&quot;b&quot;]}""#
+            ),
+            "{}",
+            result.html
+        );
+        assert!(result.html.contains("data-count=\"3\""), "{}", result.html);
+
+        let object_warnings: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("Z-WARN-DATA-ATTR-OBJECT"))
+            .collect();
+        assert_eq!(object_warnings.len(), 1, "{:?}", result.warnings);
+        assert!(object_warnings[0].contains("`data-meta={meta}` on <div>"));
+        assert!(result
+            .manifest
+            .unwrap()
+            .bundle
+            .contains("JSON.stringify(v) : String(v))(_expr_"));
+    }
+
+    #[test]
+    fn test_aria_hidden_is_not_a_boolean_attribute() {
+        let result = compile(
+            "<script>\nstate shown = true\n</script>\n<span aria-hidden={false}>x</span><span aria-hidden={shown}>y</span><span aria-hidden={null}>z</span>",
+        );
+        assert!(!result.has_errors, "{:?}", result.errors);
+        // A false value is rendered as the string, never dropped like `hidden` would be
+        assert!(
+            result.html.contains("<span aria-hidden=\"false\""),
+            "{}",
+            result.html
+        );
+        assert!(
+            result.html.contains("<span aria-hidden=\"true\""),
+            "{}",
+            result.html
+        );
+        assert_eq!(
+            result.html.matches("aria-hidden=\"").count(),
+            2,
+            "{}",
+            result.html
+        );
+    }
+}
@@ -0,0 +1,499 @@
+//! `zenith-compile`: compile `.zen` pages without the Node toolchain
+//!
+//! ```text
+//! zenith-compile pages/index.zen pages/about.zen --components ./components --out dist/
+//! ```
+//!
+//! A thin layer over `compile_zen_batch_internal`. Every `.zen` file under
+//! `--components` becomes a registry entry (`ComponentIR::from_source`), keyed
+//! by its file stem, or `dir:Stem` for files in subdirectories. For each page
+//! `<stem>` the output directory receives `<stem>.html`, `<stem>.js` (the
+//! bundle, with its lazy chunks next to it under their own names), `<stem>.css`
+//! and `<stem>.manifest.json`. Pages keep their path below the deepest
+//! directory holding all of them, so `pages/index.zen` and
+//! `pages/blog/index.zen` write `index.html` and `blog/index.html`; two
+//! arguments naming the same page are a usage error.
+//!
+//! Diagnostics go to stderr as `file:line:column: severity[code]: message`,
+//! colorized on a terminal (`--no-color` or `NO_COLOR` turn it off), or to
+//! stdout as a JSON array with `--json-diagnostics`. The exit code is 1 when a
+//! page has errors, or warnings under `--deny-warnings`, and 2 for usage errors.
+//!
+//! Built with `--no-default-features --features cli` (the binary does not link
+//! against Node).
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use compiler_native::prelude::*;
+use regex::Regex;
+use serde::Serialize;
+
+const USAGE: &str = "Usage: zenith-compile [OPTIONS] <FILE>...
+
+Compile .zen pages to HTML, a hydration bundle, CSS and a manifest.
+
+Options:
+  -c, --components <DIR>   Scan DIR for .zen components (repeatable)
+  -o, --out <DIR>          Output directory [default: dist]
+      --mode <MODE>        full (default), metadata or component
+  -p, --prop <KEY=VALUE>   Static page prop (repeatable)
+      --filter <NAME>      Runtime filter available to pipe syntax (repeatable)
+      --env <NAME>         Build environment for zen:if-env regions
+      --target <ES>        es2017, es2020 or esnext
+      --css-scope <STRAT>  class-suffix, where-wrapper or attribute-selector
+      --class-prefix <P>   Prefix of generated class names (default zen-)
+      --minify             Production output
+      --islands            Hydrate only islands and interactive components
+      --inline-components  Substitute components that need nothing at runtime
+      --ast-classifier     Lower .map(), ternary and && markup to fragments
+      --reactive-head      Update <head> text expressions after hydration
+      --strip-debug        Remove console calls and debugger statements
+      --strict-nullish     Warn for member access on state that may be undefined
+      --max-depth <N>      Deepest markup nesting accepted [default: 5000]
+      --deny-warnings      Exit with an error when there are warnings
+      --json-diagnostics   Print diagnostics as JSON on stdout
+      --no-color           Plain diagnostics
+  -h, --help               Print this help
+  -V, --version            Print the version";
+
+/// Parsed command line
+#[derive(Debug, Default)]
+struct Args {
+    files: Vec<PathBuf>,
+    components: Vec<PathBuf>,
+    out: Option<PathBuf>,
+    options: CompileOptions,
+    deny_warnings: bool,
+    json_diagnostics: bool,
+    no_color: bool,
+}
+
+enum Command {
+    Compile(Box<Args>),
+    Help,
+    Version,
+}
+
+fn parse_args(raw: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = Args::default();
+    let mut raw = raw.into_iter();
+    while let Some(arg) = raw.next() {
+        // `--out=dist` is accepted as well as `--out dist`
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let inline = inline.map(str::to_string);
+        let mut value = |name: &str| -> Result<String, String> {
+            inline
+                .clone()
+                .or_else(|| raw.next())
+                .ok_or_else(|| format!("`{}` needs a value", name))
+        };
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-c" | "--components" => args.components.push(value(&flag)?.into()),
+            "-o" | "--out" => args.out = Some(value(&flag)?.into()),
+            "--mode" => {
+                let mode = value(&flag)?;
+                if !matches!(mode.as_str(), "full" | "metadata" | "component") {
+                    return Err(format!(
+                        "unknown mode `{}`; expected `full`, `metadata` or `component`",
+                        mode
+                    ));
+                }
+                args.options.mode = mode;
+            }
+            "-p" | "--prop" => {
+                let prop = value(&flag)?;
+                let (key, val) = prop
+                    .split_once('=')
+                    .ok_or_else(|| format!("`--prop {}` is not KEY=VALUE", prop))?;
+                args.options.props.insert(key.to_string(), val.to_string());
+            }
+            "--filter" => args.options.filters.push(value(&flag)?),
+            "--env" => args.options.env = value(&flag)?,
+            "--target" => args.options.target_es = Some(EsTarget::from_name(&value(&flag)?)?),
+            "--css-scope" => {
+                args.options.css_scope_strategy = Some(ScopeStrategy::from_name(&value(&flag)?)?)
+            }
+            "--class-prefix" => args.options.class_prefix = Some(value(&flag)?),
+            "--minify" => args.options.minify = true,
+            "--islands" => args.options.islands = true,
+            "--inline-components" => args.options.inline_components = true,
+            "--ast-classifier" => args.options.ast_classifier = true,
+            "--reactive-head" => args.options.reactive_head = true,
+            "--strip-debug" => {
+                args.options.strip_debug = StripDebug {
+                    console: true,
+                    debugger: true,
+                    ..Default::default()
+                }
+            }
+            "--strict-nullish" => args.options.strict_nullish = true,
+            "--max-depth" => {
+                let depth = value(&flag)?;
+                let depth = depth
+                    .parse()
+                    .map_err(|_| format!("`--max-depth {}` is not a number", depth))?;
+                args.options.max_depth = Some(depth);
+            }
+            "--deny-warnings" => args.deny_warnings = true,
+            "--json-diagnostics" => args.json_diagnostics = true,
+            "--no-color" => args.no_color = true,
+            _ if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("unknown option `{}`", flag))
+            }
+            _ => args.files.push(arg.into()),
+        }
+    }
+    if args.files.is_empty() {
+        return Err("no input files".to_string());
+    }
+    Ok(Command::Compile(Box::new(args)))
+}
+
+/// `path` as written, without a leading `./`
+fn display_path(path: &Path) -> String {
+    let shown = path.to_string_lossy().replace('\\', "/");
+    shown.strip_prefix("./").unwrap_or(&shown).to_string()
+}
+
+/// Registry entries for every `.zen` file under `dir`
+fn scan_components(
+    dir: &Path,
+    registry: &mut HashMap<String, serde_json::Value>,
+) -> Result<(), String> {
+    if !dir.is_dir() {
+        return Err(format!(
+            "components directory `{}` not found",
+            dir.display()
+        ));
+    }
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().is_none_or(|e| e != "zen") {
+            continue;
+        }
+        let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+        let namespace = path
+            .parent()
+            .and_then(|p| p.strip_prefix(dir).ok())
+            .map(|p| display_path(p).replace('/', "."))
+            .filter(|ns| !ns.is_empty());
+        let key = match namespace {
+            Some(ns) => format!("{}:{}", ns, stem),
+            None => stem.clone(),
+        };
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read `{}`: {}", path.display(), e))?;
+        let component = ComponentIR::from_source(&stem, &display_path(path), &source)?;
+        registry.insert(
+            key,
+            serde_json::to_value(component).map_err(|e| e.to_string())?,
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// One compiler message, with the code and location read from its text
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    file: String,
+    severity: Severity,
+    code: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+    message: String,
+}
+
+impl Diagnostic {
+    fn parse(text: &str, page: &str, listed_as: Severity) -> Diagnostic {
+        lazy_static::lazy_static! {
+            /// `Z-ERR-…`, `Z-WARN-…`, `Z-INFO-…`, and invariant codes (`INV007`), which are errors
+            static ref CODE: Regex =
+                Regex::new(r"\[?\b(Z-(ERR|WARN|INFO)(?:-[A-Z0-9]+)+|INV\d{3})\b\]?:?\s*").unwrap();
+            /// `in page.zen (line 3, column 5)`, `at components/Card.zen (line 3, column 5)`
+            static ref FILE_LINE: Regex =
+                Regex::new(r"([^\s()]+\.zen)\)? \(line (\d+), column (\d+)\)").unwrap();
+            /// `(page.zen:3:5)`
+            static ref FILE_COLON: Regex = Regex::new(r"([^\s()]+\.zen):(\d+):(\d+)").unwrap();
+            static ref LINE_ONLY: Regex = Regex::new(r"\(line (\d+), column (\d+)\)").unwrap();
+        }
+        let text = text.trim();
+        let (code, severity, message) = match CODE.captures(text) {
+            Some(caps) => {
+                let severity = match caps.get(2).map(|m| m.as_str()) {
+                    Some("WARN") => Severity::Warning,
+                    Some("INFO") => Severity::Info,
+                    _ => Severity::Error,
+                };
+                let whole = caps.get(0).unwrap();
+                let message = format!("{}{}", &text[..whole.start()], &text[whole.end()..]);
+                (Some(caps[1].to_string()), severity, message)
+            }
+            None => (None, listed_as, text.to_string()),
+        };
+        let (file, line, column) = if let Some(caps) = FILE_LINE.captures(text) {
+            (
+                caps[1].to_string(),
+                caps[2].parse().ok(),
+                caps[3].parse().ok(),
+            )
+        } else if let Some(caps) = FILE_COLON.captures(text) {
+            (
+                caps[1].to_string(),
+                caps[2].parse().ok(),
+                caps[3].parse().ok(),
+            )
+        } else if let Some(caps) = LINE_ONLY.captures(text) {
+            (page.to_string(), caps[1].parse().ok(), caps[2].parse().ok())
+        } else {
+            (page.to_string(), None, None)
+        };
+        Diagnostic {
+            file,
+            severity,
+            code,
+            line,
+            column,
+            message: message.trim().to_string(),
+        }
+    }
+
+    fn render(&self, color: bool) -> String {
+        let paint = |code: &str, text: &str| {
+            if color {
+                format!("\x1b[{}m{}\x1b[0m", code, text)
+            } else {
+                text.to_string()
+            }
+        };
+        let (label, style) = match self.severity {
+            Severity::Error => ("error", "1;31"),
+            Severity::Warning => ("warning", "1;33"),
+            Severity::Info => ("info", "1;36"),
+        };
+        let mut location = self.file.clone();
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            location.push_str(&format!(":{}:{}", line, column));
+        }
+        let heading = match &self.code {
+            Some(code) => format!("{}[{}]", label, code),
+            None => label.to_string(),
+        };
+        format!(
+            "{}: {}: {}",
+            paint("1", &location),
+            paint(style, &heading),
+            self.message
+        )
+    }
+}
+
+/// Directory of each page's outputs, relative to the output directory: the
+/// page's directory below the deepest directory holding every page
+fn output_dirs(files: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut canonical = Vec::with_capacity(files.len());
+    for file in files {
+        let path = fs::canonicalize(file)
+            .map_err(|e| format!("cannot read `{}`: {}", file.display(), e))?;
+        if let Some(i) = canonical.iter().position(|seen| *seen == path) {
+            return Err(format!(
+                "`{}` and `{}` are the same page",
+                display_path(&files[i]),
+                display_path(file)
+            ));
+        }
+        canonical.push(path);
+    }
+    let parents: Vec<&Path> = canonical
+        .iter()
+        .map(|path| path.parent().unwrap_or(Path::new("")))
+        .collect();
+    let common: PathBuf = match parents.split_first() {
+        Some((first, rest)) => first
+            .components()
+            .enumerate()
+            .take_while(|(i, component)| {
+                rest.iter()
+                    .all(|parent| parent.components().nth(*i) == Some(*component))
+            })
+            .map(|(_, component)| component)
+            .collect(),
+        None => PathBuf::new(),
+    };
+    Ok(parents
+        .iter()
+        .map(|parent| parent.strip_prefix(&common).unwrap_or(parent).to_path_buf())
+        .collect())
+}
+
+/// Output files of one page: (file name, contents)
+fn page_outputs(stem: &str, result: &CompileResult) -> Result<Vec<(String, String)>, String> {
+    let mut files = vec![(format!("{}.html", stem), result.html.clone())];
+    if let Some(code) = &result.module_code {
+        files.push((format!("{}.js", stem), code.clone()));
+    }
+    if let Some(manifest) = &result.manifest {
+        if !manifest.bundle.is_empty() {
+            files.push((format!("{}.js", stem), manifest.bundle.clone()));
+        }
+        for chunk in &manifest.chunks {
+            files.push((chunk.name.clone(), chunk.code.clone()));
+        }
+        if !manifest.styles.is_empty() {
+            files.push((format!("{}.css", stem), manifest.styles.clone()));
+        }
+        let json = serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())?;
+        files.push((format!("{}.manifest.json", stem), json));
+    }
+    Ok(files)
+}
+
+fn run(args: Args) -> Result<bool, String> {
+    let mut registry = HashMap::new();
+    for dir in &args.components {
+        scan_components(dir, &mut registry)?;
+    }
+    let mut options = args.options;
+    options.components = registry;
+
+    let dirs = output_dirs(&args.files)?;
+    let mut sources = Vec::with_capacity(args.files.len());
+    for file in &args.files {
+        let source = fs::read_to_string(file)
+            .map_err(|e| format!("cannot read `{}`: {}", file.display(), e))?;
+        sources.push((source, display_path(file)));
+    }
+    let pages: Vec<(&str, &str)> = sources
+        .iter()
+        .map(|(source, path)| (source.as_str(), path.as_str()))
+        .collect();
+    let results = compile_zen_batch_internal(&pages, options)?;
+
+    let out = args.out.unwrap_or_else(|| PathBuf::from("dist"));
+    fs::create_dir_all(&out).map_err(|e| format!("cannot create `{}`: {}", out.display(), e))?;
+
+    let mut diagnostics = Vec::new();
+    for (((_, page), dir), result) in pages.iter().zip(&dirs).zip(results) {
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                // A failed compile reports every error in one message, one per line
+                let first = diagnostics.len();
+                for line in e
+                    .lines()
+                    .filter(|l| l.contains("Z-") || l.contains("INV0") || l.contains("error"))
+                {
+                    diagnostics.push(Diagnostic::parse(line, page, Severity::Error));
+                }
+                if diagnostics.len() == first {
+                    diagnostics.push(Diagnostic::parse(&e, page, Severity::Error));
+                }
+                continue;
+            }
+        };
+        for error in &result.errors {
+            diagnostics.push(Diagnostic::parse(error, page, Severity::Error));
+        }
+        for warning in &result.warnings {
+            diagnostics.push(Diagnostic::parse(warning, page, Severity::Warning));
+        }
+        let stem = Path::new(page)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| page.to_string());
+        let dir = out.join(dir);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("cannot create `{}`: {}", dir.display(), e))?;
+        for (name, contents) in page_outputs(&stem, &result)? {
+            let path = dir.join(&name);
+            fs::write(&path, contents)
+                .map_err(|e| format!("cannot write `{}`: {}", path.display(), e))?;
+        }
+    }
+
+    if args.json_diagnostics {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&diagnostics).map_err(|e| e.to_string())?
+        );
+    } else {
+        let color = !args.no_color
+            && std::env::var_os("NO_COLOR").is_none()
+            && std::io::stderr().is_terminal();
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic.render(color));
+        }
+    }
+
+    let failed = diagnostics.iter().any(|d| {
+        d.severity == Severity::Error || (args.deny_warnings && d.severity == Severity::Warning)
+    });
+    Ok(!failed)
+}
+
+fn main() -> ExitCode {
+    match parse_args(std::env::args().skip(1)) {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Version) => {
+            println!("zenith-compile {}", env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Compile(args)) => match run(*args) {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::from(1),
+            Err(e) => {
+                eprintln!("zenith-compile: {}", e);
+                ExitCode::from(2)
+            }
+        },
+        Err(e) => {
+            eprintln!("zenith-compile: {}\n\n{}", e, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, Severity};
+
+    #[test]
+    fn test_invariant_with_parenthesized_location() {
+        let diagnostic = Diagnostic::parse(
+            "INV009: Expression `expr_1` referenced by a text expression is not registered. (pages/home.zen:3:5)",
+            "pages/home.zen",
+            Severity::Warning,
+        );
+        assert_eq!(
+            serde_json::to_value(&diagnostic).unwrap(),
+            serde_json::json!({
+                "file": "pages/home.zen",
+                "severity": "error",
+                "code": "INV009",
+                "line": 3,
+                "column": 5,
+                "message": "Expression `expr_1` referenced by a text expression is not registered. (pages/home.zen:3:5)",
+            })
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Frozen Binding Table
+//!
+//! The identifiers a page's script binds, by category, collected once from the
+//! merged script before any expression is renamed. Codegen freezes the table and
+//! builds every `ScriptRenamer` from it, so each expression in a file classifies
+//! `title` the same way whether it is the first expression or the last.
+//!
+//! Top-level declarations, destructuring patterns included, are found in a
+//! pre-pass (`top_level_names`). A binding added after `freeze` is not applied;
+//! it is recorded as an `INV008` violation, since it means two expressions could
+//! have seen different binding sets.
+
+use std::collections::HashSet;
+
+use oxc_ast::ast::{BindingPattern, Statement};
+
+use crate::validate::INV_BINDING_AFTER_FREEZE;
+
+/// What a name is bound to, in classification order after scope-stack locals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BindingKind {
+    /// `const`/`let`/`function` at the top of the script → `scope.locals.x`
+    Local,
+    /// Provided by the runtime (`loaderData`, `params`, ...) → `scope.locals.x`
+    ExternalLocal,
+    /// `state x` → `scope.state.x`
+    State,
+    /// `prop x` → `scope.props.x`
+    Prop,
+    /// Imported names, real JS locals of the module
+    Module,
+}
+
+impl BindingKind {
+    fn label(self) -> &'static str {
+        match self {
+            BindingKind::Local => "local",
+            BindingKind::ExternalLocal => "external local",
+            BindingKind::State => "state",
+            BindingKind::Prop => "prop",
+            BindingKind::Module => "import",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BindingTable {
+    pub(crate) states: HashSet<String>,
+    pub(crate) props: HashSet<String>,
+    pub(crate) locals: HashSet<String>,
+    pub(crate) external_locals: HashSet<String>,
+    pub(crate) module: HashSet<String>,
+    frozen: bool,
+    /// Bindings refused since the table was frozen
+    violations: Vec<String>,
+}
+
+impl BindingTable {
+    fn set_mut(&mut self, kind: BindingKind) -> &mut HashSet<String> {
+        match kind {
+            BindingKind::Local => &mut self.locals,
+            BindingKind::ExternalLocal => &mut self.external_locals,
+            BindingKind::State => &mut self.states,
+            BindingKind::Prop => &mut self.props,
+            BindingKind::Module => &mut self.module,
+        }
+    }
+
+    /// Add a binding; once frozen, a new name is recorded as a violation instead
+    pub(crate) fn add(&mut self, kind: BindingKind, name: &str) -> bool {
+        if self.set_mut(kind).contains(name) {
+            return true;
+        }
+        if self.frozen {
+            self.violations
+                .push(binding_after_freeze(name, kind.label()));
+            return false;
+        }
+        self.set_mut(kind).insert(name.to_string());
+        true
+    }
+
+    pub(crate) fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    pub(crate) fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+}
+
+/// The `INV008` diagnostic for a binding registered after freezing
+pub(crate) fn binding_after_freeze(name: &str, kind: &str) -> String {
+    format!(
+        "{}: {} binding `{}` was registered after the binding table was frozen; it must be collected before expressions are classified.",
+        INV_BINDING_AFTER_FREEZE, kind, name
+    )
+}
+
+/// Names a top-level statement declares: variables (destructured ones included)
+/// and functions. Imports are collected separately.
+pub(crate) fn top_level_names(stmt: &Statement) -> Vec<String> {
+    let mut names = Vec::new();
+    match stmt {
+        Statement::VariableDeclaration(decl) => {
+            for d in &decl.declarations {
+                pattern_names(&d.id, &mut names);
+            }
+        }
+        Statement::FunctionDeclaration(decl) => {
+            if let Some(id) = &decl.id {
+                names.push(id.name.to_string());
+            }
+        }
+        _ => {}
+    }
+    names
+}
+
+fn pattern_names(pattern: &BindingPattern, names: &mut Vec<String>) {
+    match pattern {
+        BindingPattern::BindingIdentifier(id) => names.push(id.name.to_string()),
+        BindingPattern::ObjectPattern(obj) => {
+            for prop in &obj.properties {
+                pattern_names(&prop.value, names);
+            }
+            if let Some(rest) = &obj.rest {
+                pattern_names(&rest.argument, names);
+            }
+        }
+        BindingPattern::ArrayPattern(arr) => {
+            for elem in arr.elements.iter().flatten() {
+                pattern_names(elem, names);
+            }
+            if let Some(rest) = &arr.rest {
+                pattern_names(&rest.argument, names);
+            }
+        }
+        BindingPattern::AssignmentPattern(assign) => pattern_names(&assign.left, names),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsx_lowerer::ScriptRenamer;
+    use crate::parse::{compile_zen_internal, CompileOptions};
+    use oxc_allocator::Allocator;
+    use oxc_ast_visit::VisitMut;
+    use oxc_parser::Parser;
+    use oxc_span::SourceType;
+
+    #[test]
+    fn test_destructured_local_classifies_the_same_in_every_expression() {
+        let source = "<script>\nconst config = { title: 'Hi', size: 2 }\nconst { title, size: [width] } = config\n</script>\n<h1>{title}</h1><p>{width} {title.length}</p>";
+        let result = compile_zen_internal(source, "page.zen", CompileOptions::default()).unwrap();
+        let bundle = result.manifest.unwrap().bundle;
+        let titles = bundle.matches("scope.locals.title").count();
+        // The hoisted assignment, then one read in each expression
+        assert!(titles >= 3, "{}", bundle);
+        assert!(bundle.contains("scope.locals.width"), "{}", bundle);
+        assert!(!bundle.contains("scope.props.title"), "{}", bundle);
+    }
+
+    #[test]
+    fn test_add_after_freeze_is_a_violation() {
+        let mut table = BindingTable::default();
+        assert!(table.add(BindingKind::State, "count"));
+        table.freeze();
+        // Re-adding a known name is harmless
+        assert!(table.add(BindingKind::State, "count"));
+        assert!(!table.add(BindingKind::Local, "late"));
+        assert!(!table.locals.contains("late"));
+        assert_eq!(table.violations.len(), 1);
+        assert!(table.violations[0].starts_with("INV008: local binding `late`"));
+    }
+
+    #[test]
+    fn test_frozen_renamer_reports_unseen_top_level_binding() {
+        let allocator = Allocator::default();
+        let mut table = BindingTable::default();
+        table.add(BindingKind::State, "count");
+        table.freeze();
+        let mut renamer = ScriptRenamer::from_table(&allocator, &table);
+        let parsed = Parser::new(
+            &allocator,
+            "const { late } = source()",
+            SourceType::default(),
+        )
+        .parse();
+        let mut program = parsed.program;
+        renamer.visit_program(&mut program);
+        assert!(
+            renamer
+                .errors
+                .iter()
+                .any(|e| e.starts_with("INV008: local binding `late`")),
+            "{:?}",
+            renamer.errors
+        );
+        assert!(!renamer.local_bindings.contains("late"));
+    }
+}
@@ -0,0 +1,275 @@
+//! Responsive Style Shorthands
+//!
+//! Page and component `<style>` blocks may use two at-rule shorthands, expanded
+//! once components are resolved so the result joins the normal style collection:
+//!
+//! - `@zen-screen(md) { ... }` → `@media (min-width: 768px) { ... }`
+//! - `@zen-container(card, md) { ... }` → `@container card (min-width: 768px) { ... }`;
+//!   the size is a breakpoint name or a length (`@zen-container(card, 30rem)`)
+//!
+//! Breakpoints come from `CompileOptions.breakpoints` on top of `DEFAULT_BREAKPOINTS`.
+//! A value is a media condition (`(min-width: 900px)`, `(orientation: landscape)`)
+//! or a bare length, read as a `min-width`. Only the shorthand prelude is
+//! rewritten: the block, anything nested around it (`@supports`, `@layer`),
+//! comments, and strings are kept byte for byte.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::validate::ZenIR;
+
+/// Breakpoints available without configuration (min-width, mobile first)
+pub(crate) const DEFAULT_BREAKPOINTS: &[(&str, &str)] = &[
+    ("sm", "640px"),
+    ("md", "768px"),
+    ("lg", "1024px"),
+    ("xl", "1280px"),
+];
+
+const SCREEN_RULE: &str = "@zen-screen";
+const CONTAINER_RULE: &str = "@zen-container";
+
+/// Expand the shorthands in every style of the page; returns errors for unknown
+/// breakpoints or malformed shorthands (the style is left unchanged)
+pub(crate) fn expand_style_queries(
+    ir: &mut ZenIR,
+    configured: &HashMap<String, String>,
+) -> Vec<String> {
+    if !ir.styles.iter().any(|s| s.raw.contains("@zen-")) {
+        return Vec::new();
+    }
+    let breakpoints = breakpoints(configured);
+    let mut errors = Vec::new();
+    for style in &mut ir.styles {
+        match expand_css(&style.raw, &breakpoints, &ir.file_path) {
+            Ok(css) => style.raw = css,
+            Err(e) => errors.extend(e),
+        }
+    }
+    errors
+}
+
+/// Defaults overridden and extended by the configured names, sorted for messages
+fn breakpoints(configured: &HashMap<String, String>) -> BTreeMap<String, String> {
+    let mut all: BTreeMap<String, String> = DEFAULT_BREAKPOINTS
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    all.extend(configured.iter().map(|(k, v)| (k.clone(), v.clone())));
+    all
+}
+
+fn expand_css(
+    css: &str,
+    breakpoints: &BTreeMap<String, String>,
+    file_path: &str,
+) -> Result<String, Vec<String>> {
+    let bytes = css.as_bytes();
+    let mut out = String::with_capacity(css.len());
+    let mut errors = Vec::new();
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = css[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 2);
+            }
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+            }
+            b'@' => {
+                let rest = &css[i..];
+                let rule = [SCREEN_RULE, CONTAINER_RULE].into_iter().find(|rule| {
+                    rest.starts_with(rule) && rest[rule.len()..].trim_start().starts_with('(')
+                });
+                let Some(rule) = rule else {
+                    i += 1;
+                    continue;
+                };
+                let open = i
+                    + rule.len()
+                    + (rest[rule.len()..].len() - rest[rule.len()..].trim_start().len());
+                let Some(close) = css[open..].find(')').map(|end| open + end) else {
+                    errors.push(format!(
+                        "Z-ERR-STYLE-QUERY: `{}` in a style block of {} has no closing parenthesis.",
+                        rule, file_path
+                    ));
+                    break;
+                };
+                let args: Vec<&str> = css[open + 1..close].split(',').map(str::trim).collect();
+                match expand_rule(rule, &args, breakpoints) {
+                    Ok(prelude) => {
+                        out.push_str(&css[copied..i]);
+                        out.push_str(&prelude);
+                        copied = close + 1;
+                    }
+                    Err(message) => errors.push(format!(
+                        "Z-ERR-STYLE-QUERY: `{}` in a style block of {}: {}",
+                        &css[i..=close],
+                        file_path,
+                        message
+                    )),
+                }
+                i = close + 1;
+            }
+            _ => i += 1,
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    out.push_str(&css[copied.min(css.len())..]);
+    Ok(out)
+}
+
+fn expand_rule(
+    rule: &str,
+    args: &[&str],
+    breakpoints: &BTreeMap<String, String>,
+) -> Result<String, String> {
+    match (rule, args) {
+        (SCREEN_RULE, [name]) => Ok(format!("@media {}", breakpoint(name, breakpoints)?)),
+        (SCREEN_RULE, _) => Err("expected a single breakpoint name, as in `@zen-screen(md)`".to_string()),
+        (CONTAINER_RULE, [name, size]) if is_container_name(name) => {
+            let condition = if is_length(size) {
+                format!("(min-width: {})", size)
+            } else {
+                breakpoint(size, breakpoints)?
+            };
+            Ok(format!("@container {} {}", name, condition))
+        }
+        _ => Err(
+            "expected a container name and a size, as in `@zen-container(card, md)` or `@zen-container(card, 30rem)`"
+                .to_string(),
+        ),
+    }
+}
+
+/// Media condition of a named breakpoint
+fn breakpoint(name: &str, breakpoints: &BTreeMap<String, String>) -> Result<String, String> {
+    let Some(value) = breakpoints.get(name) else {
+        return Err(format!(
+            "unknown breakpoint `{}`. Available breakpoints: {}. Add it to the `breakpoints` compile option.",
+            name,
+            breakpoints.keys().cloned().collect::<Vec<_>>().join(", ")
+        ));
+    };
+    let value = value.trim();
+    Ok(if value.starts_with('(') {
+        value.to_string()
+    } else {
+        format!("(min-width: {})", value)
+    })
+}
+
+fn is_container_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// A number followed by a unit (`30rem`, `480px`, `40ch`)
+fn is_length(value: &str) -> bool {
+    let unit_at = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    unit_at > 0
+        && unit_at < value.len()
+        && value[unit_at..].chars().all(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+    use std::collections::HashMap;
+
+    fn compile(style: &str, breakpoints: &[(&str, &str)]) -> CompileResult {
+        let source = format!(
+            "<div class=\"card\">Card</div>\n<style>\n{}\n</style>",
+            style
+        );
+        let options = CompileOptions {
+            breakpoints: breakpoints
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+            ..Default::default()
+        };
+        compile_zen_internal(&source, "page.zen", options).unwrap()
+    }
+
+    fn styles(result: &CompileResult) -> String {
+        result.manifest.as_ref().unwrap().styles.clone()
+    }
+
+    #[test]
+    fn test_screen_expands_default_and_configured_breakpoints() {
+        let style = ".card { padding: 1rem; }\n@zen-screen(md) {\n  .card { padding: 2rem; }\n}\n@zen-container(card, sm) { .title { font-size: 2em; } }";
+        let result = compile(style, &[]);
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(
+            styles(&result).contains(
+                ".card { padding: 1rem; }\n@media (min-width: 768px) {\n  .card { padding: 2rem; }\n}\n@container card (min-width: 640px) { .title { font-size: 2em; } }"
+            ),
+            "{}",
+            styles(&result)
+        );
+
+        let result = compile(
+            style,
+            &[("md", "900px"), ("tablet", "(orientation: landscape)")],
+        );
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(
+            styles(&result).contains("@media (min-width: 900px) {\n  .card"),
+            "{}",
+            styles(&result)
+        );
+
+        let result = compile(
+            "@zen-screen(tablet) { a { color: red; } }",
+            &[("tablet", "(orientation: landscape)")],
+        );
+        assert!(
+            styles(&result).contains("@media (orientation: landscape) { a { color: red; } }"),
+            "{}",
+            styles(&result)
+        );
+    }
+
+    #[test]
+    fn test_unknown_breakpoint_lists_available_names() {
+        let result = compile(
+            "@zen-screen(xxl) { a { color: red; } }",
+            &[("tablet", "600px")],
+        );
+        assert!(result.has_errors);
+        assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+        assert_eq!(
+            result.errors[0],
+            "Z-ERR-STYLE-QUERY: `@zen-screen(xxl)` in a style block of page.zen: unknown breakpoint `xxl`. Available breakpoints: lg, md, sm, tablet, xl. Add it to the `breakpoints` compile option."
+        );
+    }
+
+    #[test]
+    fn test_nested_shorthand_keeps_surrounding_css() {
+        let style = "@supports (display: grid) {\n  /* @zen-screen(nope) stays a comment */\n  .grid { content: \"@zen-screen(lg)\"; }\n  @zen-screen(lg) {\n    .grid { display: grid; }\n  }\n}";
+        let result = compile(style, &[]);
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(
+            styles(&result).contains(
+                "@supports (display: grid) {\n  /* @zen-screen(nope) stays a comment */\n  .grid { content: \"@zen-screen(lg)\"; }\n  @media (min-width: 1024px) {\n    .grid { display: grid; }\n  }\n}"
+            ),
+            "{}",
+            styles(&result)
+        );
+    }
+}
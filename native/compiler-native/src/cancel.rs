@@ -0,0 +1,127 @@
+//! Cooperative Cancellation
+//!
+//! The dev server aborts an in-flight compile when the file changes again. A
+//! `CancellationToken` passed in `CompileOptions.cancel` is checked at phase
+//! boundaries and inside the hot loops (top-level nodes in component resolution
+//! and transform, expressions in codegen); once it is cancelled the compile
+//! returns `Err(COMPILE_CANCELLED)`, which callers recognize and discard. Checks
+//! are a relaxed atomic load; without a token nothing changes.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Error returned by a cancelled compile (never a diagnostic of the page itself)
+pub const COMPILE_CANCELLED: &str = "Z-CANCELLED: compilation was cancelled";
+
+/// Shared flag: clones observe the same cancellation, so one token can cover a
+/// whole batch
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    flag: Arc<AtomicBool>,
+    /// Test hook: the token cancels itself once this many checks have passed
+    #[cfg(test)]
+    countdown: Option<Arc<std::sync::atomic::AtomicUsize>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        #[cfg(test)]
+        if let Some(countdown) = &self.countdown {
+            if countdown.fetch_sub(1, Ordering::Relaxed) <= 1 {
+                self.cancel();
+            }
+        }
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn cancel_after(checks: usize) -> Self {
+        Self {
+            countdown: Some(Arc::new(std::sync::atomic::AtomicUsize::new(checks))),
+            ..Self::default()
+        }
+    }
+}
+
+/// `Err(COMPILE_CANCELLED)` once the token (if any) is cancelled
+pub(crate) fn check(token: Option<&CancellationToken>) -> Result<(), String> {
+    match token {
+        Some(token) if token.is_cancelled() => Err(COMPILE_CANCELLED.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Whether a compile error is a cancellation rather than a failure
+pub fn is_cancelled_error(err: &str) -> bool {
+    err == COMPILE_CANCELLED
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_batch_internal, CompileOptions};
+
+    const PAGES: &[(&str, &str)] = &[
+        (
+            "<script>\nstate a = 1\nstate b = 2\n</script>\n<p>{a}</p><p>{b}</p><p>{a + b}</p>",
+            "one.zen",
+        ),
+        (
+            "<script>\nstate name = 'x'\n</script>\n<h1>{name}</h1><p>{name.length}</p>",
+            "two.zen",
+        ),
+        (
+            "<script>\nstate n = 3\n</script>\n<span>{n * 2}</span>",
+            "three.zen",
+        ),
+    ];
+
+    /// HTML of each page
+    fn batch(cancel: Option<CancellationToken>) -> Result<Vec<String>, String> {
+        let options = CompileOptions {
+            cancel,
+            ..Default::default()
+        };
+        compile_zen_batch_internal(PAGES, options)
+            .map(|results| results.into_iter().map(|r| r.unwrap().html).collect())
+    }
+
+    #[test]
+    fn test_batch_without_token_compiles_every_file() {
+        let plain = batch(None).unwrap();
+        assert_eq!(plain.len(), PAGES.len());
+        assert_eq!(batch(Some(CancellationToken::new())).unwrap(), plain);
+
+        let token = CancellationToken::new();
+        token.cancel();
+        assert_eq!(batch(Some(token)), Err(COMPILE_CANCELLED.to_string()));
+    }
+
+    #[test]
+    fn test_cancel_at_any_check_aborts_whole_batch() {
+        let complete = batch(None).unwrap();
+        // Flip the flag after every possible number of checks: each run either
+        // aborts with the cancelled error or, past the last check, completes
+        let mut checks = 1;
+        loop {
+            match batch(Some(CancellationToken::cancel_after(checks))) {
+                Err(e) => assert!(is_cancelled_error(&e), "{}", e),
+                Ok(html) => {
+                    assert_eq!(html, complete);
+                    break;
+                }
+            }
+            checks += 1;
+        }
+        // Four phase boundaries per file; the rest are per-node and per-expression checks
+        assert!(checks > PAGES.len() * 4, "{} checks", checks);
+    }
+}
@@ -0,0 +1,231 @@
+//! Generated Class Names
+//!
+//! Every class the compiler adds to markup comes from one `ClassNameAllocator`
+//! per compile, so passes cannot hand out the same name twice or take one the
+//! author already uses. A name is the configured prefix (`CompileOptions.class_prefix`,
+//! default `zen-`) and a hash of what it is for:
+//!
+//! - the purpose (`scope` for scoped component styles, see `css_scope.rs`)
+//! - the path of the component it belongs to
+//! - a discriminator telling apart several names of one purpose in one component
+//!
+//! so the same component gets the same name in every build. Classes written in the
+//! templates and style blocks of the page and its components are registered up
+//! front; a generated name that matches one is `Z-WARN-CLASS-COLLISION` and is
+//! salted and hashed again. The manifest lists the generated names by purpose.
+
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::validate::TemplateNode;
+
+/// Prefix of generated class names when `class_prefix` is unset
+pub const DEFAULT_CLASS_PREFIX: &str = "zen-";
+
+/// Purpose of the class marking a component's elements for its scoped styles
+pub(crate) const SCOPE_PURPOSE: &str = "scope";
+
+/// `prefix` followed by the hash of (purpose, path, discriminator), salted when
+/// `salt` is not zero
+pub(crate) fn class_name(
+    prefix: &str,
+    purpose: &str,
+    path: &str,
+    discriminator: &str,
+    salt: usize,
+) -> String {
+    let mut key = format!("{}|{}|{}", purpose, path, discriminator);
+    if salt > 0 {
+        key.push_str(&format!("|{}", salt));
+    }
+    let digest = Sha256::digest(key.as_bytes());
+    format!("{}{}", prefix, &format!("{:x}", digest)[..8])
+}
+
+/// Hands out the compiler's class names for one compile
+#[derive(Debug, Default)]
+pub(crate) struct ClassNameAllocator {
+    prefix: String,
+    /// Classes the author wrote, which generated names must avoid
+    user_classes: HashSet<String>,
+    /// (purpose, path, discriminator) → name
+    names: HashMap<(String, String, String), String>,
+    taken: HashSet<String>,
+    by_purpose: BTreeMap<String, BTreeSet<String>>,
+    warnings: Vec<String>,
+}
+
+impl ClassNameAllocator {
+    pub(crate) fn new(prefix: Option<&str>) -> Self {
+        ClassNameAllocator {
+            prefix: prefix.unwrap_or(DEFAULT_CLASS_PREFIX).to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Register the classes of a template and its style blocks
+    pub(crate) fn register_user_classes<'s>(
+        &mut self,
+        nodes: &[TemplateNode],
+        styles: impl IntoIterator<Item = &'s str>,
+    ) {
+        self.user_classes
+            .extend(crate::finalize::collect_css_classes(nodes));
+        for css in styles {
+            self.user_classes
+                .extend(crate::global_styles::selector_classes(css));
+        }
+    }
+
+    /// The name for (purpose, path, discriminator); the same one on every call
+    pub(crate) fn allocate(&mut self, purpose: &str, path: &str, discriminator: &str) -> String {
+        let key = (
+            purpose.to_string(),
+            path.to_string(),
+            discriminator.to_string(),
+        );
+        if let Some(name) = self.names.get(&key) {
+            return name.clone();
+        }
+        let mut salt = 0;
+        let mut name = class_name(&self.prefix, purpose, path, discriminator, salt);
+        while self.taken.contains(&name) || self.user_classes.contains(&name) {
+            if self.user_classes.contains(&name) {
+                self.warnings.push(format!(
+                    "Z-WARN-CLASS-COLLISION: the class `{}` the compiler generates for the {} of {} is also written in a template or style block; a salted name is generated instead.",
+                    name, purpose, path
+                ));
+            }
+            salt += 1;
+            name = class_name(&self.prefix, purpose, path, discriminator, salt);
+        }
+        self.taken.insert(name.clone());
+        self.by_purpose
+            .entry(purpose.to_string())
+            .or_default()
+            .insert(name.clone());
+        self.names.insert(key, name.clone());
+        name
+    }
+
+    /// Generated names by purpose, sorted, and the collision warnings
+    pub(crate) fn finish(self) -> (BTreeMap<String, Vec<String>>, Vec<String>) {
+        let generated = self
+            .by_purpose
+            .into_iter()
+            .map(|(purpose, names)| (purpose, names.into_iter().collect()))
+            .collect();
+        (generated, self.warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::ComponentIR;
+    use crate::css_scope::ScopeStrategy;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn component(name: &str, class: &str) -> (String, serde_json::Value) {
+        let source = format!(
+            "<div class=\"{}\">{}</div>\n<style>\n.{} {{ color: red; }}\n</style>",
+            class, name, class
+        );
+        let comp =
+            ComponentIR::from_source(name, &format!("components/{}.zen", name), &source).unwrap();
+        (name.to_string(), serde_json::to_value(comp).unwrap())
+    }
+
+    fn compile(page: &str, class_prefix: Option<&str>) -> CompileResult {
+        let options = CompileOptions {
+            components: [component("Badge", "badge"), component("Card", "card")].into(),
+            css_scope_strategy: Some(ScopeStrategy::ClassSuffix),
+            class_prefix: class_prefix.map(String::from),
+            ..Default::default()
+        };
+        let result = compile_zen_internal(page, "page.zen", options).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        result
+    }
+
+    fn scope_class(path: &str, prefix: &str, salt: usize) -> String {
+        class_name(prefix, SCOPE_PURPOSE, path, "", salt)
+    }
+
+    #[test]
+    fn test_components_get_distinct_names() {
+        let result = compile("<main><Badge /><Card /></main>", None);
+        let badge = scope_class("components/Badge.zen", "zen-", 0);
+        let card = scope_class("components/Card.zen", "zen-", 0);
+        assert_ne!(badge, card);
+        assert!(
+            result
+                .html
+                .contains(&format!("<div class=\"badge {}\">", badge)),
+            "{}",
+            result.html
+        );
+        assert!(
+            result
+                .html
+                .contains(&format!("<div class=\"card {}\">", card)),
+            "{}",
+            result.html
+        );
+    }
+
+    #[test]
+    fn test_user_class_collision_is_salted() {
+        let taken = scope_class("components/Badge.zen", "zen-", 0);
+        let result = compile(
+            &format!("<main><p class=\"{}\">Mine</p><Badge /></main>", taken),
+            None,
+        );
+        let salted = scope_class("components/Badge.zen", "zen-", 1);
+        assert!(
+            result
+                .html
+                .contains(&format!("<div class=\"badge {}\">", salted)),
+            "{}",
+            result.html
+        );
+        assert!(
+            result
+                .warnings
+                .iter()
+                .any(|w| w.starts_with("Z-WARN-CLASS-COLLISION") && w.contains(&taken)),
+            "{:?}",
+            result.warnings
+        );
+        let styles = result.manifest.unwrap().styles;
+        assert!(styles.contains(&format!(".badge.{}", salted)), "{}", styles);
+    }
+
+    #[test]
+    fn test_manifest_lists_generated_names_by_purpose() {
+        let result = compile("<main><Badge /><Card /><Badge /></main>", None);
+        let generated = result.manifest.unwrap().generated_classes;
+        let mut expected = vec![
+            scope_class("components/Badge.zen", "zen-", 0),
+            scope_class("components/Card.zen", "zen-", 0),
+        ];
+        expected.sort();
+        assert_eq!(generated.get(SCOPE_PURPOSE), Some(&expected));
+        assert_eq!(generated.len(), 1);
+    }
+
+    #[test]
+    fn test_class_prefix_is_respected() {
+        let result = compile("<main><Badge /><Card /></main>", Some("acme-"));
+        let manifest = result.manifest.unwrap();
+        let names = &manifest.generated_classes[SCOPE_PURPOSE];
+        assert_eq!(names.len(), 2);
+        for name in names {
+            assert!(name.starts_with("acme-"), "{}", name);
+            assert!(result.html.contains(name.as_str()), "{}", result.html);
+            assert!(manifest.styles.contains(&format!(".{}", name)));
+        }
+        let unprefixed = scope_class("components/Badge.zen", "zen-", 0);
+        assert!(!result.html.contains(&unprefixed), "{}", result.html);
+    }
+}
@@ -0,0 +1,867 @@
+//! Expression Classification
+//!
+//! Recognizes text expressions that render markup structurally and lowers them
+//! to template fragments:
+//!
+//! - `source.map((item, index) => <li/>)` → loop fragment
+//! - `condition ? <a/> : <b/>` → conditional fragment
+//! - `a ? <a/> : b ? <b/> : <c/>` → one multi-branch fragment for the whole chain
+//! - `condition && <a/>` → optional fragment
+//!
+//! The expression is parsed with oxc (JSX enabled) and every part is sliced from
+//! the source through its span, so block-bodied callbacks (`{ return <li/> }`),
+//! comments between tokens, and optional chains in a condition (`a?.b ? ...`)
+//! need no special casing. Code oxc rejects is left as a plain value.
+//!
+//! Lowering is opt-in (`CompileOptions.ast_classifier`). Without it text
+//! expressions stay expression nodes and their JSX is lowered to `__zenith.h()`
+//! calls that render at runtime.
+
+use std::collections::HashSet;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{CallExpression, Expression, FunctionBody, Statement};
+use oxc_parser::Parser;
+use oxc_span::{GetSpan, SourceType, Span};
+use oxc_syntax::operator::LogicalOperator;
+
+use crate::validate::{
+    ConditionalBranch, ConditionalFragmentNode, ExpressionIR, ExpressionNode, LoopContext,
+    LoopFragmentNode, MultiBranchFragmentNode, OptionalFragmentNode, SourceLocation, TemplateIR,
+    TemplateNode,
+};
+
+/// How a template expression renders. Parts are source slices of the expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionClass {
+    /// `source.map((item_var, index_var) => body)` where the body renders markup
+    Loop {
+        source: String,
+        item_var: String,
+        index_var: Option<String>,
+        body: String,
+    },
+    /// `condition ? consequent : alternate` where a branch renders markup
+    Conditional {
+        condition: String,
+        consequent: String,
+        alternate: String,
+    },
+    /// `condition && fragment` where the fragment renders markup
+    Optional { condition: String, fragment: String },
+    /// A JSX element, or the children of a JSX fragment (`<>...</>`)
+    Markup(String),
+    /// Anything else, rendered as a value
+    Value,
+}
+
+/// Classify a template expression. Code that does not parse as a single
+/// expression is a `Value`.
+pub fn classify_expression(code: &str) -> ExpressionClass {
+    let allocator = Allocator::default();
+    let source_type = SourceType::default().with_jsx(true).with_typescript(true);
+    let ret = Parser::new(&allocator, code, source_type).parse();
+    if !ret.errors.is_empty() || ret.program.body.len() != 1 {
+        return ExpressionClass::Value;
+    }
+    match &ret.program.body[0] {
+        Statement::ExpressionStatement(stmt) => classify(&stmt.expression, code),
+        _ => ExpressionClass::Value,
+    }
+}
+
+fn classify(expr: &Expression, code: &str) -> ExpressionClass {
+    let slice = |span: Span| {
+        code[span.start as usize..span.end as usize]
+            .trim()
+            .to_string()
+    };
+
+    match expr.without_parentheses() {
+        Expression::JSXElement(el) => ExpressionClass::Markup(slice(el.span)),
+        Expression::JSXFragment(frag) => ExpressionClass::Markup(slice(Span::new(
+            frag.opening_fragment.span.end,
+            frag.closing_fragment.span.start,
+        ))),
+        Expression::ConditionalExpression(cond)
+            if renders_markup(&cond.consequent, code) || renders_markup(&cond.alternate, code) =>
+        {
+            ExpressionClass::Conditional {
+                condition: slice(cond.test.span()),
+                consequent: slice(cond.consequent.span()),
+                alternate: slice(cond.alternate.span()),
+            }
+        }
+        Expression::LogicalExpression(logical)
+            if logical.operator == LogicalOperator::And && renders_markup(&logical.right, code) =>
+        {
+            ExpressionClass::Optional {
+                condition: slice(logical.left.span()),
+                fragment: slice(logical.right.span()),
+            }
+        }
+        Expression::CallExpression(call) => {
+            map_callback(call, code).unwrap_or(ExpressionClass::Value)
+        }
+        _ => ExpressionClass::Value,
+    }
+}
+
+fn renders_markup(expr: &Expression, code: &str) -> bool {
+    classify(expr, code) != ExpressionClass::Value
+}
+
+/// `source.map(callback)` whose callback takes (item, index?) and returns markup
+fn map_callback(call: &CallExpression, code: &str) -> Option<ExpressionClass> {
+    let slice = |span: Span| {
+        code[span.start as usize..span.end as usize]
+            .trim()
+            .to_string()
+    };
+
+    let Expression::StaticMemberExpression(member) = &call.callee else {
+        return None;
+    };
+    if member.property.name.as_str() != "map" || call.arguments.len() != 1 {
+        return None;
+    }
+    let (params, body) = match call.arguments[0].as_expression()? {
+        Expression::ArrowFunctionExpression(arrow) => (
+            &arrow.params,
+            returned_expression(&arrow.body, arrow.expression)?,
+        ),
+        Expression::FunctionExpression(func) => (
+            &func.params,
+            returned_expression(func.body.as_ref()?, false)?,
+        ),
+        _ => return None,
+    };
+    if params.items.is_empty() || params.items.len() > 2 || params.rest.is_some() {
+        return None;
+    }
+    if !renders_markup(body, code) {
+        return None;
+    }
+
+    Some(ExpressionClass::Loop {
+        source: slice(member.object.span()),
+        item_var: slice(params.items[0].pattern.span()),
+        index_var: params.items.get(1).map(|p| slice(p.pattern.span())),
+        body: slice(body.span()),
+    })
+}
+
+/// What a callback renders: its expression body, or the argument of a block
+/// body that is a single `return`. Blocks with other statements are not lowered,
+/// since their locals would be lost.
+fn returned_expression<'b, 'a>(
+    body: &'b FunctionBody<'a>,
+    is_expression_body: bool,
+) -> Option<&'b Expression<'a>> {
+    if body.statements.len() != 1 {
+        return None;
+    }
+    match &body.statements[0] {
+        Statement::ExpressionStatement(stmt) if is_expression_body => Some(&stmt.expression),
+        Statement::ReturnStatement(ret) => ret.argument.as_ref(),
+        _ => None,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// FRAGMENT LOWERING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Replace text expressions that render markup with loop, conditional, and
+/// optional fragments. Expressions in `<head>` and attribute values are left
+/// alone. Replaced expressions are removed from the registry; the fragments'
+/// conditions, loop sources, and markup expressions are registered instead.
+///
+/// Lowered markup keeps the last of duplicated attributes (`class` and `style`
+/// merge as in templates) with a warning; attribute names that start with a digit
+/// or contain quotes are dropped and reported as errors, which are returned.
+pub fn lower_fragments(
+    template: &mut TemplateIR,
+    file_path: &str,
+    warnings: &mut Vec<String>,
+) -> Vec<String> {
+    let mut lowering = Lowering {
+        file_path,
+        expressions: Vec::new(),
+        lowered: HashSet::new(),
+        context: Vec::new(),
+        anchor: String::new(),
+        registered: 0,
+        warnings: Vec::new(),
+        errors: Vec::new(),
+    };
+    let nodes = std::mem::take(&mut template.nodes);
+    template.nodes = lowering.lower_nodes(nodes, &template.expressions);
+
+    template
+        .expressions
+        .retain(|e| !lowering.lowered.contains(&e.id));
+    template.expressions.extend(lowering.expressions);
+    warnings.extend(lowering.warnings);
+    lowering.errors
+}
+
+struct Lowering<'f> {
+    file_path: &'f str,
+    /// Expressions registered by lowering
+    expressions: Vec<ExpressionIR>,
+    /// Ids of expressions replaced by fragments
+    lowered: HashSet<String>,
+    /// Enclosing fragments, innermost last (`a loop over `items``)
+    context: Vec<String>,
+    /// Id of the expression being lowered; its parts get ids derived from it
+    anchor: String,
+    /// Parts registered for `anchor` so far
+    registered: usize,
+    warnings: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl Lowering<'_> {
+    fn lower_nodes(
+        &mut self,
+        nodes: Vec<TemplateNode>,
+        registry: &[ExpressionIR],
+    ) -> Vec<TemplateNode> {
+        let mut out = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            match node {
+                TemplateNode::Expression(e) if !e.is_in_head => {
+                    let class = registry
+                        .iter()
+                        .find(|ex| ex.id == e.expression)
+                        .map(|ex| (classify_expression(&ex.code), ex.code.as_str()));
+                    match class {
+                        Some((
+                            class @ (ExpressionClass::Loop { .. }
+                            | ExpressionClass::Conditional { .. }
+                            | ExpressionClass::Optional { .. }),
+                            code,
+                        )) => {
+                            self.lowered.insert(e.expression.clone());
+                            let outer = (
+                                std::mem::replace(&mut self.anchor, e.expression.clone()),
+                                std::mem::take(&mut self.registered),
+                            );
+                            out.extend(self.lower_class(class, code, &e.loop_context, &e.location));
+                            (self.anchor, self.registered) = outer;
+                        }
+                        _ => out.push(TemplateNode::Expression(e)),
+                    }
+                }
+                TemplateNode::Element(mut el) => {
+                    el.children = self.lower_nodes(el.children, registry);
+                    out.push(TemplateNode::Element(el));
+                }
+                TemplateNode::Component(mut c) => {
+                    c.children = self.lower_nodes(c.children, registry);
+                    out.push(TemplateNode::Component(c));
+                }
+                TemplateNode::ConditionalFragment(mut cf) => {
+                    cf.consequent = self.lower_nodes(cf.consequent, registry);
+                    cf.alternate = self.lower_nodes(cf.alternate, registry);
+                    out.push(TemplateNode::ConditionalFragment(cf));
+                }
+                TemplateNode::MultiBranchFragment(mut mb) => {
+                    for nodes in mb.children_mut() {
+                        *nodes = self.lower_nodes(std::mem::take(nodes), registry);
+                    }
+                    out.push(TemplateNode::MultiBranchFragment(mb));
+                }
+                TemplateNode::OptionalFragment(mut of) => {
+                    of.fragment = self.lower_nodes(of.fragment, registry);
+                    out.push(TemplateNode::OptionalFragment(of));
+                }
+                TemplateNode::LoopFragment(mut lf) => {
+                    lf.body = self.lower_nodes(lf.body, registry);
+                    out.push(TemplateNode::LoopFragment(lf));
+                }
+                other => out.push(other),
+            }
+        }
+        out
+    }
+
+    fn lower(
+        &mut self,
+        code: &str,
+        loop_context: &Option<LoopContext>,
+        location: &SourceLocation,
+    ) -> Vec<TemplateNode> {
+        self.lower_class(classify_expression(code), code, loop_context, location)
+    }
+
+    fn lower_class(
+        &mut self,
+        class: ExpressionClass,
+        code: &str,
+        loop_context: &Option<LoopContext>,
+        location: &SourceLocation,
+    ) -> Vec<TemplateNode> {
+        match class {
+            ExpressionClass::Loop {
+                source,
+                item_var,
+                index_var,
+                body,
+            } => {
+                let source_id = self.register(&source, loop_context, location);
+                let mut variables = loop_context
+                    .as_ref()
+                    .map(|lc| lc.variables.clone())
+                    .unwrap_or_default();
+                for name in crate::codegen::loop_binding_names(&item_var, index_var.as_deref()) {
+                    if !variables.contains(&name) {
+                        variables.push(name);
+                    }
+                }
+                self.context.push(format!("a loop over `{}`", source));
+                let body_context = Some(LoopContext {
+                    variables,
+                    map_source: Some(source),
+                });
+                let body = self.lower(&body, &body_context, location);
+                self.context.pop();
+                vec![TemplateNode::LoopFragment(LoopFragmentNode {
+                    source: source_id,
+                    item_var,
+                    index_var,
+                    body,
+                    location: location.clone(),
+                    loop_context: loop_context.clone(),
+                    extra: Default::default(),
+                })]
+            }
+            ExpressionClass::Conditional {
+                condition,
+                consequent,
+                alternate,
+            } if matches!(
+                classify_expression(&alternate),
+                ExpressionClass::Conditional { .. }
+            ) =>
+            {
+                self.lower_chain(condition, consequent, alternate, loop_context, location)
+            }
+            ExpressionClass::Conditional {
+                condition,
+                consequent,
+                alternate,
+            } => {
+                self.context
+                    .push(format!("a conditional on `{}`", condition.trim()));
+                let condition = self.register(&condition, loop_context, location);
+                let consequent = self.lower(&consequent, loop_context, location);
+                let alternate = self.lower(&alternate, loop_context, location);
+                self.context.pop();
+                vec![TemplateNode::ConditionalFragment(ConditionalFragmentNode {
+                    condition,
+                    consequent,
+                    alternate,
+                    location: location.clone(),
+                    loop_context: loop_context.clone(),
+                    extra: Default::default(),
+                })]
+            }
+            ExpressionClass::Optional {
+                condition,
+                fragment,
+            } => {
+                self.context
+                    .push(format!("an optional fragment on `{}`", condition.trim()));
+                let condition = self.register(&condition, loop_context, location);
+                let fragment = self.lower(&fragment, loop_context, location);
+                self.context.pop();
+                vec![TemplateNode::OptionalFragment(OptionalFragmentNode {
+                    condition,
+                    fragment,
+                    location: location.clone(),
+                    loop_context: loop_context.clone(),
+                    extra: Default::default(),
+                })]
+            }
+            ExpressionClass::Markup(markup) => {
+                let context = self
+                    .context
+                    .last()
+                    .cloned()
+                    .unwrap_or_else(|| "a JSX expression".to_string());
+                let anchor = self.next_id();
+                match crate::parse::parse_lowered_markup(&markup, self.file_path, &context, &anchor)
+                {
+                    Ok(mut parsed) => {
+                        self.warnings.append(&mut parsed.warnings);
+                        self.check_attribute_names(&mut parsed.nodes, &context);
+                        let mut expressions = parsed.expressions;
+                        for e in &mut expressions {
+                            e.loop_context = loop_context.clone();
+                        }
+                        let nodes = parsed
+                            .nodes
+                            .into_iter()
+                            .map(|n| crate::component::rebind_node_to_scope(n, loop_context))
+                            .collect();
+                        // Markup may itself contain lowerable expressions
+                        let nodes = self.lower_nodes(nodes, &expressions);
+                        let lowered = &self.lowered;
+                        let kept: Vec<ExpressionIR> = expressions
+                            .into_iter()
+                            .filter(|e| !lowered.contains(&e.id))
+                            .collect();
+                        self.expressions.extend(kept);
+                        nodes
+                    }
+                    Err(_) => self.value(code, loop_context, location),
+                }
+            }
+            ExpressionClass::Value => self.value(code, loop_context, location),
+        }
+    }
+
+    /// `a ? <A/> : b ? <B/> : <C/>`: the right-nested conditionals become the
+    /// ordered branches of one fragment, the last alternate its default
+    fn lower_chain(
+        &mut self,
+        condition: String,
+        consequent: String,
+        alternate: String,
+        loop_context: &Option<LoopContext>,
+        location: &SourceLocation,
+    ) -> Vec<TemplateNode> {
+        let mut branches = Vec::new();
+        let (mut condition, mut consequent, mut rest) = (condition, consequent, alternate);
+        loop {
+            self.context
+                .push(format!("a conditional on `{}`", condition.trim()));
+            let id = self.register(&condition, loop_context, location);
+            let nodes = self.lower(&consequent, loop_context, location);
+            branches.push(ConditionalBranch {
+                condition: id,
+                nodes,
+                extra: Default::default(),
+            });
+            match classify_expression(&rest) {
+                ExpressionClass::Conditional {
+                    condition: next,
+                    consequent: next_consequent,
+                    alternate,
+                } => {
+                    self.context.pop();
+                    (condition, consequent, rest) = (next, next_consequent, alternate);
+                }
+                _ => break,
+            }
+        }
+        let default = self.lower(&rest, loop_context, location);
+        self.context.pop();
+        vec![TemplateNode::MultiBranchFragment(MultiBranchFragmentNode {
+            branches,
+            default,
+            location: location.clone(),
+            loop_context: loop_context.clone(),
+            extra: Default::default(),
+        })]
+    }
+
+    /// Names html5ever accepts but no DOM API can set (`1x`, `a"b`) are dropped with an error
+    fn check_attribute_names(&mut self, nodes: &mut [TemplateNode], context: &str) {
+        for node in nodes {
+            let (tag, attributes, children) = match node {
+                TemplateNode::Element(el) => (&el.tag, &mut el.attributes, &mut el.children),
+                TemplateNode::Component(c) => (&c.name, &mut c.attributes, &mut c.children),
+                _ => continue,
+            };
+            let file_path = self.file_path;
+            let errors = &mut self.errors;
+            attributes.retain(|attr| {
+                let valid = !attr.name.starts_with(|c: char| c.is_ascii_digit())
+                    && !attr.name.contains(['"', '\'']);
+                if !valid {
+                    errors.push(format!(
+                        "Z-ERR-ATTRIBUTE-NAME: `{}` on <{}> in {} in {} is not a valid attribute name; names cannot start with a digit or contain quotes.",
+                        attr.name, tag, context, file_path
+                    ));
+                }
+                valid
+            });
+            self.check_attribute_names(children, context);
+        }
+    }
+
+    /// A branch or body that is not markup renders as a value; empty values render nothing
+    fn value(
+        &mut self,
+        code: &str,
+        loop_context: &Option<LoopContext>,
+        location: &SourceLocation,
+    ) -> Vec<TemplateNode> {
+        if matches!(code.trim(), "" | "null" | "undefined" | "false") {
+            return Vec::new();
+        }
+        let id = self.register(code, loop_context, location);
+        vec![TemplateNode::Expression(ExpressionNode {
+            expression: id,
+            location: location.clone(),
+            loop_context: loop_context.clone(),
+            is_in_head: false,
+            extra: Default::default(),
+        })]
+    }
+
+    /// Id for the next part of the expression being lowered (see `expr_ids.rs`)
+    fn next_id(&mut self) -> String {
+        self.registered += 1;
+        crate::expr_ids::derived_id(&self.anchor, self.registered - 1)
+    }
+
+    fn register(
+        &mut self,
+        code: &str,
+        loop_context: &Option<LoopContext>,
+        location: &SourceLocation,
+    ) -> String {
+        let id = self.next_id();
+        self.expressions.push(ExpressionIR {
+            id: id.clone(),
+            code: code.trim().to_string(),
+            location: location.clone(),
+            loop_context: loop_context.clone(),
+            origin: None,
+            extra: Default::default(),
+        });
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::AttributeValue;
+
+    fn is_loop(class: &ExpressionClass, source: &str, item: &str, body: &str) -> bool {
+        matches!(class, ExpressionClass::Loop { source: s, item_var: i, body: b, .. }
+            if s == source && i == item && b == body)
+    }
+
+    #[test]
+    fn test_block_bodied_map_callback() {
+        let class = classify_expression("items.map(item => { return <li>{item.name}</li> })");
+        assert!(
+            is_loop(&class, "items", "item", "<li>{item.name}</li>"),
+            "{:?}",
+            class
+        );
+
+        let class =
+            classify_expression("rows.map(function (row, i) { return <tr data-i={i}></tr>; })");
+        assert!(matches!(
+            &class,
+            ExpressionClass::Loop { index_var: Some(i), .. } if i == "i"
+        ));
+
+        // Statements besides the return would be lost, so the callback stays a value
+        let class = classify_expression(
+            "items.map(item => { const label = item.name; return <li>{label}</li> })",
+        );
+        assert_eq!(class, ExpressionClass::Value);
+    }
+
+    #[test]
+    fn test_optional_chain_condition_ternary() {
+        let class = classify_expression("user?.profile ? <Avatar /> : <span>Guest</span>");
+        assert_eq!(
+            class,
+            ExpressionClass::Conditional {
+                condition: "user?.profile".to_string(),
+                consequent: "<Avatar />".to_string(),
+                alternate: "<span>Guest</span>".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_comments_between_tokens() {
+        let class = classify_expression(
+            "/* list */ todos /* all */ .map((todo /* item */, i) => (\n  // row\n  <li>{todo}</li>\n))",
+        );
+        assert!(
+            is_loop(&class, "todos", "todo", "(\n  // row\n  <li>{todo}</li>\n)"),
+            "{:?}",
+            class
+        );
+
+        let class = classify_expression("open /* flag */ && <Menu />");
+        assert_eq!(
+            class,
+            ExpressionClass::Optional {
+                condition: "open".to_string(),
+                fragment: "<Menu />".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_values_are_not_fragments() {
+        for code in [
+            "count + 1",
+            "a ? 1 : 2",
+            "ready && label",
+            "items.map(i => i * 2)",
+            "items.filter(Boolean).length",
+            "not valid (",
+        ] {
+            assert_eq!(
+                classify_expression(code),
+                ExpressionClass::Value,
+                "{}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn test_lower_nested_fragments() {
+        let mut template =
+            crate::parse::parse_template("<ul>{items.map(item => item.done ? (show && <li class=\"done\">{item.name}</li>) : <li>{item.name}</li>)}</ul>", "page.zen").unwrap();
+        let errors = lower_fragments(&mut template, "page.zen", &mut Vec::new());
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let TemplateNode::Element(ul) = &template.nodes[0] else {
+            panic!("expected <ul>, got {:?}", template.nodes[0]);
+        };
+        let TemplateNode::LoopFragment(lf) = &ul.children[0] else {
+            panic!("expected a loop fragment, got {:?}", ul.children);
+        };
+        assert_eq!(lf.item_var, "item");
+        let source = template
+            .expressions
+            .iter()
+            .find(|e| e.id == lf.source)
+            .unwrap();
+        assert_eq!(source.code, "items");
+
+        let TemplateNode::ConditionalFragment(cf) = &lf.body[0] else {
+            panic!("expected a conditional fragment, got {:?}", lf.body);
+        };
+        assert!(matches!(
+            cf.consequent[0],
+            TemplateNode::OptionalFragment(_)
+        ));
+        assert!(matches!(cf.alternate[0], TemplateNode::Element(_)));
+        let condition = template
+            .expressions
+            .iter()
+            .find(|e| e.id == cf.condition)
+            .unwrap();
+        assert_eq!(condition.code, "item.done");
+        assert_eq!(
+            condition.loop_context.as_ref().unwrap().variables,
+            vec!["item".to_string()]
+        );
+
+        // The original .map expression is gone from the registry
+        assert!(!template
+            .expressions
+            .iter()
+            .any(|e| e.code.contains(".map(")));
+    }
+
+    /// Attributes of the first element in the body of the loop at the root of `template`
+    fn loop_item_attributes(template: &TemplateIR) -> &[crate::validate::AttributeIR] {
+        let TemplateNode::Element(ul) = &template.nodes[0] else {
+            panic!("expected <ul>, got {:?}", template.nodes[0]);
+        };
+        let TemplateNode::LoopFragment(lf) = &ul.children[0] else {
+            panic!("expected a loop fragment, got {:?}", ul.children);
+        };
+        let TemplateNode::Element(li) = &lf.body[0] else {
+            panic!("expected <li>, got {:?}", lf.body);
+        };
+        &li.attributes
+    }
+
+    #[test]
+    fn test_duplicate_class_in_loop_body_merges() {
+        let mut template = crate::parse::parse_template(
+            "<ul>{items.map(item => <li class=\"row\" class={item.kind}>{item.name}</li>)}</ul>",
+            "page.zen",
+        )
+        .unwrap();
+        let mut warnings = Vec::new();
+        let errors = lower_fragments(&mut template, "page.zen", &mut warnings);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let classes: Vec<&AttributeValue> = loop_item_attributes(&template)
+            .iter()
+            .filter(|a| a.name == "class")
+            .map(|a| &a.value)
+            .collect();
+        assert_eq!(classes.len(), 2, "{:?}", classes);
+        assert!(matches!(classes[0], AttributeValue::Static(v) if v == "row"));
+        assert!(matches!(classes[1], AttributeValue::Dynamic(e) if e.code == "item.kind"));
+    }
+
+    #[test]
+    fn test_duplicate_attribute_in_loop_body_keeps_last() {
+        let mut template = crate::parse::parse_template(
+            "<ul>{items.map(item => <li data-x=\"first\" data-x={item.id}>{item.name}</li>)}</ul>",
+            "page.zen",
+        )
+        .unwrap();
+        let mut warnings = Vec::new();
+        let errors = lower_fragments(&mut template, "page.zen", &mut warnings);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let data_x: Vec<&AttributeValue> = loop_item_attributes(&template)
+            .iter()
+            .filter(|a| a.name == "data-x")
+            .map(|a| &a.value)
+            .collect();
+        assert_eq!(data_x.len(), 1);
+        assert!(matches!(data_x[0], AttributeValue::Dynamic(e) if e.code == "item.id"));
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].starts_with("Z-WARN-DUPLICATE-ATTRIBUTE: <li> in a loop over `items` in page.zen has 2 `data-x` attributes"),
+            "{}",
+            warnings[0]
+        );
+        assert!(warnings[0].contains("{item.id}"));
+    }
+
+    #[test]
+    fn test_invalid_attribute_names_are_rejected() {
+        let mut nodes =
+            crate::parse::parse_template("<li 1x=\"a\" title=\"ok\">x</li>", "page.zen")
+                .unwrap()
+                .nodes;
+        let mut lowering = Lowering {
+            file_path: "page.zen",
+            expressions: Vec::new(),
+            lowered: HashSet::new(),
+            context: Vec::new(),
+            anchor: String::new(),
+            registered: 0,
+            warnings: Vec::new(),
+            errors: Vec::new(),
+        };
+        lowering.check_attribute_names(&mut nodes, "a loop over `items`");
+
+        assert_eq!(lowering.errors.len(), 1);
+        assert!(lowering.errors[0]
+            .starts_with("Z-ERR-ATTRIBUTE-NAME: `1x` on <li> in a loop over `items` in page.zen"));
+        let TemplateNode::Element(li) = &nodes[0] else {
+            panic!("expected <li>, got {:?}", nodes[0]);
+        };
+        let names: Vec<&str> = li.attributes.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["title"]);
+    }
+
+    fn compile(source: &str) -> crate::parse::CompileResult {
+        crate::parse::compile_zen_internal(
+            source,
+            "page.zen",
+            crate::parse::CompileOptions {
+                ast_classifier: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    const STATUS_CHAIN: &str = "<script>\nstate status = 'loading'\n</script>\n<main>{status === 'loading' ? <p>Loading</p> : status === 'error' ? <p>Failed</p> : <p>Done</p>}</main>";
+
+    #[test]
+    fn test_else_if_chain_lowers_to_one_multi_branch_fragment() {
+        let mut template = crate::parse::parse_template(STATUS_CHAIN, "page.zen").unwrap();
+        let errors = lower_fragments(&mut template, "page.zen", &mut Vec::new());
+        assert!(errors.is_empty(), "{:?}", errors);
+        let TemplateNode::Element(main) = template
+            .nodes
+            .iter()
+            .find(|n| matches!(n, TemplateNode::Element(el) if el.tag == "main"))
+            .unwrap()
+        else {
+            unreachable!()
+        };
+        let TemplateNode::MultiBranchFragment(mb) = &main.children[0] else {
+            panic!("expected a multi-branch fragment, got {:?}", main.children);
+        };
+        assert_eq!(mb.branches.len(), 2);
+        assert!(mb
+            .children()
+            .all(|nodes| matches!(nodes.as_slice(), [TemplateNode::Element(_)])));
+
+        let result = compile(STATUS_CHAIN);
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert_eq!(
+            result.html.matches("data-zen-switch=").count(),
+            1,
+            "{}",
+            result.html
+        );
+        assert_eq!(
+            result.html.matches("data-zen-case=").count(),
+            3,
+            "{}",
+            result.html
+        );
+        assert!(
+            !result.html.contains("data-zen-conditional"),
+            "{}",
+            result.html
+        );
+        assert!(
+            result.html.contains(
+                "<div data-zen-case=\"default\" style=\"display: contents;\"><p>Done</p></div>"
+            ),
+            "{}",
+            result.html
+        );
+    }
+
+    #[test]
+    fn test_multi_branch_bindings_follow_chain_order() {
+        let result = compile(STATUS_CHAIN);
+        let cases: Vec<_> = result
+            .bindings
+            .iter()
+            .filter(|b| b.r#type == "case")
+            .collect();
+        let codes: Vec<&str> = cases.iter().map(|b| b.expression.as_str()).collect();
+        assert_eq!(codes, vec!["status === 'loading'", "status === 'error'"]);
+
+        let ids: Vec<&str> = cases.iter().map(|b| b.id.as_str()).collect();
+        assert!(
+            result
+                .html
+                .contains(&format!("data-zen-switch=\"{}\"", ids.join(" "))),
+            "{}",
+            result.html
+        );
+        // The render function keeps the chained ternary
+        let bundle = result.manifest.unwrap().bundle;
+        let first = bundle.find(&format!("(_expr_{}(", ids[0])).unwrap();
+        let second = bundle.find(&format!("(_expr_{}(", ids[1])).unwrap();
+        assert!(first < second, "{}", bundle);
+    }
+
+    #[test]
+    fn test_plain_ternary_stays_conditional_fragment() {
+        let result = compile(
+            "<script>\nstate open = true\n</script>\n<main>{open ? <p>Open</p> : <p>Closed</p>}</main>",
+        );
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(
+            result.html.contains("data-zen-conditional="),
+            "{}",
+            result.html
+        );
+        assert!(!result.html.contains("data-zen-switch"), "{}", result.html);
+        assert!(result.bindings.iter().any(|b| b.r#type == "conditional"));
+    }
+}
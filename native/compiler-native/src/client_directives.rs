@@ -0,0 +1,375 @@
+//! Client Loading Directives
+//!
+//! A component usage chooses when its instance hydrates:
+//!
+//! - `client:load` (the default): scope registration, expressions and `__run()`
+//!   happen with the rest of the page.
+//! - `client:visible`: once the instance's server-rendered HTML scrolls into view.
+//! - `client:idle`: once the browser is idle (`requestIdleCallback`).
+//!
+//! The directives are not props. A usage carrying one is wrapped in
+//! `<zen-hydrate data-zen-hydrate="visible" data-zen-instance="instN" style="display: contents">`.
+//! For a deferred instance, component resolution moves the scope setup of the
+//! instance, and of every instance nested in it, into a function registered on
+//! `window.__ZENITH_DEFERRED__["instN"]`. Codegen keeps the wrapper's registry
+//! entries out of the eager registry and hydrates the subtree from a `__zenDefer`
+//! block instead. Until then the page hydration keeps the server-rendered wrapper.
+//!
+//! A directive inside a deferred instance has no effect of its own: nested
+//! components hydrate with the outermost deferral.
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+use crate::validate::{AttributeIR, AttributeValue, ElementNode, SourceLocation, TemplateNode};
+
+/// Prefix of the loading directives on a component usage
+pub const CLIENT_DIRECTIVE_PREFIX: &str = "client:";
+
+/// Wrapper element around an instance with a loading directive
+pub const HYDRATE_TAG: &str = "zen-hydrate";
+
+/// Loading strategy on the wrapper (`load`, `visible`, `idle`)
+pub const HYDRATE_ATTR: &str = "data-zen-hydrate";
+
+/// Instance id on the wrapper, the key of its deferred setup function
+pub const HYDRATE_INSTANCE_ATTR: &str = "data-zen-instance";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HydrateStrategy {
+    Load,
+    Visible,
+    Idle,
+}
+
+impl HydrateStrategy {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "load" => Some(Self::Load),
+            "visible" => Some(Self::Visible),
+            "idle" => Some(Self::Idle),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Load => "load",
+            Self::Visible => "visible",
+            Self::Idle => "idle",
+        }
+    }
+
+    pub(crate) fn is_deferred(self) -> bool {
+        self != Self::Load
+    }
+}
+
+/// Manifest entry for an instance whose hydration a loading directive decides
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi(object))]
+pub struct InstanceHydration {
+    /// `instN` of the instance
+    pub instance: String,
+    /// Registry key of the component
+    pub component: String,
+    /// `load`, `visible` or `idle`; inherited from `group` when nested in it
+    pub strategy: String,
+    /// Outermost instance whose trigger hydrates this one (itself unless nested)
+    pub group: String,
+}
+
+/// Remove the `client:*` directives from a usage's attributes and return the
+/// strategy they select. Unknown and conflicting directives are errors.
+pub(crate) fn take_client_directive(
+    tag: &str,
+    path: &str,
+    attributes: &mut Vec<AttributeIR>,
+) -> Result<Option<HydrateStrategy>, String> {
+    let mut found: Option<HydrateStrategy> = None;
+    let mut error = None;
+    attributes.retain(|attr| {
+        let Some(value) = attr.name.strip_prefix(CLIENT_DIRECTIVE_PREFIX) else {
+            return true;
+        };
+        match HydrateStrategy::parse(value) {
+            Some(strategy) if found.is_some_and(|f| f != strategy) => {
+                error.get_or_insert(format!(
+                    "Z-ERR-CLIENT-DIRECTIVE: <{}> ({}) has both `client:{}` and `client:{}`; a usage takes one loading directive.",
+                    tag,
+                    path,
+                    found.unwrap().as_str(),
+                    value
+                ));
+            }
+            Some(strategy) => found = Some(strategy),
+            None => {
+                error.get_or_insert(format!(
+                    "Z-ERR-CLIENT-DIRECTIVE: <{}> ({}) has unknown directive `{}`. Use `client:load`, `client:visible` or `client:idle`.",
+                    tag, path, attr.name
+                ));
+            }
+        }
+        false
+    });
+    match error {
+        Some(error) => Err(error),
+        None => Ok(found),
+    }
+}
+
+/// Wrapper placed around an instance with a loading directive (component resolution)
+pub(crate) fn hydrate_wrapper(
+    nodes: Vec<TemplateNode>,
+    instance: &str,
+    strategy: HydrateStrategy,
+    location: SourceLocation,
+) -> TemplateNode {
+    TemplateNode::Element(ElementNode {
+        tag: HYDRATE_TAG.to_string(),
+        attributes: vec![
+            static_attr(HYDRATE_ATTR, strategy.as_str()),
+            static_attr(HYDRATE_INSTANCE_ATTR, instance),
+            static_attr("style", "display: contents"),
+        ],
+        children: nodes,
+        location,
+        loop_context: None,
+        ordinal: 0,
+        extra: Default::default(),
+    })
+}
+
+/// Register the scope setup of a deferred instance and the instances nested in
+/// it (`blocks`) under `window.__ZENITH_DEFERRED__`, instead of running it inline.
+/// The function returns the scope ids it registered, for their `__run()` calls.
+pub(crate) fn deferred_setup(
+    group: &str,
+    strategy: HydrateStrategy,
+    blocks: &str,
+    scopes: &[String],
+) -> String {
+    format!(
+        "\n\n// --- Deferred {group} ({strategy}) ---\nwindow.__ZENITH_DEFERRED__ = window.__ZENITH_DEFERRED__ || {{}};\nwindow.__ZENITH_DEFERRED__[\"{group}\"] = function() {{{blocks}\n  return [{scopes}];\n}};",
+        strategy = strategy.as_str(),
+        scopes = scopes
+            .iter()
+            .map(|s| format!("\"{}\"", s))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// A deferred wrapper in the resolved template
+pub(crate) struct DeferredGroup<'n> {
+    pub instance: String,
+    pub strategy: HydrateStrategy,
+    pub nodes: &'n [TemplateNode],
+}
+
+/// The wrapper's instance and strategy, when it defers hydration
+pub(crate) fn deferred_wrapper(el: &ElementNode) -> Option<(String, HydrateStrategy)> {
+    if el.tag != HYDRATE_TAG {
+        return None;
+    }
+    let value = |name: &str| {
+        el.attributes.iter().find_map(|a| match &a.value {
+            AttributeValue::Static(v) if a.name == name => Some(v.clone()),
+            _ => None,
+        })
+    };
+    let strategy = HydrateStrategy::parse(&value(HYDRATE_ATTR)?)?;
+    if !strategy.is_deferred() {
+        return None;
+    }
+    Some((value(HYDRATE_INSTANCE_ATTR)?, strategy))
+}
+
+/// Deferred wrappers in document order (nested components never get their own)
+pub(crate) fn deferred_groups(nodes: &[TemplateNode]) -> Vec<DeferredGroup<'_>> {
+    fn walk<'n>(nodes: &'n [TemplateNode], groups: &mut Vec<DeferredGroup<'n>>) {
+        for node in nodes {
+            match node {
+                TemplateNode::Element(el) => match deferred_wrapper(el) {
+                    Some((instance, strategy)) => groups.push(DeferredGroup {
+                        instance,
+                        strategy,
+                        nodes: &el.children,
+                    }),
+                    None => walk(&el.children, groups),
+                },
+                TemplateNode::Component(c) => walk(&c.children, groups),
+                TemplateNode::ConditionalFragment(cf) => {
+                    walk(&cf.consequent, groups);
+                    walk(&cf.alternate, groups);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    for nodes in mb.children() {
+                        walk(nodes, groups);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => walk(&of.fragment, groups),
+                TemplateNode::LoopFragment(lf) => walk(&lf.body, groups),
+                TemplateNode::Text(_) | TemplateNode::Expression(_) | TemplateNode::Doctype(_) => {}
+            }
+        }
+    }
+
+    let mut groups = Vec::new();
+    walk(nodes, &mut groups);
+    groups
+}
+
+/// Bundle helper that runs a deferred hydration when its trigger fires. `visible`
+/// observes the wrapper's element children (the wrapper itself has no box); with
+/// nothing to observe, or no IntersectionObserver, it hydrates right away.
+pub(crate) const DEFER_HELPER_JS: &str = "    const __zenDefer = (strategy, root, hydrate) => {
+      if (strategy === 'idle') {
+        const idle = window.requestIdleCallback || ((cb) => setTimeout(cb, 1));
+        idle(() => hydrate());
+        return;
+      }
+      const targets = root ? Array.from(root.children) : [];
+      if (targets.length === 0 || typeof IntersectionObserver === 'undefined') {
+        hydrate();
+        return;
+      }
+      const observer = new IntersectionObserver((entries) => {
+        if (!entries.some((e) => e.isIntersecting)) return;
+        observer.disconnect();
+        hydrate();
+      });
+      targets.forEach((t) => observer.observe(t));
+    };";
+
+fn static_attr(name: &str, value: &str) -> AttributeIR {
+    AttributeIR {
+        name: name.to_string(),
+        value: AttributeValue::Static(value.to_string()),
+        location: SourceLocation::default(),
+        loop_context: None,
+        optional: false,
+        extra: Default::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::component::ComponentIR;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(page: &str, components: &[(&str, &str)]) -> Result<CompileResult, String> {
+        let mut registry = std::collections::HashMap::new();
+        for (name, source) in components {
+            let path = format!("components/{}.zen", name);
+            let component = ComponentIR::from_source(name, &path, source).unwrap();
+            registry.insert(name.to_string(), serde_json::to_value(component).unwrap());
+        }
+        let options = CompileOptions {
+            components: registry,
+            ..Default::default()
+        };
+        compile_zen_internal(page, "page.zen", options)
+    }
+
+    const CHART: &str = "<script>\nprop points\nstate zoom = 1\n</script>\n<figure class=\"chart\">{points.length} at {zoom}x</figure>";
+
+    /// The eager registry: everything before the first deferred block
+    fn eager_part(bundle: &str) -> &str {
+        bundle.split("// Deferred ").next().unwrap()
+    }
+
+    #[test]
+    fn test_visible_instance_registers_expressions_when_deferred() {
+        let page = "<script>\nstate points = [1, 2]\n</script>\n<main><h1>{points.length}</h1><Chart client:visible points={points} /></main>";
+        let result = compile(page, &[("Chart", CHART)]).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let manifest = result.manifest.unwrap();
+        let bundle = &manifest.bundle;
+
+        assert!(result
+            .html
+            .contains("<zen-hydrate data-zen-hydrate=\"visible\" data-zen-instance=\"inst0\""));
+        assert!(result.html.contains("class=\"chart\""));
+
+        let eager = eager_part(bundle);
+        assert!(!eager.contains("_inst0', { fn"));
+        // The scope is only registered inside its deferred setup function
+        let (before, setup) = bundle
+            .split_once("window.__ZENITH_DEFERRED__[\"inst0\"] = function()")
+            .unwrap();
+        assert!(!before.contains("__ZENITH_SCOPES__[\"inst0\"] = {"));
+        assert!(setup.contains("__ZENITH_SCOPES__[\"inst0\"] = {"));
+        // Only the page's own `{points.length}` stays eager
+        assert_eq!(eager.matches("__ZENITH_EXPRESSIONS__.set(").count(), 1);
+
+        let deferred = &bundle[eager.len()..];
+        assert!(deferred.starts_with("// Deferred inst0 (visible)"));
+        assert!(deferred.contains("__zenDefer('visible', __root"));
+        // Both of the chart's expressions (ids are numbered across the whole run)
+        assert_eq!(deferred.matches("_inst0', { fn").count(), 2);
+
+        // A loading directive is not a prop
+        assert!(!bundle.contains("\"client:visible\""));
+        assert_eq!(manifest.hydration.len(), 1);
+        assert_eq!(manifest.hydration[0].strategy, "visible");
+        assert_eq!(manifest.hydration[0].group, "inst0");
+    }
+
+    #[test]
+    fn test_nested_components_inherit_outermost_deferral() {
+        let panel = "<script>\nstate open = false\n</script>\n<section><Chart client:idle points={[3]} /><p>{open}</p></section>";
+        let page = "<main><Panel client:visible /></main>";
+        let result = compile(page, &[("Panel", panel), ("Chart", CHART)]).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let manifest = result.manifest.unwrap();
+
+        // One wrapper: the nested directive has no effect of its own
+        assert_eq!(result.html.matches("<zen-hydrate").count(), 1);
+        assert!(!manifest.bundle.contains("Deferred inst1"));
+        let hydration: Vec<(&str, &str, &str)> = manifest
+            .hydration
+            .iter()
+            .map(|h| (h.instance.as_str(), h.strategy.as_str(), h.group.as_str()))
+            .collect();
+        assert_eq!(
+            hydration,
+            vec![("inst0", "visible", "inst0"), ("inst1", "visible", "inst0")]
+        );
+
+        // Both scopes are set up, and their expressions registered, by the outer trigger
+        let setup = manifest
+            .bundle
+            .split("window.__ZENITH_DEFERRED__[\"inst0\"] = function()")
+            .nth(1)
+            .unwrap();
+        assert!(setup.contains("__ZENITH_SCOPES__[\"inst1\"] = {"));
+        assert!(setup.contains("return [\"inst0\", \"inst1\"];"));
+        let eager = eager_part(&manifest.bundle);
+        assert!(!eager.contains("_inst1', { fn"));
+        assert!(manifest.bundle[eager.len()..].contains("_inst1', { fn"));
+    }
+
+    #[test]
+    fn test_explicit_load_hydrates_eagerly() {
+        let page = "<main><Chart client:load points={[1]} /></main>";
+        let result = compile(page, &[("Chart", CHART)]).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let bundle = result.manifest.unwrap().bundle;
+        assert!(result.html.contains("data-zen-hydrate=\"load\""));
+        assert!(!bundle.contains("__zenDefer"));
+        assert_eq!(bundle.matches("_inst0', { fn").count(), 2);
+    }
+
+    #[test]
+    fn test_unknown_client_directive_errors() {
+        let page = "<main><Chart client:hover points={[1]} /></main>";
+        let err = compile(page, &[("Chart", CHART)]).unwrap_err();
+        assert!(err.contains("Z-ERR-CLIENT-DIRECTIVE"), "{}", err);
+        assert!(err.contains("`client:hover`"), "{}", err);
+    }
+}
@@ -3,8 +3,12 @@
 //! Generates runtime JavaScript code from ZenIR input.
 //! This is the Rust authority for all compilation - no TypeScript fallback.
 
+use crate::binding_table::{BindingKind, BindingTable};
 use crate::jsx_lowerer::{JsxLowerer, ScriptRenamer};
-use crate::validate::{AttributeValue, ElementNode, ExpressionInput, StyleIR, TemplateNode};
+use crate::validate::{
+    AttributeValue, ElementNode, ExpressionInput, LoopContextInput, StyleIR, TemplateNode,
+};
+use lazy_static::lazy_static;
 #[cfg(feature = "napi")]
 use napi_derive::napi;
 use oxc_allocator::{Allocator, CloneIn};
@@ -17,6 +21,11 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
+lazy_static! {
+    /// Specifiers of a printed import statement
+    static ref IMPORT_SPECIFIERS_RE: Regex = Regex::new(r"import\s+(.*?)\s+from").unwrap();
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // INPUT/OUTPUT TYPES
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -39,6 +48,25 @@ pub struct CodegenInput {
     pub all_states: HashMap<String, String>,
     #[serde(default)]
     pub locals: Vec<String>, // Component-level local variables (const, let, var, function)
+    /// Islands mode: `nodes` is the islands' content and each island mounts on its own
+    #[serde(default)]
+    pub islands: Option<Vec<crate::islands::IslandIR>>,
+    /// Production output: no banner comments, compact expression and template IR sections
+    #[serde(default)]
+    pub minify: bool,
+    /// Embedded in an iframe `srcdoc` (see `embed.rs`): hydrate without assuming
+    /// the document is still loading
+    #[serde(default)]
+    pub embed: bool,
+    /// Console calls and `debugger` statements to remove (see `strip_debug.rs`)
+    #[serde(default)]
+    pub strip_debug: crate::strip_debug::StripDebug,
+    /// Checked before each expression; the caller discards a cancelled result
+    #[serde(skip)]
+    pub cancel: Option<crate::cancel::CancellationToken>,
+    /// Syntax level the generated sections are lowered to (see `es_target.rs`)
+    #[serde(default)]
+    pub target_es: crate::es_target::EsTarget,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,7 +76,10 @@ pub struct StateDeclaration {
     pub initial_value: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Package every bundle imports the Zenith runtime from
+pub(crate) const RUNTIME_PACKAGE: &str = "@zenithbuild/runtime";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "napi", napi(object))]
 #[serde(rename_all = "camelCase")]
 pub struct RuntimeCode {
@@ -61,6 +92,18 @@ pub struct RuntimeCode {
     pub bundle: String,
     pub npm_imports: Vec<ScriptImport>,
     pub errors: Vec<String>,
+    /// Non-fatal diagnostics from expressions (e.g. an event handler mutating a prop)
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Console calls and `debugger` statements removed from the script and expressions
+    #[serde(default)]
+    pub stripped_debug_statements: u32,
+    /// Lazy handler chunk, when a `zen:lazy` handler's imports were split off
+    #[serde(default)]
+    pub chunks: Vec<crate::lazy::BundleChunk>,
+    /// `Z-ERR-ES-TARGET` for syntax that could not be lowered to `target_es`
+    #[serde(default)]
+    pub es_target_errors: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,54 +126,802 @@ pub fn generate_codegen_intent() -> String {
     "Rust Codegen Authority".to_string()
 }
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// CODEGEN PLAN
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// What codegen knows about a page before it generates any code, so a caller can
+/// decide whether and how to emit it (see `CompileOptions.plan_filter`).
+/// `emit_runtime_code` generates the runtime code from the plan alone.
+#[derive(Debug, Clone)]
+pub struct CodegenPlan {
+    /// Page states with their initial values, in the order the bundle declares them
+    pub state_declarations: Vec<StateDeclaration>,
+    /// Expressions the bundle registers, in emit order
+    pub expressions: Vec<ExpressionPlan>,
+    /// Runtime imports of the script, with `.zen` components dropped and duplicates merged
+    pub imports: Vec<ScriptImport>,
+    /// Ids of the expressions bound as event handlers, sorted
+    pub event_handler_ids: Vec<String>,
+    /// `zenRoute()` declarations hoisted ahead of the state
+    pub environment_prelude: Vec<String>,
+    /// Lines (1-based) of the script's top-level `await`s
+    pub top_level_awaits: Vec<u32>,
+    /// Number of islands in islands mode (see `islands.rs`), `None` otherwise
+    pub islands: Option<u32>,
+    pub estimated_sizes: SizeEstimate,
+    /// The input with loop sources registered and island content flattened
+    input: CodegenInput,
+    /// Expressions the template references, before wrapping
+    sources: Vec<ExpressionInput>,
+    lazy_split: crate::lazy::LazySplit,
+}
+
+/// One expression of a `CodegenPlan`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpressionPlan {
+    pub id: String,
+    /// State it re-runs on; for a handler wrapped in a function, the state it
+    /// writes (its reads happen when the event fires)
+    pub deps: Vec<String>,
+    /// State it assigns, notified after it runs
+    pub mutations: Vec<String>,
+    pub event_handler: bool,
+    /// Called with the loop variables in scope
+    pub uses_loop: bool,
+    /// `zen:static`: evaluated once and never re-run
+    pub once: bool,
+    /// Registered when its `client:visible` / `client:idle` trigger fires
+    pub deferred: bool,
+    /// `zen:lazy` handler whose imports load on first use
+    pub lazy: bool,
+}
+
+/// Output sizes in bytes, estimated from the sources before minification and
+/// target lowering
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SizeEstimate {
+    pub script: u32,
+    pub expressions: u32,
+    pub template_ir: u32,
+    pub styles: u32,
+    pub bundle: u32,
+}
+
+/// What `CompileOptions.plan_filter` decides for a page
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanDecision {
+    /// Emit the runtime code as planned
+    Proceed,
+    /// Emit nothing: the page ships its HTML without a bundle, and the compile
+    /// reports `Z-INFO-CODEGEN-SKIPPED` with the reason
+    Skip(String),
+    /// Compile the page again in islands mode (see `islands.rs`); a page that is
+    /// already in islands mode proceeds
+    ForceIslands,
+}
+
+/// Decides on a page's codegen plan (see `CompileOptions.plan_filter`)
+pub type PlanFilter = fn(&CodegenPlan) -> PlanDecision;
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // INTERNAL IMPLEMENTATION
 // ═══════════════════════════════════════════════════════════════════════════════
 
+/// `plan_runtime_code` then `emit_runtime_code`
 pub fn generate_runtime_code_internal(input: CodegenInput) -> RuntimeCode {
+    emit_runtime_code(&plan_runtime_code(&input))
+}
+
+/// Analyze `input` without generating code
+pub fn plan_runtime_code(input: &CodegenInput) -> CodegenPlan {
     let allocator = Allocator::default();
-    let mut source_type = SourceType::default();
-    source_type = source_type.with_typescript(true);
-    source_type = source_type.with_jsx(true);
-    source_type = source_type.with_module(true);
+    let mut input = input.clone();
+    let (state_bindings, prop_bindings) = reactive_bindings(&input);
+
+    // 1. Replace "state " and "prop " with "let " for parsing
+    let parsable_script = parsable_script(&input.script_content);
+    let ret = Parser::new(&allocator, &parsable_script, script_source_type()).parse();
+    zen_debug!(parse_errors = ret.errors.len(), "parsed script for codegen");
+    let program = ret.program;
+
+    // 2-4. Initial values of the state bindings
+    let state_declarations =
+        state_declarations(&input, &parsable_script, &program, &state_bindings);
+
+    let sources = prepare_expressions(&mut input);
+    let lazy_split = split_lazy_imports(&input.nodes, &sources, &parsable_script);
+
+    let mut awaits = TopLevelAwaits::default();
+    oxc_ast_visit::Visit::visit_program(&mut awaits, &program);
+    let top_level_awaits = awaits
+        .0
+        .iter()
+        .map(|&start| parsable_script[..start as usize].matches('\n').count() as u32 + 1)
+        .collect();
 
-    // 1. Replace "state " with "let " for parsing
-    // Only match 'state' at statement boundaries (start, newline, semicolon, braces)
-    // Avoid matching 'state' in comments or strings
-    let state_re = Regex::new(r"state(\s+)").unwrap();
-    // 2. Extract state and prop bindings using Regex
-    let mut state_bindings = HashSet::new();
-    let mut prop_bindings = HashSet::new();
+    let script = split_script(
+        &allocator,
+        program,
+        &input,
+        &state_bindings,
+        &prop_bindings,
+        &lazy_split,
+    );
+    let bindings = binding_table(
+        &state_bindings,
+        &state_declarations,
+        &prop_bindings,
+        &script.script_locals,
+        &script.imported_identifiers,
+    );
+
+    // Intents, as emit computes them; only what they depend on is kept
+    let loop_vars: HashSet<String> = input.template_bindings.iter().cloned().collect();
+    let mut event_handler_ids = HashSet::new();
+    collect_event_handler_ids(&input.nodes, &mut event_handler_ids);
+    let lazy_ids = crate::lazy::lazy_handler_ids(&input.nodes);
+    let pending_targets = crate::pending::pending_targets(&input.nodes);
+    let frozen_ids = collect_frozen_expression_ids(&input.nodes);
+    let deferred_ids: HashSet<String> = crate::client_directives::deferred_groups(&input.nodes)
+        .into_iter()
+        .flat_map(|group| collect_expression_refs(group.nodes))
+        .collect();
+    let expressions: Vec<ExpressionPlan> = sources
+        .iter()
+        .take_while(|_| !input.cancel.as_ref().is_some_and(|c| c.is_cancelled()))
+        .map(|expr| {
+            let event_handler = event_handler_ids.contains(&expr.id);
+            let lazy = !lazy_split.is_empty() && lazy_ids.contains(&expr.id);
+            let (wrapped_expr, wrapped, _) = wrapped_expression(
+                expr,
+                event_handler,
+                lazy,
+                pending_targets.get(&expr.id),
+                &input.strip_debug,
+            );
+            let (_, state_deps, uses_loop, _, _, mutations) =
+                compute_expression_intent(&wrapped_expr, &bindings, &loop_vars, event_handler);
+            ExpressionPlan {
+                id: expr.id.clone(),
+                deps: if wrapped {
+                    mutations.clone()
+                } else {
+                    state_deps
+                },
+                mutations,
+                event_handler,
+                uses_loop,
+                once: frozen_ids.contains(&expr.id),
+                deferred: deferred_ids.contains(&expr.id),
+                lazy,
+            }
+        })
+        .collect();
+    let mut event_handler_ids: Vec<String> = event_handler_ids.into_iter().collect();
+    event_handler_ids.sort();
+
+    let estimated_sizes = estimate_sizes(&input, &sources, &state_declarations);
+    CodegenPlan {
+        state_declarations,
+        expressions,
+        imports: script.imports,
+        event_handler_ids,
+        environment_prelude: script.environment_prelude,
+        top_level_awaits,
+        islands: input.islands.as_ref().map(|islands| islands.len() as u32),
+        estimated_sizes,
+        input,
+        sources,
+        lazy_split,
+    }
+}
+
+/// Generate the runtime code `plan` describes
+pub fn emit_runtime_code(plan: &CodegenPlan) -> RuntimeCode {
+    let allocator = Allocator::default();
+    let input = &plan.input;
+    let expressions = &plan.sources;
+    let lazy_split = &plan.lazy_split;
+    let state_decls = &plan.state_declarations;
+    let (state_bindings, prop_bindings) = reactive_bindings(input);
+
+    // 3. Transform script with identifier renaming and HOIST IMPORTS
+    let parsable_script = parsable_script(&input.script_content);
+    let program = Parser::new(&allocator, &parsable_script, script_source_type())
+        .parse()
+        .program;
+
+    // 2.45 `client:visible` / `client:idle` subtrees register their expressions when
+    // their trigger fires, never with the eager registry (see `client_directives.rs`)
+    let deferred_groups: Vec<(String, &'static str, String, HashSet<String>)> =
+        crate::client_directives::deferred_groups(&input.nodes)
+            .into_iter()
+            .map(|group| {
+                (
+                    group.instance,
+                    group.strategy.as_str(),
+                    nodes_template_ir(group.nodes),
+                    collect_expression_refs(group.nodes),
+                )
+            })
+            .collect();
+    let deferred_ids: HashSet<&String> = deferred_groups
+        .iter()
+        .flat_map(|(_, _, _, ids)| ids)
+        .collect();
+    let lazy_ids = crate::lazy::lazy_handler_ids(&input.nodes);
+    let lazy_chunk_name = crate::lazy::chunk_name(&input.file_path);
+
+    let ScriptParts {
+        mut program,
+        import_lines,
+        imported_identifiers,
+        script_locals,
+        ..
+    } = split_script(
+        &allocator,
+        program,
+        input,
+        &state_bindings,
+        &prop_bindings,
+        lazy_split,
+    );
+
+    let mut all_errors = Vec::new();
+    let mut all_warnings = lazy_split.diagnostics.clone();
+    all_warnings.extend(crate::event_value::check_dynamic_types(
+        &input.nodes,
+        &input.file_path,
+    ));
+
+    // 3.5 One frozen binding table for the script and every expression (see `binding_table.rs`)
+    let bindings = binding_table(
+        &state_bindings,
+        state_decls,
+        &prop_bindings,
+        &script_locals,
+        &imported_identifiers,
+    );
+
+    // 3. (Continued) Final script and imports
+    let mut renamer = ScriptRenamer::from_table(&allocator, &bindings);
+    renamer.allow_prop_fallback = false; // Script context: Strict resolution
+    renamer.visit_program(&mut program);
+    all_errors.extend(renamer.errors);
+    let stripped_debug = std::cell::Cell::new(crate::strip_debug::strip_program(
+        &allocator,
+        &mut program,
+        &input.strip_debug,
+    ));
+
+    let script_no_imports = Codegen::new().build(&program).code;
+    let mut all_imports = import_lines.join("");
+    if !lazy_split.is_empty() {
+        all_imports.push_str(&lazy_split.loader(&lazy_chunk_name));
+    }
+
+    // 4. Loop variables are the only per-expression bindings
+    let loop_vars: HashSet<String> = input.template_bindings.iter().cloned().collect();
+
+    // 4.5 Loop sources that are not already expressions were registered in 2.5.
+    // Sources may be any expression (Object.entries(x), Array.from(x), [...a, b]),
+    // so they go through the same AST-based dependency extraction as expressions.
+
+    // 5. Generate Template IR
+    let template_ir = nodes_template_ir(&input.nodes);
+
+    let render_fn = format!(
+        "function renderDynamicPage(state) {{\n  return {};\n}}",
+        template_ir
+    );
+
+    // 5.5 Detect Event Handler Expression IDs (Phase A8)
+    let event_handler_ids: HashSet<&String> = plan.event_handler_ids.iter().collect();
+
+    // 5.52 Handlers of `zen:pending` elements, with the state they hold while running
+    let pending_targets = crate::pending::pending_targets(&input.nodes);
+
+    // 5.55 `zen:static` subtrees: evaluated once, never re-run on state changes
+    let frozen_ids = collect_frozen_expression_ids(&input.nodes);
+
+    // 5.6 Head expressions run immediately during render; on failure they fall back
+    // to their statically-evaluated (initial state) value instead of rendering empty
+    let mut head_expression_ids = HashSet::new();
+    collect_head_expression_ids(&input.nodes, &mut head_expression_ids);
+    let initial_values = crate::static_eval::initial_state_values(&input.all_states);
+    let head_fallbacks: HashMap<String, String> = expressions
+        .iter()
+        .filter(|e| head_expression_ids.contains(&e.id))
+        .filter_map(|e| {
+            crate::static_eval::static_eval(&e.code, &initial_values)
+                .and_then(|v| serde_json::to_string(&v).ok())
+                .map(|literal| (e.id.clone(), literal))
+        })
+        .collect();
+
+    // 6. Generate Expression Wrappers
+    let expression_deps = std::cell::RefCell::new(HashMap::new());
+    let expressions_code =
+        expressions
+            .iter()
+            .take_while(|_| !input.cancel.as_ref().is_some_and(|c| c.is_cancelled()))
+            .map(|expr| {
+                let is_event_handler = event_handler_ids.contains(&expr.id);
+                let (expr, wrapped, stripped) = wrapped_expression(
+                    expr,
+                    is_event_handler,
+                    !lazy_split.is_empty() && lazy_ids.contains(&expr.id),
+                    pending_targets.get(&expr.id),
+                    &input.strip_debug,
+                );
+                stripped_debug.set(stripped_debug.get() + stripped);
+                let expr = &expr;
+                let (
+                    transformed_code,
+                    state_deps,
+                    uses_loop,
+                    expr_errors,
+                    expr_warnings,
+                    mutated_deps,
+                ) = compute_expression_intent(expr, &bindings, &loop_vars, is_event_handler);
+                all_errors.extend(expr_errors.into_iter().map(|e| match &expr.origin {
+                    Some(origin) => format!("{}, in {}", e, origin),
+                    None => e,
+                }));
+                all_warnings.extend(expr_warnings.into_iter().map(|w| match &expr.origin {
+                    Some(origin) => format!("{}, in {}", w, origin),
+                    None => w,
+                }));
+                // What a wrapped handler reads is read when the event fires, not at render
+                let registered_deps = if wrapped {
+                    mutated_deps.clone()
+                } else {
+                    state_deps
+                };
+                expression_deps
+                    .borrow_mut()
+                    .insert(expr.id.clone(), registered_deps);
+
+                // Phase 6: Wrap expressions with notification for mutated deps
+                let mut final_code = transformed_code.trim_end_matches(';').to_string();
+                if !mutated_deps.is_empty() {
+                    let notifications: Vec<String> = mutated_deps
+                        .iter()
+                        .map(|d| format!("window.zenithNotify(scope, 'state', '{}');", d))
+                        .collect();
+                    final_code = format!(
+                        "(() => {{ const __v = ({});\n  {};\n  return __v; }})()",
+                        final_code,
+                        notifications.join("\n  ")
+                    );
+                } else {
+                    final_code = format!("({});", final_code);
+                }
+
+                // Render calls pass the loop variables in scope at the node (see `get_node_args`)
+                let args = match &expr.loop_context {
+                    Some(lc) if !lc.variables.is_empty() => {
+                        format!("scope, {}", lc.variables.join(", "))
+                    }
+                    _ if uses_loop => "scope, item, index, array".to_string(),
+                    _ => "scope".to_string(),
+                };
+
+                let fn_name = format!("_expr_{}", expr.id);
+                let fallback = head_fallbacks
+                    .get(&expr.id)
+                    .map(|literal| literal.as_str())
+                    .unwrap_or("''");
+                expression_function(&fn_name, &args, &final_code, &expr.id, fallback)
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+    let deps_map = expression_deps.into_inner();
+    let registry_entry = |e: &ExpressionInput| {
+        if frozen_ids.contains(&e.id) {
+            return format!(
+                "window.__ZENITH_EXPRESSIONS__.set('{}', {{ fn: _expr_{}, deps: [], once: true }});",
+                e.id, e.id
+            );
+        }
+        let deps = deps_map.get(&e.id).cloned().unwrap_or_default();
+        let deps_js = format!(
+            "[{}]",
+            deps.iter()
+                .map(|d| format!("'{}'", d))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        format!(
+            "window.__ZENITH_EXPRESSIONS__.set('{}', {{ fn: _expr_{}, deps: {} }});",
+            e.id, e.id, deps_js
+        )
+    };
+    let eager_entries: Vec<String> = expressions
+        .iter()
+        .filter(|e| !deferred_ids.contains(&e.id))
+        .map(|e| format!("  {}", registry_entry(e)))
+        .collect();
+    let expression_registry = if eager_entries.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "if (typeof window !== 'undefined') {{\n  if (!window.__ZENITH_EXPRESSIONS__) window.__ZENITH_EXPRESSIONS__ = new Map();\n{}\n}}",
+            eager_entries.join("\n")
+        )
+    };
+
+    // 8. Styles
+    let styles_code = input
+        .styles
+        .iter()
+        .map(|s| s.raw.clone())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // 9. State Init
+    let state_init_code = state_decls
+        .iter()
+        .map(|d| format!("state.{} = {};", d.name, d.initial_value))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let state_props: Vec<String> = state_decls
+        .iter()
+        .map(|d| format!("  {}: {}", d.name, d.initial_value))
+        .collect();
+
+    let reactive_state_init = format!(
+        "const state = /* @__PURE__ */ zenState({{\n{}\n}});\n  const __defaultState = state;\n  const props = {{}};\n  const locals = {{}};\n  const scope = {{ state, props, locals }};",
+        state_props.join(",\n")
+    );
+
+    // 10. Hydration Runtime (External Import)
+    // We no longer embed the runtime string. We generate an ESM import with named aliases.
+    let hydration = format!(
+        r#"import {{
+  signal as zenSignal,
+  state as zenState,
+  effect as zenEffect,
+  memo as zenMemo,
+  ref as zenRef,
+  onMount as zenOnMount,
+  onUnmount as zenOnUnmount,
+  batch as zenBatch,
+  untrack as zenUntrack
+}} from "{}";"#,
+        RUNTIME_PACKAGE
+    );
+
+    // 10.5 Islands mode mounts each island into its own wrapper instead of the document
+    let (template_section, hydrate_section) = match &input.islands {
+        None => (
+            format!(
+                "const canonicalIR = (scope) => {{\n    return {};\n  }};\n  window.canonicalIR = canonicalIR;",
+                template_ir
+            ),
+            "    if (typeof window.zenithHydrate === 'function') {\n      window.zenithHydrate(state, document, locals);\n    }".to_string(),
+        ),
+        Some(islands) if islands.is_empty() => (
+            "// Islands mode: built per island in initHydration".to_string(),
+            "    // Islands mode: nothing on this page hydrates".to_string(),
+        ),
+        Some(islands) => (
+            "// Islands mode: built per island in initHydration".to_string(),
+            islands
+                .iter()
+                .map(island_init_block)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+    };
+
+    // 10.52 Deferred subtrees hydrate after the page, each on its own trigger
+    let hydrate_section = if deferred_groups.is_empty() {
+        hydrate_section
+    } else {
+        let blocks: Vec<String> = deferred_groups
+            .iter()
+            .map(|(instance, strategy, ir, ids)| {
+                let registrations: Vec<String> = expressions
+                    .iter()
+                    .filter(|e| ids.contains(&e.id))
+                    .map(registry_entry)
+                    .collect();
+                deferred_init_block(instance, strategy, &registrations, ir)
+            })
+            .collect();
+        format!(
+            "{}\n{}\n{}",
+            hydrate_section,
+            crate::client_directives::DEFER_HELPER_JS,
+            blocks.join("\n")
+        )
+    };
+
+    // 10.55 Development asserts that survived stripping need their helper
+    let script_no_imports = if [&script_no_imports, &expressions_code]
+        .iter()
+        .any(|code| code.contains("zenAssert("))
+    {
+        format!(
+            "{}{}",
+            crate::zen_assert::ASSERT_HELPER_JS,
+            script_no_imports
+        )
+    } else {
+        script_no_imports
+    };
 
-    // Merge page-level bindings
-    if !input.page_bindings.is_empty() {
+    // 10.6 Legacy browser output: lower the generated sections to the target
+    let mut lowering = crate::es_target::Lowering::new(input.target_es);
+    let script_no_imports = lowering.section(script_no_imports);
+    let expressions_code = lowering.section(expressions_code);
+    let expression_registry = lowering.section(expression_registry);
+    let template_section = lowering.section(template_section);
+    let chunks = if lazy_split.is_empty() {
+        vec![]
     } else {
+        let mut chunk = lazy_split.chunk(&lazy_chunk_name);
+        chunk.code = lowering.section(chunk.code);
+        vec![chunk]
+    };
+    let es_target_errors =
+        lowering.errors(&input.script_content, &input.expressions, &input.file_path);
+
+    // 10.7 Production output: compact the generated sections
+    let (expressions_code, expression_registry, template_section) = if input.minify {
+        (
+            crate::minify::minify_js(&expressions_code),
+            crate::minify::minify_js(&expression_registry),
+            crate::minify::minify_js(&template_section),
+        )
+    } else {
+        (expressions_code, expression_registry, template_section)
+    };
+
+    // 10.8 Empty sections are left out entirely rather than emitted as no-ops
+    let expressions_section = if expressions_code.is_empty() && expression_registry.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "  // 7. Expressions\n  {}\n  {}\n\n",
+            expressions_code, expression_registry
+        )
+    };
+    let styles_section = if styles_code.trim().is_empty() {
+        String::new()
+    } else {
+        format!(
+            r#"  // 8. Styles injection
+  const __styles = /* @__PURE__ */ `{}`.replace(/`/g, '\\\\`');
+  if (typeof document !== 'undefined') {{
+    const styleTag = document.head.querySelector('style[data-zen-styles]') || document.createElement('style');
+    styleTag.textContent = (styleTag.textContent || '') + __styles;
+    if (!styleTag.parentNode) document.head.appendChild(styleTag);
+  }}
+
+"#,
+            styles_code
+        )
+    };
+
+    // 11. Bundle construction
+    let bundle_code = BundleSections {
+        imports: &all_imports,
+        hydration: &hydration,
+        environment_prelude: &plan.environment_prelude.join("\n"),
+        reactive_state: &reactive_state_init,
+        script: &script_no_imports,
+        expressions: &expressions_section,
+        styles: &styles_section,
+        template: &template_section,
+        hydrate: &hydrate_section,
+        init: if input.embed {
+            EMBED_INIT
+        } else {
+            DOCUMENT_INIT
+        },
     }
+    .assemble();
+    let bundle_code = if input.minify {
+        strip_banner_comments(&bundle_code)
+    } else {
+        bundle_code
+    };
 
-    for pb in &input.page_bindings {
-        state_bindings.insert(pb.clone());
+    RuntimeCode {
+        expressions: expressions_code,
+        render: render_fn,
+        hydration,
+        styles: styles_code,
+        script: script_no_imports,
+        state_init: state_init_code,
+        bundle: bundle_code,
+        npm_imports: plan.imports.clone(),
+        errors: all_errors,
+        warnings: all_warnings,
+        stripped_debug_statements: stripped_debug.get(),
+        chunks,
+        es_target_errors,
     }
-    for pp in &input.page_props {
-        prop_bindings.insert(pp.clone());
+}
+
+/// Hydrate once the document has parsed
+const DOCUMENT_INIT: &str = "  if (document.readyState === 'loading') {
+    document.addEventListener('DOMContentLoaded', initHydration);
+  } else {
+    initHydration();
+  }";
+
+/// Embedded previews may be injected after the document is interactive, and
+/// re-injected on reload: hydrate as soon as there is a body, exactly once
+const EMBED_INIT: &str = "  let __zenHydrated = false;
+  const __zenInit = () => {
+    if (__zenHydrated || !document.body) return;
+    __zenHydrated = true;
+    initHydration();
+  };
+  __zenInit();
+  if (!__zenHydrated) document.addEventListener('DOMContentLoaded', __zenInit, { once: true });";
+
+/// Comment lines of the bundle scaffold above, matched whole so user code is never touched
+const BANNER_COMMENTS: &[&str] = &[
+    "// [ZENITH-NATIVE] Rust Compiler Authority Bundle",
+    "// Zenith standard aliases",
+    "// 3. Component instance",
+    "// 4. Environment Prelude (hoisted zenRoute calls)",
+    "// === ZENITH ENVIRONMENT PRELUDE ===",
+    "// 5. Reactive state",
+    "// 6. User script (Flattened for scope visibility)",
+    "// 7. Expressions",
+    "// 8. Styles injection",
+    "// 9. Template IR",
+    "// 10. Hydration",
+    "// Initialize components",
+];
+
+fn strip_banner_comments(bundle: &str) -> String {
+    bundle
+        .lines()
+        .filter(|line| !BANNER_COMMENTS.contains(&line.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// What the bundle scaffold is filled with
+#[derive(Default)]
+struct BundleSections<'s> {
+    imports: &'s str,
+    hydration: &'s str,
+    /// Hoisted `zenRoute()` declarations, one per line
+    environment_prelude: &'s str,
+    reactive_state: &'s str,
+    script: &'s str,
+    expressions: &'s str,
+    styles: &'s str,
+    template: &'s str,
+    hydrate: &'s str,
+    init: &'s str,
+}
+
+impl BundleSections<'_> {
+    fn assemble(&self) -> String {
+        let environment_prelude = format!(
+            "// === ZENITH ENVIRONMENT PRELUDE ===\n{}",
+            self.environment_prelude
+                .replace("zenRoute(", "__ZENITH_RUNTIME__.zenRoute(")
+        );
+        format!(
+            r#"
+{}
+// [ZENITH-NATIVE] Rust Compiler Authority Bundle
+{}
+
+  if (!window.__ZENITH_SCOPES__) window.__ZENITH_SCOPES__ = {{}};
+  
+  // Zenith standard aliases
+  const ref = zenRef;
+  const reactive = zenState;
+  const effect = zenEffect;
+  const memo = zenMemo;
+  const onMount = zenOnMount;
+
+  // 3. Component instance
+  const __instance = {{ mountHooks: [] }};
+  if (window.__zenith && window.__zenith.setActiveInstance) {{
+    window.__zenith.setActiveInstance(__instance);
+  }}
+
+  // 4. Environment Prelude (hoisted zenRoute calls)
+  {}
+
+  // 5. Reactive state
+  {}
+
+  // 6. User script (Flattened for scope visibility)
+  {}
+
+{}{}
+  // 9. Template IR
+  {}
+
+  // 10. Hydration
+  function initHydration() {{
+{}
+    
+    // Initialize components
+    if (window.__ZENITH_SCOPES__) {{
+        Object.values(window.__ZENITH_SCOPES__).forEach(s => {{
+            if (typeof s.__run === 'function') s.__run();
+        }});
+    }}
+
+    if (window.__zenith && window.__zenith.triggerMount) {{
+      window.__zenith.triggerMount(__instance);
+    }}
+  }}
+
+{}
+"#,
+            self.imports,
+            self.hydration,
+            environment_prelude,
+            self.reactive_state,
+            self.script,
+            self.expressions,
+            self.styles,
+            self.template,
+            self.hydrate,
+            self.init
+        )
     }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SCRIPT ANALYSIS
+// ═══════════════════════════════════════════════════════════════════════════════
 
+fn script_source_type() -> SourceType {
+    SourceType::default()
+        .with_typescript(true)
+        .with_jsx(true)
+        .with_module(true)
+}
+
+/// The script with `state` and `prop` declarations rewritten to `let`, so it parses
+fn parsable_script(script: &str) -> String {
+    // Only match 'state' at statement boundaries (start, newline, semicolon, braces)
+    // Avoid matching 'state' in comments or strings
+    let state_re = Regex::new(r"state(\s+)").unwrap();
     let prop_re = Regex::new(r"prop(\s+)").unwrap();
-    let parsable_script = state_re
-        .replace_all(&input.script_content, "let$1")
-        .to_string();
-    let parsable_script = prop_re.replace_all(&parsable_script, "let$1").to_string();
+    let parsable_script = state_re.replace_all(script, "let$1").to_string();
+    prop_re.replace_all(&parsable_script, "let$1").to_string()
+}
 
-    let mut state_decls = Vec::new();
-    let parser = Parser::new(&allocator, &parsable_script, source_type);
-    let ret = parser.parse();
+/// Page-level state and prop bindings
+fn reactive_bindings(input: &CodegenInput) -> (HashSet<String>, HashSet<String>) {
+    (
+        input.page_bindings.iter().cloned().collect(),
+        input.page_props.iter().cloned().collect(),
+    )
+}
 
-    if !ret.errors.is_empty() {
-        // eprintln!("[Zenith CODEGEN] Oxc Parse Errors: {:?}", ret.errors);
-    }
+/// Initial value of every state binding: its declaration in the script, else the
+/// pre-collected `all_states` value, else a regex match, else `undefined`
+fn state_declarations(
+    input: &CodegenInput,
+    parsable_script: &str,
+    program: &Program,
+    state_bindings: &HashSet<String>,
+) -> Vec<StateDeclaration> {
+    let mut state_decls = Vec::new();
 
     // 3. Extract default values from AST (where possible)
     let mut found_bindings = HashSet::new();
-    for stmt in &ret.program.body {
+    for stmt in &program.body {
         if let Statement::VariableDeclaration(var_decl) = stmt {
             for decl in &var_decl.declarations {
                 if let BindingPattern::BindingIdentifier(id) = &decl.id {
@@ -155,8 +946,11 @@ pub fn generate_runtime_code_internal(input: CodegenInput) -> RuntimeCode {
         }
     }
 
-    // 4. Fallback for uninitialized bindings or failed AST extraction
-    for binding in &state_bindings {
+    // 4. Fallback for uninitialized bindings or failed AST extraction, in name order
+    // so the declarations do not depend on hash order
+    let mut remaining: Vec<&String> = state_bindings.iter().collect();
+    remaining.sort();
+    for binding in remaining {
         if !found_bindings.contains(binding) && binding != "state" {
             // Priority 1: Use pre-collected value from all_states
             if let Some(val) = input.all_states.get(binding) {
@@ -192,15 +986,97 @@ pub fn generate_runtime_code_internal(input: CodegenInput) -> RuntimeCode {
             }
         }
     }
+    state_decls
+}
 
-    // 3. Transform script with identifier renaming and HOIST IMPORTS
+/// The expressions to emit: loop sources registered, island content flattened
+/// into `input.nodes`, and only what the template references kept
+fn prepare_expressions(input: &mut CodegenInput) -> Vec<ExpressionInput> {
+    let mut expressions = input.expressions.clone();
+    match &mut input.islands {
+        // Islands mode generates IR per island, so their nodes carry the source ids
+        Some(islands) => {
+            for island in islands.iter_mut() {
+                register_loop_sources(&mut island.nodes, &mut expressions);
+            }
+            input.nodes = islands
+                .iter()
+                .flat_map(|island| island.nodes.iter().cloned())
+                .collect();
+        }
+        None => register_loop_sources(&mut input.nodes, &mut expressions),
+    }
+    // 2.4 Only expressions the template references are emitted: a fully static page
+    // carries no `_expr_*` functions and no registry for the bundler to keep alive
+    let template_refs = collect_expression_refs(&input.nodes);
+    expressions.retain(|e| template_refs.contains(&e.id));
+    expressions
+}
+
+/// 2.5 Imports only `zen:lazy` handlers reach move to a split chunk (see `lazy.rs`)
+fn split_lazy_imports(
+    nodes: &[TemplateNode],
+    expressions: &[ExpressionInput],
+    parsable_script: &str,
+) -> crate::lazy::LazySplit {
+    let lazy_ids = crate::lazy::lazy_handler_ids(nodes);
+    let (lazy_sources, eager_sources): (Vec<&ExpressionInput>, Vec<&ExpressionInput>) =
+        expressions.iter().partition(|e| lazy_ids.contains(&e.id));
+    crate::lazy::plan_split(
+        parsable_script,
+        &lazy_sources
+            .iter()
+            .map(|e| e.code.as_str())
+            .collect::<Vec<_>>(),
+        &eager_sources
+            .iter()
+            .map(|e| e.code.as_str())
+            .collect::<Vec<_>>(),
+    )
+}
 
-    let parser = Parser::new(&allocator, &parsable_script, source_type);
-    let parser_ret = parser.parse();
+/// Offsets of `await` outside any function
+#[derive(Default)]
+struct TopLevelAwaits(Vec<u32>);
 
-    let mut program = parser_ret.program;
+impl<'a> oxc_ast_visit::Visit<'a> for TopLevelAwaits {
+    fn visit_await_expression(&mut self, expr: &AwaitExpression<'a>) {
+        self.0.push(expr.span.start);
+        oxc_ast_visit::walk::walk_await_expression(self, expr);
+    }
 
-    let ast = AstBuilder::new(&allocator);
+    fn visit_for_of_statement(&mut self, stmt: &ForOfStatement<'a>) {
+        if stmt.r#await {
+            self.0.push(stmt.span.start);
+        }
+        oxc_ast_visit::walk::walk_for_of_statement(self, stmt);
+    }
+
+    fn visit_function(&mut self, _func: &Function<'a>, _flags: oxc_syntax::scope::ScopeFlags) {}
+
+    fn visit_arrow_function_expression(&mut self, _arrow: &ArrowFunctionExpression<'a>) {}
+}
+
+/// The script with its imports and `zenRoute()` declarations taken out
+struct ScriptParts<'a> {
+    program: Program<'a>,
+    import_lines: Vec<String>,
+    imports: Vec<ScriptImport>,
+    imported_identifiers: HashSet<String>,
+    script_locals: HashSet<String>,
+    environment_prelude: Vec<String>,
+}
+
+fn split_script<'a>(
+    allocator: &'a Allocator,
+    mut program: Program<'a>,
+    input: &CodegenInput,
+    state_bindings: &HashSet<String>,
+    prop_bindings: &HashSet<String>,
+    lazy_split: &crate::lazy::LazySplit,
+) -> ScriptParts<'a> {
+    let source_type = script_source_type();
+    let ast = AstBuilder::new(allocator);
 
     // Separate imports from body
     let mut body = ast.vec();
@@ -217,13 +1093,22 @@ pub fn generate_runtime_code_internal(input: CodegenInput) -> RuntimeCode {
     }
 
     for stmt in program.body.into_iter() {
-        if let Statement::ImportDeclaration(import_decl) = stmt {
+        if let Statement::ImportDeclaration(mut import_decl) = stmt {
             let source = import_decl.source.value.to_string();
             if source.ends_with(".zen") {
-                // Zenith architectural decision: Components are compile-time structural declarations.
-                // ESM imports of .zen files in the script are stripped to prevent runtime resolution errors.
-                // Component tags are resolved and inlined during the expansion phase.
-                continue;
+                let is_module_import = import_decl
+                    .specifiers
+                    .as_ref()
+                    .is_some_and(|s| crate::module::is_module_import(s));
+                if !is_module_import {
+                    // Zenith architectural decision: Components are compile-time structural declarations.
+                    // ESM imports of .zen files in the script are stripped to prevent runtime resolution errors.
+                    // Component tags are resolved and inlined during the expansion phase.
+                    continue;
+                }
+                // Named imports target script-only modules (see `module.rs`)
+                let path = crate::module::module_js_path(&source);
+                import_decl.source.value = allocator.alloc_str(&path).into();
             }
 
             // Collect imported identifiers to prevent renaming them as state
@@ -243,10 +1128,20 @@ pub fn generate_runtime_code_internal(input: CodegenInput) -> RuntimeCode {
                 }
             }
 
+            // Split off: the names are bound by the lazy loader instead
+            if import_decl.specifiers.as_ref().is_some_and(|specifiers| {
+                !specifiers.is_empty()
+                    && specifiers
+                        .iter()
+                        .all(|s| lazy_split.locals.contains(s.local().name.as_str()))
+            }) {
+                continue;
+            }
+
             // Capture info BEFORE moving import_decl
             let is_type = import_decl.import_kind.is_type();
             let is_side_effect = import_decl.specifiers.is_none();
-            let source_for_struct = source.clone();
+            let source_for_struct = import_decl.source.value.to_string();
 
             let import_code = Codegen::new()
                 .build(&Program {
@@ -271,8 +1166,7 @@ pub fn generate_runtime_code_internal(input: CodegenInput) -> RuntimeCode {
                 import_lines.push(import_code);
 
                 // Extract specifiers using regex from the generated import_code
-                let spec_re = Regex::new(r"import\s+(.*?)\s+from").unwrap();
-                let specifiers = if let Some(cap) = spec_re.captures(&trimmed_import) {
+                let specifiers = if let Some(cap) = IMPORT_SPECIFIERS_RE.captures(&trimmed_import) {
                     cap.get(1)
                         .map_or("".to_string(), |m| m.as_str().to_string())
                 } else {
@@ -287,19 +1181,14 @@ pub fn generate_runtime_code_internal(input: CodegenInput) -> RuntimeCode {
                 });
             }
         } else {
-            if let Statement::VariableDeclaration(decl) = &stmt {
-                for d in &decl.declarations {
-                    if let BindingPattern::BindingIdentifier(id) = &d.id {
-                        let name = id.name.to_string();
-                        // Only add to locals if NOT a state or prop binding
-                        if !state_bindings.contains(&name) && !prop_bindings.contains(&name) {
-                            script_locals.insert(name);
-                        }
-                    }
-                }
-            } else if let Statement::FunctionDeclaration(decl) = &stmt {
-                if let Some(id) = &decl.id {
-                    script_locals.insert(id.name.to_string());
+            // Destructured names too, so the renamer never discovers one mid-script
+            let is_function = matches!(stmt, Statement::FunctionDeclaration(_));
+            for name in crate::binding_table::top_level_names(&stmt) {
+                // Only add variables to locals if NOT a state or prop binding
+                if is_function
+                    || (!state_bindings.contains(&name) && !prop_bindings.contains(&name))
+                {
+                    script_locals.insert(name);
                 }
             }
             body.push(stmt);
@@ -347,7 +1236,7 @@ pub fn generate_runtime_code_internal(input: CodegenInput) -> RuntimeCode {
                                     directives: ast.vec(),
                                     body: {
                                         let mut b = ast.vec();
-                                        b.push(stmt.clone_in(&allocator));
+                                        b.push(stmt.clone_in(allocator));
                                         b
                                     },
                                     source_text: "",
@@ -375,115 +1264,158 @@ pub fn generate_runtime_code_internal(input: CodegenInput) -> RuntimeCode {
     }
     program.body = script_body_no_env;
 
-    let mut all_errors = Vec::new();
-    let mut local_vars = HashSet::new();
-    local_vars.insert("stores".to_string());
-    local_vars.insert("loaderData".to_string());
-    local_vars.insert("query".to_string());
-    local_vars.insert("params".to_string());
-
-    // 3. (Continued) Final script and imports
-    let mut renamer = ScriptRenamer::with_categories(
-        &allocator,
-        state_bindings.clone(),
-        prop_bindings.clone(),
-        script_locals.clone(),
-        local_vars.clone(),
-    );
-    renamer.allow_prop_fallback = false; // Script context: Strict resolution
-                                         // Imports are real JS locals in this scope
-    for imp in &imported_identifiers {
-        renamer.add_local(imp.clone());
-    }
-    renamer.visit_program(&mut program);
-    all_errors.extend(renamer.errors);
-
-    let script_no_imports = Codegen::new().build(&program).code;
-    let all_imports = import_lines.join("");
-
-    // 4. Prepare binding categories for expression transformation
-    let mut state_vars = state_bindings.clone();
-    for sd in &state_decls {
-        state_vars.insert(sd.name.clone());
+    ScriptParts {
+        program,
+        import_lines,
+        imports: script_imports,
+        imported_identifiers,
+        script_locals,
+        environment_prelude,
     }
+}
 
-    let mut prop_vars = prop_bindings.clone();
-    prop_vars.insert("props".to_string()); // Legacy support for props object
-
-    let loop_vars: HashSet<String> = input.template_bindings.iter().cloned().collect();
-
-    // 5. Generate Template IR
-    let template_ir = if input.nodes.is_empty() {
-        "window.__zenith.fragment([])".to_string()
-    } else if input.nodes.len() == 1 {
-        generate_template_ir(&input.nodes[0], &input.expressions)
-    } else {
-        let child_irs: Vec<String> = input
-            .nodes
-            .iter()
-            .map(|n| generate_template_ir(n, &input.expressions))
-            .collect();
-        format!("window.__zenith.fragment([{}])", child_irs.join(", "))
-    };
-
-    let render_fn = format!(
-        "function renderDynamicPage(state) {{\n  return {};\n}}",
-        template_ir
-    );
+/// The binding table of the script and every expression
+fn binding_table(
+    state_bindings: &HashSet<String>,
+    state_decls: &[StateDeclaration],
+    prop_bindings: &HashSet<String>,
+    script_locals: &HashSet<String>,
+    imported_identifiers: &HashSet<String>,
+) -> BindingTable {
+    let mut bindings = BindingTable::default();
+    for name in state_bindings {
+        bindings.add(BindingKind::State, name);
+    }
+    for sd in state_decls {
+        bindings.add(BindingKind::State, &sd.name);
+    }
+    for name in prop_bindings {
+        bindings.add(BindingKind::Prop, name);
+    }
+    bindings.add(BindingKind::Prop, "props"); // Legacy support for props object
+    for name in script_locals {
+        bindings.add(BindingKind::Local, name);
+    }
+    for name in ["stores", "loaderData", "query", "params"] {
+        bindings.add(BindingKind::ExternalLocal, name);
+    }
+    for name in imported_identifiers {
+        bindings.add(BindingKind::Module, name);
+    }
+    bindings.freeze();
+    bindings
+}
 
-    // 5.5 Detect Event Handler Expression IDs (Phase A8)
-    let mut event_handler_ids = HashSet::new();
-    collect_event_handler_ids(&input.nodes, &mut event_handler_ids);
+/// `expr` as its intent is computed: debug statements stripped, then wrapped as
+/// a handler, a `zen:lazy` handler and a `zen:pending` handler where it is one.
+/// Also returns whether a handler was wrapped in a function and how many debug
+/// statements were stripped.
+fn wrapped_expression(
+    expr: &ExpressionInput,
+    is_event_handler: bool,
+    lazy: bool,
+    pending_state: Option<&String>,
+    strip_debug: &crate::strip_debug::StripDebug,
+) -> (ExpressionInput, bool, u32) {
+    let mut expr = expr.clone();
+    let mut stripped = 0;
+    if let Some((code, count)) = crate::strip_debug::strip_expression_code(&expr.code, strip_debug)
+    {
+        expr.code = code;
+        stripped = count;
+    }
+    // A handler that is not already a function is wrapped in one (see `handlers.rs`)
+    let handler_code = is_event_handler
+        .then(|| crate::handlers::wrap_handler_code(&expr.code))
+        .flatten();
+    let wrapped = handler_code.is_some();
+    if let Some(code) = handler_code {
+        expr.code = code;
+    }
+    if lazy {
+        expr.code = crate::lazy::wrap_handler(&expr.code);
+    }
+    if let Some(state) = pending_state {
+        expr.code = crate::pending::wrap_handler(&expr.code, state);
+    }
+    (expr, wrapped, stripped)
+}
 
-    // 6. Generate Expression Wrappers
-    let expression_deps = std::cell::RefCell::new(HashMap::new());
-    let expressions_code = input
-        .expressions
+/// Sizes from the sources plus the fixed text generated around them
+fn estimate_sizes(
+    input: &CodegenInput,
+    expressions: &[ExpressionInput],
+    state_decls: &[StateDeclaration],
+) -> SizeEstimate {
+    let script = input.script_content.len();
+    let expressions: usize = expressions
         .iter()
-        .map(|expr| {
-            let mut all_locals: HashSet<String> = loop_vars.clone();
-            for imp in &imported_identifiers {
-                all_locals.insert(imp.clone());
-            }
+        .map(|e| {
+            expression_function("", "scope", "", "", "''").len()
+                + REGISTRY_ENTRY_BYTES
+                + 3 * e.id.len()
+                + e.code.len()
+        })
+        .sum();
+    let template_ir = template_ir_estimate(&input.nodes);
+    let styles: usize = input.styles.iter().map(|s| s.raw.len() + 1).sum();
+    let state: usize = state_decls
+        .iter()
+        .map(|d| d.name.len() + d.initial_value.len() + 6)
+        .sum();
+    let bundle = BundleSections::default().assemble().len()
+        + script
+        + expressions
+        + template_ir
+        + styles
+        + state;
+    SizeEstimate {
+        script: script as u32,
+        expressions: expressions as u32,
+        template_ir: template_ir as u32,
+        styles: styles as u32,
+        bundle: bundle as u32,
+    }
+}
 
-            let mut expression_locals = local_vars.clone();
-            for loc in &script_locals {
-                expression_locals.insert(loc.clone());
-            }
+/// Registry entry text around the id (twice) and deps of an expression
+const REGISTRY_ENTRY_BYTES: usize = 64;
 
-            let is_event_handler = event_handler_ids.contains(&expr.id);
-            let (transformed_code, state_deps, uses_loop, expr_errors, mutated_deps) = compute_expression_intent(
-                expr,
-                &state_vars,
-                &prop_vars,
-                &expression_locals,
-                &local_vars,
-                &all_locals,
-                is_event_handler,
-            );
-            all_errors.extend(expr_errors);
-            expression_deps.borrow_mut().insert(expr.id.clone(), state_deps);
-
-            // Phase 6: Wrap expressions with notification for mutated deps
-            let mut final_code = transformed_code.trim_end_matches(';').to_string();
-            if !mutated_deps.is_empty() {
-                let notifications: Vec<String> = mutated_deps.iter()
-                    .map(|d| format!("window.zenithNotify(scope, 'state', '{}');", d))
-                    .collect();
-                final_code = format!("(() => {{ const __v = ({});\n  {};\n  return __v; }})()", final_code, notifications.join("\n  "));
-            } else {
-                final_code = format!("({});", final_code);
+/// Template IR bytes per node kind, around the node's own text (with an explicit
+/// stack, as `generate_template_ir` walks deep trees)
+fn template_ir_estimate(nodes: &[TemplateNode]) -> usize {
+    let mut stack: Vec<&TemplateNode> = nodes.iter().collect();
+    let mut bytes = 0;
+    while let Some(node) = stack.pop() {
+        bytes += match node {
+            TemplateNode::Element(el) => {
+                48 + el.tag.len()
+                    + el.attributes
+                        .iter()
+                        .map(|a| {
+                            12 + a.name.len()
+                                + match &a.value {
+                                    AttributeValue::Static(value) => value.len(),
+                                    AttributeValue::Dynamic(expr) => 24 + expr.id.len(),
+                                }
+                        })
+                        .sum::<usize>()
             }
+            TemplateNode::Text(t) => t.value.len() + 4,
+            TemplateNode::Expression(e) => 40 + e.expression.len(),
+            TemplateNode::Doctype(_) => 0,
+            _ => 64,
+        };
+        stack.extend(child_groups(node).into_iter().flatten());
+    }
+    bytes
+}
 
-            let args = if uses_loop {
-                "scope, item, index, array"
-            } else {
-                "scope"
-            };
-
-            let fn_name = format!("_expr_{}", expr.id);
-            format!(
-                "function {}({}) {{
+/// `_expr_<id>`: evaluates the expression and unwraps signals, falling back to
+/// `fallback` when it throws
+fn expression_function(fn_name: &str, args: &str, code: &str, id: &str, fallback: &str) -> String {
+    format!(
+        "function {}({}) {{
   try {{
     const v = {};
     return (v && typeof v === 'function' && v._isSignal) ? v() : (v === undefined ? '' : (Number.isNaN(v) ? 0 : v));
@@ -494,186 +1426,11 @@ pub fn generate_runtime_code_internal(input: CodegenInput) -> RuntimeCode {
     if (e instanceof ReferenceError) {{
        console.warn('[Z-ERR-UNRESOLVED-IDENT] Identifier in expression might be missing from scope:', e.message);
     }}
-    return '';
-  }}
-}}",
-                fn_name,
-                args,
-                final_code,
-                expr.id
-            )
-        })
-        .collect::<Vec<_>>()
-        .join("\n\n");
-
-    let expression_registry = if input.expressions.is_empty() {
-        "// No expressions to register".to_string()
-    } else {
-        let deps_map = expression_deps.into_inner();
-        let entries: Vec<String> = input
-            .expressions
-            .iter()
-            .map(|e| {
-                let deps = deps_map.get(&e.id).cloned().unwrap_or_default();
-                let deps_js = format!(
-                    "[{}]",
-                    deps.iter()
-                        .map(|d| format!("'{}'", d))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                );
-                format!(
-                    "  window.__ZENITH_EXPRESSIONS__.set('{}', {{ fn: _expr_{}, deps: {} }});",
-                    e.id, e.id, deps_js
-                )
-            })
-            .collect();
-        format!(
-            "if (typeof window !== 'undefined') {{\n  if (!window.__ZENITH_EXPRESSIONS__) window.__ZENITH_EXPRESSIONS__ = new Map();\n{}\n}}",
-            entries.join("\n")
-        )
-    };
-
-    // 8. Styles
-    let styles_code = input
-        .styles
-        .iter()
-        .map(|s| s.raw.clone())
-        .collect::<Vec<_>>()
-        .join("\n");
-
-    // 9. State Init
-    let state_init_code = state_decls
-        .iter()
-        .map(|d| format!("state.{} = {};", d.name, d.initial_value))
-        .collect::<Vec<_>>()
-        .join("\n");
-
-    let state_props: Vec<String> = state_decls
-        .iter()
-        .map(|d| format!("  {}: {}", d.name, d.initial_value))
-        .collect();
-
-    let reactive_state_init = format!(
-        "const state = zenState({{\n{}\n}});\n  const __defaultState = state;\n  const props = {{}};\n  const locals = {{}};\n  const scope = {{ state, props, locals }};",
-        state_props.join(",\n")
-    );
-
-    // 10. Hydration Runtime (External Import)
-    // We no longer embed the runtime string. We generate an ESM import with named aliases.
-    let hydration = r#"import {
-  signal as zenSignal,
-  state as zenState,
-  effect as zenEffect,
-  memo as zenMemo,
-  ref as zenRef,
-  onMount as zenOnMount,
-  onUnmount as zenOnUnmount,
-  batch as zenBatch,
-  untrack as zenUntrack
-} from "@zenithbuild/runtime";"#;
-
-    // 11. Bundle construction
-    let bundle_code = format!(
-        r#"
-{}
-// [ZENITH-NATIVE] Rust Compiler Authority Bundle
-{}
-
-  if (!window.__ZENITH_SCOPES__) window.__ZENITH_SCOPES__ = {{}};
-  
-  // Zenith standard aliases
-  const ref = zenRef;
-  const reactive = zenState;
-  const effect = zenEffect;
-  const memo = zenMemo;
-  const onMount = zenOnMount;
-
-  // 3. Component instance
-  const __instance = {{ mountHooks: [] }};
-  if (window.__zenith && window.__zenith.setActiveInstance) {{
-    window.__zenith.setActiveInstance(__instance);
-  }}
-
-  // 4. Environment Prelude (hoisted zenRoute calls)
-  {}
-
-  // 5. Reactive state
-  {}
-
-  // 6. User script (Flattened for scope visibility)
-  {}
-
-  // 7. Expressions
-  {}
-  {}
-
-  // 8. Styles injection
-  const __styles = `{}`.replace(/`/g, '\\\\`');
-  if (__styles && typeof document !== 'undefined') {{
-    const styleTag = document.head.querySelector('style[data-zen-styles]') || document.createElement('style');
-    styleTag.textContent = (styleTag.textContent || '') + __styles;
-    if (!styleTag.parentNode) document.head.appendChild(styleTag);
-  }}
-
-  // 9. Template IR
-  const canonicalIR = (scope) => {{
     return {};
-  }};
-  window.canonicalIR = canonicalIR;
-
-  // 10. Hydration
-  function initHydration() {{
-    if (typeof window.zenithHydrate === 'function') {{
-      window.zenithHydrate(state, document, locals);
-    }}
-    
-    // Initialize components
-    if (window.__ZENITH_SCOPES__) {{
-        Object.values(window.__ZENITH_SCOPES__).forEach(s => {{
-            if (typeof s.__run === 'function') s.__run();
-        }});
-    }}
-
-    if (window.__zenith && window.__zenith.triggerMount) {{
-      window.__zenith.triggerMount(__instance);
-    }}
-  }}
-
-  if (document.readyState === 'loading') {{
-    document.addEventListener('DOMContentLoaded', initHydration);
-  }} else {{
-    initHydration();
   }}
-}}
-"#,
-        all_imports,
-        hydration,
-        format!(
-            "// === ZENITH ENVIRONMENT PRELUDE ===\n{}",
-            environment_prelude
-                .join("\n")
-                .replace("zenRoute(", "__ZENITH_RUNTIME__.zenRoute(")
-        ),
-        reactive_state_init,
-        script_no_imports,
-        expressions_code,
-        expression_registry,
-        styles_code,
-        template_ir
-    );
-
-    RuntimeCode {
-        expressions: expressions_code,
-        render: render_fn,
-        hydration: hydration.to_string(),
-        styles: styles_code,
-        script: script_no_imports,
-        state_init: state_init_code,
-        bundle: bundle_code,
-        npm_imports: script_imports,
-        errors: all_errors,
-    }
+}}",
+        fn_name, args, code, id, fallback
+    )
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -692,16 +1449,108 @@ fn get_node_args(node_loop_context: &Option<crate::validate::LoopContext>) -> St
     }
 }
 
-fn generate_template_ir(node: &TemplateNode, expressions: &[ExpressionInput]) -> String {
+fn nodes_template_ir(nodes: &[TemplateNode]) -> String {
+    if nodes.is_empty() {
+        "window.__zenith.fragment([])".to_string()
+    } else if nodes.len() == 1 {
+        generate_template_ir(&nodes[0])
+    } else {
+        let child_irs: Vec<String> = nodes.iter().map(generate_template_ir).collect();
+        format!("window.__zenith.fragment([{}])", child_irs.join(", "))
+    }
+}
+
+/// Mounts one island into its `data-zen-island` wrapper (the wrapper's content is replaced)
+pub(crate) fn island_init_block(island: &crate::islands::IslandIR) -> String {
+    format!(
+        "    // Island {id}\n    {{\n      const __island = document.querySelector('[{attr}=\"{id}\"]');\n      if (__island && typeof window.zenithHydrate === 'function') {{\n        window.canonicalIR = (scope) => {ir};\n        window.zenithHydrate(state, __island, locals);\n      }}\n    }}",
+        id = island.id,
+        attr = crate::islands::ISLAND_ID_ATTR,
+        ir = nodes_template_ir(&island.nodes)
+    )
+}
+
+/// Hydrates one deferred subtree into its `data-zen-instance` wrapper when the
+/// wrapper's trigger fires: scope setup, expression registrations, render, `__run()`
+fn deferred_init_block(
+    instance: &str,
+    strategy: &str,
+    registrations: &[String],
+    ir: &str,
+) -> String {
+    format!(
+        "    // Deferred {id} ({strategy})\n    {{\n      const __root = document.querySelector('[{attr}=\"{id}\"]');\n      __zenDefer('{strategy}', __root, () => {{\n        const __setup = window.__ZENITH_DEFERRED__ && window.__ZENITH_DEFERRED__[\"{id}\"];\n        const __scopes = __setup ? __setup() : [];\n        if (!window.__ZENITH_EXPRESSIONS__) window.__ZENITH_EXPRESSIONS__ = new Map();\n{registrations}        if (__root && typeof window.zenithHydrate === 'function') {{\n          window.canonicalIR = (scope) => {ir};\n          window.zenithHydrate(state, __root, locals);\n        }}\n        __scopes.forEach((id) => {{\n          const s = window.__ZENITH_SCOPES__[id];\n          if (s && typeof s.__run === 'function') s.__run();\n        }});\n      }});\n    }}",
+        id = instance,
+        attr = crate::client_directives::HYDRATE_INSTANCE_ATTR,
+        registrations = registrations
+            .iter()
+            .map(|r| format!("        {}\n", r))
+            .collect::<String>(),
+    )
+}
+
+/// Generates the IR for `node` with an explicit stack instead of recursion, so deep
+/// trees are bounded by the heap rather than the thread's stack: every node is
+/// assembled from its children's IR once they are generated
+fn generate_template_ir(node: &TemplateNode) -> String {
+    enum Step<'a> {
+        Enter(&'a TemplateNode),
+        Exit(&'a TemplateNode),
+    }
+
+    let mut stack = vec![Step::Enter(node)];
+    let mut generated: Vec<String> = Vec::new();
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Enter(node) => {
+                stack.push(Step::Exit(node));
+                for child in child_groups(node).into_iter().flatten().rev() {
+                    stack.push(Step::Enter(child));
+                }
+            }
+            Step::Exit(node) => {
+                let groups = child_groups(node);
+                let start = generated.len() - groups.iter().map(|g| g.len()).sum::<usize>();
+                let mut children = generated.drain(start..);
+                let groups: Vec<Vec<String>> = groups
+                    .iter()
+                    .map(|g| children.by_ref().take(g.len()).collect())
+                    .collect();
+                drop(children);
+                generated.push(node_ir(node, groups));
+            }
+        }
+    }
+    generated.pop().unwrap_or_default()
+}
+
+/// The child lists `node_ir` takes the IR of, in order
+fn child_groups(node: &TemplateNode) -> Vec<&[TemplateNode]> {
+    match node {
+        // A deferred wrapper renders without its content
+        TemplateNode::Element(el) if crate::client_directives::deferred_wrapper(el).is_some() => {
+            vec![]
+        }
+        TemplateNode::Element(el) => vec![&el.children],
+        TemplateNode::LoopFragment(lp) => vec![&lp.body],
+        TemplateNode::ConditionalFragment(cond) => vec![&cond.consequent, &cond.alternate],
+        TemplateNode::MultiBranchFragment(multi) => {
+            multi.children().map(|nodes| nodes.as_slice()).collect()
+        }
+        TemplateNode::OptionalFragment(opt) => vec![&opt.fragment],
+        TemplateNode::Component(c) => vec![&c.children],
+        TemplateNode::Text(_) | TemplateNode::Expression(_) | TemplateNode::Doctype(_) => vec![],
+    }
+}
+
+/// The IR for one node, given the IR of each of its `child_groups`
+fn node_ir(node: &TemplateNode, children: Vec<Vec<String>>) -> String {
+    let mut children = children.into_iter();
     match node {
-        TemplateNode::Element(el) => generate_element_ir(el, expressions),
+        TemplateNode::Element(el) => generate_element_ir(el, children.next().unwrap_or_default()),
         TemplateNode::Text(t) => format!("\"{}\"", escape_js_string(&t.value)),
         TemplateNode::Expression(e) => {
-            let expr_id = expressions
-                .iter()
-                .find(|ex| ex.code.trim() == e.expression.trim() || ex.id == e.expression)
-                .map(|ex| ex.id.clone())
-                .unwrap_or_else(|| format!("inline_{}", e.expression.len()));
+            let expr_id = &e.expression;
             let args = get_node_args(&e.loop_context);
 
             // HEAD EXPRESSIONS: If in <head>, execute the expression immediately during render
@@ -717,29 +1566,19 @@ fn generate_template_ir(node: &TemplateNode, expressions: &[ExpressionInput]) ->
         }
 
         TemplateNode::LoopFragment(loop_node) => {
-            let body_ir: Vec<String> = loop_node
-                .body
-                .iter()
-                .map(|n| generate_template_ir(n, expressions))
-                .collect();
-            let source_id = expressions
-                .iter()
-                .find(|ex| ex.code.trim() == loop_node.source.trim() || ex.id == loop_node.source)
-                .map(|ex| ex.id.clone())
-                .unwrap_or_else(|| loop_node.source.clone());
+            let body_ir = children.next().unwrap_or_default();
+            let source_id = &loop_node.source;
 
             // CRITICAL: The source expression should NOT receive loop variables that are
             // introduced BY this loop. Those variables (item_var, index_var) don't exist
             // until INSIDE the .map() callback. We need to filter them out.
+            let own_vars = loop_binding_names(&loop_node.item_var, loop_node.index_var.as_deref());
             let parent_args = if let Some(ref lc) = loop_node.loop_context {
                 // Filter out this loop's own variables from the context
                 let parent_vars: Vec<&String> = lc
                     .variables
                     .iter()
-                    .filter(|v| {
-                        *v != &loop_node.item_var
-                            && loop_node.index_var.as_ref().map_or(true, |idx| *v != idx)
-                    })
+                    .filter(|v| !own_vars.contains(*v))
                     .collect();
                 if parent_vars.is_empty() {
                     "state".to_string()
@@ -776,21 +1615,9 @@ fn generate_template_ir(node: &TemplateNode, expressions: &[ExpressionInput]) ->
         }
 
         TemplateNode::ConditionalFragment(cond) => {
-            let cons: Vec<String> = cond
-                .consequent
-                .iter()
-                .map(|n| generate_template_ir(n, expressions))
-                .collect();
-            let alt: Vec<String> = cond
-                .alternate
-                .iter()
-                .map(|n| generate_template_ir(n, expressions))
-                .collect();
-            let cond_id = expressions
-                .iter()
-                .find(|ex| ex.code.trim() == cond.condition.trim() || ex.id == cond.condition)
-                .map(|ex| ex.id.clone())
-                .unwrap_or_else(|| cond.condition.clone());
+            let cons = children.next().unwrap_or_default();
+            let alt = children.next().unwrap_or_default();
+            let cond_id = &cond.condition;
             let args = get_node_args(&cond.loop_context);
             format!(
                 "(_expr_{}({})) ? {} : {}",
@@ -808,17 +1635,32 @@ fn generate_template_ir(node: &TemplateNode, expressions: &[ExpressionInput]) ->
                 }
             )
         }
+        // Emitted as the chained ternary it was written as
+        TemplateNode::MultiBranchFragment(multi) => {
+            let mut render = || {
+                let ir = children.next().unwrap_or_default();
+                if ir.len() == 1 {
+                    ir[0].clone()
+                } else {
+                    format!("[{}]", ir.join(", "))
+                }
+            };
+            let args = get_node_args(&multi.loop_context);
+            let mut out = String::new();
+            for branch in &multi.branches {
+                out.push_str(&format!(
+                    "(_expr_{}({})) ? {} : ",
+                    branch.condition,
+                    args,
+                    render()
+                ));
+            }
+            out.push_str(&render());
+            out
+        }
         TemplateNode::OptionalFragment(opt) => {
-            let frag: Vec<String> = opt
-                .fragment
-                .iter()
-                .map(|n| generate_template_ir(n, expressions))
-                .collect();
-            let cond_id = expressions
-                .iter()
-                .find(|ex| ex.code.trim() == opt.condition.trim() || ex.id == opt.condition)
-                .map(|ex| ex.id.clone())
-                .unwrap_or_else(|| opt.condition.clone());
+            let frag = children.next().unwrap_or_default();
+            let cond_id = &opt.condition;
             let args = get_node_args(&opt.loop_context);
             format!(
                 "(_expr_{}({})) && {}",
@@ -837,11 +1679,7 @@ fn generate_template_ir(node: &TemplateNode, expressions: &[ExpressionInput]) ->
             if c.children.is_empty() {
                 format!("/* Component {} */\"\"", c.name)
             } else {
-                let child_irs: Vec<String> = c
-                    .children
-                    .iter()
-                    .map(|n| generate_template_ir(n, expressions))
-                    .collect();
+                let child_irs = children.next().unwrap_or_default();
                 format!(
                     "/* Component {} */window.__zenith.fragment([{}])",
                     c.name,
@@ -853,9 +1691,41 @@ fn generate_template_ir(node: &TemplateNode, expressions: &[ExpressionInput]) ->
     }
 }
 
-fn generate_element_ir(el: &ElementNode, expressions: &[ExpressionInput]) -> String {
+fn generate_element_ir(el: &ElementNode, children: Vec<String>) -> String {
+    // A deferred subtree keeps its server-rendered wrapper (content included) until
+    // its trigger fires; an empty one stands in when the server did not render it
+    match crate::client_directives::deferred_wrapper(el) {
+        Some((instance, _)) => format!(
+            "(document.querySelector('[{}=\"{}\"]') || {})",
+            crate::client_directives::HYDRATE_INSTANCE_ATTR,
+            instance,
+            element_ir(el, &[])
+        ),
+        None => match crate::portal::portal_wrapper(el) {
+            // Mounted at the target by the runtime; ids in loops are per item
+            Some((target, id, indices)) => {
+                let id = if indices.is_empty() {
+                    format!("\"{}\"", id)
+                } else {
+                    format!("\"{}-\" + {}", id, indices.join(" + \"-\" + "))
+                };
+                format!(
+                    "{{ portal: \"{}\", id: {}, children: [{}] }}",
+                    escape_js_string(&target),
+                    id,
+                    children.join(", ")
+                )
+            }
+            None => element_ir(el, &children),
+        },
+    }
+}
+
+fn element_ir(el: &ElementNode, children: &[String]) -> String {
     let args = get_node_args(&el.loop_context);
-    let props: Vec<String> = el
+    // `(event, value)` for input/change handlers of form controls (see `event_value.rs`)
+    let event_value = crate::event_value::value_extraction(el);
+    let mut props: Vec<String> = el
         .attributes
         .iter()
         .filter_map(|attr| {
@@ -874,9 +1744,13 @@ fn generate_element_ir(el: &ElementNode, expressions: &[ExpressionInput]) -> Str
                 }
                 "data-zen-change" => {
                     if let AttributeValue::Static(fn_name) = &attr.value {
+                        let call_args = match event_value {
+                            Some(value) => format!("event, {}", value),
+                            None => "event".to_string(),
+                        };
                         (
                             "onchange".to_string(),
-                            format!("function(event, target) {{ {}(event) }}", fn_name),
+                            format!("function(event, target) {{ {}({}) }}", fn_name, call_args),
                         )
                     } else {
                         return Some(format!("\"onchange\": function(event, target) {{}}"));
@@ -884,14 +1758,38 @@ fn generate_element_ir(el: &ElementNode, expressions: &[ExpressionInput]) -> Str
                 }
                 "data-zen-input" => {
                     if let AttributeValue::Static(fn_name) = &attr.value {
+                        let call_args = match event_value {
+                            Some(value) => format!("event, {}", value),
+                            None => "event".to_string(),
+                        };
                         (
                             "oninput".to_string(),
-                            format!("function(event, target) {{ {}(event) }}", fn_name),
+                            format!("function(event, target) {{ {}({}) }}", fn_name, call_args),
                         )
                     } else {
                         return Some(format!("\"oninput\": function(event, target) {{}}"));
                     }
                 }
+                // Marker only; freezing happens through the expression registry
+                crate::transform::STATIC_ATTR => return None,
+                crate::transform::ISLAND_ATTR => return None,
+                crate::transform::PENDING_ATTR => return None,
+                // DOM properties are collected into `props` below
+                name if name.starts_with(crate::transform::PROP_PREFIX) => return None,
+                crate::transform::FORM_ACTION_ATTR => {
+                    if let AttributeValue::Dynamic(expr) = &attr.value {
+                        // Enhanced form: block the native POST and hand the handler the form data
+                        (
+                            "onsubmit".to_string(),
+                            format!(
+                                "function(event, target) {{ event.preventDefault(); const handler = _expr_{}({}); if (typeof handler === 'function') handler.call(target, new FormData(target), event); }}",
+                                expr.id, args
+                            ),
+                        )
+                    } else {
+                        return None;
+                    }
+                }
                 "data-zen-submit" => {
                     if let AttributeValue::Static(fn_name) = &attr.value {
                         (
@@ -915,6 +1813,10 @@ fn generate_element_ir(el: &ElementNode, expressions: &[ExpressionInput]) -> Str
                     }
 
                     let val = match &attr.value {
+                        // Merged into the dynamic value's prop below
+                        AttributeValue::Static(_) if has_dynamic_sibling(el, &attr.name) => {
+                            return None;
+                        }
                         AttributeValue::Static(s) => {
                             // If it's a standard event handler, wrap it correctly
                             if p_name.starts_with("on") && p_name.len() > 2 {
@@ -922,7 +1824,11 @@ fn generate_element_ir(el: &ElementNode, expressions: &[ExpressionInput]) -> Str
                                     .chars()
                                     .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
                                     && !s.is_empty();
-                                if is_simple_id {
+                                let value = event_value
+                                    .filter(|_| crate::event_value::is_value_event(&p_name));
+                                if let (true, Some(value)) = (is_simple_id, value) {
+                                    format!("function(event, target) {{ {}(event, {}) }}", s, value)
+                                } else if is_simple_id {
                                     format!("function(event, target) {{ {}() }}", s)
                                 } else {
                                     format!("function(event, target) {{ {} }}", s)
@@ -932,12 +1838,45 @@ fn generate_element_ir(el: &ElementNode, expressions: &[ExpressionInput]) -> Str
                             }
                         }
                         AttributeValue::Dynamic(expr) => {
-                            if p_name.starts_with("on") {
+                            let value = event_value.filter(|_| {
+                                crate::event_value::is_value_event(&p_name)
+                                    && crate::event_value::is_simple_handler(&expr.code)
+                            });
+                            if let Some(value) = value {
+                                // The handler is called here, with the control's typed value
+                                format!(
+                                    "function(event, target) {{ const handler = _expr_{}({}); if (typeof handler === 'function') handler.call(target, event, {}); }}",
+                                    expr.id, args, value
+                                )
+                            } else if p_name.starts_with("on") {
                                 // Event Handler: Return function directly
                                 format!(
                                     "function(event, target) {{ return _expr_{}({}); }}",
                                     expr.id, args
                                 )
+                            } else if let Some(prefix) =
+                                crate::transform::merged_static_value(&el.attributes, &attr.name)
+                            {
+                                // Duplicate class/style: one prop carrying both parts
+                                format!(
+                                    "{{ fn: () => ([\"{}\", _expr_{}({})].filter(Boolean).join(\"{}\")), id: '{}' }}",
+                                    escape_js_string(&prefix),
+                                    expr.id,
+                                    args,
+                                    crate::transform::merged_attr_separator(&p_name),
+                                    expr.id
+                                )
+                            } else if let Some(coercion) =
+                                crate::attr_coerce::AttrCoercion::for_attribute(&attr.name)
+                            {
+                                // aria-*/data-*: always a string
+                                format!(
+                                    "{{ fn: () => ({}(_expr_{}({}))), id: '{}' }}",
+                                    coercion.js_wrapper(),
+                                    expr.id,
+                                    args,
+                                    expr.id
+                                )
                             } else {
                                 // Reactive Attribute: Return wrapper
                                 format!(
@@ -950,9 +1889,33 @@ fn generate_element_ir(el: &ElementNode, expressions: &[ExpressionInput]) -> Str
                     (p_name, val)
                 }
             };
-            Some(format!("\"{}\": {}", prop_name, prop_val))
+            Some(format!("\"{}\": {}", prop_name, prop_val))
+        })
+        .collect();
+
+    // `prop:` bindings go in a `props` sub-object so the runtime assigns DOM properties
+    let dom_props: Vec<String> = el
+        .attributes
+        .iter()
+        .filter_map(|attr| {
+            let name = attr.name.strip_prefix(crate::transform::PROP_PREFIX)?;
+            let val = match &attr.value {
+                AttributeValue::Static(s) => format!("\"{}\"", escape_js_string(s)),
+                AttributeValue::Dynamic(expr) => format!(
+                    "{{ fn: () => (_expr_{}({})), id: '{}' }}",
+                    expr.id, args, expr.id
+                ),
+            };
+            Some(format!(
+                "\"{}\": {}",
+                crate::transform::dom_property_name(name),
+                val
+            ))
         })
         .collect();
+    if !dom_props.is_empty() {
+        props.push(format!("\"props\": {{ {} }}", dom_props.join(", ")));
+    }
 
     // For structural elements, we still use __zenith.h but they are handled specially by the runtime hydration
     let props_str = if props.is_empty() {
@@ -961,11 +1924,6 @@ fn generate_element_ir(el: &ElementNode, expressions: &[ExpressionInput]) -> Str
         format!("{{ {} }}", props.join(", "))
     };
 
-    let children: Vec<String> = el
-        .children
-        .iter()
-        .map(|c| generate_template_ir(c, expressions))
-        .collect();
     let children_str = format!("[{}]", children.join(", "));
 
     format!(
@@ -974,19 +1932,31 @@ fn generate_element_ir(el: &ElementNode, expressions: &[ExpressionInput]) -> Str
     )
 }
 
+/// A static attribute whose dynamic duplicate carries it (see `merged_static_value`)
+fn has_dynamic_sibling(el: &ElementNode, name: &str) -> bool {
+    el.attributes
+        .iter()
+        .any(|a| a.name == name && matches!(a.value, AttributeValue::Dynamic(_)))
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // EXPRESSION INTENT
 // ═══════════════════════════════════════════════════════════════════════════════
 
 fn compute_expression_intent(
     expr: &ExpressionInput,
-    state_bindings: &HashSet<String>,
-    prop_bindings: &HashSet<String>,
-    local_bindings: &HashSet<String>,
-    external_locals: &HashSet<String>,
+    bindings: &BindingTable,
     loop_vars: &HashSet<String>,
     is_event_handler: bool,
-) -> (String, Vec<String>, bool, Vec<String>, Vec<String>) {
+) -> (
+    String,
+    Vec<String>,
+    bool,
+    Vec<String>,
+    Vec<String>,
+    Vec<String>,
+) {
+    zen_span!(TRACE, "compute_expression_intent", expression = %expr.id);
     let allocator = Allocator::default();
     let source_type = SourceType::default().with_jsx(true).with_typescript(true);
     let code = &expr.code;
@@ -998,8 +1968,21 @@ fn compute_expression_intent(
     let parser = Parser::new(&allocator, code, source_type);
     let ret = parser.parse();
     if !ret.errors.is_empty() {
-        // Fallback to original code if parsing fails (e.g. fragment bits)
-        return (code.clone(), vec![], uses_loop, vec![], vec![]);
+        // Left as written; the error carries oxc's message
+        let errors = ret
+            .errors
+            .iter()
+            .map(|err| {
+                format!(
+                    "{}: {} in expression `{{{}}}` ({})",
+                    crate::script_syntax::SCRIPT_SYNTAX_ERROR,
+                    err.message,
+                    code.trim(),
+                    expr.id
+                )
+            })
+            .collect();
+        return (code.clone(), vec![], uses_loop, errors, vec![], vec![]);
     }
 
     let mut program = ret.program;
@@ -1008,13 +1991,7 @@ fn compute_expression_intent(
     let mut jsx_lowerer = JsxLowerer::new(&allocator);
     jsx_lowerer.visit_program(&mut program);
 
-    let mut renamer = ScriptRenamer::with_categories(
-        &allocator,
-        state_bindings.clone(),
-        prop_bindings.clone(),
-        local_bindings.clone(),
-        external_locals.clone(),
-    );
+    let mut renamer = ScriptRenamer::from_table(&allocator, bindings);
     renamer.allow_prop_fallback = false; // Strict Enforcement: Disallow fallback for root-level identifiers
                                          // Add loop variables from context as true JS locals
     if let Some(lc) = &expr.loop_context {
@@ -1025,11 +2002,10 @@ fn compute_expression_intent(
     for v in loop_vars {
         renamer.add_local(v.clone());
     }
+    // Set before the first pass so handler mutations are not reported as expression ones
+    renamer.is_event_handler = is_event_handler;
     renamer.visit_program(&mut program);
 
-    if is_event_handler {
-        renamer.is_event_handler = true;
-    }
     // Re-visit for the new enforcement logic (VisitMut is idempotent for renaming)
     renamer.visit_program(&mut program);
 
@@ -1038,27 +2014,259 @@ fn compute_expression_intent(
     // Trim trailing whitespace and SEMICOLONS (Expressions in Zenith should not have them internally)
     transformed = transformed.trim().trim_end_matches(';').to_string();
 
-    if transformed.contains("docsOrder") || transformed.contains("render") {
-        panic!(
-            "\n\n[DEBUG PANIC] Found target code!\nCode: {}\nTransformed: {}\n\n",
-            expr.code, transformed
-        );
-    }
-
     // Phase 5 Enhancement 3: Use direct dependency tracking from ScriptRenamer
     // No more string matching - deps are collected during AST traversal
-    let deps: Vec<String> = renamer.state_deps.into_iter().collect();
-    let mutated = renamer.mutated_state_deps.into_iter().collect();
+    // Sorted, so the emitted registry does not depend on hash order
+    let mut deps: Vec<String> = renamer.state_deps.into_iter().collect();
+    deps.sort();
+    let mut mutated: Vec<String> = renamer.mutated_state_deps.into_iter().collect();
+    mutated.sort();
+
+    (
+        transformed,
+        deps,
+        uses_loop,
+        renamer.errors,
+        renamer.warnings,
+        mutated,
+    )
+}
+
+/// Registers every loop source that is not an expression id as a synthetic
+/// expression and points the fragment at it, so codegen resolves every
+/// reference by id. The source is kept verbatim so member calls and spread
+/// literals are parsed (and their deps extracted) as a whole rather than by name.
+fn register_loop_sources(nodes: &mut [TemplateNode], expressions: &mut Vec<ExpressionInput>) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => register_loop_sources(&mut el.children, expressions),
+            TemplateNode::Component(c) => register_loop_sources(&mut c.children, expressions),
+            TemplateNode::ConditionalFragment(cf) => {
+                register_loop_sources(&mut cf.consequent, expressions);
+                register_loop_sources(&mut cf.alternate, expressions);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children_mut() {
+                    register_loop_sources(nodes, expressions);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => {
+                register_loop_sources(&mut of.fragment, expressions)
+            }
+            TemplateNode::LoopFragment(lf) => {
+                if !expressions.iter().any(|ex| ex.id == lf.source) {
+                    // The source is evaluated outside the callback, so this loop's own
+                    // variables (including destructured ones) are not in scope for it.
+                    let own_vars = loop_binding_names(&lf.item_var, lf.index_var.as_deref());
+                    let loop_context = lf.loop_context.as_ref().and_then(|lc| {
+                        let variables: Vec<String> = lc
+                            .variables
+                            .iter()
+                            .filter(|v| !own_vars.contains(*v))
+                            .cloned()
+                            .collect();
+                        if variables.is_empty() {
+                            None
+                        } else {
+                            Some(LoopContextInput {
+                                variables,
+                                map_source: lc.map_source.clone(),
+                            })
+                        }
+                    });
+                    let id = format!("loop_source_{}", expressions.len());
+                    expressions.push(ExpressionInput {
+                        id: id.clone(),
+                        code: lf.source.trim().to_string(),
+                        loop_context,
+                        origin: None,
+                    });
+                    lf.source = id;
+                }
+                register_loop_sources(&mut lf.body, expressions);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Names bound by a loop callback, flattening destructured item patterns
+/// such as `[key, value]` or `{ id, label: name }`.
+pub(crate) fn loop_binding_names(item_var: &str, index_var: Option<&str>) -> Vec<String> {
+    let ident_re = Regex::new(r"[\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*").unwrap();
+    let mut names = Vec::new();
+    let trimmed = item_var.trim();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        // For `{ key: alias }` only the alias is bound
+        for part in trimmed
+            .trim_matches(|c| c == '[' || c == ']' || c == '{' || c == '}')
+            .split(',')
+        {
+            let binding = part.rsplit(':').next().unwrap_or(part);
+            let binding = binding.split('=').next().unwrap_or(binding);
+            if let Some(m) = ident_re.find(binding) {
+                names.push(m.as_str().to_string());
+            }
+        }
+    } else {
+        names.push(trimmed.to_string());
+    }
+    if let Some(idx) = index_var {
+        names.push(idx.trim().to_string());
+    }
+    names
+}
+
+/// Expression ids inside `zen:static` subtrees, excluding event handlers (which stay live)
+pub(crate) fn collect_frozen_expression_ids(nodes: &[TemplateNode]) -> HashSet<String> {
+    fn walk(nodes: &[TemplateNode], frozen: bool, ids: &mut HashSet<String>) {
+        for node in nodes {
+            match node {
+                TemplateNode::Expression(e) if frozen => {
+                    ids.insert(e.expression.clone());
+                }
+                TemplateNode::Element(el) => {
+                    let frozen = frozen
+                        || el
+                            .attributes
+                            .iter()
+                            .any(|a| a.name == crate::transform::STATIC_ATTR);
+                    if frozen {
+                        for attr in &el.attributes {
+                            if let AttributeValue::Dynamic(expr) = &attr.value {
+                                ids.insert(expr.id.clone());
+                            }
+                        }
+                    }
+                    walk(&el.children, frozen, ids);
+                }
+                TemplateNode::Component(c) => walk(&c.children, frozen, ids),
+                TemplateNode::ConditionalFragment(cf) => {
+                    if frozen {
+                        ids.insert(cf.condition.clone());
+                    }
+                    walk(&cf.consequent, frozen, ids);
+                    walk(&cf.alternate, frozen, ids);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    if frozen {
+                        ids.extend(mb.branches.iter().map(|b| b.condition.clone()));
+                    }
+                    for nodes in mb.children() {
+                        walk(nodes, frozen, ids);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => {
+                    if frozen {
+                        ids.insert(of.condition.clone());
+                    }
+                    walk(&of.fragment, frozen, ids);
+                }
+                TemplateNode::LoopFragment(lf) => {
+                    if frozen {
+                        ids.insert(lf.source.clone());
+                    }
+                    walk(&lf.body, frozen, ids);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut ids = HashSet::new();
+    walk(nodes, false, &mut ids);
+    let mut handlers = HashSet::new();
+    collect_event_handler_ids(nodes, &mut handlers);
+    ids.retain(|id| !handlers.contains(id));
+    ids
+}
+
+/// Every expression id the template references: text and head expressions,
+/// dynamic attributes and props, branch conditions and loop sources
+fn collect_expression_refs(nodes: &[TemplateNode]) -> HashSet<String> {
+    fn dynamic_attrs(attributes: &[crate::validate::AttributeIR], refs: &mut HashSet<String>) {
+        for attr in attributes {
+            if let AttributeValue::Dynamic(expr) = &attr.value {
+                refs.insert(expr.id.clone());
+            }
+        }
+    }
+    fn walk(nodes: &[TemplateNode], refs: &mut HashSet<String>) {
+        for node in nodes {
+            match node {
+                TemplateNode::Expression(e) => {
+                    refs.insert(e.expression.clone());
+                }
+                TemplateNode::Element(el) => {
+                    dynamic_attrs(&el.attributes, refs);
+                    walk(&el.children, refs);
+                }
+                TemplateNode::Component(c) => {
+                    dynamic_attrs(&c.attributes, refs);
+                    walk(&c.children, refs);
+                }
+                TemplateNode::ConditionalFragment(cf) => {
+                    refs.insert(cf.condition.clone());
+                    walk(&cf.consequent, refs);
+                    walk(&cf.alternate, refs);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    refs.extend(mb.branches.iter().map(|b| b.condition.clone()));
+                    for nodes in mb.children() {
+                        walk(nodes, refs);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => {
+                    refs.insert(of.condition.clone());
+                    walk(&of.fragment, refs);
+                }
+                TemplateNode::LoopFragment(lf) => {
+                    refs.insert(lf.source.clone());
+                    walk(&lf.body, refs);
+                }
+                TemplateNode::Text(_) | TemplateNode::Doctype(_) => {}
+            }
+        }
+    }
 
-    (transformed, deps, uses_loop, renamer.errors, mutated)
+    let mut refs = HashSet::new();
+    walk(nodes, &mut refs);
+    refs
+}
+
+pub(crate) fn collect_head_expression_ids(nodes: &[TemplateNode], ids: &mut HashSet<String>) {
+    for node in nodes {
+        match node {
+            TemplateNode::Expression(e) if e.is_in_head => {
+                ids.insert(e.expression.clone());
+            }
+            TemplateNode::Element(el) => collect_head_expression_ids(&el.children, ids),
+            TemplateNode::Component(c) => collect_head_expression_ids(&c.children, ids),
+            TemplateNode::ConditionalFragment(cf) => {
+                collect_head_expression_ids(&cf.consequent, ids);
+                collect_head_expression_ids(&cf.alternate, ids);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    collect_head_expression_ids(nodes, ids);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => collect_head_expression_ids(&of.fragment, ids),
+            TemplateNode::LoopFragment(lf) => collect_head_expression_ids(&lf.body, ids),
+            _ => {}
+        }
+    }
 }
 
-fn collect_event_handler_ids(nodes: &[TemplateNode], ids: &mut HashSet<String>) {
+pub(crate) fn collect_event_handler_ids(nodes: &[TemplateNode], ids: &mut HashSet<String>) {
     for node in nodes {
         match node {
             TemplateNode::Element(el) => {
                 for attr in &el.attributes {
-                    if attr.name.starts_with("on") || attr.name.starts_with("data-zen-") {
+                    if attr.name.starts_with("on")
+                        || attr.name.starts_with("data-zen-")
+                        || attr.name == crate::transform::FORM_ACTION_ATTR
+                    {
                         if let AttributeValue::Dynamic(expr) = &attr.value {
                             ids.insert(expr.id.clone());
                         }
@@ -1080,6 +2288,11 @@ fn collect_event_handler_ids(nodes: &[TemplateNode], ids: &mut HashSet<String>)
                 collect_event_handler_ids(&cf.consequent, ids);
                 collect_event_handler_ids(&cf.alternate, ids);
             }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    collect_event_handler_ids(nodes, ids);
+                }
+            }
             TemplateNode::OptionalFragment(of) => {
                 collect_event_handler_ids(&of.fragment, ids);
             }
@@ -1118,18 +2331,15 @@ mod tests {
             id: "test".to_string(),
             code: "count + 1".to_string(),
             loop_context: None,
+            origin: None,
         };
-        let mut state_vars = HashSet::new();
-        state_vars.insert("count".to_string());
-        let comp_prop_bindings = HashSet::new();
-        let comp_local_bindings = HashSet::new();
+        let mut bindings = BindingTable::default();
+        bindings.add(BindingKind::State, "count");
+        bindings.freeze();
 
-        let (code, deps, uses_loop, errors, _mutated) = compute_expression_intent(
+        let (code, deps, uses_loop, errors, _warnings, _mutated) = compute_expression_intent(
             &expr,
-            &state_vars,
-            &comp_prop_bindings,
-            &comp_local_bindings,
-            &HashSet::new(), // Component-level external locals
+            &bindings,
             &HashSet::new(),
             true, // Phase A7: Disallow reactive access in __run()
         );
@@ -1138,4 +2348,743 @@ mod tests {
         assert!(!uses_loop);
         assert!(errors.is_empty());
     }
+
+    fn loop_page(source: &str, item_var: &str) -> CodegenInput {
+        let nodes: Vec<TemplateNode> = serde_json::from_value(serde_json::json!([{
+            "type": "loop-fragment",
+            "source": source,
+            "itemVar": item_var,
+            "indexVar": null,
+            "body": [],
+            "loopContext": null
+        }]))
+        .unwrap();
+        CodegenInput {
+            file_path: "page.zen".to_string(),
+            script_content: String::new(),
+            expressions: vec![],
+            styles: vec![],
+            template_bindings: vec![],
+            location: "page.zen".to_string(),
+            nodes,
+            page_bindings: vec![
+                "stats".to_string(),
+                "selection".to_string(),
+                "items".to_string(),
+                "extra".to_string(),
+            ],
+            page_props: vec![],
+            all_states: HashMap::new(),
+            locals: vec![],
+            islands: None,
+            minify: false,
+            embed: false,
+            strip_debug: Default::default(),
+            cancel: None,
+            target_es: Default::default(),
+        }
+    }
+
+    fn registered_deps(runtime: &RuntimeCode, id: &str) -> String {
+        let marker = format!("set('{}', {{ fn: _expr_{}, deps: ", id, id);
+        // The registry is emitted with the bundle
+        let start = runtime
+            .bundle
+            .find(&marker)
+            .expect("loop source not registered")
+            + marker.len();
+        let end = runtime.bundle[start..].find(']').unwrap() + start + 1;
+        runtime.bundle[start..end].to_string()
+    }
+
+    #[test]
+    fn test_object_entries_loop_source() {
+        let runtime = generate_runtime_code_internal(loop_page("Object.entries(stats)", "[k, v]"));
+        assert_eq!(registered_deps(&runtime, "loop_source_0"), "['stats']");
+        assert!(runtime
+            .expressions
+            .contains("Object.entries(scope.state.stats)"));
+    }
+
+    #[test]
+    fn test_array_from_loop_source() {
+        let runtime = generate_runtime_code_internal(loop_page("Array.from(selection)", "x"));
+        assert_eq!(registered_deps(&runtime, "loop_source_0"), "['selection']");
+        assert!(runtime
+            .expressions
+            .contains("Array.from(scope.state.selection)"));
+    }
+
+    #[test]
+    fn test_spread_literal_loop_source() {
+        let runtime =
+            generate_runtime_code_internal(loop_page("[...items, extra]", "{ id, label: name }"));
+        let deps = registered_deps(&runtime, "loop_source_0");
+        assert!(deps.contains("'items'") && deps.contains("'extra'"));
+        assert!(runtime
+            .render
+            .contains("(_expr_loop_source_0(state)).map(({ id, label: name }) =>"));
+    }
+
+    #[test]
+    fn test_loop_binding_names_destructured() {
+        assert_eq!(loop_binding_names("[k, v]", Some("i")), vec!["k", "v", "i"]);
+        assert_eq!(
+            loop_binding_names("{ id, label: name = 'x' }", None),
+            vec!["id", "name"]
+        );
+        assert_eq!(loop_binding_names("item", None), vec!["item"]);
+    }
+
+    fn static_page() -> CodegenInput {
+        let nodes: Vec<TemplateNode> = serde_json::from_value(serde_json::json!([{
+            "type": "element",
+            "tag": "div",
+            "attributes": [],
+            "children": [
+                {
+                    "type": "element",
+                    "tag": "p",
+                    "attributes": [
+                        { "name": "zen:static", "value": "" },
+                        { "name": "title", "value": { "id": "expr_1", "code": "count" } }
+                    ],
+                    "children": [
+                        { "type": "expression", "expression": "expr_0" },
+                        {
+                            "type": "element",
+                            "tag": "button",
+                            "attributes": [
+                                { "name": "onclick", "value": { "id": "expr_2", "code": "bump" } }
+                            ],
+                            "children": []
+                        }
+                    ]
+                },
+                { "type": "expression", "expression": "expr_3" },
+                {
+                    "type": "loop-fragment",
+                    "source": "expr_5",
+                    "itemVar": "item",
+                    "indexVar": null,
+                    "loopContext": null,
+                    "body": [{
+                        "type": "element",
+                        "tag": "li",
+                        "attributes": [{ "name": "zen:static", "value": "" }],
+                        "children": [{
+                            "type": "expression",
+                            "expression": "expr_4",
+                            "loopContext": { "variables": ["item"], "mapSource": "items" }
+                        }]
+                    }]
+                }
+            ]
+        }]))
+        .unwrap();
+        let expr = |id: &str, code: &str, loop_vars: Option<Vec<&str>>| ExpressionInput {
+            id: id.to_string(),
+            code: code.to_string(),
+            loop_context: loop_vars.map(|vars| LoopContextInput {
+                variables: vars.into_iter().map(String::from).collect(),
+                map_source: Some("items".to_string()),
+            }),
+            origin: None,
+        };
+        CodegenInput {
+            file_path: "page.zen".to_string(),
+            script_content: "function bump() {}".to_string(),
+            expressions: vec![
+                expr("expr_0", "count", None),
+                expr("expr_1", "count", None),
+                expr("expr_2", "bump", None),
+                expr("expr_3", "count", None),
+                expr("expr_4", "item", Some(vec!["item"])),
+                expr("expr_5", "items", None),
+            ],
+            styles: vec![],
+            template_bindings: vec![],
+            location: "page.zen".to_string(),
+            nodes,
+            page_bindings: vec!["count".to_string(), "items".to_string()],
+            page_props: vec![],
+            all_states: HashMap::new(),
+            locals: vec![],
+            islands: None,
+            minify: false,
+            embed: false,
+            strip_debug: Default::default(),
+            cancel: None,
+            target_es: Default::default(),
+        }
+    }
+
+    fn is_once(runtime: &RuntimeCode, id: &str) -> bool {
+        runtime.bundle.contains(&format!(
+            "set('{}', {{ fn: _expr_{}, deps: [], once: true }})",
+            id, id
+        ))
+    }
+
+    #[test]
+    fn test_zen_static_freezes_subtree() {
+        let input = static_page();
+        let runtime = generate_runtime_code_internal(input.clone());
+
+        // Text and attribute expressions inside the static <p>
+        assert!(is_once(&runtime, "expr_0"));
+        assert!(is_once(&runtime, "expr_1"));
+        // Event handlers stay live; expressions outside stay reactive
+        assert!(!is_once(&runtime, "expr_2"));
+        assert!(!is_once(&runtime, "expr_3"));
+        assert_eq!(registered_deps(&runtime, "expr_3"), "['count']");
+        assert!(runtime
+            .render
+            .contains("\"onclick\": function(event, target)"));
+        assert!(!runtime.render.contains("zen:static"));
+
+        assert_eq!(collect_frozen_expression_ids(&input.nodes).len(), 3);
+    }
+
+    #[test]
+    fn test_zen_static_in_loop_freezes_per_item() {
+        let runtime = generate_runtime_code_internal(static_page());
+        assert!(is_once(&runtime, "expr_4"));
+        // The loop itself still tracks its source
+        assert_eq!(registered_deps(&runtime, "expr_5"), "['items']");
+    }
+
+    #[test]
+    fn test_zen_static_folds_initial_values() {
+        let input = static_page();
+        let expressions: Vec<crate::validate::ExpressionIR> = input
+            .expressions
+            .iter()
+            .map(|e| crate::validate::ExpressionIR {
+                id: e.id.clone(),
+                code: e.code.clone(),
+                location: Default::default(),
+                loop_context: None,
+                origin: None,
+                extra: Default::default(),
+            })
+            .collect();
+        let head = crate::transform::HeadOptions {
+            reactive: false,
+            initial_values: HashMap::from([("count".to_string(), "5".to_string())]),
+            initial_objects: HashMap::new(),
+            minify: false,
+            virtual_loops: HashMap::new(),
+            select_loops: HashMap::new(),
+            html: Default::default(),
+        };
+        let output = crate::transform::transform_template_with_options(
+            &input.nodes,
+            &expressions,
+            None,
+            &head,
+        );
+
+        assert!(output.html.contains("<p data-zen-static"));
+        assert!(output.html.contains("5<button"));
+        assert!(!output.html.contains("<!--zen:expr_0-->"));
+        assert!(output.html.contains("<!--zen:expr_3-->"));
+        assert!(!output.bindings.iter().any(|b| b.id == "expr_0"));
+    }
+
+    /// Compiled page plus the client render function for its template
+    /// Codegen input straight from a page's template and script
+    fn page_input(source: &str) -> CodegenInput {
+        let template = crate::parse::parse_template(source, "page.zen").unwrap();
+        let script = crate::parse::parse_script(source);
+        CodegenInput {
+            file_path: "page.zen".to_string(),
+            script_content: script.as_ref().map(|s| s.raw.clone()).unwrap_or_default(),
+            expressions: template
+                .expressions
+                .iter()
+                .map(|e| ExpressionInput {
+                    id: e.id.clone(),
+                    code: e.code.clone(),
+                    loop_context: None,
+                    origin: None,
+                })
+                .collect(),
+            styles: vec![],
+            template_bindings: vec![],
+            location: "page.zen".to_string(),
+            nodes: template.nodes,
+            page_bindings: script
+                .map(|s| s.states.into_keys().collect())
+                .unwrap_or_default(),
+            page_props: vec![],
+            all_states: HashMap::new(),
+            locals: vec![],
+            islands: None,
+            minify: false,
+            embed: false,
+            strip_debug: Default::default(),
+            cancel: None,
+            target_es: Default::default(),
+        }
+    }
+
+    fn property_page(source: &str) -> (crate::parse::CompileResult, RuntimeCode) {
+        let result =
+            crate::parse::compile_zen_internal(source, "page.zen", Default::default()).unwrap();
+        let runtime = generate_runtime_code_internal(page_input(source));
+        (result, runtime)
+    }
+
+    fn property_binding(result: &crate::parse::CompileResult) -> &crate::transform::Binding {
+        result
+            .bindings
+            .iter()
+            .find(|b| b.r#type == "property")
+            .expect("no property binding")
+    }
+
+    #[test]
+    fn test_prop_binding_with_object_value() {
+        let (result, runtime) = property_page(
+            "<script>\nstate chartData = { points: [1, 2, 3] }\n</script>\n<my-chart prop:data={chartData}></my-chart>",
+        );
+        let binding = property_binding(&result);
+        assert_eq!(binding.target, "data");
+
+        // Complex values SSR nothing but the hydration marker
+        assert!(result
+            .html
+            .contains(&format!("<my-chart data-zen-prop-data=\"{}\">", binding.id)));
+        assert!(!result.html.contains(" data=\""));
+
+        // The id as the runtime registers it
+        let marker = "\"props\": { \"data\": { fn: () => (_expr_";
+        let start = runtime.render.find(marker).expect("no props entry") + marker.len();
+        let id: String = runtime.render[start..]
+            .chars()
+            .take_while(|c| *c != '(')
+            .collect();
+        assert_eq!(registered_deps(&runtime, &id), "['chartData']");
+    }
+
+    #[test]
+    fn test_value_and_checked_props_reflect_when_static() {
+        let (result, runtime) = property_page(
+            "<script>\nstate draft = \"hello\"\nstate done = true\n</script>\n<input prop:value={draft} /><input type=\"checkbox\" prop:checked={done} /><input prop:value={draft.trim()} />",
+        );
+        assert!(result
+            .html
+            .contains("<input value=\"hello\" data-zen-prop-value="));
+        assert!(result
+            .html
+            .contains("<input type=\"checkbox\" checked data-zen-prop-checked="));
+        // Not statically known: property only
+        assert_eq!(result.html.matches("value=\"hello\"").count(), 1);
+
+        let targets: Vec<&str> = result
+            .bindings
+            .iter()
+            .filter(|b| b.r#type == "property")
+            .map(|b| b.target.as_str())
+            .collect();
+        assert_eq!(targets, vec!["value", "checked", "value"]);
+        assert!(runtime.render.contains("\"props\": { \"value\": { fn:"));
+        assert!(!runtime.render.contains("\"prop:value\""));
+    }
+
+    fn attribute_binding<'r>(
+        result: &'r crate::parse::CompileResult,
+        target: &str,
+    ) -> Option<&'r crate::transform::Binding> {
+        result
+            .bindings
+            .iter()
+            .find(|b| b.r#type == "attribute" && b.target == target)
+    }
+
+    #[test]
+    fn test_static_and_dynamic_style_merge() {
+        let (result, runtime) = property_page(
+            "<script>\nstate tint = \"color: blue\"\n</script>\n<div style=\"margin: 0;\" style={tint}></div>",
+        );
+        let binding = attribute_binding(&result, "style").expect("no style binding");
+        assert!(binding.merge_with_static);
+        assert_eq!(binding.static_prefix.as_deref(), Some("margin: 0"));
+        // The static part is rendered; the runtime appends the dynamic part to it
+        assert!(result.html.contains("style=\"margin: 0\""));
+        assert!(result
+            .html
+            .contains(&format!("data-zen-attr-style={}", binding.id)));
+
+        assert_eq!(runtime.render.matches("\"style\":").count(), 1);
+        assert!(runtime.render.contains("fn: () => ([\"margin: 0\", _expr_"));
+        assert!(runtime.render.contains(".filter(Boolean).join(\"; \"))"));
+    }
+
+    #[test]
+    fn test_static_and_dynamic_class_merge() {
+        // A constant expression folds into the static class
+        let (result, runtime) =
+            property_page("<button class=\"btn\" class={\"btn-\" + \"primary\"}>Go</button>");
+        assert!(result.html.contains("<button class=\"btn btn-primary\">"));
+        assert!(attribute_binding(&result, "class").is_none());
+        assert!(runtime.render.contains("\"class\": \"btn btn-primary\""));
+
+        let (result, runtime) = property_page(
+            "<script>\nstate variant = \"primary\"\n</script>\n<button class=\"btn\" class={variant}>Go</button>",
+        );
+        let binding = attribute_binding(&result, "class").expect("no class binding");
+        assert!(binding.merge_with_static);
+        assert_eq!(binding.static_prefix.as_deref(), Some("btn"));
+        assert_eq!(runtime.render.matches("\"class\":").count(), 1);
+        assert!(runtime.render.contains(".filter(Boolean).join(\" \"))"));
+    }
+
+    #[test]
+    fn test_dashed_prop_names_are_camel_cased() {
+        assert_eq!(
+            crate::transform::dom_property_name("chart-data"),
+            "chartData"
+        );
+        assert_eq!(
+            crate::transform::dom_property_name("contenteditable"),
+            "contentEditable"
+        );
+        assert_eq!(crate::transform::dom_property_name("data"), "data");
+
+        let (result, runtime) = property_page(
+            "<script>\nstate draft = \"hi\"\n</script>\n<div contenteditable=\"true\" prop:text-content={draft}></div>",
+        );
+        assert_eq!(property_binding(&result).target, "textContent");
+        assert!(result.html.contains("data-zen-prop-text-content="));
+        // textContent has no attribute equivalent
+        assert!(!result.html.contains(">hi</div>"));
+        assert!(!result.html.contains(" text-content="));
+        assert!(runtime
+            .render
+            .contains("\"props\": { \"textContent\": { fn:"));
+    }
+
+    #[test]
+    fn test_static_page_bundle_has_no_dead_sections() {
+        let (_, runtime) = property_page("<main><h1>Hello</h1><p>Static</p></main>");
+        assert!(!runtime.bundle.contains("__ZENITH_EXPRESSIONS__"));
+        assert!(!runtime.bundle.contains("// 7. Expressions"));
+        assert!(!runtime.bundle.contains("__styles"));
+        assert!(!runtime.bundle.contains("function _expr_"));
+        assert!(runtime
+            .bundle
+            .contains("const state = /* @__PURE__ */ zenState({"));
+    }
+
+    #[test]
+    fn test_unreferenced_expressions_are_not_emitted() {
+        let mut input = static_page();
+        input.expressions.push(ExpressionInput {
+            id: "expr_9".to_string(),
+            code: "orphan".to_string(),
+            loop_context: None,
+            origin: None,
+        });
+        let runtime = generate_runtime_code_internal(input);
+        assert!(!runtime.expressions.contains("function _expr_expr_9("));
+        assert!(!runtime.bundle.contains("set('expr_9'"));
+        // Everything the template references is still emitted and registered
+        for id in ["expr_0", "expr_1", "expr_2", "expr_3", "expr_4", "expr_5"] {
+            assert!(runtime
+                .expressions
+                .contains(&format!("function _expr_{}(", id)));
+            assert!(runtime.bundle.contains(&format!("set('{}'", id)));
+        }
+    }
+
+    #[test]
+    fn test_styles_are_injected_only_when_present() {
+        let mut input = static_page();
+        input.styles.push(crate::validate::StyleIR {
+            raw: "p { color: red; }".to_string(),
+            global: false,
+            source_component: "page.zen".to_string(),
+        });
+        let runtime = generate_runtime_code_internal(input);
+        assert!(runtime
+            .bundle
+            .contains("const __styles = /* @__PURE__ */ `p { color: red; }`"));
+        assert!(runtime
+            .bundle
+            .contains("if (typeof document !== 'undefined')"));
+    }
+
+    #[test]
+    fn test_identical_code_in_two_loops_binds_by_id() {
+        let nodes: Vec<TemplateNode> = serde_json::from_value(serde_json::json!([
+            { "type": "expression", "expression": "expr_0", "loopContext": null },
+            {
+                "type": "loop-fragment",
+                "source": "expr_1",
+                "itemVar": "label",
+                "indexVar": null,
+                "loopContext": null,
+                "body": [{
+                    "type": "expression",
+                    "expression": "expr_2",
+                    "loopContext": { "variables": ["label"], "mapSource": "tags" }
+                }]
+            },
+            {
+                "type": "loop-fragment",
+                "source": "expr_3",
+                "itemVar": "row",
+                "indexVar": "label",
+                "loopContext": null,
+                "body": [{
+                    "type": "expression",
+                    "expression": "expr_4",
+                    "loopContext": { "variables": ["row", "label"], "mapSource": "rows" }
+                }]
+            }
+        ]))
+        .unwrap();
+        let expr = |id: &str, code: &str, vars: Option<Vec<&str>>| ExpressionInput {
+            id: id.to_string(),
+            code: code.to_string(),
+            loop_context: vars.map(|vars| LoopContextInput {
+                variables: vars.into_iter().map(String::from).collect(),
+                map_source: None,
+            }),
+            origin: None,
+        };
+        let mut input = loop_page("tags", "label");
+        input.nodes = nodes;
+        input.page_bindings = vec!["label".into(), "tags".into(), "rows".into()];
+        input.expressions = vec![
+            expr("expr_0", "label", None),
+            expr("expr_1", "tags", None),
+            expr("expr_2", "label", Some(vec!["label"])),
+            expr("expr_3", "rows", None),
+            expr("expr_4", "label", Some(vec!["row", "label"])),
+        ];
+        let runtime = generate_runtime_code_internal(input);
+
+        assert!(runtime.render.contains("_expr_expr_0(scope)"));
+        assert!(runtime.render.contains("_expr_expr_2(scope, label)"));
+        assert!(runtime.render.contains("_expr_expr_4(scope, row, label)"));
+        assert!(runtime
+            .expressions
+            .contains("function _expr_expr_2(scope, label)"));
+        assert!(runtime
+            .expressions
+            .contains("function _expr_expr_4(scope, row, label)"));
+        assert_eq!(registered_deps(&runtime, "expr_0"), "['label']");
+        assert_eq!(registered_deps(&runtime, "expr_2"), "[]");
+    }
+
+    #[test]
+    fn test_adjacent_duplicate_expressions_both_render() {
+        let (result, runtime) = property_page("<script>\nstate n = 1\n</script>\n<p>{n}{n}</p>");
+        assert_eq!(result.bindings.len(), 2);
+        let ids: Vec<&str> = Regex::new(r"id: '(expr_\d+)'")
+            .unwrap()
+            .captures_iter(&runtime.render)
+            .map(|c| c.get(1).unwrap().as_str())
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert_ne!(ids[0], ids[1]);
+        for id in ids {
+            assert!(runtime.render.contains(&format!("_expr_{}(scope)", id)));
+            assert_eq!(registered_deps(&runtime, id), "['n']");
+        }
+    }
+
+    #[test]
+    fn test_deep_tree_without_recursion() {
+        // The test harness's default stack; a frame per level would exhaust it
+        std::thread::Builder::new()
+            .stack_size(2 * 1024 * 1024)
+            .spawn(|| {
+                let depth = 10_000;
+                let mut node = TemplateNode::Text(crate::validate::TextNode {
+                    value: "x".to_string(),
+                    location: Default::default(),
+                    loop_context: None,
+                    ordinal: 0,
+                    extra: Default::default(),
+                });
+                for _ in 0..depth {
+                    node = TemplateNode::Element(ElementNode {
+                        tag: "section".to_string(),
+                        attributes: vec![],
+                        children: vec![node],
+                        location: Default::default(),
+                        loop_context: None,
+                        ordinal: 0,
+                        extra: Default::default(),
+                    });
+                }
+                let nodes = vec![node];
+
+                let output = crate::transform::transform_template_with_scope(&nodes, &[], None);
+                assert_eq!(
+                    output.html,
+                    format!(
+                        "{}x{}",
+                        "<section>".repeat(depth),
+                        "</section>".repeat(depth)
+                    )
+                );
+                let ir = nodes_template_ir(&nodes);
+                assert!(ir.starts_with("window.__zenith.h(\"section\", null, [window.__zenith.h("));
+                assert_eq!(ir.matches("window.__zenith.h(\"section\"").count(), depth);
+                assert!(ir.ends_with(&format!("[\"x\"]{}", ")]".repeat(depth - 1) + ")")));
+
+                // Dropping the tree recurses too; take it apart level by level
+                let mut node = nodes.into_iter().next().unwrap();
+                while let TemplateNode::Element(mut el) = node {
+                    match el.children.pop() {
+                        Some(child) => node = child,
+                        None => break,
+                    }
+                }
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    const PLANNED_PAGE: &str = "<script>\nimport { format } from \"date-fns\";\nimport { format } from \"date-fns\";\nimport Card from \"./Card.zen\";\nconst route = zenRoute();\nstate count = 1\nstate label = \"Count\"\nconst config = await loadConfig();\nasync function refresh() { await loadConfig(); }\n</script>\n<main><h1>{label}</h1><p>{format(count)}</p><button onclick={() => count += 1}>+</button></main>";
+
+    #[test]
+    fn test_plan_facts() {
+        let plan = plan_runtime_code(&page_input(PLANNED_PAGE));
+
+        let states: Vec<(&str, &str)> = plan
+            .state_declarations
+            .iter()
+            .map(|d| (d.name.as_str(), d.initial_value.as_str()))
+            .collect();
+        assert_eq!(states, [("count", "1"), ("label", "\"Count\"")]);
+
+        // The component import is dropped and the repeated one merged
+        assert_eq!(plan.imports.len(), 1);
+        assert_eq!(plan.imports[0].source, "date-fns");
+        assert_eq!(plan.imports[0].specifiers, "{ format }");
+        assert_eq!(plan.environment_prelude, ["const route = zenRoute();"]);
+        // Not the one inside `refresh`
+        assert_eq!(plan.top_level_awaits, [7]);
+        assert_eq!(plan.islands, None);
+
+        assert_eq!(plan.expressions.len(), 3);
+        let label = &plan.expressions[0];
+        assert_eq!(label.deps, ["label"]);
+        assert!(!label.event_handler && label.mutations.is_empty());
+        let handler = &plan.expressions[2];
+        assert!(handler.event_handler);
+        assert_eq!(handler.mutations, ["count"]);
+        assert_eq!(plan.event_handler_ids, [handler.id.as_str()]);
+
+        let runtime = emit_runtime_code(&plan);
+        let sizes = plan.estimated_sizes;
+        assert_eq!(sizes.script as usize, plan.input.script_content.len());
+        assert!(
+            sizes.bundle as usize > runtime.bundle.len() / 2
+                && (sizes.bundle as usize) < runtime.bundle.len() * 2,
+            "estimated {} for {} bytes",
+            sizes.bundle,
+            runtime.bundle.len()
+        );
+    }
+
+    #[test]
+    fn test_plan_lists_do_not_depend_on_hash_order() {
+        let mut input = page_input("<main><p>{zeta + alpha + mid}</p></main>");
+        input.page_bindings = vec!["zeta".into(), "alpha".into(), "mid".into()];
+        let plan = plan_runtime_code(&input);
+
+        let states: Vec<&str> = plan
+            .state_declarations
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect();
+        assert_eq!(states, ["alpha", "mid", "zeta"]);
+        assert_eq!(plan.expressions[0].deps, ["alpha", "mid", "zeta"]);
+    }
+
+    fn skip_static(plan: &CodegenPlan) -> PlanDecision {
+        if plan.expressions.is_empty() {
+            PlanDecision::Skip("no interactivity".to_string())
+        } else {
+            PlanDecision::Proceed
+        }
+    }
+
+    #[test]
+    fn test_plan_filter_skips_emission() {
+        let options = || crate::parse::CompileOptions {
+            plan_filter: Some(skip_static),
+            ..Default::default()
+        };
+        let unfiltered = crate::parse::compile_zen_internal(
+            "<main><h1>Hello</h1></main>",
+            "page.zen",
+            Default::default(),
+        )
+        .unwrap();
+        assert!(!unfiltered.manifest.as_ref().unwrap().bundle.is_empty());
+
+        let result = crate::parse::compile_zen_internal(
+            "<main><h1>Hello</h1></main>",
+            "page.zen",
+            options(),
+        )
+        .unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert_eq!(result.html, "<main><h1>Hello</h1></main>");
+        assert!(result.manifest.as_ref().unwrap().bundle.is_empty());
+        assert!(
+            result.warnings.iter().any(|w| w.starts_with(
+                "Z-INFO-CODEGEN-SKIPPED: the plan filter skipped codegen for page.zen (no interactivity)"
+            )),
+            "{:?}",
+            result.warnings
+        );
+
+        let result =
+            crate::parse::compile_zen_internal(PLANNED_PAGE, "page.zen", options()).unwrap();
+        assert!(!result.manifest.as_ref().unwrap().bundle.is_empty());
+        assert!(!result
+            .warnings
+            .iter()
+            .any(|w| w.starts_with("Z-INFO-CODEGEN-SKIPPED")));
+    }
+
+    #[test]
+    fn test_plan_filter_forces_islands() {
+        fn force_islands(_: &CodegenPlan) -> PlanDecision {
+            PlanDecision::ForceIslands
+        }
+        const PAGE: &str = "<script>\nstate count = 0\n</script>\n<main><h1>Title</h1><div zen:island><button onclick={() => count += 1}>{count}</button></div></main>";
+        let unfiltered =
+            crate::parse::compile_zen_internal(PAGE, "page.zen", Default::default()).unwrap();
+        assert!(unfiltered.manifest.unwrap().islands.is_empty());
+        assert!(!unfiltered.html.contains("data-zen-island="));
+
+        let result = crate::parse::compile_zen_internal(
+            PAGE,
+            "page.zen",
+            crate::parse::CompileOptions {
+                plan_filter: Some(force_islands),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let manifest = result.manifest.unwrap();
+        assert_eq!(manifest.islands.len(), 1);
+        assert!(
+            result.html.contains("data-zen-island=\"island0\""),
+            "{}",
+            result.html
+        );
+    }
 }
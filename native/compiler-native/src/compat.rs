@@ -0,0 +1,494 @@
+//! Output Compatibility Report
+//!
+//! Compares the compiled output of one page across two compiler versions and
+//! sorts the differences into buckets, so an upgrade can be reviewed page by
+//! page:
+//!
+//! - `identical`: nothing differs
+//! - `cosmetic-only`: the HTML is the same DOM once whitespace, attribute order
+//!   and expression ids are normalized, bindings match by code, and the bundle
+//!   differs only in whitespace or ids
+//! - `behavioral-html`: the DOM structure, attributes, or text differ
+//! - `behavioral-bindings`: binding expressions, types, targets, or dependencies
+//!   differ, or the bundle differs beyond whitespace and ids
+//! - `errors-changed`: the error list (or `has_errors`) differs
+//!
+//! Expression ids are not compared directly. Bindings are paired by type, target
+//! and code, and each pair's ids are renamed to a shared placeholder before the
+//! HTML and bundle are compared.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::parse::CompileResult;
+
+lazy_static! {
+    /// `expr_3`, `expr_0_inst1` (also inside `_expr_expr_3`)
+    static ref EXPRESSION_ID: Regex = Regex::new(r"expr_\d+(?:_[a-z]+\d+)*").unwrap();
+    /// Registry entry: `__ZENITH_EXPRESSIONS__.set('expr_3', { fn: _expr_expr_3, deps: ['count'] })`
+    static ref REGISTRY_ENTRY: Regex = Regex::new(
+        r"__ZENITH_EXPRESSIONS__\.set\('([^']+)',\s*\{[^}]*?deps:\s*\[([^\]]*)\]"
+    )
+    .unwrap();
+    static ref QUOTED: Regex = Regex::new(r#"'([^']*)'|"([^"]*)""#).unwrap();
+    /// A tag or a run of text
+    static ref HTML_TOKEN: Regex = Regex::new(r"(?s)<!--.*?-->|<[^>]*>|[^<]+").unwrap();
+    static ref TAG_NAME: Regex = Regex::new(r"^</?\s*([^\s/>]+)").unwrap();
+    static ref TAG_ATTRIBUTE: Regex =
+        Regex::new(r#"([^\s=/>]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+)))?"#).unwrap();
+}
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Kind of difference, from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompatBucket {
+    Identical,
+    CosmeticOnly,
+    BehavioralHtml,
+    BehavioralBindings,
+    ErrorsChanged,
+}
+
+/// One bucket a page falls into, with what put it there
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatFinding {
+    pub bucket: CompatBucket,
+    /// The first differing element path, changed expression codes, added or removed errors
+    pub evidence: Vec<String>,
+}
+
+/// Differences between two compilations of the same page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatReport {
+    /// Most severe bucket among `findings` (`identical` when there are none)
+    pub classification: CompatBucket,
+    /// Every bucket that applies, most severe first
+    pub findings: Vec<CompatFinding>,
+}
+
+impl CompatReport {
+    pub fn is_identical(&self) -> bool {
+        self.classification == CompatBucket::Identical
+    }
+
+    /// Whether only cosmetic differences were found
+    pub fn is_cosmetic(&self) -> bool {
+        self.classification <= CompatBucket::CosmeticOnly
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// Classify how `new` differs from `old` (see the module docs for the buckets)
+pub fn compare_outputs(old: &CompileResult, new: &CompileResult) -> CompatReport {
+    let mut findings: BTreeMap<CompatBucket, Vec<String>> = BTreeMap::new();
+    let mut record = |bucket: CompatBucket, evidence: String| {
+        findings.entry(bucket).or_default().push(evidence);
+    };
+
+    // Errors
+    let old_errors: BTreeSet<&String> = old.errors.iter().collect();
+    let new_errors: BTreeSet<&String> = new.errors.iter().collect();
+    if old.has_errors != new.has_errors {
+        record(
+            CompatBucket::ErrorsChanged,
+            format!("has_errors: {} → {}", old.has_errors, new.has_errors),
+        );
+    }
+    for error in old_errors.difference(&new_errors) {
+        record(
+            CompatBucket::ErrorsChanged,
+            format!("removed error: {}", error),
+        );
+    }
+    for error in new_errors.difference(&old_errors) {
+        record(
+            CompatBucket::ErrorsChanged,
+            format!("added error: {}", error),
+        );
+    }
+
+    // Bindings, paired by (type, target, code)
+    let old_deps = registered_deps(old);
+    let new_deps = registered_deps(new);
+    let mut unmatched: Vec<&crate::transform::Binding> = new.bindings.iter().collect();
+    let mut renames: HashMap<String, String> = HashMap::new();
+    let mut binding_changed = false;
+    for binding in &old.bindings {
+        let position = unmatched.iter().position(|b| {
+            b.r#type == binding.r#type
+                && b.target == binding.target
+                && b.expression.trim() == binding.expression.trim()
+        });
+        let Some(position) = position else {
+            record(
+                CompatBucket::BehavioralBindings,
+                format!("removed binding: {}", describe_binding(binding)),
+            );
+            binding_changed = true;
+            continue;
+        };
+        let paired = unmatched.remove(position);
+        let placeholder = format!("expr_{}", renames.len() / 2);
+        renames.insert(format!("old:{}", binding.id), placeholder.clone());
+        renames.insert(format!("new:{}", paired.id), placeholder);
+
+        let (before, after) = (old_deps.get(&binding.id), new_deps.get(&paired.id));
+        if before != after {
+            record(
+                CompatBucket::BehavioralBindings,
+                format!(
+                    "deps of `{}`: [{}] → [{}]",
+                    binding.expression.trim(),
+                    before.map_or(String::new(), |d| d.join(", ")),
+                    after.map_or(String::new(), |d| d.join(", "))
+                ),
+            );
+            binding_changed = true;
+        }
+    }
+    for binding in unmatched {
+        record(
+            CompatBucket::BehavioralBindings,
+            format!("added binding: {}", describe_binding(binding)),
+        );
+        binding_changed = true;
+    }
+
+    // HTML, compared as a DOM with paired ids renamed alike
+    let old_html = canonical_ids(&old.html, "old", &renames);
+    let new_html = canonical_ids(&new.html, "new", &renames);
+    match first_dom_difference(&old_html, &new_html) {
+        Some(evidence) => record(CompatBucket::BehavioralHtml, evidence),
+        None if old.html != new.html => record(
+            CompatBucket::CosmeticOnly,
+            "html differs only in whitespace, attribute order, or expression ids".to_string(),
+        ),
+        None => {}
+    }
+
+    // Bundle
+    let bundle = |result: &CompileResult, side: &str| {
+        let code = result.manifest.as_ref().map_or("", |m| m.bundle.as_str());
+        canonical_ids(code, side, &renames)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let old_bundle = old.manifest.as_ref().map_or("", |m| m.bundle.as_str());
+    let new_bundle = new.manifest.as_ref().map_or("", |m| m.bundle.as_str());
+    if bundle(old, "old") != bundle(new, "new") {
+        // Changed expressions already explain a changed bundle
+        if !binding_changed {
+            record(
+                CompatBucket::BehavioralBindings,
+                format!(
+                    "bundle differs beyond whitespace and ids, first at: {}",
+                    first_line_difference(
+                        &canonical_ids(old_bundle, "old", &renames),
+                        &canonical_ids(new_bundle, "new", &renames)
+                    )
+                ),
+            );
+        }
+    } else if old_bundle != new_bundle {
+        record(
+            CompatBucket::CosmeticOnly,
+            "bundle differs only in whitespace or expression ids".to_string(),
+        );
+    }
+
+    let findings: Vec<CompatFinding> = findings
+        .into_iter()
+        .rev()
+        .map(|(bucket, evidence)| CompatFinding { bucket, evidence })
+        .collect();
+    CompatReport {
+        classification: findings
+            .first()
+            .map_or(CompatBucket::Identical, |f| f.bucket),
+        findings,
+    }
+}
+
+fn describe_binding(binding: &crate::transform::Binding) -> String {
+    format!(
+        "{} {} = {}",
+        binding.r#type,
+        binding.target,
+        binding.expression.trim()
+    )
+}
+
+/// Dependencies of each registered expression, from the bundle's registry entries
+fn registered_deps(result: &CompileResult) -> HashMap<String, Vec<String>> {
+    let bundle = result.manifest.as_ref().map_or("", |m| m.bundle.as_str());
+    REGISTRY_ENTRY
+        .captures_iter(bundle)
+        .map(|caps| {
+            let mut deps: Vec<String> = QUOTED
+                .captures_iter(&caps[2])
+                .filter_map(|q| q.get(1).or_else(|| q.get(2)))
+                .map(|m| m.as_str().to_string())
+                .collect();
+            deps.sort();
+            (caps[1].to_string(), deps)
+        })
+        .collect()
+}
+
+/// Paired ids become their shared placeholder; unpaired ids are kept
+fn canonical_ids(code: &str, side: &str, renames: &HashMap<String, String>) -> String {
+    EXPRESSION_ID
+        .replace_all(code, |caps: &regex::Captures| {
+            renames
+                .get(&format!("{}:{}", side, &caps[0]))
+                .cloned()
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .into_owned()
+}
+
+fn first_line_difference(old: &str, new: &str) -> String {
+    let mut old_lines = old.lines().map(str::trim).filter(|l| !l.is_empty());
+    let mut new_lines = new.lines().map(str::trim).filter(|l| !l.is_empty());
+    loop {
+        match (old_lines.next(), new_lines.next()) {
+            (Some(a), Some(b)) if a == b => continue,
+            (Some(a), Some(b)) => return format!("`{}` → `{}`", a, b),
+            (Some(a), None) => return format!("removed `{}`", a),
+            (None, Some(b)) => return format!("added `{}`", b),
+            (None, None) => return "whitespace inside a line".to_string(),
+        }
+    }
+}
+
+/// A tag with sorted attributes, or collapsed text
+#[derive(Debug, PartialEq)]
+enum DomToken {
+    Open(String, Vec<(String, String)>),
+    Close(String),
+    Text(String),
+}
+
+fn dom_tokens(html: &str) -> Vec<DomToken> {
+    let mut tokens = Vec::new();
+    for m in HTML_TOKEN.find_iter(html) {
+        let token = m.as_str();
+        if token.starts_with("<!") {
+            // Comments carry expression markers; a doctype is not part of the DOM tree
+            if let Some(marker) = token.strip_prefix("<!--zen:") {
+                tokens.push(DomToken::Text(format!(
+                    "<!--zen:{}",
+                    marker.trim_end_matches("-->")
+                )));
+            }
+            continue;
+        }
+        if token.starts_with('<') {
+            let Some(name) = TAG_NAME.captures(token).map(|c| c[1].to_ascii_lowercase()) else {
+                continue;
+            };
+            if token.starts_with("</") {
+                tokens.push(DomToken::Close(name));
+                continue;
+            }
+            let body = &token[TAG_NAME.find(token).map_or(0, |m| m.end())..];
+            let mut attributes: Vec<(String, String)> = TAG_ATTRIBUTE
+                .captures_iter(body)
+                .map(|c| {
+                    let value = c.get(2).or(c.get(3)).or(c.get(4));
+                    (
+                        c[1].to_string(),
+                        value.map_or(String::new(), |v| v.as_str().to_string()),
+                    )
+                })
+                .collect();
+            attributes.sort();
+            let void = VOID_ELEMENTS.contains(&name.as_str()) || token.ends_with("/>");
+            tokens.push(DomToken::Open(name.clone(), attributes));
+            if void {
+                tokens.push(DomToken::Close(name));
+            }
+            continue;
+        }
+        let text = token.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            tokens.push(DomToken::Text(text));
+        }
+    }
+    tokens
+}
+
+/// Path of the first element whose content differs (`html > body > ul > li[2]`), if any
+fn first_dom_difference(old: &str, new: &str) -> Option<String> {
+    let (old, new) = (dom_tokens(old), dom_tokens(new));
+    // Open elements as (tag, position among same-tag siblings), and sibling counts per level
+    let mut path: Vec<(String, usize)> = Vec::new();
+    let mut siblings: Vec<HashMap<String, usize>> = vec![HashMap::new()];
+    for index in 0..old.len().max(new.len()) {
+        let (a, b) = (old.get(index), new.get(index));
+        if a != b {
+            let at = describe_path(&path);
+            let detail = match (a, b) {
+                (Some(DomToken::Open(tag, a)), Some(DomToken::Open(other, b))) if tag == other => {
+                    format!("attributes of <{}>: {:?} → {:?}", tag, a, b)
+                }
+                (Some(a), Some(b)) => format!("{} → {}", describe_token(a), describe_token(b)),
+                (Some(a), None) => format!("removed {}", describe_token(a)),
+                (None, Some(b)) => format!("added {}", describe_token(b)),
+                (None, None) => unreachable!(),
+            };
+            return Some(format!("at {}: {}", at, detail));
+        }
+        match a? {
+            DomToken::Open(tag, _) => {
+                let level = siblings.last_mut().unwrap();
+                let nth = level.entry(tag.clone()).or_insert(0);
+                *nth += 1;
+                path.push((tag.clone(), *nth));
+                siblings.push(HashMap::new());
+            }
+            DomToken::Close(_) => {
+                if path.pop().is_some() {
+                    siblings.pop();
+                }
+            }
+            DomToken::Text(_) => {}
+        }
+    }
+    None
+}
+
+fn describe_path(path: &[(String, usize)]) -> String {
+    if path.is_empty() {
+        return "(root)".to_string();
+    }
+    path.iter()
+        .map(|(tag, nth)| {
+            if *nth > 1 {
+                format!("{}[{}]", tag, nth)
+            } else {
+                tag.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" > ")
+}
+
+fn describe_token(token: &DomToken) -> String {
+    match token {
+        DomToken::Open(tag, _) => format!("<{}>", tag),
+        DomToken::Close(tag) => format!("</{}>", tag),
+        DomToken::Text(text) => format!("text {:?}", text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(source: &str) -> CompileResult {
+        crate::parse::compile_zen_internal(source, "page.zen", Default::default()).unwrap()
+    }
+
+    const PAGE: &str = "<script>\nstate count = 1\n</script>\n<main class=\"page\" id=\"top\">\n  <p>{count} items</p>\n  <ul><li>One</li><li>Two</li></ul>\n</main>";
+
+    #[test]
+    fn test_identical_and_cosmetic_outputs() {
+        let old = compile(PAGE);
+        assert!(compare_outputs(&old, &old.clone()).is_identical());
+
+        // Same page from a compiler that numbers expressions differently; plus
+        // reformatted markup
+        let mut new = compile(PAGE);
+        let id = new.bindings[0].id.clone();
+        let renumber = |code: &str| code.replace(&id, "expr_7");
+        new.html = renumber(&new.html)
+            .replace("class=\"page\" id=\"top\"", "id=\"top\"  class=\"page\"")
+            .replace("<li>", "\n    <li>");
+        new.bindings[0].id = renumber(&id);
+        let manifest = new.manifest.as_mut().unwrap();
+        manifest.bundle = renumber(&manifest.bundle) + "\n\n";
+        assert_ne!(old.bindings[0].id, new.bindings[0].id);
+
+        let report = compare_outputs(&old, &new);
+        assert_eq!(
+            report.classification,
+            CompatBucket::CosmeticOnly,
+            "{:?}",
+            report
+        );
+        assert!(report.is_cosmetic());
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].evidence.len(), 2);
+    }
+
+    #[test]
+    fn test_behavioral_html_names_first_differing_element() {
+        let old = compile(PAGE);
+        let mut new = old.clone();
+        new.html = new.html.replace("<li>Two</li>", "<li>Three</li>");
+
+        let report = compare_outputs(&old, &new);
+        assert_eq!(report.classification, CompatBucket::BehavioralHtml);
+        let evidence = &report.findings[0].evidence[0];
+        assert!(
+            evidence.starts_with("at main > ul > li[2]: text \"Two\" → text \"Three\""),
+            "{}",
+            evidence
+        );
+    }
+
+    #[test]
+    fn test_behavioral_bindings_and_errors() {
+        let old = compile(PAGE);
+        let new = compile(&PAGE.replace("{count} items", "{count + 1} items"));
+        let report = compare_outputs(&old, &new);
+        assert_eq!(report.classification, CompatBucket::BehavioralBindings);
+        let evidence = &report.findings[0].evidence;
+        assert!(evidence
+            .iter()
+            .any(|e| e.contains("removed binding: text") && e.ends_with("= count")));
+        assert!(evidence.iter().any(|e| e.ends_with("= count + 1")));
+
+        // Same code, different dependencies
+        let mut new = old.clone();
+        let bundle = &mut new.manifest.as_mut().unwrap().bundle;
+        *bundle = bundle.replace("deps: ['count']", "deps: ['count', 'total']");
+        let report = compare_outputs(&old, &new);
+        assert_eq!(report.classification, CompatBucket::BehavioralBindings);
+        assert_eq!(
+            report.findings[0].evidence,
+            vec!["deps of `count`: [count] → [count, total]".to_string()]
+        );
+
+        let mut new = old.clone();
+        new.has_errors = true;
+        new.errors
+            .push("Z-ERR-SCOPE-002: Identifier `x` is not declared".to_string());
+        new.html = new.html.replace("items", "things");
+        let report = compare_outputs(&old, &new);
+        assert_eq!(report.classification, CompatBucket::ErrorsChanged);
+        let buckets: Vec<CompatBucket> = report.findings.iter().map(|f| f.bucket).collect();
+        assert_eq!(
+            buckets,
+            vec![CompatBucket::ErrorsChanged, CompatBucket::BehavioralHtml]
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["classification"], "errors-changed");
+        assert_eq!(json["findings"][1]["bucket"], "behavioral-html");
+    }
+}
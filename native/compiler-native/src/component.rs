@@ -9,9 +9,11 @@ use oxc_codegen::Codegen;
 use oxc_parser::Parser;
 use oxc_span::SourceType;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::rc::Rc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ComponentIR {
     pub name: String,
@@ -31,6 +33,9 @@ pub struct ComponentIR {
     pub states: HashMap<String, String>,
     #[serde(default)]
     pub styles: Vec<String>,
+    /// `<style global>` blocks, emitted once per page however many instances there are
+    #[serde(default)]
+    pub global_styles: Vec<String>,
     #[serde(default)]
     pub script: Option<String>,
     #[serde(default)]
@@ -39,9 +44,27 @@ pub struct ComponentIR {
     pub has_script: bool,
     #[serde(default)]
     pub has_styles: bool,
+    /// Package namespace (e.g. `ui` for `<ui.Button>`); may also be given as a `ui:Button` registry key
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Event names the component emits through `zenDispatch()` (`on:name` at usage sites)
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Hash of the entry's content, keying the per-component analysis of resolution.
+    /// Computed once per compile when absent; a caller reusing a registry across
+    /// compiles may supply it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(
+        flatten,
+        skip_serializing_if = "crate::validate::UnknownFields::is_empty"
+    )]
+    pub extra: crate::validate::UnknownFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct SlotDefinition {
     pub name: Option<String>, // None = default slot
@@ -57,12 +80,111 @@ pub struct ResolvedSlots {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SourceLocation {
     pub line: u32,
     pub column: u32,
 }
 
-// ... existing structs ...
+impl ComponentIR {
+    /// Build a registry entry from a `.zen` component source, parsed the same way as pages.
+    pub fn from_source(name: &str, path: &str, source: &str) -> Result<ComponentIR, String> {
+        let source = crate::parse::normalize_source(source);
+        let source = source.as_ref();
+        let template = crate::parse::parse_template(source, path)
+            .map_err(|e| format!("Template parse error in {}: {}", path, e.message))?;
+        let script = crate::parse::parse_script(source);
+        let (global_styles, styles): (Vec<_>, Vec<_>) =
+            crate::discovery::extract_style_blocks(source)
+                .into_iter()
+                .partition(|(_, global)| *global);
+        let styles: Vec<String> = styles.into_iter().map(|(raw, _)| raw).collect();
+        let global_styles: Vec<String> = global_styles.into_iter().map(|(raw, _)| raw).collect();
+
+        let mut slots = Vec::new();
+        collect_slot_definitions(&template.nodes, &mut slots);
+        let events = dispatched_events(
+            script.as_ref().map(|s| s.raw.as_str()),
+            &template.expressions,
+        );
+
+        Ok(ComponentIR {
+            name: name.to_string(),
+            path: path.to_string(),
+            template: template.raw,
+            nodes: template.nodes,
+            expressions: template.expressions,
+            slots,
+            props: script.as_ref().map(|s| s.props.clone()).unwrap_or_default(),
+            states: script
+                .as_ref()
+                .map(|s| s.states.clone())
+                .unwrap_or_default(),
+            has_script: script.is_some(),
+            has_styles: !styles.is_empty() || !global_styles.is_empty(),
+            styles,
+            global_styles,
+            script_attributes: script.as_ref().map(|s| s.attributes.clone()),
+            script: script.map(|s| s.raw),
+            namespace: None,
+            events,
+            content_hash: None,
+            extra: Default::default(),
+        })
+    }
+
+    /// Hash of everything resolution reads from the entry (`content_hash` excluded)
+    pub(crate) fn compute_content_hash(&self) -> String {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        let mut states: Vec<_> = self.states.iter().collect();
+        states.sort();
+        (&self.name, &self.path, &self.template, &self.script).hash(&mut hasher);
+        (&self.props, states, &self.events).hash(&mut hasher);
+        serde_json::to_string(&(&self.nodes, &self.expressions))
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+fn collect_slot_definitions(nodes: &[TemplateNode], slots: &mut Vec<SlotDefinition>) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) if el.tag == "slot" => {
+                let name = el
+                    .attributes
+                    .iter()
+                    .find(|a| a.name == "name")
+                    .and_then(|a| match &a.value {
+                        crate::validate::AttributeValue::Static(s) => Some(s.clone()),
+                        _ => None,
+                    });
+                slots.push(SlotDefinition {
+                    name,
+                    location: SourceLocation {
+                        line: el.location.line,
+                        column: el.location.column,
+                    },
+                });
+            }
+            TemplateNode::Element(el) => collect_slot_definitions(&el.children, slots),
+            TemplateNode::Component(c) => collect_slot_definitions(&c.children, slots),
+            TemplateNode::ConditionalFragment(cf) => {
+                collect_slot_definitions(&cf.consequent, slots);
+                collect_slot_definitions(&cf.alternate, slots);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    collect_slot_definitions(nodes, slots);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => collect_slot_definitions(&of.fragment, slots),
+            TemplateNode::LoopFragment(lf) => collect_slot_definitions(&lf.body, slots),
+            _ => {}
+        }
+    }
+}
 
 #[cfg_attr(feature = "napi", napi)]
 #[derive(Default)]
@@ -74,54 +196,403 @@ struct ResolutionContext {
     merged_script: String,
     all_states: HashMap<String, String>,
     all_props: HashSet<String>,
-    collected_imports: HashSet<String>,
+    /// Hoisted component imports, in first-use order
+    collected_imports: Vec<String>,
+    /// Top-level names of the merged script, for hoisting imports (see `script_collisions.rs`)
+    top_level: crate::script_collisions::TopLevelBindings,
     collected_errors: Vec<String>,
-    /// Head directive collected from Head component during resolution
-    head_directive: Option<crate::validate::HeadDirective>,
+    collected_warnings: Vec<String>,
+    /// Resolved registry entry and source path for each component usage
+    component_usages: Vec<crate::validate::ComponentUsage>,
+    /// Declared props each component actually received at some usage site
+    received_props: HashMap<String, HashSet<String>>,
+    /// Props passed conditionally (`name?={expr}`) with a dynamic value
+    conditional_props: HashMap<String, HashSet<String>>,
+    /// Static props passed as number/boolean literals, as `"name: type"`
+    coerced_props: HashMap<String, HashSet<String>>,
+    /// Apply the untyped literal heuristic (see `ResolveOptions`)
+    coerce_static_props: bool,
+    /// Dependency edges of every file reached during resolution
+    dep_graph: crate::dep_graph::DepGraphBuilder,
+    /// Position of each layout in the page's layout chain (outermost is 0)
+    layout_ranks: HashMap<String, usize>,
+    /// Head directives from Head components with the layout rank they belong to;
+    /// the page and non-layout components rank after every layout
+    head_directives: Vec<(usize, crate::validate::HeadDirective)>,
+    /// Script-defined names of the page, for compiling `on:` handlers in its scope
+    page_locals: HashSet<String>,
+    /// Events each used component emits
+    component_events: BTreeMap<String, Vec<String>>,
+    /// Islands mode (see `islands.rs`): only interactive instances register a scope
+    islands: bool,
+    inline_components: bool,
+    strict_slots: bool,
+    /// Scoped component styles: elements of each component with styles are marked
+    css_scope: Option<crate::css_scope::ScopeStrategy>,
+    /// Names of the classes resolution adds to markup (see `class_names.rs`)
+    class_names: crate::class_names::ClassNameAllocator,
+    /// Page being resolved and its template source
+    file_path: String,
+    page_source: String,
+    /// Resolving content that already hydrates as part of an island
+    in_island: bool,
+    /// Static props of instances that do not hydrate, keyed `instN.name`
+    island_props: HashMap<String, String>,
+    /// Stops resolution between nodes once cancelled
+    cancel: Option<crate::cancel::CancellationToken>,
+    /// Nesting limit with components expanded; once past it, nothing deeper is resolved
+    max_depth: usize,
+    too_deep: bool,
+    /// Tags and component names from the root to the nodes being resolved
+    tag_path: Vec<String>,
+    /// Per-component analysis keyed by (registry key, content hash)
+    analysis_cache: HashMap<(String, String), Rc<ComponentAnalysis>>,
+    /// Component script summaries, possibly shared with other compiles
+    script_cache: crate::script_cache::ScriptCache,
+    /// Usages substituted in place without an instance (see `inline_template`)
+    inlined_components: u32,
+    /// Ids of dynamic attributes on component usages, whose code resolution
+    /// copies into instance scripts
+    consumed_expressions: HashSet<String>,
+    /// Components whose templates are being resolved, outermost first
+    component_stack: Vec<String>,
+    /// Every instance created, for duplicate render detection (see `instances.rs`)
+    instances: Vec<crate::instances::InstanceRecord>,
+    /// Outermost `client:visible` / `client:idle` instance being resolved, whose
+    /// deferred setup collects the scopes of everything nested in it
+    deferral: Option<(String, crate::client_directives::HydrateStrategy)>,
+    /// Instances whose hydration a `client:*` directive decides
+    hydration: Vec<crate::client_directives::InstanceHydration>,
+}
+
+/// What resolution derives from a component alone, shared by all of its instances.
+/// Rename maps and expression promotion stay per instance.
+struct ComponentAnalysis {
+    state_bindings: HashSet<String>,
+    prop_bindings: HashSet<String>,
+    /// Script declarations that are neither props nor state
+    locals: HashSet<String>,
+    /// (code, imports, errors) of the script renamed for the `__run()` thunk
+    renamed_script: (String, Vec<String>, Vec<String>),
+    /// `false` when the script has syntax errors: its scope errors are not reported
+    script_parses: bool,
+    /// `Z-ERR-SCRIPT-SYNTAX` for template expressions that do not parse
+    expression_errors: Vec<String>,
+    prop_types: HashMap<String, PropType>,
+    events: Vec<String>,
+    is_document: bool,
+    /// The template has a `<slot>` to fill
+    has_slots: bool,
+    /// The template has an unnamed `<slot>`
+    has_default_slot: bool,
+    /// Names of the template's named slots, lowercased and sorted
+    named_slots: Vec<String>,
 }
 
-/// Internal component resolution for use by parse_full_zen_native
-pub fn resolve_components(
+impl ComponentAnalysis {
+    fn of(comp: &ComponentIR, scripts: &crate::script_cache::ScriptCache) -> Self {
+        let state_bindings: HashSet<String> = comp.states.keys().cloned().collect();
+        let prop_bindings: HashSet<String> = comp.props.iter().cloned().collect();
+        let (locals, renamed_script) = match scripts.get(comp) {
+            Some(parsed) => (
+                parsed.locals.clone(),
+                (
+                    parsed.renamed.clone(),
+                    parsed.imports.clone(),
+                    parsed.errors.clone(),
+                ),
+            ),
+            None => (HashSet::new(), (String::new(), Vec::new(), Vec::new())),
+        };
+        let script_parses = !renamed_script
+            .2
+            .iter()
+            .any(|e| e.starts_with(crate::script_syntax::SCRIPT_SYNTAX_ERROR));
+        let expression_errors =
+            crate::script_syntax::check_expressions(&comp.expressions, &comp.template, &comp.path);
+        let mut slots = Vec::new();
+        collect_slot_definitions(&comp.nodes, &mut slots);
+        ComponentAnalysis {
+            state_bindings,
+            prop_bindings,
+            locals,
+            renamed_script,
+            script_parses,
+            expression_errors,
+            prop_types: declared_prop_types(comp.script.as_deref().unwrap_or("")),
+            events: emitted_events(comp),
+            is_document: crate::document::is_document_module(&comp.nodes),
+            has_slots: !slots.is_empty(),
+            has_default_slot: slots.iter().any(|s| s.name.is_none()),
+            named_slots: slots
+                .iter()
+                .filter_map(|s| s.name.as_ref().map(|n| n.to_lowercase()))
+                .collect::<std::collections::BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+/// ` (line L, column C)` of a component tag in the page, or nothing when its
+/// position is not known (it comes from a component template or a JSX expression)
+fn tag_location(location: &crate::validate::SourceLocation) -> String {
+    if location.line == 0 {
+        return String::new();
+    }
+    format!(" (line {}, column {})", location.line, location.column)
+}
+
+/// Component tags of an expanded template were located in the component's
+/// file, not the page
+fn forget_tag_locations(nodes: &mut [TemplateNode]) {
+    for node in nodes {
+        match node {
+            TemplateNode::Component(c) => {
+                c.location = Default::default();
+                forget_tag_locations(&mut c.children);
+            }
+            TemplateNode::Element(el) => forget_tag_locations(&mut el.children),
+            TemplateNode::ConditionalFragment(cf) => {
+                forget_tag_locations(&mut cf.consequent);
+                forget_tag_locations(&mut cf.alternate);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children_mut() {
+                    forget_tag_locations(nodes);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => forget_tag_locations(&mut of.fragment),
+            TemplateNode::LoopFragment(lf) => forget_tag_locations(&mut lf.body),
+            _ => {}
+        }
+    }
+}
+
+impl ResolutionContext {
+    /// Analysis of the registry entry `key`, computed by its first instance
+    fn component_analysis(&mut self, key: &str) -> Rc<ComponentAnalysis> {
+        let comp = &self.components[key];
+        #[cfg(test)]
+        if tests::ANALYSIS_CACHE_DISABLED.with(std::cell::Cell::get) {
+            let scripts = crate::script_cache::ScriptCache::new();
+            return Rc::new(ComponentAnalysis::of(comp, &scripts));
+        }
+        let cache_key = (
+            key.to_string(),
+            comp.content_hash.clone().unwrap_or_default(),
+        );
+        if let Some(analysis) = self.analysis_cache.get(&cache_key) {
+            return Rc::clone(analysis);
+        }
+        let analysis = Rc::new(ComponentAnalysis::of(comp, &self.script_cache));
+        self.analysis_cache.insert(cache_key, Rc::clone(&analysis));
+        analysis
+    }
+}
+
+/// Options that change how component usages are resolved
+#[derive(Debug, Clone, Default)]
+pub struct ResolveOptions {
+    /// Pass untyped static props that are exactly `true`/`false` or an integer
+    /// as literals. Props declared as `number`/`boolean` are always coerced.
+    pub coerce_static_props: bool,
+    /// Islands mode: instances outside islands are rendered statically and get no scope
+    pub islands: bool,
+    /// Inline usages that need nothing at runtime (see `inline_template`); usages
+    /// marked `zen:inline-always` are tried either way
+    pub inline_components: bool,
+    /// Compound children (`<Card.Footer>`) for a slot the component does not
+    /// declare are errors instead of warnings
+    pub strict_slots: bool,
+    /// Checked before each node; a cancelled resolution returns `COMPILE_CANCELLED`
+    pub cancel: Option<crate::cancel::CancellationToken>,
+    /// Scope component styles to the component's markup (see `css_scope.rs`)
+    pub css_scope: Option<crate::css_scope::ScopeStrategy>,
+    /// Prefix of generated class names (see `class_names.rs`); unset: `zen-`
+    pub class_prefix: Option<String>,
+    /// Unknown IR fields are errors instead of being carried through (see `ir_compat.rs`)
+    pub strict_ir: bool,
+    /// Registry entries that fail the integrity checks of `registry.rs` fail the
+    /// compile instead of being left out
+    pub strict_registry: bool,
+    /// Nesting limit of the expanded tree (see `depth.rs`); unset: `DEFAULT_MAX_DEPTH`
+    pub max_depth: Option<usize>,
+    /// Component script summaries shared with other compiles (see `script_cache.rs`);
+    /// unset: a cache for this resolution only
+    pub script_cache: Option<crate::script_cache::ScriptCache>,
+}
+
+pub fn resolve_components_with_options(
     mut ir: ZenIR,
     components_map: HashMap<String, serde_json::Value>,
+    options: &ResolveOptions,
 ) -> Result<ZenIR, String> {
-    // Convert serde_json::Value to ComponentIR
-    let components: HashMap<String, ComponentIR> = components_map
-        .into_iter()
-        .filter_map(|(k, v)| serde_json::from_value(v).ok().map(|c| (k, c)))
-        .collect();
+    // Convert serde_json::Value to ComponentIR, leaving out entries that do not hold together
+    let (mut components, registry_diagnostics) =
+        crate::registry::take_in(components_map, options.strict_registry);
+    let mut assert_errors = Vec::new();
+    let mut registry_warnings = Vec::new();
+    if options.strict_registry {
+        assert_errors.extend(registry_diagnostics);
+    } else {
+        registry_warnings.extend(registry_diagnostics);
+    }
+    if options.strict_ir {
+        assert_errors.extend(crate::ir_compat::check_page(&ir));
+        let mut keys: Vec<&String> = components.keys().collect();
+        keys.sort();
+        for key in keys {
+            assert_errors.extend(crate::ir_compat::check_component(&components[key]));
+        }
+    }
+    for comp in components.values_mut() {
+        if comp.content_hash.is_none() {
+            comp.content_hash = Some(comp.compute_content_hash());
+        }
+        if let Some(script) = &comp.script {
+            let (script, errors) = crate::zen_assert::prepare_script(
+                script,
+                &comp.template,
+                &comp.path,
+                Some(&comp.name),
+            );
+            comp.script = Some(script);
+            assert_errors.extend(errors);
+        }
+    }
 
     let mut ctx = ResolutionContext {
         components,
+        coerce_static_props: options.coerce_static_props,
+        islands: options.islands,
+        inline_components: options.inline_components,
+        strict_slots: options.strict_slots,
+        css_scope: options.css_scope,
+        class_names: crate::class_names::ClassNameAllocator::new(options.class_prefix.as_deref()),
+        file_path: ir.file_path.clone(),
+        page_source: ir.template.raw.clone(),
+        cancel: options.cancel.clone(),
+        max_depth: options.max_depth.unwrap_or(crate::depth::DEFAULT_MAX_DEPTH),
+        script_cache: options.script_cache.clone().unwrap_or_default(),
+        collected_errors: assert_errors,
+        collected_warnings: registry_warnings,
         ..Default::default()
     };
 
+    // Only scoped styles generate classes; the author's classes are what they avoid
+    if ctx.css_scope.is_some() {
+        ctx.class_names
+            .register_user_classes(&ir.template.nodes, ir.styles.iter().map(|s| s.raw.as_str()));
+        for comp in ctx.components.values() {
+            ctx.class_names.register_user_classes(
+                &comp.nodes,
+                comp.styles
+                    .iter()
+                    .chain(&comp.global_styles)
+                    .map(String::as_str),
+            );
+        }
+    }
+
+    // Layout nesting is checked up front: a cycle would otherwise expand forever
+    ir.layout_chain = layout_chain(&ir.template.nodes, &ctx.components).map_err(|e| {
+        format!(
+            "Zenith Component Expansion Failed in {}:\n{}",
+            ir.file_path, e
+        )
+    })?;
+    ctx.layout_ranks = ir
+        .layout_chain
+        .iter()
+        .enumerate()
+        .map(|(rank, level)| (level.component.clone(), rank))
+        .collect();
+
+    let page_id = ctx
+        .dep_graph
+        .node(crate::dep_graph::DepKind::Page, &ir.file_path);
+    let page_script = ir.script.as_ref().map(|s| s.raw.as_str());
+    record_dependencies(&page_id, &ir.template.nodes, page_script, &mut ctx);
+
     // Accumulate existing script and initial states
     if let Some(script) = &ir.script {
         ctx.merged_script = script.raw.clone();
         for (k, v) in &script.states {
             ctx.all_states.insert(k.clone(), v.clone());
         }
+        ctx.top_level = crate::script_collisions::TopLevelBindings::from_page(
+            &script.raw,
+            &ctx.page_source,
+            &ir.file_path,
+        );
+        ctx.page_locals = get_local_declarations(&script.raw)
+            .into_iter()
+            .filter(|name| !script.states.contains_key(name) && !script.props.contains(name))
+            .collect();
     }
 
     // Resolve nodes
+    let tracked_content = crate::content_audit::track_page_content(&ir.template);
     let resolved_nodes = resolve_nodes(ir.template.nodes, &mut ctx, 0);
+    crate::cancel::check(ctx.cancel.as_ref())?;
 
     ir.template.nodes = resolved_nodes;
 
     // Append collected expressions
     ir.template.expressions.extend(ctx.collected_expressions);
+    // Props and handlers copied into instance scripts are used up, not orphaned
+    let referenced: HashSet<String> = crate::reconcile::referenced_ids(&ir.template.nodes)
+        .into_iter()
+        .collect();
+    ir.template
+        .expressions
+        .retain(|e| !ctx.consumed_expressions.contains(&e.id) || referenced.contains(&e.id));
 
-    // Collect styles from components
+    // Collect styles from components; global blocks are keyed by component path, so
+    // registry aliases of one component still contribute them once
     let mut component_styles = Vec::new();
+    let mut global_styles = std::collections::BTreeMap::new();
     for name in &ctx.used_components {
         if let Some(comp) = ctx.components.get(name) {
-            for style in &comp.styles {
-                component_styles.push(crate::validate::StyleIR { raw: style.clone() });
+            let (styles, errors) = crate::style_props::rewrite_styles(comp);
+            ctx.collected_errors.extend(errors);
+            for style in &styles {
+                let raw = match ctx.css_scope {
+                    Some(strategy) => crate::css_scope::scope_css(
+                        style,
+                        &crate::css_scope::scope_id(&comp.path),
+                        &ctx.class_names.allocate(
+                            crate::class_names::SCOPE_PURPOSE,
+                            &comp.path,
+                            "",
+                        ),
+                        strategy,
+                    ),
+                    None => style.clone(),
+                };
+                component_styles.push(crate::validate::StyleIR {
+                    raw,
+                    global: false,
+                    source_component: comp.path.clone(),
+                });
+            }
+            if !comp.global_styles.is_empty() && !global_styles.contains_key(&comp.path) {
+                ctx.collected_warnings
+                    .extend(crate::global_styles::check_component_classes(comp));
+                global_styles.insert(comp.path.clone(), comp.global_styles.clone());
             }
         }
     }
     ir.styles.extend(component_styles);
+    ir.styles
+        .extend(global_styles.into_iter().flat_map(|(path, styles)| {
+            styles.into_iter().map(move |raw| crate::validate::StyleIR {
+                raw,
+                global: true,
+                source_component: path.clone(),
+            })
+        }));
 
     // Generate scope registration for each instance
     // (Handled internally by resolve_component_node)
@@ -152,13 +623,79 @@ pub fn resolve_components(
     ir.page_bindings = ctx.all_states.keys().cloned().collect();
     ir.page_props = ctx.all_props.into_iter().collect();
     ir.all_states = ctx.all_states;
-    ir.head_directive = ctx.head_directive;
+    // Outermost layout first, so more specific levels override it
+    ctx.head_directives.sort_by_key(|(rank, _)| *rank);
+    ir.head_directive = ctx
+        .head_directives
+        .into_iter()
+        .map(|(_, head)| head)
+        .reduce(|mut merged, inner| {
+            merged.merge(inner);
+            merged
+        });
+
+    // Cross-component id references cannot be rewritten safely; surface what remains
+    for (id, count) in find_duplicate_ids(&ir.template.nodes) {
+        ctx.collected_warnings.push(format!(
+            "Z-WARN-A11Y-DUPLICATE-ID: id \"{}\" appears {} times in {}. Label associations and querySelector will target the first match only.",
+            id, count, ir.file_path
+        ));
+    }
+    // Components rendered more than once (see `instances.rs`)
+    ctx.collected_warnings
+        .extend(crate::instances::check_instances(
+            &ctx.instances,
+            &ctx.components,
+            &ir.file_path,
+        ));
+    // Page content that slot resolution dropped or left outside the layout's document
+    ctx.collected_warnings
+        .extend(crate::content_audit::check_resolved_content(
+            &tracked_content,
+            &ir.template.nodes,
+            &ir.file_path,
+        ));
+    // Declared props no usage site passed (input for a project-wide unused-prop report)
+    for name in &ctx.used_components {
+        if let Some(comp) = ctx.components.get(name) {
+            let received = ctx.received_props.get(name);
+            let mut unused: Vec<String> = declared_props(comp)
+                .into_iter()
+                .filter(|p| !received.is_some_and(|r| r.contains(p)))
+                .collect();
+            if !unused.is_empty() {
+                unused.sort();
+                ir.unused_props.insert(name.clone(), unused);
+            }
+        }
+    }
+
+    ir.warnings.extend(ctx.collected_warnings);
+    ir.component_usages = ctx.component_usages;
+    ir.inlined_components = ctx.inlined_components;
+    ir.dep_graph = ctx.dep_graph.build();
+    for (name, props) in ctx.conditional_props {
+        let mut props: Vec<String> = props.into_iter().collect();
+        props.sort();
+        ir.conditional_props.insert(name, props);
+    }
+    for (name, props) in ctx.coerced_props {
+        let mut props: Vec<String> = props.into_iter().collect();
+        props.sort();
+        ir.coerced_props.insert(name, props);
+    }
+    ir.component_events = ctx.component_events;
+    ir.island_props = ctx.island_props;
+    ir.hydration = ctx.hydration;
+    let (generated_classes, class_warnings) = ctx.class_names.finish();
+    ir.generated_classes = generated_classes;
+    ir.warnings.extend(class_warnings);
 
     if !ctx.collected_errors.is_empty() {
         return Err(format!(
             "Zenith Component Expansion Failed in {}:\n{}",
             ir.file_path,
-            ctx.collected_errors.join("\n")
+            crate::diagnostics::normalize(ctx.collected_errors).join("\n")
         ));
     }
 
@@ -170,14 +707,39 @@ fn resolve_nodes(
     ctx: &mut ResolutionContext,
     depth: u32,
 ) -> Vec<TemplateNode> {
+    // Resolution and every pass after it recurse per level; the compile fails here
+    if depth as usize >= ctx.max_depth || ctx.too_deep {
+        if !ctx.too_deep && !nodes.is_empty() {
+            ctx.too_deep = true;
+            ctx.collected_errors.push(crate::depth::too_deep(
+                depth as usize + 1,
+                ctx.max_depth,
+                &ctx.tag_path,
+                &ctx.file_path,
+            ));
+        }
+        return nodes;
+    }
     let mut resolved = Vec::new();
     for node in nodes {
+        // The partial tree is discarded by the caller
+        if ctx.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            break;
+        }
         match node {
             TemplateNode::Component(comp) => {
                 resolved.extend(resolve_component_node(comp, ctx, depth));
             }
             TemplateNode::Element(mut elem) => {
+                let outer_island = ctx.in_island;
+                ctx.in_island |= elem
+                    .attributes
+                    .iter()
+                    .any(|a| a.name == crate::transform::ISLAND_ATTR);
+                ctx.tag_path.push(elem.tag.clone());
                 elem.children = resolve_nodes(elem.children, ctx, depth + 1);
+                ctx.tag_path.pop();
+                ctx.in_island = outer_island;
                 resolved.push(TemplateNode::Element(elem));
             }
             TemplateNode::ConditionalFragment(mut cond) => {
@@ -185,6 +747,13 @@ fn resolve_nodes(
                 cond.alternate = resolve_nodes(cond.alternate, ctx, depth + 1);
                 resolved.push(TemplateNode::ConditionalFragment(cond));
             }
+            TemplateNode::MultiBranchFragment(mut multi) => {
+                for branch in &mut multi.branches {
+                    branch.nodes = resolve_nodes(std::mem::take(&mut branch.nodes), ctx, depth + 1);
+                }
+                multi.default = resolve_nodes(multi.default, ctx, depth + 1);
+                resolved.push(TemplateNode::MultiBranchFragment(multi));
+            }
             TemplateNode::OptionalFragment(mut opt) => {
                 opt.fragment = resolve_nodes(opt.fragment, ctx, depth + 1);
                 resolved.push(TemplateNode::OptionalFragment(opt));
@@ -200,84 +769,173 @@ fn resolve_nodes(
 }
 
 fn resolve_component_node(
-    node: crate::validate::ComponentNode,
+    mut node: crate::validate::ComponentNode,
     ctx: &mut ResolutionContext,
     depth: u32,
 ) -> Vec<TemplateNode> {
     let mut name = node.name.clone();
+    // `zen:island` and `zen:inline-always` are directives, not props
+    let marked_island = node
+        .attributes
+        .iter()
+        .any(|a| a.name == crate::transform::ISLAND_ATTR);
+    let inline_always = node
+        .attributes
+        .iter()
+        .any(|a| a.name == crate::transform::INLINE_ALWAYS_ATTR);
+    let allow_multiple = node
+        .attributes
+        .iter()
+        .any(|a| a.name == crate::instances::ALLOW_MULTIPLE_ATTR);
+    node.attributes.retain(|a| {
+        a.name != crate::transform::ISLAND_ATTR
+            && a.name != crate::transform::INLINE_ALWAYS_ATTR
+            && a.name != crate::instances::ALLOW_MULTIPLE_ATTR
+    });
 
     // PHASE 3: Handle virtual Head component for compile-time teleportation
     if name == "Head" {
-        // Extract attributes for head directive
-        let mut head_directive = crate::validate::HeadDirective::default();
-
-        for attr in &node.attributes {
-            let value = match &attr.value {
-                crate::validate::AttributeValue::Static(s) => s.clone(),
-                crate::validate::AttributeValue::Dynamic(expr) => {
-                    // STRICT HEAD ENFORCEMENT
-                    // Attributes on <Head> component must be static.
-                    // Try to resolve using static_eval (with empty props for now - strict mode)
-                    let empty_props = std::collections::HashMap::new();
-                    // We need to resolve expression code.
-                    match crate::static_eval::static_eval(&expr.code, &empty_props) {
-                        Some(val) => val,
-                        None => {
-                            // FAIL HARD
-                            format!(
-                                "ZENITH_COMPILE_ERROR: Dynamic head attribute '{}' not allowed",
-                                expr.code
-                            )
-                        }
-                    }
-                }
-            };
-
-            match attr.name.as_str() {
-                "title" => head_directive.title = Some(value),
-                "description" => {
-                    head_directive.description = Some(value.clone());
-                    head_directive.meta.push(crate::validate::MetaTag {
-                        name: Some("description".to_string()),
-                        property: None,
-                        content: value,
-                    });
-                }
-                _ => {}
-            }
-        }
-
         // Store the head directive in context for later injection
-        ctx.head_directive = Some(head_directive);
+        let rank = ctx.layout_ranks.len();
+        ctx.head_directives.push((rank, head_directive(&node)));
 
         // Head component doesn't render inline - it teleports to <head>
         return vec![];
     }
 
-    // Invariants check: try exact match first, then case-insensitive
-
-    if !ctx.components.contains_key(&name) {
-        let lower_name = name.to_lowercase();
-        let mut found = false;
-        for (comp_name, _) in &ctx.components {
-            if comp_name.to_lowercase() == lower_name {
-                name = comp_name.clone();
-                found = true;
-                break;
-            }
-        }
-
-        if !found {
+    // Invariants check: exact match first, then case-insensitive; collisions are errors
+    match find_component_key(&name, &ctx.components) {
+        Ok(Some(key)) => name = key,
+        Ok(None) => {
+            check_orphan_slot_marker(&node, ctx);
             // BUG FIX: If the component isn't in the registry (e.g. it's a Layout tag),
             // we MUST still resolve its children, otherwise the page content is lost.
             let mut unresolved_node = node.clone();
+            ctx.tag_path.push(unresolved_node.name.clone());
             unresolved_node.children = resolve_nodes(node.children, ctx, depth + 1);
+            ctx.tag_path.pop();
             return vec![TemplateNode::Component(unresolved_node)];
         }
+        Err(e) => {
+            ctx.collected_errors.push(e);
+            return vec![];
+        }
     }
 
     ctx.used_components.insert(name.clone());
     let comp = ctx.components.get(&name).unwrap().clone();
+    // `client:load` / `client:visible` / `client:idle` are loading directives, not props
+    let directive = match crate::client_directives::take_client_directive(
+        &node.name,
+        &comp.path,
+        &mut node.attributes,
+    ) {
+        Ok(Some(strategy)) if strategy.is_deferred() && node.loop_context.is_some() => {
+            ctx.collected_warnings.push(format!(
+                "Z-WARN-CLIENT-DIRECTIVE-LOOP: <{}> ({}) is rendered in a loop, so `client:{}` cannot defer each copy; it hydrates on load.",
+                node.name,
+                comp.path,
+                strategy.as_str()
+            ));
+            Some(crate::client_directives::HydrateStrategy::Load)
+        }
+        Ok(directive) => directive,
+        Err(e) => {
+            ctx.collected_errors.push(e);
+            None
+        }
+    };
+    ctx.component_usages.push(crate::validate::ComponentUsage {
+        tag: node.name.clone(),
+        component: name.clone(),
+        path: comp.path.clone(),
+    });
+    let analysis = ctx.component_analysis(&name);
+    let dep_id = component_dep_node(
+        &name,
+        &comp,
+        analysis.is_document,
+        &ctx.layout_ranks,
+        &mut ctx.dep_graph,
+    );
+    record_dependencies(&dep_id, &comp.nodes, comp.script.as_deref(), ctx);
+
+    check_usage_props(&name, &comp, &node.attributes, ctx);
+    for attr in &node.attributes {
+        if let crate::validate::AttributeValue::Dynamic(expr) = &attr.value {
+            ctx.consumed_expressions.insert(expr.id.clone());
+        }
+    }
+    let attributes = apply_conditional_props(&name, &comp, node.attributes.clone(), ctx);
+    // `on:event` handlers are wired to the instance's dispatcher, not passed as props
+    let (event_attrs, attributes): (Vec<_>, Vec<_>) = attributes
+        .into_iter()
+        .partition(|a| a.name.starts_with(EVENT_ATTR_PREFIX));
+    let events = analysis.events.clone();
+    check_usage_events(&name, &events, &event_attrs, ctx);
+    check_usage_slots(&name, &analysis, &node.children, ctx);
+    if !events.is_empty() {
+        ctx.component_events.insert(name.clone(), events);
+    }
+
+    // Leaf components with nothing to resolve at runtime are substituted in place:
+    // no instance, no scope registration, no `__run()`
+    let inlined = if !ctx.inline_components && !inline_always {
+        Err(String::new())
+    } else if marked_island {
+        Err("it is marked `zen:island`".to_string())
+    } else if directive.is_some() {
+        Err("it has a `client:` loading directive".to_string())
+    } else {
+        inline_template(
+            &name,
+            &comp,
+            &analysis,
+            &attributes,
+            &event_attrs,
+            &node.children,
+            ctx,
+        )
+    };
+    match inlined {
+        Ok(mut template_nodes) => {
+            ctx.inlined_components += 1;
+            mark_style_scope(&mut template_nodes, &comp, ctx);
+            // Inlined usages only pass static props
+            crate::style_props::apply_instance_vars(
+                &mut template_nodes,
+                &comp,
+                &attributes,
+                None,
+                &HashMap::new(),
+            );
+            let resolved_template = if analysis.has_slots {
+                let slots = extract_slots(&name, node.children, node.loop_context.clone());
+                resolve_slots(template_nodes, &slots)
+            } else {
+                template_nodes
+            };
+            ctx.tag_path.push(name.clone());
+            let nodes = resolve_nodes(resolved_template, ctx, depth + 1);
+            ctx.tag_path.pop();
+            return nodes;
+        }
+        Err(reason) if inline_always => ctx.collected_warnings.push(format!(
+            "Z-WARN-INLINE-ALWAYS: <{}> ({}) is marked `zen:inline-always` but cannot be inlined because {}; it resolves as a regular instance.",
+            node.name, comp.path, reason
+        )),
+        Err(_) => {}
+    }
+    // Islands mode: instances outside islands render statically, without a scope;
+    // an interactive instance (or one with a loading directive) becomes an island of its own
+    let hydrates = !ctx.islands
+        || ctx.in_island
+        || crate::islands::is_interactive_component(
+            &comp,
+            marked_island || directive.is_some(),
+            !event_attrs.is_empty(),
+        );
+    let own_island = ctx.islands && !ctx.in_island && hydrates;
 
     // 1. Extract slots
     let slots = extract_slots(&name, node.children, node.loop_context.clone());
@@ -285,41 +943,62 @@ fn resolve_component_node(
     // 2. Clone and rename logic
     let instance_id = ctx.instance_counter;
     ctx.instance_counter += 1;
+    ctx.instances.push(crate::instances::InstanceRecord {
+        component: name.clone(),
+        instance_id: format!("inst{}", instance_id),
+        parents: ctx.component_stack.clone(),
+        allow_multiple,
+        runs_script: hydrates,
+    });
+    let first_instance = ctx.instances.len() - 1;
     let instance_suffix = format!("inst{}", instance_id);
+    // A deferred instance starts a deferral group unless it is nested in one already
+    let outer_deferral = ctx.deferral.clone();
+    let deferral = match (&outer_deferral, directive) {
+        (Some((group, strategy)), _) => Some((group.clone(), *strategy)),
+        (None, Some(strategy)) if strategy.is_deferred() => {
+            Some((instance_suffix.clone(), strategy))
+        }
+        _ => None,
+    };
+    let recorded = deferral
+        .clone()
+        .or(directive.map(|strategy| (instance_suffix.clone(), strategy)));
+    if let Some((group, strategy)) = recorded {
+        ctx.hydration
+            .push(crate::client_directives::InstanceHydration {
+                instance: instance_suffix.clone(),
+                component: name.clone(),
+                strategy: strategy.as_str().to_string(),
+                group,
+            });
+    }
+    zen_span!(TRACE, "resolve_component_node", component = %name, instance = instance_id);
 
-    // Categories for ScriptRenamer
-    let mut comp_state_bindings = HashSet::new();
-    let mut comp_prop_bindings = HashSet::new();
-    let mut comp_local_bindings = HashSet::new();
+    // Categories for ScriptRenamer, shared by every instance of the component
+    let comp_state_bindings = &analysis.state_bindings;
+    let comp_prop_bindings = &analysis.prop_bindings;
+    let comp_local_bindings = &analysis.locals;
 
-    // 1. Initial categorization from metadata
-    for prop in &comp.props {
-        comp_prop_bindings.insert(prop.clone());
-    }
     for (name, val) in &comp.states {
-        comp_state_bindings.insert(name.clone());
         ctx.all_states.insert(name.clone(), val.clone());
     }
-
-    for prop in &comp_prop_bindings {
+    for prop in comp_prop_bindings {
         ctx.all_props.insert(prop.clone());
     }
 
-    // 2. Discover locals from script (all other symbols are locals)
-    if let Some(script_content) = &comp.script {
-        let all_decls = get_local_declarations(script_content);
-        for decl in all_decls {
-            if !comp_prop_bindings.contains(&decl) && !comp_state_bindings.contains(&decl) {
-                comp_local_bindings.insert(decl);
-            }
-        }
-    }
-
     // Map passed attributes to prop values for scope registration
+    let prop_types = &analysis.prop_types;
     let mut prop_vals = Vec::new();
-    for attr in &node.attributes {
+    for attr in &attributes {
         let val = match &attr.value {
-            crate::validate::AttributeValue::Static(s) => format!("\"{}\"", s),
+            crate::validate::AttributeValue::Static(s) => {
+                static_prop_literal(&name, attr, s, prop_types, ctx)
+            }
+            // Nullish conditional values collapse to `undefined` so the component default applies
+            crate::validate::AttributeValue::Dynamic(expr) if attr.optional => {
+                format!("({}) ?? undefined", expr.code)
+            }
             crate::validate::AttributeValue::Dynamic(expr) => format!("({})", expr.code),
         };
         prop_vals.push(format!("    \"{}\": {}", attr.name, val));
@@ -333,17 +1012,33 @@ fn resolve_component_node(
         expression_id_map.insert(expr.id.clone(), new_id.clone());
         let (renamed_code, _, expr_errors) = rename_symbols_safe(
             &expr.code,
-            &comp_state_bindings,
-            &comp_prop_bindings,
-            &comp_local_bindings,
+            comp_state_bindings,
+            comp_prop_bindings,
+            comp_local_bindings,
             &HashSet::new(),
             false, // Not in __run(), these are promoted expressions
             false, // Template context: Strict mode (Phase 4) - NO fallback
         );
 
-        if !expr_errors.is_empty() {
-            ctx.collected_errors.extend(expr_errors);
-        }
+        let origin = expr
+            .origin
+            .clone()
+            .unwrap_or_else(|| crate::validate::ExpressionOrigin {
+                component_path: comp.path.clone(),
+                component_name: name.clone(),
+                instance_id: instance_suffix.clone(),
+                original_id: expr.id.clone(),
+                original_location: expr.location.clone(),
+            });
+        ctx.collected_errors.extend(
+            expr_errors
+                .into_iter()
+                .filter(|e| {
+                    analysis.script_parses
+                        || !e.starts_with(crate::validate::INV_UNRESOLVED_IDENTIFIER)
+                })
+                .map(|e| format!("{}, in {}", e, origin)),
+        );
 
         let final_code = renamed_code.replace(
             "scope.",
@@ -355,42 +1050,38 @@ fn resolve_component_node(
             code: final_code,
             location: expr.location.clone(),
             loop_context: expr.loop_context.clone(),
+            origin: Some(origin),
+            extra: expr.extra.clone(),
         });
     }
 
     // 4. Merge Script with Scope Registry + Execution Contract
-    let (renamed_script, script_imports, script_errors) = if let Some(script_content) = &comp.script
-    {
-        rename_symbols_safe(
-            script_content,
-            &comp_state_bindings,
-            &comp_prop_bindings,
-            &comp_local_bindings,
-            &HashSet::new(), // Component-level external locals (usually none)
-            true,            // Phase A7: Disallow reactive access in __run()
-            false,           // Script context: NO prop fallback
-        )
-    } else {
-        (String::new(), Vec::new(), Vec::new())
-    };
+    let (mut renamed_script, script_imports, script_errors) = analysis.renamed_script.clone();
 
-    // Collect extracted imports
-    ctx.collected_imports.extend(script_imports);
+    // Collect extracted imports (a static instance's script never runs); imports
+    // colliding with the page's top-level names are renamed for this instance
+    if hydrates {
+        let hoisted = ctx
+            .top_level
+            .hoist(&script_imports, &comp, &instance_suffix);
+        for statement in hoisted.statements {
+            if !ctx.collected_imports.contains(&statement) {
+                ctx.collected_imports.push(statement);
+            }
+        }
+        ctx.collected_errors.extend(hoisted.errors);
+        renamed_script =
+            crate::script_collisions::rename_references(&renamed_script, &hoisted.renames);
+    }
 
     // Phase A7: Hard enforcement of non-reactive __run()
     if !script_errors.is_empty() {
         ctx.collected_errors.extend(script_errors);
     }
+    ctx.collected_errors
+        .extend(analysis.expression_errors.iter().cloned());
 
-    /*
-    if ctx.file_path.contains("documentation") {
-        println!(
-            "\n\n[DEBUG DOCUMENTATION SCRIPT] File: {}\nRenamed Script:\n{}\n\n",
-            ctx.file_path, renamed_script
-        );
-    }
-    */
-
+    let block_start = ctx.merged_script.len();
     ctx.merged_script.push_str("\n\n");
     ctx.merged_script
         .push_str(&format!("// --- Instance {} ---\n{{\n", instance_suffix));
@@ -423,7 +1114,7 @@ fn resolve_component_node(
     // List of effects to sync props from parent to child
     let mut prop_sync_effects = Vec::new();
 
-    for (i, attr) in node.attributes.iter().enumerate() {
+    for (i, attr) in attributes.iter().enumerate() {
         if let crate::validate::AttributeValue::Dynamic(expr) = &attr.value {
             // Transform parent expression code in parent context
             let (renamed, _, _) = rename_symbols_safe(
@@ -436,6 +1127,14 @@ fn resolve_component_node(
                 true, // Use fallback for parent expressions in template
             );
 
+            // The renamer terminates the expression as a statement
+            let renamed = renamed.trim_end().trim_end_matches(';').trim_end();
+            let renamed = if attr.optional {
+                format!("({}) ?? undefined", renamed)
+            } else {
+                renamed.to_string()
+            };
+
             // Generate Effect to sync: parent_expr -> child_scope.props.name -> Notify
             let effect_id = format!("prop_sync_{}_{}_{}", instance_suffix, attr.name, i);
             let effect_js = format!(
@@ -455,6 +1154,9 @@ fn resolve_component_node(
     for effect in prop_sync_effects {
         ctx.merged_script.push_str(&effect);
     }
+    if let Some(handlers) = event_handlers_js(&event_attrs, ctx) {
+        ctx.merged_script.push_str(&handlers);
+    }
 
     // Destructuring removed to avoid 'state' variable name collision with codegen regex
     // User code is already renamed to use scope.state, scope.props, scope.locals
@@ -469,6 +1171,13 @@ fn resolve_component_node(
         .push_str("    const scope = __zen_inst_scope;\n");
     ctx.merged_script
         .push_str("    const { state, props, locals } = scope;\n");
+    if comp
+        .script
+        .as_deref()
+        .is_some_and(|s| s.contains("zenDispatch"))
+    {
+        ctx.merged_script.push_str(DISPATCH_HELPER_JS);
+    }
     if renamed_script.trim().is_empty() {
         ctx.merged_script
             .push_str("    // No component script - empty execution thunk\n");
@@ -483,61 +1192,362 @@ fn resolve_component_node(
     }
     ctx.merged_script.push_str("  };\n");
     ctx.merged_script.push_str("}");
+    if !hydrates {
+        ctx.merged_script.truncate(block_start);
+        record_island_props(&instance_suffix, &attributes, ctx);
+        if comp.script.as_deref().is_some_and(|s| !s.trim().is_empty()) {
+            ctx.collected_warnings.push(format!(
+                "Z-WARN-ISLAND-SCRIPT: <{}> ({}) renders outside every island, so its script never runs. Mark the usage `zen:island` to hydrate it.",
+                name, comp.path
+            ));
+        }
+    }
 
     // 5. Expand Template
     // Need to clone nodes first as we are mutating
     let mut template_nodes = comp.nodes.clone();
+    forget_tag_locations(&mut template_nodes);
     rewrite_node_expressions(&mut template_nodes, &expression_id_map);
-    let resolved_template = resolve_slots(template_nodes, &slots);
+    // A layout's own Head belongs to its chain level; slot content keeps the caller's
+    if let Some(&rank) = ctx.layout_ranks.get(&name) {
+        for head in take_head_nodes(&mut template_nodes) {
+            ctx.head_directives.push((rank, head_directive(&head)));
+        }
+    }
+    // Scope ids and styles before slots are filled so parent-provided content keeps its own
+    scope_component_ids(&mut template_nodes, &instance_suffix);
+    mark_style_scope(&mut template_nodes, &comp, ctx);
+    let style_origin = crate::validate::ExpressionOrigin {
+        component_path: comp.path.clone(),
+        component_name: name.clone(),
+        instance_id: instance_suffix.clone(),
+        original_id: "style".to_string(),
+        original_location: node.location.clone(),
+    };
+    ctx.collected_expressions
+        .extend(crate::style_props::apply_instance_vars(
+            &mut template_nodes,
+            &comp,
+            &attributes,
+            hydrates.then_some(style_origin),
+            &crate::static_eval::initial_state_values(&ctx.all_states),
+        ));
+    let resolved_template = if analysis.has_slots {
+        resolve_slots(template_nodes, &slots)
+    } else {
+        template_nodes
+    };
+
+    let outer_island = ctx.in_island;
+    ctx.in_island |= hydrates;
+    ctx.deferral = deferral;
+    ctx.component_stack.push(name.clone());
+    ctx.tag_path.push(name.clone());
+    let nodes = resolve_nodes(resolved_template, ctx, depth + 1);
+    ctx.tag_path.pop();
+    ctx.component_stack.pop();
+    ctx.deferral = outer_deferral.clone();
+    ctx.in_island = outer_island;
+
+    // The group's scope blocks (this instance's and those nested in it) run on its trigger
+    let nodes = match directive {
+        Some(strategy) if outer_deferral.is_none() => {
+            if strategy.is_deferred() {
+                let blocks = ctx.merged_script.split_off(block_start);
+                let scopes: Vec<String> = ctx.instances[first_instance..]
+                    .iter()
+                    .filter(|i| i.runs_script)
+                    .map(|i| i.instance_id.clone())
+                    .collect();
+                ctx.merged_script
+                    .push_str(&crate::client_directives::deferred_setup(
+                        &instance_suffix,
+                        strategy,
+                        &blocks,
+                        &scopes,
+                    ));
+            }
+            vec![crate::client_directives::hydrate_wrapper(
+                nodes,
+                &instance_suffix,
+                strategy,
+                node.location.clone(),
+            )]
+        }
+        _ => nodes,
+    };
+    if own_island {
+        vec![crate::islands::component_island(
+            nodes,
+            node.location.clone(),
+        )]
+    } else {
+        nodes
+    }
+}
+
+/// Template of a usage with every prop substituted, when the component needs nothing
+/// at runtime: no state, locals or events, a script that only declares props, static
+/// usage attributes, template expressions that fold to constants, no fragments or
+/// scoped ids, and static slot content. Otherwise the reason it cannot be inlined.
+fn inline_template(
+    name: &str,
+    comp: &ComponentIR,
+    analysis: &ComponentAnalysis,
+    attributes: &[crate::validate::AttributeIR],
+    event_attrs: &[crate::validate::AttributeIR],
+    children: &[TemplateNode],
+    ctx: &mut ResolutionContext,
+) -> Result<Vec<TemplateNode>, String> {
+    lazy_static::lazy_static! {
+        /// `prop name`, `prop name: Type`, `prop name = default` as a whole line
+        static ref PROP_ONLY_LINE: regex::Regex = regex::Regex::new(
+            r"^prop\s+([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)\s*\??\s*(?::[^=;]+)?(?:=\s*([^;]+?))?\s*;?$"
+        )
+        .unwrap();
+    }
+
+    if !analysis.state_bindings.is_empty() || !analysis.locals.is_empty() {
+        return Err("it declares state or script locals".to_string());
+    }
+    if !analysis.events.is_empty() || !event_attrs.is_empty() {
+        return Err("it dispatches or handles component events".to_string());
+    }
+    if analysis.is_document {
+        return Err("it is a document layout".to_string());
+    }
+
+    let empty = HashMap::new();
+    let mut props = HashMap::new();
+    for line in comp.script.as_deref().unwrap_or("").lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let Some(caps) = PROP_ONLY_LINE.captures(line) else {
+            return Err("its script does more than declare props".to_string());
+        };
+        if let Some(default) = caps.get(2) {
+            let value = crate::static_eval::static_eval(default.as_str(), &empty)
+                .ok_or_else(|| format!("the default of prop `{}` is not a literal", &caps[1]))?;
+            props.insert(caps[1].to_string(), value);
+        }
+    }
+    if let Some(attr) = attributes
+        .iter()
+        .find(|a| matches!(a.value, crate::validate::AttributeValue::Dynamic(_)))
+    {
+        return Err(format!("prop `{}` is dynamic", attr.name));
+    }
+    for attr in attributes {
+        if let crate::validate::AttributeValue::Static(v) = &attr.value {
+            // Typed props are checked and coerced as for a regular instance
+            let literal = static_prop_literal(name, attr, v, &analysis.prop_types, ctx);
+            let value = crate::static_eval::static_eval(&literal, &empty).unwrap_or(v.clone());
+            props.insert(attr.name.clone(), value);
+        }
+    }
+
+    if analysis.has_slots && !is_static_markup(children) {
+        return Err("its slot content is dynamic".to_string());
+    }
+    let mut scoped_ids = HashSet::new();
+    collect_scopable_ids(&comp.nodes, &mut scoped_ids);
+    if !scoped_ids.is_empty() {
+        return Err("its ids are scoped per instance".to_string());
+    }
+
+    let codes: HashMap<&str, &str> = comp
+        .expressions
+        .iter()
+        .map(|e| (e.id.as_str(), e.code.as_str()))
+        .collect();
+    fold_template(comp.nodes.clone(), &codes, &props)
+}
 
-    resolve_nodes(resolved_template, ctx, depth + 1)
+/// Only elements and text (no expressions, components, or fragments)
+fn is_static_markup(nodes: &[TemplateNode]) -> bool {
+    nodes.iter().all(|node| match node {
+        TemplateNode::Text(_) => true,
+        TemplateNode::Element(el) => {
+            el.attributes
+                .iter()
+                .all(|a| matches!(a.value, crate::validate::AttributeValue::Static(_)))
+                && is_static_markup(&el.children)
+        }
+        _ => false,
+    })
 }
 
-fn rewrite_node_expressions(nodes: &mut Vec<TemplateNode>, id_map: &HashMap<String, String>) {
+/// `nodes` with every expression replaced by its value under `props`
+fn fold_template(
+    nodes: Vec<TemplateNode>,
+    codes: &HashMap<&str, &str>,
+    props: &HashMap<String, String>,
+) -> Result<Vec<TemplateNode>, String> {
+    let fold = |code: &str| {
+        crate::static_eval::static_eval(code, props)
+            .ok_or_else(|| format!("`{}` is not known at build time", code.trim()))
+    };
+    let mut folded = Vec::new();
     for node in nodes {
-        match node {
+        folded.push(match node {
+            TemplateNode::Text(_) | TemplateNode::Component(_) => node,
             TemplateNode::Expression(e) => {
-                if let Some(new_id) = id_map.get(&e.expression) {
-                    e.expression = new_id.clone();
-                }
-                // Note: ExpressionNode only has `expression` (ID string), not the raw code
-                // The actual code lives in ExpressionIR in the expressions array
-            }
-            TemplateNode::Element(elem) => {
-                for attr in &mut elem.attributes {
-                    match &mut attr.value {
-                        crate::validate::AttributeValue::Dynamic(expr) => {
-                            if let Some(new_id) = id_map.get(&expr.id) {
-                                expr.id = new_id.clone();
-                            }
-                            // Symbol renaming in expr.code is now handled in resolve_component_node
-                            // using rename_symbols_safe before pushing to collected_expressions.
-                        }
-                        _ => {}
-                    }
-                }
-                rewrite_node_expressions(&mut elem.children, id_map);
+                let code = codes.get(e.expression.as_str()).copied();
+                TemplateNode::Text(crate::validate::TextNode {
+                    value: fold(code.unwrap_or(&e.expression))?,
+                    location: e.location,
+                    loop_context: None,
+                    ordinal: 0,
+                    extra: e.extra,
+                })
             }
-            TemplateNode::Component(comp) => {
-                for attr in &mut comp.attributes {
-                    match &mut attr.value {
-                        crate::validate::AttributeValue::Dynamic(expr) => {
-                            if let Some(new_id) = id_map.get(&expr.id) {
-                                expr.id = new_id.clone();
-                            }
+            TemplateNode::Element(mut el) => {
+                let mut attributes = Vec::new();
+                for mut attr in el.attributes {
+                    if let crate::validate::AttributeValue::Dynamic(expr) = &attr.value {
+                        let value = fold(&expr.code)?;
+                        // Like the runtime: `false`, `null` and `undefined` remove the attribute
+                        if matches!(value.as_str(), "false" | "null" | "undefined") {
+                            continue;
                         }
-                        _ => {}
+                        attr.value = crate::validate::AttributeValue::Static(value);
                     }
+                    attributes.push(attr);
                 }
-                rewrite_node_expressions(&mut comp.children, id_map);
+                el.attributes = attributes;
+                el.children = fold_template(el.children, codes, props)?;
+                TemplateNode::Element(el)
             }
-            TemplateNode::ConditionalFragment(cf) => {
+            _ => return Err("its template has conditional or loop fragments".to_string()),
+        });
+    }
+    Ok(folded)
+}
+
+/// Prop values of a static (islands mode) instance that are known at build time
+fn record_island_props(
+    instance_suffix: &str,
+    attributes: &[crate::validate::AttributeIR],
+    ctx: &mut ResolutionContext,
+) {
+    let state_values = crate::static_eval::initial_state_values(&ctx.all_states);
+    for attr in attributes {
+        let value = match &attr.value {
+            crate::validate::AttributeValue::Static(s) => Some(s.clone()),
+            crate::validate::AttributeValue::Dynamic(expr) => {
+                crate::static_eval::static_eval(&expr.code, &state_values)
+            }
+        };
+        if let Some(value) = value {
+            ctx.island_props
+                .insert(format!("{}.{}", instance_suffix, attr.name), value);
+        }
+    }
+}
+
+/// Head directive from the static attributes of a `<Head>` component
+fn head_directive(node: &crate::validate::ComponentNode) -> crate::validate::HeadDirective {
+    let mut head_directive = crate::validate::HeadDirective::default();
+
+    for attr in &node.attributes {
+        let value = match &attr.value {
+            crate::validate::AttributeValue::Static(s) => s.clone(),
+            crate::validate::AttributeValue::Dynamic(expr) => {
+                // STRICT HEAD ENFORCEMENT
+                // Attributes on <Head> component must be static.
+                // Try to resolve using static_eval (with empty props for now - strict mode)
+                let empty_props = std::collections::HashMap::new();
+                // We need to resolve expression code.
+                match crate::static_eval::static_eval(&expr.code, &empty_props) {
+                    Some(val) => val,
+                    None => {
+                        // FAIL HARD
+                        format!(
+                            "ZENITH_COMPILE_ERROR: Dynamic head attribute '{}' not allowed",
+                            expr.code
+                        )
+                    }
+                }
+            }
+        };
+
+        match attr.name.as_str() {
+            "title" => head_directive.title = Some(value),
+            "description" => {
+                head_directive.description = Some(value.clone());
+                head_directive.meta.push(crate::validate::MetaTag {
+                    name: Some("description".to_string()),
+                    property: None,
+                    content: value,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    head_directive
+}
+
+pub(crate) fn rewrite_node_expressions(
+    nodes: &mut Vec<TemplateNode>,
+    id_map: &HashMap<String, String>,
+) {
+    for node in nodes {
+        match node {
+            TemplateNode::Expression(e) => {
+                if let Some(new_id) = id_map.get(&e.expression) {
+                    e.expression = new_id.clone();
+                }
+                // Note: ExpressionNode only has `expression` (ID string), not the raw code
+                // The actual code lives in ExpressionIR in the expressions array
+            }
+            TemplateNode::Element(elem) => {
+                for attr in &mut elem.attributes {
+                    match &mut attr.value {
+                        crate::validate::AttributeValue::Dynamic(expr) => {
+                            if let Some(new_id) = id_map.get(&expr.id) {
+                                expr.id = new_id.clone();
+                            }
+                            // Symbol renaming in expr.code is now handled in resolve_component_node
+                            // using rename_symbols_safe before pushing to collected_expressions.
+                        }
+                        _ => {}
+                    }
+                }
+                rewrite_node_expressions(&mut elem.children, id_map);
+            }
+            TemplateNode::Component(comp) => {
+                for attr in &mut comp.attributes {
+                    match &mut attr.value {
+                        crate::validate::AttributeValue::Dynamic(expr) => {
+                            if let Some(new_id) = id_map.get(&expr.id) {
+                                expr.id = new_id.clone();
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                rewrite_node_expressions(&mut comp.children, id_map);
+            }
+            TemplateNode::ConditionalFragment(cf) => {
                 if let Some(new_id) = id_map.get(&cf.condition) {
                     cf.condition = new_id.clone();
                 }
                 rewrite_node_expressions(&mut cf.consequent, id_map);
                 rewrite_node_expressions(&mut cf.alternate, id_map);
             }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for branch in &mut mb.branches {
+                    if let Some(new_id) = id_map.get(&branch.condition) {
+                        branch.condition = new_id.clone();
+                    }
+                }
+                for nodes in mb.children_mut() {
+                    rewrite_node_expressions(nodes, id_map);
+                }
+            }
             TemplateNode::LoopFragment(lf) => {
                 if let Some(new_id) = id_map.get(&lf.source) {
                     lf.source = new_id.clone();
@@ -555,432 +1565,2857 @@ fn rewrite_node_expressions(nodes: &mut Vec<TemplateNode>, id_map: &HashMap<Stri
     }
 }
 
-fn extract_slots(
-    parent_name: &str,
-    children: Vec<TemplateNode>,
-    parent_scope: Option<LoopContext>,
-) -> ResolvedSlots {
-    let mut default = Vec::new();
-    let mut named = HashMap::new();
-
-    for child in children {
-        let mut is_named = false;
+// ═══════════════════════════════════════════════════════════════════════════════
+// DEPENDENCY GRAPH
+// ═══════════════════════════════════════════════════════════════════════════════
 
-        if let TemplateNode::Component(ref comp) = child {
-            if let Some(slot_name) = parse_compound_name(&comp.name, parent_name) {
-                is_named = true;
-                let scoped_children = comp
-                    .children
-                    .iter()
-                    .map(|c| rebind_node_to_scope(c.clone(), &parent_scope))
-                    .collect::<Vec<_>>();
+/// Dependency graph of a page that is not resolved against a registry
+/// (its npm imports and external scripts only).
+pub(crate) fn page_dep_graph(ir: &ZenIR) -> crate::dep_graph::DepGraph {
+    let mut ctx = ResolutionContext::default();
+    let page_id = ctx
+        .dep_graph
+        .node(crate::dep_graph::DepKind::Page, &ir.file_path);
+    let page_script = ir.script.as_ref().map(|s| s.raw.as_str());
+    record_dependencies(&page_id, &ir.template.nodes, page_script, &mut ctx);
+    ctx.dep_graph.build()
+}
 
-                named
-                    .entry(slot_name)
-                    .or_insert_with(Vec::new)
-                    .extend(scoped_children);
-            }
-        }
+/// Graph node for a registry entry: a layout when its template is a document
+/// module or it is part of the page's layout chain, otherwise a component. Entries without a path fall back to the key.
+fn component_dep_node(
+    key: &str,
+    comp: &ComponentIR,
+    is_document: bool,
+    layout_ranks: &HashMap<String, usize>,
+    graph: &mut crate::dep_graph::DepGraphBuilder,
+) -> String {
+    let kind = if is_document || layout_ranks.contains_key(key) {
+        crate::dep_graph::DepKind::Layout
+    } else {
+        crate::dep_graph::DepKind::Component
+    };
+    let path = if comp.path.is_empty() {
+        key
+    } else {
+        comp.path.as_str()
+    };
+    graph.node(kind, path)
+}
 
-        if !is_named {
-            default.push(rebind_node_to_scope(child, &parent_scope));
-        }
+/// Record the references written in one file (once per file): the components
+/// its template uses, plus its npm imports and external scripts.
+fn record_dependencies(
+    owner: &str,
+    nodes: &[TemplateNode],
+    script: Option<&str>,
+    ctx: &mut ResolutionContext,
+) {
+    if !ctx.dep_graph.first_visit(owner) {
+        return;
     }
 
-    ResolvedSlots {
-        default,
-        named,
-        parent_loop_context: parent_scope,
+    let mut refs = crate::dep_graph::TemplateRefs::default();
+    crate::dep_graph::template_refs(nodes, &mut refs);
+    for tag in &refs.components {
+        // Unknown and ambiguous tags are reported by resolution itself
+        if let Ok(Some(key)) = find_component_key(tag, &ctx.components) {
+            let is_document = ctx.component_analysis(&key).is_document;
+            let dep_id = component_dep_node(
+                &key,
+                &ctx.components[&key],
+                is_document,
+                &ctx.layout_ranks,
+                &mut ctx.dep_graph,
+            );
+            ctx.dep_graph.edge(owner, &dep_id);
+        }
     }
+    ctx.dep_graph
+        .record_imports_and_assets(owner, script, nodes);
 }
 
-fn parse_compound_name(component_name: &str, parent_name: &str) -> Option<String> {
-    let prefix = format!("{}.", parent_name);
-    if component_name.starts_with(&prefix) {
-        Some(component_name[prefix.len()..].to_lowercase())
-    } else {
-        None
+// ═══════════════════════════════════════════════════════════════════════════════
+// LAYOUT CHAIN
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Layouts wrapping a page, outermost first. A layout is a component whose
+/// template is rooted at `<html>` or at another layout, so the chain is empty
+/// unless following root components ends in a document. Nesting a layout inside
+/// itself (directly or through others) is an error.
+fn layout_chain(
+    nodes: &[TemplateNode],
+    components: &HashMap<String, ComponentIR>,
+) -> Result<Vec<crate::validate::LayoutLevel>, String> {
+    let mut chain: Vec<crate::validate::LayoutLevel> = Vec::new();
+    let mut nodes = nodes;
+    loop {
+        match root_node(nodes) {
+            Some(TemplateNode::Element(el)) if el.tag.eq_ignore_ascii_case("html") => {
+                chain.reverse();
+                return Ok(chain);
+            }
+            Some(TemplateNode::Component(c)) => {
+                let Ok(Some(key)) = find_component_key(&c.name, components) else {
+                    return Ok(Vec::new());
+                };
+                if let Some(start) = chain.iter().position(|level| level.component == key) {
+                    let cycle: Vec<&str> = chain[start..]
+                        .iter()
+                        .map(|level| level.component.as_str())
+                        .chain([key.as_str()])
+                        .collect();
+                    return Err(format!(
+                        "  [Z-ERR-LAYOUT-CYCLE] Layout `{}` is nested inside itself: {}. A layout may only wrap layouts outside its own chain.",
+                        key,
+                        cycle.join(" → ")
+                    ));
+                }
+                let props = c
+                    .attributes
+                    .iter()
+                    .filter_map(|attr| match &attr.value {
+                        crate::validate::AttributeValue::Static(v) => {
+                            Some((attr.name.clone(), v.clone()))
+                        }
+                        crate::validate::AttributeValue::Dynamic(_) => None,
+                    })
+                    .collect();
+                nodes = components[&key].nodes.as_slice();
+                chain.push(crate::validate::LayoutLevel {
+                    component: key,
+                    props,
+                });
+            }
+            _ => return Ok(Vec::new()),
+        }
     }
 }
 
-fn rebind_node_to_scope(node: TemplateNode, loop_context: &Option<LoopContext>) -> TemplateNode {
-    if loop_context.is_none() {
-        return node;
-    }
-    let _lc = loop_context.as_ref().unwrap();
+/// First node that is not whitespace or a doctype
+fn root_node(nodes: &[TemplateNode]) -> Option<&TemplateNode> {
+    nodes.iter().find(|node| match node {
+        TemplateNode::Text(text) => !text.value.trim().is_empty(),
+        TemplateNode::Doctype(_) => false,
+        _ => true,
+    })
+}
 
-    match node {
-        TemplateNode::Element(mut elem) => {
-            elem.loop_context = merge_loop_context(&elem.loop_context, loop_context);
-            // Attributes rebinding if dynamic?
-            // TS impl: node.attributes.map(attr => ... merge ...)
-            for attr in &mut elem.attributes {
-                attr.loop_context = merge_loop_context(&attr.loop_context, loop_context);
-            }
-            elem.children = elem
-                .children
-                .into_iter()
-                .map(|c| rebind_node_to_scope(c, loop_context))
-                .collect();
-            TemplateNode::Element(elem)
+/// Remove `<Head>` components from a template (at any depth) and return them
+fn take_head_nodes(nodes: &mut Vec<TemplateNode>) -> Vec<crate::validate::ComponentNode> {
+    let mut heads = Vec::new();
+    nodes.retain_mut(|node| match node {
+        TemplateNode::Component(c) if c.name == "Head" => {
+            heads.push(c.clone());
+            false
         }
-        TemplateNode::Component(mut comp) => {
-            comp.loop_context = merge_loop_context(&comp.loop_context, loop_context);
-            comp.children = comp
-                .children
-                .into_iter()
-                .map(|c| rebind_node_to_scope(c, loop_context))
-                .collect();
-            TemplateNode::Component(comp)
+        TemplateNode::Component(c) => {
+            heads.extend(take_head_nodes(&mut c.children));
+            true
         }
-        TemplateNode::Expression(mut expr) => {
-            expr.loop_context = merge_loop_context(&expr.loop_context, loop_context);
-            TemplateNode::Expression(expr)
+        TemplateNode::Element(el) => {
+            heads.extend(take_head_nodes(&mut el.children));
+            true
         }
-        TemplateNode::ConditionalFragment(mut cf) => {
-            cf.loop_context = merge_loop_context(&cf.loop_context, loop_context);
-            cf.consequent = cf
-                .consequent
-                .into_iter()
-                .map(|c| rebind_node_to_scope(c, loop_context))
-                .collect();
-            cf.alternate = cf
-                .alternate
-                .into_iter()
-                .map(|c| rebind_node_to_scope(c, loop_context))
-                .collect();
-            TemplateNode::ConditionalFragment(cf)
+        TemplateNode::ConditionalFragment(cf) => {
+            heads.extend(take_head_nodes(&mut cf.consequent));
+            heads.extend(take_head_nodes(&mut cf.alternate));
+            true
         }
-        TemplateNode::OptionalFragment(mut of) => {
-            of.loop_context = merge_loop_context(&of.loop_context, loop_context);
-            of.fragment = of
-                .fragment
-                .into_iter()
-                .map(|c| rebind_node_to_scope(c, loop_context))
-                .collect();
-            TemplateNode::OptionalFragment(of)
+        TemplateNode::MultiBranchFragment(mb) => {
+            for nodes in mb.children_mut() {
+                heads.extend(take_head_nodes(nodes));
+            }
+            true
         }
-        TemplateNode::LoopFragment(mut lf) => {
-            lf.loop_context = merge_loop_context(&lf.loop_context, loop_context);
-            // Loop Fragment body already has its own scope derived from source,
-            // but if parent scope has vars, they should flow through?
-            // TS impl doesn't explicitly recurse body for loop fragment because the body scope is generated later?
-            // Actually TS `rebindNodeToScope` handles default case as return node.
-            // For LoopFragment, we usually don't rebind because it creates a NEW scope boundary.
-            // But variables from parent scope *should* be available.
-            // mergeLoopContext handles merging variables.
-            // So we should merge, but maybe not recurse if variables are shadowed?
-            // Let's recurse to be safe.
-            lf.body = lf
-                .body
-                .into_iter()
-                .map(|c| rebind_node_to_scope(c, loop_context))
-                .collect();
-            TemplateNode::LoopFragment(lf)
+        TemplateNode::OptionalFragment(of) => {
+            heads.extend(take_head_nodes(&mut of.fragment));
+            true
         }
-        _ => node,
-    }
+        TemplateNode::LoopFragment(lf) => {
+            heads.extend(take_head_nodes(&mut lf.body));
+            true
+        }
+        _ => true,
+    });
+    heads
 }
 
-fn merge_loop_context(
-    existing: &Option<LoopContext>,
-    parent: &Option<LoopContext>,
-) -> Option<LoopContext> {
-    if existing.is_none() && parent.is_none() {
-        return None;
+// ═══════════════════════════════════════════════════════════════════════════════
+// COMPONENT LOOKUP
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Split `ui.Button` / `ui:Button` into (namespace, local name).
+/// The namespace is the run of lowercase segments before the first capitalized
+/// one, so `forms.inputs:Text` is `Text` in `forms.inputs`, `ui.Card.Header` is
+/// `Card.Header` in `ui`, and `Layout.Header` stays a plain name.
+pub fn split_component_name(name: &str) -> (Option<&str>, &str) {
+    let mut split = None;
+    let mut segment_start = 0;
+    for (idx, c) in name.char_indices() {
+        if c != '.' && c != ':' {
+            continue;
+        }
+        if !name[segment_start..].starts_with(|c: char| c.is_ascii_lowercase()) {
+            break;
+        }
+        split = Some(idx);
+        segment_start = idx + 1;
     }
-    if existing.is_none() {
-        return parent.clone();
+    match split {
+        Some(idx) => (Some(&name[..idx]), &name[idx + 1..]),
+        None => (None, name),
     }
-    if parent.is_none() {
-        return existing.clone();
+}
+
+/// `ui.forms` and `ui:forms` name the same namespace
+fn same_namespace(a: Option<&str>, b: Option<&str>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.replace(':', ".") == b.replace(':', "."),
+        (a, b) => a.is_none() && b.is_none(),
     }
+}
 
-    let ex = existing.as_ref().unwrap();
-    let p = parent.as_ref().unwrap();
+/// Namespace and local name of a registry entry (`namespace` field wins over a `ns:` key prefix).
+fn registry_entry_name<'c>(key: &'c str, comp: &'c ComponentIR) -> (Option<&'c str>, &'c str) {
+    let (key_ns, local) = split_component_name(key);
+    (comp.namespace.as_deref().or(key_ns), local)
+}
 
-    let mut vars = ex.variables.clone();
-    for v in &p.variables {
-        if !vars.contains(v) {
-            vars.push(v.clone());
+fn describe_candidates(candidates: &[(&String, &ComponentIR)]) -> String {
+    let mut described: Vec<String> = candidates
+        .iter()
+        .map(|(key, comp)| {
+            if comp.path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{} ({})", key, comp.path)
+            }
+        })
+        .collect();
+    described.sort();
+    described.join(", ")
+}
+
+/// Registry entries whose namespace passes `in_namespace` and whose local name is
+/// `tag_local`; exact case wins over case-insensitive matches
+fn matching_entries<'c>(
+    tag_local: &str,
+    components: &'c HashMap<String, ComponentIR>,
+    in_namespace: impl Fn(Option<&str>) -> bool,
+) -> Vec<(&'c String, &'c ComponentIR)> {
+    let in_scope: Vec<(&String, &ComponentIR, &str)> = components
+        .iter()
+        .filter_map(|(key, comp)| {
+            let (ns, local) = registry_entry_name(key, comp);
+            in_namespace(ns).then_some((key, comp, local))
+        })
+        .collect();
+
+    let exact: Vec<(&String, &ComponentIR)> = in_scope
+        .iter()
+        .filter(|(_, _, local)| *local == tag_local)
+        .map(|(key, comp, _)| (*key, *comp))
+        .collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+    in_scope
+        .iter()
+        .filter(|(_, _, local)| local.eq_ignore_ascii_case(tag_local))
+        .map(|(key, comp, _)| (*key, *comp))
+        .collect()
+}
+
+/// Resolve a tag to its registry key.
+/// Namespaced tags match only entries in that namespace and plain tags only
+/// unnamespaced entries. A plain tag naming only namespaced components, or any
+/// ambiguity left after exact case wins over case-insensitive matches, is an error.
+fn find_component_key(
+    tag: &str,
+    components: &HashMap<String, ComponentIR>,
+) -> Result<Option<String>, String> {
+    let (tag_ns, tag_local) = split_component_name(tag);
+    let candidates = matching_entries(tag_local, components, |ns| same_namespace(ns, tag_ns));
+
+    match candidates.len() {
+        0 if tag_ns.is_none() => {
+            let namespaced = matching_entries(tag_local, components, |ns| ns.is_some());
+            let Some((key, comp)) = namespaced.first() else {
+                return Ok(None);
+            };
+            let ns = registry_entry_name(key, comp).0.unwrap_or_default();
+            Err(format!(
+                "  [Z-ERR-COMPONENT-AMBIGUOUS] <{}> matches only namespaced components: {}. Use a namespaced tag such as <{}.{}>.",
+                tag,
+                describe_candidates(&namespaced),
+                ns,
+                tag_local
+            ))
         }
+        0 | 1 => Ok(candidates.first().map(|(key, _)| key.to_string())),
+        _ => Err(format!(
+            "  [Z-ERR-COMPONENT-AMBIGUOUS] <{}> matches multiple components: {}. Use a namespaced tag such as <ns.{}>.",
+            tag,
+            describe_candidates(&candidates),
+            tag_local
+        )),
     }
+}
 
-    Some(LoopContext {
-        variables: vars,
-        map_source: p.map_source.clone().or(ex.map_source.clone()),
+// ═══════════════════════════════════════════════════════════════════════════════
+// PROP FLOW CHECKING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Usage-site attributes forwarded as HTML rather than consumed as props.
+const FORWARDED_ATTRS: &[&str] = &["class", "classname", "style", "id", "key", "slot", "ref"];
+
+/// DOM events a usage site may attach a handler for (`onclick`, `onClick`)
+const DOM_EVENTS: &[&str] = &[
+    "abort",
+    "animationend",
+    "animationiteration",
+    "animationstart",
+    "auxclick",
+    "beforeinput",
+    "blur",
+    "cancel",
+    "change",
+    "click",
+    "close",
+    "contextmenu",
+    "copy",
+    "cut",
+    "dblclick",
+    "drag",
+    "dragend",
+    "dragenter",
+    "dragleave",
+    "dragover",
+    "dragstart",
+    "drop",
+    "error",
+    "focus",
+    "focusin",
+    "focusout",
+    "input",
+    "invalid",
+    "keydown",
+    "keypress",
+    "keyup",
+    "load",
+    "mousedown",
+    "mouseenter",
+    "mouseleave",
+    "mousemove",
+    "mouseout",
+    "mouseover",
+    "mouseup",
+    "paste",
+    "pointercancel",
+    "pointerdown",
+    "pointerenter",
+    "pointerleave",
+    "pointermove",
+    "pointerout",
+    "pointerover",
+    "pointerup",
+    "reset",
+    "resize",
+    "scroll",
+    "select",
+    "submit",
+    "toggle",
+    "touchcancel",
+    "touchend",
+    "touchmove",
+    "touchstart",
+    "transitionend",
+    "wheel",
+];
+
+fn is_forwarded_attr(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    FORWARDED_ATTRS.contains(&lower.as_str())
+        || lower.starts_with("data-")
+        || lower.starts_with("aria-")
+        || is_event_attr(name)
+}
+
+/// `on:event` listeners and DOM handlers: `on` followed by an uppercase letter
+/// or a known event name. Props that merely start with `on` (`options`,
+/// `online`) are not handlers.
+fn is_event_attr(name: &str) -> bool {
+    if name.starts_with("on:") {
+        return true;
+    }
+    name.strip_prefix("on").is_some_and(|event| {
+        event.starts_with(|c: char| c.is_ascii_uppercase())
+            || DOM_EVENTS.contains(&event.to_ascii_lowercase().as_str())
     })
 }
 
-fn resolve_slots(nodes: Vec<TemplateNode>, slots: &ResolvedSlots) -> Vec<TemplateNode> {
-    let mut resolved = Vec::new();
-    for node in nodes {
-        match node {
-            TemplateNode::Element(ref elem) if elem.tag == "slot" => {
-                // Find name attr
-                let name = elem
-                    .attributes
-                    .iter()
-                    .find(|a| a.name == "name")
-                    .and_then(|a| match &a.value {
-                        crate::validate::AttributeValue::Static(s) => Some(s.clone()),
-                        _ => None,
-                    });
+/// Props declared via `prop x` and `interface Props { ... }`.
+pub(crate) fn declared_props(comp: &ComponentIR) -> HashSet<String> {
+    let mut declared: HashSet<String> = comp.props.iter().cloned().collect();
+    if let Some(script) = &comp.script {
+        declared.extend(crate::parse::extract_props_from_interface(script));
+    }
+    declared
+}
 
-                if let Some(n) = &name {
-                    if let Some(content) = slots.named.get(n) {
-                        resolved.extend(content.clone());
-                        continue;
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b_chars.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b_chars.len() + 1];
+        for (j, cb) in b_chars.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b_chars.len()]
+}
+
+/// Closest declared prop within a small edit distance, for "did you mean" hints.
+pub(crate) fn suggest_prop<'d>(attr: &str, declared: &'d HashSet<String>) -> Option<&'d String> {
+    let attr = attr.to_lowercase();
+    let max_distance = (attr.len() / 3).max(2);
+    declared
+        .iter()
+        .map(|p| (edit_distance(&attr, &p.to_lowercase()), p))
+        .filter(|(d, _)| *d <= max_distance)
+        .min_by(|(da, pa), (db, pb)| da.cmp(db).then_with(|| pa.cmp(pb)))
+        .map(|(_, p)| p)
+}
+
+/// Compare a usage site's attributes against the component's declared props,
+/// recording received props and warning on attributes that match no prop.
+fn check_usage_props(
+    name: &str,
+    comp: &ComponentIR,
+    attributes: &[crate::validate::AttributeIR],
+    ctx: &mut ResolutionContext,
+) {
+    let declared = declared_props(comp);
+    let received = ctx.received_props.entry(name.to_string()).or_default();
+
+    // Attribute names may arrive lowercased from the HTML parser
+    let mut unknown = Vec::new();
+    let mut has_spread = false;
+    for attr in attributes {
+        if attr.name.starts_with("...") {
+            has_spread = true;
+        } else if let Some(prop) = declared.iter().find(|p| p.eq_ignore_ascii_case(&attr.name)) {
+            // A declared prop is a prop even when named like an attribute (`onChange`)
+            received.insert(prop.clone());
+        } else if !is_forwarded_attr(&attr.name) {
+            unknown.push(attr.name.as_str());
+        }
+    }
+
+    if has_spread {
+        // A spread may supply any declared prop
+        received.extend(declared.iter().cloned());
+        ctx.collected_warnings.push(format!(
+            "Z-INFO-SPREAD-PROPS: <{}> receives spread props; unknown-prop checking is disabled for this usage.",
+            name
+        ));
+        return;
+    }
+
+    // Without declarations there is nothing to compare against
+    if declared.is_empty() {
+        return;
+    }
+
+    for attr in unknown {
+        let hint = suggest_prop(attr, &declared)
+            .map(|p| format!(" Did you mean `{}`?", p))
+            .unwrap_or_default();
+        ctx.collected_warnings.push(format!(
+            "Z-WARN-UNKNOWN-PROP: <{}> does not declare prop `{}`; the attribute is ignored.{}",
+            name, attr, hint
+        ));
+    }
+}
+
+/// Resolve conditional props (`name?={expr}`) at a usage site.
+/// Statically nullish values are dropped so the component's own default applies;
+/// dynamic ones are recorded and passed through `?? undefined`.
+fn apply_conditional_props(
+    name: &str,
+    comp: &ComponentIR,
+    attributes: Vec<crate::validate::AttributeIR>,
+    ctx: &mut ResolutionContext,
+) -> Vec<crate::validate::AttributeIR> {
+    let required = required_props(comp.script.as_deref().unwrap_or(""));
+
+    let mut kept = Vec::with_capacity(attributes.len());
+    for attr in attributes {
+        if !attr.optional {
+            kept.push(attr);
+            continue;
+        }
+
+        if required.iter().any(|p| p.eq_ignore_ascii_case(&attr.name)) {
+            ctx.collected_warnings.push(format!(
+                "Z-WARN-CONDITIONAL-REQUIRED-PROP: <{}> may omit `{}` (passed as `{}?=`), but the component declares it without a default.",
+                name, attr.name, attr.name
+            ));
+        }
+
+        match &attr.value {
+            crate::validate::AttributeValue::Dynamic(expr) if is_nullish_literal(&expr.code) => {
+                continue;
+            }
+            crate::validate::AttributeValue::Dynamic(_) => {
+                ctx.conditional_props
+                    .entry(name.to_string())
+                    .or_default()
+                    .insert(attr.name.clone());
+            }
+            crate::validate::AttributeValue::Static(_) => {}
+        }
+        kept.push(attr);
+    }
+    kept
+}
+
+fn is_nullish_literal(code: &str) -> bool {
+    matches!(code.trim(), "null" | "undefined" | "void 0")
+}
+
+/// Props declared in the script without a default value (`prop title` but not `prop size = "md"`)
+fn required_props(script: &str) -> HashSet<String> {
+    PROP_DECL_RE
+        .captures_iter(script)
+        .filter(|c| c.get(2).is_none())
+        .map(|c| c[1].to_string())
+        .collect()
+}
+
+/// Prop types a static attribute string can be coerced to
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PropType {
+    Number,
+    Boolean,
+}
+
+impl PropType {
+    fn name(self) -> &'static str {
+        match self {
+            PropType::Number => "number",
+            PropType::Boolean => "boolean",
+        }
+    }
+}
+
+/// `number` / `boolean` props declared via `prop x: T` or `interface Props { x: T }`.
+/// Any other type (including mixed unions) leaves the prop untyped.
+fn declared_prop_types(script: &str) -> HashMap<String, PropType> {
+    let interface_body = INTERFACE_PROPS_RE
+        .captures(script)
+        .and_then(|c| c.get(1))
+        .map_or("", |m| m.as_str());
+
+    PROP_TYPE_RE
+        .captures_iter(script)
+        .chain(INTERFACE_MEMBER_RE.captures_iter(interface_body))
+        .filter_map(|c| classify_prop_type(&c[2]).map(|ty| (c[1].to_string(), ty)))
+        .collect()
+}
+
+/// `number`, `boolean` and literal `true | false` unions, ignoring `undefined` / `null` members
+fn classify_prop_type(ty: &str) -> Option<PropType> {
+    let mut kinds = ty
+        .split('|')
+        .map(str::trim)
+        .filter(|m| !matches!(*m, "undefined" | "null"))
+        .map(|m| match m {
+            "number" => Some(PropType::Number),
+            "boolean" | "true" | "false" => Some(PropType::Boolean),
+            _ => None,
+        });
+    let first = kinds.next()??;
+    kinds.all(|k| k == Some(first)).then_some(first)
+}
+
+/// Literal for a static value passed to a prop declared as `ty`.
+/// A bare boolean attribute (`<Modal open>`) arrives as an empty string and means `true`.
+fn coerce_typed(value: &str, ty: PropType) -> Option<String> {
+    let value = value.trim();
+    match ty {
+        PropType::Number => value
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .map(|n| n.to_string()),
+        PropType::Boolean => match value {
+            "true" | "" => Some("true".to_string()),
+            "false" => Some("false".to_string()),
+            _ => None,
+        },
+    }
+}
+
+/// Conservative coercion for untyped props: exact `true`/`false`, or an integer
+/// without leading zeros that fits in a JS safe integer (`"007"` stays a string).
+fn coerce_untyped(value: &str) -> Option<(String, PropType)> {
+    if value == "true" || value == "false" {
+        return Some((value.to_string(), PropType::Boolean));
+    }
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    let is_integer = !digits.is_empty()
+        && digits.len() <= 15
+        && digits.bytes().all(|b| b.is_ascii_digit())
+        && (digits == "0" || !digits.starts_with('0'));
+    is_integer.then(|| (value.to_string(), PropType::Number))
+}
+
+/// JS value for a static prop in the instance's props registration. Typed
+/// `number`/`boolean` props become literals (erroring when the value does not
+/// parse); untyped props only when `coerce_static_props` is on. Everything else
+/// stays a string.
+fn static_prop_literal(
+    component: &str,
+    attr: &crate::validate::AttributeIR,
+    value: &str,
+    types: &HashMap<String, PropType>,
+    ctx: &mut ResolutionContext,
+) -> String {
+    // Attribute names may arrive lowercased from the HTML parser
+    let declared = types
+        .iter()
+        .find(|(prop, _)| prop.eq_ignore_ascii_case(&attr.name))
+        .map(|(_, ty)| *ty);
+
+    let coerced = match declared {
+        Some(ty) => {
+            let literal = coerce_typed(value, ty);
+            if literal.is_none() {
+                ctx.collected_errors.push(format!(
+                    "  [Z-ERR-PROP-TYPE] <{}> prop `{}` is declared as {} but received \"{}\" (line {}, column {}). Pass a {} literal or an expression ({}={{...}}).",
+                    component,
+                    attr.name,
+                    ty.name(),
+                    value,
+                    attr.location.line,
+                    attr.location.column,
+                    ty.name(),
+                    attr.name
+                ));
+            }
+            literal.map(|l| (l, ty))
+        }
+        None if ctx.coerce_static_props => coerce_untyped(value),
+        None => None,
+    };
+
+    match coerced {
+        Some((literal, ty)) => {
+            ctx.coerced_props
+                .entry(component.to_string())
+                .or_default()
+                .insert(format!("{}: {}", attr.name, ty.name()));
+            literal
+        }
+        None => format!("\"{}\"", value),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// COMPONENT EVENTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Usage-site attribute prefix that subscribes to a component event (`on:select={handler}`)
+const EVENT_ATTR_PREFIX: &str = "on:";
+
+/// Events passed to a `zenDispatch()` dispatcher (`dispatch('select', item)`) in the
+/// script or template expressions; sorted and unique.
+fn dispatched_events(script: Option<&str>, expressions: &[ExpressionIR]) -> Vec<String> {
+    let Some(script) = script else {
+        return Vec::new();
+    };
+    let mut events = std::collections::BTreeSet::new();
+    for decl in DISPATCHER_DECL_RE.captures_iter(script) {
+        let call_re = regex::Regex::new(&format!(
+            r#"(?:^|[^\w$.]){}\s*\(\s*['"`]([^'"`]+)['"`]"#,
+            regex::escape(&decl[1])
+        ))
+        .unwrap();
+        let sources = std::iter::once(script).chain(expressions.iter().map(|e| e.code.as_str()));
+        for code in sources {
+            events.extend(call_re.captures_iter(code).map(|c| c[1].to_string()));
+        }
+    }
+    events.into_iter().collect()
+}
+
+/// Declared events plus any dispatched by the component's own code
+/// (registry entries built outside `from_source` may not list them).
+pub(crate) fn emitted_events(comp: &ComponentIR) -> Vec<String> {
+    let mut events = comp.events.clone();
+    events.extend(dispatched_events(comp.script.as_deref(), &comp.expressions));
+    events.sort();
+    events.dedup();
+    events
+}
+
+/// Warn on `on:` handlers for events the component never dispatches.
+fn check_usage_events(
+    name: &str,
+    events: &[String],
+    event_attrs: &[crate::validate::AttributeIR],
+    ctx: &mut ResolutionContext,
+) {
+    let declared: HashSet<String> = events.iter().cloned().collect();
+    for attr in event_attrs {
+        let event = &attr.name[EVENT_ATTR_PREFIX.len()..];
+        if declared.contains(event) {
+            continue;
+        }
+        let hint = suggest_prop(event, &declared)
+            .map(|e| format!(" Did you mean `{}{}`?", EVENT_ATTR_PREFIX, e))
+            .unwrap_or_default();
+        ctx.collected_warnings.push(format!(
+            "Z-WARN-UNKNOWN-EVENT: <{}> does not emit `{}`; the `{}` handler is never called.{}",
+            name, event, attr.name, hint
+        ));
+    }
+}
+
+/// Compare the compound children at a usage site (`<Card.Footer>`) against the
+/// slots the component's template declares. Content for an undeclared slot is
+/// dropped by slot resolution, so it warns (errors with `strict_slots`); default
+/// content for a component without an unnamed `<slot>` is reported as info.
+fn check_usage_slots(
+    name: &str,
+    analysis: &ComponentAnalysis,
+    children: &[TemplateNode],
+    ctx: &mut ResolutionContext,
+) {
+    let declared: HashSet<String> = analysis.named_slots.iter().cloned().collect();
+    let mut has_default_content = false;
+    for child in children {
+        let marker = match child {
+            TemplateNode::Component(c) => parse_compound_name(&c.name, name).map(|s| (c, s)),
+            _ => None,
+        };
+        let Some((marker, slot)) = marker else {
+            has_default_content |= !is_blank(child);
+            continue;
+        };
+        if declared.contains(&slot) {
+            continue;
+        }
+        let mut available: Vec<String> = analysis
+            .named_slots
+            .iter()
+            .map(|s| format!("`{}`", s))
+            .collect();
+        if analysis.has_default_slot {
+            available.insert(0, "the default slot".to_string());
+        }
+        let available = if available.is_empty() {
+            "it declares no slots".to_string()
+        } else {
+            format!("it declares {}", available.join(", "))
+        };
+        let hint = suggest_prop(&slot, &declared)
+            .map(|s| format!(" Did you mean `<{}.{}>`?", name, s))
+            .unwrap_or_default();
+        let message = format!(
+            "<{}>{} passes content to slot `{}`, which <{}> does not declare; {} and the content is dropped.{}",
+            marker.name,
+            tag_location(&marker.location),
+            slot,
+            name,
+            available,
+            hint
+        );
+        if ctx.strict_slots {
+            ctx.collected_errors
+                .push(format!("Z-ERR-UNKNOWN-SLOT: {}", message));
+        } else {
+            ctx.collected_warnings
+                .push(format!("Z-WARN-UNKNOWN-SLOT: {}", message));
+        }
+    }
+    if has_default_content && !analysis.has_default_slot {
+        ctx.collected_warnings.push(format!(
+            "Z-INFO-NO-DEFAULT-SLOT: <{}> has no unnamed <slot>; the children passed to it outside compound slot tags are not rendered.",
+            name
+        ));
+    }
+}
+
+fn is_blank(node: &TemplateNode) -> bool {
+    matches!(node, TemplateNode::Text(t) if t.value.trim().is_empty())
+}
+
+/// A compound slot tag (`<Card.Footer>`) is only meaningful as a direct child of
+/// its component; anywhere else it would render as an unknown element.
+fn check_orphan_slot_marker(node: &crate::validate::ComponentNode, ctx: &mut ResolutionContext) {
+    let Some((parent, _)) = node.name.rsplit_once('.') else {
+        return;
+    };
+    if !matches!(find_component_key(parent, &ctx.components), Ok(Some(_))) {
+        return;
+    }
+    ctx.collected_errors.push(format!(
+        "{}: <{}>{} in {} is not a direct child of <{}>, so it fills no slot; move it directly inside <{}>.",
+        crate::validate::INV_ORPHAN_SLOT_MARKER,
+        node.name,
+        tag_location(&node.location),
+        ctx.file_path,
+        parent,
+        parent
+    ));
+}
+
+/// `__locals.__events` entries for an instance: each handler is compiled in the
+/// parent's scope and called with the dispatched payload.
+fn event_handlers_js(
+    event_attrs: &[crate::validate::AttributeIR],
+    ctx: &ResolutionContext,
+) -> Option<String> {
+    let states: HashSet<String> = ctx.all_states.keys().cloned().collect();
+    let entries: Vec<String> = event_attrs
+        .iter()
+        .filter_map(|attr| match &attr.value {
+            crate::validate::AttributeValue::Dynamic(expr) => {
+                let handler =
+                    rename_event_handler(&expr.code, &states, &ctx.all_props, &ctx.page_locals);
+                Some(format!(
+                    "    \"{}\": (__payload) => ({})(__payload)",
+                    &attr.name[EVENT_ATTR_PREFIX.len()..],
+                    handler
+                ))
+            }
+            crate::validate::AttributeValue::Static(_) => None,
+        })
+        .collect();
+    if entries.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "  __locals.__events = {{\n{}\n  }};\n",
+        entries.join(",\n")
+    ))
+}
+
+/// `zenDispatch()` for a component's `__run()`: dispatchers look up the handler a
+/// usage site registered for the event, so events without one are no-ops.
+const DISPATCH_HELPER_JS: &str = "    const zenDispatch = () => (event, payload) => {\n      const handler = scope.locals.__events && scope.locals.__events[event];\n      if (typeof handler === 'function') handler(payload);\n    };\n";
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// INSTANCE ID SCOPING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Opt-out marker: elements carrying this attribute keep their `id` verbatim.
+const GLOBAL_ID_ATTR: &str = "data-zen-global-id";
+
+/// Mark a component's own elements for its scoped styles (see `css_scope.rs`)
+fn mark_style_scope(nodes: &mut [TemplateNode], comp: &ComponentIR, ctx: &mut ResolutionContext) {
+    if let Some(strategy) = ctx.css_scope.filter(|_| !comp.styles.is_empty()) {
+        let class = ctx
+            .class_names
+            .allocate(crate::class_names::SCOPE_PURPOSE, &comp.path, "");
+        crate::css_scope::mark_elements(
+            nodes,
+            &crate::css_scope::scope_id(&comp.path),
+            &class,
+            strategy,
+        );
+    }
+}
+
+/// Attributes holding a single id reference.
+const ID_REF_ATTRS: &[&str] = &["for", "list"];
+
+/// Attributes holding a whitespace-separated list of id references.
+const ID_LIST_ATTRS: &[&str] = &["aria-labelledby", "aria-describedby", "aria-controls"];
+
+/// Suffix static `id` attributes declared inside a component template with the
+/// instance suffix, along with same-component references to them.
+/// Inlining a component twice would otherwise emit duplicate ids.
+fn scope_component_ids(nodes: &mut Vec<TemplateNode>, instance_suffix: &str) {
+    let mut local_ids = HashSet::new();
+    collect_scopable_ids(nodes, &mut local_ids);
+    if !local_ids.is_empty() {
+        rewrite_id_references(nodes, &local_ids, instance_suffix);
+    }
+}
+
+fn static_attr_value<'n>(
+    attributes: &'n [crate::validate::AttributeIR],
+    name: &str,
+) -> Option<&'n str> {
+    attributes
+        .iter()
+        .find(|a| a.name == name)
+        .and_then(|a| match &a.value {
+            crate::validate::AttributeValue::Static(s) => Some(s.as_str()),
+            _ => None,
+        })
+}
+
+fn collect_scopable_ids(nodes: &[TemplateNode], ids: &mut HashSet<String>) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(elem) => {
+                let is_global = elem.attributes.iter().any(|a| a.name == GLOBAL_ID_ATTR);
+                if !is_global {
+                    if let Some(id) = static_attr_value(&elem.attributes, "id") {
+                        if !id.is_empty() {
+                            ids.insert(id.to_string());
+                        }
                     }
-                } else {
-                    // Default slot
-                    if !slots.default.is_empty() {
-                        resolved.extend(slots.default.clone());
-                        continue;
+                }
+                collect_scopable_ids(&elem.children, ids);
+            }
+            TemplateNode::Component(comp) => collect_scopable_ids(&comp.children, ids),
+            TemplateNode::ConditionalFragment(cf) => {
+                collect_scopable_ids(&cf.consequent, ids);
+                collect_scopable_ids(&cf.alternate, ids);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    collect_scopable_ids(nodes, ids);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => collect_scopable_ids(&of.fragment, ids),
+            TemplateNode::LoopFragment(lf) => collect_scopable_ids(&lf.body, ids),
+            _ => {}
+        }
+    }
+}
+
+fn rewrite_id_references(nodes: &mut Vec<TemplateNode>, ids: &HashSet<String>, suffix: &str) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(elem) => {
+                let is_global = elem.attributes.iter().any(|a| a.name == GLOBAL_ID_ATTR);
+                for attr in &mut elem.attributes {
+                    if let crate::validate::AttributeValue::Static(value) = &mut attr.value {
+                        let name = attr.name.to_lowercase();
+                        if name == "id" && is_global {
+                            continue;
+                        }
+                        if let Some(rewritten) = scope_id_reference(&name, value, ids, suffix) {
+                            *value = rewritten;
+                        }
+                    }
+                }
+                rewrite_id_references(&mut elem.children, ids, suffix);
+            }
+            TemplateNode::Component(comp) => rewrite_id_references(&mut comp.children, ids, suffix),
+            TemplateNode::ConditionalFragment(cf) => {
+                rewrite_id_references(&mut cf.consequent, ids, suffix);
+                rewrite_id_references(&mut cf.alternate, ids, suffix);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children_mut() {
+                    rewrite_id_references(nodes, ids, suffix);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => {
+                rewrite_id_references(&mut of.fragment, ids, suffix)
+            }
+            TemplateNode::LoopFragment(lf) => rewrite_id_references(&mut lf.body, ids, suffix),
+            _ => {}
+        }
+    }
+}
+
+/// Returns the instance-scoped value for an id-bearing attribute, or None if untouched.
+fn scope_id_reference(
+    attr_name: &str,
+    value: &str,
+    ids: &HashSet<String>,
+    suffix: &str,
+) -> Option<String> {
+    if attr_name == "id" || ID_REF_ATTRS.contains(&attr_name) {
+        if ids.contains(value) {
+            return Some(format!("{}-{}", value, suffix));
+        }
+    } else if ID_LIST_ATTRS.contains(&attr_name) {
+        if value.split_whitespace().any(|id| ids.contains(id)) {
+            let scoped: Vec<String> = value
+                .split_whitespace()
+                .map(|id| {
+                    if ids.contains(id) {
+                        format!("{}-{}", id, suffix)
+                    } else {
+                        id.to_string()
+                    }
+                })
+                .collect();
+            return Some(scoped.join(" "));
+        }
+    } else if attr_name == "href" {
+        if let Some(fragment) = value.strip_prefix('#') {
+            if ids.contains(fragment) {
+                return Some(format!("#{}-{}", fragment, suffix));
+            }
+        }
+    }
+    None
+}
+
+/// Returns static ids that occur more than once in the resolved tree, sorted by id.
+/// Branches of a conditional are mutually exclusive, so they are counted separately.
+pub fn find_duplicate_ids(nodes: &[TemplateNode]) -> Vec<(String, usize)> {
+    fn count(nodes: &[TemplateNode], counts: &mut HashMap<String, usize>) {
+        for node in nodes {
+            match node {
+                TemplateNode::Element(elem) => {
+                    if let Some(id) = static_attr_value(&elem.attributes, "id") {
+                        if !id.is_empty() {
+                            *counts.entry(id.to_string()).or_insert(0) += 1;
+                        }
+                    }
+                    count(&elem.children, counts);
+                }
+                TemplateNode::Component(comp) => count(&comp.children, counts),
+                TemplateNode::ConditionalFragment(cf) => {
+                    let mut consequent = HashMap::new();
+                    let mut alternate = HashMap::new();
+                    count(&cf.consequent, &mut consequent);
+                    count(&cf.alternate, &mut alternate);
+                    for (id, c) in alternate {
+                        let entry = consequent.entry(id).or_insert(0);
+                        *entry = (*entry).max(c);
+                    }
+                    for (id, c) in consequent {
+                        *counts.entry(id).or_insert(0) += c;
+                    }
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    // Only one branch renders: the largest count of each id
+                    let mut most = HashMap::new();
+                    for nodes in mb.children() {
+                        let mut branch = HashMap::new();
+                        count(nodes, &mut branch);
+                        for (id, c) in branch {
+                            let entry = most.entry(id).or_insert(0);
+                            *entry = (*entry).max(c);
+                        }
+                    }
+                    for (id, c) in most {
+                        *counts.entry(id).or_insert(0) += c;
                     }
                 }
+                TemplateNode::OptionalFragment(of) => count(&of.fragment, counts),
+                TemplateNode::LoopFragment(lf) => count(&lf.body, counts),
+                _ => {}
+            }
+        }
+    }
+
+    let mut counts = HashMap::new();
+    count(nodes, &mut counts);
+    let mut duplicates: Vec<(String, usize)> = counts.into_iter().filter(|(_, c)| *c > 1).collect();
+    duplicates.sort();
+    duplicates
+}
+
+fn extract_slots(
+    parent_name: &str,
+    children: Vec<TemplateNode>,
+    parent_scope: Option<LoopContext>,
+) -> ResolvedSlots {
+    let mut default = Vec::new();
+    let mut named = HashMap::new();
+
+    for child in children {
+        let mut is_named = false;
+
+        if let TemplateNode::Component(ref comp) = child {
+            if let Some(slot_name) = parse_compound_name(&comp.name, parent_name) {
+                is_named = true;
+                let scoped_children = comp
+                    .children
+                    .iter()
+                    .map(|c| rebind_node_to_scope(c.clone(), &parent_scope))
+                    .collect::<Vec<_>>();
+
+                named
+                    .entry(slot_name)
+                    .or_insert_with(Vec::new)
+                    .extend(scoped_children);
+            }
+        }
+
+        if !is_named {
+            default.push(rebind_node_to_scope(child, &parent_scope));
+        }
+    }
+
+    ResolvedSlots {
+        default,
+        named,
+        parent_loop_context: parent_scope,
+    }
+}
+
+fn parse_compound_name(component_name: &str, parent_name: &str) -> Option<String> {
+    let prefix = format!("{}.", parent_name);
+    if component_name.starts_with(&prefix) {
+        Some(component_name[prefix.len()..].to_lowercase())
+    } else {
+        None
+    }
+}
+
+pub(crate) fn rebind_node_to_scope(
+    node: TemplateNode,
+    loop_context: &Option<LoopContext>,
+) -> TemplateNode {
+    if loop_context.is_none() {
+        return node;
+    }
+    let _lc = loop_context.as_ref().unwrap();
+
+    match node {
+        TemplateNode::Element(mut elem) => {
+            elem.loop_context = merge_loop_context(&elem.loop_context, loop_context);
+            // Attributes rebinding if dynamic?
+            // TS impl: node.attributes.map(attr => ... merge ...)
+            for attr in &mut elem.attributes {
+                attr.loop_context = merge_loop_context(&attr.loop_context, loop_context);
+            }
+            elem.children = elem
+                .children
+                .into_iter()
+                .map(|c| rebind_node_to_scope(c, loop_context))
+                .collect();
+            TemplateNode::Element(elem)
+        }
+        TemplateNode::Component(mut comp) => {
+            comp.loop_context = merge_loop_context(&comp.loop_context, loop_context);
+            comp.children = comp
+                .children
+                .into_iter()
+                .map(|c| rebind_node_to_scope(c, loop_context))
+                .collect();
+            TemplateNode::Component(comp)
+        }
+        TemplateNode::Expression(mut expr) => {
+            expr.loop_context = merge_loop_context(&expr.loop_context, loop_context);
+            TemplateNode::Expression(expr)
+        }
+        TemplateNode::ConditionalFragment(mut cf) => {
+            cf.loop_context = merge_loop_context(&cf.loop_context, loop_context);
+            cf.consequent = cf
+                .consequent
+                .into_iter()
+                .map(|c| rebind_node_to_scope(c, loop_context))
+                .collect();
+            cf.alternate = cf
+                .alternate
+                .into_iter()
+                .map(|c| rebind_node_to_scope(c, loop_context))
+                .collect();
+            TemplateNode::ConditionalFragment(cf)
+        }
+        TemplateNode::MultiBranchFragment(mut mb) => {
+            mb.loop_context = merge_loop_context(&mb.loop_context, loop_context);
+            for nodes in mb.children_mut() {
+                *nodes = std::mem::take(nodes)
+                    .into_iter()
+                    .map(|c| rebind_node_to_scope(c, loop_context))
+                    .collect();
+            }
+            TemplateNode::MultiBranchFragment(mb)
+        }
+        TemplateNode::OptionalFragment(mut of) => {
+            of.loop_context = merge_loop_context(&of.loop_context, loop_context);
+            of.fragment = of
+                .fragment
+                .into_iter()
+                .map(|c| rebind_node_to_scope(c, loop_context))
+                .collect();
+            TemplateNode::OptionalFragment(of)
+        }
+        TemplateNode::LoopFragment(mut lf) => {
+            lf.loop_context = merge_loop_context(&lf.loop_context, loop_context);
+            // Loop Fragment body already has its own scope derived from source,
+            // but if parent scope has vars, they should flow through?
+            // TS impl doesn't explicitly recurse body for loop fragment because the body scope is generated later?
+            // Actually TS `rebindNodeToScope` handles default case as return node.
+            // For LoopFragment, we usually don't rebind because it creates a NEW scope boundary.
+            // But variables from parent scope *should* be available.
+            // mergeLoopContext handles merging variables.
+            // So we should merge, but maybe not recurse if variables are shadowed?
+            // Let's recurse to be safe.
+            lf.body = lf
+                .body
+                .into_iter()
+                .map(|c| rebind_node_to_scope(c, loop_context))
+                .collect();
+            TemplateNode::LoopFragment(lf)
+        }
+        _ => node,
+    }
+}
+
+fn merge_loop_context(
+    existing: &Option<LoopContext>,
+    parent: &Option<LoopContext>,
+) -> Option<LoopContext> {
+    if existing.is_none() && parent.is_none() {
+        return None;
+    }
+    if existing.is_none() {
+        return parent.clone();
+    }
+    if parent.is_none() {
+        return existing.clone();
+    }
+
+    let ex = existing.as_ref().unwrap();
+    let p = parent.as_ref().unwrap();
+
+    let mut vars = ex.variables.clone();
+    for v in &p.variables {
+        if !vars.contains(v) {
+            vars.push(v.clone());
+        }
+    }
+
+    Some(LoopContext {
+        variables: vars,
+        map_source: p.map_source.clone().or(ex.map_source.clone()),
+    })
+}
+
+fn resolve_slots(nodes: Vec<TemplateNode>, slots: &ResolvedSlots) -> Vec<TemplateNode> {
+    let mut resolved = Vec::new();
+    for node in nodes {
+        match node {
+            TemplateNode::Element(ref elem) if elem.tag == "slot" => {
+                // Find name attr
+                let name = elem
+                    .attributes
+                    .iter()
+                    .find(|a| a.name == "name")
+                    .and_then(|a| match &a.value {
+                        crate::validate::AttributeValue::Static(s) => Some(s.clone()),
+                        _ => None,
+                    });
+
+                if let Some(n) = &name {
+                    if let Some(content) = slots.named.get(&n.to_lowercase()) {
+                        resolved.extend(content.clone());
+                        continue;
+                    }
+                } else {
+                    // Default slot
+                    if !slots.default.is_empty() {
+                        resolved.extend(slots.default.clone());
+                        continue;
+                    }
+                }
+
+                // Z-ERR-ORPHAN-SLOT: Slot has no content provided and no fallback
+                if elem.children.is_empty() {
+                    eprintln!(
+                        "  [Z-ERR-ORPHAN-SLOT] Unresolved slot '{}'. Ensure children are provided or add fallback content.",
+                        name.as_deref().unwrap_or("default")
+                    );
+                }
+
+                // Fallback content (if any)
+                resolved.extend(resolve_slots(elem.children.clone(), slots));
+            }
+            TemplateNode::Element(mut elem) => {
+                elem.children = resolve_slots(elem.children, slots);
+                resolved.push(TemplateNode::Element(elem));
+            }
+            // A slot passed down to a nested component (e.g. a layout wrapping another
+            // layout) still belongs to this component's caller
+            TemplateNode::Component(mut c) => {
+                c.children = resolve_slots(c.children, slots);
+                resolved.push(TemplateNode::Component(c));
+            }
+            TemplateNode::ConditionalFragment(mut cf) => {
+                cf.consequent = resolve_slots(cf.consequent, slots);
+                cf.alternate = resolve_slots(cf.alternate, slots);
+                resolved.push(TemplateNode::ConditionalFragment(cf));
+            }
+            TemplateNode::OptionalFragment(mut of) => {
+                of.fragment = resolve_slots(of.fragment, slots);
+                resolved.push(TemplateNode::OptionalFragment(of));
+            }
+            TemplateNode::LoopFragment(mut lf) => {
+                lf.body = resolve_slots(lf.body, slots);
+                resolved.push(TemplateNode::LoopFragment(lf));
+            }
+            _ => resolved.push(node),
+        }
+    }
+    resolved
+}
+
+lazy_static::lazy_static! {
+    /// `state x` / `prop x` in declaration position (line start or after `;` / `{`)
+    static ref ZEN_DECL_RE: regex::Regex =
+        regex::Regex::new(r"(?m)(^|[;{])(\s*)(?:state|prop)(\s+)([\p{ID_Start}_$\[{])").unwrap();
+    /// `prop name`, `prop name: Type`, `prop name = default` (group 2 marks a default)
+    static ref PROP_DECL_RE: regex::Regex =
+        regex::Regex::new(r"(?m)^\s*prop\s+([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)\s*(?::[^=;\n]+)?(=)?").unwrap();
+    /// `prop name: Type` (group 2 is the type annotation)
+    static ref PROP_TYPE_RE: regex::Regex =
+        regex::Regex::new(r"(?m)^\s*prop\s+([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)\s*\??\s*:\s*([^=;\n]+)").unwrap();
+    /// Body of `interface Props { ... }`
+    static ref INTERFACE_PROPS_RE: regex::Regex =
+        regex::Regex::new(r"(?s)interface\s+Props\s*\{([^}]*)\}").unwrap();
+    /// `name: Type` / `name?: Type` members of an interface body
+    static ref INTERFACE_MEMBER_RE: regex::Regex =
+        regex::Regex::new(r"([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)\s*\??\s*:\s*([^;,\n]+)").unwrap();
+    /// `const dispatch = zenDispatch()` (group 1 is the dispatcher's name)
+    static ref DISPATCHER_DECL_RE: regex::Regex =
+        regex::Regex::new(r"\b(?:const|let|var)\s+([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)\s*=\s*zenDispatch\s*\(\s*\)").unwrap();
+}
+
+/// Rewrite Zenith `state`/`prop` declaration keywords to `let` so Oxc can parse the script.
+/// Only keywords in declaration position are touched; `state ` inside strings, identifiers
+/// (`mystate `) or property access is left alone, as are the author's own `let` declarations.
+pub(crate) fn zen_keywords_to_let(code: &str) -> String {
+    ZEN_DECL_RE
+        .replace_all(code, "${1}${2}let${3}${4}")
+        .to_string()
+}
+
+/// `zen_keywords_to_let` padded with spaces, so offsets into the result are
+/// offsets into `code`
+pub(crate) fn zen_keywords_to_let_in_place(code: &str) -> String {
+    ZEN_DECL_RE
+        .replace_all(code, |cap: &regex::Captures| {
+            let keyword = cap[0].len() - cap[1].len() - cap[2].len() - cap[3].len() - cap[4].len();
+            format!(
+                "{}{}let{}{}{}",
+                &cap[1],
+                &cap[2],
+                " ".repeat(keyword - 3),
+                &cap[3],
+                &cap[4]
+            )
+        })
+        .to_string()
+}
+
+/// Robust symbol renaming using Oxc parser.
+/// Renames identifiers in `code` based on `rename_map`.
+/// Avoids renaming object properties (e.g. `obj.prop`).
+pub fn rename_symbols_safe(
+    code: &str,
+    state_bindings: &HashSet<String>,
+    prop_bindings: &HashSet<String>,
+    local_bindings: &HashSet<String>,
+    external_locals: &HashSet<String>,
+    disallow_reactive_access: bool,
+    allow_prop_fallback: bool,
+) -> (String, Vec<String>, Vec<String>) {
+    rename_symbols_with(
+        code,
+        state_bindings,
+        prop_bindings,
+        local_bindings,
+        external_locals,
+        disallow_reactive_access,
+        allow_prop_fallback,
+        false,
+    )
+}
+
+/// Rename a parent's event handler (`on:select={...}`), where state mutation is allowed.
+/// Returns the handler as a bare expression.
+fn rename_event_handler(
+    code: &str,
+    state_bindings: &HashSet<String>,
+    prop_bindings: &HashSet<String>,
+    local_bindings: &HashSet<String>,
+) -> String {
+    let (renamed, _, _) = rename_symbols_with(
+        code,
+        state_bindings,
+        prop_bindings,
+        local_bindings,
+        &HashSet::new(),
+        false,
+        false,
+        true,
+    );
+    renamed.trim().trim_end_matches(';').to_string()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn rename_symbols_with(
+    code: &str,
+    state_bindings: &HashSet<String>,
+    prop_bindings: &HashSet<String>,
+    local_bindings: &HashSet<String>,
+    external_locals: &HashSet<String>,
+    disallow_reactive_access: bool,
+    allow_prop_fallback: bool,
+    is_event_handler: bool,
+) -> (String, Vec<String>, Vec<String>) {
+    // (code, imports, errors)
+    zen_trace!(
+        code,
+        state = ?state_bindings,
+        props = ?prop_bindings,
+        locals = ?local_bindings,
+        "renaming script identifiers"
+    );
+    if state_bindings.is_empty() && prop_bindings.is_empty() && local_bindings.is_empty() {
+        return (code.to_string(), Vec::new(), Vec::new());
+    }
+
+    // Preprocess: Replace `state`/`prop` declarations with `let` so Oxc can parse Zenith's custom keywords
+    let parsable_code = zen_keywords_to_let(code);
+    let _used_preprocessing = parsable_code != code;
+
+    let allocator = Allocator::default();
+    let source_type = SourceType::default()
+        .with_module(true)
+        .with_typescript(true)
+        .with_jsx(true);
+    let mut ret = Parser::new(&allocator, &parsable_code, source_type).parse();
+    if !ret.errors.is_empty() {
+        return (code.to_string(), Vec::new(), Vec::new());
+    }
+
+    rename_program(
+        &allocator,
+        &mut ret.program,
+        state_bindings,
+        prop_bindings,
+        local_bindings,
+        external_locals,
+        disallow_reactive_access,
+        allow_prop_fallback,
+        is_event_handler,
+    )
+}
+
+/// Rename an already parsed program and print it back
+#[allow(clippy::too_many_arguments)]
+fn rename_program<'a>(
+    allocator: &'a Allocator,
+    program: &mut oxc_ast::ast::Program<'a>,
+    state_bindings: &HashSet<String>,
+    prop_bindings: &HashSet<String>,
+    local_bindings: &HashSet<String>,
+    external_locals: &HashSet<String>,
+    disallow_reactive_access: bool,
+    allow_prop_fallback: bool,
+    is_event_handler: bool,
+) -> (String, Vec<String>, Vec<String>) {
+    let mut renamer = ScriptRenamer::with_categories(
+        allocator,
+        state_bindings.clone(),
+        prop_bindings.clone(),
+        local_bindings.clone(),
+        external_locals.clone(),
+    );
+    renamer.disallow_reactive_access = disallow_reactive_access;
+    renamer.allow_prop_fallback = allow_prop_fallback;
+    renamer.is_event_handler = is_event_handler;
+    renamer.visit_program(program);
+
+    let result = Codegen::new().build(program).code;
+
+    (result, renamer.collected_imports, renamer.errors)
+}
+
+/// Locals of a component script (declarations that are neither props nor state)
+/// and the script renamed for its `__run()` thunk, from a single parse
+pub(crate) fn analyze_component_script(
+    comp: &ComponentIR,
+    script: &str,
+) -> crate::script_cache::ParsedScript {
+    #[cfg(test)]
+    crate::script_cache::SCRIPT_PARSES.with(|count| count.set(count.get() + 1));
+
+    let state_bindings: &HashSet<String> = &comp.states.keys().cloned().collect();
+    let prop_bindings: &HashSet<String> = &comp.props.iter().cloned().collect();
+    let parsed = |locals, (renamed, imports, errors)| crate::script_cache::ParsedScript {
+        locals,
+        renamed,
+        imports,
+        errors,
+    };
+
+    let parsable_script = zen_keywords_to_let(script);
+    let allocator = Allocator::default();
+    let source_type = SourceType::default()
+        .with_module(true)
+        .with_typescript(true)
+        .with_jsx(true);
+    let mut ret = Parser::new(&allocator, &parsable_script, source_type).parse();
+    if !ret.errors.is_empty() {
+        // Declarations before the error still resolve; scope errors are suppressed
+        let errors = crate::script_syntax::script_errors(
+            &ret,
+            &parsable_script,
+            script,
+            &comp.template,
+            &comp.path,
+            true,
+        );
+        let locals = declared_names(&ret.program.body)
+            .into_iter()
+            .filter(|decl| !prop_bindings.contains(decl) && !state_bindings.contains(decl))
+            .collect();
+        return parsed(locals, (script.to_string(), Vec::new(), errors));
+    }
+
+    let locals: HashSet<String> = declared_names(&ret.program.body)
+        .into_iter()
+        .filter(|decl| !prop_bindings.contains(decl) && !state_bindings.contains(decl))
+        .collect();
+    if state_bindings.is_empty() && prop_bindings.is_empty() && locals.is_empty() {
+        return parsed(locals, (script.to_string(), Vec::new(), Vec::new()));
+    }
+
+    let renamed = rename_program(
+        &allocator,
+        &mut ret.program,
+        state_bindings,
+        prop_bindings,
+        &locals,
+        &HashSet::new(), // Component-level external locals (usually none)
+        true,            // Phase A7: Disallow reactive access in __run()
+        false,           // Script context: NO prop fallback
+        false,
+    );
+    parsed(locals, renamed)
+}
+
+fn get_local_declarations(script: &str) -> HashSet<String> {
+    // Preprocess: Replace `state`/`prop` declarations with `let` so Oxc can parse Zenith's custom keywords
+    let parsable_script = zen_keywords_to_let(script);
+
+    let allocator = Allocator::default();
+    let source_type = SourceType::default()
+        .with_module(true)
+        .with_typescript(true)
+        .with_jsx(true);
+
+    let parser = Parser::new(&allocator, &parsable_script, source_type);
+    let ret = parser.parse();
+
+    if !ret.errors.is_empty() {
+        eprintln!("[Zenith ERROR] Failed to parse component script for local discovery:");
+        for err in &ret.errors {
+            eprintln!("  - {}", err.message);
+        }
+        return HashSet::new();
+    }
+
+    declared_names(&ret.program.body)
+}
+
+/// Names declared at the top level of a program
+fn declared_names(body: &[Statement]) -> HashSet<String> {
+    let mut symbols = HashSet::new();
+    for stmt in body {
+        match stmt {
+            Statement::VariableDeclaration(var_decl) => {
+                for decl in &var_decl.declarations {
+                    collect_binding_pattern(&decl.id, &mut symbols);
+                }
+            }
+            Statement::FunctionDeclaration(func_decl) => {
+                if let Some(id) = &func_decl.id {
+                    symbols.insert(id.name.to_string());
+                }
+            }
+            Statement::ClassDeclaration(class_decl) => {
+                if let Some(id) = &class_decl.id {
+                    symbols.insert(id.name.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    symbols
+}
+
+fn collect_binding_pattern(pattern: &oxc_ast::ast::BindingPattern, symbols: &mut HashSet<String>) {
+    match pattern {
+        oxc_ast::ast::BindingPattern::BindingIdentifier(id) => {
+            symbols.insert(id.name.to_string());
+        }
+        oxc_ast::ast::BindingPattern::ObjectPattern(obj) => {
+            for prop in &obj.properties {
+                collect_binding_pattern(&prop.value, symbols);
+            }
+            if let Some(rest) = &obj.rest {
+                collect_binding_pattern(&rest.argument, symbols);
+            }
+        }
+        oxc_ast::ast::BindingPattern::ArrayPattern(arr) => {
+            for elem in &arr.elements {
+                if let Some(pattern) = elem {
+                    collect_binding_pattern(pattern, symbols);
+                }
+            }
+            if let Some(rest) = &arr.rest {
+                collect_binding_pattern(&rest.argument, symbols);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::{ElementNode, SourceLocation, TemplateNode};
+    use std::cell::Cell;
+
+    thread_local! {
+        /// Analyze every instance afresh, as resolution did before the cache
+        pub(super) static ANALYSIS_CACHE_DISABLED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    fn resolve_components(
+        ir: ZenIR,
+        components_map: HashMap<String, serde_json::Value>,
+    ) -> Result<ZenIR, String> {
+        resolve_components_with_options(ir, components_map, &ResolveOptions::default())
+    }
+
+    fn mock_loc() -> SourceLocation {
+        SourceLocation { line: 1, column: 1 }
+    }
+
+    #[test]
+    fn test_extract_slots_default() {
+        let children = vec![TemplateNode::Element(ElementNode {
+            tag: "div".to_string(),
+            attributes: vec![],
+            children: vec![],
+            location: mock_loc(),
+            loop_context: None,
+            ordinal: 0,
+            extra: Default::default(),
+        })];
+
+        let slots = extract_slots("Card", children, None);
+        assert_eq!(slots.default.len(), 1);
+        assert!(slots.named.is_empty());
+    }
+
+    #[test]
+    fn test_extract_slots_named() {
+        let header_node = TemplateNode::Component(crate::validate::ComponentNode {
+            name: "Card.Header".to_string(),
+            attributes: vec![],
+            children: vec![TemplateNode::Element(ElementNode {
+                tag: "h1".to_string(),
+                attributes: vec![],
+                children: vec![],
+                location: mock_loc(),
+                loop_context: None,
+                ordinal: 0,
+                extra: Default::default(),
+            })],
+            location: mock_loc(),
+            loop_context: None,
+            extra: Default::default(),
+        });
+
+        let children = vec![header_node];
+
+        let slots = extract_slots("Card", children, None);
+        assert!(slots.default.is_empty());
+        assert_eq!(slots.named.get("header").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rename_symbols_simple() {
+        let code = "const x = a + b;";
+        let mut state = HashSet::new();
+        let mut props = HashSet::new();
+        let locals = HashSet::new();
+        state.insert("a".to_string());
+        props.insert("b".to_string());
+
+        let (renamed, _, _) =
+            rename_symbols_safe(code, &state, &props, &locals, &HashSet::new(), false, false);
+        assert!(renamed.contains("state.a"));
+        assert!(renamed.contains("props.b"));
+    }
+
+    #[test]
+    fn test_rename_symbols_object_property() {
+        let code = "const obj = { a: a, b: 2 };";
+        let mut state = HashSet::new();
+        state.insert("a".to_string());
+        let props = HashSet::new();
+        let locals = HashSet::new();
+
+        let (renamed, _, _) =
+            rename_symbols_safe(code, &state, &props, &locals, &HashSet::new(), false, false);
+        assert!(
+            renamed.contains("a: scope.state.a"),
+            "Expected scope.state.a but got: {}",
+            renamed
+        );
+    }
+
+    #[test]
+    fn test_rename_symbols_shorthand() {
+        let code = "const obj = { a };";
+        let mut state = HashSet::new();
+        state.insert("a".to_string());
+        let props = HashSet::new();
+        let locals = HashSet::new();
+
+        let (renamed, _, _) =
+            rename_symbols_safe(code, &state, &props, &locals, &HashSet::new(), false, false);
+        assert!(
+            renamed.contains("a: scope.state.a"),
+            "Expected scope.state.a but got: {}",
+            renamed
+        );
+    }
+
+    #[test]
+    fn test_zen_keywords_only_rewritten_in_declaration_position() {
+        let code = "state x = 1\nlet y = 2\nconst label = \"state of the art\"; prop title\nconst mystate = y";
+        assert_eq!(
+            zen_keywords_to_let(code),
+            "let x = 1\nlet y = 2\nconst label = \"state of the art\"; let title\nconst mystate = y"
+        );
+    }
+
+    #[test]
+    fn test_rename_symbols_mixed_state_and_let() {
+        let code =
+            "state x = 1\nlet y = 2\nconst label = \"state of the art\"\nconst total = x + y";
+        let mut state = HashSet::new();
+        state.insert("x".to_string());
+        let props = HashSet::new();
+        let mut locals = HashSet::new();
+        locals.insert("y".to_string());
+
+        let (renamed, _, errors) =
+            rename_symbols_safe(code, &state, &props, &locals, &HashSet::new(), false, false);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(renamed.contains("scope.state.x = 1"), "got: {}", renamed);
+        assert!(renamed.contains("scope.locals.y = 2"), "got: {}", renamed);
+        // `state` inside a string is not a declaration keyword
+        assert!(renamed.contains("\"state of the art\""), "got: {}", renamed);
+        assert!(
+            renamed.contains("scope.state.x + scope.locals.y"),
+            "got: {}",
+            renamed
+        );
+    }
+
+    #[test]
+    fn test_rename_symbols_multibyte_identifiers() {
+        let code = "let 合計 = 状態 + 1\nconst label = `${合計}件`\nconst ß = 合計 * 2";
+        let state: HashSet<String> = ["状態".to_string()].into();
+        let locals: HashSet<String> = ["合計".to_string(), "ß".to_string()].into();
+
+        let (renamed, _, errors) = rename_symbols_safe(
+            code,
+            &state,
+            &HashSet::new(),
+            &locals,
+            &HashSet::new(),
+            false,
+            false,
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(
+            renamed.contains("scope.locals.合計 = scope.state.状態 + 1"),
+            "got: {}",
+            renamed
+        );
+        assert!(
+            renamed.contains("`${scope.locals.合計}件`"),
+            "got: {}",
+            renamed
+        );
+        assert!(
+            renamed.contains("scope.locals.ß = scope.locals.合計 * 2"),
+            "got: {}",
+            renamed
+        );
+    }
+
+    #[test]
+    fn test_unicode_state_and_prop_names() {
+        let counter = ComponentIR::from_source(
+            "Counter",
+            "components/Counter.zen",
+            "<script>\nstate 状態カウント = 0\nprop ラベル\n</script>\n<p>{ラベル}: {状態カウント}</p>",
+        )
+        .unwrap();
+        assert_eq!(counter.props, ["ラベル"]);
+        assert_eq!(
+            counter.states.get("状態カウント").map(String::as_str),
+            Some("0")
+        );
+
+        let result = crate::parse::compile_zen_internal(
+            "<script>\nstate 状態カウント = 1\n</script>\n<main><p>{状態カウント + 1}</p><button onclick={() => 状態カウント += 1}>+</button><Counter ラベル=\"件数\" /></main>",
+            "page.zen",
+            crate::parse::CompileOptions {
+                components: [(
+                    "Counter".to_string(),
+                    serde_json::to_value(counter).unwrap(),
+                )]
+                .into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        let manifest = result.manifest.unwrap();
+        assert!(
+            manifest
+                .expressions
+                .contains("const v = (scope.state.状態カウント + 1);"),
+            "{}",
+            manifest.expressions
+        );
+        assert!(
+            manifest.bundle.contains("deps: ['状態カウント'] });"),
+            "{}",
+            manifest.bundle
+        );
+
+        // The instance reads its own scope
+        let instance: Vec<&str> = result
+            .bindings
+            .iter()
+            .filter(|b| b.origin.is_some())
+            .map(|b| b.expression.trim())
+            .collect();
+        assert_eq!(
+            instance,
+            [
+                "window.__ZENITH_SCOPES__[\"inst0\"].props.ラベル;",
+                "window.__ZENITH_SCOPES__[\"inst0\"].state.状態カウント;"
+            ]
+        );
+    }
+
+    fn field_component() -> serde_json::Value {
+        serde_json::json!({
+            "name": "Field",
+            "nodes": [
+                {
+                    "type": "element",
+                    "tag": "label",
+                    "attributes": [{ "name": "for", "value": "email" }],
+                    "children": []
+                },
+                {
+                    "type": "element",
+                    "tag": "input",
+                    "attributes": [
+                        { "name": "id", "value": "email" },
+                        { "name": "aria-describedby", "value": "email-hint external" }
+                    ],
+                    "children": []
+                },
+                {
+                    "type": "element",
+                    "tag": "p",
+                    "attributes": [
+                        { "name": "id", "value": "skip" },
+                        { "name": "data-zen-global-id", "value": "" }
+                    ],
+                    "children": []
+                }
+            ]
+        })
+    }
+
+    fn page_with_fields(count: usize) -> ZenIR {
+        let usages: Vec<serde_json::Value> = (0..count)
+            .map(|_| {
+                serde_json::json!({
+                    "type": "component",
+                    "name": "Field",
+                    "attributes": [],
+                    "children": []
+                })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "filePath": "page.zen",
+            "template": { "raw": "", "nodes": usages, "expressions": [] },
+            "script": null,
+            "styles": []
+        }))
+        .unwrap()
+    }
+
+    fn static_attrs(nodes: &[TemplateNode], tag: &str, attr: &str) -> Vec<String> {
+        nodes
+            .iter()
+            .filter_map(|n| match n {
+                TemplateNode::Element(el) if el.tag == tag => {
+                    static_attr_value(&el.attributes, attr).map(|v| v.to_string())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_component_ids_scoped_per_instance() {
+        let mut components = HashMap::new();
+        components.insert("Field".to_string(), field_component());
+
+        let ir = resolve_components(page_with_fields(2), components).unwrap();
+        let nodes = &ir.template.nodes;
+
+        assert_eq!(
+            static_attrs(nodes, "input", "id"),
+            vec!["email-inst0", "email-inst1"]
+        );
+        assert_eq!(
+            static_attrs(nodes, "label", "for"),
+            vec!["email-inst0", "email-inst1"]
+        );
+        // Unknown references inside an id list are left alone
+        assert_eq!(
+            static_attrs(nodes, "input", "aria-describedby")[0],
+            "email-hint external"
+        );
+    }
+
+    fn card_component() -> serde_json::Value {
+        serde_json::json!({
+            "name": "Card",
+            "props": ["title", "subtitle"],
+            "script": "prop title\nprop subtitle",
+            "nodes": [{ "type": "element", "tag": "div", "attributes": [], "children": [] }]
+        })
+    }
+
+    fn page_with_card(attributes: serde_json::Value) -> ZenIR {
+        page_using("Card", attributes)
+    }
+
+    fn page_using(tag: &str, attributes: serde_json::Value) -> ZenIR {
+        serde_json::from_value(serde_json::json!({
+            "filePath": "page.zen",
+            "template": {
+                "raw": "",
+                "nodes": [{
+                    "type": "component",
+                    "name": tag,
+                    "attributes": attributes,
+                    "children": []
+                }],
+                "expressions": []
+            },
+            "script": null,
+            "styles": []
+        }))
+        .unwrap()
+    }
+
+    fn warnings_with(ir: &ZenIR, code: &str) -> Vec<String> {
+        ir.warnings
+            .iter()
+            .filter(|w| w.starts_with(code))
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_unknown_prop_warns_with_suggestion() {
+        let mut components = HashMap::new();
+        components.insert("Card".to_string(), card_component());
+
+        let page = page_with_card(serde_json::json!([
+            { "name": "titel", "value": "Hi" },
+            { "name": "class", "value": "wide" },
+            { "name": "onclick", "value": "go" }
+        ]));
+        let ir = resolve_components(page, components).unwrap();
+
+        let unknown = warnings_with(&ir, "Z-WARN-UNKNOWN-PROP");
+        assert_eq!(unknown.len(), 1);
+        assert!(unknown[0].contains("`titel`"));
+        assert!(unknown[0].contains("Did you mean `title`?"));
+        assert_eq!(
+            ir.unused_props.get("Card"),
+            Some(&vec!["subtitle".to_string(), "title".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_props_starting_with_on_are_not_handlers() {
+        let mut components = HashMap::new();
+        components.insert("Card".to_string(), card_component());
+
+        let page = page_with_card(serde_json::json!([
+            { "name": "options", "value": "a,b" },
+            { "name": "online", "value": "true" },
+            { "name": "onchange", "value": "save" },
+            { "name": "on:select", "value": "pick" }
+        ]));
+        let ir = resolve_components(page, components).unwrap();
+
+        let unknown = warnings_with(&ir, "Z-WARN-UNKNOWN-PROP");
+        assert_eq!(unknown.len(), 2, "{:?}", unknown);
+        assert!(unknown[0].contains("`options`"));
+        assert!(unknown[1].contains("`online`"));
+        assert!(is_event_attr("onClick"));
+        assert!(!is_event_attr("one"));
+    }
+
+    #[test]
+    fn test_spread_disables_unknown_prop_check() {
+        let mut components = HashMap::new();
+        components.insert("Card".to_string(), card_component());
+
+        let page = page_with_card(serde_json::json!([
+            { "name": "...rest", "value": "" },
+            { "name": "bogus", "value": "x" }
+        ]));
+        let ir = resolve_components(page, components).unwrap();
+
+        assert!(warnings_with(&ir, "Z-WARN-UNKNOWN-PROP").is_empty());
+        assert_eq!(warnings_with(&ir, "Z-INFO-SPREAD-PROPS").len(), 1);
+        assert!(!ir.unused_props.contains_key("Card"));
+    }
+
+    #[test]
+    fn test_global_id_opt_out_preserved() {
+        let mut components = HashMap::new();
+        components.insert("Field".to_string(), field_component());
+
+        let ir = resolve_components(page_with_fields(2), components).unwrap();
+
+        assert_eq!(
+            static_attrs(&ir.template.nodes, "p", "id"),
+            vec!["skip", "skip"]
+        );
+        assert_eq!(ir.warnings.len(), 1);
+        assert!(ir.warnings[0].contains("Z-WARN-A11Y-DUPLICATE-ID"));
+        assert!(ir.warnings[0].contains("\"skip\""));
+    }
+
+    fn button_component(path: &str, namespace: Option<&str>) -> serde_json::Value {
+        serde_json::json!({
+            "name": "Button",
+            "path": path,
+            "namespace": namespace,
+            "nodes": [{ "type": "element", "tag": "button", "attributes": [], "children": [] }]
+        })
+    }
+
+    #[test]
+    fn test_ambiguous_component_name_errors() {
+        let mut components = HashMap::new();
+        components.insert(
+            "ui:Button".to_string(),
+            button_component("node_modules/ui/Button.zen", None),
+        );
+        components.insert(
+            "kit:Button".to_string(),
+            button_component("node_modules/kit/Button.zen", None),
+        );
+
+        let err = resolve_components(page_using("Button", serde_json::json!([])), components)
+            .unwrap_err();
+        assert!(err.contains("Z-ERR-COMPONENT-AMBIGUOUS"));
+        assert!(err.contains("kit:Button (node_modules/kit/Button.zen)"));
+        assert!(err.contains("ui:Button (node_modules/ui/Button.zen)"));
+    }
+
+    #[test]
+    fn test_namespaced_tag_resolves() {
+        let mut components = HashMap::new();
+        components.insert(
+            "Button".to_string(),
+            button_component("src/components/Button.zen", None),
+        );
+        components.insert(
+            "ui:Button".to_string(),
+            button_component("node_modules/ui/Button.zen", None),
+        );
+
+        // Namespaced tag picks the package component
+        let ir = resolve_components(
+            page_using("ui.Button", serde_json::json!([])),
+            components.clone(),
+        )
+        .unwrap();
+        assert_eq!(ir.component_usages.len(), 1);
+        assert_eq!(ir.component_usages[0].tag, "ui.Button");
+        assert_eq!(ir.component_usages[0].path, "node_modules/ui/Button.zen");
+
+        // A plain tag matches only the local (unnamespaced) component
+        let ir =
+            resolve_components(page_using("Button", serde_json::json!([])), components).unwrap();
+        assert_eq!(ir.component_usages[0].component, "Button");
+        assert_eq!(ir.component_usages[0].path, "src/components/Button.zen");
+    }
+
+    #[test]
+    fn test_plain_tag_ignores_namespaced_components() {
+        let mut components = HashMap::new();
+        components.insert(
+            "ui:Button".to_string(),
+            button_component("node_modules/ui/Button.zen", None),
+        );
+
+        let err = resolve_components(page_using("Button", serde_json::json!([])), components)
+            .unwrap_err();
+        assert!(err.contains("Z-ERR-COMPONENT-AMBIGUOUS"));
+        assert!(err.contains("ui:Button (node_modules/ui/Button.zen)"));
+        assert!(err.contains("<ui.Button>"));
+    }
+
+    #[test]
+    fn test_nested_namespace_splits_on_last_separator() {
+        assert_eq!(
+            split_component_name("forms.inputs:Text"),
+            (Some("forms.inputs"), "Text")
+        );
+        assert_eq!(
+            split_component_name("ui.Card.Header"),
+            (Some("ui"), "Card.Header")
+        );
+        assert_eq!(
+            split_component_name("Layout.Header"),
+            (None, "Layout.Header")
+        );
+
+        let mut components = HashMap::new();
+        components.insert(
+            "forms.inputs:Text".to_string(),
+            button_component("node_modules/forms/inputs/Text.zen", None),
+        );
+        components.insert(
+            "forms:Text".to_string(),
+            button_component("node_modules/forms/Text.zen", None),
+        );
+
+        let ir = resolve_components(
+            page_using("forms.inputs.Text", serde_json::json!([])),
+            components,
+        )
+        .unwrap();
+        assert_eq!(ir.component_usages.len(), 1);
+        assert_eq!(
+            ir.component_usages[0].path,
+            "node_modules/forms/inputs/Text.zen"
+        );
+    }
+
+    #[test]
+    fn test_case_collision_errors() {
+        let mut components = HashMap::new();
+        components.insert("Navbar".to_string(), button_component("a/Navbar.zen", None));
+        components.insert("NavBar".to_string(), button_component("b/NavBar.zen", None));
+
+        let err = resolve_components(page_using("NAVBAR", serde_json::json!([])), components)
+            .unwrap_err();
+        assert!(err.contains("Z-ERR-COMPONENT-AMBIGUOUS"));
+        assert!(err.contains("<NAVBAR>"));
+    }
+
+    fn sized_component() -> serde_json::Value {
+        serde_json::json!({
+            "name": "Badge",
+            "props": ["label", "size"],
+            "script": "prop label\nprop size = \"md\"",
+            "nodes": [{ "type": "element", "tag": "span", "attributes": [], "children": [] }]
+        })
+    }
+
+    fn merged_script(ir: &ZenIR) -> &str {
+        ir.script.as_ref().map(|s| s.raw.as_str()).unwrap_or("")
+    }
+
+    #[test]
+    fn test_conditional_prop_static_nullish_is_omitted() {
+        let mut components = HashMap::new();
+        components.insert("Badge".to_string(), sized_component());
+
+        let page = page_using(
+            "Badge",
+            serde_json::json!([
+                { "name": "size", "value": { "code": "undefined" }, "optional": true }
+            ]),
+        );
+        let ir = resolve_components(page, components).unwrap();
+
+        assert!(!merged_script(&ir).contains("\"size\":"));
+        assert!(ir.conditional_props.is_empty());
+        assert!(warnings_with(&ir, "Z-WARN-CONDITIONAL-REQUIRED-PROP").is_empty());
+    }
+
+    #[test]
+    fn test_conditional_prop_dynamic_falls_back_to_undefined() {
+        let mut components = HashMap::new();
+        components.insert("Badge".to_string(), sized_component());
+
+        let page = page_using(
+            "Badge",
+            serde_json::json!([
+                { "name": "size", "value": { "code": "chosen" }, "optional": true }
+            ]),
+        );
+        let ir = resolve_components(page, components).unwrap();
+
+        assert!(merged_script(&ir).contains("\"size\": (chosen) ?? undefined"));
+        assert_eq!(ir.conditional_props["Badge"], vec!["size".to_string()]);
+    }
+
+    #[test]
+    fn test_conditional_prop_compiles_to_a_valid_bundle() {
+        let badge = ComponentIR::from_source(
+            "Badge",
+            "Badge.zen",
+            "<script>\nprop text\n</script>\n<span>{text}</span>",
+        )
+        .unwrap();
+        let options = crate::parse::CompileOptions {
+            components: HashMap::from([(
+                "Badge".to_string(),
+                serde_json::to_value(badge).unwrap(),
+            )]),
+            ..Default::default()
+        };
+        let result = crate::parse::compile_zen_internal(
+            "<script>\nstate x = \"hi\"\n</script>\n<main><Badge text?={x} /></main>",
+            "page.zen",
+            options,
+        )
+        .unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+
+        let bundle = result.manifest.unwrap().bundle;
+        assert!(
+            bundle.contains("__props[\"text\"] = scope.state.x ?? undefined;"),
+            "{}",
+            bundle
+        );
+        assert!(
+            bundle.contains("window.__ZENITH_SCOPES__[\"inst0\"]"),
+            "{}",
+            bundle
+        );
+        let allocator = Allocator::default();
+        let ret = Parser::new(&allocator, &bundle, oxc_span::SourceType::mjs()).parse();
+        assert!(ret.errors.is_empty(), "{:?}\n{}", ret.errors, bundle);
+    }
+
+    #[test]
+    fn test_conditional_required_prop_warns() {
+        let mut components = HashMap::new();
+        components.insert("Badge".to_string(), sized_component());
+
+        let page = page_using(
+            "Badge",
+            serde_json::json!([
+                { "name": "label", "value": { "code": "maybeLabel" }, "optional": true }
+            ]),
+        );
+        let ir = resolve_components(page, components).unwrap();
+
+        let warnings = warnings_with(&ir, "Z-WARN-CONDITIONAL-REQUIRED-PROP");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("`label`"));
+    }
+
+    fn pagination_component(script: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": "Pagination",
+            "props": ["pageSize", "open"],
+            "script": script,
+            "nodes": [{ "type": "element", "tag": "nav", "attributes": [], "children": [] }]
+        })
+    }
+
+    #[test]
+    fn test_typed_number_prop_is_coerced() {
+        let mut components = HashMap::new();
+        components.insert(
+            "Pagination".to_string(),
+            pagination_component("prop pageSize: number = 20"),
+        );
+
+        let page = page_using(
+            "Pagination",
+            serde_json::json!([{ "name": "pageSize", "value": "10" }]),
+        );
+        let ir = resolve_components(page, components).unwrap();
+
+        assert!(merged_script(&ir).contains("\"pageSize\": 10"));
+        assert_eq!(
+            ir.coerced_props["Pagination"],
+            vec!["pageSize: number".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_typed_boolean_prop_from_interface() {
+        let mut components = HashMap::new();
+        components.insert(
+            "Pagination".to_string(),
+            pagination_component("interface Props { open?: boolean; pageSize: number }"),
+        );
+
+        let page = page_using(
+            "Pagination",
+            serde_json::json!([{ "name": "open", "value": "false" }]),
+        );
+        let ir = resolve_components(page, components).unwrap();
+
+        assert!(merged_script(&ir).contains("\"open\": false"));
+        assert!(!merged_script(&ir).contains("\"open\": \"false\""));
+    }
+
+    #[test]
+    fn test_untyped_prop_heuristic_is_opt_in() {
+        let attributes = serde_json::json!([
+            { "name": "title", "value": "42" },
+            { "name": "subtitle", "value": "007" }
+        ]);
+
+        let mut components = HashMap::new();
+        components.insert("Card".to_string(), card_component());
+        let ir =
+            resolve_components(page_with_card(attributes.clone()), components.clone()).unwrap();
+        assert!(merged_script(&ir).contains("\"title\": \"42\""));
+        assert!(ir.coerced_props.is_empty());
+
+        let options = ResolveOptions {
+            coerce_static_props: true,
+            ..Default::default()
+        };
+        let ir = resolve_components_with_options(page_with_card(attributes), components, &options)
+            .unwrap();
+        assert!(merged_script(&ir).contains("\"title\": 42"));
+        // Leading zeros are not a number the author meant
+        assert!(merged_script(&ir).contains("\"subtitle\": \"007\""));
+        assert_eq!(ir.coerced_props["Card"], vec!["title: number".to_string()]);
+    }
+
+    #[test]
+    fn test_unparseable_typed_prop_errors() {
+        let mut components = HashMap::new();
+        components.insert(
+            "Pagination".to_string(),
+            pagination_component("prop pageSize: number"),
+        );
 
-                // Z-ERR-ORPHAN-SLOT: Slot has no content provided and no fallback
-                if elem.children.is_empty() {
-                    eprintln!(
-                        "  [Z-ERR-ORPHAN-SLOT] Unresolved slot '{}'. Ensure children are provided or add fallback content.",
-                        name.as_deref().unwrap_or("default")
-                    );
-                }
+        let page = page_using(
+            "Pagination",
+            serde_json::json!([{
+                "name": "pageSize",
+                "value": "ten",
+                "location": { "line": 4, "column": 13 }
+            }]),
+        );
+        let err = resolve_components(page, components).unwrap_err();
 
-                // Fallback content (if any)
-                resolved.extend(resolve_slots(elem.children.clone(), slots));
-            }
-            TemplateNode::Element(mut elem) => {
-                elem.children = resolve_slots(elem.children, slots);
-                resolved.push(TemplateNode::Element(elem));
-            }
-            // Recurse other types...
-            _ => resolved.push(node),
-        }
+        assert!(err.contains("Z-ERR-PROP-TYPE"));
+        assert!(err.contains("declared as number but received \"ten\""));
+        assert!(err.contains("line 4, column 13"));
     }
-    resolved
-}
 
-/// Robust symbol renaming using Oxc parser.
-/// Renames identifiers in `code` based on `rename_map`.
-/// Avoids renaming object properties (e.g. `obj.prop`).
-pub fn rename_symbols_safe(
-    code: &str,
-    state_bindings: &HashSet<String>,
-    prop_bindings: &HashSet<String>,
-    local_bindings: &HashSet<String>,
-    external_locals: &HashSet<String>,
-    disallow_reactive_access: bool,
-    allow_prop_fallback: bool,
-) -> (String, Vec<String>, Vec<String>) {
-    // (code, imports, errors)
-    /*
-    eprintln!("[Zenith RENAME] Input code: {}", code);
-    eprintln!("[Zenith RENAME] State: {:?}", state_bindings);
-    eprintln!("[Zenith RENAME] Props: {:?}", prop_bindings);
-    eprintln!("[Zenith RENAME] Locals: {:?}", local_bindings);
-    */
-    if state_bindings.is_empty() && prop_bindings.is_empty() && local_bindings.is_empty() {
-        return (code.to_string(), Vec::new(), Vec::new());
+    /// Layout whose whole template is another layout wrapping a slot
+    fn wrapping_layout(name: &str, inner: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "nodes": [{
+                "type": "component",
+                "name": inner,
+                "attributes": [],
+                "children": [{ "type": "element", "tag": "slot", "attributes": [], "children": [] }]
+            }]
+        })
     }
 
-    // Preprocess: Replace "state " and "prop " with "let " so Oxc can parse Zenith's custom keywords
-    let parsable_code = code.replace("state ", "let ").replace("prop ", "let ");
-    let _used_preprocessing = parsable_code != code;
+    #[test]
+    fn test_layout_cycle_errors() {
+        let mut components = HashMap::new();
+        components.insert(
+            "OuterLayout".to_string(),
+            wrapping_layout("OuterLayout", "InnerLayout"),
+        );
+        components.insert(
+            "InnerLayout".to_string(),
+            wrapping_layout("InnerLayout", "OuterLayout"),
+        );
 
-    let allocator = Allocator::default();
-    let source_type = SourceType::default()
-        .with_module(true)
-        .with_typescript(true)
-        .with_jsx(true);
-    let mut ret = Parser::new(&allocator, &parsable_code, source_type).parse();
-    if !ret.errors.is_empty() {
-        return (code.to_string(), Vec::new(), Vec::new());
+        let err = resolve_components(page_using("InnerLayout", serde_json::json!([])), components)
+            .unwrap_err();
+        assert!(err.contains("Z-ERR-LAYOUT-CYCLE"));
+        assert!(err.contains("InnerLayout → OuterLayout → InnerLayout"));
     }
 
-    let mut renamer = ScriptRenamer::with_categories(
-        &allocator,
-        state_bindings.clone(),
-        prop_bindings.clone(),
-        local_bindings.clone(),
-        external_locals.clone(),
-    );
-    renamer.disallow_reactive_access = disallow_reactive_access;
-    renamer.allow_prop_fallback = allow_prop_fallback;
-    renamer.visit_program(&mut ret.program);
+    #[test]
+    fn test_head_merge_inner_wins() {
+        let mut outer = crate::validate::HeadDirective {
+            title: Some("Base".to_string()),
+            ..Default::default()
+        };
+        outer.meta.push(crate::validate::MetaTag {
+            name: Some("description".to_string()),
+            property: None,
+            content: "Base site".to_string(),
+        });
+        let mut inner = crate::validate::HeadDirective::default();
+        inner.meta.push(crate::validate::MetaTag {
+            name: Some("description".to_string()),
+            property: None,
+            content: "Docs".to_string(),
+        });
 
-    let result = Codegen::new().build(&ret.program).code;
+        outer.merge(inner);
+        assert_eq!(outer.title.as_deref(), Some("Base"));
+        assert_eq!(outer.meta.len(), 1);
+        assert_eq!(outer.meta[0].content, "Docs");
+    }
 
-    (result, renamer.collected_imports, renamer.errors)
-}
+    #[test]
+    fn test_required_props_skip_defaults() {
+        let required = required_props("prop title\nprop size: string = \"md\"\nprop count: number");
+        assert!(required.contains("title"));
+        assert!(required.contains("count"));
+        assert!(!required.contains("size"));
+    }
 
-fn get_local_declarations(script: &str) -> HashSet<String> {
-    // Preprocess: Replace "state " and "prop " with "let " so Oxc can parse Zenith's custom keywords
-    let parsable_script = script.replace("state ", "let ").replace("prop ", "let ");
+    fn item_row_component() -> serde_json::Value {
+        serde_json::json!({
+            "name": "ItemRow",
+            "props": ["label"],
+            "script": "prop label\nconst dispatch = zenDispatch()\nfunction choose() { dispatch('select', label) }",
+            "expressions": [{ "id": "expr_0", "code": "() => dispatch(\"remove\", label)" }],
+            "nodes": [{ "type": "element", "tag": "li", "attributes": [], "children": [] }]
+        })
+    }
 
-    let allocator = Allocator::default();
-    let source_type = SourceType::default()
-        .with_module(true)
-        .with_typescript(true)
-        .with_jsx(true);
+    #[test]
+    fn test_dispatched_events_from_script_and_template() {
+        let comp: ComponentIR = serde_json::from_value(item_row_component()).unwrap();
+        assert_eq!(emitted_events(&comp), vec!["remove", "select"]);
+        assert!(dispatched_events(Some("dispatch('select')"), &[]).is_empty());
+    }
 
-    let parser = Parser::new(&allocator, &parsable_script, source_type);
-    let ret = parser.parse();
+    #[test]
+    fn test_event_handler_receives_payload() {
+        let mut components = HashMap::new();
+        components.insert("ItemRow".to_string(), item_row_component());
 
-    let mut symbols = HashSet::new();
-    if !ret.errors.is_empty() {
-        eprintln!("[Zenith ERROR] Failed to parse component script for local discovery:");
-        for err in &ret.errors {
-            eprintln!("  - {}", err.message);
-        }
-        return symbols;
+        let mut page = page_using(
+            "ItemRow",
+            serde_json::json!([
+                { "name": "label", "value": "Apples" },
+                { "name": "on:select", "value": { "code": "(item) => picked = item" } }
+            ]),
+        );
+        page.script = Some(crate::validate::ScriptIR {
+            raw: "state picked = null".to_string(),
+            attributes: HashMap::new(),
+            states: HashMap::from([("picked".to_string(), "null".to_string())]),
+            props: vec![],
+        });
+        let ir = resolve_components(page, components).unwrap();
+        let script = merged_script(&ir);
+
+        // The handler is compiled in the page's scope (mutation allowed) and gets the payload
+        assert!(script.contains("__locals.__events = {"), "{}", script);
+        assert!(
+            script.contains(
+                "\"select\": (__payload) => ((item) => scope.state.picked = item)(__payload)"
+            ),
+            "{}",
+            script
+        );
+        assert!(script.contains("const zenDispatch = () => (event, payload) =>"));
+        assert!(script.contains("handler(payload)"));
+        // The handler is not passed to the child as a prop
+        assert!(!script.contains("\"on:select\":"));
+        assert_eq!(ir.component_events["ItemRow"], vec!["remove", "select"]);
     }
 
-    for stmt in ret.program.body {
-        match stmt {
-            Statement::VariableDeclaration(var_decl) => {
-                for decl in &var_decl.declarations {
-                    collect_binding_pattern(&decl.id, &mut symbols);
-                }
-            }
-            Statement::FunctionDeclaration(func_decl) => {
-                if let Some(id) = &func_decl.id {
-                    symbols.insert(id.name.to_string());
-                }
-            }
-            Statement::ClassDeclaration(class_decl) => {
-                if let Some(id) = &class_decl.id {
-                    symbols.insert(id.name.to_string());
-                }
-            }
-            _ => {}
-        }
+    #[test]
+    fn test_unknown_event_warns_with_suggestion() {
+        let mut components = HashMap::new();
+        components.insert("ItemRow".to_string(), item_row_component());
+
+        let page = page_using(
+            "ItemRow",
+            serde_json::json!([{ "name": "on:selct", "value": { "code": "go" } }]),
+        );
+        let ir = resolve_components(page, components).unwrap();
+
+        let unknown = warnings_with(&ir, "Z-WARN-UNKNOWN-EVENT");
+        assert_eq!(unknown.len(), 1);
+        assert!(unknown[0].contains("`selct`"));
+        assert!(unknown[0].contains("Did you mean `on:select`?"));
+        assert!(warnings_with(&ir, "Z-WARN-UNKNOWN-PROP").is_empty());
     }
 
-    symbols
-}
+    /// Compiles a page rendering `<Greeting />` twice
+    fn compile_with_greeting(greeting: &str) -> Result<crate::parse::CompileResult, String> {
+        let component =
+            ComponentIR::from_source("Greeting", "components/Greeting.zen", greeting).unwrap();
+        let mut components = HashMap::new();
+        components.insert(
+            "Greeting".to_string(),
+            serde_json::to_value(component).unwrap(),
+        );
+        crate::parse::compile_zen_internal(
+            "<main><Greeting name=\"a\" /><Greeting name=\"b\" /></main>",
+            "page.zen",
+            crate::parse::CompileOptions {
+                components,
+                ..Default::default()
+            },
+        )
+    }
 
-fn collect_binding_pattern(pattern: &oxc_ast::ast::BindingPattern, symbols: &mut HashSet<String>) {
-    match pattern {
-        oxc_ast::ast::BindingPattern::BindingIdentifier(id) => {
-            symbols.insert(id.name.to_string());
-        }
-        oxc_ast::ast::BindingPattern::ObjectPattern(obj) => {
-            for prop in &obj.properties {
-                collect_binding_pattern(&prop.value, symbols);
-            }
-            if let Some(rest) = &obj.rest {
-                collect_binding_pattern(&rest.argument, symbols);
-            }
-        }
-        oxc_ast::ast::BindingPattern::ArrayPattern(arr) => {
-            for elem in &arr.elements {
-                if let Some(pattern) = elem {
-                    collect_binding_pattern(pattern, symbols);
-                }
-            }
-            if let Some(rest) = &arr.rest {
-                collect_binding_pattern(&rest.argument, symbols);
-            }
+    #[test]
+    fn test_promoted_expression_error_names_component_and_instance() {
+        let err =
+            compile_with_greeting("<script>\nprop name\n</script>\n<p>{missing}</p>").unwrap_err();
+        let scope_errors: Vec<&str> = err
+            .lines()
+            .filter(|l| l.starts_with("Z-ERR-SCOPE-002"))
+            .collect();
+        // Both instances report the same identifier: one error listing each occurrence
+        assert_eq!(scope_errors.len(), 1, "{}", err);
+        let error = scope_errors[0];
+        assert!(error.contains("`missing`"), "{}", error);
+        assert!(
+            error.contains("(2 occurrences: component Greeting at components/Greeting.zen (line"),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains("instance inst0; component Greeting"),
+            "{}",
+            error
+        );
+        assert!(error.ends_with("instance inst1)"), "{}", error);
+    }
+
+    #[test]
+    fn test_bindings_carry_expression_origin() {
+        let result =
+            compile_with_greeting("<script>\nprop name\n</script>\n<p>{name}</p>").unwrap();
+        let origins: Vec<&crate::validate::ExpressionOrigin> = result
+            .bindings
+            .iter()
+            .filter_map(|b| b.origin.as_ref())
+            .collect();
+        assert_eq!(origins.len(), 2, "{:?}", result.bindings);
+        assert_eq!(origins[0].instance_id, "inst0");
+        assert_eq!(origins[1].instance_id, "inst1");
+        assert_eq!(origins[0].original_id, origins[1].original_id);
+        for (binding, origin) in result.bindings.iter().zip(&origins) {
+            assert_eq!(origin.component_name, "Greeting");
+            assert_eq!(origin.component_path, "components/Greeting.zen");
+            assert_eq!(
+                binding.id,
+                format!("{}_{}", origin.original_id, origin.instance_id)
+            );
         }
-        _ => {}
+
+        // Page expressions have no origin and serialize without the field
+        let page = crate::parse::compile_zen_internal(
+            "<script>\nstate n = 1\n</script>\n<p>{n}</p>",
+            "page.zen",
+            Default::default(),
+        )
+        .unwrap();
+        let json = serde_json::to_string(&page.bindings).unwrap();
+        assert!(!json.contains("origin"), "{}", json);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::validate::{ElementNode, SourceLocation, TemplateNode};
+    const COUNTER: &str = "<script>\nstate count = 0\nprop label\nfunction format(n) { return label + ': ' + n }\n</script>\n<p class=\"counter\">{format(count)}</p>";
 
-    fn mock_loc() -> SourceLocation {
-        SourceLocation { line: 1, column: 1 }
+    fn counter_registry() -> HashMap<String, serde_json::Value> {
+        let counter =
+            ComponentIR::from_source("Counter", "components/Counter.zen", COUNTER).unwrap();
+        let mut components = HashMap::new();
+        components.insert(
+            "Counter".to_string(),
+            serde_json::to_value(counter).unwrap(),
+        );
+        components
+    }
+
+    /// (parses, html, manifest, errors) of a page rendering 100 counters
+    fn compile_counters(
+        components: &HashMap<String, serde_json::Value>,
+        cached: bool,
+    ) -> (usize, String, serde_json::Value, Vec<String>) {
+        let page = format!("<main>{}</main>", "<Counter label=\"n\" />".repeat(100));
+        crate::script_cache::SCRIPT_PARSES.with(|count| count.set(0));
+        ANALYSIS_CACHE_DISABLED.with(|disabled| disabled.set(!cached));
+        let result = crate::parse::compile_zen_internal(
+            &page,
+            "page.zen",
+            crate::parse::CompileOptions {
+                components: components.clone(),
+                ..Default::default()
+            },
+        );
+        ANALYSIS_CACHE_DISABLED.with(|disabled| disabled.set(false));
+        let result = result.unwrap();
+        (
+            crate::script_cache::SCRIPT_PARSES.with(Cell::get),
+            result.html,
+            serde_json::to_value(result.manifest).unwrap(),
+            result.errors,
+        )
     }
 
     #[test]
-    fn test_extract_slots_default() {
-        let children = vec![TemplateNode::Element(ElementNode {
-            tag: "div".to_string(),
-            attributes: vec![],
-            children: vec![],
-            location: mock_loc(),
-            loop_context: None,
-        })];
+    fn test_component_script_parsed_once_per_compile() {
+        let components = counter_registry();
+        let (parses, html, manifest, errors) = compile_counters(&components, true);
+        assert_eq!(parses, 1);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(html.matches("class=\"counter\"").count(), 100);
 
-        let slots = extract_slots("Card", children, None);
-        assert_eq!(slots.default.len(), 1);
-        assert!(slots.named.is_empty());
+        let (uncached_parses, uncached_html, uncached_manifest, uncached_errors) =
+            compile_counters(&components, false);
+        // Without the cache every lookup analyzes afresh: the dependency graph
+        // and the expansion of each instance
+        assert_eq!(uncached_parses, 200);
+        assert_eq!(uncached_html, html);
+        assert_eq!(uncached_manifest, manifest);
+        assert_eq!(uncached_errors, errors);
     }
 
     #[test]
-    fn test_extract_slots_named() {
-        let header_node = TemplateNode::Component(crate::validate::ComponentNode {
-            name: "Card.Header".to_string(),
-            attributes: vec![],
-            children: vec![TemplateNode::Element(ElementNode {
-                tag: "h1".to_string(),
-                attributes: vec![],
-                children: vec![],
-                location: mock_loc(),
-                loop_context: None,
-            })],
-            location: mock_loc(),
-            loop_context: None,
-        });
+    fn test_content_hash_follows_content() {
+        let counter =
+            ComponentIR::from_source("Counter", "components/Counter.zen", COUNTER).unwrap();
+        assert_eq!(counter.content_hash, None);
+        let hash = counter.compute_content_hash();
+        assert_eq!(counter.clone().compute_content_hash(), hash);
 
-        let children = vec![header_node];
+        let mut edited = counter.clone();
+        edited.script = Some("state count = 1\nprop label".to_string());
+        assert_ne!(edited.compute_content_hash(), hash);
 
-        let slots = extract_slots("Card", children, None);
-        assert!(slots.default.is_empty());
-        assert_eq!(slots.named.get("header").unwrap().len(), 1);
+        // A hash supplied by the caller is kept and serialized with the entry
+        let mut supplied = counter;
+        supplied.content_hash = Some("registry-v1".to_string());
+        let value = serde_json::to_value(&supplied).unwrap();
+        assert_eq!(value["contentHash"], "registry-v1");
+        let back: ComponentIR = serde_json::from_value(value).unwrap();
+        assert_eq!(back.content_hash.as_deref(), Some("registry-v1"));
+        assert!(counter_registry()["Counter"].get("contentHash").is_none());
+    }
+
+    /// Page rendering `usage` inside `<main>` with a leaf `Icon` registered
+    fn compile_with_icon(usage: &str, inline_components: bool) -> crate::parse::CompileResult {
+        let source = "<script>\nprop name\nprop size = 16\n</script>\n<svg class=\"icon\" width={size}><use href={`#${name}`} /></svg>";
+        let icon = ComponentIR::from_source("Icon", "components/Icon.zen", source).unwrap();
+        let mut components = HashMap::new();
+        components.insert("Icon".to_string(), serde_json::to_value(icon).unwrap());
+        crate::parse::compile_zen_internal(
+            &format!(
+                "<script>\nstate current = \"check\"\n</script>\n<main>{}</main>",
+                usage
+            ),
+            "page.zen",
+            crate::parse::CompileOptions {
+                components,
+                inline_components,
+                ..Default::default()
+            },
+        )
+        .unwrap()
     }
 
     #[test]
-    fn test_rename_symbols_simple() {
-        let code = "const x = a + b;";
-        let mut state = HashSet::new();
-        let mut props = HashSet::new();
-        let locals = HashSet::new();
-        state.insert("a".to_string());
-        props.insert("b".to_string());
+    fn test_static_leaf_component_is_inlined() {
+        let result = compile_with_icon("<Icon name=\"check\" />", true);
+        assert_eq!(
+            result.html,
+            "<main><svg class=\"icon\" width=\"16\"><use href=\"#check\"></use></svg></main>"
+        );
+        assert!(result.bindings.is_empty(), "{:?}", result.bindings);
+        let manifest = result.manifest.unwrap();
+        assert_eq!(manifest.inlined_components, 1);
+        assert!(!manifest.bundle.contains("__ZENITH_SCOPES__[\"inst"));
+    }
 
-        let (renamed, _, _) =
-            rename_symbols_safe(code, &state, &props, &locals, &HashSet::new(), false, false);
-        assert!(renamed.contains("state.a"));
-        assert!(renamed.contains("props.b"));
+    #[test]
+    fn test_dynamic_prop_takes_the_instance_path() {
+        let result = compile_with_icon("<Icon name={current} />", true);
+        let manifest = result.manifest.as_ref().unwrap();
+        assert_eq!(manifest.inlined_components, 0);
+        assert!(manifest.bundle.contains("__ZENITH_SCOPES__[\""));
+        assert!(!result.bindings.is_empty());
+        // Same depth, but the href is bound at runtime
+        assert!(result.html.starts_with("<main><svg class=\"icon\""));
+        assert!(!result.html.contains("#check"));
+        assert!(result
+            .warnings
+            .iter()
+            .all(|w| !w.starts_with("Z-WARN-INLINE-ALWAYS")));
+
+        // Off by default; a marked usage is inlined anyway or says why not
+        let result = compile_with_icon("<Icon name=\"check\" />", false);
+        assert_eq!(result.manifest.unwrap().inlined_components, 0);
+        let result = compile_with_icon("<Icon name=\"check\" zen:inline-always />", false);
+        assert_eq!(result.manifest.unwrap().inlined_components, 1);
+        let result = compile_with_icon("<Icon name={current} zen:inline-always />", false);
+        assert!(
+            result.warnings.iter().any(|w| w.starts_with(
+                "Z-WARN-INLINE-ALWAYS: <Icon> (components/Icon.zen) is marked `zen:inline-always` but cannot be inlined because prop `name` is dynamic"
+            )),
+            "{:?}",
+            result.warnings
+        );
+    }
+
+    /// Page rendering `usage` inside `<main>` with a `Card` that has a default
+    /// slot and a `footer` slot, or only `card_template` when given
+    fn compile_with_card(
+        usage: &str,
+        card_template: Option<&str>,
+        strict_slots: bool,
+    ) -> Result<crate::parse::CompileResult, String> {
+        let source = card_template.unwrap_or(
+            "<div class=\"card\"><slot></slot><footer><slot name=\"Footer\"></slot></footer></div>",
+        );
+        let card = ComponentIR::from_source("Card", "components/Card.zen", source).unwrap();
+        let mut components = HashMap::new();
+        components.insert("Card".to_string(), serde_json::to_value(card).unwrap());
+        crate::parse::compile_zen_internal(
+            &format!("<main>{}</main>", usage),
+            "page.zen",
+            crate::parse::CompileOptions {
+                components,
+                strict_slots,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn slot_diagnostics(result: &crate::parse::CompileResult) -> Vec<&String> {
+        result
+            .warnings
+            .iter()
+            .filter(|w| w.contains("-UNKNOWN-SLOT") || w.contains("-NO-DEFAULT-SLOT"))
+            .collect()
     }
 
     #[test]
-    fn test_rename_symbols_object_property() {
-        let code = "const obj = { a: a, b: 2 };";
-        let mut state = HashSet::new();
-        state.insert("a".to_string());
-        let props = HashSet::new();
-        let locals = HashSet::new();
+    fn test_undeclared_named_slot_warns_with_declared_slots() {
+        let usage = "<Card><p>Body</p><Card.footer>Ok</Card.footer></Card>\n<Card>\n  <p>Body</p><Card.Fotter><a>More</a></Card.Fotter></Card>";
+        let result = compile_with_card(usage, None, false).unwrap();
+        let warnings = slot_diagnostics(&result);
+        assert_eq!(warnings.len(), 1, "{:?}", result.warnings);
+        assert!(
+            warnings[0].starts_with("Z-WARN-UNKNOWN-SLOT: <Card.Fotter> (line 3, column 14)")
+                && warnings[0].contains("it declares the default slot, `footer`")
+                && warnings[0].ends_with("Did you mean `<Card.footer>`?"),
+            "{}",
+            warnings[0]
+        );
 
-        let (renamed, _, _) =
-            rename_symbols_safe(code, &state, &props, &locals, &HashSet::new(), false, false);
+        let err = compile_with_card(usage, None, true).unwrap_err();
+        assert!(err.contains("Z-ERR-UNKNOWN-SLOT: <Card.Fotter>"), "{}", err);
+    }
+
+    #[test]
+    fn test_declared_slot_matches_case_insensitively() {
+        let result = compile_with_card(
+            "<Card><p>Body</p><Card.footer><a>More</a></Card.footer></Card>",
+            None,
+            true,
+        )
+        .unwrap();
         assert!(
-            renamed.contains("a: scope.state.a"),
-            "Expected scope.state.a but got: {}",
-            renamed
+            slot_diagnostics(&result).is_empty(),
+            "{:?}",
+            result.warnings
+        );
+        // `<slot name="Footer">` is filled by `<Card.footer>`
+        assert!(
+            result.html.contains("<footer><a>More</a></footer>"),
+            "{}",
+            result.html
         );
     }
 
     #[test]
-    fn test_rename_symbols_shorthand() {
-        let code = "const obj = { a };";
-        let mut state = HashSet::new();
-        state.insert("a".to_string());
-        let props = HashSet::new();
-        let locals = HashSet::new();
+    fn test_default_content_without_default_slot_is_info() {
+        let result = compile_with_card(
+            "<Card>\n  <p>Body</p>\n  <Card.Title>Hi</Card.Title>\n</Card>",
+            Some("<h2><slot name=\"title\"></slot></h2>"),
+            false,
+        )
+        .unwrap();
+        let diagnostics = slot_diagnostics(&result);
+        assert_eq!(diagnostics.len(), 1, "{:?}", result.warnings);
+        assert!(
+            diagnostics[0].starts_with("Z-INFO-NO-DEFAULT-SLOT: <Card> has no unnamed <slot>"),
+            "{}",
+            diagnostics[0]
+        );
+        // Whitespace between compound tags is not content
+        let result = compile_with_card(
+            "<Card>\n  <Card.Title>Hi</Card.Title>\n</Card>",
+            Some("<h2><slot name=\"title\"></slot></h2>"),
+            false,
+        )
+        .unwrap();
+        assert!(
+            slot_diagnostics(&result).is_empty(),
+            "{:?}",
+            result.warnings
+        );
+    }
 
-        let (renamed, _, _) =
-            rename_symbols_safe(code, &state, &props, &locals, &HashSet::new(), false, false);
+    #[test]
+    fn test_orphan_slot_marker_is_an_invariant_error() {
+        // Located at the orphan, not the first `<Card.Footer>` in the page
+        let err = compile_with_card(
+            "<Card><Card.Footer>Ok</Card.Footer></Card><div>\n  <Card.Footer>Lost</Card.Footer></div>",
+            None,
+            false,
+        )
+        .unwrap_err();
         assert!(
-            renamed.contains("a: scope.state.a"),
-            "Expected scope.state.a but got: {}",
-            renamed
+            err.contains(
+                "INV007: <Card.Footer> (line 2, column 3) in page.zen is not a direct child of <Card>"
+            ),
+            "{}",
+            err
         );
+        // Nested one level too deep inside the component is still an orphan
+        let err = compile_with_card(
+            "<Card><div><Card.Footer>Lost</Card.Footer></div></Card>",
+            None,
+            false,
+        )
+        .unwrap_err();
+        assert!(err.contains("INV007"), "{}", err);
     }
 }
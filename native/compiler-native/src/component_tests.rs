@@ -18,6 +18,7 @@ mod tests {
                 children: vec![],
                 location: mock_loc(),
                 loop_context: None,
+                ordinal: 0,
             })
         ];
         
@@ -38,6 +39,7 @@ mod tests {
                     children: vec![],
                     location: mock_loc(),
                     loop_context: None,
+                    ordinal: 0,
                 })
             ],
             location: mock_loc(),
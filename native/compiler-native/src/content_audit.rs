@@ -0,0 +1,500 @@
+//! Content Accounting
+//!
+//! Two steps restructure a page before it renders, and both can take authored
+//! content out of the page without an error:
+//!
+//! - document restructuring: in a page written as a full `<html>` document,
+//!   anything before `<html>` or after `</html>` is outside the document; the
+//!   HTML parser re-homes it into `<body>`, far from where it was written
+//! - slot resolution: children passed to a component (a layout included) render
+//!   only where its template has a matching `<slot>`; a page node beside a layout
+//!   ends up after the layout's `</html>`
+//!
+//! Each meaningful node written at the top of the page, or as a component's
+//! children, is followed by identity (the element/text ordinal assigned at parse,
+//! the expression id) rather than by count, so content that is only moved into a
+//! slot is not reported. Comments, stripped `<script>`/`<style>` blocks, and
+//! `<Head>` directives are removed on purpose and are not counted. Every node
+//! that does not survive gets one `Z-WARN-UNREACHABLE-CONTENT` naming the step.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::validate::{TemplateIR, TemplateNode};
+
+/// Longest text preview in a warning
+const PREVIEW_CHARS: usize = 40;
+
+/// Identity of a tracked page node
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum NodeKey {
+    Ordinal(u64),
+    Expression(String),
+}
+
+/// A page node whose presence is checked after slot resolution
+#[derive(Debug, Clone)]
+pub(crate) struct TrackedNode {
+    key: NodeKey,
+    preview: String,
+    location: Option<(usize, usize)>,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DOCUMENT RESTRUCTURING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Meaningful content written before `<html>` or after `</html>` in a document page
+pub(crate) fn check_outside_document(source: &str, file_path: &str) -> Vec<String> {
+    let blanked = blank_ignored(source);
+    let lower = blanked.to_ascii_lowercase();
+    let (Some(open), Some(close)) = (find_tag(&lower, "<html", 0), lower.rfind("</html")) else {
+        return Vec::new();
+    };
+    let after = lower[close..]
+        .find('>')
+        .map_or(lower.len(), |end| close + end + 1);
+
+    let mut items = top_level_items(&blanked, 0, open);
+    items.extend(top_level_items(&blanked, after, blanked.len()));
+    items
+        .into_iter()
+        .map(|(offset, preview)| {
+            let (line, column) = line_column(source, offset);
+            let side = if offset < open {
+                "before `<html>`"
+            } else {
+                "after `</html>`"
+            };
+            format!(
+                "Z-WARN-UNREACHABLE-CONTENT: {} (line {}, column {}) in {} is {}; document restructuring does not keep it where it is written (the HTML parser moves it into <body>). Move it inside <body>.",
+                preview, line, column, file_path, side
+            )
+        })
+        .collect()
+}
+
+/// Start of `<tag` followed by the end of the tag name
+fn find_tag(lower: &str, open: &str, from: usize) -> Option<usize> {
+    let mut at = from;
+    while let Some(found) = lower[at..].find(open) {
+        let start = at + found;
+        let next = lower[start + open.len()..].chars().next();
+        if next.is_none() || next.is_some_and(|c| c.is_whitespace() || c == '>' || c == '/') {
+            return Some(start);
+        }
+        at = start + open.len();
+    }
+    None
+}
+
+/// Top-level elements and text runs in `source[start..end]`, with their offset
+fn top_level_items(source: &str, start: usize, end: usize) -> Vec<(usize, String)> {
+    let lower = source.to_ascii_lowercase();
+    let mut items = Vec::new();
+    let mut at = start;
+    while at < end {
+        let rest = &source[at..end];
+        let trimmed = rest.trim_start();
+        if trimmed.is_empty() {
+            break;
+        }
+        at = end - trimmed.len();
+        if trimmed.starts_with("<!") || trimmed.starts_with("</") {
+            // Doctype or a stray close tag
+            at = source[at..end].find('>').map_or(end, |i| at + i + 1);
+        } else if let Some(tag) = tag_name(trimmed) {
+            items.push((at, format!("`<{}>`", tag)));
+            at = element_end(&lower, at, end, &tag);
+        } else {
+            let len = trimmed.find('<').unwrap_or(trimmed.len());
+            items.push((at, format!("text \"{}\"", preview_text(&trimmed[..len]))));
+            at += len.max(1);
+        }
+    }
+    items
+}
+
+fn tag_name(s: &str) -> Option<String> {
+    let name: String = s
+        .strip_prefix('<')?
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == ':' || *c == '.')
+        .collect();
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic())
+        .then(|| name.to_ascii_lowercase())
+}
+
+/// Offset just past the element starting at `start` (its matching close tag,
+/// or the start tag itself when it is void or self-closing)
+fn element_end(lower: &str, start: usize, end: usize, tag: &str) -> usize {
+    let tag_end = |from: usize| lower[from..end].find('>').map_or(end, |i| from + i + 1);
+    let open_end = tag_end(start);
+    if lower[..open_end].ends_with("/>") || is_void(tag) {
+        return open_end;
+    }
+    let (open, close) = (format!("<{}", tag), format!("</{}", tag));
+    let mut depth = 1;
+    let mut at = open_end;
+    while at < end {
+        let next_open = find_tag(&lower[..end], &open, at);
+        let Some(next_close) = lower[at..end].find(&close).map(|i| at + i) else {
+            return end;
+        };
+        match next_open {
+            Some(o) if o < next_close => {
+                depth += 1;
+                at = tag_end(o);
+            }
+            _ => {
+                depth -= 1;
+                at = tag_end(next_close);
+                if depth == 0 {
+                    return at;
+                }
+            }
+        }
+    }
+    end
+}
+
+fn is_void(tag: &str) -> bool {
+    matches!(
+        tag,
+        "area"
+            | "base"
+            | "br"
+            | "col"
+            | "embed"
+            | "hr"
+            | "img"
+            | "input"
+            | "link"
+            | "meta"
+            | "source"
+            | "track"
+            | "wbr"
+    )
+}
+
+/// Comments and `<script>` / `<style>` blocks replaced by spaces (newlines kept),
+/// so they are neither counted nor shift offsets
+pub(crate) fn blank_ignored(source: &str) -> String {
+    let lower = source.to_ascii_lowercase();
+    let mut out = source.to_string().into_bytes();
+    let mut blank = |from: usize, to: usize| {
+        for b in &mut out[from..to] {
+            if *b != b'\n' {
+                *b = b' ';
+            }
+        }
+    };
+    let mut at = 0;
+    while at < lower.len() {
+        let next = [
+            lower[at..].find("<!--").map(|i| (at + i, "-->")),
+            find_tag(&lower, "<script", at).map(|i| (i, "</script>")),
+            find_tag(&lower, "<style", at).map(|i| (i, "</style>")),
+        ]
+        .into_iter()
+        .flatten()
+        .min_by_key(|(i, _)| *i);
+        let Some((from, terminator)) = next else {
+            break;
+        };
+        let to = lower[from..]
+            .find(terminator)
+            .map_or(lower.len(), |i| from + i + terminator.len());
+        blank(from, to);
+        at = to;
+    }
+    // Whole characters were replaced, so the result is still UTF-8
+    String::from_utf8(out).unwrap_or_else(|_| source.to_string())
+}
+
+pub(crate) fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before[before.rfind('\n').map_or(0, |i| i + 1)..]
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+fn preview_text(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() > PREVIEW_CHARS {
+        format!("{}…", text.chars().take(PREVIEW_CHARS).collect::<String>())
+    } else {
+        text
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SLOT RESOLUTION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Page nodes to follow through component resolution: top-level nodes, and the
+/// children of top-level components (their slot content)
+pub(crate) fn track_page_content(template: &TemplateIR) -> Vec<TrackedNode> {
+    let source = blank_ignored(&template.raw);
+    let lower = source.to_ascii_lowercase();
+    let mut occurrence = HashMap::new();
+    let mut seen_tags: HashMap<String, usize> = HashMap::new();
+    number_elements(&template.nodes, &mut seen_tags, &mut occurrence);
+
+    let mut tracked = Vec::new();
+    collect_tracked(&template.nodes, template, &lower, &occurrence, &mut tracked);
+    tracked
+}
+
+/// Source order index of each element among elements with the same tag
+fn number_elements(
+    nodes: &[TemplateNode],
+    seen: &mut HashMap<String, usize>,
+    occurrence: &mut HashMap<u64, usize>,
+) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                let count = seen.entry(el.tag.to_ascii_lowercase()).or_insert(0);
+                occurrence.insert(el.ordinal, *count);
+                *count += 1;
+                number_elements(&el.children, seen, occurrence);
+            }
+            TemplateNode::Component(c) => number_elements(&c.children, seen, occurrence),
+            _ => {}
+        }
+    }
+}
+
+fn collect_tracked(
+    nodes: &[TemplateNode],
+    template: &TemplateIR,
+    lower: &str,
+    occurrence: &HashMap<u64, usize>,
+    tracked: &mut Vec<TrackedNode>,
+) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) if el.ordinal != 0 => {
+                let tag = el.tag.to_ascii_lowercase();
+                let location = occurrence.get(&el.ordinal).and_then(|&n| {
+                    let open = format!("<{}", tag);
+                    let mut at = find_tag(lower, &open, 0)?;
+                    for _ in 0..n {
+                        at = find_tag(lower, &open, at + open.len())?;
+                    }
+                    Some(line_column(lower, at))
+                });
+                tracked.push(TrackedNode {
+                    key: NodeKey::Ordinal(el.ordinal),
+                    preview: format!("`<{}>`", el.tag),
+                    location,
+                });
+            }
+            TemplateNode::Text(text) if text.ordinal != 0 && !text.value.trim().is_empty() => {
+                let needle = text.value.trim().to_ascii_lowercase();
+                tracked.push(TrackedNode {
+                    key: NodeKey::Ordinal(text.ordinal),
+                    preview: format!("text \"{}\"", preview_text(&text.value)),
+                    location: lower.find(&needle).map(|at| line_column(lower, at)),
+                });
+            }
+            TemplateNode::Expression(expr) => {
+                let code = template
+                    .expressions
+                    .iter()
+                    .find(|e| e.id == expr.expression)
+                    .map_or(expr.expression.as_str(), |e| e.code.trim());
+                tracked.push(TrackedNode {
+                    key: NodeKey::Expression(expr.expression.clone()),
+                    preview: format!("`{{{}}}`", preview_text(code)),
+                    location: lower
+                        .find(&code.to_ascii_lowercase())
+                        .map(|at| line_column(lower, at)),
+                });
+            }
+            // A `<Head>` is a directive, taken out of the template on purpose
+            TemplateNode::Component(c) if c.name == "Head" => {}
+            TemplateNode::Component(c) => {
+                collect_tracked(&c.children, template, lower, occurrence, tracked)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Warnings for tracked nodes that resolution dropped, or left outside the
+/// `<html>` root when the resolved page is a document
+pub(crate) fn check_resolved_content(
+    tracked: &[TrackedNode],
+    resolved: &[TemplateNode],
+    file_path: &str,
+) -> Vec<String> {
+    let root = crate::document::is_document_module(resolved)
+        .then(|| {
+            resolved
+                .iter()
+                .position(|node| matches!(node, TemplateNode::Element(_)))
+        })
+        .flatten();
+
+    let mut inside = HashSet::new();
+    let mut outside = HashSet::new();
+    for (i, node) in resolved.iter().enumerate() {
+        let keys = if root.is_none() || root == Some(i) {
+            &mut inside
+        } else {
+            &mut outside
+        };
+        collect_keys(std::slice::from_ref(node), keys);
+    }
+
+    tracked
+        .iter()
+        .filter(|node| !inside.contains(&node.key))
+        .map(|node| {
+            let location = node
+                .location
+                .map(|(line, column)| format!(" (line {}, column {})", line, column))
+                .unwrap_or_default();
+            if outside.contains(&node.key) {
+                format!(
+                    "Z-WARN-UNREACHABLE-CONTENT: {}{} in {} is outside the document after slot resolution: the layout renders the whole <html> document, so content beside it ends up after </html>. Pass it to the layout as slot content instead.",
+                    node.preview, location, file_path
+                )
+            } else {
+                format!(
+                    "Z-WARN-UNREACHABLE-CONTENT: {}{} in {} was dropped by slot resolution: the component it is passed to has no slot that renders it. Add a <slot> (or the matching named slot) to the component, or move the content.",
+                    node.preview, location, file_path
+                )
+            }
+        })
+        .collect()
+}
+
+fn collect_keys(nodes: &[TemplateNode], keys: &mut HashSet<NodeKey>) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                keys.insert(NodeKey::Ordinal(el.ordinal));
+                collect_keys(&el.children, keys);
+            }
+            TemplateNode::Text(text) => {
+                keys.insert(NodeKey::Ordinal(text.ordinal));
+            }
+            TemplateNode::Expression(expr) => {
+                keys.insert(NodeKey::Expression(expr.expression.clone()));
+            }
+            TemplateNode::Component(c) => collect_keys(&c.children, keys),
+            TemplateNode::ConditionalFragment(cf) => {
+                collect_keys(&cf.consequent, keys);
+                collect_keys(&cf.alternate, keys);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    collect_keys(nodes, keys);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => collect_keys(&of.fragment, keys),
+            TemplateNode::LoopFragment(lf) => collect_keys(&lf.body, keys),
+            TemplateNode::Doctype(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+    use std::collections::HashMap;
+
+    fn unreachable(result: &CompileResult) -> Vec<&String> {
+        result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("Z-WARN-UNREACHABLE-CONTENT"))
+            .collect()
+    }
+
+    fn layout() -> serde_json::Value {
+        serde_json::json!({
+            "name": "Layout",
+            "nodes": [{
+                "type": "element", "tag": "html", "attributes": [], "children": [
+                    { "type": "element", "tag": "head", "attributes": [], "children": [] },
+                    { "type": "element", "tag": "body", "attributes": [], "children": [
+                        { "type": "element", "tag": "slot", "attributes": [], "children": [] }
+                    ]}
+                ]
+            }]
+        })
+    }
+
+    fn compile_with_layout(source: &str) -> CompileResult {
+        let options = CompileOptions {
+            components: HashMap::from([("Layout".to_string(), layout())]),
+            ..Default::default()
+        };
+        compile_zen_internal(source, "page.zen", options).unwrap()
+    }
+
+    #[test]
+    fn test_content_after_html_warns() {
+        let source = "<html>\n<head><title>T</title></head>\n<body><main>Main</main></body>\n</html>\n<footer>Lost</footer>\n<!-- note -->\n";
+        let result = compile_zen_internal(source, "page.zen", Default::default()).unwrap();
+
+        let warnings = unreachable(&result);
+        assert_eq!(warnings.len(), 1, "{:?}", result.warnings);
+        assert!(
+            warnings[0].starts_with(
+                "Z-WARN-UNREACHABLE-CONTENT: `<footer>` (line 5, column 1) in page.zen is after `</html>`; document restructuring"
+            ),
+            "{}",
+            warnings[0]
+        );
+    }
+
+    #[test]
+    fn test_sibling_of_layout_warns() {
+        let result = compile_with_layout(
+            "<Layout>\n  <main>Inside</main>\n</Layout>\n<aside>Beside</aside>\n",
+        );
+        assert!(!result.has_errors, "{:?}", result.errors);
+
+        let warnings = unreachable(&result);
+        assert_eq!(warnings.len(), 1, "{:?}", result.warnings);
+        assert!(
+            warnings[0].starts_with(
+                "Z-WARN-UNREACHABLE-CONTENT: `<aside>` (line 4, column 1) in page.zen is outside the document after slot resolution"
+            ),
+            "{}",
+            warnings[0]
+        );
+    }
+
+    #[test]
+    fn test_normal_pages_have_no_drop_warnings() {
+        let plain = compile_zen_internal(
+            "<script>\nstate n = 1\n</script>\n<main><h1>Hi</h1><p>{n}</p></main>\n<footer>Bye</footer>",
+            "page.zen",
+            Default::default(),
+        )
+        .unwrap();
+        assert!(unreachable(&plain).is_empty(), "{:?}", plain.warnings);
+
+        let document = compile_zen_internal(
+            "<!DOCTYPE html>\n<html>\n<head><title>T</title></head>\n<body><p>Body</p></body>\n</html>\n",
+            "page.zen",
+            Default::default(),
+        )
+        .unwrap();
+        assert!(unreachable(&document).is_empty(), "{:?}", document.warnings);
+
+        let layout =
+            compile_with_layout("<Layout>\n  <main>Inside</main>\n  text {1 + 1}\n</Layout>\n");
+        assert!(unreachable(&layout).is_empty(), "{:?}", layout.warnings);
+    }
+}
@@ -0,0 +1,552 @@
+//! Scoped Component Styles
+//!
+//! With `CompileOptions.css_scope_strategy` set, a component's regular `<style>`
+//! blocks only apply to its own markup. Each component gets a scope id hashed from
+//! its path, and a scope class from the page's `ClassNameAllocator` (see
+//! `class_names.rs`); resolution marks every element of the component's template
+//! (slot content from the caller excluded) and the CSS is rewritten to match:
+//!
+//! | strategy            | selector suffix              | markup                         |
+//! |---------------------|------------------------------|--------------------------------|
+//! | `ClassSuffix`       | `.<class>`                   | `class="... <class>"`          |
+//! | `WhereWrapper`      | `:where(.<class>)`           | `class="... <class>"`          |
+//! | `AttributeSelector` | `[data-zen-scope="<id>"]`    | `data-zen-scope="<id>"`        |
+//!
+//! One rewriter finds where the scope attaches and the strategy only decides the
+//! text: it goes on the subject (last compound) of each selector, before a
+//! pseudo-element, so `.title::before` becomes `.title.<class>::before`.
+//! `WhereWrapper` adds no specificity, so design-system overrides keep working.
+//!
+//! Keyframes are renamed `<name>-<id>` under every strategy, together with the
+//! `animation` / `animation-name` declarations that use them. Rules inside
+//! `@media`, `@supports`, `@container` and `@layer` are scoped; other at-rules
+//! (`@font-face`, `@page`, keyframe steps) are left alone, as are `<style global>`
+//! blocks.
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::validate::{AttributeIR, AttributeValue, SourceLocation, TemplateNode};
+
+/// Attribute carrying the scope id under `ScopeStrategy::AttributeSelector`
+pub(crate) const SCOPE_ATTR: &str = "data-zen-scope";
+
+lazy_static! {
+    static ref KEYFRAMES: Regex = Regex::new(r"@(?:-webkit-)?keyframes\s+([\w-]+)").unwrap();
+    /// `animation` / `animation-name` declaration and its value
+    static ref ANIMATION_DECL: Regex =
+        Regex::new(r"(?i)((?:^|[;{\s])(?:-webkit-)?animation(?:-name)?\s*:)([^;}]*)").unwrap();
+    static ref IDENT: Regex = Regex::new(r"[\w-]+").unwrap();
+}
+
+/// At-rules whose block holds ordinary rules that are scoped like top-level ones
+const GROUPING_AT_RULES: &[&str] = &["media", "supports", "container", "layer", "document"];
+
+/// Pseudo-elements that may still be written with a single colon
+const LEGACY_PSEUDO_ELEMENTS: &[&str] = &["before", "after", "first-line", "first-letter"];
+
+/// How scoped component styles select their component's markup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScopeStrategy {
+    /// `.title.<class>`: one class of extra specificity
+    #[default]
+    ClassSuffix,
+    /// `.title:where(.<class>)`: the selector keeps its own specificity
+    WhereWrapper,
+    /// `.title[data-zen-scope="<id>"]`, with the attribute emitted instead of a class
+    AttributeSelector,
+}
+
+impl ScopeStrategy {
+    /// `"class-suffix"`, `"where-wrapper"` or `"attribute-selector"` (any case)
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "class-suffix" => Ok(Self::ClassSuffix),
+            "where-wrapper" => Ok(Self::WhereWrapper),
+            "attribute-selector" => Ok(Self::AttributeSelector),
+            other => Err(format!(
+                "Z-ERR-CSS-SCOPE: unknown scope strategy `{}`; expected `class-suffix`, `where-wrapper` or `attribute-selector`",
+                other
+            )),
+        }
+    }
+
+    /// Text appended to a selector's subject compound
+    fn selector_suffix(self, scope: &str, class: &str) -> String {
+        match self {
+            Self::ClassSuffix => format!(".{}", class),
+            Self::WhereWrapper => format!(":where(.{})", class),
+            Self::AttributeSelector => format!("[{}=\"{}\"]", SCOPE_ATTR, scope),
+        }
+    }
+}
+
+/// Scope id of a component, stable across edits to its content
+pub(crate) fn scope_id(component_path: &str) -> String {
+    let digest = Sha256::digest(component_path.as_bytes());
+    format!("{:x}", digest)[..8].to_string()
+}
+
+/// Mark every element of a component's own template with its scope (the id under
+/// `AttributeSelector`, the class otherwise)
+pub(crate) fn mark_elements(
+    nodes: &mut [TemplateNode],
+    scope: &str,
+    class: &str,
+    strategy: ScopeStrategy,
+) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                if el.tag != "slot" {
+                    mark_element(&mut el.attributes, &el.location, scope, class, strategy);
+                }
+                mark_elements(&mut el.children, scope, class, strategy);
+            }
+            // The child component scopes its own markup; content passed to it is ours
+            TemplateNode::Component(comp) => {
+                mark_elements(&mut comp.children, scope, class, strategy)
+            }
+            TemplateNode::ConditionalFragment(cf) => {
+                mark_elements(&mut cf.consequent, scope, class, strategy);
+                mark_elements(&mut cf.alternate, scope, class, strategy);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children_mut() {
+                    mark_elements(nodes, scope, class, strategy);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => {
+                mark_elements(&mut of.fragment, scope, class, strategy)
+            }
+            TemplateNode::LoopFragment(lf) => mark_elements(&mut lf.body, scope, class, strategy),
+            _ => {}
+        }
+    }
+}
+
+fn mark_element(
+    attributes: &mut Vec<AttributeIR>,
+    location: &SourceLocation,
+    scope: &str,
+    class: &str,
+    strategy: ScopeStrategy,
+) {
+    let (name, value) = match strategy {
+        ScopeStrategy::AttributeSelector => (SCOPE_ATTR, scope.to_string()),
+        ScopeStrategy::ClassSuffix | ScopeStrategy::WhereWrapper => {
+            // A dynamic `class` is merged with the static one by the transformer
+            let existing = attributes.iter_mut().find(|a| {
+                a.name.eq_ignore_ascii_case("class") && matches!(a.value, AttributeValue::Static(_))
+            });
+            if let Some(AttributeIR {
+                value: AttributeValue::Static(value),
+                ..
+            }) = existing
+            {
+                if !value.split_whitespace().any(|c| c == class) {
+                    value.push(' ');
+                    value.push_str(class);
+                }
+                return;
+            }
+            ("class", class.to_string())
+        }
+    };
+    attributes.push(AttributeIR {
+        name: name.to_string(),
+        value: AttributeValue::Static(value),
+        location: location.clone(),
+        loop_context: None,
+        optional: false,
+        extra: Default::default(),
+    });
+}
+
+/// `css` with every rule limited to elements marked with `scope` or `class`
+pub(crate) fn scope_css(css: &str, scope: &str, class: &str, strategy: ScopeStrategy) -> String {
+    let keyframes: HashMap<String, String> = KEYFRAMES
+        .captures_iter(css)
+        .map(|c| (c[1].to_string(), format!("{}-{}", &c[1], scope)))
+        .collect();
+    let scoper = Scoper {
+        suffix: strategy.selector_suffix(scope, class),
+        keyframes,
+    };
+    scoper.block(css)
+}
+
+struct Scoper {
+    suffix: String,
+    /// Keyframe name → scoped name
+    keyframes: HashMap<String, String>,
+}
+
+impl Scoper {
+    /// Rules of a stylesheet or grouping at-rule body
+    fn block(&self, css: &str) -> String {
+        let mut out = String::with_capacity(css.len() + css.len() / 4);
+        let mut rest = css;
+        while let Some(open) = find_top_level(rest, |c| c == '{') {
+            let prelude = &rest[..open];
+            let close = matching_brace(rest, open);
+            let body = &rest[open + 1..close];
+            // A statement at-rule (`@import ...;`) before the rule passes through
+            let (statements, prelude) = match find_top_level(prelude, |c| c == ';') {
+                Some(_) => {
+                    let split = prelude.rfind(';').unwrap() + 1;
+                    (&prelude[..split], &prelude[split..])
+                }
+                None => ("", prelude),
+            };
+            out.push_str(statements);
+            out.push_str(&self.rule(prelude, body));
+            rest = &rest[(close + 1).min(rest.len())..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn rule(&self, prelude: &str, body: &str) -> String {
+        let trimmed = prelude.trim_start();
+        if let Some(at_rule) = trimmed.strip_prefix('@') {
+            let keyword = at_rule
+                .split(|c: char| c.is_whitespace() || c == '(')
+                .next()
+                .unwrap_or("")
+                .to_ascii_lowercase();
+            if keyword.ends_with("keyframes") {
+                let renamed = KEYFRAMES.replace(prelude, |c: &regex::Captures| {
+                    let name = c.get(1).unwrap();
+                    let keyword = &c[0][..name.start() - c.get(0).unwrap().start()];
+                    format!("{}{}", keyword, self.keyframes[name.as_str()])
+                });
+                return format!("{}{{{}}}", renamed, body);
+            }
+            if GROUPING_AT_RULES.contains(&keyword.as_str()) {
+                return format!("{}{{{}}}", prelude, self.block(body));
+            }
+            return format!("{}{{{}}}", prelude, body);
+        }
+        format!(
+            "{}{{{}}}",
+            self.selector_list(prelude),
+            self.declarations(body)
+        )
+    }
+
+    /// Each comma-separated selector with the scope attached to its subject
+    fn selector_list(&self, prelude: &str) -> String {
+        let mut out = String::new();
+        let mut rest = prelude;
+        loop {
+            let end = find_top_level(rest, |c| c == ',').unwrap_or(rest.len());
+            out.push_str(&self.selector(&rest[..end]));
+            if end == rest.len() {
+                return out;
+            }
+            out.push(',');
+            rest = &rest[end + 1..];
+        }
+    }
+
+    fn selector(&self, selector: &str) -> String {
+        let content = selector.trim_end();
+        if content.trim().is_empty() {
+            return selector.to_string();
+        }
+        let at = scope_position(content);
+        format!(
+            "{}{}{}{}",
+            &content[..at],
+            self.suffix,
+            &content[at..],
+            &selector[content.len()..]
+        )
+    }
+
+    /// Declarations with keyframe references renamed
+    fn declarations(&self, body: &str) -> String {
+        if self.keyframes.is_empty() {
+            return body.to_string();
+        }
+        ANIMATION_DECL
+            .replace_all(body, |c: &regex::Captures| {
+                let value = IDENT.replace_all(&c[2], |ident: &regex::Captures| {
+                    self.keyframes
+                        .get(&ident[0])
+                        .cloned()
+                        .unwrap_or_else(|| ident[0].to_string())
+                });
+                format!("{}{}", &c[1], value)
+            })
+            .into_owned()
+    }
+}
+
+/// Byte offset in `selector` where the scope goes: the end of the subject
+/// compound, or before its pseudo-element
+fn scope_position(selector: &str) -> usize {
+    let subject_start = last_combinator_end(selector);
+    let subject = &selector[subject_start..];
+    let bytes = subject.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth -= 1,
+            b'\\' => i += 1,
+            b':' if depth == 0 => {
+                if bytes.get(i + 1) == Some(&b':') {
+                    return subject_start + i;
+                }
+                let name: String = subject[i + 1..]
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+                    .collect();
+                if LEGACY_PSEUDO_ELEMENTS.contains(&name.to_ascii_lowercase().as_str()) {
+                    return subject_start + i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    selector.len()
+}
+
+/// Start of the last compound selector (after the last top-level combinator)
+fn last_combinator_end(selector: &str) -> usize {
+    let bytes = selector.as_bytes();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth -= 1,
+            b'\\' => i += 1,
+            b' ' | b'\t' | b'\n' | b'\r' | b'>' | b'+' | b'~' if depth == 0 => start = i + 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    start
+}
+
+/// First byte matching `pred` outside comments, strings, and parentheses
+fn find_top_level(css: &str, pred: impl Fn(char) -> bool) -> Option<usize> {
+    let bytes = css.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = css[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 1);
+            }
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            c if depth == 0 && pred(c as char) => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Offset of the `}` closing the block opened at `open` (the end when unclosed)
+fn matching_brace(css: &str, open: usize) -> usize {
+    let mut depth = 0;
+    let mut offset = open;
+    while let Some(i) = find_top_level(&css[offset..], |c| c == '{' || c == '}') {
+        offset += i;
+        if css.as_bytes()[offset] == b'{' {
+            depth += 1;
+        } else {
+            depth -= 1;
+            if depth == 0 {
+                return offset;
+            }
+        }
+        offset += 1;
+    }
+    css.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::component::ComponentIR;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    const BADGE: &str = "<div class=\"badge\"><span>New</span></div>\n<style>\n.badge::before { content: \"*\"; animation: pulse 1s infinite; }\n.badge > span, .badge:hover span { color: red; }\n@media (min-width: 40em) { .badge { padding: 2px; } }\n@keyframes pulse { from { opacity: 0; } to { opacity: 1; } }\n</style>\n<style global>\nbody { margin: 0; }\n</style>";
+
+    fn scope_class(path: &str) -> String {
+        let prefix = crate::class_names::DEFAULT_CLASS_PREFIX;
+        crate::class_names::class_name(prefix, crate::class_names::SCOPE_PURPOSE, path, "", 0)
+    }
+
+    fn compile(strategy: Option<ScopeStrategy>) -> (CompileResult, String) {
+        let badge = ComponentIR::from_source("Badge", "components/Badge.zen", BADGE).unwrap();
+        let mut components = HashMap::new();
+        components.insert("Badge".to_string(), serde_json::to_value(badge).unwrap());
+        let result = compile_zen_internal(
+            "<main><p class=\"intro\">Hi</p><Badge /></main>",
+            "page.zen",
+            CompileOptions {
+                components,
+                css_scope_strategy: strategy,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let styles = result.manifest.as_ref().unwrap().styles.clone();
+        (result, styles)
+    }
+
+    #[test]
+    fn test_class_suffix_strategy() {
+        let id = scope_id("components/Badge.zen");
+        let class = scope_class("components/Badge.zen");
+        let (result, css) = compile(Some(ScopeStrategy::ClassSuffix));
+        assert!(
+            result.html.contains(&format!(
+                "<div class=\"badge {class}\"><span class=\"{class}\">New</span></div>"
+            )),
+            "{}",
+            result.html
+        );
+        // Page markup is not part of the component
+        assert!(
+            result.html.contains("<p class=\"intro\">"),
+            "{}",
+            result.html
+        );
+        assert!(
+            css.contains(&format!(
+                ".badge.{class}::before {{ content: \"*\"; animation: pulse-{id} 1s infinite; }}"
+            )),
+            "{}",
+            css
+        );
+        assert!(
+            css.contains(&format!(
+                ".badge > span.{class}, .badge:hover span.{class} {{"
+            )),
+            "{}",
+            css
+        );
+        assert!(
+            css.contains(&format!("@media (min-width: 40em) {{ .badge.{class} {{")),
+            "{}",
+            css
+        );
+        assert!(
+            css.contains(&format!("@keyframes pulse-{id} {{ from {{ opacity: 0; }}")),
+            "{}",
+            css
+        );
+        // Global blocks pass through
+        assert!(css.contains("body { margin: 0; }"), "{}", css);
+    }
+
+    #[test]
+    fn test_where_wrapper_strategy() {
+        let id = scope_id("components/Badge.zen");
+        let class = scope_class("components/Badge.zen");
+        let (result, css) = compile(Some(ScopeStrategy::WhereWrapper));
+        assert!(
+            result
+                .html
+                .contains(&format!("<div class=\"badge {class}\">")),
+            "{}",
+            result.html
+        );
+        assert!(
+            css.contains(&format!(
+                ".badge:where(.{class})::before {{ content: \"*\"; animation: pulse-{id} 1s infinite; }}"
+            )),
+            "{}",
+            css
+        );
+        assert!(
+            css.contains(&format!(
+                ".badge > span:where(.{class}), .badge:hover span:where(.{class}) {{"
+            )),
+            "{}",
+            css
+        );
+        assert!(css.contains(&format!("@keyframes pulse-{id} ")), "{}", css);
+    }
+
+    #[test]
+    fn test_attribute_selector_strategy() {
+        let id = scope_id("components/Badge.zen");
+        let (result, css) = compile(Some(ScopeStrategy::AttributeSelector));
+        assert!(
+            result.html.contains(&format!(
+                "<div class=\"badge\" data-zen-scope=\"{id}\"><span data-zen-scope=\"{id}\">New</span></div>"
+            )),
+            "{}",
+            result.html
+        );
+        assert!(
+            css.contains(&format!(
+                ".badge[data-zen-scope=\"{id}\"]::before {{ content: \"*\"; animation: pulse-{id} 1s infinite; }}"
+            )),
+            "{}",
+            css
+        );
+        assert!(css.contains(&format!("@keyframes pulse-{id} ")), "{}", css);
+    }
+
+    #[test]
+    fn test_unscoped_by_default() {
+        let (result, css) = compile(None);
+        assert!(
+            result
+                .html
+                .contains("<div class=\"badge\"><span>New</span></div>"),
+            "{}",
+            result.html
+        );
+        assert!(css.contains(".badge::before { content: \"*\"; animation: pulse 1s"));
+    }
+
+    #[test]
+    fn test_selector_edge_cases() {
+        let scoped = scope_css(
+            "/* a, b */\na:after, :is(h1, h2) + p[title=\"x y\"] { color: red }\n@font-face { font-family: X; }",
+            "s",
+            "zen-s",
+            ScopeStrategy::ClassSuffix,
+        );
+        assert_eq!(
+            scoped,
+            "/* a, b */\na.zen-s:after, :is(h1, h2) + p[title=\"x y\"].zen-s { color: red }\n@font-face { font-family: X; }"
+        );
+        assert!(ScopeStrategy::from_name("Where-Wrapper").is_ok());
+        assert!(ScopeStrategy::from_name("shadow")
+            .unwrap_err()
+            .starts_with("Z-ERR-CSS-SCOPE"));
+    }
+}
@@ -0,0 +1,229 @@
+//! Class Usage Cross-Check
+//!
+//! `ZenIR.css_classes` lists the classes the resolved template can carry (see
+//! `finalize::collect_css_classes`); bundlers prune CSS with it. With
+//! `CompileOptions.warn_unstyled_classes` it is also held against the selectors of
+//! the page's style blocks, component styles included:
+//!
+//! - `Z-WARN-UNSTYLED-CLASS`: template classes no selector mentions
+//! - `Z-WARN-UNUSED-CSS-CLASS`: rules whose whole selector is one class (`.card`,
+//!   `.card:hover`) that no template class matches, i.e. dead CSS
+//!
+//! Classes in compound or descendant selectors count as styled but are never
+//! reported as dead, since scripts may add them. Nothing is reported as dead
+//! either when a `class` expression renders names not known at compile time
+//! (`ZenManifestExport.has_dynamic_classes`). Classes matching
+//! `unstyled_class_ignore` (`^js-`, utility prefixes) are skipped both ways.
+
+use std::collections::BTreeSet;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::global_styles::{selector_classes, selector_list};
+use crate::validate::StyleIR;
+
+lazy_static! {
+    /// `.name`, optionally followed by pseudo-classes or pseudo-elements
+    static ref SIMPLE_CLASS_SELECTOR: Regex =
+        Regex::new(r"^\.(-?[_a-zA-Z][_a-zA-Z0-9-]*)(::?[a-zA-Z-]+(\([^()]*\))?)*$").unwrap();
+}
+
+/// Both warning directions for `classes` against `styles`, dead CSS only when
+/// every class is known (not `dynamic`). `ignore` is a regex; an invalid one is
+/// a `Z-ERR-UNSTYLED-CLASS-IGNORE` error.
+pub(crate) fn check_class_usage(
+    classes: &[String],
+    dynamic: bool,
+    styles: &[StyleIR],
+    ignore: Option<&str>,
+    file_path: &str,
+) -> Result<Vec<String>, String> {
+    let ignore = ignore
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|e| {
+                format!(
+                    "Z-ERR-UNSTYLED-CLASS-IGNORE: invalid pattern `{}` in {}: {}",
+                    pattern, file_path, e
+                )
+            })
+        })
+        .transpose()?;
+    let checked = |class: &str| !ignore.as_ref().is_some_and(|re| re.is_match(class));
+
+    let mut styled = BTreeSet::new();
+    let mut simple = BTreeSet::new();
+    for style in styles {
+        styled.extend(selector_classes(&style.raw));
+        for selector in selector_list(&style.raw) {
+            if let Some(cap) = SIMPLE_CLASS_SELECTOR.captures(&selector) {
+                simple.insert(cap[1].to_string());
+            }
+        }
+    }
+
+    let unstyled: Vec<&String> = classes
+        .iter()
+        .filter(|c| checked(c) && !styled.contains(*c))
+        .collect();
+    let unused: Vec<&String> = simple
+        .iter()
+        .filter(|c| !dynamic && checked(c) && !classes.contains(c))
+        .collect();
+
+    let mut warnings = Vec::new();
+    if !unstyled.is_empty() {
+        warnings.push(format!(
+            "Z-WARN-UNSTYLED-CLASS: {} uses {} with no matching style rule.",
+            file_path,
+            list(&unstyled)
+        ));
+    }
+    if !unused.is_empty() {
+        warnings.push(format!(
+            "Z-WARN-UNUSED-CSS-CLASS: {} in the styles of {} matches no class in the template.",
+            list(&unused),
+            file_path
+        ));
+    }
+    Ok(warnings)
+}
+
+fn list(classes: &[&String]) -> String {
+    classes
+        .iter()
+        .map(|c| format!("`.{}`", c))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::component::ComponentIR;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+    use std::collections::HashMap;
+
+    const CARD: &str = "<script>\nprop title\n</script>\n<div class=\"card\"><h2 class={'card-title'}>{title}</h2><slot /></div>\n<style>\n.card { padding: 1rem; }\n.card-title { margin: 0; }\n</style>";
+
+    fn compile(page: &str, ignore: Option<&str>) -> CompileResult {
+        let card = ComponentIR::from_source("Card", "components/Card.zen", CARD).unwrap();
+        let mut components = HashMap::new();
+        components.insert("Card".to_string(), serde_json::to_value(card).unwrap());
+        let result = compile_zen_internal(
+            page,
+            "page.zen",
+            CompileOptions {
+                components,
+                warn_unstyled_classes: true,
+                unstyled_class_ignore: ignore.map(String::from),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        result
+    }
+
+    fn warnings<'r>(result: &'r CompileResult, prefix: &str) -> Vec<&'r String> {
+        result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with(prefix))
+            .collect()
+    }
+
+    #[test]
+    fn test_classes_collected_through_component_boundary() {
+        let result = compile(
+            "<main class=\"page\"><Card title=\"Hi\"><p class=\"body\">x</p></Card></main>\n<style>\n.page { margin: 0 auto; }\n.body { color: gray; }\n</style>",
+            None,
+        );
+        assert_eq!(
+            result.manifest.as_ref().unwrap().css_classes,
+            vec!["body", "card", "card-title", "page"]
+        );
+        assert!(
+            warnings(&result, "Z-WARN-UNSTYLED-CLASS").is_empty(),
+            "{:?}",
+            result.warnings
+        );
+        assert!(warnings(&result, "Z-WARN-UNUSED-CSS-CLASS").is_empty());
+    }
+
+    #[test]
+    fn test_unstyled_classes_and_dead_rules_warn() {
+        let result = compile(
+            "<main class=\"page hero\"><Card title=\"Hi\" /></main>\n<style>\n.page { margin: 0; }\n.banner:hover { color: red; }\nmain .legacy { display: none; }\n</style>",
+            None,
+        );
+        assert_eq!(
+            warnings(&result, "Z-WARN-UNSTYLED-CLASS"),
+            vec!["Z-WARN-UNSTYLED-CLASS: page.zen uses `.hero` with no matching style rule."]
+        );
+        // `.legacy` sits in a descendant selector and is not reported
+        assert_eq!(
+            warnings(&result, "Z-WARN-UNUSED-CSS-CLASS"),
+            vec!["Z-WARN-UNUSED-CSS-CLASS: `.banner` in the styles of page.zen matches no class in the template."]
+        );
+    }
+
+    #[test]
+    fn test_every_branch_of_a_class_expression_counts() {
+        let result = compile(
+            "<script>\nstate active = false\nstate size = \"sm\"\n</script>\n<main class={active ? 'page is-active' : 'page'}><p class={`note note-${size === 'lg' ? 'lg' : 'sm'}`}>x</p><p class={active && 'lit'}>y</p><Card title=\"Hi\" /></main>\n<style>\n.page { margin: 0; }\n.banner { color: red; }\n</style>",
+            None,
+        );
+        let manifest = result.manifest.as_ref().unwrap();
+        assert_eq!(
+            manifest.css_classes,
+            vec![
+                "card",
+                "card-title",
+                "is-active",
+                "lit",
+                "note",
+                "note-lg",
+                "note-sm",
+                "page"
+            ]
+        );
+        assert!(!manifest.has_dynamic_classes);
+        assert_eq!(warnings(&result, "Z-WARN-UNUSED-CSS-CLASS").len(), 1);
+
+        // A name only known at runtime keeps every rule
+        let result = compile(
+            "<script>\nstate theme = \"dark\"\n</script>\n<main class={`page ${theme}`}><Card title=\"Hi\" /></main>\n<style>\n.page { margin: 0; }\n.banner { color: red; }\n</style>",
+            None,
+        );
+        let manifest = result.manifest.as_ref().unwrap();
+        assert!(manifest.has_dynamic_classes);
+        assert!(manifest.css_classes.contains(&"card".to_string()));
+        assert!(
+            warnings(&result, "Z-WARN-UNUSED-CSS-CLASS").is_empty(),
+            "{:?}",
+            result.warnings
+        );
+    }
+
+    #[test]
+    fn test_ignore_pattern_is_respected() {
+        let result = compile(
+            "<main class=\"js-root u-flex\"><Card title=\"Hi\" /></main>\n<style>\n.u-hidden { display: none; }\n</style>",
+            Some("^(js-|u-)"),
+        );
+        assert!(
+            warnings(&result, "Z-WARN-UNSTYLED-CLASS").is_empty(),
+            "{:?}",
+            result.warnings
+        );
+        assert!(warnings(&result, "Z-WARN-UNUSED-CSS-CLASS").is_empty());
+        // Off by default
+        let result = compile_zen_internal(
+            "<main class=\"hero\"></main>",
+            "page.zen",
+            CompileOptions::default(),
+        )
+        .unwrap();
+        assert!(warnings(&result, "Z-WARN-UNSTYLED-CLASS").is_empty());
+    }
+}
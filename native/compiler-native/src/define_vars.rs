@@ -0,0 +1,364 @@
+//! `define:vars` on Inline Scripts
+//!
+//! `<script is:inline>` bodies are stashed before expression normalization and
+//! restored verbatim, so `{...}` inside them is never an expression.
+//! `define:vars={{ key: expr }}` is the explicit way to pass build-time values in:
+//! each value is evaluated statically against the document scope and static
+//! props, and `const key = <json literal>;` is prepended to the script body.
+//! The attribute and its expression are removed; the body itself is untouched.
+//!
+//! Values are literals, template literals and names with a static value; literal
+//! numbers, booleans and `null` keep their type, every other value is emitted as
+//! a string. Anything computed (`1 + 2`, calls, member access) and names that
+//! need runtime state are `Z-ERR-DEFINE-VARS`.
+
+use std::collections::HashMap;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{Expression, UnaryOperator};
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use crate::document::DocumentScope;
+use crate::validate::{AttributeValue, ElementNode, TemplateNode, TextNode, ZenIR};
+
+const DEFINE_VARS_ATTR: &str = "define:vars";
+
+/// Values `define:vars` may read: static props, shadowed by document consts
+pub(crate) fn static_values(
+    scope: Option<&DocumentScope>,
+    props: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    let mut values = props.clone();
+    if let Some(scope) = scope {
+        values.extend(scope.props.clone());
+        values.extend(scope.locals.clone());
+    }
+    values
+}
+
+/// Inline `define:vars` into every `is:inline` script of the page.
+/// Returns `Z-ERR-DEFINE-VARS` errors for values that cannot be evaluated.
+pub(crate) fn apply_define_vars(ir: &mut ZenIR, values: &HashMap<String, String>) -> Vec<String> {
+    let mut inliner = Inliner {
+        values,
+        file_path: &ir.file_path,
+        errors: Vec::new(),
+        removed: Vec::new(),
+    };
+    inliner.visit(&mut ir.template.nodes);
+    let Inliner {
+        errors, removed, ..
+    } = inliner;
+    ir.template.expressions.retain(|e| !removed.contains(&e.id));
+    errors
+}
+
+struct Inliner<'a> {
+    values: &'a HashMap<String, String>,
+    file_path: &'a str,
+    errors: Vec<String>,
+    /// Expression ids of the removed attributes
+    removed: Vec<String>,
+}
+
+impl Inliner<'_> {
+    fn visit(&mut self, nodes: &mut [TemplateNode]) {
+        for node in nodes {
+            match node {
+                TemplateNode::Element(el) if is_inline_script(el) => self.inline(el),
+                TemplateNode::Element(el) => self.visit(&mut el.children),
+                TemplateNode::Component(c) => self.visit(&mut c.children),
+                TemplateNode::ConditionalFragment(cf) => {
+                    self.visit(&mut cf.consequent);
+                    self.visit(&mut cf.alternate);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    for nodes in mb.children_mut() {
+                        self.visit(nodes);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => self.visit(&mut of.fragment),
+                TemplateNode::LoopFragment(lf) => self.visit(&mut lf.body),
+                _ => {}
+            }
+        }
+    }
+
+    fn inline(&mut self, script: &mut ElementNode) {
+        let Some(at) = script
+            .attributes
+            .iter()
+            .position(|a| a.name == DEFINE_VARS_ATTR)
+        else {
+            return;
+        };
+        let code = match script.attributes.remove(at).value {
+            AttributeValue::Dynamic(expr) => {
+                self.removed.push(expr.id);
+                expr.code
+            }
+            AttributeValue::Static(value) => value,
+        };
+
+        let Some(entries) = object_entries(&code) else {
+            self.errors.push(format!(
+                "Z-ERR-DEFINE-VARS: define:vars on an is:inline script in {} expects an object literal, e.g. define:vars={{{{ id: siteId }}}}; got `{}`.",
+                self.file_path,
+                code.trim()
+            ));
+            return;
+        };
+
+        let mut declarations = String::new();
+        for (key, expr) in entries {
+            if !crate::static_eval::is_valid_identifier(&key) {
+                self.errors.push(format!(
+                    "Z-ERR-DEFINE-VARS: `{}` in define:vars on an is:inline script in {} is not a valid variable name.",
+                    key, self.file_path
+                ));
+                continue;
+            }
+            if !is_plain_value(&expr) {
+                self.errors.push(format!(
+                    "Z-ERR-DEFINE-VARS: `{}: {}` in define:vars on an is:inline script in {} is computed. Values must be literals, template literals or names from the document scope or static props; compute it in the script instead.",
+                    key, expr, self.file_path
+                ));
+                continue;
+            }
+            match crate::static_eval::static_eval(&expr, self.values) {
+                Some(value) => {
+                    declarations.push_str(&format!(
+                        "const {} = {};\n",
+                        key,
+                        json_literal(&expr, &value)
+                    ));
+                }
+                None => self.errors.push(format!(
+                    "Z-ERR-DEFINE-VARS: `{}: {}` in define:vars on an is:inline script in {} cannot be evaluated at build time. Values must be literals or resolve from the document scope or static props.",
+                    key, expr, self.file_path
+                )),
+            }
+        }
+        if declarations.is_empty() {
+            return;
+        }
+
+        match script.children.first_mut() {
+            Some(TemplateNode::Text(body)) => body.value.insert_str(0, &declarations),
+            _ => script.children.insert(
+                0,
+                TemplateNode::Text(TextNode {
+                    value: declarations,
+                    location: script.location.clone(),
+                    loop_context: script.loop_context.clone(),
+                    ordinal: 0,
+                    extra: Default::default(),
+                }),
+            ),
+        }
+    }
+}
+
+pub(crate) fn is_inline_script(el: &ElementNode) -> bool {
+    el.tag.eq_ignore_ascii_case("script") && el.attributes.iter().any(|a| a.name == "is:inline")
+}
+
+/// `(key, value expression)` pairs of `{ a: x, b }`, or None if `code` is not an object literal
+fn object_entries(code: &str) -> Option<Vec<(String, String)>> {
+    let inner = code.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let entries = split_top_level(inner, ',')
+        .into_iter()
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match split_top_level(entry, ':').as_slice() {
+            // Shorthand `{ siteId }`
+            [key] => (key.trim().to_string(), key.trim().to_string()),
+            [key, ..] => {
+                let key = key.trim();
+                let value = entry[key.len()..].trim_start().trim_start_matches(':');
+                (unquote(key).to_string(), value.trim().to_string())
+            }
+            [] => (String::new(), String::new()),
+        })
+        .collect();
+    Some(entries)
+}
+
+/// `text` split at `separator` outside strings and brackets
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' | '`' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+fn unquote(key: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|q| key.strip_prefix(*q)?.strip_suffix(*q))
+        .unwrap_or(key)
+}
+
+/// Whether `code` is a literal, a name, or a template literal of those
+fn is_plain_value(code: &str) -> bool {
+    fn plain(expr: &Expression) -> bool {
+        match expr {
+            Expression::StringLiteral(_)
+            | Expression::NumericLiteral(_)
+            | Expression::BooleanLiteral(_)
+            | Expression::NullLiteral(_)
+            | Expression::Identifier(_) => true,
+            Expression::UnaryExpression(unary) => {
+                unary.operator == UnaryOperator::UnaryNegation
+                    && matches!(unary.argument, Expression::NumericLiteral(_))
+            }
+            Expression::TemplateLiteral(template) => template.expressions.iter().all(plain),
+            Expression::ParenthesizedExpression(paren) => plain(&paren.expression),
+            _ => false,
+        }
+    }
+
+    let allocator = Allocator::default();
+    Parser::new(&allocator, code, SourceType::default())
+        .parse_expression()
+        .is_ok_and(|expr| plain(&expr))
+}
+
+/// JS literal for an evaluated value; `</` is escaped so the value cannot close the script
+fn json_literal(expr: &str, value: &str) -> String {
+    let expr = expr.trim();
+    if matches!(expr, "true" | "false" | "null") || expr.parse::<f64>().is_ok() {
+        return value.to_string();
+    }
+    serde_json::to_string(value)
+        .unwrap_or_default()
+        .replace("</", "<\\/")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+    use std::collections::HashMap;
+
+    fn compile(page: &str, props: &[(&str, &str)]) -> CompileResult {
+        let options = CompileOptions {
+            props: props
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+            ..Default::default()
+        };
+        compile_zen_internal(page, "page.zen", options).unwrap()
+    }
+
+    #[test]
+    fn test_define_vars_prepends_literals() {
+        let result = compile(
+            "<main><h1>Hi</h1><script is:inline define:vars={{ siteId, version: 3, debug: false, label: `v${version}` }}>track(siteId, { version, label });</script></main>",
+            &[("siteId", "UA-1</script>"), ("version", "3")],
+        );
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(
+            result.html.contains(
+                "const siteId = \"UA-1<\\/script>\";\nconst version = 3;\nconst debug = false;\nconst label = \"v3\";\ntrack(siteId, { version, label });</script>"
+            ),
+            "{}",
+            result.html
+        );
+        assert!(!result.html.contains("define:vars"), "{}", result.html);
+    }
+
+    #[test]
+    fn test_define_vars_rejects_runtime_values() {
+        let result = compile(
+            "<script>\nstate user = { name: 'Ada' }\n</script>\n<main><script is:inline define:vars={{ name: user.name, ok: 1 }}>greet(name);</script></main>",
+            &[],
+        );
+        assert!(result.has_errors);
+        let errors: Vec<_> = result
+            .errors
+            .iter()
+            .filter(|e| e.starts_with("Z-ERR-DEFINE-VARS"))
+            .collect();
+        assert_eq!(errors.len(), 1, "{:?}", result.errors);
+        assert!(
+            errors[0].contains("`name: user.name`") && errors[0].contains("page.zen"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn test_define_vars_rejects_computed_values() {
+        // `+` would otherwise be read as string concatenation (`"12"`)
+        let result = compile(
+            "<main><script is:inline define:vars={{ sum: 1 + 2, offset: -1 }}>use(sum, offset);</script></main>",
+            &[],
+        );
+        let errors: Vec<_> = result
+            .errors
+            .iter()
+            .filter(|e| e.starts_with("Z-ERR-DEFINE-VARS"))
+            .collect();
+        assert_eq!(errors.len(), 1, "{:?}", result.errors);
+        assert!(
+            errors[0].contains("`sum: 1 + 2`") && errors[0].contains("is computed"),
+            "{}",
+            errors[0]
+        );
+        assert!(
+            result.html.contains("const offset = -1;"),
+            "{}",
+            result.html
+        );
+        assert!(!result.html.contains("\"12\""), "{}", result.html);
+    }
+
+    #[test]
+    fn test_inline_script_attributes_survive() {
+        let result = compile(
+            "<main><script is:inline type=\"module\" defer nonce=\"r4nd\">import(\"./a.js\").then((m) => m.run({ x: 1 }));</script><script is:inline async>ping('a' && \"b\");</script></main>",
+            &[],
+        );
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let html = &result.html;
+        assert!(html.contains("type=\"module\""), "{}", html);
+        assert!(html.contains("defer"), "{}", html);
+        assert!(html.contains("nonce=\"r4nd\""), "{}", html);
+        assert!(html.contains("async"), "{}", html);
+        // Script bodies are raw text, not escaped
+        assert!(
+            html.contains(">import(\"./a.js\").then((m) => m.run({ x: 1 }));</script>"),
+            "{}",
+            html
+        );
+        assert!(html.contains(">ping('a' && \"b\");</script>"), "{}", html);
+        assert!(!html.contains("data-zen-inline-id"), "{}", html);
+    }
+}
@@ -0,0 +1,263 @@
+//! Dependency Graph
+//!
+//! File-level dependencies of a compiled page, for incremental rebuilds and
+//! bundle analysis. Edges follow what each file's own source refers to:
+//!
+//! - page/layout/component → the layouts and components whose tags it writes
+//!   (slot content belongs to the file that passes it, not the receiving component)
+//! - any file → npm packages its script imports (type-only imports excluded)
+//! - any file → external `<script src>` assets its template loads
+//!
+//! Component nodes use `ComponentIR.path`, so they are real file paths. Nodes
+//! are sorted by id and edges by (from, to), so the output is deterministic.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::validate::{AttributeValue, TemplateNode};
+
+lazy_static::lazy_static! {
+    /// Static `import ... from 'x'` / `import 'x'` (group 1 marks `import type`)
+    static ref IMPORT_SOURCE_RE: Regex = Regex::new(
+        r#"(?m)^\s*import\s+(type\s+)?(?:[\w$*{}\s,]+?\s+from\s*)?['"]([^'"]+)['"]"#
+    )
+    .unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum DepKind {
+    Page,
+    Layout,
+    Component,
+    Npm,
+    Asset,
+}
+
+impl DepKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DepKind::Page => "page",
+            DepKind::Layout => "layout",
+            DepKind::Component => "component",
+            DepKind::Npm => "npm",
+            DepKind::Asset => "asset",
+        }
+    }
+
+    fn dot_shape(self) -> &'static str {
+        match self {
+            DepKind::Page | DepKind::Layout | DepKind::Component => "box",
+            DepKind::Npm => "ellipse",
+            DepKind::Asset => "note",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DepNode {
+    /// `kind:path`, unique within the graph
+    pub id: String,
+    pub kind: DepKind,
+    /// File path, npm package name, or script src
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DepGraph {
+    pub nodes: Vec<DepNode>,
+    /// (from, to) indices into `nodes`
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl DepGraph {
+    /// Graphviz rendering (`dot -Tsvg`)
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph deps {\n");
+        for node in &self.nodes {
+            out.push_str(&format!(
+                "  \"{}\" [label=\"{}\", shape={}];\n",
+                dot_escape(&node.id),
+                dot_escape(&node.path),
+                node.kind.dot_shape()
+            ));
+        }
+        for (from, to) in &self.edges {
+            out.push_str(&format!(
+                "  \"{}\" -> \"{}\";\n",
+                dot_escape(&self.nodes[*from].id),
+                dot_escape(&self.nodes[*to].id)
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Collects nodes and edges by id during resolution; `build` sorts and indexes them.
+#[derive(Debug, Default)]
+pub(crate) struct DepGraphBuilder {
+    nodes: BTreeMap<String, DepNode>,
+    edges: BTreeSet<(String, String)>,
+    /// Files whose own references have been recorded
+    visited: BTreeSet<String>,
+}
+
+impl DepGraphBuilder {
+    /// Add a node (the first kind recorded for an id wins) and return its id
+    pub(crate) fn node(&mut self, kind: DepKind, path: &str) -> String {
+        let id = format!("{}:{}", kind.as_str(), path);
+        self.nodes.entry(id.clone()).or_insert_with(|| DepNode {
+            id: id.clone(),
+            kind,
+            path: path.to_string(),
+        });
+        id
+    }
+
+    pub(crate) fn edge(&mut self, from: &str, to: &str) {
+        self.edges.insert((from.to_string(), to.to_string()));
+    }
+
+    /// True the first time a file is seen; its references only need recording once
+    pub(crate) fn first_visit(&mut self, id: &str) -> bool {
+        self.visited.insert(id.to_string())
+    }
+
+    /// Npm packages imported by `script` and external scripts loaded by `nodes`
+    pub(crate) fn record_imports_and_assets(
+        &mut self,
+        owner: &str,
+        script: Option<&str>,
+        nodes: &[TemplateNode],
+    ) {
+        for package in script.map(npm_packages).unwrap_or_default() {
+            let id = self.node(DepKind::Npm, &package);
+            self.edge(owner, &id);
+        }
+        let mut refs = TemplateRefs::default();
+        template_refs(nodes, &mut refs);
+        for src in refs.scripts {
+            let id = self.node(DepKind::Asset, &src);
+            self.edge(owner, &id);
+        }
+    }
+
+    pub(crate) fn build(self) -> DepGraph {
+        let index: BTreeMap<&str, usize> = self
+            .nodes
+            .keys()
+            .enumerate()
+            .map(|(i, id)| (id.as_str(), i))
+            .collect();
+        let edges = self
+            .edges
+            .iter()
+            .map(|(from, to)| (index[from.as_str()], index[to.as_str()]))
+            .collect();
+        DepGraph {
+            nodes: self.nodes.into_values().collect(),
+            edges,
+        }
+    }
+}
+
+/// References written directly in a template
+#[derive(Debug, Default)]
+pub(crate) struct TemplateRefs {
+    /// Component tags, including those inside other components' slot content
+    pub components: Vec<String>,
+    /// Static `src` of `<script>` elements
+    pub scripts: Vec<String>,
+}
+
+pub(crate) fn template_refs(nodes: &[TemplateNode], refs: &mut TemplateRefs) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                if el.tag.eq_ignore_ascii_case("script") {
+                    let src = el.attributes.iter().find(|a| a.name == "src");
+                    if let Some(AttributeValue::Static(src)) = src.map(|a| &a.value) {
+                        refs.scripts.push(src.trim().to_string());
+                    }
+                }
+                template_refs(&el.children, refs);
+            }
+            TemplateNode::Component(c) => {
+                refs.components.push(c.name.clone());
+                template_refs(&c.children, refs);
+            }
+            TemplateNode::ConditionalFragment(cf) => {
+                template_refs(&cf.consequent, refs);
+                template_refs(&cf.alternate, refs);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    template_refs(nodes, refs);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => template_refs(&of.fragment, refs),
+            TemplateNode::LoopFragment(lf) => template_refs(&lf.body, refs),
+            _ => {}
+        }
+    }
+}
+
+/// Packages named by the script's runtime imports (`date-fns/format` → `date-fns`)
+fn npm_packages(script: &str) -> Vec<String> {
+    IMPORT_SOURCE_RE
+        .captures_iter(script)
+        .filter(|c| c.get(1).is_none())
+        .filter_map(|c| npm_package(&c[2]))
+        .collect()
+}
+
+/// Relative, absolute, `.zen`, and scheme-prefixed (`node:`, `https:`) sources are not packages
+fn npm_package(source: &str) -> Option<String> {
+    if source.starts_with('.')
+        || source.starts_with('/')
+        || source.contains(':')
+        || source.ends_with(".zen")
+    {
+        return None;
+    }
+    let mut parts = source.split('/');
+    let first = parts.next()?;
+    if first.starts_with('@') {
+        parts.next().map(|name| format!("{}/{}", first, name))
+    } else {
+        Some(first.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_npm_packages_from_imports() {
+        let script = "import { format } from 'date-fns/format';\nimport type { Props } from '@acme/ui/types';\nimport '@acme/ui/styles';\nimport Card from './Card.zen';\nimport { helper } from './utils';\nimport fs from 'node:fs';";
+        assert_eq!(npm_packages(script), vec!["date-fns", "@acme/ui"]);
+    }
+
+    #[test]
+    fn test_build_is_sorted_and_indexed() {
+        let mut builder = DepGraphBuilder::default();
+        let page = builder.node(DepKind::Page, "page.zen");
+        let card = builder.node(DepKind::Component, "Card.zen");
+        builder.edge(&page, &card);
+
+        let graph = builder.build();
+        assert_eq!(graph.nodes[0].id, "component:Card.zen");
+        assert_eq!(graph.nodes[1].id, "page:page.zen");
+        assert_eq!(graph.edges, vec![(1, 0)]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Nesting limit for the passes that still walk the node tree recursively
+//! (component resolution, fragment lowering and the analyses). DOM conversion,
+//! rendering and codegen use explicit stacks; the rest stop at the limit with
+//! `Z-ERR-MAX-DEPTH`. Compiles run on a thread whose stack fits the limit
+//! (`with_stack`), so markup just under it compiles whatever stack the caller
+//! has.
+
+use markup5ever_rcdom::{Handle, NodeData};
+
+pub const MAX_DEPTH_ERROR: &str = "Z-ERR-MAX-DEPTH";
+
+/// Far beyond hand-written markup; generated pages have been seen near 2,000
+pub const DEFAULT_MAX_DEPTH: usize = 5000;
+
+/// Stack the recursive passes may take per level of nesting; the deepest takes
+/// about 1.5KB in unoptimized builds
+const STACK_PER_LEVEL: usize = 4 * 1024;
+
+/// Stack for everything that does not grow with nesting
+const BASE_STACK: usize = 8 * 1024 * 1024;
+
+/// Run `compile` on a thread with room for `max_depth` levels of the recursive
+/// passes. Panics are passed on to the caller.
+pub(crate) fn with_stack<T: Send>(
+    max_depth: usize,
+    compile: impl FnOnce() -> T + Send,
+) -> Result<T, String> {
+    // Unit tests count work in thread-locals; `tests/deep_nesting.rs` covers the
+    // worker thread
+    if cfg!(test) {
+        return Ok(compile());
+    }
+    let stack_size = max_depth
+        .saturating_mul(STACK_PER_LEVEL)
+        .saturating_add(BASE_STACK);
+    // The caller's subscriber is thread-local; it and the open span carry over
+    #[cfg(feature = "tracing")]
+    let compile = {
+        let dispatch = tracing::dispatcher::get_default(|d| d.clone());
+        let span = tracing::Span::current();
+        move || {
+            tracing::dispatcher::with_default(&dispatch, || {
+                let _span = span.entered();
+                compile()
+            })
+        }
+    };
+    std::thread::scope(|scope| {
+        let worker = std::thread::Builder::new()
+            .name("zenith-compile".to_string())
+            .stack_size(stack_size)
+            .spawn_scoped(scope, compile)
+            .map_err(|e| {
+                format!(
+                    "{}: cannot start a compile thread for a nesting limit of {}: {}",
+                    MAX_DEPTH_ERROR, max_depth, e
+                )
+            })?;
+        Ok(worker
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+    })
+}
+
+/// The message for markup nested `depth` levels deep; `path` is the tags
+/// leading to the first node past `max_depth`
+pub(crate) fn too_deep(depth: usize, max_depth: usize, path: &[String], file: &str) -> String {
+    format!(
+        "{}: markup in {} is nested {} levels deep, past the limit of {}, at `{}`. Flatten the markup or raise `maxDepth`.",
+        MAX_DEPTH_ERROR,
+        file,
+        depth,
+        max_depth,
+        describe_path(path)
+    )
+}
+
+/// `main > ul > li > ul` with runs of one tag collapsed (`div ×4998`)
+fn describe_path(path: &[String]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < path.len() {
+        let run = path[i..].iter().take_while(|t| **t == path[i]).count();
+        parts.push(match run {
+            1 => path[i].clone(),
+            n => format!("{} \u{d7}{}", path[i], n),
+        });
+        i += run;
+    }
+    parts.join(" > ")
+}
+
+/// Elements of a parsed document nested deeper than `max_depth`: the deepest
+/// nesting and the tags leading to the first element past the limit. Iterative,
+/// since it runs before anything that recurses over the document.
+pub(crate) fn check_dom(document: &Handle, max_depth: usize) -> Option<(usize, Vec<String>)> {
+    let mut stack: Vec<(Handle, usize)> = vec![(document.clone(), 0)];
+    let mut path: Vec<String> = Vec::new();
+    let mut deepest = 0;
+    let mut first_past: Option<Vec<String>> = None;
+    while let Some((node, depth)) = stack.pop() {
+        let depth = match &node.data {
+            NodeData::Element { name, .. } => {
+                path.truncate(depth);
+                path.push(name.local.to_string());
+                deepest = deepest.max(depth + 1);
+                if depth + 1 > max_depth && first_past.is_none() {
+                    first_past = Some(path.clone());
+                }
+                depth + 1
+            }
+            _ => depth,
+        };
+        for child in node.children.borrow().iter().rev() {
+            stack.push((child.clone(), depth));
+        }
+    }
+    first_past.map(|path| (deepest, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nested(tag: &str, depth: usize) -> String {
+        format!(
+            "<main>{}x{}</main>",
+            format!("<{}>", tag).repeat(depth),
+            format!("</{}>", tag).repeat(depth)
+        )
+    }
+
+    #[test]
+    fn test_deep_page_errors_without_crashing() {
+        // The test harness's default stack: nothing may recurse before the guard
+        let result = std::thread::Builder::new()
+            .stack_size(2 * 1024 * 1024)
+            .spawn(|| {
+                crate::parse::compile_zen_internal(
+                    &nested("div", 10_000),
+                    "deep.zen",
+                    Default::default(),
+                )
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        let err = result.unwrap_err();
+        assert!(err.contains(MAX_DEPTH_ERROR), "{}", err);
+        assert!(
+            err.contains("levels deep, past the limit of 5000"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_error_names_depth_and_path() {
+        let source = nested("ul", 40);
+        let compile = |max_depth| {
+            let options = crate::parse::CompileOptions {
+                max_depth,
+                ..Default::default()
+            };
+            crate::parse::compile_zen_internal(&source, "page.zen", options)
+        };
+        assert!(compile(None).is_ok());
+        let err = compile(Some(20)).unwrap_err();
+        assert!(
+            err.contains("Z-ERR-MAX-DEPTH: markup in page.zen is nested 43 levels deep"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("at `html > body > main > ul \u{d7}18`"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_components_count_toward_the_limit() {
+        let list = crate::component::ComponentIR::from_source(
+            "List",
+            "List.zen",
+            "<ul><li><ul><li><slot /></li></ul></li></ul>",
+        )
+        .unwrap();
+        let options = |max_depth| crate::parse::CompileOptions {
+            components: [("List".to_string(), serde_json::to_value(&list).unwrap())].into(),
+            max_depth,
+            ..Default::default()
+        };
+        let page = "<main><List><List><p>x</p></List></List></main>";
+        assert!(crate::parse::compile_zen_internal(page, "page.zen", options(None)).is_ok());
+
+        // Each page element fits; the expansion does not
+        let err =
+            crate::parse::compile_zen_internal(page, "page.zen", options(Some(8))).unwrap_err();
+        assert!(err.contains(MAX_DEPTH_ERROR), "{}", err);
+        assert!(
+            err.contains("at `main > List > ul > li > ul > li > List > ul`"),
+            "{}",
+            err
+        );
+    }
+}
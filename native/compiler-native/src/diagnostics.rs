@@ -0,0 +1,223 @@
+//! Diagnostic Post-Processing
+//!
+//! Errors are collected by several phases, some of them iterating hash sets, so
+//! the raw list has no stable order and one root cause can appear many times.
+//! Before errors are returned they are normalized:
+//!
+//! 1. exact repeats are dropped
+//! 2. errors about the same identifier (same code and message, e.g. one undeclared
+//!    name used in five expressions) become one error with an occurrence count and
+//!    the list of places, capped at `MAX_LISTED_OCCURRENCES`
+//! 3. the result is sorted by (file, line, column, code), then by message
+//!
+//! Only the list changes; whether a compile has errors never does.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Places listed in a grouped error before the rest is summarized as `+N more`
+const MAX_LISTED_OCCURRENCES: usize = 10;
+
+lazy_static! {
+    /// `Z-ERR-SCOPE-002: Identifier `x` is not declared ...` followed by where it occurred
+    static ref IDENTIFIER_ERROR: Regex =
+        Regex::new(r"^(Z-[A-Z]+-[A-Z0-9-]+: Identifier `[^`]+`.*?)(?:, in (.+)| \((.+)\))?$")
+            .unwrap();
+    /// Origin of a promoted expression: `at components/Card.zen (line 3, column 5)`
+    static ref ORIGIN_LOCATION: Regex =
+        Regex::new(r" at (\S+) \(line (\d+), column (\d+)\)").unwrap();
+    /// Invariant violation suffix: `(page.zen:3:5)`
+    static ref FILE_LOCATION: Regex = Regex::new(r"\(([^()\s]+):(\d+):(\d+)\)$").unwrap();
+    static ref FILE_MENTION: Regex = Regex::new(r" in (\S+?\.zen)\b").unwrap();
+}
+
+/// Deduplicated, grouped, and sorted form of `errors`
+pub(crate) fn normalize(errors: Vec<String>) -> Vec<String> {
+    // (shared message, places), in first-seen order
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for error in errors {
+        if !seen.insert(error.clone()) {
+            continue;
+        }
+        // Already grouped (errors pass through here again when phases are merged)
+        let caps = IDENTIFIER_ERROR
+            .captures(&error)
+            .filter(|_| !error.contains(" occurrences: "));
+        let Some(caps) = caps else {
+            groups.push((error, vec![]));
+            continue;
+        };
+        let message = caps[1].to_string();
+        let place = caps
+            .get(2)
+            .or_else(|| caps.get(3))
+            .map(|m| m.as_str().to_string());
+        match groups
+            .iter_mut()
+            .find(|(m, places)| *m == message && !places.is_empty())
+        {
+            Some((_, places)) => places.extend(place),
+            None => match place {
+                Some(place) => groups.push((message, vec![place])),
+                None => groups.push((error, vec![])),
+            },
+        }
+    }
+
+    let mut normalized: Vec<String> = groups
+        .into_iter()
+        .map(|(message, places)| match places.len() {
+            0 => message,
+            1 => rejoin(&message, &places[0]),
+            count => {
+                let mut listed: Vec<String> = places
+                    .iter()
+                    .take(MAX_LISTED_OCCURRENCES)
+                    .cloned()
+                    .collect();
+                if count > MAX_LISTED_OCCURRENCES {
+                    listed.push(format!("+{} more", count - MAX_LISTED_OCCURRENCES));
+                }
+                format!("{} ({} occurrences: {})", message, count, listed.join("; "))
+            }
+        })
+        .collect();
+    normalized.sort_by_cached_key(|error| (sort_key(error), error.clone()));
+    normalized
+}
+
+/// A single occurrence keeps its original wording
+fn rejoin(message: &str, place: &str) -> String {
+    if FILE_LOCATION.is_match(&format!("({})", place)) {
+        format!("{} ({})", message, place)
+    } else {
+        format!("{}, in {}", message, place)
+    }
+}
+
+/// (file, line, column, code); errors without a location sort first within their file
+fn sort_key(error: &str) -> (String, u32, u32, String) {
+    let code = error
+        .split_once(':')
+        .filter(|(code, _)| code.starts_with("Z-"))
+        .map_or(String::new(), |(code, _)| code.to_string());
+    let location = ORIGIN_LOCATION
+        .captures(error)
+        .or_else(|| FILE_LOCATION.captures(error));
+    match location {
+        Some(caps) => (
+            caps[1].to_string(),
+            caps[2].parse().unwrap_or(0),
+            caps[3].parse().unwrap_or(0),
+            code,
+        ),
+        None => {
+            let file = FILE_MENTION
+                .captures(error)
+                .map_or(String::new(), |caps| caps[1].to_string());
+            (file, 0, 0, code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::ComponentIR;
+    use std::collections::HashMap;
+
+    /// `Widget` with a state, so its template expressions are scope-checked
+    fn widget(template: &str) -> HashMap<String, serde_json::Value> {
+        let source = format!("<script>\nstate count = 0\n</script>\n{}", template);
+        let component =
+            ComponentIR::from_source("Widget", "components/Widget.zen", &source).unwrap();
+        let mut components = HashMap::new();
+        components.insert(
+            "Widget".to_string(),
+            serde_json::to_value(component).unwrap(),
+        );
+        components
+    }
+
+    /// Compiles a page rendering `<Widget />` once; scope errors fail component expansion
+    fn compile_with_widget(components: HashMap<String, serde_json::Value>) -> String {
+        crate::parse::compile_zen_internal(
+            "<main><Widget /></main>",
+            "page.zen",
+            crate::parse::CompileOptions {
+                components,
+                ..Default::default()
+            },
+        )
+        .unwrap_err()
+    }
+
+    fn scope_errors(err: &str) -> Vec<&str> {
+        err.lines()
+            .filter(|l| l.starts_with("Z-ERR-SCOPE-002"))
+            .collect()
+    }
+
+    #[test]
+    fn test_repeated_identifier_is_grouped() {
+        let err = compile_with_widget(widget(
+            "<p>{missing}</p><p>{missing}</p><p>{missing}</p><p>{missing}</p><p>{missing}</p>",
+        ));
+        let errors = scope_errors(&err);
+        assert_eq!(errors.len(), 1, "{}", err);
+        assert!(
+            errors[0].starts_with(
+                "Z-ERR-SCOPE-002: Identifier `missing` is not declared in state, props, or locals (5 occurrences: component Widget at components/Widget.zen"
+            ),
+            "{}",
+            errors[0]
+        );
+        assert_eq!(errors[0].matches("instance inst0").count(), 5);
+    }
+
+    #[test]
+    fn test_distinct_identifiers_and_codes_stay_separate() {
+        let err = compile_with_widget(widget("<p>{first}</p><p>{second}</p><p>{first}</p>"));
+        let errors = scope_errors(&err);
+        assert_eq!(errors.len(), 2, "{}", err);
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("`first`") && e.contains("(2 occurrences")));
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("`second`") && !e.contains("occurrences")));
+
+        let normalized = normalize(vec![
+            "Z-ERR-SCOPE-002: Identifier `x` is not declared in state, props, or locals".into(),
+            "Z-ERR-OTHER: Identifier `x` is not declared in state, props, or locals".into(),
+        ]);
+        assert_eq!(normalized.len(), 2, "{:?}", normalized);
+    }
+
+    #[test]
+    fn test_order_is_stable_and_repeats_are_dropped() {
+        let components = widget("<p>{b}</p><p>{a}</p><p>{c}</p><p>{a}</p>");
+        let first = compile_with_widget(components.clone());
+        assert_eq!(scope_errors(&first).len(), 3, "{}", first);
+        assert_eq!(compile_with_widget(components), first);
+
+        let errors = vec![
+            "Z-ERR-B: late (page.zen:9:1)".to_string(),
+            "Z-ERR-FILTER: unknown filter in page.zen".to_string(),
+            "Z-ERR-A: early (page.zen:2:4)".to_string(),
+            "Z-ERR-B: late (page.zen:9:1)".to_string(),
+            "Z-ERR-A: other file (about.zen:1:1)".to_string(),
+        ];
+        let expected = vec![
+            "Z-ERR-A: other file (about.zen:1:1)",
+            "Z-ERR-FILTER: unknown filter in page.zen",
+            "Z-ERR-A: early (page.zen:2:4)",
+            "Z-ERR-B: late (page.zen:9:1)",
+        ];
+        assert_eq!(normalize(errors.clone()), expected);
+        let mut reversed = errors;
+        reversed.reverse();
+        assert_eq!(normalize(reversed), expected);
+    }
+}
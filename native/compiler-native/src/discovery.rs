@@ -5,9 +5,16 @@
 
 // ═══════════════════════════════════════════════════════════════════════════════
 
-pub fn extract_styles_native(source: String) -> Vec<String> {
-    let re = regex::Regex::new(r"(?is)<style[^>]*>([\s\S]*?)</style>").unwrap();
-    re.captures_iter(&source)
-        .filter_map(|cap| cap.get(1).map(|m| m.as_str().trim().to_string()))
+/// `<style>` block contents with whether the block carries the `global` attribute
+pub(crate) fn extract_style_blocks(source: &str) -> Vec<(String, bool)> {
+    let re = regex::Regex::new(r"(?is)<style([^>]*)>([\s\S]*?)</style>").unwrap();
+    let global_re =
+        regex::Regex::new(r#"(?i)(^|\s)global(\s*=\s*("(true)?"|'(true)?'|true))?(\s|/?$)"#)
+            .unwrap();
+    re.captures_iter(source)
+        .filter_map(|cap| {
+            let raw = cap.get(2)?.as_str().trim().to_string();
+            Some((raw, global_re.is_match(&cap[1])))
+        })
         .collect()
 }
@@ -20,12 +20,14 @@
 //! - Runtime mutation
 
 use regex::Regex;
+use serde::Deserialize;
 use std::collections::HashMap;
 
 use crate::validate::TemplateNode;
 
 /// Document compilation scope containing resolved props and locals
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
 pub struct DocumentScope {
     /// Props passed to the document (e.g., from page/route)
     pub props: HashMap<String, String>,
@@ -184,33 +186,36 @@ pub fn extract_const_declarations(script: &str) -> HashMap<String, String> {
     consts
 }
 
-/// Execute document script at compile time to build scope
-/// This resolves const declarations using the provided props
-pub fn execute_document_script(
-    script: &str,
+/// Execute the scripts of nested layouts, outermost first, into one scope.
+/// Every script sees the merged props and the consts of the levels outside it;
+/// an inner const shadows an outer one with the same name.
+pub fn execute_document_chain(
+    scripts: &[&str],
     props: &HashMap<String, String>,
 ) -> Result<DocumentScope, DocumentCompileError> {
-    // First validate the script
-    validate_document_script(script)?;
-
     // Create scope with props
     let mut scope = DocumentScope::with_props(props.clone());
 
-    // Extract const declarations
-    let consts = extract_const_declarations(script);
+    for script in scripts {
+        // First validate the script
+        validate_document_script(script)?;
 
-    // Resolve each const declaration
-    // Note: Order matters - consts may reference earlier consts
-    for (name, expr) in &consts {
-        match resolve_const_expression(expr, &scope) {
-            Some(value) => {
-                scope.add_local(name.clone(), value);
-            }
-            None => {
-                return Err(DocumentCompileError::UnresolvedExpression {
-                    expr: expr.clone(),
-                    reason: format!("Cannot statically resolve const '{}'", name),
-                });
+        // Extract const declarations
+        let consts = extract_const_declarations(script);
+
+        // Resolve each const declaration
+        // Note: Order matters - consts may reference earlier consts
+        for (name, expr) in &consts {
+            match resolve_const_expression(expr, &scope) {
+                Some(value) => {
+                    scope.add_local(name.clone(), value);
+                }
+                None => {
+                    return Err(DocumentCompileError::UnresolvedExpression {
+                        expr: expr.clone(),
+                        reason: format!("Cannot statically resolve const '{}'", name),
+                    });
+                }
             }
         }
     }
@@ -396,23 +401,7 @@ fn resolve_variable_reference(expr: &str, scope: &DocumentScope) -> Option<Strin
 
 /// Check if string is a valid JavaScript identifier
 fn is_valid_identifier(s: &str) -> bool {
-    if s.is_empty() {
-        return false;
-    }
-
-    let mut chars = s.chars();
-    match chars.next() {
-        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {}
-        _ => return false,
-    }
-
-    for c in chars {
-        if !c.is_ascii_alphanumeric() && c != '_' && c != '$' {
-            return false;
-        }
-    }
-
-    true
+    oxc_syntax::identifier::is_identifier_name(s)
 }
 
 /// Resolve an expression using the document scope
@@ -474,6 +463,8 @@ mod tests {
             children: vec![],
             location: SourceLocation { line: 1, column: 1 },
             loop_context: None,
+            ordinal: 0,
+            extra: Default::default(),
         });
 
         let div_node = TemplateNode::Element(ElementNode {
@@ -482,6 +473,8 @@ mod tests {
             children: vec![],
             location: SourceLocation { line: 1, column: 1 },
             loop_context: None,
+            ordinal: 0,
+            extra: Default::default(),
         });
 
         assert!(is_document_module(&[html_node.clone()]));
@@ -0,0 +1,84 @@
+//! Iframe `srcdoc` Embedding
+//!
+//! `to_srcdoc` turns a compile result into the value of an `<iframe srcdoc="...">`
+//! attribute, for previews such as design-system docs. The bundle is inlined as a
+//! module script before `</body>`, with `</script` and `<!--` broken up so the
+//! HTML parser cannot end or comment out the script early. The whole document is
+//! then attribute-escaped, so the value contains no `"`, `<` or raw `&`.
+//!
+//! Each srcdoc iframe is its own browsing context with its own `window`, so
+//! several previews on one docs page never share runtime globals. Compile with
+//! `CompileOptions.embed_mode` so the bundle hydrates even when the iframe
+//! document is already interactive when the script runs. The bundle's imports
+//! (the runtime package included) are left as written; the docs page supplies an
+//! import map inside the document if it needs bare specifiers resolved.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::parse::CompileResult;
+
+lazy_static! {
+    static ref SCRIPT_END_RE: Regex = Regex::new(r"(?i)</(script)").unwrap();
+    static ref BODY_CLOSE_RE: Regex = Regex::new(r"(?i)</body\s*>").unwrap();
+}
+
+/// The `srcdoc` attribute value (without the surrounding quotes) for a compiled page
+pub fn to_srcdoc(result: &CompileResult) -> String {
+    let mut document = result.html.clone();
+    let bundle = result
+        .manifest
+        .as_ref()
+        .map(|m| m.bundle.as_str())
+        .filter(|b| !b.trim().is_empty());
+    if let Some(bundle) = bundle {
+        let script = format!("<script type=\"module\">{}</script>", escape_script(bundle));
+        match BODY_CLOSE_RE.find_iter(&document).last() {
+            Some(close) => document.insert_str(close.start(), &script),
+            None => document.push_str(&script),
+        }
+    }
+    escape_attribute(&document)
+}
+
+/// Script text that cannot close its `<script>` element or open an HTML comment.
+/// `<\/script` and `<\!--` read the same inside JS strings, regexes and comments.
+fn escape_script(code: &str) -> String {
+    SCRIPT_END_RE
+        .replace_all(code, r"<\/$1")
+        .replace("<!--", r"<\!--")
+}
+
+fn escape_attribute(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + value.len() / 8);
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_cannot_close_early() {
+        let escaped = escape_script("const s = '</SCRIPT><!-- x'; // </script>");
+        assert_eq!(escaped, r"const s = '<\/SCRIPT><\!-- x'; // <\/script>");
+    }
+
+    #[test]
+    fn test_attribute_escaping() {
+        assert_eq!(
+            escape_attribute(r#"<p title="a & 'b'">x</p>"#),
+            "&lt;p title=&quot;a &amp; &#39;b&#39;&quot;&gt;x&lt;/p&gt;"
+        );
+    }
+}
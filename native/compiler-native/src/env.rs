@@ -0,0 +1,438 @@
+//! Environment Regions
+//!
+//! Template content that only exists in some builds:
+//!
+//! ```html
+//! <!-- zen:if-env staging preview -->
+//! <Banner>Staging build</Banner>
+//! <!-- zen:end-env -->
+//! <aside zen:env="internal">{debugInfo}</aside>
+//! ```
+//!
+//! A region is kept when one of its names equals `CompileOptions.env`; otherwise it
+//! is cut from the source before the template is parsed, so its expressions are
+//! never normalized, its components never resolve, and nothing of it reaches the
+//! HTML, the bundle or the manifest. Cut text is replaced by its line breaks, so
+//! diagnostics for the rest of the file keep their line numbers.
+//!
+//! Comment regions nest and resolve innermost-first; each stripped region is
+//! reported (names and location only) in the manifest's `stripped_env_regions`.
+//! A `zen:env` element that is kept only loses the attribute. Unmatched or unclosed
+//! markers fail the compile with `Z-ERR-ENV-REGION`.
+
+use std::borrow::Cow;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+use crate::content_audit::{blank_ignored, line_column};
+use crate::validate::CompilerError;
+
+/// `<aside zen:env="internal">`: the element-level form of a region
+pub(crate) const ENV_ATTR: &str = "zen:env";
+
+const ENV_REGION_ERROR: &str = "Z-ERR-ENV-REGION";
+
+lazy_static! {
+    static ref MARKER_RE: Regex =
+        Regex::new(r"<!--\s*zen:(if-env\b([^>]*?)|end-env)\s*-->").unwrap();
+    static ref RAW_TEXT_RE: Regex =
+        Regex::new(r"(?is)<script\b.*?</script\s*>|<style\b.*?</style\s*>").unwrap();
+    static ref ENV_ATTR_RE: Regex =
+        Regex::new(r#"\szen:env\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+}
+
+/// A region left out of this build
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct StrippedEnvRegion {
+    /// The environments the region is for, as written (`staging preview`)
+    pub env: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Source with the regions for other environments removed, and those regions
+pub(crate) fn resolve_env_regions<'s>(
+    source: &'s str,
+    file_path: &str,
+    env: &str,
+) -> Result<(Cow<'s, str>, Vec<StrippedEnvRegion>), Box<CompilerError>> {
+    if !source.contains("zen:if-env")
+        && !source.contains("zen:end-env")
+        && !source.contains(ENV_ATTR)
+    {
+        return Ok((Cow::Borrowed(source), Vec::new()));
+    }
+    let mut stripped = Vec::new();
+    let resolved = resolve_markers(source, env, &mut stripped)
+        .map_err(|(offset, message)| region_error(source, file_path, offset, message))?;
+    let resolved = resolve_attributes(&resolved, env, &mut stripped)
+        .map_err(|(offset, message)| region_error(&resolved, file_path, offset, message))?;
+    Ok((Cow::Owned(resolved), stripped))
+}
+
+fn env_matches(names: &str, env: &str) -> bool {
+    names
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .any(|name| !name.is_empty() && name == env)
+}
+
+fn region(source: &str, offset: usize, names: &str) -> StrippedEnvRegion {
+    let (line, column) = line_column(source, offset);
+    StrippedEnvRegion {
+        env: names.split_whitespace().collect::<Vec<_>>().join(" "),
+        line: line as u32,
+        column: column as u32,
+    }
+}
+
+/// Regions failing to resolve, as `(offset, message)`
+type RegionError = (usize, String);
+
+fn region_error(
+    source: &str,
+    file_path: &str,
+    offset: usize,
+    message: String,
+) -> Box<CompilerError> {
+    let (line, column) = line_column(source, offset);
+    Box::new(CompilerError::new(
+        ENV_REGION_ERROR,
+        &format!(
+            "{}: {} (line {}, column {}) in {}",
+            ENV_REGION_ERROR, message, line, column, file_path
+        ),
+        file_path,
+        line as u32,
+        column as u32,
+    ))
+}
+
+/// `source` without `ranges` (sorted by start, possibly overlapping); removed text
+/// keeps only its newlines
+fn cut(source: &str, mut ranges: Vec<(usize, usize)>) -> String {
+    ranges.sort();
+    let mut out = String::with_capacity(source.len());
+    let mut at = 0;
+    for (start, end) in ranges {
+        if end <= at {
+            continue;
+        }
+        let start = start.max(at);
+        out.push_str(&source[at..start]);
+        out.extend(source[start..end].matches('\n'));
+        at = end;
+    }
+    out.push_str(&source[at..]);
+    out
+}
+
+fn resolve_markers(
+    source: &str,
+    env: &str,
+    stripped: &mut Vec<StrippedEnvRegion>,
+) -> Result<String, RegionError> {
+    let raw_text: Vec<(usize, usize)> = RAW_TEXT_RE
+        .find_iter(source)
+        .map(|m| (m.start(), m.end()))
+        .collect();
+    let mut open: Vec<(usize, usize, &str)> = Vec::new();
+    let mut ranges = Vec::new();
+    for marker in MARKER_RE.captures_iter(source) {
+        let whole = marker.get(0).unwrap();
+        if raw_text
+            .iter()
+            .any(|(s, e)| (*s..*e).contains(&whole.start()))
+        {
+            continue;
+        }
+        match marker.get(2) {
+            Some(names) => {
+                let names = names.as_str();
+                if names.trim().is_empty() {
+                    return Err((
+                        whole.start(),
+                        "`<!-- zen:if-env -->` names no environment".to_string(),
+                    ));
+                }
+                open.push((whole.start(), whole.end(), names));
+            }
+            None => {
+                let Some((start, open_end, names)) = open.pop() else {
+                    return Err((
+                        whole.start(),
+                        "`<!-- zen:end-env -->` has no matching `<!-- zen:if-env -->`".to_string(),
+                    ));
+                };
+                // Closing order is innermost-first
+                if env_matches(names, env) {
+                    ranges.push((start, open_end));
+                    ranges.push((whole.start(), whole.end()));
+                } else {
+                    stripped.push(region(source, start, names));
+                    ranges.push((start, whole.end()));
+                }
+            }
+        }
+    }
+    if let Some((start, _, names)) = open.pop() {
+        return Err((
+            start,
+            format!(
+                "`<!-- zen:if-env {} -->` is never closed with `<!-- zen:end-env -->`",
+                names.trim()
+            ),
+        ));
+    }
+    Ok(cut(source, ranges))
+}
+
+fn resolve_attributes(
+    source: &str,
+    env: &str,
+    stripped: &mut Vec<StrippedEnvRegion>,
+) -> Result<String, RegionError> {
+    if !source.contains(ENV_ATTR) {
+        return Ok(source.to_string());
+    }
+    let blanked = blank_ignored(source);
+    let lower = blanked.to_ascii_lowercase();
+    let mut ranges = Vec::new();
+    let mut at = 0;
+    while let Some(found) = blanked[at..].find('<') {
+        let start = at + found;
+        let Some(tag) = tag_name(&lower[start..]) else {
+            at = start + 1;
+            continue;
+        };
+        let open_end = open_tag_end(&blanked, start);
+        let open_tag = &blanked[start..open_end];
+        let Some(attr) = ENV_ATTR_RE.captures(open_tag) else {
+            at = open_end;
+            continue;
+        };
+        let names = attr.get(1).or(attr.get(2)).unwrap().as_str();
+        let span = attr.get(0).unwrap();
+        if env_matches(names, env) {
+            ranges.push((start + span.start(), start + span.end()));
+            at = open_end;
+        } else {
+            let Some(end) = element_end(&blanked, &lower, start, open_end, &tag) else {
+                return Err((
+                    start,
+                    format!("`<{} zen:env=\"{}\">` is never closed", tag, names),
+                ));
+            };
+            stripped.push(region(source, start, names));
+            ranges.push((start, end));
+            at = end;
+        }
+    }
+    Ok(cut(source, ranges))
+}
+
+/// Lowercased tag name at the start of `s` (`<div`, `<card.footer`)
+fn tag_name(s: &str) -> Option<String> {
+    let name: String = s
+        .strip_prefix('<')?
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | ':' | '.'))
+        .collect();
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        .then_some(name)
+}
+
+/// Offset just past the `>` of the start tag at `start`; `>` inside quotes or an
+/// `{expression}` (an arrow function, a comparison) does not end it
+fn open_tag_end(source: &str, start: usize) -> usize {
+    let mut quote = None;
+    let mut braces = 0usize;
+    for (i, c) in source[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') if braces == 0 => quote = Some(c),
+            (None, '{') => braces += 1,
+            (None, '}') => braces = braces.saturating_sub(1),
+            (None, '>') if braces == 0 => return start + i + 1,
+            _ => {}
+        }
+    }
+    source.len()
+}
+
+/// Offset just past the element's matching close tag; `None` when it is never closed
+fn element_end(
+    source: &str,
+    lower: &str,
+    start: usize,
+    open_end: usize,
+    tag: &str,
+) -> Option<usize> {
+    if source[start..open_end].ends_with("/>") || is_void(tag) {
+        return Some(open_end);
+    }
+    let (open, close) = (format!("<{}", tag), format!("</{}", tag));
+    let is_tag_at = |at: usize, prefix: &str| {
+        lower[at..].starts_with(prefix)
+            && !lower[at + prefix.len()..]
+                .starts_with(|c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | ':' | '.'))
+    };
+    let mut depth = 1;
+    let mut at = open_end;
+    while let Some(found) = lower[at..].find('<') {
+        let next = at + found;
+        if is_tag_at(next, &close) {
+            let end = lower[next..]
+                .find('>')
+                .map_or(lower.len(), |i| next + i + 1);
+            depth -= 1;
+            if depth == 0 {
+                return Some(end);
+            }
+            at = end;
+        } else if is_tag_at(next, &open) {
+            let end = open_tag_end(source, next);
+            if !source[next..end].ends_with("/>") {
+                depth += 1;
+            }
+            at = end;
+        } else {
+            at = next + 1;
+        }
+    }
+    None
+}
+
+fn is_void(tag: &str) -> bool {
+    matches!(
+        tag,
+        "area"
+            | "base"
+            | "br"
+            | "col"
+            | "embed"
+            | "hr"
+            | "img"
+            | "input"
+            | "link"
+            | "meta"
+            | "source"
+            | "track"
+            | "wbr"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::ComponentIR;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+    use std::collections::HashMap;
+
+    const PAGE: &str = "<script>\nstate build = 'abc123'\n</script>\n<main>\n<h1>Home</h1>\n<!-- zen:if-env staging -->\n<Banner label={build} />\n<!-- zen:end-env -->\n<aside zen:env=\"internal\" class=\"debug\">{build.length > 3 ? build : ''}</aside>\n</main>";
+
+    fn compile(source: &str, env: &str) -> Result<CompileResult, String> {
+        let banner = ComponentIR::from_source(
+            "Banner",
+            "components/Banner.zen",
+            "<script>\nprop label\nconst bannerSecret = 'BANNER_SCRIPT_MARKER'\n</script>\n<div class=\"banner\">Staging {label}</div>",
+        )
+        .unwrap();
+        let mut components = HashMap::new();
+        components.insert("Banner".to_string(), serde_json::to_value(banner).unwrap());
+        compile_zen_internal(
+            source,
+            "page.zen",
+            CompileOptions {
+                components,
+                env: env.to_string(),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_matching_env_keeps_content() {
+        let result = compile(PAGE, "staging").unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(result.html.contains("class=\"banner\""), "{}", result.html);
+        assert!(!result.html.contains("zen:if-env"), "{}", result.html);
+        let manifest = result.manifest.unwrap();
+        assert!(manifest.bundle.contains("BANNER_SCRIPT_MARKER"));
+        // The internal panel is still stripped
+        assert!(!result.html.contains("debug"), "{}", result.html);
+        assert_eq!(
+            manifest.stripped_env_regions,
+            vec![StrippedEnvRegion {
+                env: "internal".to_string(),
+                line: 9,
+                column: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_other_env_removes_expressions_and_components() {
+        let result = compile(PAGE, "production").unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(!result.html.contains("banner"), "{}", result.html);
+        assert!(!result.html.contains("<aside"), "{}", result.html);
+        let manifest = result.manifest.unwrap();
+        assert!(!manifest.bundle.contains("BANNER_SCRIPT_MARKER"));
+        assert!(!manifest.bundle.contains("build.length"));
+        assert!(manifest.component_usages.is_empty());
+        assert_eq!(manifest.stripped_env_regions.len(), 2);
+        assert_eq!(manifest.stripped_env_regions[0].env, "staging");
+        assert_eq!(manifest.stripped_env_regions[0].line, 6);
+
+        // `internal` keeps the panel without its marker attribute
+        let result = compile(PAGE, "internal").unwrap();
+        assert!(
+            result.html.contains("<aside class=\"debug\">"),
+            "{}",
+            result.html
+        );
+    }
+
+    #[test]
+    fn test_unmatched_markers_error() {
+        let err = compile(
+            "<main>\n<p>a</p>\n<!-- zen:if-env staging -->\n<p>b</p>\n</main>",
+            "staging",
+        )
+        .unwrap_err();
+        assert!(
+            err.contains("Z-ERR-ENV-REGION: `<!-- zen:if-env staging -->` is never closed")
+                && err.contains("(line 3, column 1) in page.zen"),
+            "{}",
+            err
+        );
+        let err = compile("<main>\n<!-- zen:end-env -->\n</main>", "staging").unwrap_err();
+        assert!(err.contains("has no matching"), "{}", err);
+    }
+
+    #[test]
+    fn test_nested_regions_resolve_innermost_first() {
+        let source = "<main>\n<!-- zen:if-env staging preview -->\n<p>shared</p>\n<!-- zen:if-env preview -->\n<p>preview only</p>\n<!-- zen:end-env -->\n<!-- zen:end-env -->\n<p>always</p>\n</main>";
+        let (resolved, stripped) = resolve_env_regions(source, "page.zen", "staging").unwrap();
+        assert!(resolved.contains("shared") && resolved.contains("always"));
+        assert!(!resolved.contains("preview only"));
+        assert_eq!(stripped.len(), 1);
+        assert_eq!((stripped[0].line, stripped[0].env.as_str()), (4, "preview"));
+        // Line numbers after a region are unchanged
+        assert_eq!(resolved.lines().count(), source.lines().count());
+
+        let (resolved, stripped) = resolve_env_regions(source, "page.zen", "production").unwrap();
+        assert!(!resolved.contains("shared") && resolved.contains("always"));
+        let envs: Vec<&str> = stripped.iter().map(|r| r.env.as_str()).collect();
+        assert_eq!(envs, vec!["preview", "staging preview"]);
+    }
+}
@@ -0,0 +1,329 @@
+//! Legacy Browser Output
+//!
+//! Generated bundles use whatever syntax the page and its components are written
+//! in, so `a?.b ?? c` in a template expression ships as is and fails to parse in
+//! an older embedded browser. With `CompileOptions.target_es` below `EsNext`, the
+//! sections codegen assembles the bundle from (user script, expressions and their
+//! registry, the canonical IR wrapper) and the lazy chunk are run through oxc's
+//! transformer with the plugins that lower without runtime helpers:
+//!
+//! | target   | lowered                                                         |
+//! |----------|-----------------------------------------------------------------|
+//! | `Es2020` | logical assignment, class fields (as constructor assignments), static blocks |
+//! | `Es2017` | the above, optional chaining, nullish coalescing, optional catch binding |
+//!
+//! Syntax that would need a helper module or cannot be lowered at all (BigInt,
+//! `import.meta`, private class members, object rest/spread, async iteration) is
+//! left in place and reported as `Z-ERR-ES-TARGET`, naming the construct and
+//! where it was written. The default target leaves the bundle untouched.
+
+use std::collections::BTreeSet;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{
+    BigIntLiteral, ForOfStatement, Function, MetaProperty, ObjectAssignmentTarget,
+    ObjectExpression, ObjectPattern, ObjectPropertyKind, PrivateIdentifier,
+};
+use oxc_ast_visit::{walk, Visit};
+use oxc_codegen::Codegen;
+use oxc_parser::Parser;
+use oxc_semantic::SemanticBuilder;
+use oxc_span::{GetSpan, SourceType, Span};
+use oxc_syntax::scope::ScopeFlags;
+use oxc_transformer::{ClassPropertiesOptions, TransformOptions, Transformer};
+use serde::{Deserialize, Serialize};
+
+use crate::validate::ExpressionInput;
+
+pub(crate) const ES_TARGET_ERROR: &str = "Z-ERR-ES-TARGET";
+
+/// Syntax level of the generated bundle. The default keeps the source syntax.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EsTarget {
+    Es2017,
+    Es2020,
+    #[default]
+    EsNext,
+}
+
+impl EsTarget {
+    /// `"es2017"`, `"es2020"` or `"esnext"` (any case)
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "es2017" => Ok(Self::Es2017),
+            "es2020" => Ok(Self::Es2020),
+            "esnext" => Ok(Self::EsNext),
+            other => Err(format!(
+                "{}: unknown target `{}`; expected `es2017`, `es2020` or `esnext`",
+                ES_TARGET_ERROR, other
+            )),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Es2017 => "ES2017",
+            Self::Es2020 => "ES2020",
+            Self::EsNext => "ESNext",
+        }
+    }
+
+    fn transform_options(self) -> TransformOptions {
+        let mut options = TransformOptions::default();
+        // Public fields become `this.x = ...`, which needs no `defineProperty` helper
+        options.assumptions.set_public_class_fields = true;
+        options.env.es2022.class_properties = Some(ClassPropertiesOptions { loose: true });
+        options.env.es2022.class_static_block = true;
+        options.env.es2021.logical_assignment_operators = true;
+        if self < Self::Es2020 {
+            options.env.es2020.optional_chaining = true;
+            options.env.es2020.nullish_coalescing_operator = true;
+            options.env.es2019.optional_catch_binding = true;
+        }
+        options
+    }
+}
+
+/// Syntax left in a lowered section
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Unsupported {
+    construct: &'static str,
+    /// Source text of the construct, cut to its first line
+    snippet: String,
+}
+
+/// Lowers the generated bundle sections one by one, collecting what stays above
+/// the target
+pub(crate) struct Lowering {
+    target: EsTarget,
+    unsupported: BTreeSet<Unsupported>,
+}
+
+impl Lowering {
+    pub(crate) fn new(target: EsTarget) -> Self {
+        Self {
+            target,
+            unsupported: BTreeSet::new(),
+        }
+    }
+
+    /// `code` lowered to the target; a section that does not parse on its own is
+    /// returned unchanged
+    pub(crate) fn section(&mut self, code: String) -> String {
+        if self.target == EsTarget::EsNext {
+            return code;
+        }
+        let allocator = Allocator::default();
+        let ret = Parser::new(&allocator, &code, SourceType::mjs()).parse();
+        if !ret.errors.is_empty() {
+            return code;
+        }
+        let mut program = ret.program;
+
+        let mut finder = UnsupportedFinder {
+            code: &code,
+            target: self.target,
+            found: &mut self.unsupported,
+        };
+        finder.visit_program(&program);
+
+        let scoping = SemanticBuilder::new()
+            .build(&program)
+            .semantic
+            .into_scoping();
+        Transformer::new(
+            &allocator,
+            std::path::Path::new("bundle.js"),
+            &self.target.transform_options(),
+        )
+        .build_with_scoping(scoping, &mut program);
+        Codegen::new().build(&program).code
+    }
+
+    /// `Z-ERR-ES-TARGET` for each construct left
+    pub(crate) fn errors(
+        &self,
+        script: &str,
+        expressions: &[ExpressionInput],
+        file_path: &str,
+    ) -> Vec<String> {
+        self.unsupported
+            .iter()
+            .map(|u| {
+                format!(
+                    "{}: {} `{}` cannot be lowered to {}{}",
+                    ES_TARGET_ERROR,
+                    u.construct,
+                    u.snippet,
+                    self.target.label(),
+                    location(&u.snippet, script, expressions, file_path)
+                )
+            })
+            .collect()
+    }
+}
+
+/// The page script (with the component scripts merged into it) carries no
+/// positions, so the construct is placed by the script or expression it is in
+fn location(
+    snippet: &str,
+    script: &str,
+    expressions: &[ExpressionInput],
+    file_path: &str,
+) -> String {
+    if script.contains(snippet) {
+        return format!(" in the script of {}", file_path);
+    }
+    match expressions.iter().find(|e| e.code.contains(snippet)) {
+        Some(expr) => format!(" in expression `{{{}}}` in {}", expr.code.trim(), file_path),
+        None => format!(" in the generated code of {}", file_path),
+    }
+}
+
+struct UnsupportedFinder<'c> {
+    code: &'c str,
+    target: EsTarget,
+    found: &'c mut BTreeSet<Unsupported>,
+}
+
+impl UnsupportedFinder<'_> {
+    fn report(&mut self, construct: &'static str, span: Span) {
+        let text = &self.code[span.start as usize..span.end as usize];
+        self.found.insert(Unsupported {
+            construct,
+            snippet: text.lines().next().unwrap_or_default().trim().to_string(),
+        });
+    }
+
+    fn below_es2020(&self) -> bool {
+        self.target < EsTarget::Es2020
+    }
+}
+
+impl<'a> Visit<'a> for UnsupportedFinder<'_> {
+    fn visit_big_int_literal(&mut self, it: &BigIntLiteral<'a>) {
+        if self.below_es2020() {
+            self.report("BigInt literal", it.span);
+        }
+    }
+
+    fn visit_meta_property(&mut self, it: &MetaProperty<'a>) {
+        if self.below_es2020() && it.meta.name == "import" {
+            self.report("`import.meta`", it.span);
+        }
+    }
+
+    fn visit_private_identifier(&mut self, it: &PrivateIdentifier<'a>) {
+        self.report("private class member", it.span);
+    }
+
+    fn visit_object_expression(&mut self, it: &ObjectExpression<'a>) {
+        if self.below_es2020() {
+            for property in &it.properties {
+                if let ObjectPropertyKind::SpreadProperty(spread) = property {
+                    self.report("object spread", spread.span);
+                }
+            }
+        }
+        walk::walk_object_expression(self, it);
+    }
+
+    fn visit_object_pattern(&mut self, it: &ObjectPattern<'a>) {
+        if let (true, Some(rest)) = (self.below_es2020(), &it.rest) {
+            self.report("object rest", rest.span);
+        }
+        walk::walk_object_pattern(self, it);
+    }
+
+    fn visit_object_assignment_target(&mut self, it: &ObjectAssignmentTarget<'a>) {
+        if let (true, Some(rest)) = (self.below_es2020(), &it.rest) {
+            self.report("object rest", rest.span());
+        }
+        walk::walk_object_assignment_target(self, it);
+    }
+
+    fn visit_function(&mut self, it: &Function<'a>, flags: ScopeFlags) {
+        if self.below_es2020() && it.r#async && it.generator {
+            self.report("async generator", it.span);
+        }
+        walk::walk_function(self, it, flags);
+    }
+
+    fn visit_for_of_statement(&mut self, it: &ForOfStatement<'a>) {
+        if self.below_es2020() && it.r#await {
+            self.report("`for await`", it.span);
+        }
+        walk::walk_for_of_statement(self, it);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(source: &str, target_es: Option<EsTarget>) -> CompileResult {
+        compile_zen_internal(
+            source,
+            "page.zen",
+            CompileOptions {
+                target_es,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn bundle(result: &CompileResult) -> &str {
+        &result.manifest.as_ref().unwrap().bundle
+    }
+
+    const PAGE: &str = "<script>\nstate user = null\n</script>\n<p>{user?.name ?? 'guest'}</p>";
+
+    #[test]
+    fn test_es2017_lowers_optional_chaining_and_nullish_coalescing() {
+        let result = compile(PAGE, Some(EsTarget::Es2017));
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let code = bundle(&result);
+        assert!(!code.contains("?."), "{}", code);
+        assert!(!code.contains("??"), "{}", code);
+        assert!(code.contains("=== void 0"), "{}", code);
+    }
+
+    #[test]
+    fn test_esnext_output_is_unchanged() {
+        let default = compile(PAGE, None);
+        let esnext = compile(PAGE, Some(EsTarget::EsNext));
+        assert!(bundle(&esnext).contains("user?.name ?? \"guest\""));
+        // Expression ids are numbered per process
+        let ids = regex::Regex::new(r"expr_\d+").unwrap();
+        assert_eq!(
+            ids.replace_all(bundle(&default), "expr_N"),
+            ids.replace_all(bundle(&esnext), "expr_N")
+        );
+        assert_eq!(
+            ids.replace_all(&default.html, "expr_N"),
+            ids.replace_all(&esnext.html, "expr_N")
+        );
+    }
+
+    #[test]
+    fn test_bigint_literal_is_diagnosed() {
+        let result = compile(
+            "<script>\nstate total = 10n\n</script>\n<p>{String(total)}</p>",
+            Some(EsTarget::Es2017),
+        );
+        assert!(result.has_errors);
+        assert_eq!(
+            result.errors,
+            vec!["Z-ERR-ES-TARGET: BigInt literal `10n` cannot be lowered to ES2017 in the script of page.zen"]
+        );
+        // ES2020 has BigInt
+        let result = compile(
+            "<script>\nstate total = 10n\n</script>\n<p>{String(total)}</p>",
+            Some(EsTarget::Es2020),
+        );
+        assert!(!result.has_errors, "{:?}", result.errors);
+    }
+}
@@ -0,0 +1,188 @@
+//! Typed Event Values
+//!
+//! An `oninput` / `onchange` handler named by a plain identifier on a form control
+//! is called as `handler(event, value)`, with the value already read the way the
+//! control's static `type` calls for:
+//!
+//! | control                         | value                                       |
+//! |---------------------------------|---------------------------------------------|
+//! | `<input type="number/range">`   | `target.valueAsNumber`                      |
+//! | `<input type="checkbox">`       | `target.checked`                            |
+//! | `<input type="file">`           | `target.files`                              |
+//! | `<select multiple>`             | values of `target.selectedOptions` (array)  |
+//! | any other input, select, textarea | `target.value`                            |
+//!
+//! `target` is the element the handler is bound to. Handlers that only take
+//! `event` are unaffected. An `<input>` whose `type` is an expression gets the
+//! plain value and a `Z-INFO-EVENT-VALUE-DYNAMIC-TYPE` note. Two-way binding has no
+//! directive yet; one would read its value from this table as well.
+
+use crate::validate::{AttributeValue, ElementNode, TemplateNode};
+
+/// Handlers that receive the control's value
+const VALUE_EVENTS: &[&str] = &["oninput", "onchange"];
+
+/// `target.valueAsNumber`-style read of the value of `el`, when its handlers get one
+pub(crate) fn value_extraction(el: &ElementNode) -> Option<&'static str> {
+    let tag = el.tag.to_ascii_lowercase();
+    match tag.as_str() {
+        // A dynamic `type` reads the string value, which every input has
+        "input" => Some(
+            match static_attr(el, "type")
+                .flatten()
+                .map(|kind| kind.trim().to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("number" | "range") => "target.valueAsNumber",
+                Some("checkbox") => "target.checked",
+                Some("file") => "target.files",
+                _ => "target.value",
+            },
+        ),
+        "select" if static_attr(el, "multiple").is_some() => {
+            Some("Array.from(target.selectedOptions, (option) => option.value)")
+        }
+        "select" | "textarea" => Some("target.value"),
+        _ => None,
+    }
+}
+
+/// `Some(Some(value))` for a static attribute, `Some(None)` for a dynamic one
+fn static_attr<'e>(el: &'e ElementNode, name: &str) -> Option<Option<&'e str>> {
+    el.attributes
+        .iter()
+        .find(|a| a.name.eq_ignore_ascii_case(name))
+        .map(|a| match &a.value {
+            AttributeValue::Static(value) => Some(value.as_str()),
+            AttributeValue::Dynamic(_) => None,
+        })
+}
+
+/// Whether `attr` (`oninput`, `on:change`, ...) is a handler that gets the value
+pub(crate) fn is_value_event(attr: &str) -> bool {
+    let name = attr
+        .strip_prefix("on:")
+        .map_or(attr.to_string(), |e| format!("on{}", e));
+    VALUE_EVENTS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// A handler written as a bare name (`update`), not a call or an inline function
+pub(crate) fn is_simple_handler(code: &str) -> bool {
+    let code = code.trim();
+    !code.is_empty()
+        && !code.starts_with(|c: char| c.is_ascii_digit())
+        && code
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// `Z-INFO-EVENT-VALUE-DYNAMIC-TYPE` for inputs whose value handlers cannot be typed
+pub(crate) fn check_dynamic_types(nodes: &[TemplateNode], file_path: &str) -> Vec<String> {
+    let mut notes = Vec::new();
+    collect_dynamic_types(nodes, file_path, &mut notes);
+    notes
+}
+
+fn collect_dynamic_types(nodes: &[TemplateNode], file_path: &str, notes: &mut Vec<String>) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                let handler = el.attributes.iter().find_map(|a| {
+                    let code = match &a.value {
+                        AttributeValue::Static(s) => s.as_str(),
+                        AttributeValue::Dynamic(expr) => expr.code.as_str(),
+                    };
+                    (is_value_event(&a.name) && is_simple_handler(code)).then_some(code.trim())
+                });
+                if let (Some(handler), true) = (
+                    handler,
+                    el.tag.eq_ignore_ascii_case("input")
+                        && static_attr(el, "type").is_some_and(|t| t.is_none()),
+                ) {
+                    notes.push(format!(
+                        "Z-INFO-EVENT-VALUE-DYNAMIC-TYPE: `{}` on an <input> with a dynamic `type` in {} receives `target.value` as its second argument; convert it in the handler if the input is numeric, a checkbox, or a file input.",
+                        handler, file_path
+                    ));
+                }
+                collect_dynamic_types(&el.children, file_path, notes);
+            }
+            TemplateNode::Component(c) => collect_dynamic_types(&c.children, file_path, notes),
+            TemplateNode::ConditionalFragment(cf) => {
+                collect_dynamic_types(&cf.consequent, file_path, notes);
+                collect_dynamic_types(&cf.alternate, file_path, notes);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    collect_dynamic_types(nodes, file_path, notes);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => {
+                collect_dynamic_types(&of.fragment, file_path, notes)
+            }
+            TemplateNode::LoopFragment(lf) => collect_dynamic_types(&lf.body, file_path, notes),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(markup: &str) -> CompileResult {
+        let source = format!(
+            "<script>\nstate amount = 0\nstate kind = 'text'\nfunction update(event, value) {{ amount = value }}\n</script>\n<main>{}</main>",
+            markup
+        );
+        let result = compile_zen_internal(&source, "page.zen", CompileOptions::default()).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        result
+    }
+
+    fn bundle(result: &CompileResult) -> &str {
+        &result.manifest.as_ref().unwrap().bundle
+    }
+
+    #[test]
+    fn test_number_and_checkbox_inputs_get_typed_values() {
+        let result = compile("<input type=\"number\" oninput={update} />");
+        assert!(
+            bundle(&result).contains("handler.call(target, event, target.valueAsNumber)"),
+            "{}",
+            bundle(&result)
+        );
+        let result = compile("<input type=\"checkbox\" onchange={update} />");
+        assert!(bundle(&result).contains("handler.call(target, event, target.checked)"));
+        // A non-value event keeps the plain call
+        let result = compile("<input type=\"number\" onfocus={update} />");
+        assert!(!bundle(&result).contains("valueAsNumber"));
+    }
+
+    #[test]
+    fn test_dynamic_type_falls_back_to_value() {
+        let result = compile("<input type={kind} oninput={update} />");
+        assert!(bundle(&result).contains("handler.call(target, event, target.value)"));
+        assert!(
+            result
+                .warnings
+                .iter()
+                .any(|w| w.starts_with("Z-INFO-EVENT-VALUE-DYNAMIC-TYPE: `update`")),
+            "{:?}",
+            result.warnings
+        );
+    }
+
+    #[test]
+    fn test_select_multiple_maps_selected_options() {
+        let result = compile(
+            "<select multiple onchange={update}><option>a</option><option>b</option></select>",
+        );
+        assert!(
+            bundle(&result).contains(
+                "handler.call(target, event, Array.from(target.selectedOptions, (option) => option.value))"
+            ),
+            "{}",
+            bundle(&result)
+        );
+    }
+}
@@ -0,0 +1,393 @@
+//! Expression Complexity
+//!
+//! Every registered expression is measured after lowering, before codegen:
+//!
+//! - `length`: characters of the code, with component scope access
+//!   (`window.__ZENITH_SCOPES__["inst0"].state.`) counted as the bare name
+//! - `nodes`: AST nodes of the parsed expression
+//! - `depth`: deepest nesting of conditional (`?:`) and logical (`&&`, `||`,
+//!   `??`) expressions
+//! - `deps`: distinct page and instance state keys read
+//!
+//! The metrics are reported per expression in the manifest
+//! (`ZenManifestExport.expression_metrics`). An expression over any threshold of
+//! its kind gets a `Z-WARN-EXPRESSION-COMPLEXITY` warning naming the exceeded
+//! metrics, with a skeleton for moving the logic into a named function.
+//! Event handlers and expressions in loop bodies get their own, higher defaults
+//! (`CompileOptions.expression_complexity` overrides any of them).
+
+use lazy_static::lazy_static;
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{ConditionalExpression, IdentifierReference, LogicalExpression};
+use oxc_ast::AstKind;
+use oxc_ast_visit::Visit;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+use crate::validate::{ExpressionIR, ZenIR};
+
+pub const COMPLEXITY_WARNING: &str = "Z-WARN-EXPRESSION-COMPLEXITY";
+
+lazy_static! {
+    /// Scope access of a promoted component expression, with the key read
+    static ref INSTANCE_ACCESS: Regex = Regex::new(
+        r#"window\.__ZENITH_SCOPES__\["(inst\d+)"\]\.(state|props|locals)\.([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)"#
+    )
+    .unwrap();
+}
+
+/// Thresholds for one kind of expression; unset fields use the kind's default
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct ComplexityThresholds {
+    pub max_length: Option<u32>,
+    pub max_nodes: Option<u32>,
+    pub max_depth: Option<u32>,
+    pub max_deps: Option<u32>,
+}
+
+/// Expression complexity thresholds by kind (see module docs)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct ComplexityLimits {
+    /// Render expressions outside loops
+    pub render: Option<ComplexityThresholds>,
+    /// Render expressions in a loop body
+    pub loop_body: Option<ComplexityThresholds>,
+    /// Event handlers
+    pub handler: Option<ComplexityThresholds>,
+}
+
+/// Measured complexity of one expression, always reported in the manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct ExpressionMetrics {
+    pub id: String,
+    /// `render`, `loop` or `handler`
+    pub kind: String,
+    pub length: u32,
+    pub nodes: u32,
+    pub depth: u32,
+    pub deps: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Render,
+    Loop,
+    Handler,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Render => "render",
+            Kind::Loop => "loop",
+            Kind::Handler => "handler",
+        }
+    }
+
+    /// (length, nodes, depth, deps)
+    fn defaults(self) -> [u32; 4] {
+        match self {
+            Kind::Render => [120, 40, 3, 5],
+            Kind::Loop => [160, 60, 4, 6],
+            Kind::Handler => [240, 80, 4, 8],
+        }
+    }
+}
+
+#[derive(Default)]
+struct Measure {
+    nodes: u32,
+    depth: u32,
+    max_depth: u32,
+    identifiers: Vec<String>,
+}
+
+impl Measure {
+    fn nested(&mut self, walk: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+        walk(self);
+        self.depth -= 1;
+    }
+}
+
+impl<'a> Visit<'a> for Measure {
+    fn enter_node(&mut self, _kind: AstKind<'a>) {
+        self.nodes += 1;
+    }
+
+    fn visit_conditional_expression(&mut self, it: &ConditionalExpression<'a>) {
+        self.nested(|m| oxc_ast_visit::walk::walk_conditional_expression(m, it));
+    }
+
+    fn visit_logical_expression(&mut self, it: &LogicalExpression<'a>) {
+        self.nested(|m| oxc_ast_visit::walk::walk_logical_expression(m, it));
+    }
+
+    fn visit_identifier_reference(&mut self, ident: &IdentifierReference<'a>) {
+        self.nodes += 1;
+        self.identifiers.push(ident.name.to_string());
+    }
+}
+
+fn measure(expr: &ExpressionIR, kind: Kind, state_keys: &HashSet<&String>) -> ExpressionMetrics {
+    let mut deps = BTreeSet::new();
+    for caps in INSTANCE_ACCESS.captures_iter(&expr.code) {
+        if &caps[2] == "state" {
+            deps.insert(format!("{}.{}", &caps[1], &caps[3]));
+        }
+    }
+    let code = INSTANCE_ACCESS.replace_all(&expr.code, "$3");
+
+    let allocator = Allocator::default();
+    let mut measure = Measure::default();
+    // Code oxc rejects is reported elsewhere; it still has a length
+    if let Ok(parsed) = Parser::new(&allocator, &code, SourceType::tsx()).parse_expression() {
+        measure.visit_expression(&parsed);
+    }
+    for name in measure.identifiers {
+        if state_keys.contains(&name) {
+            deps.insert(name);
+        }
+    }
+    ExpressionMetrics {
+        id: expr.id.clone(),
+        kind: kind.as_str().to_string(),
+        length: code.chars().count() as u32,
+        nodes: measure.nodes,
+        depth: measure.max_depth,
+        deps: deps.len() as u32,
+    }
+}
+
+/// Metrics of every expression of the page, in registration order
+pub(crate) fn expression_metrics(ir: &ZenIR) -> Vec<ExpressionMetrics> {
+    let mut handlers = HashSet::new();
+    crate::codegen::collect_event_handler_ids(&ir.template.nodes, &mut handlers);
+    let mut state_keys: HashSet<&String> = ir.all_states.keys().collect();
+    state_keys.extend(ir.page_bindings.iter());
+    ir.template
+        .expressions
+        .iter()
+        .map(|expr| measure(expr, kind_of(expr, &handlers), &state_keys))
+        .collect()
+}
+
+fn kind_of(expr: &ExpressionIR, handlers: &HashSet<String>) -> Kind {
+    if handlers.contains(&expr.id) {
+        Kind::Handler
+    } else if expr
+        .loop_context
+        .as_ref()
+        .is_some_and(|lc| !lc.variables.is_empty())
+    {
+        Kind::Loop
+    } else {
+        Kind::Render
+    }
+}
+
+/// `Z-WARN-EXPRESSION-COMPLEXITY` for every expression over a threshold of its kind
+pub(crate) fn check_complexity(
+    ir: &ZenIR,
+    metrics: &[ExpressionMetrics],
+    limits: Option<&ComplexityLimits>,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (expr, m) in ir.template.expressions.iter().zip(metrics) {
+        let kind = match m.kind.as_str() {
+            "handler" => Kind::Handler,
+            "loop" => Kind::Loop,
+            _ => Kind::Render,
+        };
+        let configured = limits.and_then(|l| match kind {
+            Kind::Render => l.render.as_ref(),
+            Kind::Loop => l.loop_body.as_ref(),
+            Kind::Handler => l.handler.as_ref(),
+        });
+        let [length, nodes, depth, deps] = kind.defaults();
+        let max = |field: fn(&ComplexityThresholds) -> Option<u32>, default: u32| {
+            configured.and_then(field).unwrap_or(default)
+        };
+        let exceeded: Vec<String> = [
+            ("length", m.length, max(|t| t.max_length, length)),
+            ("AST nodes", m.nodes, max(|t| t.max_nodes, nodes)),
+            ("nesting depth", m.depth, max(|t| t.max_depth, depth)),
+            ("state deps", m.deps, max(|t| t.max_deps, deps)),
+        ]
+        .into_iter()
+        .filter(|(_, value, limit)| value > limit)
+        .map(|(metric, value, limit)| format!("{} {} (max {})", metric, value, limit))
+        .collect();
+        if exceeded.is_empty() {
+            continue;
+        }
+        let location = match &expr.origin {
+            Some(origin) => origin.to_string(),
+            None => format!(
+                "{} (line {}, column {})",
+                ir.file_path, expr.location.line, expr.location.column
+            ),
+        };
+        warnings.push(format!(
+            "{}: {} `{}` in {} exceeds {}. Extract it into a named function or a zenComputed in the script, e.g.\n  {}",
+            COMPLEXITY_WARNING,
+            match kind {
+                Kind::Handler => "event handler",
+                _ => "expression",
+            },
+            expr.id,
+            location,
+            exceeded.join(", "),
+            skeleton(expr, kind)
+        ));
+    }
+    warnings
+}
+
+/// A named-function version of the expression and how the template uses it
+fn skeleton(expr: &ExpressionIR, kind: Kind) -> String {
+    let code = INSTANCE_ACCESS.replace_all(&expr.code, "$3");
+    match kind {
+        Kind::Handler => format!(
+            "const handleEvent = {};\nand reference `handleEvent` from the attribute.",
+            code
+        ),
+        Kind::Loop => {
+            let params = expr
+                .loop_context
+                .as_ref()
+                .map(|lc| lc.variables.join(", "))
+                .unwrap_or_default();
+            format!(
+                "const itemLabel = ({}) => {};\nand render `{{itemLabel({})}}`.",
+                params, code, params
+            )
+        }
+        Kind::Render => format!("const label = () => {};\nand render `{{label()}}`.", code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(source: &str, options: CompileOptions) -> CompileResult {
+        compile_zen_internal(source, "page.zen", options).unwrap()
+    }
+
+    fn complexity_warnings(output: &CompileResult) -> Vec<&String> {
+        output
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with(COMPLEXITY_WARNING))
+            .collect()
+    }
+
+    #[test]
+    fn test_measure_counts_depth_and_deps() {
+        let expr = ExpressionIR {
+            id: "expr_0".to_string(),
+            code: r#"a ? (b && window.__ZENITH_SCOPES__["inst0"].state.open ? 1 : 2) : a"#
+                .to_string(),
+            location: Default::default(),
+            loop_context: None,
+            origin: None,
+            extra: Default::default(),
+        };
+        let (a, b) = ("a".to_string(), "b".to_string());
+        let m = measure(&expr, Kind::Render, &HashSet::from([&a, &b]));
+        assert_eq!(m.length, "a ? (b && open ? 1 : 2) : a".len() as u32);
+        assert_eq!(m.depth, 3);
+        assert_eq!(m.deps, 3);
+        assert!(m.nodes > 8);
+    }
+
+    #[test]
+    fn test_over_threshold_expression_warns_with_metrics() {
+        let source = r#"<script>
+state count = 0
+state total = 10
+</script>
+<p>{count > 0 ? (count > 5 ? (count > 9 ? (count > 99 ? "lots" : "many") : "some") : "few") : total > 0 ? "none yet" : "empty"}</p>"#;
+        let output = compile(source, CompileOptions::default());
+        let warnings = complexity_warnings(&output);
+        assert_eq!(warnings.len(), 1, "{:?}", output.warnings);
+        assert!(warnings[0].contains("nesting depth 4 (max 3)"));
+        assert!(!warnings[0].contains("state deps"));
+        assert!(warnings[0].contains("const label = () => count > 0"));
+        assert!(warnings[0].contains("page.zen (line"));
+    }
+
+    #[test]
+    fn test_handler_threshold_differs() {
+        let source = r#"<script>
+state count = 0
+</script>
+<button onclick={() => { count = count > 0 ? (count > 5 ? (count > 9 ? 0 : count + 2) : count + 1) : 1 }}>Go</button>"#;
+        // Depth 3 is within the handler default
+        assert!(complexity_warnings(&compile(source, CompileOptions::default())).is_empty());
+
+        let options = CompileOptions {
+            expression_complexity: Some(ComplexityLimits {
+                handler: Some(ComplexityThresholds {
+                    max_depth: Some(2),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let output = compile(source, options);
+        let warnings = complexity_warnings(&output);
+        assert_eq!(warnings.len(), 1, "{:?}", output.warnings);
+        assert!(warnings[0].contains("event handler"));
+        assert!(warnings[0].contains("nesting depth 3 (max 2)"));
+        assert!(warnings[0].contains("const handleEvent = () =>"));
+    }
+
+    #[test]
+    fn test_manifest_reports_metrics_for_all_expressions() {
+        let source = r#"<script>
+state items = [1, 2]
+state label = "x"
+</script>
+<h1>{label}</h1>
+<ul>{items.map(item => <li onclick={() => console.log(item)}>{item * 2}</li>)}</ul>"#;
+        let options = CompileOptions {
+            ast_classifier: true,
+            ..Default::default()
+        };
+        let output = compile(source, options);
+        assert!(complexity_warnings(&output).is_empty());
+        let manifest = output.manifest.unwrap();
+        let metrics = &manifest.expression_metrics;
+        assert_eq!(metrics.len(), 4);
+        for m in metrics {
+            assert!(manifest.expressions.contains(&m.id), "{}", m.id);
+        }
+        let kinds: Vec<&str> = metrics.iter().map(|m| m.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["render", "render", "handler", "loop"]);
+        // `{label}` sits outside the classified loop
+        assert_eq!(
+            (metrics[0].length, metrics[0].nodes, metrics[0].deps),
+            (5, 1, 1)
+        );
+    }
+}
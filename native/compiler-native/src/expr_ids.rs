@@ -0,0 +1,201 @@
+//! Expression Ids
+//!
+//! An expression's id comes from where it sits in the template, not from the
+//! order expressions were parsed in, so recompiling an edited page only changes
+//! the ids of the expressions the edit touched and the dev server's HMR diff
+//! sees the rest as unchanged. The anchor hashed into an id is:
+//!
+//! - the path of enclosing elements and components from the template root, one
+//!   step per tag name, with the static `id` or `key` of elements that have one
+//! - the attribute name for an attribute expression, nothing for a child one
+//! - the code, and its ordinal among expressions with the same code at the same
+//!   path and attribute, so identical expressions in one place stay apart
+//!
+//! Sibling positions are left out on purpose: a paragraph inserted above another
+//! must not shift the ids below it. Moving an element changes the path, and with
+//! it the ids of that element's expressions only. Two anchors that hash alike
+//! (not expected) get a disambiguating suffix on the anchor of the later one.
+//!
+//! Fragment lowering (`classify.rs`) registers the parts of an expression under
+//! ids derived from the lowered expression's id (`derived_id`), and markup in it
+//! is anchored below that, so a lowered fragment is as stable as its expression.
+//! Ids keep the `expr_<digits>` form the bundle and tooling expect.
+
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+
+use crate::validate::{AttributeValue, ExpressionIR, TemplateNode};
+
+/// Attributes that name an element independently of its position
+const IDENTITY_ATTRS: &[&str] = &["id", crate::loop_keys::KEY_ATTR];
+
+/// Id for an anchor string
+fn id_for(anchor: &str) -> String {
+    let digest = Sha256::digest(anchor.as_bytes());
+    let hash = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+    format!("expr_{}", hash)
+}
+
+/// Id of the `ordinal`th expression registered while lowering `parent`
+pub(crate) fn derived_id(parent: &str, ordinal: usize) -> String {
+    id_for(&format!("{}>{}", parent, ordinal))
+}
+
+struct Anchors<'a> {
+    seed: &'a str,
+    codes: HashMap<&'a str, &'a str>,
+    /// Expressions seen per (path, attribute, code)
+    seen: HashMap<String, usize>,
+    taken: HashSet<String>,
+    ids: HashMap<String, String>,
+}
+
+impl<'a> Anchors<'a> {
+    fn assign(&mut self, old: &str, path: &str, slot: &str) {
+        if self.ids.contains_key(old) {
+            return;
+        }
+        let code = self.codes.get(old).copied().unwrap_or_default();
+        let key = format!("{}|{}|{}|{}", self.seed, path, slot, code);
+        let ordinal = self.seen.entry(key.clone()).or_default();
+        let anchor = format!("{}|{}", key, ordinal);
+        *ordinal += 1;
+
+        let mut id = id_for(&anchor);
+        let mut suffix = 1;
+        while self.taken.contains(&id) {
+            id = id_for(&format!("{}~{}", anchor, suffix));
+            suffix += 1;
+        }
+        self.taken.insert(id.clone());
+        self.ids.insert(old.to_string(), id);
+    }
+
+    fn walk(&mut self, nodes: &[TemplateNode], path: &str) {
+        for node in nodes {
+            let (name, attributes, children) = match node {
+                TemplateNode::Expression(e) => {
+                    self.assign(&e.expression, path, "");
+                    continue;
+                }
+                TemplateNode::Element(el) => (&el.tag, &el.attributes, &el.children),
+                TemplateNode::Component(c) => (&c.name, &c.attributes, &c.children),
+                _ => continue,
+            };
+            let mut step = format!("{}/{}", path, name);
+            for attr in attributes {
+                if let AttributeValue::Static(value) = &attr.value {
+                    if IDENTITY_ATTRS.contains(&attr.name.as_str()) {
+                        step.push_str(&format!("#{}", value));
+                    }
+                }
+            }
+            for attr in attributes {
+                if let AttributeValue::Dynamic(expr) = &attr.value {
+                    self.assign(&expr.id, &step, &attr.name);
+                }
+            }
+            self.walk(children, &step);
+        }
+    }
+}
+
+/// Replace the ids of a freshly parsed template with anchored ones. `seed` sets
+/// lowered markup apart from the page (empty for a template of its own).
+pub(crate) fn anchor_ids(
+    nodes: &mut Vec<TemplateNode>,
+    expressions: &mut [ExpressionIR],
+    seed: &str,
+) {
+    let ids = {
+        let mut anchors = Anchors {
+            seed,
+            codes: expressions
+                .iter()
+                .map(|e| (e.id.as_str(), e.code.as_str()))
+                .collect(),
+            seen: HashMap::new(),
+            taken: HashSet::new(),
+            ids: HashMap::new(),
+        };
+        anchors.walk(nodes, "");
+        // Registered but not placed in the tree: anchored by code alone
+        for expr in expressions.iter() {
+            anchors.assign(&expr.id, "?", "");
+        }
+        anchors.ids
+    };
+    crate::component::rewrite_node_expressions(nodes, &ids);
+    for expr in expressions {
+        if let Some(id) = ids.get(&expr.id) {
+            expr.id = id.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::parse_template;
+    use crate::validate::TemplateIR;
+
+    fn id_of<'t>(template: &'t TemplateIR, code: &str) -> &'t str {
+        &template
+            .expressions
+            .iter()
+            .find(|e| e.code == code)
+            .unwrap_or_else(|| panic!("no `{}` in {:?}", code, template.expressions))
+            .id
+    }
+
+    fn parse(source: &str) -> TemplateIR {
+        parse_template(source, "page.zen").unwrap()
+    }
+
+    #[test]
+    fn test_ids_are_the_same_across_compiles() {
+        let source = "<main><h1 title={title}>{title}</h1><p>{body}</p></main>";
+        let (first, second) = (parse(source), parse(source));
+        let ids = |t: &TemplateIR| {
+            t.expressions
+                .iter()
+                .map(|e| e.id.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&first), ids(&second));
+        assert!(ids(&first)
+            .iter()
+            .all(|id| regex::Regex::new(r"^expr_\d+$").unwrap().is_match(id)));
+    }
+
+    #[test]
+    fn test_inserting_a_paragraph_keeps_existing_ids() {
+        let before = parse("<main><p>{body}</p><button onclick={save}>Save</button></main>");
+        let after =
+            parse("<main><p>{intro}</p><p>{body}</p><button onclick={save}>Save</button></main>");
+        assert_eq!(id_of(&before, "body"), id_of(&after, "body"));
+        assert_eq!(id_of(&before, "save"), id_of(&after, "save"));
+        assert_eq!(after.expressions.len(), 3);
+    }
+
+    #[test]
+    fn test_moving_an_element_changes_only_its_ids() {
+        let before = parse("<main><section><p>{a}</p></section><aside><b>{b}</b></aside></main>");
+        let after = parse("<main><section></section><aside><p>{a}</p><b>{b}</b></aside></main>");
+        assert_ne!(id_of(&before, "a"), id_of(&after, "a"));
+        assert_eq!(id_of(&before, "b"), id_of(&after, "b"));
+    }
+
+    #[test]
+    fn test_identical_expressions_get_distinct_ids() {
+        let template = parse("<main><p>{count}</p><p>{count}</p><span>{count}</span></main>");
+        let mut ids: Vec<&str> = template.expressions.iter().map(|e| e.id.as_str()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+
+        // Element ids anchor their subtree
+        let before = parse("<main><p id=\"x\">{count}</p></main>");
+        let after = parse("<main><p>{count}</p><p id=\"x\">{count}</p></main>");
+        assert_eq!(before.expressions[0].id, after.expressions[1].id);
+    }
+}
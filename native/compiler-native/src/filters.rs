@@ -0,0 +1,203 @@
+//! Filter Pipes for Zenith Compiler
+//!
+//! Lowers `{value | name(args)}` in template expressions to calls of the
+//! compiler-known global `zenFilter('name', value, args)`, before identifier
+//! classification, so the piped value is tracked like any other expression.
+//!
+//! A top-level `|` is only treated as a pipe when every right-hand segment is a
+//! filter call shape (`name` or `name(args)`) and the left operand is neither a
+//! numeric literal nor parenthesized; anything else stays a bitwise-or.
+
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::validate::ZenIR;
+
+lazy_static::lazy_static! {
+    static ref FILTER_RE: Regex =
+        Regex::new(r"(?s)^\s*([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)\s*(?:\((.*)\))?\s*$").unwrap();
+    static ref NUMERIC_RE: Regex = Regex::new(r"^[0-9.][\w.]*$").unwrap();
+}
+
+/// Split at top-level single `|` (not `||`, not `|=`), ignoring strings and nesting.
+fn split_top_level_pipes(code: &str) -> Vec<&str> {
+    let bytes = code.as_bytes();
+    let mut segments = Vec::new();
+    let mut depth = 0i32;
+    let mut quote: Option<u8> = None;
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        if let Some(q) = quote {
+            if b == b'\\' {
+                i += 1;
+            } else if b == q {
+                quote = None;
+            }
+        } else {
+            match b {
+                b'\'' | b'"' | b'`' => quote = Some(b),
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' | b'}' => depth -= 1,
+                b'|' if depth == 0 => {
+                    let prev = if i > 0 { bytes[i - 1] } else { 0 };
+                    let next = bytes.get(i + 1).copied().unwrap_or(0);
+                    if prev != b'|' && next != b'|' && next != b'=' {
+                        segments.push(&code[start..i]);
+                        start = i + 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    segments.push(&code[start..]);
+    segments
+}
+
+fn is_parenthesized(operand: &str) -> bool {
+    if !operand.starts_with('(') || !operand.ends_with(')') {
+        return false;
+    }
+    // The opening paren must close at the very end, not `(a) + (b)`
+    let mut depth = 0i32;
+    for (i, c) in operand.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i == operand.len() - 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Rewrite a pipe expression into nested `zenFilter` calls.
+/// Returns the rewritten code and the filter names used (left to right),
+/// or None when the expression contains no pipes.
+pub fn lower_pipes(code: &str) -> Option<(String, Vec<String>)> {
+    let segments = split_top_level_pipes(code);
+    if segments.len() < 2 {
+        return None;
+    }
+
+    let value = segments[0].trim();
+    if value.is_empty() || NUMERIC_RE.is_match(value) || is_parenthesized(value) {
+        return None;
+    }
+
+    let mut filters = Vec::new();
+    for segment in &segments[1..] {
+        let caps = FILTER_RE.captures(segment)?;
+        let name = caps.get(1)?.as_str().to_string();
+        let args = caps
+            .get(2)
+            .map(|m| m.as_str().trim().to_string())
+            .filter(|a| !a.is_empty());
+        filters.push((name, args));
+    }
+
+    let mut lowered = value.to_string();
+    for (name, args) in &filters {
+        lowered = match args {
+            Some(args) => format!("zenFilter('{}', {}, {})", name, lowered, args),
+            None => format!("zenFilter('{}', {})", name, lowered),
+        };
+    }
+
+    Some((lowered, filters.into_iter().map(|(name, _)| name).collect()))
+}
+
+/// Lower pipes in all template expressions and record used filters on the IR.
+pub fn lower_filter_pipes(ir: &mut ZenIR) {
+    let mut rewritten: HashMap<String, String> = HashMap::new();
+
+    for expr in &mut ir.template.expressions {
+        if let Some((code, used)) = lower_pipes(&expr.code) {
+            expr.code = code.clone();
+            rewritten.insert(expr.id.clone(), code);
+            for name in used {
+                if !ir.used_filters.contains(&name) {
+                    ir.used_filters.push(name);
+                }
+            }
+        }
+    }
+
+    if !rewritten.is_empty() {
+        crate::i18n::update_attribute_code(&mut ir.template.nodes, &rewritten);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions};
+
+    #[test]
+    fn test_single_pipe() {
+        let (code, used) = lower_pipes("price | currency").unwrap();
+        assert_eq!(code, "zenFilter('currency', price)");
+        assert_eq!(used, vec!["currency"]);
+    }
+
+    #[test]
+    fn test_chained_pipes_with_args() {
+        let (code, used) = lower_pipes("createdAt | date('YYYY-MM-DD') | upper").unwrap();
+        assert_eq!(
+            code,
+            "zenFilter('upper', zenFilter('date', createdAt, 'YYYY-MM-DD'))"
+        );
+        assert_eq!(used, vec!["date", "upper"]);
+    }
+
+    #[test]
+    fn test_bitwise_or_untouched() {
+        assert!(lower_pipes("flags | 0").is_none());
+        assert!(lower_pipes("1 | mask").is_none());
+        assert!(lower_pipes("(a + b) | mask").is_none());
+        assert!(lower_pipes("a | (b)").is_none());
+        assert!(lower_pipes("a || b").is_none());
+        assert!(lower_pipes("label + ' | ' + name").is_none());
+    }
+
+    const PAGE: &str = r#"<script>
+state price = 10
+</script>
+<p>{price | currency}</p>"#;
+
+    #[test]
+    fn test_piped_value_is_tracked() {
+        let options = CompileOptions {
+            filters: vec!["currency".to_string()],
+            ..Default::default()
+        };
+        let result = compile_zen_internal(PAGE, "page.zen", options).unwrap();
+        assert!(!result.has_errors);
+        let manifest = result.manifest.unwrap();
+        assert!(manifest
+            .expressions
+            .contains("zenFilter(\"currency\", scope.state.price)"));
+        // The expression registry is part of the bundle
+        assert!(manifest.bundle.contains("deps: ['price']"));
+    }
+
+    #[test]
+    fn test_unknown_filter_errors() {
+        let result = compile_zen_internal(PAGE, "page.zen", CompileOptions::default()).unwrap();
+        assert!(result.has_errors);
+        assert!(result.errors[0].starts_with("Z-ERR-UNKNOWN-FILTER"));
+        assert!(result.errors[0].contains("`currency`"));
+    }
+}
@@ -5,34 +5,57 @@
 
 #[cfg(feature = "napi")]
 use napi_derive::napi;
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{Expression, IdentifierReference, Statement};
+use oxc_ast_visit::Visit;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use crate::codegen::{generate_runtime_code_internal, CodegenInput, ScriptImport};
+use crate::codegen::{
+    collect_head_expression_ids, emit_runtime_code, generate_runtime_code_internal,
+    plan_runtime_code, CodegenInput, PlanDecision, RuntimeCode, ScriptImport,
+};
+use crate::component::ComponentIR;
 use crate::validate::{ExpressionInput, LoopContextInput, TemplateNode, ZenIR};
 
 /// Inject head directive elements into HTML <head> section at compile time
-fn inject_head_elements(html: &str, head: &crate::validate::HeadDirective) -> String {
+fn inject_head_elements(html: &str, head: &crate::validate::HeadDirective, minify: bool) -> String {
+    lazy_static::lazy_static! {
+        static ref TITLE_RE: Regex = Regex::new(r"(?is)<title\b[^>]*>.*?</title>").unwrap();
+        static ref DESCRIPTION_RE: Regex =
+            Regex::new(r#"(?i)<meta\s+name=["']description["'][^>]*>"#).unwrap();
+        static ref HEAD_CLOSE_RE: Regex = Regex::new(r"(?i)</head>").unwrap();
+    }
+
+    let mut html = html.to_string();
     let mut injected = String::new();
+    let separator = if minify { "" } else { "\n    " };
+    // Only the document head is searched (an inline <svg> may carry its own <title>)
+    let head_end = |html: &str| HEAD_CLOSE_RE.find(html).map_or(0, |m| m.start());
 
-    // Inject title if present
-    // Inject title if present
+    // Inject title if present; an existing <title> (e.g. from a layout) is replaced, not duplicated
     if let Some(title) = &head.title {
         // Title is already statically resolved in component.rs or transform.rs.
         // We do strictly no runtime resolution here.
-        let resolved = title.clone();
-        injected.push_str(&format!("<title>{}</title>\n    ", resolved));
+        let tag = format!("<title>{}</title>", title);
+        match TITLE_RE.find(&html[..head_end(&html)]).map(|m| m.range()) {
+            Some(range) => html.replace_range(range, &tag),
+            None => injected.push_str(&format!("{}{}", tag, separator)),
+        }
     }
 
-    // Inject description meta tag if present
-    // Inject description meta tag if present
+    // Inject description meta tag if present (same replacement rule as the title)
     if let Some(desc) = &head.description {
-        let resolved = desc.clone();
-        injected.push_str(&format!(
-            r#"<meta name="description" content="{}" />"#,
-            resolved
-        ));
-        injected.push_str("\n    ");
+        let tag = format!(r#"<meta name="description" content="{}" />"#, desc);
+        match DESCRIPTION_RE
+            .find(&html[..head_end(&html)])
+            .map(|m| m.range())
+        {
+            Some(range) => html.replace_range(range, &tag),
+            None => injected.push_str(&format!("{}{}", tag, separator)),
+        }
     }
 
     // Inject additional meta tags
@@ -54,7 +77,7 @@ fn inject_head_elements(html: &str, head: &crate::validate::HeadDirective) -> St
                 prop, content
             ));
         }
-        injected.push_str("\n    ");
+        injected.push_str(separator);
     }
 
     // Inject link tags
@@ -63,12 +86,13 @@ fn inject_head_elements(html: &str, head: &crate::validate::HeadDirective) -> St
         if let Some(t) = &link.r#type {
             link_tag.push_str(&format!(r#" type="{}""#, t));
         }
-        link_tag.push_str(" />\n    ");
+        link_tag.push_str(" />");
+        link_tag.push_str(separator);
         injected.push_str(&link_tag);
     }
 
     if injected.is_empty() {
-        return html.to_string();
+        return html;
     }
 
     // Find the </head> tag and inject before it
@@ -81,13 +105,13 @@ fn inject_head_elements(html: &str, head: &crate::validate::HeadDirective) -> St
         // Inject after <head>
         let head_end = idx + 6;
         let mut result = html[..head_end].to_string();
-        result.push_str("\n    ");
+        result.push_str(separator);
         result.push_str(&injected);
         result.push_str(&html[head_end..]);
         result
     } else {
         // No head tag found, just return as-is
-        html.to_string()
+        html
     }
 }
 
@@ -96,11 +120,43 @@ fn inject_head_elements(html: &str, head: &crate::validate::HeadDirective) -> St
 pub struct CompiledTemplate {
     pub html: String,
     pub styles: Vec<String>,
+    /// Number of hydration bindings produced by transform (for payload metrics)
+    #[serde(default)]
+    pub binding_count: usize,
+}
+
+/// Hydration payload budgets. Each exceeded limit is reported as an error.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct Budgets {
+    pub max_expressions: Option<u32>,
+    pub max_bundle_bytes: Option<u32>,
+    pub max_state_keys: Option<u32>,
+    pub max_bindings: Option<u32>,
+}
+
+/// Measured hydration payload of a page, always reported in the manifest.
+/// Taken at finalize time, i.e. after transform has produced the final bindings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadMetrics {
+    pub expressions: u32,
+    pub bundle_bytes: u32,
+    pub state_keys: u32,
+    pub bindings: u32,
+    /// Expressions inside `zen:static` subtrees (evaluated once, never updated)
+    #[serde(default)]
+    pub frozen_expressions: u32,
 }
 
 /// Manifest export for the bundler's capability-based chunking.
 /// This is the Compiler → Bundler handshake contract.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "napi", napi(object))]
 #[serde(rename_all = "camelCase")]
 pub struct ZenManifestExport {
@@ -116,6 +172,10 @@ pub struct ZenManifestExport {
     pub is_static: bool,
     /// CSS classes used by this page (for pruning)
     pub css_classes: Vec<String>,
+    /// A `class` expression renders names not known at compile time, so
+    /// `css_classes` may be incomplete and no CSS should be pruned
+    #[serde(default)]
+    pub has_dynamic_classes: bool,
     /// Required runtime capabilities (as strings for JS interop)
     pub required_capabilities: Vec<String>,
     /// Compiled script content (author code)
@@ -128,6 +188,69 @@ pub struct ZenManifestExport {
     pub styles: String,
     /// NPM imports
     pub npm_imports: String,
+    /// Measured hydration payload (expressions, bundle size, state keys, bindings)
+    #[serde(default)]
+    pub metrics: PayloadMetrics,
+    /// Per component: declared props never received in this compilation
+    #[serde(default)]
+    pub unused_props: std::collections::BTreeMap<String, Vec<String>>,
+    /// Resolved component for each tag usage (tag, registry key, source path)
+    #[serde(default)]
+    pub component_usages: Vec<crate::validate::ComponentUsage>,
+    /// Component usages inlined as plain markup, without scope registration
+    #[serde(default)]
+    pub inlined_components: u32,
+    /// Console calls and `debugger` statements removed by `strip_debug`
+    #[serde(default)]
+    pub stripped_debug_statements: u32,
+    /// Per component: props passed conditionally (`name?={expr}`) with a dynamic value
+    #[serde(default)]
+    pub conditional_props: std::collections::BTreeMap<String, Vec<String>>,
+    /// Per component: static props passed as number/boolean literals (`"pageSize: number"`)
+    #[serde(default)]
+    pub coerced_props: std::collections::BTreeMap<String, Vec<String>>,
+    /// Per component: events emitted via `zenDispatch()` (handled with `on:name`)
+    #[serde(default)]
+    pub component_events: std::collections::BTreeMap<String, Vec<String>>,
+    /// Declared props (page props, or the component's own props in component mode)
+    #[serde(default)]
+    pub props: Vec<String>,
+    /// Slot names a standalone component exposes (`default` for the unnamed slot)
+    #[serde(default)]
+    pub slots: Vec<String>,
+    /// Expression ids of `zen:action` handlers on progressively enhanced forms
+    #[serde(default)]
+    pub enhanced_forms: Vec<String>,
+    /// Hydrated islands with their size and dependencies (islands mode only)
+    #[serde(default)]
+    pub islands: Vec<crate::islands::IslandManifest>,
+    /// Modules emitted next to `bundle` and imported by it on demand (see `lazy.rs`)
+    #[serde(default)]
+    pub chunks: Vec<crate::lazy::BundleChunk>,
+    /// `zen:if-env` / `zen:env` regions left out for this build's environment
+    #[serde(default)]
+    pub stripped_env_regions: Vec<crate::env::StrippedEnvRegion>,
+    /// When each instance with a `client:*` loading directive hydrates
+    #[serde(default)]
+    pub hydration: Vec<crate::client_directives::InstanceHydration>,
+    /// Complexity of every expression, whether or not it exceeded a threshold
+    #[serde(default)]
+    pub expression_metrics: Vec<crate::expr_complexity::ExpressionMetrics>,
+    /// Routes the page navigates to, for prefetching (see `navigation.rs`)
+    #[serde(default)]
+    pub navigation: crate::navigation::Navigation,
+    /// Repeated inline SVGs moved into the page's sprite (see `svg_sprite.rs`)
+    #[serde(default)]
+    pub svg_sprite: crate::svg_sprite::SvgSprite,
+    /// Item keys of loops over statically known objects (see `loop_shapes.rs`)
+    #[serde(default)]
+    pub loop_item_keys: Vec<crate::loop_shapes::LoopItemKeys>,
+    /// Class names the compiler generated, by purpose (see `class_names.rs`)
+    #[serde(default)]
+    pub generated_classes: std::collections::BTreeMap<String, Vec<String>>,
+    /// Outputs of the compile's observers (see `observers.rs`)
+    #[serde(default)]
+    pub observations: std::collections::BTreeMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,8 +260,13 @@ pub struct FinalizedOutput {
     pub html: String,
     pub has_errors: bool,
     pub errors: Vec<String>,
+    /// Non-fatal diagnostics (e.g. a11y warnings) that do not block output
+    pub warnings: Vec<String>,
     /// Manifest for bundler's capability-based chunking
     pub manifest: Option<ZenManifestExport>,
+    /// Critical images and fonts (also injected into `<head>` when the document has one)
+    #[serde(default)]
+    pub preload_hints: Vec<crate::preload::PreloadHint>,
 }
 
 fn emit_imports(imports: &[ScriptImport]) -> String {
@@ -165,9 +293,13 @@ fn emit_imports(imports: &[ScriptImport]) -> String {
 fn verify_no_raw_expressions(html: &str, file_path: &str) -> Vec<String> {
     let mut errors = Vec::new();
     let re = Regex::new(r"\{[^}]*\}").unwrap();
+    // Script and style bodies are raw text (e.g. `is:inline` scripts), not markup
+    let raw_text =
+        Regex::new(r"(?is)<script\b[^>]*>.*?</script>|<style\b[^>]*>.*?</style>").unwrap();
+    let markup = raw_text.replace_all(html, "");
 
     let mut actual_expressions = Vec::new();
-    for mat in re.find_iter(html) {
+    for mat in re.find_iter(&markup) {
         let s = mat.as_str();
 
         // Exclusions matching TS logic
@@ -207,44 +339,293 @@ fn verify_no_raw_expressions(html: &str, file_path: &str) -> Vec<String> {
     errors
 }
 
-/// Internal finalize function for use by parse_full_zen_native
-pub fn finalize_output_internal(
+/// Compares measured metrics against budgets, one error per exceeded limit.
+fn check_budgets(metrics: &PayloadMetrics, budgets: &Budgets, file_path: &str) -> Vec<String> {
+    let checks = [
+        (
+            "Z-ERR-BUDGET-EXPRESSIONS",
+            "expressions",
+            metrics.expressions,
+            budgets.max_expressions,
+        ),
+        (
+            "Z-ERR-BUDGET-BUNDLE",
+            "bundle bytes",
+            metrics.bundle_bytes,
+            budgets.max_bundle_bytes,
+        ),
+        (
+            "Z-ERR-BUDGET-STATE",
+            "state keys",
+            metrics.state_keys,
+            budgets.max_state_keys,
+        ),
+        (
+            "Z-ERR-BUDGET-BINDINGS",
+            "bindings",
+            metrics.bindings,
+            budgets.max_bindings,
+        ),
+    ];
+
+    checks
+        .iter()
+        .filter_map(|(code, label, actual, limit)| {
+            limit.filter(|max| actual > max).map(|max| {
+                format!(
+                    "{}: {} has {} {}, exceeding the budget of {}",
+                    code, file_path, actual, label, max
+                )
+            })
+        })
+        .collect()
+}
+
+/// Every filter used by the page must be provided at runtime.
+fn check_filters(used: &[String], available: &[String], file_path: &str) -> Vec<String> {
+    used.iter()
+        .filter(|name| !available.contains(name))
+        .map(|name| {
+            format!(
+                "Z-ERR-UNKNOWN-FILTER: Filter `{}` used in {} is not provided. Add it to the compile `filters` list.",
+                name, file_path
+            )
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct IdentifierCollector {
+    names: Vec<String>,
+}
+
+impl<'a> Visit<'a> for IdentifierCollector {
+    fn visit_identifier_reference(&mut self, ident: &IdentifierReference<'a>) {
+        self.names.push(ident.name.to_string());
+    }
+}
+
+pub(crate) fn referenced_identifiers(code: &str) -> Vec<String> {
+    let allocator = Allocator::default();
+    let source_type = SourceType::default().with_jsx(true).with_typescript(true);
+    let ret = Parser::new(&allocator, code, source_type).parse();
+    let mut collector = IdentifierCollector::default();
+    collector.visit_program(&ret.program);
+    collector.names
+}
+
+/// Head expressions render once, immediately: warn when they depend on state
+/// (initial value only, unless reactive head is enabled) or have no static fallback.
+fn check_head_expressions(ir: &ZenIR, reactive_head: bool) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let initial_values = crate::static_eval::initial_state_values(&ir.all_states);
+    let mut head_ids = std::collections::HashSet::new();
+    collect_head_expression_ids(&ir.template.nodes, &mut head_ids);
+
+    for expr in ir
+        .template
+        .expressions
+        .iter()
+        .filter(|e| head_ids.contains(&e.id))
+    {
+        if !reactive_head {
+            let mut deps: Vec<String> = referenced_identifiers(&expr.code)
+                .into_iter()
+                .filter(|name| ir.all_states.contains_key(name) || ir.page_bindings.contains(name))
+                .collect();
+            deps.sort();
+            deps.dedup();
+            if !deps.is_empty() {
+                warnings.push(format!(
+                    "Z-WARN-HEAD-REACTIVE: <head> expression `{}` in {} depends on state `{}` and only reflects its initial value. Enable reactive_head to update it after hydration.",
+                    expr.code,
+                    ir.file_path,
+                    deps.join("`, `")
+                ));
+            }
+        }
+
+        if crate::static_eval::static_eval(&expr.code, &initial_values).is_none() {
+            warnings.push(format!(
+                "Z-WARN-HEAD-FALLBACK: <head> expression `{}` in {} cannot be statically evaluated and renders empty if it fails during render.",
+                expr.code, ir.file_path
+            ));
+        }
+    }
+
+    warnings
+}
+
+fn collect_child_expression_ids<'n>(nodes: &'n [TemplateNode], ids: &mut Vec<&'n str>) {
+    for node in nodes {
+        match node {
+            TemplateNode::Expression(e) if !e.is_in_head => ids.push(&e.expression),
+            TemplateNode::Element(el) => collect_child_expression_ids(&el.children, ids),
+            TemplateNode::Component(c) => collect_child_expression_ids(&c.children, ids),
+            TemplateNode::ConditionalFragment(cf) => {
+                collect_child_expression_ids(&cf.consequent, ids);
+                collect_child_expression_ids(&cf.alternate, ids);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    collect_child_expression_ids(nodes, ids);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => collect_child_expression_ids(&of.fragment, ids),
+            TemplateNode::LoopFragment(lf) => collect_child_expression_ids(&lf.body, ids),
+            _ => {}
+        }
+    }
+}
+
+/// Callee name when the whole expression is a single call to a bare identifier
+fn top_level_call_name(code: &str) -> Option<String> {
+    let allocator = Allocator::default();
+    let source_type = SourceType::default().with_jsx(true).with_typescript(true);
+    let ret = Parser::new(&allocator, code, source_type).parse();
+    if !ret.errors.is_empty() || ret.program.body.len() != 1 {
+        return None;
+    }
+    let stmt = match &ret.program.body[0] {
+        Statement::ExpressionStatement(stmt) => stmt,
+        _ => return None,
+    };
+    match stmt.expression.without_parentheses() {
+        Expression::CallExpression(call) => match &call.callee {
+            Expression::Identifier(ident) => Some(ident.name.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Render expressions re-run on every dependency change, so a bare call to a
+/// function outside the pure whitelist is most likely an unintended side effect.
+fn check_side_effect_expressions(ir: &ZenIR) -> Vec<String> {
+    let mut ids = Vec::new();
+    collect_child_expression_ids(&ir.template.nodes, &mut ids);
+
+    ids.into_iter()
+        .filter_map(|id| ir.template.expressions.iter().find(|e| e.id == id))
+        .filter_map(|expr| {
+            let name = top_level_call_name(&expr.code)?;
+            if crate::static_eval::is_pure_function(&name) {
+                return None;
+            }
+            Some(format!(
+                "Z-WARN-SIDE-EFFECT-EXPRESSION: `{}` in {} calls `{}` during render. Render expressions must be pure and re-run whenever their dependencies change; move side effects to zenOnMount/zenEffect, or write `{{void {}}}` to run it as an effect that renders nothing.",
+                expr.code, ir.file_path, name, expr.code
+            ))
+        })
+        .collect()
+}
+
+/// Compile-time checks applied by finalize on top of the IR
+#[derive(Debug, Clone, Default)]
+pub struct FinalizeOptions {
+    /// Hydration payload budgets
+    pub budgets: Option<Budgets>,
+    /// Filters provided at runtime via `zenFilter`
+    pub filters: Vec<String>,
+    /// Head text expressions are updated after hydration (see transform HeadOptions)
+    pub reactive_head: bool,
+    /// Maximum preload hints (`DEFAULT_PRELOAD_LIMIT` when unset, 0 disables them)
+    pub preload_limit: Option<u32>,
+    /// Production output: compact bundle sections, no cosmetic newlines in injected head tags
+    pub minify: bool,
+    /// Console calls and `debugger` statements to remove from the bundle
+    pub strip_debug: crate::strip_debug::StripDebug,
+    /// Checked per expression during codegen; a cancelled finalize returns `COMPILE_CANCELLED`
+    pub cancel: Option<crate::cancel::CancellationToken>,
+    /// Bundle hydrates inside an iframe `srcdoc` (see `embed.rs`)
+    pub embed: bool,
+    /// `<link rel="modulepreload">` for the runtime and the bundle's imports
+    pub modulepreload: bool,
+    /// Import specifier → URL for modulepreload links
+    pub import_url_map: std::collections::HashMap<String, String>,
+    /// `<link rel="prefetch">` for the assets of the routes the page navigates to
+    pub prefetch_links: bool,
+    /// Route → asset URL for prefetch links
+    pub prefetch_routes: std::collections::HashMap<String, String>,
+    /// Repeated static inline SVGs become `<use>` references to one hidden sprite
+    pub svg_sprite: bool,
+    /// Syntax level the bundle and its chunks are lowered to
+    pub target_es: crate::es_target::EsTarget,
+    /// Per-kind expression complexity thresholds (defaults when unset)
+    pub expression_complexity: Option<crate::expr_complexity::ComplexityLimits>,
+    /// Decides on the codegen plan before any code is generated
+    pub plan_filter: Option<crate::codegen::PlanFilter>,
+}
+
+/// What finalizing a page produced
+#[allow(clippy::large_enum_variant)]
+pub enum Finalized {
+    Page(FinalizedOutput),
+    /// The plan filter decided `PlanDecision::ForceIslands` for a page that is not
+    /// in islands mode: the compile starts over with `islands` set
+    ForceIslands,
+}
+
+/// Finalize with payload budgets and the filter whitelist checked
+pub fn finalize_output_with_options(
     ir: ZenIR,
     compiled: CompiledTemplate,
-) -> Result<FinalizedOutput, String> {
+    options: &FinalizeOptions,
+) -> Result<Finalized, String> {
     // PHASE 3: Resolve HEAD_EXPR markers to static values
     let mut resolved_html = compiled.html.clone();
 
     // PHASE 3.5: Inject Head component content into HTML <head>
     if let Some(ref head_directive) = ir.head_directive {
-        resolved_html = inject_head_elements(&resolved_html, head_directive);
+        resolved_html = inject_head_elements(&resolved_html, head_directive, options.minify);
+    }
+
+    // PHASE 3.6: Preload hints for above-the-fold images and font-face sources
+    let preload_limit = options
+        .preload_limit
+        .unwrap_or(crate::preload::DEFAULT_PRELOAD_LIMIT) as usize;
+    let preload_hints =
+        crate::preload::collect_preload_hints(&ir.template.nodes, &ir.styles, preload_limit);
+    resolved_html = crate::preload::inject_preload_links(&resolved_html, &preload_hints);
+
+    // PHASE 3.7: Routes reachable from the page, prefetched when mapped to assets
+    let navigation = crate::navigation::collect_navigation(&ir);
+    if options.prefetch_links {
+        resolved_html = crate::navigation::inject_prefetch_links(
+            &resolved_html,
+            &navigation.targets,
+            &options.prefetch_routes,
+        );
+    }
+
+    // PHASE 3.8: Repeated inline SVGs share one copy in a sprite
+    let mut svg_sprite = crate::svg_sprite::SvgSprite::default();
+    if options.svg_sprite {
+        (resolved_html, svg_sprite) = crate::svg_sprite::dedupe_inline_svgs(&resolved_html);
     }
 
     // Verify HTML (after HEAD_EXPR resolution)
     let html_errors = verify_no_raw_expressions(&resolved_html, &ir.file_path);
     if !html_errors.is_empty() {
-        return Ok(FinalizedOutput {
+        return Ok(Finalized::Page(FinalizedOutput {
             has_errors: true,
             errors: html_errors,
+            warnings: ir.warnings.clone(),
             html: String::new(),
             manifest: None,
-        });
+            preload_hints: vec![],
+        }));
     }
 
+    // Expression complexity, measured on the lowered code before codegen
+    let expression_metrics = crate::expr_complexity::expression_metrics(&ir);
+
     // Prepare Codegen Input
-    /*
-    eprintln!(
-        "[Zenith FINALIZE] IR script present: {}",
-        ir.script.is_some()
-    );
-    */
+    zen_trace!(has_script = ir.script.is_some(), "preparing codegen input");
     let script_content = ir
         .script
         .as_ref()
-        .map(|s| {
-            // eprintln!("[Zenith FINALIZE] Script length: {}", s.raw.len());
-            s.raw.clone()
-        })
+        .map(|s| s.raw.clone())
         .unwrap_or_default();
 
     // Map expressions
@@ -259,26 +640,70 @@ pub fn finalize_output_internal(
                 variables: lc.variables.clone(),
                 map_source: lc.map_source.clone(),
             }),
+            origin: e.origin.clone(),
         })
         .collect();
 
+    let islands = island_manifests(&ir, &expressions);
+    // Islands mode only generates canonical IR for island content
+    let nodes = match &ir.islands {
+        Some(islands) => islands
+            .iter()
+            .flat_map(|island| island.nodes.iter().cloned())
+            .collect(),
+        None => ir.template.nodes.clone(),
+    };
     let codegen_input = CodegenInput {
         file_path: ir.file_path.clone(),
         script_content,
         expressions,
-        styles: ir.styles.clone(),
+        styles: crate::global_styles::document_order(ir.styles.clone()),
         template_bindings: vec![],
         location: ir.file_path.clone(),
-        nodes: ir.template.nodes.clone(),
+        nodes,
         page_bindings: ir.page_bindings.clone(),
         page_props: ir.page_props.clone(),
         all_states: ir.all_states.clone(),
         locals: vec![],
+        islands: ir.islands.clone(),
+        minify: options.minify,
+        embed: options.embed,
+        strip_debug: options.strip_debug.clone(),
+        cancel: options.cancel.clone(),
+        target_es: options.target_es,
     };
 
-    let runtime_code = generate_runtime_code_internal(codegen_input);
+    let (runtime_code, skipped) = {
+        zen_span!(
+            DEBUG,
+            "codegen",
+            expressions = codegen_input.expressions.len()
+        );
+        let plan = plan_runtime_code(&codegen_input);
+        match options.plan_filter.map(|filter| filter(&plan)) {
+            Some(PlanDecision::Skip(reason)) => (RuntimeCode::default(), Some(reason)),
+            // The compile starts over in islands mode
+            Some(PlanDecision::ForceIslands) if ir.islands.is_none() => {
+                return Ok(Finalized::ForceIslands);
+            }
+            _ => (emit_runtime_code(&plan), None),
+        }
+    };
+    crate::cancel::check(options.cancel.as_ref())?;
     let final_imports = emit_imports(&runtime_code.npm_imports);
 
+    // PHASE 4.1: Module preloads for the runtime and npm imports
+    let mut unmapped_imports = Vec::new();
+    if options.modulepreload && skipped.is_none() {
+        let (html, unmapped) = crate::preload::inject_modulepreload_links(
+            &resolved_html,
+            &runtime_code.npm_imports,
+            &options.import_url_map,
+        );
+        resolved_html = html;
+        unmapped_imports = unmapped;
+    }
+
     // Scan for event handlers if not already detected
     let mut has_events = ir.has_events;
     if !has_events {
@@ -296,30 +721,476 @@ pub fn finalize_output_internal(
         required_capabilities.push("hydration".to_string());
     }
 
+    let mut state_keys: std::collections::HashSet<&String> = ir.all_states.keys().collect();
+    state_keys.extend(ir.page_bindings.iter());
+    let metrics = PayloadMetrics {
+        expressions: ir.template.expressions.len() as u32,
+        bundle_bytes: runtime_code.bundle.len() as u32,
+        state_keys: state_keys.len() as u32,
+        bindings: compiled.binding_count as u32,
+        frozen_expressions: crate::codegen::collect_frozen_expression_ids(&ir.template.nodes).len()
+            as u32,
+    };
+    let mut check_errors = check_filters(&ir.used_filters, &options.filters, &ir.file_path);
+    let mut enhanced_forms = Vec::new();
+    check_errors.extend(check_enhanced_forms(
+        &ir.template.nodes,
+        &ir.file_path,
+        &mut enhanced_forms,
+    ));
+    if let Some(budgets) = &options.budgets {
+        check_errors.extend(check_budgets(&metrics, budgets, &ir.file_path));
+    }
+    let (key_warnings, key_errors) = crate::loop_keys::check_loop_keys(&ir);
+    check_errors.extend(key_errors);
+    check_errors.extend(runtime_code.es_target_errors.clone());
+
     let manifest = ZenManifestExport {
         entry: ir.file_path.clone(),
         template: resolved_html.clone(),
         uses_state: ir.uses_state || !ir.all_states.is_empty(),
         has_events,
         is_static,
-        css_classes: ir.css_classes.clone(),
+        css_classes: if ir.css_classes.is_empty() {
+            collect_css_classes(&ir.template.nodes)
+        } else {
+            ir.css_classes.clone()
+        },
+        has_dynamic_classes: has_dynamic_classes(&ir.template.nodes),
         required_capabilities,
         script: runtime_code.script,
         bundle: runtime_code.bundle,
         expressions: runtime_code.expressions,
         styles: runtime_code.styles,
         npm_imports: final_imports,
+        metrics,
+        unused_props: ir.unused_props.clone(),
+        component_usages: ir.component_usages.clone(),
+        inlined_components: ir.inlined_components,
+        stripped_debug_statements: runtime_code.stripped_debug_statements,
+        conditional_props: ir.conditional_props.clone(),
+        coerced_props: ir.coerced_props.clone(),
+        component_events: ir.component_events.clone(),
+        props: ir.page_props.clone(),
+        slots: vec![],
+        enhanced_forms,
+        islands,
+        chunks: runtime_code.chunks.clone(),
+        stripped_env_regions: Vec::new(),
+        navigation,
+        svg_sprite,
+        loop_item_keys: ir.loop_item_keys.clone(),
+        generated_classes: ir.generated_classes.clone(),
+        observations: Default::default(),
+        hydration: ir.hydration.clone(),
+        expression_metrics: expression_metrics.clone(),
     };
 
-    Ok(FinalizedOutput {
+    let mut warnings = ir.warnings.clone();
+    warnings.extend(runtime_code.warnings);
+    warnings.extend(check_head_expressions(&ir, options.reactive_head));
+    warnings.extend(check_side_effect_expressions(&ir));
+    warnings.extend(crate::handlers::check_handler_calls(&ir));
+    warnings.extend(crate::expr_complexity::check_complexity(
+        &ir,
+        &expression_metrics,
+        options.expression_complexity.as_ref(),
+    ));
+    warnings.extend(crate::attr_coerce::check_data_attribute_objects(&ir));
+    warnings.extend(key_warnings);
+    if let Some(reason) = skipped {
+        warnings.push(format!(
+            "Z-INFO-CODEGEN-SKIPPED: the plan filter skipped codegen for {} ({}); the page ships without a bundle.",
+            ir.file_path, reason
+        ));
+    }
+    if !unmapped_imports.is_empty() {
+        unmapped_imports.sort();
+        warnings.push(format!(
+            "Z-INFO-MODULEPRELOAD-UNMAPPED: {} has no URL in `import_url_map` for {}; these imports are not preloaded.",
+            ir.file_path,
+            unmapped_imports
+                .iter()
+                .map(|s| format!("`{}`", s))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    Ok(Finalized::Page(FinalizedOutput {
         html: resolved_html,
-        has_errors: false,
-        errors: vec![],
+        has_errors: !check_errors.is_empty(),
+        errors: crate::diagnostics::normalize(check_errors),
+        warnings,
         manifest: Some(manifest),
-    })
+        preload_hints,
+    }))
+}
+
+/// Manifest entries for islands mode: expression count, init block plus expression
+/// source bytes, and the page state and component scopes each island reads
+fn island_manifests(
+    ir: &ZenIR,
+    expressions: &[ExpressionInput],
+) -> Vec<crate::islands::IslandManifest> {
+    let Some(islands) = &ir.islands else {
+        return vec![];
+    };
+    let mut state_keys: std::collections::HashSet<&String> = ir.all_states.keys().collect();
+    state_keys.extend(ir.page_bindings.iter());
+    islands
+        .iter()
+        .map(|island| {
+            let codes: Vec<&str> = expressions
+                .iter()
+                .filter(|e| island.expression_ids.contains(&e.id))
+                .map(|e| e.code.as_str())
+                .collect();
+            let bytes = crate::codegen::island_init_block(island).len()
+                + codes.iter().map(|c| c.len()).sum::<usize>();
+            crate::islands::IslandManifest {
+                id: island.id.clone(),
+                expressions: island.expression_ids.len() as u32,
+                bytes: bytes as u32,
+                deps: crate::islands::island_deps(&codes, &state_keys),
+            }
+        })
+        .collect()
 }
 
-fn check_for_events(nodes: &[TemplateNode]) -> bool {
+/// `zen:action` must sit on a `<form>` with static `action` and `method`, so the form
+/// still submits without JavaScript. Collects the handler expression ids.
+fn check_enhanced_forms(
+    nodes: &[TemplateNode],
+    file_path: &str,
+    handler_ids: &mut Vec<String>,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                let action = el
+                    .attributes
+                    .iter()
+                    .find(|a| a.name == crate::transform::FORM_ACTION_ATTR);
+                if let Some(action) = action {
+                    if let crate::validate::AttributeValue::Dynamic(expr) = &action.value {
+                        handler_ids.push(expr.id.clone());
+                    }
+                    if !el.tag.eq_ignore_ascii_case("form") {
+                        errors.push(format!(
+                            "Z-ERR-FORM-ACTION: `zen:action` is only supported on <form>, found on <{}> in {}.",
+                            el.tag, file_path
+                        ));
+                    } else {
+                        let has_static = |name: &str| {
+                            el.attributes.iter().any(|a| {
+                                a.name == name
+                                    && matches!(&a.value, crate::validate::AttributeValue::Static(v) if !v.trim().is_empty())
+                            })
+                        };
+                        let missing: Vec<&str> = ["action", "method"]
+                            .into_iter()
+                            .filter(|name| !has_static(name))
+                            .collect();
+                        if !missing.is_empty() {
+                            errors.push(format!(
+                                "Z-ERR-FORM-ACTION: <form zen:action> in {} needs a static {} so it still submits without JavaScript.",
+                                file_path,
+                                missing
+                                    .iter()
+                                    .map(|m| format!("`{}`", m))
+                                    .collect::<Vec<_>>()
+                                    .join(" and ")
+                            ));
+                        }
+                    }
+                }
+                errors.extend(check_enhanced_forms(&el.children, file_path, handler_ids));
+            }
+            TemplateNode::Component(c) => {
+                errors.extend(check_enhanced_forms(&c.children, file_path, handler_ids))
+            }
+            TemplateNode::ConditionalFragment(cf) => {
+                errors.extend(check_enhanced_forms(&cf.consequent, file_path, handler_ids));
+                errors.extend(check_enhanced_forms(&cf.alternate, file_path, handler_ids));
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    errors.extend(check_enhanced_forms(nodes, file_path, handler_ids));
+                }
+            }
+            TemplateNode::OptionalFragment(of) => {
+                errors.extend(check_enhanced_forms(&of.fragment, file_path, handler_ids))
+            }
+            TemplateNode::LoopFragment(lf) => {
+                errors.extend(check_enhanced_forms(&lf.body, file_path, handler_ids))
+            }
+            _ => {}
+        }
+    }
+    errors
+}
+
+/// Manifest for a component compiled on its own (no page, layout, or document context).
+/// Everything is derived from the component's nodes, expressions, script, and styles, so it
+/// matches what the component contributes when inlined into a page.
+pub fn finalize_component(
+    component: &ComponentIR,
+    compiled: CompiledTemplate,
+) -> ZenManifestExport {
+    let expressions: Vec<ExpressionInput> = component
+        .expressions
+        .iter()
+        .map(|e| ExpressionInput {
+            id: e.id.clone(),
+            code: e.code.clone(),
+            loop_context: e.loop_context.as_ref().map(|lc| LoopContextInput {
+                variables: lc.variables.clone(),
+                map_source: lc.map_source.clone(),
+            }),
+            origin: None,
+        })
+        .collect();
+
+    let codegen_input = CodegenInput {
+        file_path: component.path.clone(),
+        script_content: component.script.clone().unwrap_or_default(),
+        expressions,
+        styles: crate::global_styles::document_order(
+            component
+                .global_styles
+                .iter()
+                .map(|raw| (raw, true))
+                .chain(component.styles.iter().map(|raw| (raw, false)))
+                .map(|(raw, global)| crate::validate::StyleIR {
+                    raw: raw.clone(),
+                    global,
+                    source_component: component.path.clone(),
+                })
+                .collect(),
+        ),
+        template_bindings: vec![],
+        location: component.path.clone(),
+        nodes: component.nodes.clone(),
+        page_bindings: component.states.keys().cloned().collect(),
+        page_props: component.props.clone(),
+        all_states: component.states.clone(),
+        locals: vec![],
+        islands: None,
+        minify: false,
+        embed: false,
+        strip_debug: Default::default(),
+        cancel: None,
+        target_es: Default::default(),
+    };
+    let runtime_code = generate_runtime_code_internal(codegen_input);
+
+    let uses_state = !component.states.is_empty();
+    let has_events = check_for_events(&component.nodes);
+    let mut required_capabilities = vec!["core".to_string()];
+    if uses_state {
+        required_capabilities.push("reactivity".to_string());
+    }
+    if has_events || uses_state {
+        required_capabilities.push("hydration".to_string());
+    }
+
+    let mut slots: Vec<String> = component
+        .slots
+        .iter()
+        .map(|s| s.name.clone().unwrap_or_else(|| "default".to_string()))
+        .collect();
+    slots.sort();
+    slots.dedup();
+    let mut props: Vec<String> = crate::component::declared_props(component)
+        .into_iter()
+        .collect();
+    props.sort();
+    let events = crate::component::emitted_events(component);
+    let mut component_events = std::collections::BTreeMap::new();
+    if !events.is_empty() {
+        component_events.insert(component.name.clone(), events);
+    }
+
+    ZenManifestExport {
+        entry: component.path.clone(),
+        template: compiled.html,
+        uses_state,
+        has_events,
+        is_static: !uses_state && !has_events,
+        css_classes: collect_css_classes(&component.nodes),
+        has_dynamic_classes: has_dynamic_classes(&component.nodes),
+        required_capabilities,
+        script: runtime_code.script,
+        npm_imports: emit_imports(&runtime_code.npm_imports),
+        metrics: PayloadMetrics {
+            expressions: component.expressions.len() as u32,
+            bundle_bytes: runtime_code.bundle.len() as u32,
+            state_keys: component.states.len() as u32,
+            bindings: compiled.binding_count as u32,
+            frozen_expressions: crate::codegen::collect_frozen_expression_ids(&component.nodes)
+                .len() as u32,
+        },
+        bundle: runtime_code.bundle,
+        expressions: runtime_code.expressions,
+        styles: runtime_code.styles,
+        unused_props: Default::default(),
+        component_usages: vec![],
+        inlined_components: 0,
+        stripped_debug_statements: 0,
+        conditional_props: Default::default(),
+        coerced_props: Default::default(),
+        component_events,
+        props,
+        slots,
+        enhanced_forms: vec![],
+        islands: vec![],
+        chunks: runtime_code.chunks,
+        stripped_env_regions: Vec::new(),
+        navigation: Default::default(),
+        svg_sprite: Default::default(),
+        loop_item_keys: vec![],
+        generated_classes: Default::default(),
+        observations: Default::default(),
+        hydration: vec![],
+        expression_metrics: vec![],
+    }
+}
+
+/// Class names used in the template (sorted, unique): static `class` values,
+/// every string a dynamic one can produce, and `class:name` directives
+pub fn collect_css_classes(nodes: &[TemplateNode]) -> Vec<String> {
+    class_usage(nodes).0.into_iter().collect()
+}
+
+/// Whether a dynamic `class` can produce names not known at compile time, so
+/// `collect_css_classes` may miss some and no CSS should be pruned
+pub fn has_dynamic_classes(nodes: &[TemplateNode]) -> bool {
+    class_usage(nodes).1
+}
+
+fn class_usage(nodes: &[TemplateNode]) -> (std::collections::BTreeSet<String>, bool) {
+    fn walk(
+        nodes: &[TemplateNode],
+        classes: &mut std::collections::BTreeSet<String>,
+        dynamic: &mut bool,
+    ) {
+        for node in nodes {
+            match node {
+                TemplateNode::Element(el) => {
+                    for attr in &el.attributes {
+                        if let Some(name) = attr.name.strip_prefix("class:") {
+                            classes.insert(name.to_string());
+                        } else if attr.name == "class" || attr.name == "className" {
+                            let values = match &attr.value {
+                                crate::validate::AttributeValue::Static(value) => {
+                                    Some(vec![value.clone()])
+                                }
+                                crate::validate::AttributeValue::Dynamic(expr) => {
+                                    class_values(&expr.code)
+                                }
+                            };
+                            match values {
+                                Some(values) => classes.extend(
+                                    values
+                                        .iter()
+                                        .flat_map(|v| v.split_whitespace())
+                                        .map(String::from),
+                                ),
+                                None => *dynamic = true,
+                            }
+                        }
+                    }
+                    walk(&el.children, classes, dynamic);
+                }
+                TemplateNode::Component(c) => walk(&c.children, classes, dynamic),
+                TemplateNode::ConditionalFragment(cf) => {
+                    walk(&cf.consequent, classes, dynamic);
+                    walk(&cf.alternate, classes, dynamic);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    for nodes in mb.children() {
+                        walk(nodes, classes, dynamic);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => walk(&of.fragment, classes, dynamic),
+                TemplateNode::LoopFragment(lf) => walk(&lf.body, classes, dynamic),
+                _ => {}
+            }
+        }
+    }
+
+    let mut classes = std::collections::BTreeSet::new();
+    let mut dynamic = false;
+    walk(nodes, &mut classes, &mut dynamic);
+    (classes, dynamic)
+}
+
+/// Every string a class expression can render, with `""` for a falsy branch:
+/// both sides of a conditional, `a && "x"`, `a || "x"`, and template literals
+/// combined part by part. None when some part is not known at compile time.
+fn class_values(code: &str) -> Option<Vec<String>> {
+    fn values(code: &str, expr: &Expression) -> Option<Vec<String>> {
+        use oxc_span::GetSpan;
+        use oxc_syntax::operator::LogicalOperator;
+
+        match expr {
+            Expression::StringLiteral(s) => Some(vec![s.value.to_string()]),
+            Expression::BooleanLiteral(_) | Expression::NullLiteral(_) => Some(vec![String::new()]),
+            Expression::Identifier(id) if id.name == "undefined" => Some(vec![String::new()]),
+            Expression::ParenthesizedExpression(paren) => values(code, &paren.expression),
+            Expression::ConditionalExpression(cond) => {
+                let mut both = values(code, &cond.consequent)?;
+                both.extend(values(code, &cond.alternate)?);
+                Some(both)
+            }
+            // The condition of `a && "x"` only matters when falsy
+            Expression::LogicalExpression(logical) if logical.operator == LogicalOperator::And => {
+                let mut right = values(code, &logical.right)?;
+                right.push(String::new());
+                Some(right)
+            }
+            Expression::LogicalExpression(logical) => {
+                let mut both = values(code, &logical.left)?;
+                both.extend(values(code, &logical.right)?);
+                Some(both)
+            }
+            Expression::TemplateLiteral(template) => {
+                let mut rendered = vec![String::new()];
+                for (i, quasi) in template.quasis.iter().enumerate() {
+                    let text = quasi.value.cooked.as_ref()?.as_str();
+                    rendered.iter_mut().for_each(|r| r.push_str(text));
+                    if let Some(part) = template.expressions.get(i) {
+                        let parts = values(code, part)?;
+                        rendered = rendered
+                            .iter()
+                            .flat_map(|r| parts.iter().map(move |p| format!("{}{}", r, p)))
+                            .collect();
+                    }
+                }
+                Some(rendered)
+            }
+            // Numbers, concatenated literals
+            _ => {
+                let span = expr.span();
+                let value = crate::static_eval::static_eval(
+                    &code[span.start as usize..span.end as usize],
+                    &std::collections::HashMap::new(),
+                )?;
+                Some(vec![value])
+            }
+        }
+    }
+
+    let allocator = Allocator::default();
+    let expr = Parser::new(&allocator, code, SourceType::tsx())
+        .parse_expression()
+        .ok()?;
+    values(code, &expr)
+}
+
+pub(crate) fn check_for_events(nodes: &[TemplateNode]) -> bool {
     for node in nodes {
         match node {
             TemplateNode::Element(el) => {
@@ -329,6 +1200,7 @@ fn check_for_events(nodes: &[TemplateNode]) -> bool {
                         || name.starts_with("data-zen-click")
                         || name.starts_with("data-zen-change")
                         || name.starts_with("data-zen-input")
+                        || name == crate::transform::FORM_ACTION_ATTR
                     {
                         return true;
                     }
@@ -352,6 +1224,11 @@ fn check_for_events(nodes: &[TemplateNode]) -> bool {
                     return true;
                 }
             }
+            TemplateNode::MultiBranchFragment(mb)
+                if mb.children().any(|nodes| check_for_events(nodes)) =>
+            {
+                return true;
+            }
             TemplateNode::LoopFragment(lf) => {
                 if check_for_events(&lf.body) {
                     return true;
@@ -367,3 +1244,215 @@ fn check_for_events(nodes: &[TemplateNode]) -> bool {
     }
     false
 }
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::{transform_template_with_options, HeadOptions};
+
+    fn page_with_title(code: &str) -> ZenIR {
+        serde_json::from_value(serde_json::json!({
+            "filePath": "page.zen",
+            "template": {
+                "raw": "",
+                "nodes": [{
+                    "type": "element", "tag": "html", "attributes": [], "children": [{
+                        "type": "element", "tag": "head", "attributes": [], "children": [{
+                            "type": "element", "tag": "title", "attributes": [], "children": [{
+                                "type": "expression", "expression": "expr_title", "isInHead": true
+                            }]
+                        }]
+                    }]
+                }],
+                "expressions": [{ "id": "expr_title", "code": code }]
+            },
+            "script": null,
+            "styles": [],
+            "pageBindings": ["pageTitle"],
+            "allStates": { "pageTitle": "\"Inbox\"" }
+        }))
+        .unwrap()
+    }
+
+    fn compile(ir: ZenIR, reactive: bool) -> (FinalizedOutput, Vec<crate::transform::Binding>) {
+        let head = HeadOptions {
+            reactive,
+            initial_values: crate::static_eval::initial_state_values(&ir.all_states),
+            initial_objects: crate::attr_coerce::initial_object_values(&ir.all_states),
+            minify: false,
+            virtual_loops: std::collections::HashMap::new(),
+            select_loops: std::collections::HashMap::new(),
+            html: Default::default(),
+        };
+        let transformed = transform_template_with_options(
+            &ir.template.nodes,
+            &ir.template.expressions,
+            None,
+            &head,
+        );
+        let compiled = CompiledTemplate {
+            html: transformed.html,
+            styles: vec![],
+            binding_count: transformed.bindings.len(),
+        };
+        let options = FinalizeOptions {
+            reactive_head: reactive,
+            ..Default::default()
+        };
+        let Finalized::Page(output) = finalize_output_with_options(ir, compiled, &options).unwrap()
+        else {
+            panic!("no plan filter is set");
+        };
+        (output, transformed.bindings)
+    }
+
+    #[test]
+    fn test_head_expression_on_state_warns_and_falls_back() {
+        let (output, bindings) = compile(page_with_title("pageTitle"), false);
+
+        assert!(output.html.contains("<title>Inbox</title>"));
+        assert!(bindings.is_empty());
+        assert_eq!(output.warnings.len(), 1);
+        assert!(output.warnings[0].starts_with("Z-WARN-HEAD-REACTIVE"));
+        assert!(output.warnings[0].contains("`pageTitle`"));
+        // Render-time failures return the initial value rather than ''
+        let manifest = output.manifest.unwrap();
+        assert!(manifest.expressions.contains("return \"Inbox\";"));
+    }
+
+    #[test]
+    fn test_head_expression_without_static_value_warns() {
+        let (output, _) = compile(page_with_title("pageTitle.toUpperCase()"), false);
+
+        assert!(output
+            .warnings
+            .iter()
+            .any(|w| w.starts_with("Z-WARN-HEAD-FALLBACK") && w.contains("toUpperCase")));
+    }
+
+    #[test]
+    fn test_head_expression_inside_loop_is_checked() {
+        let ir: ZenIR = serde_json::from_value(serde_json::json!({
+            "filePath": "page.zen",
+            "template": {
+                "raw": "",
+                "nodes": [{
+                    "type": "element", "tag": "head", "attributes": [], "children": [{
+                        "type": "loop-fragment", "source": "expr_tags", "itemVar": "tag",
+                        "indexVar": null, "loopContext": null, "body": [{
+                            "type": "expression", "expression": "expr_tag", "isInHead": true
+                        }]
+                    }]
+                }],
+                "expressions": [
+                    { "id": "expr_tags", "code": "tags" },
+                    { "id": "expr_tag", "code": "pageTitle + tag" }
+                ]
+            },
+            "script": null,
+            "styles": [],
+            "pageBindings": ["pageTitle", "tags"],
+            "allStates": { "pageTitle": "\"Inbox\"", "tags": "[]" }
+        }))
+        .unwrap();
+
+        let warnings = check_head_expressions(&ir, false);
+        assert!(warnings
+            .iter()
+            .any(|w| w.starts_with("Z-WARN-HEAD-REACTIVE") && w.contains("pageTitle + tag")));
+    }
+
+    #[test]
+    fn test_reactive_head_registers_head_text_bindings() {
+        let (output, bindings) = compile(page_with_title("pageTitle"), true);
+
+        assert!(output
+            .html
+            .contains("<title data-zen-head-text=\"expr_title\">Inbox</title>"));
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0].r#type, "head-text");
+        assert_eq!(bindings[0].id, "expr_title");
+        assert!(!output
+            .warnings
+            .iter()
+            .any(|w| w.starts_with("Z-WARN-HEAD-REACTIVE")));
+    }
+
+    fn compile_form(form_attrs: &str) -> crate::parse::CompileResult {
+        let source = format!(
+            "<script>\nstate sent = false\nfunction submit(data, event) {{ sent = data.has('email') }}\n</script>\n<form {} zen:action={{submit}}><input name=\"email\" /></form>",
+            form_attrs
+        );
+        crate::parse::compile_zen_internal(&source, "page.zen", Default::default()).unwrap()
+    }
+
+    #[test]
+    fn test_form_action_requires_static_action_and_method() {
+        let result = compile_form("method=\"post\"");
+        assert!(result.has_errors);
+        assert!(result.errors[0].starts_with("Z-ERR-FORM-ACTION"));
+        assert!(result.errors[0].contains("`action`"));
+        assert!(!result.errors[0].contains("`method`"));
+    }
+
+    #[test]
+    fn test_form_action_wrapper_passes_form_data() {
+        let result = compile_form("action=\"/subscribe\" method=\"post\"");
+        assert!(!result.has_errors, "{:?}", result.errors);
+
+        let binding = result
+            .bindings
+            .iter()
+            .find(|b| b.r#type == "form-action")
+            .expect("form-action binding");
+        assert!(result
+            .html
+            .contains(&format!("data-zen-enhanced-form=\"{}\"", binding.id)));
+        assert!(result.html.contains("action=\"/subscribe\""));
+
+        let manifest = result.manifest.unwrap();
+        assert_eq!(manifest.enhanced_forms, vec![binding.id.clone()]);
+        assert!(manifest.has_events);
+        assert!(manifest.bundle.contains("event.preventDefault();"));
+        assert!(manifest
+            .bundle
+            .contains("handler.call(target, new FormData(target), event)"));
+    }
+
+    const TRACKED_PAGE: &str = "<script>\nstate id = 1\nfunction trackImpression(x) { return x }\nfunction formatDate(x) { return x }\n</script>\n<div>{trackImpression(id)}</div><p>{formatDate(id)}</p><i>{void trackImpression(id)}</i>";
+
+    #[test]
+    fn test_side_effect_call_warns_but_pure_call_does_not() {
+        let result =
+            crate::parse::compile_zen_internal(TRACKED_PAGE, "page.zen", Default::default())
+                .unwrap();
+        let side_effects: Vec<&String> = result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("Z-WARN-SIDE-EFFECT-EXPRESSION"))
+            .collect();
+        assert_eq!(side_effects.len(), 1);
+        assert!(side_effects[0].contains("`trackImpression(id)`"));
+        assert!(side_effects[0].contains("zenOnMount"));
+    }
+
+    #[test]
+    fn test_void_expression_is_effect_binding_without_placeholder() {
+        let result =
+            crate::parse::compile_zen_internal(TRACKED_PAGE, "page.zen", Default::default())
+                .unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let effect = result
+            .bindings
+            .iter()
+            .find(|b| b.r#type == "effect")
+            .expect("effect binding");
+        assert!(effect.expression.starts_with("void "));
+        assert!(result.html.contains("<i></i>"));
+        assert!(!result.html.contains(&format!("<!--zen:{}-->", effect.id)));
+    }
+}
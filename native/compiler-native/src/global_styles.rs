@@ -0,0 +1,158 @@
+//! Global Style Blocks
+//!
+//! `<style global>` in a component holds document-level CSS (resets, third-party
+//! overrides). Component resolution collects these blocks per component path, so
+//! a component rendered many times contributes them once, and finalize emits them
+//! ahead of the page and component styles. They are marked `StyleIR.global` and
+//! passed through verbatim: any style scoping must leave them alone.
+//!
+//! A global block selecting one of the component's own classes is usually a
+//! regular style block marked `global` by mistake: `Z-WARN-GLOBAL-STYLE-CLASS`.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::component::ComponentIR;
+use crate::validate::StyleIR;
+
+lazy_static! {
+    static ref COMMENT: Regex = Regex::new(r"(?s)/\*.*?\*/").unwrap();
+    /// Innermost `{ ... }` block, i.e. declarations
+    static ref DECLARATIONS: Regex = Regex::new(r"\{[^{}]*\}").unwrap();
+    static ref CLASS_SELECTOR: Regex = Regex::new(r"\.(-?[_a-zA-Z][_a-zA-Z0-9-]*)").unwrap();
+}
+
+/// Global styles first, then the rest, each in their collected order
+pub(crate) fn document_order(styles: Vec<StyleIR>) -> Vec<StyleIR> {
+    let (mut ordered, rest): (Vec<_>, Vec<_>) = styles.into_iter().partition(|s| s.global);
+    ordered.extend(rest);
+    ordered
+}
+
+/// `Z-WARN-GLOBAL-STYLE-CLASS` for class selectors in the component's global
+/// blocks that match classes of its own markup
+pub(crate) fn check_component_classes(component: &ComponentIR) -> Vec<String> {
+    let own = crate::finalize::collect_css_classes(&component.nodes);
+    let mut matched: Vec<String> = Vec::new();
+    for css in &component.global_styles {
+        for class in selector_classes(css) {
+            if own.contains(&class) && !matched.contains(&class) {
+                matched.push(class);
+            }
+        }
+    }
+    if matched.is_empty() {
+        return vec![];
+    }
+    vec![format!(
+        "Z-WARN-GLOBAL-STYLE-CLASS: `<style global>` in {} selects {}, used by the component's own markup. Global styles apply to the whole document; move component styles to a regular `<style>` block.",
+        component.path,
+        matched
+            .iter()
+            .map(|c| format!("`.{}`", c))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )]
+}
+
+/// Selectors of every rule, one per comma-separated entry (at-rule preludes and
+/// keyframe steps included; callers match what they look for)
+pub(crate) fn selector_list(css: &str) -> Vec<String> {
+    let css = COMMENT.replace_all(css, "");
+    let selectors = DECLARATIONS.replace_all(&css, ";");
+    selectors
+        .split([';', '{', '}', ','])
+        .map(str::trim)
+        .filter(|s| !s.is_empty() && !s.starts_with('@'))
+        .map(String::from)
+        .collect()
+}
+
+/// Class names appearing in selectors (declaration values like `.5em` are skipped)
+pub(crate) fn selector_classes(css: &str) -> Vec<String> {
+    let css = COMMENT.replace_all(css, "");
+    // One pass: rules nested in at-rules keep their selectors
+    let selectors = DECLARATIONS.replace_all(&css, ";");
+    CLASS_SELECTOR
+        .captures_iter(&selectors)
+        .map(|cap| cap[1].to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::component::ComponentIR;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+    use std::collections::HashMap;
+
+    const NOTICE: &str = "<div class=\"notice\">Hi</div>\n<style global>\nbody { margin: 0; }\n</style>\n<style>\n.notice { color: red; }\n</style>";
+
+    fn compile(component: &str, page: &str) -> CompileResult {
+        let notice =
+            ComponentIR::from_source("Notice", "components/Notice.zen", component).unwrap();
+        let mut components = HashMap::new();
+        components.insert("Notice".to_string(), serde_json::to_value(notice).unwrap());
+        let options = CompileOptions {
+            components,
+            ..Default::default()
+        };
+        compile_zen_internal(page, "page.zen", options).unwrap()
+    }
+
+    fn styles(result: &CompileResult) -> String {
+        result.manifest.as_ref().unwrap().styles.clone()
+    }
+
+    #[test]
+    fn test_global_block_emitted_once_before_other_styles() {
+        let result = compile(
+            NOTICE,
+            "<main><Notice /><Notice /></main>\n<style>\nmain { padding: 1rem; }\n</style>",
+        );
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let css = styles(&result);
+        assert_eq!(css.matches("body { margin: 0; }").count(), 1, "{}", css);
+        // Verbatim and first: ahead of the page's and the component's regular styles
+        assert!(css.starts_with("body { margin: 0; }\n"), "{}", css);
+        assert!(
+            css.find("main { padding").unwrap() < css.find(".notice { color").unwrap(),
+            "{}",
+            css
+        );
+        assert!(result
+            .warnings
+            .iter()
+            .all(|w| !w.starts_with("Z-WARN-GLOBAL-STYLE-CLASS")));
+    }
+
+    #[test]
+    fn test_global_attribute_is_captured() {
+        let notice = ComponentIR::from_source("Notice", "components/Notice.zen", NOTICE).unwrap();
+        assert_eq!(notice.global_styles, vec!["body { margin: 0; }"]);
+        assert_eq!(notice.styles, vec![".notice { color: red; }"]);
+
+        let blocks = crate::discovery::extract_style_blocks(
+            "<style global=\"true\">a{}</style><style data-global>b{}</style><style global>c{}</style>",
+        );
+        let flags: Vec<bool> = blocks.iter().map(|(_, global)| *global).collect();
+        assert_eq!(flags, vec![true, false, true]);
+    }
+
+    #[test]
+    fn test_global_block_selecting_own_class_warns() {
+        let component = "<div class=\"notice card\">Hi</div>\n<style global>\n@media (min-width: 40em) { .notice { margin: .5em; } }\n.card, .third-party { color: red; }\n</style>";
+        let result = compile(component, "<main><Notice /></main>");
+        let warnings: Vec<_> = result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("Z-WARN-GLOBAL-STYLE-CLASS"))
+            .collect();
+        assert_eq!(warnings.len(), 1, "{:?}", result.warnings);
+        assert!(
+            warnings[0].contains("in components/Notice.zen selects `.notice`, `.card`,"),
+            "{}",
+            warnings[0]
+        );
+        assert!(!warnings[0].contains("third-party"));
+    }
+}
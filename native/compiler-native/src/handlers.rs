@@ -0,0 +1,367 @@
+//! Event Handler Expressions
+//!
+//! The runtime calls whatever an `on*` attribute evaluates to when the event fires,
+//! and expressions are evaluated at render. So every handler expression must
+//! evaluate to a function:
+//!
+//! - a reference (`save`, `cart.addItem`) and an arrow or function expression are
+//!   the function itself and compile as written
+//! - anything else (`cart.addItem(product)`, `count++`, `a(), b()`) is wrapped as
+//!   `function(event, target) { return (<expr>); }` before renaming, so it runs on
+//!   the event rather than once during render, its mutations are handler mutations,
+//!   and it is not re-evaluated when the state it reads changes
+//!
+//! Template handlers are wrapped by codegen, handlers in markup inside expressions
+//! (`items.map(i => <button onclick={add(i)}>`) by the JSX lowerer.
+//!
+//! `onclick={save()}`, where `save` is a script function that does not return a
+//! function and the call does not use `event`, works but most likely meant
+//! `onclick={save}`: `Z-WARN-HANDLER-CALL`.
+
+use std::collections::HashSet;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{Expression, Statement};
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use crate::validate::{AttributeValue, TemplateNode, ZenIR};
+
+/// Source of the wrapper; `HANDLER` stands for the handler expression
+const HANDLER_WRAPPER: &str = "function(event, target) { return (HANDLER); }";
+
+/// Whether an attribute takes an event handler
+pub(crate) fn is_event_attr(name: &str) -> bool {
+    name.len() > 2 && name.starts_with("on")
+}
+
+/// A handler that already evaluates to a function: a name, a member path, or an
+/// arrow or function expression
+pub(crate) fn is_function_valued(expr: &Expression) -> bool {
+    match expr {
+        Expression::Identifier(_)
+        | Expression::ArrowFunctionExpression(_)
+        | Expression::FunctionExpression(_) => true,
+        Expression::ParenthesizedExpression(paren) => is_function_valued(&paren.expression),
+        expr => expr.is_member_expression() && is_member_path(expr),
+    }
+}
+
+/// `a.b` or `a["b"]` chains, as written or as component resolution renames them
+fn is_member_path(expr: &Expression) -> bool {
+    match expr {
+        Expression::Identifier(_) | Expression::ThisExpression(_) => true,
+        Expression::StaticMemberExpression(member) => is_member_path(&member.object),
+        Expression::ComputedMemberExpression(member) => {
+            matches!(member.expression, Expression::StringLiteral(_))
+                && is_member_path(&member.object)
+        }
+        _ => false,
+    }
+}
+
+/// The handler source wrapped in a function; None when it evaluates to one already
+/// (or does not parse, which is reported elsewhere)
+pub(crate) fn wrap_handler_code(code: &str) -> Option<String> {
+    let code = code.trim().trim_end_matches(';');
+    let allocator = Allocator::default();
+    let source_type = SourceType::default().with_jsx(true).with_typescript(true);
+    let expr = Parser::new(&allocator, code, source_type)
+        .parse_expression()
+        .ok()?;
+    // Parenthesized, or it would parse as a function declaration
+    (!is_function_valued(&expr)).then(|| format!("({})", HANDLER_WRAPPER.replace("HANDLER", code)))
+}
+
+/// `handler` wrapped in a function, for the JSX lowerer
+pub(crate) fn wrap_handler_expression<'a>(
+    allocator: &'a Allocator,
+    handler: Expression<'a>,
+) -> Expression<'a> {
+    let mut wrapper = Parser::new(allocator, HANDLER_WRAPPER, SourceType::default())
+        .parse_expression()
+        .expect("the handler wrapper parses");
+    let Expression::FunctionExpression(func) = &mut wrapper else {
+        unreachable!("the handler wrapper is a function expression");
+    };
+    let body = func.body.as_mut().expect("the handler wrapper has a body");
+    let Some(Statement::ReturnStatement(ret)) = body.statements.first_mut() else {
+        unreachable!("the handler wrapper returns its expression");
+    };
+    ret.argument = Some(handler);
+    wrapper
+}
+
+/// `Z-WARN-HANDLER-CALL` for handlers that call a script function for no reason
+/// but to call it
+pub(crate) fn check_handler_calls(ir: &ZenIR) -> Vec<String> {
+    let Some(script) = &ir.script else {
+        return vec![];
+    };
+    let functions = non_function_returning(&script.raw);
+    if functions.is_empty() {
+        return vec![];
+    }
+    let mut handlers = Vec::new();
+    collect_handlers(&ir.template.nodes, &mut handlers);
+
+    let mut warnings = Vec::new();
+    for (event, id) in handlers {
+        let Some(expr) = ir.template.expressions.iter().find(|e| e.id == id) else {
+            continue;
+        };
+        let Some(callee) = plain_call(&expr.code) else {
+            continue;
+        };
+        if functions.contains(&callee) {
+            warnings.push(format!(
+                "Z-WARN-HANDLER-CALL: `{}` on `{}` in {} calls `{}` when the event fires and ignores its result. If `{}` is the handler, pass it as `{}={{{}}}`; it receives the event.",
+                expr.code.trim(),
+                event,
+                ir.file_path,
+                callee,
+                callee,
+                event,
+                callee
+            ));
+        }
+    }
+    warnings
+}
+
+/// (attribute, expression id) of every dynamic event attribute
+fn collect_handlers(nodes: &[TemplateNode], handlers: &mut Vec<(String, String)>) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                for attr in &el.attributes {
+                    if let AttributeValue::Dynamic(expr) = &attr.value {
+                        if is_event_attr(&attr.name) {
+                            handlers.push((attr.name.clone(), expr.id.clone()));
+                        }
+                    }
+                }
+                collect_handlers(&el.children, handlers);
+            }
+            TemplateNode::Component(c) => collect_handlers(&c.children, handlers),
+            TemplateNode::ConditionalFragment(cf) => {
+                collect_handlers(&cf.consequent, handlers);
+                collect_handlers(&cf.alternate, handlers);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    collect_handlers(nodes, handlers);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => collect_handlers(&of.fragment, handlers),
+            TemplateNode::LoopFragment(lf) => collect_handlers(&lf.body, handlers),
+            _ => {}
+        }
+    }
+}
+
+/// Callee of a handler that is a call to a bare name whose arguments do not read `event`
+fn plain_call(code: &str) -> Option<String> {
+    let allocator = Allocator::default();
+    let source_type = SourceType::default().with_jsx(true).with_typescript(true);
+    let expr = Parser::new(&allocator, code, source_type)
+        .parse_expression()
+        .ok()?;
+    let Expression::CallExpression(call) = expr.without_parentheses() else {
+        return None;
+    };
+    let Expression::Identifier(callee) = &call.callee else {
+        return None;
+    };
+    let reads_event = call.arguments.iter().any(|arg| {
+        let span = oxc_span::GetSpan::span(arg);
+        crate::finalize::referenced_identifiers(&code[span.start as usize..span.end as usize])
+            .iter()
+            .any(|name| name == "event")
+    });
+    (!reads_event).then(|| callee.name.to_string())
+}
+
+/// Functions the script declares at the top level whose body returns no function
+fn non_function_returning(script: &str) -> HashSet<String> {
+    let script = crate::component::zen_keywords_to_let(script);
+    let allocator = Allocator::default();
+    let parsed = Parser::new(&allocator, &script, SourceType::tsx().with_module(true)).parse();
+    let mut functions = HashSet::new();
+    for stmt in &parsed.program.body {
+        match stmt {
+            Statement::FunctionDeclaration(decl) => {
+                let returns_function = decl
+                    .body
+                    .as_ref()
+                    .is_some_and(|body| body.statements.iter().any(returns_function));
+                if let (Some(id), false) = (&decl.id, returns_function) {
+                    functions.insert(id.name.to_string());
+                }
+            }
+            Statement::VariableDeclaration(decl) => {
+                for d in &decl.declarations {
+                    let Some(name) = d.id.get_binding_identifier() else {
+                        continue;
+                    };
+                    let returns_function = match d.init.as_ref().map(|e| e.without_parentheses()) {
+                        Some(Expression::ArrowFunctionExpression(arrow)) => {
+                            arrow.body.statements.iter().any(|s| match s {
+                                Statement::ExpressionStatement(s) if arrow.expression => {
+                                    is_function_expr(&s.expression)
+                                }
+                                s => returns_function(s),
+                            })
+                        }
+                        Some(Expression::FunctionExpression(func)) => func
+                            .body
+                            .as_ref()
+                            .is_some_and(|body| body.statements.iter().any(returns_function)),
+                        _ => continue,
+                    };
+                    if !returns_function {
+                        functions.insert(name.name.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    functions
+}
+
+/// A top-level `return` of a function expression in a function body
+fn returns_function(stmt: &Statement) -> bool {
+    matches!(stmt, Statement::ReturnStatement(ret) if ret.argument.as_ref().is_some_and(is_function_expr))
+}
+
+fn is_function_expr(expr: &Expression) -> bool {
+    matches!(
+        expr.without_parentheses(),
+        Expression::ArrowFunctionExpression(_) | Expression::FunctionExpression(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    const CART: &str = "<script>\nconst cart = { items: [], addItem(p) { this.items.push(p) } }\nstate product = \"apple\"\nstate count = 0\nfunction save() { count = count + 1 }\nfunction track(name) { return () => console.log(name) }\n</script>\n";
+
+    fn compile(markup: &str, ast_classifier: bool) -> CompileResult {
+        let options = CompileOptions {
+            ast_classifier,
+            ..Default::default()
+        };
+        compile_zen_internal(&format!("{}{}", CART, markup), "page.zen", options).unwrap()
+    }
+
+    fn handler_warnings(result: &CompileResult) -> Vec<&String> {
+        result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("Z-WARN-HANDLER-CALL"))
+            .collect()
+    }
+
+    #[test]
+    fn test_member_call_runs_on_the_event() {
+        let result = compile(
+            "<main><button onclick={cart.addItem(product)}>Add</button></main>",
+            false,
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(
+            handler_warnings(&result).is_empty(),
+            "{:?}",
+            result.warnings
+        );
+        let bundle = result.manifest.unwrap().bundle;
+        // The expression evaluates to the handler; the call is inside it
+        assert!(
+            bundle.contains("const v = ((function(event, target) {\n\treturn scope.locals.cart.addItem(scope.state.product);\n}));"),
+            "{}",
+            bundle
+        );
+        assert!(
+            bundle.contains("\"onclick\": function(event, target) { return _expr_"),
+            "{}",
+            bundle
+        );
+        // Reading `product` does not re-evaluate the handler
+        assert!(
+            regex::Regex::new(r"set\('expr_\d+', \{ fn: _expr_expr_\d+, deps: \[\] \}\)")
+                .unwrap()
+                .is_match(&bundle),
+            "{}",
+            bundle
+        );
+    }
+
+    #[test]
+    fn test_handlers_in_lowered_markup_are_wrapped() {
+        let result = compile(
+            "<ul>{[1, 2].map(n => <li><button onclick={cart.addItem(n)}>x</button></li>)}</ul>",
+            false,
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        let bundle = result.manifest.unwrap().bundle;
+        assert!(
+            bundle.contains(
+                "onclick: function(event, target) {\n\treturn scope.locals.cart.addItem(n);\n}"
+            ),
+            "{}",
+            bundle
+        );
+    }
+
+    #[test]
+    fn test_call_of_a_plain_function_warns() {
+        let result = compile(
+            "<main><button onclick={save()}>Save</button><button onclick={track('home')}>Track</button><input oninput={save(event)} /></main>",
+            false,
+        );
+        let warnings = handler_warnings(&result);
+        assert_eq!(warnings.len(), 1, "{:?}", result.warnings);
+        assert!(
+            warnings[0].starts_with("Z-WARN-HANDLER-CALL: `save()` on `onclick` in page.zen"),
+            "{}",
+            warnings[0]
+        );
+        assert!(warnings[0].contains("`onclick={save}`"), "{}", warnings[0]);
+    }
+
+    #[test]
+    fn test_arrow_and_reference_handlers_are_unchanged() {
+        let result = compile(
+            "<main><button onclick={() => save()}>A</button><button onclick={save}>B</button><button onclick={cart.addItem}>C</button></main>",
+            false,
+        );
+        assert!(
+            handler_warnings(&result).is_empty(),
+            "{:?}",
+            result.warnings
+        );
+        let bundle = result.manifest.unwrap().bundle;
+        assert!(
+            bundle.contains("const v = (() => scope.locals.save());"),
+            "{}",
+            bundle
+        );
+        assert!(
+            bundle.contains("const v = (scope.locals.save);"),
+            "{}",
+            bundle
+        );
+        assert!(
+            bundle.contains("const v = (scope.locals.cart.addItem);"),
+            "{}",
+            bundle
+        );
+        assert!(
+            !bundle.contains("function(event, target) {\n"),
+            "{}",
+            bundle
+        );
+    }
+}
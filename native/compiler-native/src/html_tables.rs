@@ -0,0 +1,449 @@
+//! HTML Element Tables
+//!
+//! html5ever lowercases every tag and attribute name, so the parser restores the
+//! camelCase of SVG attributes on SVG elements, and the transform writes void
+//! elements self-closing. Both work from built-in tables, which a compile can extend
+//! with `CompileOptions.html_extensions`:
+//!
+//! - `extra_svg_attr_case`: lowercase attribute name → the case to restore
+//! - `extra_svg_tags`: more elements whose attributes are case-corrected
+//! - `extra_void_elements`: more elements written self-closing when empty
+//! - `non_void_overrides`: built-in void elements written with a closing tag
+//!
+//! User entries win over the built-in ones. Names must be lowercase ASCII (the
+//! case to restore must be ASCII and lowercase to its key); an entry that is not is
+//! skipped with `Z-WARN-HTML-EXTENSION`. The built-in tables are readable through
+//! `default_svg_attr_case_map`, `default_svg_tags` and `default_void_elements`.
+
+use lazy_static::lazy_static;
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+lazy_static! {
+    /// SVG attribute case mapping - parse5/html5ever lowercases all attributes,
+    /// but SVG requires specific casing for these attributes.
+    static ref SVG_ATTR_CASE_MAP: HashMap<&'static str, &'static str> = {
+        let mut m = HashMap::new();
+        // Core SVG attributes with camelCase
+        m.insert("viewbox", "viewBox");
+        m.insert("preserveaspectratio", "preserveAspectRatio");
+        m.insert("basefrequency", "baseFrequency");
+        m.insert("baseprofile", "baseProfile");
+        m.insert("clippathunits", "clipPathUnits");
+        m.insert("diffuseconstant", "diffuseConstant");
+        m.insert("edgemode", "edgeMode");
+        m.insert("filterunits", "filterUnits");
+        m.insert("glyphref", "glyphRef");
+        m.insert("gradienttransform", "gradientTransform");
+        m.insert("gradientunits", "gradientUnits");
+        m.insert("kernelmatrix", "kernelMatrix");
+        m.insert("kernelunitlength", "kernelUnitLength");
+        m.insert("keypoints", "keyPoints");
+        m.insert("keysplines", "keySplines");
+        m.insert("keytimes", "keyTimes");
+        m.insert("lengthadjust", "lengthAdjust");
+        m.insert("limitingconeangle", "limitingConeAngle");
+        m.insert("markerheight", "markerHeight");
+        m.insert("markerunits", "markerUnits");
+        m.insert("markerwidth", "markerWidth");
+        m.insert("maskcontentunits", "maskContentUnits");
+        m.insert("maskunits", "maskUnits");
+        m.insert("numoctaves", "numOctaves");
+        m.insert("pathlength", "pathLength");
+        m.insert("patterncontentunits", "patternContentUnits");
+        m.insert("patterntransform", "patternTransform");
+        m.insert("patternunits", "patternUnits");
+        m.insert("pointsatx", "pointsAtX");
+        m.insert("pointsaty", "pointsAtY");
+        m.insert("pointsatz", "pointsAtZ");
+        m.insert("primitiveunits", "primitiveUnits");
+        m.insert("refx", "refX");
+        m.insert("refy", "refY");
+        m.insert("repeatcount", "repeatCount");
+        m.insert("repeatdur", "repeatDur");
+        m.insert("requiredextensions", "requiredExtensions");
+        m.insert("requiredfeatures", "requiredFeatures");
+        m.insert("specularconstant", "specularConstant");
+        m.insert("specularexponent", "specularExponent");
+        m.insert("spreadmethod", "spreadMethod");
+        m.insert("startoffset", "startOffset");
+        m.insert("stddeviation", "stdDeviation");
+        m.insert("stitchtiles", "stitchTiles");
+        m.insert("surfacescale", "surfaceScale");
+        m.insert("systemlanguage", "systemLanguage");
+        m.insert("tablevalues", "tableValues");
+        m.insert("targetx", "targetX");
+        m.insert("targety", "targetY");
+        m.insert("textlength", "textLength");
+        m.insert("xchannelselector", "xChannelSelector");
+        m.insert("ychannelselector", "yChannelSelector");
+        m.insert("zoomandpan", "zoomAndPan");
+        m.insert("attributename", "attributeName");
+        m.insert("attributetype", "attributeType");
+        m.insert("calcmode", "calcMode");
+        m.insert("clippath", "clipPath");
+        m
+    };
+
+    /// SVG tags set
+    static ref SVG_TAGS: HashSet<&'static str> = {
+        let mut s = HashSet::new();
+        s.insert("svg");
+        s.insert("path");
+        s.insert("circle");
+        s.insert("ellipse");
+        s.insert("line");
+        s.insert("polyline");
+        s.insert("polygon");
+        s.insert("rect");
+        s.insert("g");
+        s.insert("defs");
+        s.insert("use");
+        s.insert("symbol");
+        s.insert("clippath");
+        s.insert("mask");
+        s.insert("pattern");
+        s.insert("marker");
+        s.insert("lineargradient");
+        s.insert("radialgradient");
+        s.insert("stop");
+        s.insert("filter");
+        s.insert("feblend");
+        s.insert("fecolormatrix");
+        s.insert("fecomponenttransfer");
+        s.insert("fecomposite");
+        s.insert("feconvolvematrix");
+        s.insert("fediffuselighting");
+        s.insert("fedisplacementmap");
+        s.insert("fedropshadow");
+        s.insert("feflood");
+        s.insert("fefunca");
+        s.insert("fefuncb");
+        s.insert("fefuncg");
+        s.insert("fefuncr");
+        s.insert("fegaussianblur");
+        s.insert("feimage");
+        s.insert("femerge");
+        s.insert("femergenode");
+        s.insert("femorphology");
+        s.insert("feoffset");
+        s.insert("fespecularlighting");
+        s.insert("fetile");
+        s.insert("feturbulence");
+        s.insert("foreignobject");
+        s.insert("image");
+        s.insert("switch");
+        s.insert("text");
+        s.insert("tspan");
+        s.insert("textpath");
+        s.insert("title");
+        s.insert("desc");
+        s.insert("metadata");
+        s.insert("a");
+        s.insert("view");
+        s.insert("animate");
+        s.insert("animatemotion");
+        s.insert("animatetransform");
+        s.insert("set");
+        s.insert("mpath");
+        s
+    };
+}
+
+/// Elements without content, written `<tag ... />`
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// The built-in SVG attribute case corrections (lowercase name → SVG name)
+pub fn default_svg_attr_case_map() -> &'static HashMap<&'static str, &'static str> {
+    &SVG_ATTR_CASE_MAP
+}
+
+/// The built-in SVG elements whose attributes are case-corrected
+pub fn default_svg_tags() -> &'static HashSet<&'static str> {
+    &SVG_TAGS
+}
+
+/// The built-in void elements
+pub fn default_void_elements() -> &'static [&'static str] {
+    VOID_ELEMENTS
+}
+
+/// The built-in tables for the JS side: `svgAttrCase`, `svgTags` and
+/// `voidElements`, sorted
+#[cfg(feature = "napi")]
+#[napi]
+pub fn get_default_html_tables_native() -> serde_json::Value {
+    let mut svg_tags: Vec<&str> = SVG_TAGS.iter().copied().collect();
+    svg_tags.sort_unstable();
+    let mut void_elements = VOID_ELEMENTS.to_vec();
+    void_elements.sort_unstable();
+    serde_json::json!({
+        "svgAttrCase": SVG_ATTR_CASE_MAP
+            .iter()
+            .collect::<std::collections::BTreeMap<_, _>>(),
+        "svgTags": svg_tags,
+        "voidElements": void_elements,
+    })
+}
+
+/// Additions to and overrides of the built-in tables for one compile
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct HtmlExtensions {
+    /// Lowercase SVG attribute name → the case to restore (e.g. `"transformorigin": "transformOrigin"`)
+    #[serde(default)]
+    pub extra_svg_attr_case: HashMap<String, String>,
+    /// More SVG elements whose attributes are case-corrected
+    #[serde(default)]
+    pub extra_svg_tags: Vec<String>,
+    /// More elements written self-closing when they have no children
+    #[serde(default)]
+    pub extra_void_elements: Vec<String>,
+    /// Built-in void elements written with a closing tag instead
+    #[serde(default)]
+    pub non_void_overrides: Vec<String>,
+}
+
+/// The built-in tables with a compile's `HtmlExtensions` merged over them
+#[derive(Debug, Clone, Default)]
+pub struct HtmlTables {
+    svg_attr_case: HashMap<String, String>,
+    svg_tags: HashSet<String>,
+    void_elements: HashSet<String>,
+    non_void: HashSet<String>,
+}
+
+impl HtmlTables {
+    /// Merge `extensions` over the built-in tables; invalid entries are skipped
+    /// with a warning each
+    pub fn new(extensions: &HtmlExtensions) -> (Self, Vec<String>) {
+        let mut tables = HtmlTables::default();
+        let mut warnings = Vec::new();
+        let mut attrs: Vec<(&String, &String)> = extensions.extra_svg_attr_case.iter().collect();
+        attrs.sort();
+        for (name, cased) in attrs {
+            if !check_name(name, "extra_svg_attr_case", &mut warnings) {
+                continue;
+            }
+            if !cased.is_ascii() || cased.to_ascii_lowercase() != *name {
+                warnings.push(format!(
+                    "Z-WARN-HTML-EXTENSION: `{}` in html_extensions.extra_svg_attr_case is not a different case of `{}`; the entry is skipped.",
+                    cased, name
+                ));
+                continue;
+            }
+            tables.svg_attr_case.insert(name.clone(), cased.clone());
+        }
+        for (names, option, set) in [
+            (
+                &extensions.extra_svg_tags,
+                "extra_svg_tags",
+                &mut tables.svg_tags,
+            ),
+            (
+                &extensions.extra_void_elements,
+                "extra_void_elements",
+                &mut tables.void_elements,
+            ),
+            (
+                &extensions.non_void_overrides,
+                "non_void_overrides",
+                &mut tables.non_void,
+            ),
+        ] {
+            for name in names {
+                if check_name(name, option, &mut warnings) {
+                    set.insert(name.clone());
+                }
+            }
+        }
+        (tables, warnings)
+    }
+
+    /// `attr_name` with the SVG case restored when `tag_name` is an SVG element
+    pub(crate) fn svg_attribute_name(&self, attr_name: &str, tag_name: &str) -> String {
+        let lower_tag = tag_name.to_lowercase();
+        if !SVG_TAGS.contains(lower_tag.as_str()) && !self.svg_tags.contains(&lower_tag) {
+            return attr_name.to_string();
+        }
+        let lower_attr = attr_name.to_lowercase();
+        if let Some(cased) = self.svg_attr_case.get(&lower_attr) {
+            return cased.clone();
+        }
+        match SVG_ATTR_CASE_MAP.get(lower_attr.as_str()) {
+            Some(&cased) => cased.to_string(),
+            None => attr_name.to_string(),
+        }
+    }
+
+    /// Whether an empty `tag` is written self-closing
+    pub(crate) fn is_void(&self, tag: &str) -> bool {
+        let tag = tag.to_lowercase();
+        !self.non_void.contains(&tag)
+            && (VOID_ELEMENTS.contains(&tag.as_str()) || self.void_elements.contains(&tag))
+    }
+}
+
+/// Whether `name` is a lowercase ASCII name, with a warning when it is not
+fn check_name(name: &str, option: &str, warnings: &mut Vec<String>) -> bool {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !c.is_ascii_uppercase());
+    if !valid {
+        warnings.push(format!(
+            "Z-WARN-HTML-EXTENSION: `{}` in html_extensions.{} is not a lowercase ASCII name; the entry is skipped.",
+            name, option
+        ));
+    }
+    valid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(source: &str, html_extensions: HtmlExtensions) -> CompileResult {
+        let options = CompileOptions {
+            html_extensions,
+            ..Default::default()
+        };
+        let result = compile_zen_internal(source, "page.zen", options).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        result
+    }
+
+    #[test]
+    fn test_custom_svg_attribute_keeps_its_case() {
+        let source = "<main><svg viewBox=\"0 0 10 10\"><rect transformOrigin=\"center\"></rect></svg><x-glyph viewBox=\"0 0 1 1\"></x-glyph></main>";
+        let result = compile(
+            source,
+            HtmlExtensions {
+                extra_svg_attr_case: [(
+                    "transformorigin".to_string(),
+                    "transformOrigin".to_string(),
+                )]
+                .into(),
+                extra_svg_tags: vec!["x-glyph".to_string()],
+                ..Default::default()
+            },
+        );
+        assert!(
+            result.html.contains("viewBox=\"0 0 10 10\""),
+            "{}",
+            result.html
+        );
+        assert!(
+            result.html.contains("transformOrigin=\"center\""),
+            "{}",
+            result.html
+        );
+        assert!(
+            result.html.contains("<x-glyph viewBox=\"0 0 1 1\">"),
+            "{}",
+            result.html
+        );
+
+        let result = compile(source, HtmlExtensions::default());
+        assert!(
+            result.html.contains("transformorigin=\"center\""),
+            "{}",
+            result.html
+        );
+        assert!(result.html.contains("<x-glyph viewbox="), "{}", result.html);
+    }
+
+    #[test]
+    fn test_custom_void_element_is_self_closing() {
+        let source = "<main><x-icon name=\"star\"></x-icon><x-panel></x-panel></main>";
+        let result = compile(
+            source,
+            HtmlExtensions {
+                extra_void_elements: vec!["x-icon".to_string()],
+                ..Default::default()
+            },
+        );
+        assert!(
+            result.html.contains("<x-icon name=\"star\" />"),
+            "{}",
+            result.html
+        );
+        assert!(
+            result.html.contains("<x-panel></x-panel>"),
+            "{}",
+            result.html
+        );
+
+        let result = compile(
+            source,
+            HtmlExtensions {
+                extra_void_elements: vec!["X-Icon".to_string()],
+                ..Default::default()
+            },
+        );
+        assert!(
+            result.html.contains("<x-icon name=\"star\"></x-icon>"),
+            "{}",
+            result.html
+        );
+        assert!(
+            result
+                .warnings
+                .iter()
+                .any(|w| w.starts_with("Z-WARN-HTML-EXTENSION: `X-Icon`")),
+            "{:?}",
+            result.warnings
+        );
+    }
+
+    #[test]
+    fn test_built_in_void_element_can_be_overridden() {
+        let source = "<main><source src=\"a.mp4\"><br></main>";
+        assert!(compile(source, HtmlExtensions::default())
+            .html
+            .contains("<source src=\"a.mp4\" />"));
+        let result = compile(
+            source,
+            HtmlExtensions {
+                non_void_overrides: vec!["source".to_string()],
+                ..Default::default()
+            },
+        );
+        assert!(
+            result.html.contains("<source src=\"a.mp4\"></source>"),
+            "{}",
+            result.html
+        );
+        assert!(result.html.contains("<br />"), "{}", result.html);
+    }
+
+    #[test]
+    fn test_invalid_entries_are_skipped() {
+        let (tables, warnings) = HtmlTables::new(&HtmlExtensions {
+            extra_svg_attr_case: [
+                ("Origin".to_string(), "origin".to_string()),
+                ("refz".to_string(), "refZed".to_string()),
+            ]
+            .into(),
+            extra_void_elements: vec!["X-Icon".to_string(), "x-ok".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+        assert!(warnings
+            .iter()
+            .all(|w| w.starts_with("Z-WARN-HTML-EXTENSION")));
+        assert!(tables.is_void("x-ok") && !tables.is_void("x-icon"));
+        assert_eq!(tables.svg_attribute_name("refz", "svg"), "refz");
+        assert!(default_svg_tags().contains("svg"));
+        assert_eq!(default_svg_attr_case_map()["viewbox"], "viewBox");
+        assert!(default_void_elements().contains(&"br"));
+    }
+}
@@ -0,0 +1,277 @@
+//! Hydration Mismatch Analysis
+//!
+//! A state initialized from a browser API (`state theme = localStorage.getItem('theme')`)
+//! has no value on the server, yet conditions and state-reflecting attributes are
+//! rendered into the server HTML. The first client render then disagrees with what
+//! the page showed: a branch flashes, a dialog opens late, focus jumps. Before
+//! transform, the page is checked for:
+//!
+//! - conditional, else-if, and optional conditions
+//! - `value`, `checked`, `open`, and `autofocus` bindings (also as `prop:`)
+//!
+//! whose code reads such a state, directly or through other states initialized
+//! from it. Each one is `Z-WARN-HYDRATION-MISMATCH`, naming the chain from the
+//! state to the browser global.
+//!
+//! `data-zen-client-only` on an element opts its subtree out: nothing in it is
+//! checked, and transform folds nothing in it to an initial value. Its markup
+//! and hydration markers are still emitted, so the client fills them in and no
+//! server value can disagree with it.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{Expression, IdentifierReference, StaticMemberExpression};
+use oxc_ast_visit::{walk, Visit};
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use crate::transform::{CLIENT_ONLY_ATTR, PROP_PREFIX};
+use crate::validate::{AttributeValue, ExpressionIR, SourceLocation, TemplateNode, ZenIR};
+
+/// Attributes whose server-rendered value the browser acts on before hydration
+const STATE_REFLECTING_ATTRS: &[&str] = &["value", "checked", "open", "autofocus"];
+
+/// Names an expression reads: bare identifiers and `state.name`
+#[derive(Default)]
+struct Reads(HashSet<String>);
+
+impl<'a> Visit<'a> for Reads {
+    fn visit_identifier_reference(&mut self, ident: &IdentifierReference<'a>) {
+        self.0.insert(ident.name.to_string());
+    }
+
+    fn visit_static_member_expression(&mut self, member: &StaticMemberExpression<'a>) {
+        if let Expression::Identifier(object) = &member.object {
+            if object.name == "state" {
+                self.0.insert(member.property.name.to_string());
+            }
+        }
+        walk::walk_static_member_expression(self, member);
+    }
+}
+
+fn reads(code: &str) -> HashSet<String> {
+    let allocator = Allocator::default();
+    let mut reads = Reads::default();
+    if let Ok(expr) = Parser::new(&allocator, code, SourceType::tsx()).parse_expression() {
+        reads.visit_expression(&expr);
+    }
+    reads.0
+}
+
+/// States with no value on the server, each with its chain down to the browser
+/// global: `["theme", "stored", "localStorage"]`
+fn client_only_states(all_states: &HashMap<String, String>) -> BTreeMap<String, Vec<String>> {
+    let mut chains: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let states: BTreeMap<&String, &String> = all_states.iter().collect();
+    for (name, init) in &states {
+        if let Some(global) = crate::ssr_safety::browser_globals(init).into_iter().next() {
+            chains.insert((*name).clone(), vec![(*name).clone(), global]);
+        }
+    }
+    // States initialized from client-only states, until nothing changes
+    loop {
+        let derived: Vec<(String, Vec<String>)> = states
+            .iter()
+            .filter(|(name, _)| !chains.contains_key(**name))
+            .filter_map(|(name, init)| {
+                let mut read: Vec<String> = reads(init).into_iter().collect();
+                read.sort();
+                let chain = read.iter().find_map(|r| chains.get(r))?;
+                Some((
+                    (*name).clone(),
+                    [vec![(*name).clone()], chain.clone()].concat(),
+                ))
+            })
+            .collect();
+        if derived.is_empty() {
+            return chains;
+        }
+        chains.extend(derived);
+    }
+}
+
+/// Warn about conditions and state-reflecting attributes that read client-only
+/// state outside `data-zen-client-only` subtrees; warnings go to `ir.warnings`.
+pub(crate) fn check_hydration_mismatch(ir: &mut ZenIR) {
+    let states = client_only_states(&ir.all_states);
+    if states.is_empty() {
+        return;
+    }
+    let mut check = Check {
+        expressions: &ir.template.expressions,
+        file_path: &ir.file_path,
+        states: &states,
+        warnings: Vec::new(),
+    };
+    check.visit(&ir.template.nodes);
+    let warnings = check.warnings;
+    ir.warnings.extend(warnings);
+}
+
+struct Check<'a> {
+    expressions: &'a [ExpressionIR],
+    file_path: &'a str,
+    states: &'a BTreeMap<String, Vec<String>>,
+    warnings: Vec<String>,
+}
+
+impl Check<'_> {
+    fn visit(&mut self, nodes: &[TemplateNode]) {
+        for node in nodes {
+            match node {
+                TemplateNode::Element(el) => {
+                    if el.attributes.iter().any(|a| a.name == CLIENT_ONLY_ATTR) {
+                        continue;
+                    }
+                    for attr in &el.attributes {
+                        let AttributeValue::Dynamic(expr) = &attr.value else {
+                            continue;
+                        };
+                        let name = attr.name.strip_prefix(PROP_PREFIX).unwrap_or(&attr.name);
+                        if STATE_REFLECTING_ATTRS.contains(&name.to_lowercase().as_str()) {
+                            let what = format!("`{}` attribute", attr.name);
+                            self.check(&what, &expr.code, &expr.location);
+                        }
+                    }
+                    self.visit(&el.children);
+                }
+                TemplateNode::Component(c) => self.visit(&c.children),
+                TemplateNode::ConditionalFragment(cf) => {
+                    self.check_condition(&cf.condition);
+                    self.visit(&cf.consequent);
+                    self.visit(&cf.alternate);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    for branch in &mb.branches {
+                        self.check_condition(&branch.condition);
+                    }
+                    for nodes in mb.children() {
+                        self.visit(nodes);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => {
+                    self.check_condition(&of.condition);
+                    self.visit(&of.fragment);
+                }
+                TemplateNode::LoopFragment(lf) => self.visit(&lf.body),
+                _ => {}
+            }
+        }
+    }
+
+    fn check_condition(&mut self, id: &str) {
+        if let Some(expr) = self.expressions.iter().find(|e| e.id == id) {
+            self.check("condition", &expr.code, &expr.location);
+        }
+    }
+
+    fn check(&mut self, what: &str, code: &str, location: &SourceLocation) {
+        let mut read: Vec<String> = reads(code).into_iter().collect();
+        read.sort();
+        let Some(chain) = read.iter().find_map(|name| self.states.get(name)) else {
+            return;
+        };
+        let chain = chain
+            .iter()
+            .map(|name| format!("`{}`", name))
+            .collect::<Vec<_>>()
+            .join(" → ");
+        self.warnings.push(format!(
+            "Z-WARN-HYDRATION-MISMATCH: {} `{{{}}}` at {} (line {}, column {}) depends on {}, which only exists in the browser; the server-rendered HTML can differ from the first client render. Add `{}` to an enclosing element to render it on the client only.",
+            what,
+            code.trim(),
+            self.file_path,
+            location.line,
+            location.column,
+            chain,
+            CLIENT_ONLY_ATTR
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(page: &str) -> CompileResult {
+        compile_zen_internal(
+            page,
+            "page.zen",
+            CompileOptions {
+                ast_classifier: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn mismatches(result: &CompileResult) -> Vec<&String> {
+        result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("Z-WARN-HYDRATION-MISMATCH"))
+            .collect()
+    }
+
+    const SCRIPT: &str = "<script>\nstate stored = localStorage.getItem('theme')\nstate dark = stored === 'dark'\nstate name = 'Ada'\n</script>\n";
+
+    #[test]
+    fn test_local_storage_conditional_warns() {
+        let result = compile(&format!(
+            "{}<main>{{dark ? <p>Dark</p> : <p>Light</p>}}</main>",
+            SCRIPT
+        ));
+        let warnings = mismatches(&result);
+        assert_eq!(warnings.len(), 1, "{:?}", result.warnings);
+        assert!(
+            warnings[0].starts_with("Z-WARN-HYDRATION-MISMATCH: condition `{dark}` at page.zen")
+                && warnings[0].contains("depends on `dark` → `stored` → `localStorage`"),
+            "{}",
+            warnings[0]
+        );
+        assert!(result.html.contains("<p>Dark</p>"), "{}", result.html);
+    }
+
+    #[test]
+    fn test_client_only_skips_fold_and_warning() {
+        let result = compile(&format!(
+            "{}<main><div data-zen-client-only>{{dark ? <p>Dark</p> : <p>Light</p>}}<input prop:value={{name}} /></div><input prop:value={{name}} /></main>",
+            SCRIPT
+        ));
+        assert!(mismatches(&result).is_empty(), "{:?}", result.warnings);
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(
+            result
+                .html
+                .contains("<div data-zen-client-only=\"\"><div data-zen-conditional="),
+            "{}",
+            result.html
+        );
+        // Only the input outside the subtree gets its initial value
+        assert_eq!(
+            result.html.matches("value=\"Ada\"").count(),
+            1,
+            "{}",
+            result.html
+        );
+        assert!(result.bindings.iter().any(|b| b.r#type == "conditional"));
+    }
+
+    #[test]
+    fn test_state_reflecting_attributes() {
+        let result = compile(&format!(
+            "{}<main><dialog open={{stored !== null}}></dialog><input value={{name}} /><p title={{stored}}></p></main>",
+            SCRIPT
+        ));
+        let warnings = mismatches(&result);
+        assert_eq!(warnings.len(), 1, "{:?}", result.warnings);
+        assert!(
+            warnings[0].contains("`open` attribute `{stored !== null}`")
+                && warnings[0].contains("depends on `stored` → `localStorage`"),
+            "{}",
+            warnings[0]
+        );
+    }
+}
@@ -201,6 +201,12 @@
         }
     };
 
+    // zen:static: registry entries marked `once` are evaluated a single time, untracked
+    function frozenFn(fn, id) {
+        const entry = id && window.__ZENITH_EXPRESSIONS__.get(id);
+        return entry && entry.once ? () => zenUntrack(fn) : fn;
+    }
+
     var zenRef = window.zenRef = (i) => ({ current: i || null });
     var zenOnMount = window.zenOnMount = (cb) => { if (window.__zenith && window.__zenith.activeInstance) window.__zenith.activeInstance.mountHooks.push(cb); };
     var zenOnUnmount = window.zenOnUnmount = (cb) => { /* TODO: implement unmount hooks */ };
@@ -244,8 +250,9 @@
                 const ph = document.createComment('expr' + (id ? ':' + id : ''));
                 parent.appendChild(ph);
                 let curNodes = [];
+                const run = frozenFn(fn, id);
                 window.zenEffect(() => {
-                    const r = fn();
+                    const r = run();
                     curNodes.forEach(n => { if (n.parentNode) n.parentNode.removeChild(n); });
                     curNodes = [];
                     if (r == null || r === false) return;
@@ -418,6 +425,7 @@
             if (tag === 'svg' || tag === 'SVG') {
                 currentNamespace = SVG_NS;
             }
+            const afterChildren = [];
             if (props) {
                 // Helper to set class for both HTML and SVG elements
                 const setClass = (element, value) => {
@@ -435,6 +443,18 @@
                             const s = window.__ZENITH_STATE__;
                             if (s && s[v] && typeof s[v] === 'object' && 'current' in s[v]) s[v].current = el;
                         }
+                    } else if (k === 'props' && v && typeof v === 'object') {
+                        // prop:name bindings assign DOM properties and never touch attributes
+                        for (const [name, pv] of Object.entries(v)) {
+                            let assign = () => { el[name] = pv; };
+                            if (pv && typeof pv === 'object' && typeof pv.fn === 'function') {
+                                const run = frozenFn(pv.fn, pv.id);
+                                assign = () => window.zenEffect(() => { el[name] = run(); }, { id: pv.id });
+                            }
+                            // A select's value picks among its options, which are not appended yet
+                            if (name === 'value' && tag.toLowerCase() === 'select') afterChildren.push(assign);
+                            else assign();
+                        }
                     } else if (k.startsWith('on')) {
                         let fn = v;
                         if (v && typeof v === 'object' && v.fn) fn = v.fn;
@@ -452,9 +472,13 @@
                             id = v.id;
                         }
                         if (typeof fn === 'function') {
+                            const run = frozenFn(fn, id);
                             window.zenEffect(() => {
-                                const val = fn();
+                                const val = run();
                                 if (k === 'class' || k === 'className') setClass(el, val);
+                                // Form state lives in the property once the user has interacted
+                                else if (k === 'checked' && k in el) el.checked = !!val;
+                                else if (k === 'value' && k in el) el.value = val == null ? '' : String(val);
                                 else if (val == null || val === false) el.removeAttribute(k);
                                 else if (el.setAttribute) el.setAttribute(k, String(val));
                             }, { id });
@@ -469,6 +493,7 @@
                 const items = Array.isArray(children) ? children : [children];
                 items.forEach(c => hC(el, c));
             }
+            afterChildren.forEach(fn => fn());
 
             // Restore previous namespace context after processing children
             currentNamespace = previousNamespace;
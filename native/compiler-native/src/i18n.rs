@@ -0,0 +1,329 @@
+//! i18n Module for Zenith Compiler
+//!
+//! Extracts statically-known message keys from `t('key')` / `$t('key')` calls
+//! and optionally inlines a locale's translations at build time.
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{Argument, CallExpression, Expression};
+use oxc_ast_visit::Visit;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::component::zen_keywords_to_let_in_place;
+use crate::validate::{AttributeValue, SourceLocation, TemplateNode, ZenIR};
+
+/// Translation function names recognized at compile time (also registered as globals)
+pub const TRANSLATION_FNS: &[&str] = &["t", "$t"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct I18nOptions {
+    /// Collect static message keys into `CompileResult.i18n_keys`
+    #[serde(default)]
+    pub extract: bool,
+    /// Messages for the build locale; matching single-argument calls become string literals
+    #[serde(default)]
+    pub inline_locale: Option<HashMap<String, String>>,
+}
+
+/// A `t(...)` call found in a code fragment (byte offsets into that fragment)
+struct TranslationCall {
+    key: Option<String>,
+    start: usize,
+    end: usize,
+    /// Only `t('key')` without interpolation arguments can be replaced by a literal
+    inlinable: bool,
+}
+
+#[derive(Default)]
+struct TranslationCallCollector {
+    calls: Vec<TranslationCall>,
+}
+
+impl<'a> Visit<'a> for TranslationCallCollector {
+    fn visit_call_expression(&mut self, call: &CallExpression<'a>) {
+        if let Expression::Identifier(ident) = &call.callee {
+            if TRANSLATION_FNS.contains(&ident.name.as_str()) {
+                let key = match call.arguments.first() {
+                    Some(Argument::StringLiteral(lit)) => Some(lit.value.to_string()),
+                    _ => None,
+                };
+                self.calls.push(TranslationCall {
+                    key,
+                    start: call.span.start as usize,
+                    end: call.span.end as usize,
+                    inlinable: call.arguments.len() == 1,
+                });
+            }
+        }
+        oxc_ast_visit::walk::walk_call_expression(self, call);
+    }
+}
+
+fn collect_translation_calls(code: &str) -> Vec<TranslationCall> {
+    let allocator = Allocator::default();
+    let source_type = SourceType::default().with_jsx(true).with_typescript(true);
+    let ret = Parser::new(&allocator, code, source_type).parse();
+    if !ret.errors.is_empty() {
+        return Vec::new();
+    }
+
+    let mut collector = TranslationCallCollector::default();
+    collector.visit_program(&ret.program);
+    collector.calls
+}
+
+fn offset_to_location(text: &str, offset: usize) -> SourceLocation {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    SourceLocation {
+        line: before.matches('\n').count() as u32 + 1,
+        column: (before.len() - line_start) as u32 + 1,
+    }
+}
+
+/// Processes the calls of one code fragment: records keys, emits warnings, and
+/// returns the fragment with translations inlined (when a locale is given).
+fn process_fragment(
+    code: &str,
+    calls: &[TranslationCall],
+    location_of: &dyn Fn(usize) -> SourceLocation,
+    options: &I18nOptions,
+    file_path: &str,
+    keys: &mut Vec<(String, SourceLocation)>,
+    warnings: &mut Vec<String>,
+) -> String {
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+
+    for call in calls {
+        let key = match &call.key {
+            Some(key) => key,
+            None => {
+                warnings.push(format!(
+                    "Z-WARN-I18N-DYNAMIC-KEY: `{}` in {} uses a non-literal key and cannot be extracted or inlined.",
+                    &code[call.start..call.end],
+                    file_path
+                ));
+                continue;
+            }
+        };
+
+        if options.extract {
+            keys.push((key.clone(), location_of(call.start)));
+        }
+
+        if let Some(locale) = &options.inline_locale {
+            if !call.inlinable {
+                continue;
+            }
+            let message = match locale.get(key) {
+                Some(message) => message,
+                None => {
+                    warnings.push(format!(
+                        "Z-WARN-I18N-MISSING-KEY: No translation for \"{}\" in {}. Falling back to the key.",
+                        key, file_path
+                    ));
+                    key
+                }
+            };
+            let literal = serde_json::to_string(message).unwrap_or_else(|_| "\"\"".to_string());
+            replacements.push((call.start, call.end, literal));
+        }
+    }
+
+    let mut result = code.to_string();
+    // Inlined calls only take a literal argument, so their spans never overlap
+    for (start, end, literal) in replacements.into_iter().rev() {
+        result.replace_range(start..end, &literal);
+    }
+    result
+}
+
+/// Mirrors rewritten expression code (keyed by id) onto dynamic attribute values
+pub(crate) fn update_attribute_code(
+    nodes: &mut [TemplateNode],
+    rewritten: &HashMap<String, String>,
+) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                for attr in &mut el.attributes {
+                    if let AttributeValue::Dynamic(expr) = &mut attr.value {
+                        if let Some(code) = rewritten.get(&expr.id) {
+                            expr.code = code.clone();
+                        }
+                    }
+                }
+                update_attribute_code(&mut el.children, rewritten);
+            }
+            TemplateNode::Component(c) => {
+                for attr in &mut c.attributes {
+                    if let AttributeValue::Dynamic(expr) = &mut attr.value {
+                        if let Some(code) = rewritten.get(&expr.id) {
+                            expr.code = code.clone();
+                        }
+                    }
+                }
+                update_attribute_code(&mut c.children, rewritten);
+            }
+            TemplateNode::ConditionalFragment(cf) => {
+                update_attribute_code(&mut cf.consequent, rewritten);
+                update_attribute_code(&mut cf.alternate, rewritten);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children_mut() {
+                    update_attribute_code(nodes, rewritten);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => {
+                update_attribute_code(&mut of.fragment, rewritten)
+            }
+            TemplateNode::LoopFragment(lf) => update_attribute_code(&mut lf.body, rewritten),
+            _ => {}
+        }
+    }
+}
+
+/// Extracts and/or inlines translation calls in template expressions and the page script.
+/// Returns the extracted keys (empty unless `extract` is set); warnings go to `ir.warnings`.
+pub fn process_i18n(
+    ir: &mut ZenIR,
+    source: &str,
+    options: &I18nOptions,
+) -> Vec<(String, SourceLocation)> {
+    let mut keys = Vec::new();
+    let mut warnings = Vec::new();
+    let mut rewritten: HashMap<String, String> = HashMap::new();
+
+    for expr in &mut ir.template.expressions {
+        let calls = collect_translation_calls(&expr.code);
+        if calls.is_empty() {
+            continue;
+        }
+        // Template keys are reported at the enclosing expression's location
+        let location = expr.location.clone();
+        let code = process_fragment(
+            &expr.code,
+            &calls,
+            &|_| location.clone(),
+            options,
+            &ir.file_path,
+            &mut keys,
+            &mut warnings,
+        );
+        if code != expr.code {
+            expr.code = code.clone();
+            rewritten.insert(expr.id.clone(), code);
+        }
+    }
+    if !rewritten.is_empty() {
+        update_attribute_code(&mut ir.template.nodes, &rewritten);
+    }
+
+    if let Some(script) = ir.script.as_mut() {
+        let calls = collect_translation_calls(&zen_keywords_to_let_in_place(&script.raw));
+        if !calls.is_empty() {
+            let script_offset = source.find(script.raw.as_str()).unwrap_or(0);
+            script.raw = process_fragment(
+                &script.raw,
+                &calls,
+                &|offset| offset_to_location(source, script_offset + offset),
+                options,
+                &ir.file_path,
+                &mut keys,
+                &mut warnings,
+            );
+        }
+    }
+
+    ir.warnings.extend(warnings);
+    keys
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions};
+
+    const PAGE: &str = r#"<script>
+state name = "home.title"
+const heading = t('home.heading')
+</script>
+<h1 title={t('home.title')}>{t('home.title')}</h1><p>{t('home.missing')}</p><span>{t(name)}</span>"#;
+
+    fn compile(i18n: I18nOptions) -> crate::parse::CompileResult {
+        let options = CompileOptions {
+            i18n: Some(i18n),
+            ..Default::default()
+        };
+        compile_zen_internal(PAGE, "page.zen", options).unwrap()
+    }
+
+    #[test]
+    fn test_extracts_static_keys() {
+        let result = compile(I18nOptions {
+            extract: true,
+            inline_locale: None,
+        });
+        let mut keys: Vec<&str> = result.i18n_keys.iter().map(|(k, _)| k.as_str()).collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["home.heading", "home.missing", "home.title", "home.title"]
+        );
+
+        let (_, heading_loc) = result
+            .i18n_keys
+            .iter()
+            .find(|(k, _)| k == "home.heading")
+            .unwrap();
+        assert_eq!((heading_loc.line, heading_loc.column), (3, 17));
+    }
+
+    #[test]
+    fn test_inlines_locale_with_fallback() {
+        let mut locale = HashMap::new();
+        locale.insert("home.title".to_string(), "Welcome".to_string());
+        locale.insert("home.heading".to_string(), "Hello".to_string());
+        let result = compile(I18nOptions {
+            extract: false,
+            inline_locale: Some(locale),
+        });
+
+        assert!(result.i18n_keys.is_empty());
+        let manifest = result.manifest.unwrap();
+        assert!(manifest.expressions.contains("\"Welcome\""));
+        assert!(manifest.expressions.contains("\"home.missing\""));
+        assert!(!manifest.expressions.contains("home.title"));
+        assert!(manifest.script.contains("\"Hello\""));
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.starts_with("Z-WARN-I18N-MISSING-KEY") && w.contains("home.missing")));
+    }
+
+    #[test]
+    fn test_dynamic_key_warns() {
+        let result = compile(I18nOptions {
+            extract: true,
+            inline_locale: None,
+        });
+        let dynamic: Vec<&String> = result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("Z-WARN-I18N-DYNAMIC-KEY"))
+            .collect();
+        assert_eq!(dynamic.len(), 1);
+        assert!(dynamic[0].contains("t(name)"));
+    }
+}
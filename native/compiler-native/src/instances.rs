@@ -0,0 +1,325 @@
+//! Duplicate Render Detection
+//!
+//! Every resolved component usage becomes an instance with its own scope and its
+//! own copy of the component script. A component rendered twice by accident (a
+//! layout placing `<Analytics />` in the head, and slot content rendering it again
+//! in the body) therefore runs its top-level side effects twice: two beacons, two
+//! mount hooks. After resolution each component's instances are counted, with
+//! the instance id and the component chain each sits in. (The HTML parser moves a
+//! component written inside `<head>` to the start of `<body>`, so head and body
+//! usages both resolve as body content.)
+//!
+//! - `Z-WARN-DUPLICATE-RENDER` when a component whose script has top-level side
+//!   effects runs more than once on the page
+//! - one `Z-INFO-COMPONENT-INSTANCES` note listing the components with at least
+//!   `INSTANCE_NOTE_THRESHOLD` instances otherwise
+//!
+//! A usage marked `zen:allow-multiple` is intended and is not counted. Side effects
+//! are top-level calls to functions outside the pure whitelist (see `static_eval`),
+//! member calls, `new` and assignments to members, in the script or in an
+//! `is:inline` script of the template.
+
+use std::collections::{BTreeMap, HashMap};
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{Expression, Statement};
+use oxc_parser::Parser;
+use oxc_span::{GetSpan, SourceType};
+
+use crate::component::ComponentIR;
+use crate::validate::TemplateNode;
+
+/// `<Badge zen:allow-multiple />`: this usage is meant to repeat
+pub(crate) const ALLOW_MULTIPLE_ATTR: &str = "zen:allow-multiple";
+
+/// Instances of one component before the page gets an instance count note
+const INSTANCE_NOTE_THRESHOLD: usize = 20;
+
+/// Side effect snippets are cut to this many characters
+const SNIPPET_CHARS: usize = 60;
+
+/// One component instance created during resolution
+#[derive(Debug, Clone)]
+pub(crate) struct InstanceRecord {
+    /// Registry key
+    pub component: String,
+    /// `instN`, the key of the instance's scope
+    pub instance_id: String,
+    /// Components the instance is nested in, outermost first
+    pub parents: Vec<String>,
+    /// The usage is marked `zen:allow-multiple`
+    pub allow_multiple: bool,
+    /// The instance's script runs (false outside every island in islands mode)
+    pub runs_script: bool,
+}
+
+impl InstanceRecord {
+    /// `inst0 via Layout > Footer`, `inst3 in the page`
+    fn place(&self) -> String {
+        if self.parents.is_empty() {
+            format!("{} in the page", self.instance_id)
+        } else {
+            format!("{} via {}", self.instance_id, self.parents.join(" > "))
+        }
+    }
+}
+
+/// Duplicate render warnings and the instance count note for a resolved page
+pub(crate) fn check_instances(
+    instances: &[InstanceRecord],
+    components: &HashMap<String, ComponentIR>,
+    file_path: &str,
+) -> Vec<String> {
+    // Sorted by component so diagnostics are stable
+    let mut by_component: BTreeMap<&str, Vec<&InstanceRecord>> = BTreeMap::new();
+    for instance in instances.iter().filter(|i| !i.allow_multiple) {
+        by_component
+            .entry(instance.component.as_str())
+            .or_default()
+            .push(instance);
+    }
+
+    let mut diagnostics = Vec::new();
+    let mut counts = Vec::new();
+    for (name, instances) in by_component {
+        let Some(comp) = components.get(name) else {
+            continue;
+        };
+        let running: Vec<&&InstanceRecord> = instances.iter().filter(|i| i.runs_script).collect();
+        let side_effect = (running.len() > 1).then(|| side_effect(comp)).flatten();
+        if let Some(effect) = side_effect {
+            let places: Vec<String> = running.iter().map(|i| i.place()).collect();
+            diagnostics.push(format!(
+                "Z-WARN-DUPLICATE-RENDER: <{}> ({}) is rendered {} times in {} ({}), and its script has top-level side effects (`{}`) that run once per instance. Render it once, or mark intended repeats `{}`.",
+                name,
+                comp.path,
+                running.len(),
+                file_path,
+                places.join("; "),
+                effect,
+                ALLOW_MULTIPLE_ATTR
+            ));
+        } else if instances.len() >= INSTANCE_NOTE_THRESHOLD {
+            counts.push(format!("<{}> x{}", name, instances.len()));
+        }
+    }
+    if !counts.is_empty() {
+        diagnostics.push(format!(
+            "Z-INFO-COMPONENT-INSTANCES: {} renders {}; each instance registers its own scope.",
+            file_path,
+            counts.join(", ")
+        ));
+    }
+    diagnostics
+}
+
+/// First top-level side effect of the component's script or `is:inline` scripts
+fn side_effect(comp: &ComponentIR) -> Option<String> {
+    let script = comp
+        .script
+        .as_deref()
+        .map(crate::component::zen_keywords_to_let);
+    script
+        .into_iter()
+        .chain(inline_scripts(&comp.nodes))
+        .find_map(|code| first_side_effect(&code))
+}
+
+fn inline_scripts(nodes: &[TemplateNode]) -> Vec<String> {
+    let mut scripts = Vec::new();
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) if crate::define_vars::is_inline_script(el) => {
+                scripts.push(
+                    el.children
+                        .iter()
+                        .filter_map(|child| match child {
+                            TemplateNode::Text(text) => Some(text.value.as_str()),
+                            _ => None,
+                        })
+                        .collect(),
+                );
+            }
+            TemplateNode::Element(el) => scripts.extend(inline_scripts(&el.children)),
+            TemplateNode::Component(c) => scripts.extend(inline_scripts(&c.children)),
+            TemplateNode::ConditionalFragment(cf) => {
+                scripts.extend(inline_scripts(&cf.consequent));
+                scripts.extend(inline_scripts(&cf.alternate));
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    scripts.extend(inline_scripts(nodes));
+                }
+            }
+            TemplateNode::OptionalFragment(of) => scripts.extend(inline_scripts(&of.fragment)),
+            TemplateNode::LoopFragment(lf) => scripts.extend(inline_scripts(&lf.body)),
+            _ => {}
+        }
+    }
+    scripts
+}
+
+fn first_side_effect(code: &str) -> Option<String> {
+    let allocator = Allocator::default();
+    let source_type = SourceType::default()
+        .with_module(true)
+        .with_typescript(true)
+        .with_jsx(true);
+    let ret = Parser::new(&allocator, code, source_type).parse();
+    ret.program.body.iter().find_map(|stmt| {
+        let effectful = match stmt {
+            Statement::ExpressionStatement(stmt) => is_effectful(&stmt.expression),
+            // `const data = await fetch(url)`; factories (`const dispatch = zenDispatch()`)
+            // and member reads (`document.querySelector(...)`) are not
+            Statement::VariableDeclaration(decl) => decl
+                .declarations
+                .iter()
+                .filter_map(|d| d.init.as_ref())
+                .any(is_request),
+            _ => false,
+        };
+        effectful.then(|| snippet(&code[stmt.span().start as usize..stmt.span().end as usize]))
+    })
+}
+
+/// `await x`, `void x` and `(x)` are `x`
+fn unwrap<'a, 'b>(expr: &'b Expression<'a>) -> &'b Expression<'a> {
+    match expr.without_parentheses() {
+        Expression::AwaitExpression(e) => unwrap(&e.argument),
+        Expression::UnaryExpression(e) if e.operator.is_void() => unwrap(&e.argument),
+        e => e,
+    }
+}
+
+/// A network request or another awaited call to a function outside the whitelist
+fn is_request(init: &Expression) -> bool {
+    let awaited = matches!(init.without_parentheses(), Expression::AwaitExpression(_));
+    match unwrap(init) {
+        Expression::CallExpression(call) => {
+            matches!(&call.callee, Expression::Identifier(id) if id.name == "fetch")
+                || (awaited && bare_impure_call(&call.callee))
+        }
+        _ => false,
+    }
+}
+
+fn bare_impure_call(callee: &Expression) -> bool {
+    matches!(callee, Expression::Identifier(id) if !crate::static_eval::is_pure_function(&id.name))
+}
+
+fn is_effectful(expr: &Expression) -> bool {
+    match unwrap(expr) {
+        Expression::CallExpression(call) => {
+            !matches!(&call.callee, Expression::Identifier(_)) || bare_impure_call(&call.callee)
+        }
+        Expression::NewExpression(_) => true,
+        Expression::AssignmentExpression(assign) => assign.left.is_member_expression(),
+        Expression::SequenceExpression(seq) => seq.expressions.iter().any(is_effectful),
+        _ => false,
+    }
+}
+
+fn snippet(code: &str) -> String {
+    let line = code.lines().next().unwrap_or_default().trim();
+    if line.chars().count() > SNIPPET_CHARS || code.trim().contains('\n') {
+        let cut: String = line.chars().take(SNIPPET_CHARS).collect();
+        format!("{}...", cut.trim_end())
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(page: &str, components: &[(&str, &str)]) -> CompileResult {
+        let components = components
+            .iter()
+            .map(|(name, source)| {
+                let path = format!("components/{}.zen", name);
+                let comp = ComponentIR::from_source(name, &path, source).unwrap();
+                (name.to_string(), serde_json::to_value(comp).unwrap())
+            })
+            .collect();
+        let result = compile_zen_internal(
+            page,
+            "page.zen",
+            CompileOptions {
+                components,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        result
+    }
+
+    const ANALYTICS: &str = "<script>\nconst id = 'UA-1'\nzenOnMount(() => {\n  navigator.sendBeacon('/hit', id)\n})\n</script>\n<span hidden></span>";
+    const LAYOUT: &str =
+        "<html><head><title>Site</title><Analytics /></head><body><slot /></body></html>";
+
+    fn diagnostics(result: &CompileResult, prefix: &str) -> Vec<String> {
+        result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_side_effectful_component_rendered_twice_warns() {
+        let result = compile(
+            "<Layout><main><Analytics /></main></Layout>",
+            &[("Layout", LAYOUT), ("Analytics", ANALYTICS)],
+        );
+        let warnings = diagnostics(&result, "Z-WARN-DUPLICATE-RENDER");
+        assert_eq!(warnings.len(), 1, "{:?}", result.warnings);
+        assert!(
+            warnings[0].starts_with(
+                "Z-WARN-DUPLICATE-RENDER: <Analytics> (components/Analytics.zen) is rendered 2 times in page.zen (inst1 via Layout; inst2 via Layout)"
+            ),
+            "{}",
+            warnings[0]
+        );
+        assert!(
+            warnings[0].contains("(`zenOnMount(() => {...`)"),
+            "{}",
+            warnings[0]
+        );
+    }
+
+    #[test]
+    fn test_pure_component_gets_instance_note_only() {
+        let badges = "<Badge label=\"new\" />".repeat(50);
+        let result = compile(
+            &format!("<main>{}</main>", badges),
+            &[(
+                "Badge",
+                "<script>\nprop label\n</script>\n<span class=\"badge\">{String(label).toUpperCase()}</span>",
+            )],
+        );
+        assert!(diagnostics(&result, "Z-WARN-DUPLICATE-RENDER").is_empty());
+        assert_eq!(
+            diagnostics(&result, "Z-INFO-COMPONENT-INSTANCES"),
+            vec!["Z-INFO-COMPONENT-INSTANCES: page.zen renders <Badge> x50; each instance registers its own scope."]
+        );
+    }
+
+    #[test]
+    fn test_allow_multiple_silences_warning() {
+        let result = compile(
+            "<Layout><main><Analytics zen:allow-multiple /></main></Layout>",
+            &[("Layout", LAYOUT), ("Analytics", ANALYTICS)],
+        );
+        assert!(
+            diagnostics(&result, "Z-WARN-DUPLICATE-RENDER").is_empty(),
+            "{:?}",
+            result.warnings
+        );
+        // Not passed on as a prop
+        assert!(!result.html.contains("allow-multiple"), "{}", result.html);
+    }
+}
@@ -0,0 +1,430 @@
+//! Output Invariant Verification
+//!
+//! The ground-truth rules in the crate docs are upheld where each part of the
+//! output is generated; nothing checks the finished output against them, so a
+//! regression used to surface as a `ReferenceError` in the browser. With
+//! `CompileOptions.verify_invariants` (on by default in debug builds) the emitted
+//! expressions and bundle are parsed with oxc after codegen and checked:
+//!
+//! 1. every `_expr_*` function takes `scope` as its first parameter
+//! 2. no expression function reads a state or prop name bare, i.e. through a
+//!    reference that resolves to nothing declared in the generated code
+//! 3. every component instance named by a hydration marker in the HTML
+//!    (`<!--zen:expr_2_inst0-->`) has its scope registered in the bundle
+//! 4. every binding id is a marker in the HTML and is registered in
+//!    `__ZENITH_EXPRESSIONS__`; `{void ...}` effect bindings render nothing, so
+//!    they only need the registry entry
+//!
+//! Violations are INV010 errors quoting the offending generated code. They mean a
+//! compiler bug, not an authoring mistake, so a compile that already failed is not
+//! checked: its output is not expected to hold together.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use lazy_static::lazy_static;
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{Function, IdentifierReference};
+use oxc_ast_visit::{walk, Visit};
+use oxc_parser::Parser;
+use oxc_semantic::{Scoping, SemanticBuilder};
+use oxc_span::{GetSpan, SourceType};
+use oxc_syntax::scope::ScopeFlags;
+use regex::Regex;
+
+use crate::transform::Binding;
+use crate::validate::{CompilerError, SourceLocation, INV_OUTPUT_INVARIANT};
+
+lazy_static! {
+    /// Hydration marker carrying an expression id: `<!--zen:id-->`, `data-zen-*="id"`
+    static ref MARKER: Regex =
+        Regex::new(r#"<!--zen:([\w$-]+)-->|data-zen-[\w-]+=["']?([\w$]+)"#).unwrap();
+    /// Component instance suffix of a promoted expression id (`expr_2_inst0`)
+    static ref INSTANCE: Regex = Regex::new(r"_(inst\d+)\b").unwrap();
+    /// `window.__ZENITH_SCOPES__["inst0"] = ...`, or its minified `.inst0=` form
+    static ref SCOPE_REGISTRATION: Regex =
+        Regex::new(r#"__ZENITH_SCOPES__(?:\[\s*["'`](inst\d+)["'`]\s*\]|\.(inst\d+))\s*="#)
+            .unwrap();
+    /// `__ZENITH_EXPRESSIONS__.set('id', ...)`; minified output quotes with backticks
+    static ref REGISTRY_ENTRY: Regex =
+        Regex::new(r#"__ZENITH_EXPRESSIONS__\.set\(\s*["'`]([^"'`]+)["'`]"#).unwrap();
+}
+
+/// Names the ground-truth rules treat specially; never reported as bare
+const PROTECTED: &[&str] = &["scope", "state", "props", "locals"];
+
+/// The finished output of one page compile
+pub(crate) struct CompiledOutput<'a> {
+    pub(crate) html: &'a str,
+    /// Expression functions (`ZenManifestExport.expressions`)
+    pub(crate) expressions: &'a str,
+    pub(crate) bundle: &'a str,
+    pub(crate) bindings: &'a [Binding],
+    /// State and prop names that must only be read through `scope`
+    pub(crate) reactive_names: HashSet<&'a str>,
+}
+
+/// INV010 errors for every ground-truth rule `output` breaks
+pub(crate) fn verify_output(output: &CompiledOutput, file_path: &str) -> Vec<CompilerError> {
+    let locations: HashMap<&str, &SourceLocation> = output
+        .bindings
+        .iter()
+        .filter_map(|b| Some((b.id.as_str(), b.location.as_ref()?)))
+        .collect();
+    let violation = |id: Option<&str>, message: String, snippet: String| {
+        let location = id.and_then(|id| locations.get(id));
+        CompilerError::with_details(
+            INV_OUTPUT_INVARIANT,
+            &message,
+            file_path,
+            location.map_or(1, |l| l.line),
+            location.map_or(1, |l| l.column),
+            Some(snippet),
+            vec!["This is a compiler bug; please report it with the page source.".to_string()],
+        )
+    };
+
+    let mut errors = Vec::new();
+    for (id, problem, snippet) in check_expression_functions(output) {
+        errors.push(violation(id.as_deref(), problem, snippet));
+    }
+
+    // Rule 3: instances referenced by markers have a scope
+    let registered_scopes: HashSet<&str> = SCOPE_REGISTRATION
+        .captures_iter(output.bundle)
+        .filter_map(|c| c.get(1).or_else(|| c.get(2)))
+        .map(|m| m.as_str())
+        .collect();
+    let markers: BTreeSet<&str> = MARKER
+        .captures_iter(output.html)
+        .filter_map(|c| c.get(1).or_else(|| c.get(2)))
+        .map(|m| m.as_str())
+        .collect();
+    let mut reported_instances = HashSet::new();
+    for marker in &markers {
+        for caps in INSTANCE.captures_iter(marker) {
+            let instance = caps.get(1).unwrap().as_str();
+            if registered_scopes.contains(instance) || !reported_instances.insert(instance) {
+                continue;
+            }
+            errors.push(violation(
+                Some(marker),
+                format!(
+                    "Hydration marker `{}` belongs to component instance `{}`, but the bundle never registers `window.__ZENITH_SCOPES__[\"{}\"]`.",
+                    marker, instance, instance
+                ),
+                marker_snippet(output.html, marker),
+            ));
+        }
+    }
+
+    // Rule 4: bindings are both in the HTML and in the registry
+    let registry: HashSet<&str> = REGISTRY_ENTRY
+        .captures_iter(output.bundle)
+        .map(|c| c.get(1).unwrap().as_str())
+        .collect();
+    let mut seen = HashSet::new();
+    for binding in output.bindings {
+        if !seen.insert(binding.id.as_str()) {
+            continue;
+        }
+        if binding.r#type != "effect" && !markers.contains(binding.id.as_str()) {
+            errors.push(violation(
+                Some(&binding.id),
+                format!(
+                    "{} binding `{}` has no hydration marker in the HTML.",
+                    binding.r#type, binding.id
+                ),
+                binding.expression.clone(),
+            ));
+        }
+        if !registry.contains(binding.id.as_str()) {
+            errors.push(violation(
+                Some(&binding.id),
+                format!(
+                    "{} binding `{}` is not registered in `__ZENITH_EXPRESSIONS__`.",
+                    binding.r#type, binding.id
+                ),
+                binding.expression.clone(),
+            ));
+        }
+    }
+
+    errors
+}
+
+/// Rules 1 and 2 over the expression functions: (expression id, problem, snippet)
+fn check_expression_functions(output: &CompiledOutput) -> Vec<(Option<String>, String, String)> {
+    let code = output.expressions;
+    if code.trim().is_empty() {
+        return Vec::new();
+    }
+    let allocator = Allocator::default();
+    let ret = Parser::new(&allocator, code, SourceType::mjs()).parse();
+    if let Some(error) = ret.errors.first() {
+        return vec![(
+            None,
+            format!(
+                "The generated expression functions do not parse: {}.",
+                error.message
+            ),
+            code.to_string(),
+        )];
+    }
+    let scoping = SemanticBuilder::new()
+        .build(&ret.program)
+        .semantic
+        .into_scoping();
+    let mut checker = ExpressionChecker {
+        code,
+        scoping: &scoping,
+        reactive_names: &output.reactive_names,
+        current: None,
+        found: Vec::new(),
+    };
+    checker.visit_program(&ret.program);
+    checker.found
+}
+
+struct ExpressionChecker<'s> {
+    code: &'s str,
+    scoping: &'s Scoping,
+    reactive_names: &'s HashSet<&'s str>,
+    /// Expression id of the `_expr_*` function being walked
+    current: Option<String>,
+    found: Vec<(Option<String>, String, String)>,
+}
+
+impl<'a> Visit<'a> for ExpressionChecker<'_> {
+    fn visit_function(&mut self, func: &Function<'a>, flags: ScopeFlags) {
+        let Some(id) = func
+            .id
+            .as_ref()
+            .and_then(|ident| ident.name.strip_prefix("_expr_"))
+        else {
+            walk::walk_function(self, func, flags);
+            return;
+        };
+        let first = func
+            .params
+            .items
+            .first()
+            .and_then(|p| p.pattern.get_binding_identifier())
+            .map(|ident| ident.name.as_str());
+        if first != Some("scope") {
+            let end = func.body.as_ref().map_or(func.span.end, |b| b.span.start);
+            self.found.push((
+                Some(id.to_string()),
+                format!(
+                    "Expression function `_expr_{}` takes `{}` first instead of `scope`.",
+                    id,
+                    first.unwrap_or("nothing")
+                ),
+                self.code[func.span.start as usize..end as usize]
+                    .trim()
+                    .to_string(),
+            ));
+        }
+        let outer = self.current.replace(id.to_string());
+        walk::walk_function(self, func, flags);
+        self.current = outer;
+    }
+
+    fn visit_identifier_reference(&mut self, ident: &IdentifierReference<'a>) {
+        let Some(id) = &self.current else {
+            return;
+        };
+        let name = ident.name.as_str();
+        if !self.reactive_names.contains(name) || PROTECTED.contains(&name) {
+            return;
+        }
+        let resolved = ident
+            .reference_id
+            .get()
+            .and_then(|r| self.scoping.get_reference(r).symbol_id())
+            .is_some();
+        if !resolved {
+            self.found.push((
+                Some(id.clone()),
+                format!(
+                    "Expression function `_expr_{}` uses `{}` without `scope.`; the bare name is undefined at runtime.",
+                    id, name
+                ),
+                line_at(self.code, ident.span().start as usize).to_string(),
+            ));
+        }
+    }
+}
+
+/// The trimmed source line containing byte `offset`
+fn line_at(code: &str, offset: usize) -> &str {
+    let start = code[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = code[offset..].find('\n').map_or(code.len(), |i| offset + i);
+    code[start..end].trim()
+}
+
+/// The tag or comment in the HTML that carries the marker for `id`
+fn marker_snippet(html: &str, id: &str) -> String {
+    let Some(offset) = html.find(id) else {
+        return id.to_string();
+    };
+    let start = html[..offset].rfind('<').unwrap_or(offset);
+    let end = html[offset..]
+        .find('>')
+        .map_or(html.len(), |i| offset + i + 1);
+    html[start..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::component::ComponentIR;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile() -> CompileResult {
+        let greeting = "<script>\nprop name\nstate n = 1\n</script>\n<p class={name}>{n}</p>";
+        let component =
+            ComponentIR::from_source("Greeting", "components/Greeting.zen", greeting).unwrap();
+        let mut components = HashMap::new();
+        components.insert(
+            "Greeting".to_string(),
+            serde_json::to_value(component).unwrap(),
+        );
+        let result = compile_zen_internal(
+            "<script>\nstate count = 0\n</script>\n<main><Greeting name=\"a\" /><p>{count}</p></main>",
+            "page.zen",
+            CompileOptions {
+                components,
+                verify_invariants: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        result
+    }
+
+    /// Verify `result` after `corrupt` has edited its (html, expressions, bundle)
+    fn verify_corrupted(
+        result: &CompileResult,
+        corrupt: impl FnOnce(&mut String, &mut String, &mut String),
+    ) -> Vec<CompilerError> {
+        let manifest = result.manifest.as_ref().unwrap();
+        let (mut html, mut expressions, mut bundle) = (
+            result.html.clone(),
+            manifest.expressions.clone(),
+            manifest.bundle.clone(),
+        );
+        corrupt(&mut html, &mut expressions, &mut bundle);
+        let output = CompiledOutput {
+            html: &html,
+            expressions: &expressions,
+            bundle: &bundle,
+            bindings: &result.bindings,
+            reactive_names: ["count", "n", "name"].into_iter().collect(),
+        };
+        verify_output(&output, "page.zen")
+    }
+
+    /// Id of the binding for the page's own `{count}`
+    fn page_binding(result: &CompileResult) -> String {
+        let binding = result.bindings.iter().find(|b| b.origin.is_none()).unwrap();
+        binding.id.clone()
+    }
+
+    #[test]
+    fn test_compiled_output_holds_invariants() {
+        assert!(verify_corrupted(&compile(), |_, _, _| {}).is_empty());
+    }
+
+    #[test]
+    fn test_expression_without_scope_parameter() {
+        let result = compile();
+        let id = page_binding(&result);
+        let errors = verify_corrupted(&result, |_, expressions, _| {
+            *expressions = expressions.replace(
+                &format!("function _expr_{}(scope)", id),
+                &format!("function _expr_{}(ctx)", id),
+            );
+        });
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].code, INV_OUTPUT_INVARIANT);
+        assert!(
+            errors[0]
+                .message
+                .contains("takes `ctx` first instead of `scope`"),
+            "{}",
+            errors[0].message
+        );
+        assert_eq!(
+            errors[0].context.as_deref(),
+            Some(format!("function _expr_{}(ctx)", id).as_str())
+        );
+    }
+
+    #[test]
+    fn test_bare_state_reference() {
+        let result = compile();
+        let errors = verify_corrupted(&result, |_, expressions, _| {
+            *expressions = expressions.replace("scope.state.count", "count");
+        });
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0].message.contains("uses `count` without `scope.`"),
+            "{}",
+            errors[0].message
+        );
+        assert_eq!(errors[0].context.as_deref(), Some("const v = (count);;"));
+    }
+
+    #[test]
+    fn test_instance_without_scope_registration() {
+        let result = compile();
+        let errors = verify_corrupted(&result, |_, _, bundle| {
+            *bundle = bundle.replace("window.__ZENITH_SCOPES__[\"inst0\"] =", "const __lost =");
+        });
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0]
+                .message
+                .contains("belongs to component instance `inst0`"),
+            "{}",
+            errors[0].message
+        );
+        assert!(
+            errors[0].context.as_deref().unwrap().starts_with("<p "),
+            "{:?}",
+            errors[0].context
+        );
+    }
+
+    #[test]
+    fn test_binding_missing_from_html_and_registry() {
+        let result = compile();
+        let id = page_binding(&result);
+        let errors = verify_corrupted(&result, |html, _, _| {
+            *html = html.replace(&format!("<!--zen:{}-->", id), "");
+        });
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0]
+                .message
+                .contains(&format!("text binding `{}` has no hydration marker", id)),
+            "{}",
+            errors[0].message
+        );
+
+        let errors = verify_corrupted(&result, |_, _, bundle| {
+            *bundle = bundle.replace(&format!(".set('{}',", id), ".set('gone',");
+        });
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0]
+                .message
+                .contains("is not registered in `__ZENITH_EXPRESSIONS__`"),
+            "{}",
+            errors[0].message
+        );
+    }
+}
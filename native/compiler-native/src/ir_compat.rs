@@ -0,0 +1,275 @@
+//! IR Forward Compatibility
+//!
+//! The IR structs that cross the NAPI boundary (template nodes, attributes,
+//! expressions, `ComponentIR`, `ZenIR`) keep the fields they do not know in a
+//! flattened `extra: UnknownFields` map. IR from a newer producer therefore
+//! survives deserialize → resolve → serialize unchanged; a node cloned into the
+//! page by component resolution carries its unknown fields with it. The maps are
+//! skipped when empty, so payloads of current producers do not change.
+//!
+//! With `strict_ir` (a debugging aid for version mismatches) an unknown field is
+//! a `Z-ERR-IR-UNKNOWN-FIELD` error instead.
+
+use crate::component::ComponentIR;
+use crate::validate::{
+    AttributeIR, AttributeValue, ExpressionIR, TemplateNode, UnknownFields, ZenIR,
+};
+
+pub const UNKNOWN_FIELD_ERROR: &str = "Z-ERR-IR-UNKNOWN-FIELD";
+
+/// `Z-ERR-IR-UNKNOWN-FIELD` for every unknown field of a component entry
+pub(crate) fn check_component(comp: &ComponentIR) -> Vec<String> {
+    let mut found = Found::default();
+    found.extra(&comp.extra, "the component entry");
+    found.nodes(&comp.nodes);
+    found.expressions(&comp.expressions);
+    found.errors(&comp.path)
+}
+
+/// `Z-ERR-IR-UNKNOWN-FIELD` for every unknown field of a page IR
+pub(crate) fn check_page(ir: &ZenIR) -> Vec<String> {
+    let mut found = Found::default();
+    found.extra(&ir.extra, "the page IR");
+    found.nodes(&ir.template.nodes);
+    found.expressions(&ir.template.expressions);
+    found.errors(&ir.file_path)
+}
+
+/// (field, where) pairs in document order
+#[derive(Default)]
+struct Found(Vec<(String, String)>);
+
+impl Found {
+    fn extra(&mut self, extra: &UnknownFields, owner: &str) {
+        for field in extra.keys() {
+            self.0.push((field.clone(), owner.to_string()));
+        }
+    }
+
+    fn nodes(&mut self, nodes: &[TemplateNode]) {
+        for node in nodes {
+            match node {
+                TemplateNode::Element(el) => {
+                    self.extra(&el.extra, &format!("element <{}>", el.tag));
+                    self.attributes(&el.attributes);
+                    self.nodes(&el.children);
+                }
+                TemplateNode::Text(t) => self.extra(&t.extra, "a text node"),
+                TemplateNode::Expression(e) => {
+                    self.extra(&e.extra, &format!("expression node `{}`", e.expression))
+                }
+                TemplateNode::Component(c) => {
+                    self.extra(&c.extra, &format!("component usage <{}>", c.name));
+                    self.attributes(&c.attributes);
+                    self.nodes(&c.children);
+                }
+                TemplateNode::ConditionalFragment(cf) => {
+                    self.extra(&cf.extra, "a conditional fragment");
+                    self.nodes(&cf.consequent);
+                    self.nodes(&cf.alternate);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    self.extra(&mb.extra, "a multi-branch fragment");
+                    for branch in &mb.branches {
+                        self.extra(&branch.extra, &format!("branch `{}`", branch.condition));
+                    }
+                    for nodes in mb.children() {
+                        self.nodes(nodes);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => {
+                    self.extra(&of.extra, "an optional fragment");
+                    self.nodes(&of.fragment);
+                }
+                TemplateNode::LoopFragment(lf) => {
+                    self.extra(&lf.extra, "a loop fragment");
+                    self.nodes(&lf.body);
+                }
+                TemplateNode::Doctype(d) => self.extra(&d.extra, "the doctype"),
+            }
+        }
+    }
+
+    fn attributes(&mut self, attributes: &[AttributeIR]) {
+        for attr in attributes {
+            self.extra(&attr.extra, &format!("attribute `{}`", attr.name));
+            if let AttributeValue::Dynamic(expr) = &attr.value {
+                self.expressions(std::slice::from_ref(expr));
+            }
+        }
+    }
+
+    fn expressions(&mut self, expressions: &[ExpressionIR]) {
+        for expr in expressions {
+            self.extra(&expr.extra, &format!("expression `{}`", expr.id));
+        }
+    }
+
+    fn errors(self, path: &str) -> Vec<String> {
+        self.0
+            .into_iter()
+            .map(|(field, owner)| {
+                format!(
+                    "{}: unknown field `{}` on {} in {}; the IR comes from a newer producer than this compiler.",
+                    UNKNOWN_FIELD_ERROR, field, owner, path
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::{resolve_components_with_options, ResolveOptions};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+
+    /// A component entry with an unknown field at each level of its IR
+    fn future_card() -> Value {
+        json!({
+            "name": "Card",
+            "path": "components/Card.zen",
+            "props": ["title"],
+            "script": "prop title",
+            "hydrationHints": { "priority": 2 },
+            "nodes": [{
+                "type": "element",
+                "tag": "article",
+                "attributes": [
+                    { "name": "class", "value": "card", "sourceSpan": [4, 16] },
+                    {
+                        "name": "title",
+                        "value": { "id": "expr_0", "code": "title", "loopContext": null, "purity": "pure" },
+                        "loopContext": null
+                    }
+                ],
+                "children": [
+                    { "type": "text", "value": "Hi ", "loopContext": null, "whitespace": "preserve" },
+                    { "type": "expression", "expression": "expr_0", "loopContext": null, "hint": "string" }
+                ],
+                "loopContext": null,
+                "devtoolsId": "n1"
+            }],
+            "expressions": [
+                { "id": "expr_0", "code": "title", "loopContext": null, "purity": "pure" }
+            ]
+        })
+    }
+
+    fn page(usage_extra: Value) -> ZenIR {
+        let mut usage = json!({
+            "type": "component",
+            "name": "Card",
+            "attributes": [{ "name": "title", "value": "Hello", "loopContext": null }],
+            "children": [],
+            "loopContext": null
+        });
+        usage
+            .as_object_mut()
+            .unwrap()
+            .extend(usage_extra.as_object().unwrap().clone());
+        serde_json::from_value(json!({
+            "filePath": "page.zen",
+            "template": { "raw": "", "nodes": [usage], "expressions": [] },
+            "script": null,
+            "styles": [],
+            "buildId": "b-17"
+        }))
+        .unwrap()
+    }
+
+    fn resolve(ir: ZenIR, options: &ResolveOptions) -> Result<ZenIR, String> {
+        let components = HashMap::from([("Card".to_string(), future_card())]);
+        resolve_components_with_options(ir, components, options)
+    }
+
+    #[test]
+    fn test_component_entry_round_trips_unknown_fields() {
+        let comp: ComponentIR = serde_json::from_value(future_card()).unwrap();
+        let out = serde_json::to_value(&comp).unwrap();
+        assert_eq!(out["hydrationHints"], json!({ "priority": 2 }));
+        let article = &out["nodes"][0];
+        assert_eq!(article["devtoolsId"], "n1");
+        assert_eq!(article["attributes"][0]["sourceSpan"], json!([4, 16]));
+        assert_eq!(article["attributes"][1]["value"]["purity"], "pure");
+        assert_eq!(article["children"][0]["whitespace"], "preserve");
+        assert_eq!(article["children"][1]["hint"], "string");
+        assert_eq!(out["expressions"][0]["purity"], "pure");
+        // The enum tag is known, not an extra field
+        assert_eq!(article["type"], "element");
+        match &comp.nodes[0] {
+            TemplateNode::Element(el) => assert!(!el.extra.contains_key("type")),
+            other => panic!("expected an element, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unknown_fields_survive_component_resolution() {
+        let ir = resolve(page(json!({})), &ResolveOptions::default()).unwrap();
+        assert_eq!(ir.extra["buildId"], "b-17");
+
+        let out = serde_json::to_value(&ir).unwrap();
+        let article = &out["template"]["nodes"][0];
+        assert_eq!(article["tag"], "article");
+        assert_eq!(article["devtoolsId"], "n1");
+        assert_eq!(article["attributes"][0]["sourceSpan"], json!([4, 16]));
+        assert_eq!(article["children"][0]["whitespace"], "preserve");
+        assert_eq!(article["children"][1]["hint"], "string");
+        let promoted = ir
+            .template
+            .expressions
+            .iter()
+            .find(|e| e.id.ends_with("_inst0"))
+            .unwrap();
+        assert_eq!(promoted.extra["purity"], "pure");
+    }
+
+    #[test]
+    fn test_known_fields_serialize_without_extra() {
+        let ir = resolve(page(json!({})), &ResolveOptions::default()).unwrap();
+        let mut ir = ir;
+        ir.extra.clear();
+        let out = serde_json::to_string(&ir).unwrap();
+        assert!(!out.contains("\"extra\""));
+        assert!(!out.contains("buildId"));
+    }
+
+    #[test]
+    fn test_strict_ir_reports_unknown_fields() {
+        let options = ResolveOptions {
+            strict_ir: true,
+            ..Default::default()
+        };
+        let err = resolve(page(json!({ "slotHint": "none" })), &options).unwrap_err();
+        for expected in [
+            "unknown field `buildId` on the page IR in page.zen",
+            "unknown field `slotHint` on component usage <Card> in page.zen",
+            "unknown field `hydrationHints` on the component entry in components/Card.zen",
+            "unknown field `devtoolsId` on element <article>",
+            "unknown field `sourceSpan` on attribute `class`",
+            "unknown field `whitespace` on a text node",
+            "unknown field `hint` on expression node `expr_0`",
+            "unknown field `purity` on expression `expr_0`",
+        ] {
+            assert!(err.contains(expected), "missing {:?} in {}", expected, err);
+        }
+        assert!(err.contains(UNKNOWN_FIELD_ERROR));
+    }
+
+    #[test]
+    fn test_strict_ir_accepts_current_ir() {
+        let card = json!({
+            "name": "Card",
+            "nodes": [{ "type": "element", "tag": "p", "attributes": [], "children": [] }]
+        });
+        let mut ir = page(json!({}));
+        ir.extra.clear();
+        let options = ResolveOptions {
+            strict_ir: true,
+            ..Default::default()
+        };
+        let components = HashMap::from([("Card".to_string(), card)]);
+        assert!(resolve_components_with_options(ir, components, &options).is_ok());
+    }
+}
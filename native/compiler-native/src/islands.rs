@@ -0,0 +1,377 @@
+//! Islands Mode
+//!
+//! With `CompileOptions.islands`, only interactive subtrees hydrate: elements
+//! marked `zen:island`, and component usages that are marked or whose component
+//! keeps state, emits events, or binds event handlers. Each island is wrapped in
+//! `<zen-island data-zen-island="islandN" style="display: contents">` and gets its
+//! own init block in the bundle, so the runtime mounts it without touching the
+//! rest of the page.
+//!
+//! Everything outside an island is emitted as final HTML: expressions are folded
+//! against the initial state and the static props of non-island components. An
+//! expression that cannot be folded is an error pointing the author at
+//! `zen:island`. Only island expressions stay in the registry.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+use crate::transform::{FORM_ACTION_ATTR, ISLAND_ATTR};
+use crate::validate::{
+    AttributeIR, AttributeValue, ElementNode, SourceLocation, TemplateNode, TextNode, ZenIR,
+};
+
+/// Wrapper element around every island's content
+pub const ISLAND_TAG: &str = "zen-island";
+
+/// Island id on the wrapper, the key of its init block in the bundle
+pub const ISLAND_ID_ATTR: &str = "data-zen-island";
+
+lazy_static::lazy_static! {
+    /// A component instance's prop read, as promoted by component resolution
+    static ref INSTANCE_PROP_RE: Regex =
+        Regex::new(r#"window\.__ZENITH_SCOPES__\["(inst\d+)"\]\.props\.([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)"#)
+            .unwrap();
+    /// Any read of a component instance's scope
+    static ref INSTANCE_SCOPE_RE: Regex =
+        Regex::new(r#"window\.__ZENITH_SCOPES__\["(inst\d+)"\]"#).unwrap();
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct IslandIR {
+    /// `islandN`, also the `data-zen-island` value of its wrapper
+    pub id: String,
+    /// Content of the wrapper; this is what the island's init block renders
+    pub nodes: Vec<TemplateNode>,
+    /// Registry ids of the expressions the island hydrates
+    pub expression_ids: Vec<String>,
+}
+
+/// Manifest entry for one island
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi(object))]
+pub struct IslandManifest {
+    pub id: String,
+    pub expressions: u32,
+    /// Bytes of the island's init block plus its expression sources
+    pub bytes: u32,
+    /// Page state keys and component scopes the island reads
+    pub deps: Vec<String>,
+}
+
+/// Wrapper placed around a component instance that is an island on its own
+/// (component resolution); `apply_islands` assigns its id.
+pub(crate) fn component_island(nodes: Vec<TemplateNode>, location: SourceLocation) -> TemplateNode {
+    TemplateNode::Element(ElementNode {
+        tag: ISLAND_TAG.to_string(),
+        attributes: vec![static_attr(ISLAND_ATTR, "")],
+        children: nodes,
+        location,
+        loop_context: None,
+        ordinal: 0,
+        extra: Default::default(),
+    })
+}
+
+/// Component resolution in islands mode: does this usage hydrate?
+pub(crate) fn is_interactive_component(
+    comp: &crate::component::ComponentIR,
+    marked: bool,
+    has_handlers: bool,
+) -> bool {
+    marked
+        || has_handlers
+        || !comp.states.is_empty()
+        || !comp.events.is_empty()
+        || crate::finalize::check_for_events(&comp.nodes)
+}
+
+/// Wrap island roots, fold everything else to static HTML, and keep only island
+/// expressions in the registry. Returns the errors for unfoldable expressions.
+pub(crate) fn apply_islands(ir: &mut ZenIR) -> Vec<String> {
+    let mut values = crate::static_eval::initial_state_values(&ir.all_states);
+    for (name, value) in &ir.island_props {
+        values.insert(name.clone(), value.clone());
+    }
+    let mut folder = Folder {
+        file_path: &ir.file_path,
+        codes: ir
+            .template
+            .expressions
+            .iter()
+            .map(|e| (e.id.clone(), e.code.clone()))
+            .collect(),
+        values,
+        islands: Vec::new(),
+        errors: Vec::new(),
+    };
+    let nodes = std::mem::take(&mut ir.template.nodes);
+    ir.template.nodes = folder.fold_nodes(nodes);
+
+    let hydrated: HashSet<&str> = folder
+        .islands
+        .iter()
+        .flat_map(|island| island.expression_ids.iter().map(String::as_str))
+        .collect();
+    ir.template
+        .expressions
+        .retain(|e| hydrated.contains(e.id.as_str()));
+
+    let Folder {
+        islands, errors, ..
+    } = folder;
+    ir.islands = Some(islands);
+    errors
+}
+
+struct Folder<'a> {
+    file_path: &'a str,
+    /// Expression code by registry id
+    codes: HashMap<String, String>,
+    /// Initial state values, plus static instance props keyed `instN.name`
+    values: HashMap<String, String>,
+    islands: Vec<IslandIR>,
+    errors: Vec<String>,
+}
+
+impl Folder<'_> {
+    fn fold_nodes(&mut self, nodes: Vec<TemplateNode>) -> Vec<TemplateNode> {
+        let mut out = Vec::new();
+        for node in nodes {
+            match node {
+                TemplateNode::Element(el)
+                    if el.attributes.iter().any(|a| a.name == ISLAND_ATTR) =>
+                {
+                    out.push(self.island(el));
+                }
+                TemplateNode::Element(mut el) => {
+                    let attributes = std::mem::take(&mut el.attributes);
+                    el.attributes = attributes
+                        .into_iter()
+                        .filter_map(|attr| self.fold_attribute(attr))
+                        .collect();
+                    el.children = self.fold_nodes(el.children);
+                    out.push(TemplateNode::Element(el));
+                }
+                TemplateNode::Expression(e) => {
+                    let code = self.code(&e.expression);
+                    if let Some(value) = self.fold(&code, &e.location) {
+                        let value = match value.as_str() {
+                            "undefined" | "null" => String::new(),
+                            _ => value,
+                        };
+                        out.push(TemplateNode::Text(TextNode {
+                            value,
+                            location: e.location,
+                            loop_context: None,
+                            ordinal: 0,
+                            extra: e.extra,
+                        }));
+                    }
+                }
+                TemplateNode::ConditionalFragment(cf) => {
+                    let code = self.code(&cf.condition);
+                    match self.fold(&code, &cf.location).map(|v| is_truthy(&v)) {
+                        Some(true) => out.extend(self.fold_nodes(cf.consequent)),
+                        Some(false) => out.extend(self.fold_nodes(cf.alternate)),
+                        None => {}
+                    }
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    // First branch known to hold; an unknown condition stops the fold
+                    let mut chosen = Some(mb.default);
+                    for branch in mb.branches {
+                        let code = self.code(&branch.condition);
+                        match self.fold(&code, &mb.location).map(|v| is_truthy(&v)) {
+                            Some(true) => {
+                                chosen = Some(branch.nodes);
+                                break;
+                            }
+                            Some(false) => {}
+                            None => {
+                                chosen = None;
+                                break;
+                            }
+                        }
+                    }
+                    if let Some(nodes) = chosen {
+                        out.extend(self.fold_nodes(nodes));
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => {
+                    let code = self.code(&of.condition);
+                    if self
+                        .fold(&code, &of.location)
+                        .is_some_and(|v| is_truthy(&v))
+                    {
+                        out.extend(self.fold_nodes(of.fragment));
+                    }
+                }
+                TemplateNode::LoopFragment(lf) => {
+                    // Lists are never unrolled at build time
+                    let code = self.code(&lf.source);
+                    self.unfoldable(&code, &lf.location);
+                }
+                TemplateNode::Component(mut c) => {
+                    c.children = self.fold_nodes(c.children);
+                    out.push(TemplateNode::Component(c));
+                }
+                other => out.push(other),
+            }
+        }
+        out
+    }
+
+    /// Dynamic values become static; `false`, `null`, and `undefined` drop the attribute
+    fn fold_attribute(&mut self, attr: AttributeIR) -> Option<AttributeIR> {
+        let AttributeValue::Dynamic(expr) = &attr.value else {
+            return Some(attr);
+        };
+        if attr.name.to_lowercase().starts_with("on") || attr.name == FORM_ACTION_ATTR {
+            self.unfoldable(&expr.code, &attr.location);
+            return None;
+        }
+        let value = self.fold(&expr.code, &attr.location)?;
+        match value.as_str() {
+            "false" | "null" | "undefined" => None,
+            _ => Some(AttributeIR {
+                value: AttributeValue::Static(value),
+                ..attr
+            }),
+        }
+    }
+
+    fn island(&mut self, mut el: ElementNode) -> TemplateNode {
+        el.attributes.retain(|a| a.name != ISLAND_ATTR);
+        let location = el.location.clone();
+        let nodes = if el.tag == ISLAND_TAG {
+            el.children
+        } else {
+            vec![TemplateNode::Element(el)]
+        };
+
+        let id = format!("island{}", self.islands.len());
+        let mut expression_ids = crate::reconcile::referenced_ids(&nodes);
+        let mut seen = HashSet::new();
+        expression_ids.retain(|id| seen.insert(id.clone()));
+        self.islands.push(IslandIR {
+            id: id.clone(),
+            nodes: nodes.clone(),
+            expression_ids,
+        });
+
+        TemplateNode::Element(ElementNode {
+            tag: ISLAND_TAG.to_string(),
+            attributes: vec![
+                static_attr(ISLAND_ID_ATTR, &id),
+                static_attr("style", "display: contents"),
+            ],
+            children: nodes,
+            location,
+            loop_context: None,
+            ordinal: 0,
+            extra: Default::default(),
+        })
+    }
+
+    fn code(&self, id: &str) -> String {
+        self.codes
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| "undefined".to_string())
+    }
+
+    fn fold(&mut self, code: &str, location: &SourceLocation) -> Option<String> {
+        let value = fold_expression(code, &self.values);
+        if value.is_none() {
+            self.unfoldable(code, location);
+        }
+        value
+    }
+
+    fn unfoldable(&mut self, code: &str, location: &SourceLocation) {
+        self.errors.push(format!(
+            "Z-ERR-ISLAND-DYNAMIC: `{}` in {} (line {}, column {}) is outside every island and cannot be evaluated at build time. Add `zen:island` to an enclosing element to hydrate it.",
+            code.trim(),
+            self.file_path,
+            location.line,
+            location.column
+        ));
+    }
+}
+
+/// Substitute known instance props, then evaluate statically
+fn fold_expression(code: &str, values: &HashMap<String, String>) -> Option<String> {
+    let substituted = INSTANCE_PROP_RE.replace_all(code, |caps: &regex::Captures| {
+        let key = format!("{}.{}", &caps[1], &caps[2]);
+        match values.get(&key) {
+            Some(value) => serde_json::to_string(value).unwrap_or_default(),
+            None => caps[0].to_string(),
+        }
+    });
+    // Anything still reading a component scope is only known at runtime
+    if substituted.contains("__ZENITH_SCOPES__") {
+        return None;
+    }
+    crate::static_eval::static_eval(&substituted, values)
+}
+
+fn is_truthy(value: &str) -> bool {
+    !matches!(value, "" | "false" | "null" | "undefined" | "0" | "NaN")
+}
+
+fn static_attr(name: &str, value: &str) -> AttributeIR {
+    AttributeIR {
+        name: name.to_string(),
+        value: AttributeValue::Static(value.to_string()),
+        location: SourceLocation::default(),
+        loop_context: None,
+        optional: false,
+        extra: Default::default(),
+    }
+}
+
+/// Page state keys and component scopes an island's expressions read
+pub(crate) fn island_deps(codes: &[&str], state_keys: &HashSet<&String>) -> Vec<String> {
+    let mut deps = std::collections::BTreeSet::new();
+    for code in codes {
+        for caps in INSTANCE_SCOPE_RE.captures_iter(code) {
+            deps.insert(caps[1].to_string());
+        }
+        for name in crate::finalize::referenced_identifiers(code) {
+            if state_keys.contains(&name) {
+                deps.insert(name);
+            }
+        }
+    }
+    deps.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold_substitutes_instance_props() {
+        let mut values = HashMap::new();
+        values.insert("inst0.title".to_string(), "Hello".to_string());
+        values.insert("count".to_string(), "2".to_string());
+
+        assert_eq!(
+            fold_expression(r#"window.__ZENITH_SCOPES__["inst0"].props.title"#, &values),
+            Some("Hello".to_string())
+        );
+        assert_eq!(fold_expression("count", &values), Some("2".to_string()));
+        assert_eq!(
+            fold_expression(r#"window.__ZENITH_SCOPES__["inst1"].props.title"#, &values),
+            None
+        );
+    }
+}
@@ -8,6 +8,8 @@ use oxc_codegen::Codegen;
 use oxc_span::SPAN;
 use std::collections::HashSet;
 
+use crate::binding_table::{binding_after_freeze, BindingTable};
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // JSX LOWERER
 // Transforms JSX elements into __zenith.h() calls
@@ -61,7 +63,12 @@ impl<'a> JsxLowerer<'a> {
                                 .map(|e| e.clone_in(self.ast.allocator))
                             {
                                 self.visit_expression(&mut e);
-                                e
+                                let is_handler = matches!(&attr.name, JSXAttributeName::Identifier(id) if crate::handlers::is_event_attr(&id.name));
+                                if is_handler && !crate::handlers::is_function_valued(&e) {
+                                    crate::handlers::wrap_handler_expression(self.ast.allocator, e)
+                                } else {
+                                    e
+                                }
                             } else {
                                 self.ast.expression_identifier(SPAN, "undefined")
                             }
@@ -323,6 +330,8 @@ pub struct ScriptRenamer<'a> {
     pub external_locals: HashSet<String>,
     pub scope_stack: Vec<HashSet<String>>,
     pub errors: Vec<String>,
+    /// Non-fatal diagnostics (e.g. a prop mutated by an event handler)
+    pub warnings: Vec<String>,
     /// Phase 5: Directly tracked state dependencies (Enhancement 3)
     pub state_deps: HashSet<String>,
     /// Phase 5: Directly tracked prop dependencies (Enhancement 3)
@@ -339,10 +348,30 @@ pub struct ScriptRenamer<'a> {
     pub mutated_state_deps: HashSet<String>,
     /// Phase 2: Allow prop fallback for unresolved identifiers (ONLY in template root context)
     pub allow_prop_fallback: bool,
+    /// Built from a frozen `BindingTable`: top-level bindings it does not know are
+    /// invariant violations rather than late additions to `local_bindings`
+    pub bindings_frozen: bool,
 }
 
+/// Methods that change the array, map, or set they are called on
+const MUTATING_METHODS: &[&str] = &[
+    "push",
+    "pop",
+    "shift",
+    "unshift",
+    "splice",
+    "sort",
+    "reverse",
+    "fill",
+    "copyWithin",
+    "set",
+    "add",
+    "delete",
+    "clear",
+];
+
 lazy_static::lazy_static! {
-    static ref GLOBALS: HashSet<&'static str> = {
+    pub(crate) static ref GLOBALS: HashSet<&'static str> = {
         let mut s = HashSet::new();
         s.extend([
             "window", "console", "Math", "JSON", "Map", "Set", "URL", "Array", "Object",
@@ -358,12 +387,34 @@ lazy_static::lazy_static! {
             "zenOnMount", "zenOnUnmount", "zenEffect", "zenComputed", "zenWatch", "zenWatchEffect",
             "requestAnimationFrame", "cancelAnimationFrame", "Element", "Node", "Event",
             "MouseEvent", "KeyboardEvent", "URLSearchParams", "__ZENITH_STATE__", "__ZENITH_SCOPES__",
-            "ref", "zenFixSVGNamespace"
+            "ref", "zenFixSVGNamespace", "zenFilter", "zenDispatch", "__zenLoadLazy", "zenAssert"
         ]);
+        // Translation helpers (see i18n.rs)
+        s.extend(crate::i18n::TRANSLATION_FNS.iter().copied());
         s
     };
 }
 
+/// The part of `GLOBALS` that only exists in a browser (or its Zenith runtime), so
+/// code evaluated at compile time cannot read it (see `ssr_safety.rs`)
+pub(crate) const SSR_UNSAFE_GLOBALS: &[&str] = &[
+    "window",
+    "document",
+    "location",
+    "navigator",
+    "localStorage",
+    "sessionStorage",
+    "requestAnimationFrame",
+    "cancelAnimationFrame",
+    "Element",
+    "Node",
+    "Event",
+    "MouseEvent",
+    "KeyboardEvent",
+    "__zenith",
+    "__ZENITH_STATE__",
+];
+
 impl<'a> ScriptRenamer<'a> {
     pub fn with_categories(
         allocator: &'a Allocator,
@@ -381,6 +432,7 @@ impl<'a> ScriptRenamer<'a> {
             external_locals,
             scope_stack: vec![HashSet::new()],
             errors: Vec::new(),
+            warnings: Vec::new(),
             state_deps: HashSet::new(),
             prop_deps: HashSet::new(),
             disallow_reactive_access: false,
@@ -389,7 +441,37 @@ impl<'a> ScriptRenamer<'a> {
             collected_imports: Vec::new(),
             mutated_state_deps: HashSet::new(),
             allow_prop_fallback: false,
+            bindings_frozen: false,
+        }
+    }
+
+    /// A renamer classifying against `table`; imports are real JS locals
+    pub(crate) fn from_table(allocator: &'a Allocator, table: &BindingTable) -> Self {
+        let mut renamer = Self::with_categories(
+            allocator,
+            table.states.clone(),
+            table.props.clone(),
+            table.locals.clone(),
+            table.external_locals.clone(),
+        );
+        for name in &table.module {
+            renamer.add_local(name.clone());
+        }
+        renamer.bindings_frozen = table.is_frozen();
+        renamer
+    }
+
+    /// Register a hoisted top-level binding. With frozen bindings it must already be
+    /// known; otherwise later expressions would classify it differently.
+    fn register_local_binding(&mut self, name: &str) {
+        if self.local_bindings.contains(name) {
+            return;
+        }
+        if self.bindings_frozen {
+            self.errors.push(binding_after_freeze(name, "local"));
+            return;
         }
+        self.local_bindings.insert(name.to_string());
     }
 
     pub fn add_local(&mut self, name: String) {
@@ -414,6 +496,98 @@ impl<'a> ScriptRenamer<'a> {
         GLOBALS.contains(name)
     }
 
+    /// State or prop a member chain is rooted at, looking through parentheses and
+    /// optional chains: `user.name` → `user`, `props.user.tags[0]` → `user`.
+    /// `property` is the static property read from `object`, if any.
+    fn reactive_root(
+        &self,
+        object: &Expression<'a>,
+        property: Option<&str>,
+    ) -> Option<IdentifierRef> {
+        match object {
+            Expression::Identifier(id) => match self.classify_identifier(&id.name) {
+                IdentifierRef::GlobalRef(n) if n == "props" => {
+                    property.map(|p| IdentifierRef::PropRef(p.to_string()))
+                }
+                IdentifierRef::GlobalRef(n) if n == "state" => {
+                    property.map(|p| IdentifierRef::StateRef(p.to_string()))
+                }
+                root @ (IdentifierRef::StateRef(_) | IdentifierRef::PropRef(_)) => Some(root),
+                _ => None,
+            },
+            Expression::StaticMemberExpression(m) => {
+                self.reactive_root(&m.object, Some(&m.property.name))
+            }
+            Expression::ComputedMemberExpression(m) => self.reactive_root(&m.object, None),
+            Expression::ParenthesizedExpression(p) => self.reactive_root(&p.expression, property),
+            Expression::TSNonNullExpression(e) => self.reactive_root(&e.expression, property),
+            Expression::TSAsExpression(e) => self.reactive_root(&e.expression, property),
+            Expression::ChainExpression(chain) => match &chain.expression {
+                ChainElement::StaticMemberExpression(m) => {
+                    self.reactive_root(&m.object, Some(&m.property.name))
+                }
+                ChainElement::ComputedMemberExpression(m) => self.reactive_root(&m.object, None),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Root of a call to a mutating method (`tags.push(x)`, `user?.roles.add(r)`)
+    fn mutating_call_root(&self, expr: &Expression<'a>) -> Option<(IdentifierRef, String)> {
+        let call = match expr {
+            Expression::CallExpression(call) => call,
+            Expression::ChainExpression(chain) => match &chain.expression {
+                ChainElement::CallExpression(call) => call,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let (object, method) = match &call.callee {
+            Expression::StaticMemberExpression(m) => (&m.object, m.property.name.as_str()),
+            Expression::ChainExpression(chain) => match &chain.expression {
+                ChainElement::StaticMemberExpression(m) => (&m.object, m.property.name.as_str()),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if !MUTATING_METHODS.contains(&method) {
+            return None;
+        }
+        let root = self.reactive_root(object, None)?;
+        Some((root, format!("`.{}()`", method)))
+    }
+
+    /// A write below the root of a prop or state (`how` names it for diagnostics).
+    /// Props are read-only everywhere: an error in expressions, a warning in event
+    /// handlers. Nested state writes in handlers are tracked as mutations.
+    fn check_nested_mutation(&mut self, root: IdentifierRef, how: &str) {
+        match root {
+            IdentifierRef::PropRef(n) if self.disallow_reactive_access => {
+                self.errors.push(format!(
+                    "Z-ERR-RUN-REACTIVE: Component script attempt to modify reactive prop `{}` ({}) in __run(). Props are read-only.",
+                    n, how
+                ));
+            }
+            IdentifierRef::PropRef(n) if !self.is_event_handler => {
+                self.errors.push(format!(
+                    "Z-ERR-REACTIVITY-BOUNDARY: Prop `{}` mutated ({}) in an expression. Props are read-only.",
+                    n, how
+                ));
+            }
+            IdentifierRef::PropRef(n) => {
+                self.warnings.push(format!(
+                    "Z-WARN-PROP-MUTATION: Event handler mutates prop `{}` ({}); the component that passed it does not see the change. Dispatch an event and update the owner's state instead.",
+                    n, how
+                ));
+            }
+            IdentifierRef::StateRef(n) if self.is_event_handler => {
+                self.mutated_state_deps.insert(n);
+            }
+            _ => {}
+        }
+    }
+
     /// Phase 2: Classify an identifier and return its reference type.
     ///
     /// Classification priority (as defined in lib.rs ground truth):
@@ -528,7 +702,7 @@ impl<'a> ScriptRenamer<'a> {
         let mut names = Vec::new();
         self.collect_binding_names_into(pattern, &mut names);
         for name in &names {
-            self.local_bindings.insert(name.clone());
+            self.register_local_binding(name);
         }
         names
     }
@@ -588,6 +762,16 @@ impl<'a> ScriptRenamer<'a> {
         }
     }
 
+    /// Parameters of a function, including a trailing `...rest`
+    fn collect_param_names(&mut self, params: &FormalParameters<'a>) {
+        for param in &params.items {
+            self.collect_binding_names(&param.pattern);
+        }
+        if let Some(rest) = &params.rest {
+            self.collect_binding_names(&rest.rest.argument);
+        }
+    }
+
     /// Recursively expand a destructuring pattern into explicit assignments to scope.locals.
     fn expand_destructuring_to_assignments(
         &mut self,
@@ -732,7 +916,7 @@ impl<'a> VisitMut<'a> for ScriptRenamer<'a> {
 
                                 // Register AFTER visit_expression to avoid self-shadowing
                                 if is_top_level && !is_state && !is_prop && !is_explicit_local {
-                                    self.local_bindings.insert(name.clone());
+                                    self.register_local_binding(&name);
                                 }
 
                                 let left =
@@ -746,7 +930,7 @@ impl<'a> VisitMut<'a> for ScriptRenamer<'a> {
                             } else {
                                 // Register even if no init
                                 if is_top_level && !is_state && !is_prop && !is_explicit_local {
-                                    self.local_bindings.insert(name.clone());
+                                    self.register_local_binding(&name);
                                 }
                                 // Declaration without initializer - assign undefined
                                 let left =
@@ -837,9 +1021,7 @@ impl<'a> VisitMut<'a> for ScriptRenamer<'a> {
                 // Clone params and body for reuse if we transform
                 // (Actually Oxc allows moving parts if we take ownership)
 
-                for param in &mut func.params.items {
-                    self.collect_binding_names(&param.pattern);
-                }
+                self.collect_param_names(&func.params);
 
                 if let Some(body) = &mut func.body {
                     for s in &mut body.statements {
@@ -929,6 +1111,21 @@ impl<'a> VisitMut<'a> for ScriptRenamer<'a> {
             return;
         }
 
+        // Tagged templates: `${}` reads are walked like any expression; an undeclared
+        // tag gets an error naming its role
+        if let Expression::TaggedTemplateExpression(tagged) = expr {
+            if let Expression::Identifier(tag) = &tagged.tag {
+                if let IdentifierRef::UnresolvedRef(n) = self.classify_identifier(&tag.name) {
+                    self.errors.push(format!(
+                        "Z-ERR-SCOPE-002: Identifier `{}` is used as a template tag but is not declared in state, props, or locals; import it or declare it in the script",
+                        n
+                    ));
+                    self.visit_template_literal(&mut tagged.quasi);
+                    return;
+                }
+            }
+        }
+
         if let Expression::Identifier(id) = expr {
             let name = id.name.to_string();
             match self.classify_identifier(&name) {
@@ -999,9 +1196,7 @@ impl<'a> VisitMut<'a> for ScriptRenamer<'a> {
             let prev_disallow = self.disallow_reactive_access;
             self.disallow_reactive_access = false;
             self.push_scope();
-            for param in &arrow.params.items {
-                self.collect_binding_names(&param.pattern);
-            }
+            self.collect_param_names(&arrow.params);
             for stmt in &mut arrow.body.statements {
                 self.visit_statement(stmt);
             }
@@ -1014,9 +1209,7 @@ impl<'a> VisitMut<'a> for ScriptRenamer<'a> {
             let prev_disallow = self.disallow_reactive_access;
             self.disallow_reactive_access = false;
             self.push_scope();
-            for param in &func.params.items {
-                self.collect_binding_names(&param.pattern);
-            }
+            self.collect_param_names(&func.params);
             if let Some(body) = &mut func.body {
                 for s in &mut body.statements {
                     self.visit_statement(s);
@@ -1027,6 +1220,10 @@ impl<'a> VisitMut<'a> for ScriptRenamer<'a> {
             return;
         }
 
+        if let Some((root, how)) = self.mutating_call_root(expr) {
+            self.check_nested_mutation(root, &how);
+        }
+
         walk_mut::walk_expression(self, expr);
     }
 
@@ -1093,6 +1290,19 @@ impl<'a> VisitMut<'a> for ScriptRenamer<'a> {
                 }
             }
         }
+        // Nested writes (`user.name = x`, `props.tags[0] = x`), before renaming
+        let root = match target {
+            SimpleAssignmentTarget::StaticMemberExpression(m) => {
+                self.reactive_root(&m.object, Some(&m.property.name))
+            }
+            SimpleAssignmentTarget::ComputedMemberExpression(m) => {
+                self.reactive_root(&m.object, None)
+            }
+            _ => None,
+        };
+        if let Some(root) = root {
+            self.check_nested_mutation(root, "field assignment");
+        }
         walk_mut::walk_simple_assignment_target(self, target);
     }
 
@@ -0,0 +1,447 @@
+//! Lazy Handler Chunks
+//!
+//! `<button onclick|lazy={exportCsv}>` (or `zen:lazy` on the element, for all of its
+//! handlers) moves the imports only such handlers need out of the main bundle. The
+//! modifier is normalized before transform into `zen:lazy="onclick"`; a bare
+//! `zen:lazy` covers every `on*` attribute.
+//!
+//! Codegen walks the script's top-level functions from each handler to find the
+//! imports it reaches. An import is split when only lazy handlers reach it: code
+//! that runs at load (other top-level statements, render expressions, other
+//! handlers) keeps it in the main bundle with a `Z-INFO-LAZY-IMPORT-SHARED`
+//! diagnostic. Split imports are re-exported from a `<page>.lazy.js` chunk, their
+//! names become module `let` bindings, and each lazy handler is wrapped to load the
+//! chunk (once, cached) before calling the real handler:
+//!
+//! ```js
+//! (async function (...__args) { await __zenLoadLazy(); return (exportCsv).apply(this, __args); })
+//! ```
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{
+    Expression, IdentifierReference, ImportDeclarationSpecifier, ModuleExportName, Statement,
+};
+use oxc_ast_visit::Visit;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+use crate::transform::LAZY_ATTR;
+use crate::validate::{AttributeIR, AttributeValue, TemplateNode};
+
+/// `onclick|lazy`: the attribute-level form of `zen:lazy`
+pub(crate) const LAZY_MODIFIER: &str = "|lazy";
+
+/// Loads the lazy chunk; every lazy handler awaits it before running
+pub(crate) const LOAD_LAZY_FN: &str = "__zenLoadLazy";
+
+/// An extra module emitted next to the main bundle
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct BundleChunk {
+    /// File name, imported relative to the main bundle (`./about.lazy.js`)
+    pub name: String,
+    pub code: String,
+}
+
+/// Rewrite `onclick|lazy={...}` to `onclick={...}` plus `zen:lazy="onclick"`
+pub(crate) fn normalize_lazy_modifiers(nodes: &mut [TemplateNode]) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                let mut events = Vec::new();
+                for attr in &mut el.attributes {
+                    if let Some(name) = attr.name.strip_suffix(LAZY_MODIFIER) {
+                        attr.name = name.to_string();
+                        events.push(attr.name.clone());
+                    }
+                }
+                if !events.is_empty() {
+                    match el.attributes.iter_mut().find(|a| a.name == LAZY_ATTR) {
+                        // A bare `zen:lazy` already covers every handler
+                        Some(AttributeIR {
+                            value: AttributeValue::Static(existing),
+                            ..
+                        }) if !existing.trim().is_empty() => {
+                            existing.push(' ');
+                            existing.push_str(&events.join(" "));
+                        }
+                        Some(_) => {}
+                        None => {
+                            let location = el.location.clone();
+                            el.attributes.push(AttributeIR {
+                                name: LAZY_ATTR.to_string(),
+                                value: AttributeValue::Static(events.join(" ")),
+                                location,
+                                loop_context: None,
+                                optional: false,
+                                extra: Default::default(),
+                            });
+                        }
+                    }
+                }
+                normalize_lazy_modifiers(&mut el.children);
+            }
+            TemplateNode::Component(c) => normalize_lazy_modifiers(&mut c.children),
+            TemplateNode::ConditionalFragment(cf) => {
+                normalize_lazy_modifiers(&mut cf.consequent);
+                normalize_lazy_modifiers(&mut cf.alternate);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children_mut() {
+                    normalize_lazy_modifiers(nodes);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => normalize_lazy_modifiers(&mut of.fragment),
+            TemplateNode::LoopFragment(lf) => normalize_lazy_modifiers(&mut lf.body),
+            _ => {}
+        }
+    }
+}
+
+/// Expression ids of lazy event handlers
+pub(crate) fn lazy_handler_ids(nodes: &[TemplateNode]) -> HashSet<String> {
+    let mut ids = HashSet::new();
+    collect(nodes, &mut ids);
+    ids
+}
+
+fn collect(nodes: &[TemplateNode], ids: &mut HashSet<String>) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                let lazy = el.attributes.iter().find_map(|a| match &a.value {
+                    AttributeValue::Static(events) if a.name == LAZY_ATTR => Some(events.trim()),
+                    _ => None,
+                });
+                if let Some(events) = lazy {
+                    for attr in &el.attributes {
+                        if let AttributeValue::Dynamic(expr) = &attr.value {
+                            let listed = events.is_empty()
+                                || events.split_whitespace().any(|e| e == attr.name);
+                            if listed && crate::pending::is_event_attr(&attr.name) {
+                                ids.insert(expr.id.clone());
+                            }
+                        }
+                    }
+                }
+                collect(&el.children, ids);
+            }
+            TemplateNode::Component(c) => collect(&c.children, ids),
+            TemplateNode::ConditionalFragment(cf) => {
+                collect(&cf.consequent, ids);
+                collect(&cf.alternate, ids);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    collect(nodes, ids);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => collect(&of.fragment, ids),
+            TemplateNode::LoopFragment(lf) => collect(&lf.body, ids),
+            _ => {}
+        }
+    }
+}
+
+/// Handler source wrapped to load the lazy chunk before its first call
+pub(crate) fn wrap_handler(code: &str) -> String {
+    format!(
+        "(async function (...__args) {{ await {}(); return ({}).apply(this, __args); }})",
+        LOAD_LAZY_FN,
+        code.trim()
+    )
+}
+
+/// Which imports move to the lazy chunk
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LazySplit {
+    /// Local names of the split imports
+    pub locals: HashSet<String>,
+    /// `export ... from` lines of the chunk, in import order
+    pub exports: Vec<String>,
+    pub diagnostics: Vec<String>,
+}
+
+impl LazySplit {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.locals.is_empty()
+    }
+
+    /// Module-level bindings and the cached loader, for the top of the main bundle
+    pub(crate) fn loader(&self, chunk_name: &str) -> String {
+        let mut names: Vec<&String> = self.locals.iter().collect();
+        names.sort();
+        let assignments: Vec<String> = names
+            .iter()
+            .map(|name| format!("{name} = m.{name};", name = name))
+            .collect();
+        format!(
+            "let {};\nlet __zenLazyChunk;\nconst {} = () => __zenLazyChunk || (__zenLazyChunk = import(\"./{}\").then((m) => {{ {} }}));\n",
+            names
+                .iter()
+                .map(|n| n.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            LOAD_LAZY_FN,
+            chunk_name,
+            assignments.join(" ")
+        )
+    }
+
+    pub(crate) fn chunk(&self, chunk_name: &str) -> BundleChunk {
+        BundleChunk {
+            name: chunk_name.to_string(),
+            code: self.exports.join("\n") + "\n",
+        }
+    }
+}
+
+/// `pages/about.zen` → `about.lazy.js`
+pub(crate) fn chunk_name(file_path: &str) -> String {
+    let file = file_path.rsplit(['/', '\\']).next().unwrap_or(file_path);
+    let stem = file.strip_suffix(".zen").unwrap_or(file);
+    format!("{}.lazy.js", stem)
+}
+
+/// Identifiers an AST fragment references (shadowing is ignored, which only
+/// ever keeps an import in the main bundle or loads it needlessly)
+#[derive(Default)]
+struct References(HashSet<String>);
+
+impl<'a> Visit<'a> for References {
+    fn visit_identifier_reference(&mut self, ident: &IdentifierReference<'a>) {
+        self.0.insert(ident.name.to_string());
+    }
+}
+
+/// Plan the split. `script` is the page script with `state`/`prop` already
+/// rewritten to `let`; `lazy` and `eager` are handler and other expression sources.
+pub(crate) fn plan_split(script: &str, lazy: &[&str], eager: &[&str]) -> LazySplit {
+    let mut split = LazySplit::default();
+    if lazy.is_empty() {
+        return split;
+    }
+    let allocator = Allocator::default();
+    let parsed = Parser::new(
+        &allocator,
+        script,
+        SourceType::default()
+            .with_typescript(true)
+            .with_jsx(true)
+            .with_module(true),
+    )
+    .parse();
+
+    // Functions run only when called; everything else at the top level runs at load
+    let mut functions: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut at_load = References::default();
+    for stmt in &parsed.program.body {
+        match stmt {
+            Statement::ImportDeclaration(_) => {}
+            Statement::FunctionDeclaration(decl) => {
+                if let Some(id) = &decl.id {
+                    let mut refs = References::default();
+                    refs.visit_function(decl, oxc_syntax::scope::ScopeFlags::empty());
+                    functions.insert(id.name.to_string(), refs.0);
+                }
+            }
+            Statement::VariableDeclaration(decl) => {
+                for d in &decl.declarations {
+                    let mut refs = References::default();
+                    if let Some(init) = &d.init {
+                        refs.visit_expression(init);
+                    }
+                    let deferred = matches!(
+                        d.init.as_ref().map(|e| e.without_parentheses()),
+                        Some(
+                            Expression::ArrowFunctionExpression(_)
+                                | Expression::FunctionExpression(_)
+                        )
+                    );
+                    match (&d.id, deferred) {
+                        (oxc_ast::ast::BindingPattern::BindingIdentifier(id), true) => {
+                            functions.insert(id.name.to_string(), refs.0);
+                        }
+                        _ => at_load.0.extend(refs.0),
+                    }
+                }
+            }
+            other => at_load.visit_statement(other),
+        }
+    }
+
+    let reach = |roots: HashSet<String>| {
+        let mut seen = roots.clone();
+        let mut queue: Vec<String> = roots.into_iter().collect();
+        while let Some(name) = queue.pop() {
+            for next in functions.get(&name).into_iter().flatten() {
+                if seen.insert(next.clone()) {
+                    queue.push(next.clone());
+                }
+            }
+        }
+        seen
+    };
+    let expression_refs = |sources: &[&str]| {
+        let mut refs = References::default();
+        for source in sources {
+            let allocator = Allocator::default();
+            if let Ok(expr) = Parser::new(&allocator, source, SourceType::tsx()).parse_expression()
+            {
+                refs.visit_expression(&expr);
+            }
+        }
+        refs.0
+    };
+    let lazy_reach = reach(expression_refs(lazy));
+    let mut eager_roots = expression_refs(eager);
+    eager_roots.extend(at_load.0);
+    let eager_reach = reach(eager_roots);
+
+    for stmt in &parsed.program.body {
+        let Statement::ImportDeclaration(decl) = stmt else {
+            continue;
+        };
+        let Some(specifiers) = decl.specifiers.as_ref().filter(|s| !s.is_empty()) else {
+            continue;
+        };
+        if decl.import_kind.is_type() {
+            continue;
+        }
+        let source = decl.source.value.as_str();
+        let mut locals = Vec::new();
+        let mut exports = Vec::new();
+        let mut namespaces = Vec::new();
+        for spec in specifiers {
+            match spec {
+                ImportDeclarationSpecifier::ImportSpecifier(s) => {
+                    if s.import_kind.is_type() {
+                        continue;
+                    }
+                    let imported = match &s.imported {
+                        ModuleExportName::IdentifierName(n) => n.name.to_string(),
+                        ModuleExportName::IdentifierReference(n) => n.name.to_string(),
+                        ModuleExportName::StringLiteral(l) => format!("{:?}", l.value.as_str()),
+                    };
+                    exports.push(format!("{} as {}", imported, s.local.name));
+                    locals.push(s.local.name.to_string());
+                }
+                ImportDeclarationSpecifier::ImportDefaultSpecifier(s) => {
+                    exports.push(format!("default as {}", s.local.name));
+                    locals.push(s.local.name.to_string());
+                }
+                ImportDeclarationSpecifier::ImportNamespaceSpecifier(s) => {
+                    namespaces.push(format!("export * as {} from {:?};", s.local.name, source));
+                    locals.push(s.local.name.to_string());
+                }
+            }
+        }
+        if !locals.iter().any(|l| lazy_reach.contains(l)) {
+            continue;
+        }
+        let shared: BTreeSet<&String> =
+            locals.iter().filter(|l| eager_reach.contains(*l)).collect();
+        if !shared.is_empty() {
+            split.diagnostics.push(format!(
+                "Z-INFO-LAZY-IMPORT-SHARED: `{}` stays in the main bundle: {} also used outside lazy handlers.",
+                source,
+                shared
+                    .iter()
+                    .map(|l| format!("`{}` is", l))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            continue;
+        }
+        if !exports.is_empty() {
+            split.exports.push(format!(
+                "export {{ {} }} from {:?};",
+                exports.join(", "),
+                source
+            ));
+        }
+        split.exports.extend(namespaces);
+        split.locals.extend(locals);
+    }
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(page: &str) -> CompileResult {
+        compile_zen_internal(page, "pages/report.zen", CompileOptions::default()).unwrap()
+    }
+
+    const SCRIPT: &str = "<script>\nimport Papa from 'papaparse'\nimport { format } from 'date-fns'\nstate rows = []\nfunction download(text) { console.log(text) }\nfunction exportCsv() { download(Papa.unparse(rows)) }\n</script>\n";
+
+    #[test]
+    fn test_lazy_handler_import_moves_to_chunk() {
+        let result = compile(&format!(
+            "{}<button onclick|lazy={{exportCsv}}>Export</button><p>{{format(0, 'y')}}</p>",
+            SCRIPT
+        ));
+        assert!(!result.html.contains("lazy"), "{}", result.html);
+        let manifest = result.manifest.unwrap();
+        assert!(
+            !manifest.bundle.contains("from \"papaparse\""),
+            "{}",
+            manifest.bundle
+        );
+        assert!(!manifest.npm_imports.contains("papaparse"));
+        assert!(manifest.bundle.contains("from \"date-fns\""));
+        assert_eq!(manifest.chunks.len(), 1);
+        assert_eq!(manifest.chunks[0].name, "report.lazy.js");
+        assert_eq!(
+            manifest.chunks[0].code,
+            "export { default as Papa } from \"papaparse\";\n"
+        );
+        assert!(manifest.bundle.contains("let Papa;"), "{}", manifest.bundle);
+        assert!(manifest.bundle.contains("import(\"./report.lazy.js\")"));
+    }
+
+    #[test]
+    fn test_wrapper_awaits_chunk_before_calling() {
+        let result = compile(&format!(
+            "{}<button onclick={{exportCsv}} zen:lazy>Export</button>",
+            SCRIPT
+        ));
+        let bundle = result.manifest.unwrap().bundle;
+        let wrapper = bundle
+            .find("async function(...__args)")
+            .map(|at| &bundle[at..])
+            .unwrap_or_else(|| panic!("{}", bundle));
+        let load = wrapper.find("await __zenLoadLazy()").unwrap();
+        let call = wrapper
+            .find("scope.locals.exportCsv.apply(this, __args)")
+            .unwrap();
+        assert!(load < call, "{}", wrapper);
+    }
+
+    #[test]
+    fn test_shared_import_stays_in_main_bundle() {
+        let result = compile(&format!(
+            "{}<button onclick|lazy={{exportCsv}}>Export</button><p>{{Papa.version}}</p>",
+            SCRIPT
+        ));
+        let manifest = result.manifest.unwrap();
+        assert!(manifest.bundle.contains("from \"papaparse\""));
+        assert!(manifest.chunks.is_empty());
+        assert!(!manifest.bundle.contains("__zenLoadLazy"));
+        assert!(
+            result.warnings.iter().any(|w| w
+                == "Z-INFO-LAZY-IMPORT-SHARED: `papaparse` stays in the main bundle: `Papa` is also used outside lazy handlers."),
+            "{:?}",
+            result.warnings
+        );
+    }
+}
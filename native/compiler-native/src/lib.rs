@@ -36,46 +36,112 @@
 #[cfg(feature = "napi")]
 use napi_derive::napi;
 
+// Declared first so the logging macros are visible to every module below
+#[macro_use]
+mod trace;
+
+mod attr_coerce;
+mod binding_table;
+mod breakpoints;
+mod cancel;
+mod class_names;
+mod classify;
+mod client_directives;
 mod codegen;
+mod compat;
 mod component;
+mod content_audit;
+mod css_scope;
+mod css_usage;
+mod define_vars;
+mod dep_graph;
+mod depth;
+mod diagnostics;
 mod discovery;
 mod document;
-
+mod embed;
+mod env;
+mod es_target;
+mod event_value;
+mod expr_complexity;
+mod expr_ids;
+mod filters;
 mod finalize;
+mod global_styles;
+mod handlers;
+mod html_tables;
+mod hydration_mismatch;
+mod i18n;
+mod instances;
+mod invariants;
+mod ir_compat;
+mod islands;
 mod jsx_lowerer;
+mod lazy;
+mod live_regions;
+mod loop_keys;
+mod loop_shapes;
+mod minify;
+mod module;
+mod navigation;
+mod nullish;
+mod observers;
 
 mod parse;
+mod pending;
+mod portal;
+mod preload;
+mod reconcile;
+mod registry;
+#[cfg(feature = "schema")]
+mod schema;
+mod script_cache;
+mod script_collisions;
+mod script_syntax;
+mod select_options;
+mod ssr_safety;
 mod static_eval;
+mod strip_debug;
+mod style_chunks;
+mod style_props;
+mod svg_sprite;
 mod transform;
 mod validate;
+mod virtualize;
+#[cfg(feature = "napi")]
+mod wire;
+mod zen_assert;
 
 #[cfg(test)]
 mod safety_tests;
 
+pub mod prelude;
+
+// The supported surface lives in `prelude`; it is also available from the crate root.
+pub use prelude::*;
+
+// NAPI wrappers only exist when the feature is enabled, so their re-exports are gated too
 #[cfg(feature = "napi")]
 pub use codegen::generate_codegen_intent;
-// Re-export native NAPI-wrappers only if NAPI is enabled?
-// Actually if they are gated in their respective files, re-exporting them here requires they exist.
-// But some are pub fns. If I gate them in `parse.rs`, `parse_template_native` won't exist if feature is off.
-// So re-exports will fail.
-// I should gate the re-exports too if they are NAPI-specific.
-
 #[cfg(feature = "napi")]
-pub use parse::parse_full_zen_native;
-
-// Internal Rust-to-Rust API (for Rolldown plugin)
-pub use parse::{compile_zen_internal, CompileOptions, CompileResult};
-
-// Re-export types for the bundler
-pub use finalize::ZenManifestExport;
-pub use transform::Binding;
-// These seem to be internal logic, maybe not napi-gated?
-// transform_template_native might be NAPI?
-// classify_expression_native might be NAPI?
-// Let's check transform.rs
+pub use html_tables::get_default_html_tables_native;
+#[cfg(feature = "napi")]
+pub use parse::{parse_full_zen_native, parse_zen_template_native};
+#[cfg(feature = "schema")]
+pub use schema::get_json_schemas_native;
 #[cfg(feature = "napi")]
-pub use transform::transform_template_native;
-pub use validate::*;
+pub use transform::{transform_nodes_native, transform_template_native};
+#[cfg(feature = "napi")]
+pub use wire::{parse_full_zen_batch_msgpack, parse_full_zen_msgpack};
+
+/// Compatibility re-exports of root paths that predate `prelude`.
+/// Kept for one release; new code should not depend on them.
+#[doc(hidden)]
+pub use validate::{
+    ExpressionInput, HeadDirective, IdentifierCategory, LinkTag, LoopContextInput, MetaTag,
+    INV_ATTRIBUTE_NOT_FORWARDED, INV_LOOP_CONTEXT_LOST, INV_REACTIVE_BOUNDARY,
+    INV_REACTIVITY_BOUNDARY, INV_RUN_REACTIVE, INV_UNRESOLVED_IDENTIFIER,
+};
 
 #[cfg(feature = "napi")]
 #[napi]
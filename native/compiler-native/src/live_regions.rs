@@ -0,0 +1,233 @@
+//! Live Regions
+//!
+//! Screen readers announce changes inside an `aria-live` region (any value but
+//! `off`, or a dynamic one), so its text must not change at hydration and should
+//! change in place afterwards. Inside a region, on the element itself or an
+//! ancestor (components are inlined by then, so a component's own region counts
+//! for its markup), transform:
+//!
+//! - renders a text binding's initial value after its `<!--zen:id-->` marker when
+//!   it folds at compile time (as `zen:static` text does), so the first client
+//!   render finds the text already there
+//! - marks the binding `live_region`, so the runtime updates that text node instead
+//!   of replacing what follows the marker
+//!
+//! A conditional, optional or loop fragment in a region adds and removes nodes,
+//! which is announced as a whole however the text is updated:
+//! `Z-WARN-LIVE-REGION-STRUCTURE`. A region is warned about once per fragment;
+//! nothing nested in a warned fragment is checked again.
+
+use crate::validate::{AttributeIR, AttributeValue, TemplateNode, ZenIR};
+
+/// Whether `attributes` open a live region (`Some(true)`), close one with
+/// `aria-live="off"` (`Some(false)`) or leave the enclosing state (`None`)
+pub(crate) fn live_region_attr(attributes: &[AttributeIR]) -> Option<bool> {
+    attributes
+        .iter()
+        .find(|a| a.name.eq_ignore_ascii_case("aria-live"))
+        .map(|a| match &a.value {
+            AttributeValue::Static(value) => !value.trim().eq_ignore_ascii_case("off"),
+            AttributeValue::Dynamic(_) => true,
+        })
+}
+
+/// Warn for fragments that restructure a live region
+pub(crate) fn check_live_regions(ir: &mut ZenIR) {
+    let mut warnings = Vec::new();
+    walk(&ir.template.nodes, None, ir, &mut warnings);
+    ir.warnings.extend(warnings);
+}
+
+fn walk(nodes: &[TemplateNode], region: Option<&str>, ir: &ZenIR, warnings: &mut Vec<String>) {
+    for node in nodes {
+        let fragment = match node {
+            TemplateNode::Element(el) => {
+                let region = match live_region_attr(&el.attributes) {
+                    Some(true) => Some(el.tag.as_str()),
+                    Some(false) => None,
+                    None => region,
+                };
+                walk(&el.children, region, ir, warnings);
+                continue;
+            }
+            TemplateNode::Component(c) => {
+                walk(&c.children, region, ir, warnings);
+                continue;
+            }
+            TemplateNode::ConditionalFragment(cf) => Some(("conditional", cf.condition.as_str())),
+            TemplateNode::MultiBranchFragment(mb) => mb
+                .branches
+                .first()
+                .map(|b| ("conditional", b.condition.as_str())),
+            TemplateNode::OptionalFragment(of) => {
+                Some(("optional fragment", of.condition.as_str()))
+            }
+            TemplateNode::LoopFragment(lf) => Some(("loop", lf.source.as_str())),
+            TemplateNode::Text(_) | TemplateNode::Expression(_) | TemplateNode::Doctype(_) => {
+                continue
+            }
+        };
+        match (region, fragment) {
+            (Some(tag), Some((kind, condition))) => {
+                let code = ir
+                    .template
+                    .expressions
+                    .iter()
+                    .find(|e| e.id == condition)
+                    .map_or(condition, |e| e.code.as_str());
+                warnings.push(format!(
+                    "Z-WARN-LIVE-REGION-STRUCTURE: the {} on `{}` in the aria-live region <{}> in {} adds and removes nodes, which screen readers announce as a whole; keep the region's markup fixed and bind text inside it.",
+                    kind,
+                    code.trim(),
+                    tag,
+                    ir.file_path
+                ));
+            }
+            _ => {
+                for children in fragment_children(node) {
+                    walk(children, region, ir, warnings);
+                }
+            }
+        }
+    }
+}
+
+fn fragment_children(node: &TemplateNode) -> Vec<&Vec<TemplateNode>> {
+    match node {
+        TemplateNode::ConditionalFragment(cf) => vec![&cf.consequent, &cf.alternate],
+        TemplateNode::MultiBranchFragment(mb) => mb.children().collect(),
+        TemplateNode::OptionalFragment(of) => vec![&of.fragment],
+        TemplateNode::LoopFragment(lf) => vec![&lf.body],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(source: &str) -> CompileResult {
+        let options = CompileOptions {
+            ast_classifier: true,
+            ..Default::default()
+        };
+        let result = compile_zen_internal(source, "page.zen", options).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        result
+    }
+
+    const STATUS: &str = "<script>\nstate status = \"Saved\"\nstate count = 2\n</script>\n";
+
+    #[test]
+    fn test_binding_in_live_region_is_flagged_and_rendered() {
+        let result = compile(&format!(
+            "{}<main><div aria-live=\"polite\"><p>{{status}}</p></div><p>{{count}}</p></main>",
+            STATUS
+        ));
+        let status = result
+            .bindings
+            .iter()
+            .find(|b| b.expression.contains("status"))
+            .unwrap();
+        assert!(status.live_region);
+        assert!(
+            result
+                .html
+                .contains(&format!("<p><!--zen:{}-->Saved</p>", status.id)),
+            "{}",
+            result.html
+        );
+        assert!(
+            !result
+                .warnings
+                .iter()
+                .any(|w| w.starts_with("Z-WARN-LIVE-REGION")),
+            "{:?}",
+            result.warnings
+        );
+
+        // Outside the region: marker only, no flag
+        let count = result
+            .bindings
+            .iter()
+            .find(|b| b.expression.contains("count"))
+            .unwrap();
+        assert!(!count.live_region);
+        assert!(
+            result
+                .html
+                .contains(&format!("<p><!--zen:{}--></p>", count.id)),
+            "{}",
+            result.html
+        );
+    }
+
+    #[test]
+    fn test_region_on_the_element_and_off_switch() {
+        let result = compile(&format!(
+            "{}<main><output aria-live=\"assertive\">{{status}}</output><section aria-live=\"polite\"><p aria-live=\"off\">{{count}}</p></section></main>",
+            STATUS
+        ));
+        let flags: Vec<(bool, &str)> = result
+            .bindings
+            .iter()
+            .filter(|b| b.r#type == "text")
+            .map(|b| (b.live_region, b.expression.as_str()))
+            .collect();
+        assert_eq!(flags.len(), 2);
+        assert!(flags
+            .iter()
+            .all(|(live, code)| *live == code.contains("status")));
+        assert!(result.html.contains("-->Saved</output>"), "{}", result.html);
+    }
+
+    #[test]
+    fn test_component_region_covers_its_markup() {
+        let toast = crate::component::ComponentIR::from_source(
+            "Toast",
+            "components/Toast.zen",
+            "<div class=\"toast\" aria-live=\"polite\"><span>{message}</span></div>\n<script>\nprop message\n</script>",
+        )
+        .unwrap();
+        let options = CompileOptions {
+            components: [("Toast".to_string(), serde_json::to_value(toast).unwrap())].into(),
+            ..Default::default()
+        };
+        let result = compile_zen_internal(
+            &format!(
+                "{}<main><Toast message={{status}} /><p>{{count}}</p></main>",
+                STATUS
+            ),
+            "page.zen",
+            options,
+        )
+        .unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let live: Vec<bool> = result
+            .bindings
+            .iter()
+            .filter(|b| b.r#type == "text")
+            .map(|b| b.live_region)
+            .collect();
+        assert_eq!(live, [true, false], "{:?}", result.bindings);
+    }
+
+    #[test]
+    fn test_structural_fragment_in_live_region_warns() {
+        let result = compile(
+            "<script>\nstate items = [\"a\", \"b\"]\nstate done = false\n</script>\n<main><ul aria-live=\"polite\">{items.map(item => <li>{item}</li>)}</ul><div role=\"status\" aria-live=\"polite\">{done && <p>Done</p>}</div><ol>{items.map(item => <li>{item}</li>)}</ol></main>",
+        );
+        let warnings: Vec<&String> = result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("Z-WARN-LIVE-REGION-STRUCTURE"))
+            .collect();
+        assert_eq!(warnings.len(), 2, "{:?}", result.warnings);
+        assert!(
+            warnings[0].contains("the loop on `items` in the aria-live region <ul>"),
+            "{}",
+            warnings[0]
+        );
+        assert!(warnings[1].contains("<div>"), "{}", warnings[1]);
+    }
+}
@@ -0,0 +1,356 @@
+//! Loop Key Analysis
+//!
+//! A `key` on the root element of a loop body identifies an item across
+//! re-renders. Keys that change on every render, or repeat within one list,
+//! defeat reconciliation, so every loop with a key expression is checked:
+//!
+//! - the index variable as the key (shifts when the list is reordered): warning
+//! - `Math.random()`, `Date.now()`, `new Date()`, or a call to a function outside
+//!   the pure whitelist: warning
+//! - sibling loops over the same source with the same key expression, whose keys
+//!   collide in the shared DOM parent: warning
+//! - a statically known source (an array literal, or a page `state` / `const`
+//!   initialized with one) whose evaluated keys repeat: error naming the value
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{
+    CallExpression, Expression, NewExpression, ObjectPropertyKind, PropertyKey, Statement,
+};
+use oxc_ast_visit::Visit;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+use oxc_syntax::identifier::is_identifier_name;
+use std::collections::{HashMap, HashSet};
+
+use crate::validate::{AttributeValue, LoopFragmentNode, TemplateNode, ZenIR};
+
+/// Attribute on a loop body's root element that identifies the item
+pub const KEY_ATTR: &str = "key";
+
+/// Member calls that return a new value on every call
+const UNSTABLE_CALLS: &[&str] = &[
+    "Math.random",
+    "Date.now",
+    "performance.now",
+    "crypto.randomUUID",
+    "crypto.getRandomValues",
+];
+
+/// Warnings and errors for every keyed loop of the page
+pub(crate) fn check_loop_keys(ir: &ZenIR) -> (Vec<String>, Vec<String>) {
+    let mut checker = KeyChecker {
+        ir,
+        consts: ir
+            .script
+            .as_ref()
+            .map(|s| crate::document::extract_const_declarations(&s.raw))
+            .unwrap_or_default(),
+        warnings: Vec::new(),
+        errors: Vec::new(),
+    };
+    checker.check_nodes(&ir.template.nodes, &mut Vec::new());
+    (checker.warnings, checker.errors)
+}
+
+struct KeyChecker<'a> {
+    ir: &'a ZenIR,
+    /// Page `const` initializers, for statically known loop sources
+    consts: HashMap<String, String>,
+    warnings: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl KeyChecker<'_> {
+    /// `siblings` holds (source, key) of the keyed loops rendered into the current
+    /// DOM parent; fragments are transparent, elements start a new parent
+    fn check_nodes(&mut self, nodes: &[TemplateNode], siblings: &mut Vec<(String, String)>) {
+        for node in nodes {
+            match node {
+                TemplateNode::LoopFragment(lf) => {
+                    if let Some(key) = loop_key(lf) {
+                        let source = self.code(&lf.source);
+                        self.check_key(lf, &source, &key);
+                        if siblings.iter().any(|(s, k)| *s == source && *k == key) {
+                            self.warnings.push(format!(
+                                "Z-WARN-LOOP-KEY-COLLISION: loops over `{}` share the key `{}` in the same parent ({}); their keys collide. Prefix one of them (e.g. `'a-' + {}`).",
+                                source, key, self.location(lf), key
+                            ));
+                        }
+                        siblings.push((source, key));
+                    }
+                    self.check_nodes(&lf.body, &mut Vec::new());
+                }
+                TemplateNode::Element(el) => self.check_nodes(&el.children, &mut Vec::new()),
+                TemplateNode::Component(c) => self.check_nodes(&c.children, &mut Vec::new()),
+                TemplateNode::ConditionalFragment(cf) => {
+                    self.check_nodes(&cf.consequent, siblings);
+                    self.check_nodes(&cf.alternate, siblings);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    for nodes in mb.children() {
+                        self.check_nodes(nodes, siblings);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => self.check_nodes(&of.fragment, siblings),
+                _ => {}
+            }
+        }
+    }
+
+    fn check_key(&mut self, lf: &LoopFragmentNode, source: &str, key: &str) {
+        let location = self.location(lf);
+        let identifiers = crate::finalize::referenced_identifiers(key);
+        let item_var = lf.item_var.trim();
+        if let Some(index) = lf.index_var.as_deref().map(str::trim) {
+            if identifiers.iter().any(|n| n == index) && !identifiers.iter().any(|n| n == item_var)
+            {
+                self.warnings.push(format!(
+                    "Z-WARN-LOOP-KEY-INDEX: key `{}` of the loop over `{}` in {} is the loop index; it shifts when the list is reordered or filtered. Key by a stable id of the item instead.",
+                    key, source, location
+                ));
+            }
+        }
+        if let Some(call) = unstable_call(key) {
+            self.warnings.push(format!(
+                "Z-WARN-LOOP-KEY-UNSTABLE: key `{}` of the loop over `{}` in {} calls `{}`, which can return a different value on every render. Key by a stable id of the item instead.",
+                key, source, location, call
+            ));
+        }
+
+        let Some(keys) = self.static_keys(source, item_var, key) else {
+            return;
+        };
+        let mut seen = HashSet::new();
+        let mut reported = HashSet::new();
+        for value in &keys {
+            if !seen.insert(value) && reported.insert(value) {
+                self.errors.push(format!(
+                    "Z-ERR-LOOP-KEY-DUPLICATE: key `{}` of the loop over `{}` in {} is \"{}\" for more than one item; keys must be unique within a list.",
+                    key, source, location, value
+                ));
+            }
+        }
+    }
+
+    /// Keys of a statically known source when the key is a property path of the item
+    fn static_keys(&self, source: &str, item_var: &str, key: &str) -> Option<Vec<String>> {
+        let source = source.trim();
+        let array = if source.starts_with('[') {
+            source
+        } else {
+            self.ir
+                .all_states
+                .get(source)
+                .or_else(|| self.consts.get(source))?
+                .as_str()
+        };
+        let path: Vec<&str> = key.trim().split('.').map(str::trim).collect();
+        if path[0] != item_var || path.iter().any(|p| !is_identifier_name(p)) {
+            return None;
+        }
+
+        let allocator = Allocator::default();
+        let wrapped = format!("({})", array);
+        let ret = Parser::new(&allocator, &wrapped, SourceType::default()).parse();
+        if !ret.errors.is_empty() {
+            return None;
+        }
+        let Some(Statement::ExpressionStatement(stmt)) = ret.program.body.first() else {
+            return None;
+        };
+        let Expression::ArrayExpression(array) = stmt.expression.without_parentheses() else {
+            return None;
+        };
+        array
+            .elements
+            .iter()
+            .map(|element| {
+                let mut value = element.as_expression()?;
+                for name in &path[1..] {
+                    value = property(value, name)?;
+                }
+                literal_value(value)
+            })
+            .collect()
+    }
+
+    /// Loop sources are registry ids; a source that is not registered is its own code
+    fn code(&self, source: &str) -> String {
+        self.ir
+            .template
+            .expressions
+            .iter()
+            .find(|e| e.id == source)
+            .map_or(source, |e| e.code.as_str())
+            .trim()
+            .to_string()
+    }
+
+    fn location(&self, lf: &LoopFragmentNode) -> String {
+        format!(
+            "{} (line {}, column {})",
+            self.ir.file_path, lf.location.line, lf.location.column
+        )
+    }
+}
+
+/// Dynamic `key` of the first element in the loop body
+fn loop_key(lf: &LoopFragmentNode) -> Option<String> {
+    lf.body.iter().find_map(|node| match node {
+        TemplateNode::Element(el) => el.attributes.iter().find_map(|a| match &a.value {
+            AttributeValue::Dynamic(expr) if a.name == KEY_ATTR => {
+                Some(expr.code.trim().to_string())
+            }
+            _ => None,
+        }),
+        _ => None,
+    })
+}
+
+#[derive(Default)]
+struct CallCollector {
+    /// `name`, `object.name`, or `new Name` for every call in the expression
+    calls: Vec<String>,
+}
+
+impl<'a> Visit<'a> for CallCollector {
+    fn visit_call_expression(&mut self, call: &CallExpression<'a>) {
+        match &call.callee {
+            Expression::Identifier(ident) => self.calls.push(ident.name.to_string()),
+            Expression::StaticMemberExpression(member) => {
+                if let Expression::Identifier(object) = &member.object {
+                    self.calls
+                        .push(format!("{}.{}", object.name, member.property.name));
+                }
+            }
+            _ => {}
+        }
+        oxc_ast_visit::walk::walk_call_expression(self, call);
+    }
+
+    fn visit_new_expression(&mut self, new: &NewExpression<'a>) {
+        if let Expression::Identifier(ident) = &new.callee {
+            if ident.name == "Date" {
+                self.calls.push("new Date".to_string());
+            }
+        }
+        oxc_ast_visit::walk::walk_new_expression(self, new);
+    }
+}
+
+/// First call in `code` whose result may differ between renders
+fn unstable_call(code: &str) -> Option<String> {
+    let allocator = Allocator::default();
+    let source_type = SourceType::default().with_jsx(true).with_typescript(true);
+    let ret = Parser::new(&allocator, code, source_type).parse();
+    let mut collector = CallCollector::default();
+    collector.visit_program(&ret.program);
+    collector.calls.into_iter().find(|call| {
+        UNSTABLE_CALLS.contains(&call.as_str())
+            || call == "new Date"
+            || (!call.contains('.') && !crate::static_eval::is_pure_function(call))
+    })
+}
+
+fn property<'e, 'a>(value: &'e Expression<'a>, name: &str) -> Option<&'e Expression<'a>> {
+    let Expression::ObjectExpression(object) = value.without_parentheses() else {
+        return None;
+    };
+    // The last definition of a property wins
+    object.properties.iter().rev().find_map(|p| match p {
+        ObjectPropertyKind::ObjectProperty(prop) => {
+            let key = match &prop.key {
+                PropertyKey::StaticIdentifier(ident) => ident.name.as_str(),
+                PropertyKey::StringLiteral(lit) => lit.value.as_str(),
+                _ => return None,
+            };
+            (key == name).then_some(&prop.value)
+        }
+        _ => None,
+    })
+}
+
+/// Key value of a literal, as the runtime would stringify it
+fn literal_value(value: &Expression) -> Option<String> {
+    match value.without_parentheses() {
+        Expression::StringLiteral(lit) => Some(lit.value.to_string()),
+        Expression::NumericLiteral(lit) if lit.value.fract() == 0.0 && lit.value.abs() < 1e15 => {
+            Some((lit.value as i64).to_string())
+        }
+        Expression::NumericLiteral(lit) => Some(lit.value.to_string()),
+        Expression::BooleanLiteral(lit) => Some(lit.value.to_string()),
+        Expression::NullLiteral(_) => Some("null".to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions};
+
+    /// Compiles with fragment lowering so `.map()` becomes a loop fragment
+    fn compile(source: &str) -> crate::parse::CompileResult {
+        compile_zen_internal(
+            source,
+            "page.zen",
+            CompileOptions {
+                ast_classifier: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn key_warnings(result: &crate::parse::CompileResult) -> Vec<&String> {
+        result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("Z-WARN-LOOP-KEY"))
+            .collect()
+    }
+
+    #[test]
+    fn test_index_key_warns() {
+        let result = compile("<script>\nstate items = []\n</script>\n<ul>{items.map((item, i) => <li key={i}>{item.name}</li>)}</ul>");
+        let warnings = key_warnings(&result);
+        assert_eq!(warnings.len(), 1, "{:?}", result.warnings);
+        assert!(warnings[0].starts_with(
+            "Z-WARN-LOOP-KEY-INDEX: key `i` of the loop over `items` in page.zen (line"
+        ));
+    }
+
+    #[test]
+    fn test_random_key_warns() {
+        let result = compile("<script>\nstate items = []\n</script>\n<ul>{items.map(item => <li key={Math.random()}>{item.name}</li>)}</ul>");
+        let warnings = key_warnings(&result);
+        assert_eq!(warnings.len(), 1, "{:?}", result.warnings);
+        assert!(warnings[0].starts_with("Z-WARN-LOOP-KEY-UNSTABLE: key `Math.random()`"));
+        assert!(warnings[0].contains("calls `Math.random`"));
+    }
+
+    #[test]
+    fn test_static_duplicate_keys_error() {
+        let result = compile("<script>\nstate items = [{ id: 1, name: \"a\" }, { id: 2, name: \"b\" }, { id: 1, name: \"c\" }]\n</script>\n<ul>{items.map(item => <li key={item.id}>{item.name}</li>)}</ul>");
+        assert!(result.has_errors);
+        assert_eq!(
+            result
+                .errors
+                .iter()
+                .filter(|e| e.starts_with("Z-ERR-LOOP-KEY-DUPLICATE"))
+                .count(),
+            1,
+            "{:?}",
+            result.errors
+        );
+        assert!(result.errors.iter().any(|e| e
+            .contains("key `item.id` of the loop over `items` in page.zen (line")
+            && e.contains("is \"1\" for more than one item")));
+    }
+
+    #[test]
+    fn test_stable_id_key_is_clean() {
+        let result = compile("<script>\nstate items = [{ id: 1, name: \"a\" }, { id: 2, name: \"b\" }]\n</script>\n<ul>{items.map(item => <li key={item.id}>{item.name}</li>)}</ul>");
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(key_warnings(&result).is_empty(), "{:?}", result.warnings);
+    }
+}
@@ -0,0 +1,466 @@
+//! Loop Item Shapes
+//!
+//! A loop over a statically known list (see `virtualize::StaticLoops`, or a
+//! document scope value holding a JSON array) has items of a known shape, so a
+//! misspelled field in the loop body (`{post.titel}`) can be caught at build time
+//! instead of rendering empty. When every item is an object, the keys found on
+//! any item are the loop's item keys, and a direct member access on the item
+//! variable (`post.titel`, not `post[key]`) naming none of them is
+//! `Z-WARN-LOOP-ITEM-KEY`, with the closest key as a suggestion.
+//!
+//! Nested access (`post.author.nmae`) is checked past the first level only when
+//! every item has an object with the same keys there. Runtime sources, empty
+//! lists and lists of anything but objects are not checked. The key sets are
+//! kept per loop for the manifest, with the loop source as bindings in the body
+//! carry it in `LoopContext.map_source`.
+//!
+//! Only loop fragments are checked, so like the other loop analyses this needs
+//! `CompileOptions.ast_classifier` (see `classify`); without it a `.map()` stays
+//! an expression rendered at runtime and is not checked.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{ArrowFunctionExpression, Expression, FormalParameters, StaticMemberExpression};
+use oxc_ast_visit::{walk, Visit};
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+use crate::validate::{AttributeValue, LoopFragmentNode, TemplateNode, ZenIR};
+use crate::virtualize::StaticLoops;
+
+/// Keys every object has through its prototype, never reported
+const PROTOTYPE_KEYS: &[&str] = &[
+    "constructor",
+    "hasOwnProperty",
+    "isPrototypeOf",
+    "propertyIsEnumerable",
+    "toLocaleString",
+    "toString",
+    "valueOf",
+];
+
+/// Manifest entry: the item keys of a loop over a statically known list
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct LoopItemKeys {
+    /// Id of the loop source expression
+    pub source: String,
+    /// The loop source as written (`LoopContext.map_source` in the body)
+    pub expression: String,
+    /// The item variable
+    pub item: String,
+    /// Keys present on at least one item, sorted
+    pub keys: Vec<String>,
+}
+
+/// Record the item keys of every loop over static objects in `ir.loop_item_keys`
+/// and warn on item member access naming none of them. `scope` holds the
+/// document's static values (see `define_vars::static_values`).
+pub(crate) fn check_loop_item_keys(ir: &mut ZenIR, scope: &HashMap<String, String>) {
+    let (warnings, loops) = {
+        let mut checker = ShapeChecker {
+            loops: StaticLoops::new(ir),
+            scope,
+            warnings: Vec::new(),
+            reported: HashSet::new(),
+            keys: Vec::new(),
+        };
+        checker.check_nodes(&ir.template.nodes);
+        (checker.warnings, checker.keys)
+    };
+    ir.warnings.extend(warnings);
+    ir.loop_item_keys = loops;
+}
+
+struct ShapeChecker<'a> {
+    loops: StaticLoops<'a>,
+    scope: &'a HashMap<String, String>,
+    warnings: Vec<String>,
+    /// (loop source, accessed path) already warned about
+    reported: HashSet<(String, String)>,
+    keys: Vec<LoopItemKeys>,
+}
+
+impl ShapeChecker<'_> {
+    fn check_nodes(&mut self, nodes: &[TemplateNode]) {
+        for node in nodes {
+            match node {
+                TemplateNode::LoopFragment(lf) => {
+                    self.check_loop(lf);
+                    self.check_nodes(&lf.body);
+                }
+                TemplateNode::Element(el) => self.check_nodes(&el.children),
+                TemplateNode::Component(c) => self.check_nodes(&c.children),
+                TemplateNode::ConditionalFragment(cf) => {
+                    self.check_nodes(&cf.consequent);
+                    self.check_nodes(&cf.alternate);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    for nodes in mb.children() {
+                        self.check_nodes(nodes);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => self.check_nodes(&of.fragment),
+                _ => {}
+            }
+        }
+    }
+
+    /// Items of the loop source when it is a non-empty list of objects
+    fn object_items(&self, code: &str) -> Option<Vec<Value>> {
+        let items = self.loops.static_items(code).or_else(|| {
+            let name = code.strip_prefix("props.").unwrap_or(code);
+            match serde_json::from_str(self.scope.get(name)?).ok()? {
+                Value::Array(items) => Some(items),
+                _ => None,
+            }
+        })?;
+        (!items.is_empty() && items.iter().all(Value::is_object)).then_some(items)
+    }
+
+    fn check_loop(&mut self, lf: &LoopFragmentNode) {
+        let code = self.loops.code(&lf.source);
+        let Some(items) = self.object_items(&code) else {
+            return;
+        };
+        let item = lf.item_var.trim();
+        let keys: BTreeSet<String> = items
+            .iter()
+            .filter_map(Value::as_object)
+            .flat_map(|o| o.keys().cloned())
+            .collect();
+        self.keys.push(LoopItemKeys {
+            source: lf.source.clone(),
+            expression: code.clone(),
+            item: item.to_string(),
+            keys: keys.iter().cloned().collect(),
+        });
+
+        let mut codes = Vec::new();
+        self.body_codes(&lf.body, item, &mut codes);
+        for body_code in codes {
+            let mut accesses = ItemAccesses {
+                item,
+                shadowed: 0,
+                paths: Vec::new(),
+            };
+            let allocator = Allocator::default();
+            let source_type = SourceType::default().with_jsx(true).with_typescript(true);
+            // Code oxc rejects is reported elsewhere
+            if let Ok(expr) = Parser::new(&allocator, &body_code, source_type).parse_expression() {
+                accesses.visit_expression(&expr);
+            }
+            // The expression's text in the page is more precise than the loop's location
+            let base = self.loops.ir.template.raw.find(body_code.as_str());
+            for (path, start) in accesses.paths {
+                let (line, column) = match base {
+                    Some(base) => {
+                        let (line, column) = crate::content_audit::line_column(
+                            &self.loops.ir.template.raw,
+                            base + start as usize,
+                        );
+                        (line as u32, column as u32)
+                    }
+                    None => (lf.location.line, lf.location.column),
+                };
+                self.check_access(lf, &code, &items, &path, (line, column));
+            }
+        }
+    }
+
+    /// Codes of the expressions in a loop body, down to a nested loop that
+    /// rebinds the item variable
+    fn body_codes(&self, nodes: &[TemplateNode], item: &str, codes: &mut Vec<String>) {
+        for node in nodes {
+            match node {
+                TemplateNode::Expression(e) => codes.push(self.loops.code(&e.expression)),
+                TemplateNode::Element(el) => {
+                    for attr in &el.attributes {
+                        if let AttributeValue::Dynamic(expr) = &attr.value {
+                            codes.push(self.loops.code(&expr.id));
+                        }
+                    }
+                    self.body_codes(&el.children, item, codes);
+                }
+                TemplateNode::Component(c) => self.body_codes(&c.children, item, codes),
+                TemplateNode::LoopFragment(lf) => {
+                    codes.push(self.loops.code(&lf.source));
+                    let rebinds = lf.item_var.trim() == item
+                        || lf.index_var.as_deref().map(str::trim) == Some(item);
+                    if !rebinds {
+                        self.body_codes(&lf.body, item, codes);
+                    }
+                }
+                TemplateNode::ConditionalFragment(cf) => {
+                    codes.push(self.loops.code(&cf.condition));
+                    self.body_codes(&cf.consequent, item, codes);
+                    self.body_codes(&cf.alternate, item, codes);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    for nodes in mb.children() {
+                        self.body_codes(nodes, item, codes);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => {
+                    codes.push(self.loops.code(&of.condition));
+                    self.body_codes(&of.fragment, item, codes);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// `path` is the keys read below the item, the last one being checked, at
+    /// `(line, column)` of the page
+    fn check_access(
+        &mut self,
+        lf: &LoopFragmentNode,
+        source: &str,
+        items: &[Value],
+        path: &[String],
+        (line, column): (u32, u32),
+    ) {
+        let Some((key, parents)) = path.split_last() else {
+            return;
+        };
+        if PROTOTYPE_KEYS.contains(&key.as_str()) {
+            return;
+        }
+        let Some(keys) = shared_keys(items, parents) else {
+            return;
+        };
+        if keys.contains(key) {
+            return;
+        }
+        let access = format!("{}.{}", lf.item_var.trim(), path.join("."));
+        if !self.reported.insert((lf.source.clone(), access.clone())) {
+            return;
+        }
+        let hint = crate::component::suggest_prop(key, &keys)
+            .map(|k| format!(" Did you mean `{}`?", k))
+            .unwrap_or_default();
+        let mut available: Vec<&String> = keys.iter().collect();
+        available.sort();
+        self.warnings.push(format!(
+            "Z-WARN-LOOP-ITEM-KEY: `{}` in the loop over `{}` in {} (line {}, column {}) reads a key no item has; it renders empty.{} Items have: {}.",
+            access,
+            source,
+            self.loops.ir.file_path,
+            line,
+            column,
+            hint,
+            available
+                .iter()
+                .map(|k| format!("`{}`", k))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+}
+
+/// Keys of the objects at `parents` below each item: the union at the item
+/// itself, or the one key set all items share further down (None otherwise)
+fn shared_keys(items: &[Value], parents: &[String]) -> Option<HashSet<String>> {
+    if parents.is_empty() {
+        return Some(
+            items
+                .iter()
+                .filter_map(Value::as_object)
+                .flat_map(|o| o.keys().cloned())
+                .collect(),
+        );
+    }
+    let mut shared: Option<HashSet<String>> = None;
+    for item in items {
+        let mut value = item;
+        for key in parents {
+            value = value.get(key)?;
+        }
+        let keys: HashSet<String> = value.as_object()?.keys().cloned().collect();
+        match &shared {
+            Some(shared) if *shared != keys => return None,
+            Some(_) => {}
+            None => shared = Some(keys),
+        }
+    }
+    shared
+}
+
+/// Static member paths read from the item variable, outside functions that
+/// rebind it
+struct ItemAccesses<'i> {
+    item: &'i str,
+    /// Functions entered that rebind the item
+    shadowed: usize,
+    /// Keys read, with the offset of the access in the expression
+    paths: Vec<(Vec<String>, u32)>,
+}
+
+impl ItemAccesses<'_> {
+    /// Keys from the item down to `expr`; None when it is not read from the item
+    fn path(&self, expr: &Expression) -> Option<Vec<String>> {
+        match expr {
+            Expression::Identifier(id) if id.name == self.item => Some(Vec::new()),
+            Expression::StaticMemberExpression(member) => {
+                let mut path = self.path(&member.object)?;
+                path.push(member.property.name.to_string());
+                Some(path)
+            }
+            Expression::ParenthesizedExpression(paren) => self.path(&paren.expression),
+            _ => None,
+        }
+    }
+
+    fn rebinds(&self, params: &FormalParameters) -> bool {
+        params.items.iter().any(|param| {
+            param
+                .pattern
+                .get_binding_identifiers()
+                .iter()
+                .any(|id| id.name == self.item)
+        })
+    }
+}
+
+impl<'a> Visit<'a> for ItemAccesses<'_> {
+    fn visit_static_member_expression(&mut self, member: &StaticMemberExpression<'a>) {
+        if self.shadowed == 0 {
+            if let Some(mut path) = self.path(&member.object) {
+                path.push(member.property.name.to_string());
+                self.paths.push((path, member.span.start));
+            }
+        }
+        walk::walk_static_member_expression(self, member);
+    }
+
+    fn visit_arrow_function_expression(&mut self, arrow: &ArrowFunctionExpression<'a>) {
+        let rebinds = self.rebinds(&arrow.params);
+        self.shadowed += rebinds as usize;
+        walk::walk_arrow_function_expression(self, arrow);
+        self.shadowed -= rebinds as usize;
+    }
+
+    fn visit_function(
+        &mut self,
+        func: &oxc_ast::ast::Function<'a>,
+        flags: oxc_syntax::scope::ScopeFlags,
+    ) {
+        let rebinds = self.rebinds(&func.params);
+        self.shadowed += rebinds as usize;
+        walk::walk_function(self, func, flags);
+        self.shadowed -= rebinds as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    const POSTS: &str = "<script>\nconst posts = [{ id: 1, title: \"Hello\", author: { name: \"Ann\" } }, { id: 2, title: \"Again\", draft: true, author: { name: \"Bo\" } }]\n</script>\n";
+
+    fn compile(page: &str) -> CompileResult {
+        let options = CompileOptions {
+            ast_classifier: true,
+            ..Default::default()
+        };
+        compile_zen_internal(page, "page.zen", options).unwrap()
+    }
+
+    fn shape_warnings(result: &CompileResult) -> Vec<&String> {
+        result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("Z-WARN-LOOP-ITEM-KEY"))
+            .collect()
+    }
+
+    #[test]
+    fn test_misspelled_key_warns_with_suggestion() {
+        let result = compile(&format!(
+            "{}<main>\n  <ul>{{posts.map(post =>\n    <li>{{post.titel}} by {{post.author.nmae}}</li>)}}</ul>\n</main>",
+            POSTS
+        ));
+        let warnings = shape_warnings(&result);
+        assert_eq!(warnings.len(), 2, "{:?}", result.warnings);
+        assert!(
+            warnings.iter().any(|w| w.starts_with(
+                "Z-WARN-LOOP-ITEM-KEY: `post.titel` in the loop over `posts` in page.zen (line 6, column 10)"
+            ) && w
+                .contains("Did you mean `title`? Items have: `author`, `draft`, `id`, `title`.")),
+            "{:?}",
+            warnings
+        );
+        // Every author has the same keys, so the nested read is checked too
+        assert!(
+            warnings.iter().any(|w| w.contains("`post.author.nmae`")
+                && w.contains("(line 6, column 26)")
+                && w.contains("Did you mean `name`?")),
+            "{:?}",
+            warnings
+        );
+    }
+
+    #[test]
+    fn test_needs_ast_classifier() {
+        // Without lowering the `.map()` is a runtime expression, not a loop fragment
+        let page = format!(
+            "{}<ul>{{posts.map(post => <li>{{post.titel}}</li>)}}</ul>",
+            POSTS
+        );
+        let result = compile_zen_internal(&page, "page.zen", CompileOptions::default()).unwrap();
+        assert!(shape_warnings(&result).is_empty(), "{:?}", result.warnings);
+        assert!(result.manifest.unwrap().loop_item_keys.is_empty());
+        assert_eq!(shape_warnings(&compile(&page)).len(), 1);
+    }
+
+    #[test]
+    fn test_valid_keys_are_silent() {
+        // `draft` is on one item only; computed access and rebinding are not checked
+        let result = compile(&format!(
+            "{}<ul>{{posts.map(post => <li title={{post['titel']}}>{{post.title}} {{post.draft ? 'draft' : ''}} {{post.toString()}} {{[1].map(post => post.x)}}</li>)}}</ul>",
+            POSTS
+        ));
+        assert!(shape_warnings(&result).is_empty(), "{:?}", result.warnings);
+    }
+
+    #[test]
+    fn test_dynamic_and_mixed_sources_are_silent() {
+        let result = compile(
+            "<script>\nfunction load() { return [] }\nstate posts = load()\nconst mixed = [{ title: \"a\" }, 2]\n</script>\n<ul>{posts.map(post => <li>{post.titel}</li>)}</ul><ol>{mixed.map(m => <li>{m.titel}</li>)}</ol>",
+        );
+        assert!(shape_warnings(&result).is_empty(), "{:?}", result.warnings);
+        assert!(result.manifest.unwrap().loop_item_keys.is_empty());
+    }
+
+    #[test]
+    fn test_item_keys_per_loop_in_manifest() {
+        let result = compile(&format!(
+            "{}<ul>{{posts.map(post => <li>{{post.title}}</li>)}}</ul><ol>{{[{{ label: \"x\" }}].map(tag => <li>{{tag.label}}</li>)}}</ol>",
+            POSTS
+        ));
+        let loops = result.manifest.unwrap().loop_item_keys;
+        assert_eq!(loops.len(), 2, "{:?}", loops);
+        assert_eq!(loops[0].expression, "posts");
+        assert_eq!(loops[0].item, "post");
+        assert_eq!(loops[0].keys, vec!["author", "draft", "id", "title"]);
+        assert!(loops[0].source.starts_with("expr_"));
+        assert_eq!(loops[1].keys, vec!["label"]);
+        let binding = result
+            .bindings
+            .iter()
+            .find(|b| b.expression == "post.title")
+            .unwrap();
+        assert_eq!(
+            binding.loop_context.as_ref().unwrap().map_source.as_deref(),
+            Some(loops[0].expression.as_str())
+        );
+    }
+}
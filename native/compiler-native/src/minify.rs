@@ -0,0 +1,352 @@
+//! Production Minification
+//!
+//! With `CompileOptions.minify`, whitespace-only text nodes that cannot affect
+//! rendering are dropped before transform, so the HTML and the canonical IR stay
+//! in step. Under `white-space: normal` a run of whitespace renders as at most
+//! one space, and none at all next to a block boundary:
+//!
+//! - beside a block-level element, or at the edge of a block-level parent: removed
+//! - anywhere else (between inline content, inside fragments and components): one space
+//! - inside `pre`, `textarea`, `script`, and `style`: untouched
+//!
+//! Text with any visible character is never changed. Generated bundle sections
+//! that parse on their own are reprinted by oxc's codegen in minify mode.
+
+use oxc_allocator::Allocator;
+use oxc_codegen::{Codegen, CodegenOptions};
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use crate::validate::TemplateNode;
+
+/// Elements whose whitespace is content
+const WHITESPACE_SENSITIVE: &[&str] = &["pre", "textarea", "script", "style", "listing", "xmp"];
+
+/// Elements that start their own block (or never render), so whitespace around them is dropped
+const BLOCK_ELEMENTS: &[&str] = &[
+    "html",
+    "head",
+    "body",
+    "title",
+    "meta",
+    "link",
+    "base",
+    "noscript",
+    "template",
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "details",
+    "dialog",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "section",
+    "summary",
+    "table",
+    "caption",
+    "colgroup",
+    "col",
+    "thead",
+    "tbody",
+    "tfoot",
+    "tr",
+    "td",
+    "th",
+    "ul",
+    "select",
+    "optgroup",
+    "option",
+];
+
+/// Drop or collapse insignificant whitespace text in a page's node tree
+pub(crate) fn collapse_whitespace(nodes: &mut Vec<TemplateNode>) {
+    collapse_children(nodes, true);
+}
+
+fn collapse_children(nodes: &mut Vec<TemplateNode>, block_parent: bool) {
+    for node in nodes.iter_mut() {
+        match node {
+            TemplateNode::Element(el) => {
+                let tag = el.tag.to_lowercase();
+                if !WHITESPACE_SENSITIVE.contains(&tag.as_str()) {
+                    collapse_children(&mut el.children, BLOCK_ELEMENTS.contains(&tag.as_str()));
+                }
+            }
+            // Wrappers render as `display: contents`, so their edges are not block boundaries
+            TemplateNode::Component(c) => collapse_children(&mut c.children, false),
+            TemplateNode::ConditionalFragment(cf) => {
+                collapse_children(&mut cf.consequent, false);
+                collapse_children(&mut cf.alternate, false);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children_mut() {
+                    collapse_children(nodes, false);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => collapse_children(&mut of.fragment, false),
+            TemplateNode::LoopFragment(lf) => collapse_children(&mut lf.body, false),
+            _ => {}
+        }
+    }
+
+    let drop: Vec<bool> = (0..nodes.len())
+        .map(|i| {
+            let at_edge = i == 0 || i + 1 == nodes.len();
+            is_whitespace_text(&nodes[i])
+                && ((at_edge && block_parent)
+                    || (i > 0 && is_block_element(&nodes[i - 1]))
+                    || nodes.get(i + 1).is_some_and(is_block_element))
+        })
+        .collect();
+    let mut drop = drop.into_iter();
+    nodes.retain(|_| !drop.next().unwrap_or(false));
+
+    for node in nodes.iter_mut() {
+        if let TemplateNode::Text(text) = node {
+            if is_collapsible(&text.value) && !text.value.is_empty() {
+                text.value = " ".to_string();
+            }
+        }
+    }
+}
+
+fn is_whitespace_text(node: &TemplateNode) -> bool {
+    matches!(node, TemplateNode::Text(text) if is_collapsible(&text.value))
+}
+
+/// HTML whitespace only (a non-breaking space is content)
+fn is_collapsible(text: &str) -> bool {
+    text.chars()
+        .all(|c| matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0c'))
+}
+
+fn is_block_element(node: &TemplateNode) -> bool {
+    matches!(node, TemplateNode::Element(el) if is_block_tag(&el.tag))
+}
+
+/// Whether whitespace beside or at the edge of a `tag` element is a block boundary
+pub(crate) fn is_block_tag(tag: &str) -> bool {
+    BLOCK_ELEMENTS.contains(&tag.to_lowercase().as_str())
+}
+
+/// Whether whitespace inside a `tag` element is kept as written
+pub(crate) fn is_whitespace_sensitive_tag(tag: &str) -> bool {
+    WHITESPACE_SENSITIVE.contains(&tag.to_lowercase().as_str())
+}
+
+/// Compact form of a self-contained bundle section; returned unchanged if it
+/// does not parse on its own
+pub(crate) fn minify_js(code: &str) -> String {
+    let allocator = Allocator::default();
+    let ret = Parser::new(&allocator, code, SourceType::mjs()).parse();
+    if !ret.errors.is_empty() {
+        return code.to_string();
+    }
+    Codegen::new()
+        .with_options(CodegenOptions::minify())
+        .build(&ret.program)
+        .code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+    use html5ever::parse_document;
+    use html5ever::tendril::TendrilSink;
+    use markup5ever_rcdom::{Handle, NodeData, RcDom};
+    use oxc_ast::ast::Statement;
+
+    const PAGE: &str = "<script>\nstate count = 1\nstate open = true\n</script>\n<main>\n  <h1>Title</h1>\n  <p>\n    <b>bold</b> <i>{count}</i>\n  </p>\n  {open ? <b>yes</b> : <i>no</i>}\n  <pre>  keep\n    this</pre>\n  <textarea>  raw\n text</textarea>\n</main>";
+
+    fn compile(minify: bool) -> CompileResult {
+        compile_zen_internal(
+            PAGE,
+            "page.zen",
+            CompileOptions {
+                ast_classifier: true,
+                minify,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    /// `code` with expression ids renumbered by first appearance, so the expected
+    /// output reads `expr_0`, `expr_1`, ... instead of anchor hashes
+    fn canonical_ids(code: &str) -> String {
+        let id_re = regex::Regex::new(r"expr_\d+").unwrap();
+        let mut ids: Vec<String> = Vec::new();
+        id_re
+            .replace_all(code, |c: &regex::Captures| {
+                let index = match ids.iter().position(|id| *id == c[0]) {
+                    Some(index) => index,
+                    None => {
+                        ids.push(c[0].to_string());
+                        ids.len() - 1
+                    }
+                };
+                format!("expr_{}", index)
+            })
+            .into_owned()
+    }
+
+    /// Rendered structure: elements, attributes, hydration comments, and text with
+    /// whitespace runs collapsed the way `white-space: normal` renders them
+    fn dom_outline(html: &str) -> String {
+        fn walk(node: &Handle, out: &mut String) {
+            match &node.data {
+                NodeData::Element { name, attrs, .. } => {
+                    out.push_str(&format!("<{}", name.local));
+                    for attr in attrs.borrow().iter() {
+                        out.push_str(&format!(
+                            " {}={:?}",
+                            attr.name.local,
+                            attr.value.to_string()
+                        ));
+                    }
+                    out.push('>');
+                    let preserve = WHITESPACE_SENSITIVE.contains(&&*name.local);
+                    for child in node.children.borrow().iter() {
+                        match &child.data {
+                            NodeData::Text { contents } if preserve => {
+                                out.push_str(&format!("{:?}", contents.borrow().to_string()))
+                            }
+                            _ => walk(child, out),
+                        }
+                    }
+                    out.push_str("</>");
+                }
+                NodeData::Text { contents } => {
+                    let text = contents
+                        .borrow()
+                        .split_ascii_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    if !text.is_empty() {
+                        out.push_str(&format!("[{}]", text));
+                    }
+                }
+                NodeData::Comment { contents } => out.push_str(&format!("<!--{}-->", contents)),
+                _ => {
+                    for child in node.children.borrow().iter() {
+                        walk(child, out);
+                    }
+                }
+            }
+        }
+        let dom = parse_document(RcDom::default(), Default::default())
+            .from_utf8()
+            .read_from(&mut html.as_bytes())
+            .unwrap();
+        let mut out = String::new();
+        walk(&dom.document, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_minified_html_is_dom_equivalent() {
+        let plain = compile(false);
+        let minified = compile(true);
+        assert!(!minified.has_errors, "{:?}", minified.errors);
+
+        assert_eq!(
+            canonical_ids(&dom_outline(&minified.html)),
+            canonical_ids(&dom_outline(&plain.html))
+        );
+        assert!(minified.html.len() < plain.html.len());
+        // Only the whitespace-sensitive contents keep their newlines
+        assert!(
+            minified.html.contains("<pre>  keep\n    this</pre>"),
+            "{}",
+            minified.html
+        );
+        assert!(
+            minified.html.contains("<textarea>  raw\n text</textarea>"),
+            "{}",
+            minified.html
+        );
+        assert_eq!(minified.html.matches('\n').count(), 2, "{}", minified.html);
+        // Whitespace between inline siblings still renders as a space
+        assert!(
+            minified.html.contains("<b>bold</b> <i>"),
+            "{}",
+            minified.html
+        );
+    }
+
+    #[test]
+    fn test_minified_bundle_keeps_expression_registry() {
+        let entry_re = regex::Regex::new(
+            r#"__ZENITH_EXPRESSIONS__\.set\(['"`](\w+)['"`],\s*\{\s*fn:\s*(\w+),\s*deps:\s*\[([^\]]*)\]"#,
+        )
+        .unwrap();
+        // (id, function, deps) with quoting and spacing normalized
+        let registry = |bundle: &str| -> Vec<(String, String, Vec<String>)> {
+            entry_re
+                .captures_iter(bundle)
+                .map(|c| {
+                    let deps = c[3]
+                        .split(',')
+                        .map(|d| d.trim().trim_matches(['\'', '"', '`']).to_string())
+                        .filter(|d| !d.is_empty())
+                        .collect();
+                    (c[1].to_string(), c[2].to_string(), deps)
+                })
+                .collect()
+        };
+        let function_names = |code: &str| -> Vec<String> {
+            let allocator = Allocator::default();
+            let ret = Parser::new(&allocator, code, SourceType::mjs()).parse();
+            assert!(ret.errors.is_empty(), "{}", code);
+            ret.program
+                .body
+                .iter()
+                .filter_map(|stmt| match stmt {
+                    Statement::FunctionDeclaration(f) => {
+                        f.id.as_ref().map(|id| id.name.to_string())
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let plain = compile(false).manifest.unwrap();
+        let minified = compile(true).manifest.unwrap();
+
+        assert!(!registry(&plain.bundle).is_empty());
+        assert_eq!(
+            registry(&canonical_ids(&minified.bundle)),
+            registry(&canonical_ids(&plain.bundle))
+        );
+        assert_eq!(
+            function_names(&canonical_ids(&minified.expressions)),
+            function_names(&canonical_ids(&plain.expressions))
+        );
+        assert!(minified.bundle.len() < plain.bundle.len());
+        assert!(!minified.bundle.contains("// 7. Expressions"));
+        assert!(!minified.bundle.contains("[ZENITH-NATIVE]"));
+    }
+}
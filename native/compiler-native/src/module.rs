@@ -0,0 +1,195 @@
+//! Script-Only Modules
+//!
+//! A `.zen` file with a `<script>` block and no markup (shared helpers, stores)
+//! is a plain ES module, not a page: there is no template to transform, no head
+//! or document, and no component instance to hold `state` or receive `prop`s.
+//! Its script is compiled on its own, TypeScript is stripped, and the result is
+//! returned as `CompileResult.module_code`.
+//!
+//! Pages and other modules use it through named imports
+//! (`import { cart } from './store.zen'`), which are kept and rewritten to the
+//! emitted `.js` path like other script imports of `.zen` files; default imports
+//! of `.zen` files are component declarations and are still dropped.
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{ImportDeclarationSpecifier, Statement};
+use oxc_codegen::Codegen;
+use oxc_parser::Parser;
+use oxc_semantic::SemanticBuilder;
+use oxc_span::SourceType;
+use oxc_transformer::{TransformOptions, Transformer, TypeScriptOptions};
+
+use crate::validate::{ScriptIR, TemplateIR, TemplateNode};
+
+/// No markup besides whitespace; with a script block, the file is a module
+pub(crate) fn is_script_only(template: &TemplateIR) -> bool {
+    template.nodes.iter().all(|node| match node {
+        TemplateNode::Text(text) => text.value.trim().is_empty(),
+        _ => false,
+    })
+}
+
+/// Named-only imports of a `.zen` file target a script-only module; anything else
+/// (default or namespace import) names a component, resolved at compile time
+pub(crate) fn is_module_import(specifiers: &[ImportDeclarationSpecifier]) -> bool {
+    !specifiers.is_empty()
+        && specifiers
+            .iter()
+            .all(|s| matches!(s, ImportDeclarationSpecifier::ImportSpecifier(_)))
+}
+
+/// Path of the emitted module (`./store.zen` → `./store.js`)
+pub(crate) fn module_js_path(source: &str) -> String {
+    match source.strip_suffix(".zen") {
+        Some(stem) => format!("{}.js", stem),
+        None => source.to_string(),
+    }
+}
+
+/// Compile a module's script to ESM, or the errors that prevent it
+pub(crate) fn compile_module(script: &ScriptIR, file_path: &str) -> Result<String, Vec<String>> {
+    let mut errors: Vec<String> = script
+        .states
+        .keys()
+        .map(|name| ("state", name))
+        .chain(script.props.iter().map(|name| ("prop", name)))
+        .map(|(keyword, name)| {
+            format!(
+                "Z-ERR-MODULE-STATE: `{} {}` in {}: a script-only module has no component instance to hold it. Export a store or helper instead, or move it into a component.",
+                keyword, name, file_path
+            )
+        })
+        .collect();
+    errors.sort();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let allocator = Allocator::default();
+    let source_type = SourceType::default()
+        .with_module(true)
+        .with_typescript(true);
+    let ret = Parser::new(&allocator, &script.raw, source_type).parse();
+    if !ret.errors.is_empty() {
+        return Err(ret
+            .errors
+            .iter()
+            .map(|e| format!("Z-ERR-MODULE-PARSE: {}: {}", file_path, e.message))
+            .collect());
+    }
+    let mut program = ret.program;
+
+    // Component imports mean nothing without a template
+    program.body.retain(|stmt| match stmt {
+        Statement::ImportDeclaration(import) if import.source.value.ends_with(".zen") => import
+            .specifiers
+            .as_ref()
+            .is_some_and(|s| is_module_import(s)),
+        _ => true,
+    });
+    for stmt in program.body.iter_mut() {
+        if let Statement::ImportDeclaration(import) = stmt {
+            if import.source.value.ends_with(".zen") {
+                let path = module_js_path(&import.source.value);
+                import.source.value = allocator.alloc_str(&path).into();
+            }
+        }
+    }
+
+    let scoping = SemanticBuilder::new()
+        .build(&program)
+        .semantic
+        .into_scoping();
+    // Imports stay even when unused here: a module may import for side effects
+    let options = TransformOptions {
+        typescript: TypeScriptOptions {
+            only_remove_type_imports: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let transformed = Transformer::new(&allocator, std::path::Path::new(file_path), &options)
+        .build_with_scoping(scoping, &mut program);
+    if !transformed.errors.is_empty() {
+        return Err(transformed
+            .errors
+            .iter()
+            .map(|e| format!("Z-ERR-MODULE-PARSE: {}: {}", file_path, e.message))
+            .collect());
+    }
+
+    Ok(Codegen::new().build(&program).code)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions};
+
+    const STORE: &str = "<script lang=\"ts\">\nimport { writable } from 'zenith/store';\nimport { clamp } from './math.zen';\nimport Card from './Card.zen';\ninterface Item { id: number }\nexport const cart = writable<Item[]>([]);\nexport function total(items: Item[]): number {\n  return items.length;\n}\n</script>\n";
+
+    #[test]
+    fn test_store_module_compiles_to_esm() {
+        let result = compile_zen_internal(STORE, "store.zen", CompileOptions::default()).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(result.html.is_empty());
+        assert!(result.manifest.is_none());
+
+        let code = result
+            .module_code
+            .expect("script-only file compiles as a module");
+        assert!(
+            code.contains("import { writable } from \"zenith/store\""),
+            "{}",
+            code
+        );
+        assert!(
+            code.contains("export const cart = writable([])"),
+            "{}",
+            code
+        );
+        assert!(code.contains("export function total(items)"), "{}", code);
+        assert!(
+            code.contains("import { clamp } from \"./math.js\""),
+            "{}",
+            code
+        );
+        assert!(!code.contains("Card"), "{}", code);
+        assert!(!code.contains("interface"), "{}", code);
+        assert!(!code.contains("__ZENITH"), "{}", code);
+    }
+
+    #[test]
+    fn test_state_in_module_errors() {
+        let result = compile_zen_internal(
+            "<script>\nstate count = 0\nexport const double = () => count * 2\n</script>",
+            "counter.zen",
+            CompileOptions::default(),
+        )
+        .unwrap();
+        assert!(result.has_errors);
+        assert!(result.module_code.is_none());
+        assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+        assert!(result.errors[0]
+            .starts_with("Z-ERR-MODULE-STATE: `state count` in counter.zen: a script-only module"));
+    }
+
+    #[test]
+    fn test_page_imports_module_exports() {
+        let result = compile_zen_internal(
+            "<script>\nimport { total } from './store.zen';\nimport Card from './Card.zen';\nstate items = [1, 2]\n</script>\n<p>{total(items)}</p>",
+            "page.zen",
+            CompileOptions::default(),
+        )
+        .unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(result.module_code.is_none());
+
+        let bundle = &result.manifest.as_ref().unwrap().bundle;
+        assert!(
+            bundle.contains("import { total } from \"./store.js\""),
+            "{}",
+            bundle
+        );
+        assert!(!bundle.contains("Card"), "{}", bundle);
+    }
+}
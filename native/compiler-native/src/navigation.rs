@@ -0,0 +1,298 @@
+//! Navigation Targets
+//!
+//! Routes a page can navigate to, so the bundler can prefetch their chunks:
+//!
+//! - `zenLink('/pricing')` calls in the page and component scripts and in event
+//!   handlers, when the argument is a string literal or a template literal
+//!   without substitutions
+//! - static `href`s of `<a>` elements that point into the site
+//!
+//! Only site-absolute paths (`/pricing`) are routes; the query and fragment are
+//! dropped. A `zenLink()` whose argument is only known at runtime is listed in
+//! `dynamic` with its source, so the bundler knows the routes are not exhaustive.
+//!
+//! With `prefetch_links` and a route → asset mapping, finalize adds a
+//! `<link rel="prefetch">` for the asset of each mapped route after the opening
+//! `<head>` tag.
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{Argument, CallExpression, Expression};
+use oxc_ast_visit::Visit;
+use oxc_parser::Parser;
+use oxc_span::GetSpan;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+use crate::validate::{AttributeValue, TemplateNode, ZenIR};
+
+/// The router's programmatic navigation
+pub const NAVIGATE_FN: &str = "zenLink";
+
+/// Manifest entry: where the page navigates
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct Navigation {
+    /// Static routes of `zenLink()` calls and internal links, sorted
+    pub targets: Vec<String>,
+    /// Source of each `zenLink()` argument only known at runtime, sorted
+    pub dynamic: Vec<String>,
+}
+
+#[derive(Default)]
+struct Collect {
+    targets: BTreeSet<String>,
+    dynamic: BTreeSet<String>,
+}
+
+impl Collect {
+    fn code(&mut self, code: &str, expression: bool) {
+        if !code.contains(NAVIGATE_FN) {
+            return;
+        }
+        let allocator = Allocator::default();
+        let mut calls = ZenLinkCalls {
+            code,
+            collect: self,
+        };
+        // Code oxc rejects is reported elsewhere
+        if expression {
+            let source_type = oxc_span::SourceType::default()
+                .with_jsx(true)
+                .with_typescript(true);
+            if let Ok(parsed) = Parser::new(&allocator, code, source_type).parse_expression() {
+                calls.visit_expression(&parsed);
+            }
+        } else {
+            let source_type = oxc_span::SourceType::default()
+                .with_module(true)
+                .with_typescript(true)
+                .with_jsx(true);
+            let ret = Parser::new(&allocator, code, source_type).parse();
+            calls.visit_program(&ret.program);
+        }
+    }
+
+    fn nodes(&mut self, nodes: &[TemplateNode]) {
+        for node in nodes {
+            match node {
+                TemplateNode::Element(el) => {
+                    if el.tag.eq_ignore_ascii_case("a") {
+                        let href = el.attributes.iter().find(|a| a.name == "href");
+                        if let Some(AttributeValue::Static(href)) = href.map(|a| &a.value) {
+                            self.targets.extend(route(href));
+                        }
+                    }
+                    self.nodes(&el.children);
+                }
+                TemplateNode::Component(c) => self.nodes(&c.children),
+                TemplateNode::ConditionalFragment(cf) => {
+                    self.nodes(&cf.consequent);
+                    self.nodes(&cf.alternate);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    for nodes in mb.children() {
+                        self.nodes(nodes);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => self.nodes(&of.fragment),
+                TemplateNode::LoopFragment(lf) => self.nodes(&lf.body),
+                _ => {}
+            }
+        }
+    }
+}
+
+struct ZenLinkCalls<'c> {
+    code: &'c str,
+    collect: &'c mut Collect,
+}
+
+impl<'a> Visit<'a> for ZenLinkCalls<'_> {
+    fn visit_call_expression(&mut self, call: &CallExpression<'a>) {
+        if matches!(&call.callee, Expression::Identifier(id) if id.name == NAVIGATE_FN) {
+            let target = match call.arguments.first() {
+                Some(Argument::StringLiteral(s)) => Some(Some(s.value.to_string())),
+                Some(Argument::TemplateLiteral(t)) if t.expressions.is_empty() => {
+                    Some(t.quasis[0].value.cooked.map(|c| c.to_string()))
+                }
+                Some(_) => Some(None),
+                None => None,
+            };
+            match target {
+                Some(Some(target)) => self.collect.targets.extend(route(&target)),
+                Some(None) => {
+                    let span = call.arguments[0].span();
+                    self.collect
+                        .dynamic
+                        .insert(self.code[span.start as usize..span.end as usize].to_string());
+                }
+                None => {}
+            }
+        }
+        oxc_ast_visit::walk::walk_call_expression(self, call);
+    }
+}
+
+/// The route of a site-absolute path, without query or fragment
+fn route(href: &str) -> Option<String> {
+    let href = href.trim();
+    if !href.starts_with('/') || href.starts_with("//") || href.contains("__ZENITH_EXPR_") {
+        return None;
+    }
+    let end = href.find(['?', '#']).unwrap_or(href.len());
+    Some(href[..end].to_string())
+}
+
+/// Routes the page navigates to: `zenLink()` calls in the merged page and
+/// component scripts and in event handlers, and internal `<a href>`s
+pub(crate) fn collect_navigation(ir: &ZenIR) -> Navigation {
+    let mut collect = Collect::default();
+    if let Some(script) = &ir.script {
+        collect.code(&crate::component::zen_keywords_to_let(&script.raw), false);
+    }
+    let mut handlers = HashSet::new();
+    crate::codegen::collect_event_handler_ids(&ir.template.nodes, &mut handlers);
+    for expr in &ir.template.expressions {
+        if handlers.contains(&expr.id) {
+            collect.code(&expr.code, true);
+        }
+    }
+    collect.nodes(&ir.template.nodes);
+    Navigation {
+        targets: collect.targets.into_iter().collect(),
+        dynamic: collect.dynamic.into_iter().collect(),
+    }
+}
+
+/// Insert `<link rel="prefetch">` for the asset of each route in `assets`, right
+/// after the opening `<head>` tag. Unmapped routes, documents without a `<head>`
+/// and assets the author already prefetches are left alone.
+pub fn inject_prefetch_links(
+    html: &str,
+    targets: &[String],
+    assets: &HashMap<String, String>,
+) -> String {
+    let Some(head) = crate::preload::HEAD_OPEN_RE.find(html) else {
+        return html.to_string();
+    };
+
+    let mut seen = HashSet::new();
+    let links: String = targets
+        .iter()
+        .filter_map(|route| assets.get(route))
+        .filter(|asset| {
+            seen.insert(asset.as_str())
+                && !html.contains(&format!("rel=\"prefetch\" href=\"{}\"", asset))
+        })
+        .map(|asset| format!("<link rel=\"prefetch\" href=\"{}\">", asset))
+        .collect();
+
+    let mut out = String::with_capacity(html.len() + links.len());
+    out.push_str(&html[..head.end()]);
+    out.push_str(&links);
+    out.push_str(&html[head.end()..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    const PAGE: &str = r#"<script>
+state plan = "pro"
+function upgrade() { zenLink(`/checkout/${plan}`) }
+</script>
+<html>
+<head><title>Plans</title></head>
+<body>
+  <a href="/about#team">About</a>
+  <a href="https://example.com/">Elsewhere</a>
+  <button onclick={() => zenLink('/pricing')}>Pricing</button>
+  <button onclick={upgrade}>Upgrade</button>
+  <button onclick={() => zenLink(`/docs`)}>Docs</button>
+</body>
+</html>"#;
+
+    fn compile(options: CompileOptions) -> CompileResult {
+        compile_zen_internal(PAGE, "page.zen", options).unwrap()
+    }
+
+    #[test]
+    fn test_handler_and_link_targets_are_collected() {
+        let result = compile(CompileOptions::default());
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(
+            result.navigation_targets,
+            vec!["/about", "/docs", "/pricing"]
+        );
+        let navigation = result.manifest.unwrap().navigation;
+        assert_eq!(navigation.targets, result.navigation_targets);
+        // Prefetching needs the mapping
+        assert!(!result.html.contains("rel=\"prefetch\""), "{}", result.html);
+    }
+
+    #[test]
+    fn test_substituted_template_literal_is_dynamic() {
+        let navigation = compile(CompileOptions::default())
+            .manifest
+            .unwrap()
+            .navigation;
+        assert_eq!(navigation.dynamic, vec!["`/checkout/${plan}`"]);
+        assert!(!navigation
+            .targets
+            .iter()
+            .any(|t| t.starts_with("/checkout")));
+    }
+
+    #[test]
+    fn test_component_script_targets_are_collected() {
+        let nav = crate::component::ComponentIR::from_source(
+            "Nav",
+            "components/Nav.zen",
+            "<script>\nfunction home() { zenLink('/') }\n</script>\n<nav><button onclick={home}>Home</button></nav>",
+        )
+        .unwrap();
+        let options = CompileOptions {
+            components: [("Nav".to_string(), serde_json::to_value(&nav).unwrap())].into(),
+            ..Default::default()
+        };
+        let result = compile_zen_internal("<main><Nav /></main>", "page.zen", options).unwrap();
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.navigation_targets, vec!["/"]);
+    }
+
+    #[test]
+    fn test_prefetch_links_for_mapped_routes() {
+        let routes: HashMap<String, String> = [
+            ("/pricing".to_string(), "/assets/pricing.js".to_string()),
+            ("/about".to_string(), "/assets/about.js".to_string()),
+        ]
+        .into();
+        let options = |prefetch_links, prefetch_routes: &HashMap<String, String>| CompileOptions {
+            prefetch_links,
+            prefetch_routes: prefetch_routes.clone(),
+            ..Default::default()
+        };
+        let html = compile(options(true, &routes)).html;
+        assert!(
+            html.contains(
+                "<head><link rel=\"prefetch\" href=\"/assets/about.js\"><link rel=\"prefetch\" href=\"/assets/pricing.js\">"
+            ),
+            "{}",
+            html
+        );
+        assert!(!html.contains("/docs\">"), "{}", html);
+
+        // Either one alone emits nothing
+        for options in [options(false, &routes), options(true, &HashMap::new())] {
+            let html = compile(options).html;
+            assert!(!html.contains("rel=\"prefetch\""), "{}", html);
+        }
+    }
+}
@@ -0,0 +1,452 @@
+//! Strict Nullish Access
+//!
+//! `{user.profile.name}` with `state user = undefined` throws "Cannot read
+//! properties of undefined" on every render until a fetch assigns `user`. With
+//! `strict_nullish`, template expressions are checked after component resolution
+//! for member access whose object is a state that starts as `undefined` or
+//! `null`, or whose initial value is not known at compile time (a call, another
+//! binding). Roots are bare page states and `state.*` members (`scope.state.user`,
+//! `window.__ZENITH_SCOPES__["inst0"].state.user` in resolved components).
+//!
+//! An access is fine when it is optional (`user?.profile`), or guarded:
+//!
+//! - by the left side of `&&`, `||` or `??` (`user && user.name`);
+//! - by the test of a ternary (`user ? user.name : ''`);
+//! - by a conditional, else-if or optional fragment whose condition reads the root.
+//!
+//! Defaulted objects (`(user ?? {}).name`) have no state root. Event handlers run
+//! after hydration and are not checked, nor are loop item variables and function
+//! parameters that shadow a state. Props are not checked: their value comes from
+//! the usage site.
+//!
+//! Each finding is a `Z-WARN-NULLISH-ACCESS` warning naming the root and its
+//! initial value.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{
+    ArrowFunctionExpression, ConditionalExpression, Expression, FormalParameters,
+    LogicalExpression, UnaryOperator,
+};
+use oxc_ast_visit::{walk, Visit};
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use crate::validate::{AttributeValue, ExpressionIR, SourceLocation, TemplateNode, ZenIR};
+
+pub const NULLISH_WARNING: &str = "Z-WARN-NULLISH-ACCESS";
+
+/// How a state starts when it may not have a value
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Initial {
+    Nullish,
+    Unknown,
+}
+
+/// States that may start without a value, with their initial code
+fn nullable_states(all_states: &HashMap<String, String>) -> HashMap<String, (Initial, String)> {
+    all_states
+        .iter()
+        .filter_map(|(name, init)| {
+            let initial = classify(init)?;
+            Some((name.clone(), (initial, init.trim().to_string())))
+        })
+        .collect()
+}
+
+/// `None` when the initial value is certainly not nullish
+fn classify(init: &str) -> Option<Initial> {
+    let allocator = Allocator::default();
+    match Parser::new(&allocator, init, SourceType::ts()).parse_expression() {
+        Ok(expr) => classify_expression(&expr),
+        Err(_) => Some(Initial::Unknown),
+    }
+}
+
+fn classify_expression(expr: &Expression) -> Option<Initial> {
+    match expr {
+        Expression::NullLiteral(_) => Some(Initial::Nullish),
+        Expression::Identifier(id) if id.name == "undefined" => Some(Initial::Nullish),
+        Expression::UnaryExpression(unary) if unary.operator == UnaryOperator::Void => {
+            Some(Initial::Nullish)
+        }
+        Expression::ParenthesizedExpression(paren) => classify_expression(&paren.expression),
+        Expression::TSAsExpression(cast) => classify_expression(&cast.expression),
+        Expression::TSSatisfiesExpression(cast) => classify_expression(&cast.expression),
+        Expression::TSNonNullExpression(cast) => classify_expression(&cast.expression),
+        Expression::BooleanLiteral(_)
+        | Expression::NumericLiteral(_)
+        | Expression::BigIntLiteral(_)
+        | Expression::StringLiteral(_)
+        | Expression::RegExpLiteral(_)
+        | Expression::TemplateLiteral(_)
+        | Expression::ObjectExpression(_)
+        | Expression::ArrayExpression(_)
+        | Expression::ArrowFunctionExpression(_)
+        | Expression::FunctionExpression(_)
+        | Expression::ClassExpression(_)
+        | Expression::NewExpression(_)
+        | Expression::UnaryExpression(_)
+        | Expression::BinaryExpression(_)
+        | Expression::UpdateExpression(_) => None,
+        _ => Some(Initial::Unknown),
+    }
+}
+
+/// Add a `Z-WARN-NULLISH-ACCESS` warning to `ir.warnings` for each unguarded
+/// member access on a state that may start without a value
+pub(crate) fn check_nullish_access(ir: &mut ZenIR) {
+    let states = nullable_states(&ir.all_states);
+    if states.is_empty() {
+        return;
+    }
+    let mut handlers = HashSet::new();
+    crate::codegen::collect_event_handler_ids(&ir.template.nodes, &mut handlers);
+    let mut check = Check {
+        expressions: ir
+            .template
+            .expressions
+            .iter()
+            .map(|e| (e.id.as_str(), e))
+            .collect(),
+        file_path: &ir.file_path,
+        source: &ir.template.raw,
+        states: &states,
+        handlers,
+        guarded: Vec::new(),
+        warnings: Vec::new(),
+    };
+    check.visit(&ir.template.nodes);
+    let warnings = check.warnings;
+    ir.warnings.extend(warnings);
+}
+
+struct Check<'a> {
+    expressions: HashMap<&'a str, &'a ExpressionIR>,
+    file_path: &'a str,
+    /// The page's template source
+    source: &'a str,
+    states: &'a HashMap<String, (Initial, String)>,
+    handlers: HashSet<String>,
+    /// Roots read by the conditions of the enclosing fragments
+    guarded: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl Check<'_> {
+    fn visit(&mut self, nodes: &[TemplateNode]) {
+        for node in nodes {
+            match node {
+                TemplateNode::Element(el) => {
+                    for attr in &el.attributes {
+                        if let AttributeValue::Dynamic(expr) = &attr.value {
+                            if !attr.name.starts_with("on") && !self.handlers.contains(&expr.id) {
+                                self.check(expr, &attr.location);
+                            }
+                        }
+                    }
+                    self.visit(&el.children);
+                }
+                TemplateNode::Expression(e) => self.check_id(&e.expression, &e.location),
+                TemplateNode::Component(c) => self.visit(&c.children),
+                TemplateNode::ConditionalFragment(cf) => {
+                    let depth = self.guard(&cf.condition, &cf.location);
+                    self.visit(&cf.consequent);
+                    self.visit(&cf.alternate);
+                    self.guarded.truncate(depth);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    // A later condition only runs once the earlier ones failed
+                    let depth = self.guarded.len();
+                    for branch in &mb.branches {
+                        self.guard(&branch.condition, &mb.location);
+                    }
+                    for nodes in mb.children() {
+                        self.visit(nodes);
+                    }
+                    self.guarded.truncate(depth);
+                }
+                TemplateNode::OptionalFragment(of) => {
+                    let depth = self.guard(&of.condition, &of.location);
+                    self.visit(&of.fragment);
+                    self.guarded.truncate(depth);
+                }
+                TemplateNode::LoopFragment(lf) => {
+                    self.check_id(&lf.source, &lf.location);
+                    self.visit(&lf.body);
+                }
+                TemplateNode::Text(_) | TemplateNode::Doctype(_) => {}
+            }
+        }
+    }
+
+    /// Check a condition, then guard the roots it reads; returns the guard depth
+    /// before it
+    fn guard(&mut self, id: &str, location: &SourceLocation) -> usize {
+        let depth = self.guarded.len();
+        if let Some(expr) = self.expressions.get(id).copied() {
+            let roots = self.check(expr, location);
+            self.guarded.extend(roots);
+        }
+        depth
+    }
+
+    fn check_id(&mut self, id: &str, location: &SourceLocation) {
+        if let Some(expr) = self.expressions.get(id).copied() {
+            self.check(expr, location);
+        }
+    }
+
+    /// Warn about the unguarded accesses of one expression, used by the node at
+    /// `location`; returns the roots it reads
+    fn check(&mut self, expr: &ExpressionIR, location: &SourceLocation) -> HashSet<String> {
+        let allocator = Allocator::default();
+        let Ok(parsed) = Parser::new(&allocator, &expr.code, SourceType::tsx()).parse_expression()
+        else {
+            return HashSet::new();
+        };
+        let mut shadowed: Vec<String> = expr
+            .loop_context
+            .iter()
+            .flat_map(|lc| lc.variables.iter().cloned())
+            .collect();
+        shadowed.extend(self.guarded.iter().cloned());
+        let mut accesses = Accesses {
+            states: self.states,
+            shadowed,
+            reads: HashSet::new(),
+            unguarded: BTreeMap::new(),
+        };
+        accesses.visit_expression(&parsed);
+
+        let (file, (line, column)) = match &expr.origin {
+            Some(origin) => (
+                origin.component_path.as_str(),
+                (
+                    origin.original_location.line,
+                    origin.original_location.column,
+                ),
+            ),
+            // The expression's text in the page is more precise than the node's location
+            None => (
+                self.file_path,
+                self.source
+                    .find(expr.code.as_str())
+                    .map(|at| {
+                        let (line, column) = crate::content_audit::line_column(self.source, at);
+                        (line as u32, column as u32)
+                    })
+                    .unwrap_or((location.line, location.column)),
+            ),
+        };
+        for (root, member) in &accesses.unguarded {
+            let (initial, init) = &self.states[root];
+            let starts = match initial {
+                Initial::Nullish => format!("starts as `{}`", init),
+                Initial::Unknown => {
+                    format!("starts as `{}`, which is not known at compile time", init)
+                }
+            };
+            self.warnings.push(format!(
+                "{}: `{{{}}}` at {} (line {}, column {}) reads a member of state `{}`, which {}; rendering throws while it is undefined or null. Use optional chaining (`{}?.{}`) or guard it (`{} && ...`).",
+                NULLISH_WARNING,
+                expr.code.trim(),
+                file,
+                line,
+                column,
+                root,
+                starts,
+                root,
+                member,
+                root
+            ));
+        }
+        accesses.reads
+    }
+}
+
+/// State roots an expression reads, and the ones it accesses members of unguarded
+struct Accesses<'s> {
+    states: &'s HashMap<String, (Initial, String)>,
+    /// Guarded roots and names bound inside the expression
+    shadowed: Vec<String>,
+    reads: HashSet<String>,
+    /// Root → the first member accessed on it
+    unguarded: BTreeMap<String, String>,
+}
+
+impl Accesses<'_> {
+    /// The state `object` refers to: a bare state name, or `state.name` and
+    /// `<scope>.state.name`
+    fn root(&self, object: &Expression) -> Option<String> {
+        let name = match object {
+            Expression::Identifier(id) => id.name.as_str(),
+            Expression::StaticMemberExpression(member) => {
+                let is_state = match &member.object {
+                    Expression::Identifier(id) => id.name == "state",
+                    Expression::StaticMemberExpression(m) => m.property.name == "state",
+                    _ => false,
+                };
+                if !is_state {
+                    return None;
+                }
+                member.property.name.as_str()
+            }
+            _ => return None,
+        };
+        if !self.states.contains_key(name) || self.shadowed.iter().any(|s| s == name) {
+            return None;
+        }
+        Some(name.to_string())
+    }
+
+    fn access(&mut self, object: &Expression, optional: bool, member: String) {
+        if let Some(root) = self.root(object) {
+            if !optional {
+                self.unguarded.entry(root).or_insert(member);
+            }
+        }
+    }
+
+    /// Visit `guarded` with the roots `test` reads treated as checked
+    fn visit_guarded<'a>(&mut self, test: &Expression<'a>, guarded: &[&Expression<'a>]) {
+        let before = self.reads.clone();
+        self.visit_expression(test);
+        let new: Vec<String> = self.reads.difference(&before).cloned().collect();
+        let depth = self.shadowed.len();
+        self.shadowed.extend(new);
+        for expr in guarded {
+            self.visit_expression(expr);
+        }
+        self.shadowed.truncate(depth);
+    }
+
+    fn bind_params(&mut self, params: &FormalParameters) {
+        for param in &params.items {
+            for id in param.pattern.get_binding_identifiers() {
+                self.shadowed.push(id.name.to_string());
+            }
+        }
+    }
+}
+
+impl<'a> Visit<'a> for Accesses<'_> {
+    fn visit_expression(&mut self, expr: &Expression<'a>) {
+        if let Some(root) = self.root(expr) {
+            self.reads.insert(root);
+        }
+        match expr {
+            Expression::StaticMemberExpression(member) => {
+                self.access(
+                    &member.object,
+                    member.optional,
+                    member.property.name.to_string(),
+                );
+            }
+            Expression::ComputedMemberExpression(member) => {
+                self.access(&member.object, member.optional, "[...]".to_string());
+            }
+            _ => {}
+        }
+        walk::walk_expression(self, expr);
+    }
+
+    fn visit_logical_expression(&mut self, logical: &LogicalExpression<'a>) {
+        self.visit_guarded(&logical.left, &[&logical.right]);
+    }
+
+    fn visit_conditional_expression(&mut self, cond: &ConditionalExpression<'a>) {
+        self.visit_guarded(&cond.test, &[&cond.consequent, &cond.alternate]);
+    }
+
+    fn visit_arrow_function_expression(&mut self, arrow: &ArrowFunctionExpression<'a>) {
+        let depth = self.shadowed.len();
+        self.bind_params(&arrow.params);
+        walk::walk_arrow_function_expression(self, arrow);
+        self.shadowed.truncate(depth);
+    }
+
+    fn visit_function(
+        &mut self,
+        func: &oxc_ast::ast::Function<'a>,
+        flags: oxc_syntax::scope::ScopeFlags,
+    ) {
+        let depth = self.shadowed.len();
+        self.bind_params(&func.params);
+        walk::walk_function(self, func, flags);
+        self.shadowed.truncate(depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions};
+
+    fn warnings(page: &str) -> Vec<String> {
+        let options = CompileOptions {
+            strict_nullish: true,
+            ast_classifier: true,
+            ..Default::default()
+        };
+        compile_zen_internal(page, "page.zen", options)
+            .unwrap()
+            .warnings
+            .into_iter()
+            .filter(|w| w.starts_with(NULLISH_WARNING))
+            .collect()
+    }
+
+    #[test]
+    fn test_unguarded_chain_on_undefined_state_warns() {
+        let warnings =
+            warnings("<script>\nstate user = undefined\n</script>\n<p>{user.profile.name}</p>");
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(
+            warnings[0].starts_with(
+                "Z-WARN-NULLISH-ACCESS: `{user.profile.name}` at page.zen (line 4, column 5) reads a member of state `user`, which starts as `undefined`;"
+            ),
+            "{}",
+            warnings[0]
+        );
+        assert!(warnings[0].contains("`user?.profile`"));
+    }
+
+    #[test]
+    fn test_optional_chaining_does_not_warn() {
+        let warnings = warnings(
+            "<script>\nstate user = null\n</script>\n<p>{user?.profile.name}</p><p>{(user ?? {}).name}</p>",
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn test_guards_do_not_warn() {
+        let warnings = warnings(
+            "<script>\nstate user = undefined\n</script>\n<p>{user ? user.name : 'Guest'}</p><p>{user && user.name}</p>{user ? <b>{user.name}</b> : <i>none</i>}",
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn test_known_values_loop_items_and_handlers_are_exempt() {
+        let warnings = warnings(
+            "<script>\nstate count = 0\nstate items = []\nstate user\nfunction save() {}\n</script>\n<p>{count.toFixed(2)}</p><ul>{items.map(user => <li>{user.name}</li>)}</ul><button onclick={() => user.save()}>x</button>",
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn test_unknown_initial_value_warns() {
+        let warnings = warnings(
+            "<script>\nstate user = loadUser()\nfunction loadUser() { return null }\n</script>\n<p title={user.name}>x</p>",
+        );
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(
+            warnings[0].contains("starts as `loadUser()`, which is not known at compile time"),
+            "{}",
+            warnings[0]
+        );
+    }
+}
@@ -0,0 +1,536 @@
+//! IR Observers
+//!
+//! Read-only hooks for computing custom metrics over a page without a compiler
+//! pass of their own (images missing dimensions, text length, the heading
+//! outline). Observers in `CompileOptions.observers` see the final node tree,
+//! after component resolution and lowering and just before transform:
+//!
+//! - `on_node` once per node in document order (a node before its children), with
+//!   a `NodeContext` describing where it sits;
+//! - `on_complete` once per page, writing named JSON values into an
+//!   `ObserverOutput`.
+//!
+//! The outputs of all observers are merged into the manifest's `observations`
+//! (a later observer's key replaces an earlier one's). An observer that panics is
+//! skipped for the rest of the page and reported as `Z-WARN-OBSERVER-PANIC`; the
+//! compile itself goes on.
+//!
+//! `Observers` clones share their observers, so one set can watch every page of
+//! a batch; state kept between pages is the observer's to reset in `on_complete`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+
+use crate::validate::{AttributeValue, TemplateNode, ZenIR};
+
+pub const OBSERVER_PANIC_WARNING: &str = "Z-WARN-OBSERVER-PANIC";
+
+/// A read-only visitor over the compiled node tree (see module docs)
+pub trait IrObserver: Send {
+    /// Name used in diagnostics
+    fn name(&self) -> &str {
+        "observer"
+    }
+
+    fn on_node(&mut self, _node: &TemplateNode, _ctx: &NodeContext) {}
+
+    fn on_complete(&mut self, _ir: &ZenIR, _output: &mut ObserverOutput) {}
+}
+
+/// Where a node sits in the tree
+#[derive(Debug, Clone, Default)]
+pub struct NodeContext {
+    /// 0 for top-level nodes; fragments count as a level
+    pub depth: usize,
+    /// Tag of the nearest enclosing element
+    pub parent_tag: Option<String>,
+    /// Instance (`inst0`) the node was rendered from, when its expressions tell:
+    /// every expression in its subtree comes from that one instance. Otherwise the
+    /// enclosing node's instance.
+    pub component_instance: Option<String>,
+    /// Item and index variables of the enclosing loops, outermost first
+    pub loop_variables: Vec<String>,
+    /// Code of the enclosing conditions (conditional, else-if and optional
+    /// fragments), outermost first
+    pub conditions: Vec<String>,
+    /// Inside `<head>`
+    pub in_head: bool,
+}
+
+/// Named JSON values an observer reports for a page
+#[derive(Debug, Clone, Default)]
+pub struct ObserverOutput {
+    values: BTreeMap<String, serde_json::Value>,
+}
+
+impl ObserverOutput {
+    pub fn insert(&mut self, key: impl Into<String>, value: serde_json::Value) {
+        self.values.insert(key.into(), value);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        self.values.get(key)
+    }
+}
+
+/// The observers of a compile; clones share them
+#[derive(Clone, Default)]
+pub struct Observers(Vec<Arc<Mutex<Box<dyn IrObserver>>>>);
+
+impl Observers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, observer: impl IrObserver + 'static) {
+        self.0.push(Arc::new(Mutex::new(Box::new(observer))));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<Box<dyn IrObserver>>> for Observers {
+    fn from(observers: Vec<Box<dyn IrObserver>>) -> Self {
+        Self(
+            observers
+                .into_iter()
+                .map(|observer| Arc::new(Mutex::new(observer)))
+                .collect(),
+        )
+    }
+}
+
+impl std::fmt::Debug for Observers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Observers").field(&self.0.len()).finish()
+    }
+}
+
+/// Run every observer over `ir`. Returns the merged outputs and the warnings of
+/// observers that panicked.
+pub(crate) fn run_observers(
+    observers: &Observers,
+    ir: &ZenIR,
+) -> (BTreeMap<String, serde_json::Value>, Vec<String>) {
+    let mut observations = BTreeMap::new();
+    let mut warnings = Vec::new();
+    if observers.is_empty() {
+        return (observations, warnings);
+    }
+    let expressions = expression_index(ir);
+    for observer in &observers.0 {
+        let mut observer = observer.lock().unwrap_or_else(|e| e.into_inner());
+        let mut walk = Walk {
+            observer: observer.as_mut(),
+            expressions: &expressions,
+            panicked: None,
+        };
+        walk.nodes(&ir.template.nodes, &NodeContext::default());
+        let panicked = walk.panicked;
+        let name = observer.name().to_string();
+        let outcome = match panicked {
+            Some(message) => Err(("on_node", message)),
+            None => {
+                let mut output = ObserverOutput::default();
+                catch_unwind(AssertUnwindSafe(|| observer.on_complete(ir, &mut output)))
+                    .map(|_| output)
+                    .map_err(|payload| ("on_complete", panic_message(payload)))
+            }
+        };
+        match outcome {
+            Ok(output) => observations.extend(output.values),
+            Err((hook, message)) => warnings.push(format!(
+                "{}: observer `{}` panicked in {} on {}: {}; its output for the page is dropped.",
+                OBSERVER_PANIC_WARNING, name, hook, ir.file_path, message
+            )),
+        }
+    }
+    (observations, warnings)
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Code and originating instance of each expression id
+fn expression_index(ir: &ZenIR) -> HashMap<&str, (&str, Option<&str>)> {
+    ir.template
+        .expressions
+        .iter()
+        .map(|e| {
+            let instance = e.origin.as_ref().map(|o| o.instance_id.as_str());
+            (e.id.as_str(), (e.code.as_str(), instance))
+        })
+        .collect()
+}
+
+struct Walk<'w> {
+    observer: &'w mut dyn IrObserver,
+    expressions: &'w HashMap<&'w str, (&'w str, Option<&'w str>)>,
+    /// Panic message; no more nodes are visited once it is set
+    panicked: Option<String>,
+}
+
+impl Walk<'_> {
+    fn nodes(&mut self, nodes: &[TemplateNode], ctx: &NodeContext) {
+        for node in nodes {
+            self.node(node, ctx);
+        }
+    }
+
+    fn node(&mut self, node: &TemplateNode, parent: &NodeContext) {
+        if self.panicked.is_some() {
+            return;
+        }
+        let mut ctx = parent.clone();
+        if let Some(instance) = self.subtree_instance(node) {
+            ctx.component_instance = Some(instance);
+        }
+        let observer = &mut self.observer;
+        if let Err(payload) = catch_unwind(AssertUnwindSafe(|| observer.on_node(node, &ctx))) {
+            self.panicked = Some(panic_message(payload));
+            return;
+        }
+
+        let mut inner = ctx.clone();
+        inner.depth += 1;
+        match node {
+            TemplateNode::Element(el) => {
+                inner.parent_tag = Some(el.tag.clone());
+                inner.in_head |= el.tag.eq_ignore_ascii_case("head");
+                self.nodes(&el.children, &inner);
+            }
+            TemplateNode::Component(c) => self.nodes(&c.children, &inner),
+            TemplateNode::ConditionalFragment(cf) => {
+                inner.conditions.push(self.code(&cf.condition));
+                self.nodes(&cf.consequent, &inner);
+                self.nodes(&cf.alternate, &inner);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for branch in &mb.branches {
+                    let mut branch_ctx = inner.clone();
+                    branch_ctx.conditions.push(self.code(&branch.condition));
+                    self.nodes(&branch.nodes, &branch_ctx);
+                }
+                self.nodes(&mb.default, &inner);
+            }
+            TemplateNode::OptionalFragment(of) => {
+                inner.conditions.push(self.code(&of.condition));
+                self.nodes(&of.fragment, &inner);
+            }
+            TemplateNode::LoopFragment(lf) => {
+                inner.loop_variables.push(lf.item_var.clone());
+                inner.loop_variables.extend(lf.index_var.clone());
+                self.nodes(&lf.body, &inner);
+            }
+            TemplateNode::Text(_) | TemplateNode::Expression(_) | TemplateNode::Doctype(_) => {}
+        }
+    }
+
+    fn code(&self, id: &str) -> String {
+        self.expressions
+            .get(id)
+            .map_or(id, |(code, _)| code)
+            .trim()
+            .trim_end_matches(';')
+            .to_string()
+    }
+
+    /// The one instance every expression in `node`'s subtree comes from
+    fn subtree_instance(&self, node: &TemplateNode) -> Option<String> {
+        let mut ids = Vec::new();
+        expression_ids(node, &mut ids);
+        let mut instance = None;
+        for id in ids {
+            let own = self.expressions.get(id.as_str())?.1?;
+            match instance {
+                None => instance = Some(own),
+                Some(seen) if seen == own => {}
+                Some(_) => return None,
+            }
+        }
+        instance.map(str::to_string)
+    }
+}
+
+fn expression_ids(node: &TemplateNode, ids: &mut Vec<String>) {
+    let children = |nodes: &[TemplateNode], ids: &mut Vec<String>| {
+        for node in nodes {
+            expression_ids(node, ids);
+        }
+    };
+    match node {
+        TemplateNode::Element(el) => {
+            for attr in &el.attributes {
+                if let AttributeValue::Dynamic(expr) = &attr.value {
+                    ids.push(expr.id.clone());
+                }
+            }
+            children(&el.children, ids);
+        }
+        TemplateNode::Expression(e) => ids.push(e.expression.clone()),
+        TemplateNode::Component(c) => children(&c.children, ids),
+        TemplateNode::ConditionalFragment(cf) => {
+            ids.push(cf.condition.clone());
+            children(&cf.consequent, ids);
+            children(&cf.alternate, ids);
+        }
+        TemplateNode::MultiBranchFragment(mb) => {
+            ids.extend(mb.branches.iter().map(|b| b.condition.clone()));
+            for nodes in mb.children() {
+                children(nodes, ids);
+            }
+        }
+        TemplateNode::OptionalFragment(of) => {
+            ids.push(of.condition.clone());
+            children(&of.fragment, ids);
+        }
+        TemplateNode::LoopFragment(lf) => {
+            ids.push(lf.source.clone());
+            children(&lf.body, ids);
+        }
+        TemplateNode::Text(_) | TemplateNode::Doctype(_) => {}
+    }
+}
+
+/// Example observer: the page's `h1`–`h6` outline, reported as
+/// `headingOutline: [{ level, text }]`. Expressions appear as `{code}` in the text.
+#[derive(Debug, Default)]
+pub struct HeadingOutline {
+    /// Level and text of each heading, expressions by id until `on_complete`
+    headings: Vec<(u8, Vec<HeadingPart>)>,
+}
+
+#[derive(Debug)]
+enum HeadingPart {
+    Text(String),
+    Expression(String),
+}
+
+impl IrObserver for HeadingOutline {
+    fn name(&self) -> &str {
+        "heading-outline"
+    }
+
+    fn on_node(&mut self, node: &TemplateNode, _ctx: &NodeContext) {
+        let TemplateNode::Element(el) = node else {
+            return;
+        };
+        let level = match el.tag.to_ascii_lowercase().as_str() {
+            "h1" => 1,
+            "h2" => 2,
+            "h3" => 3,
+            "h4" => 4,
+            "h5" => 5,
+            "h6" => 6,
+            _ => return,
+        };
+        let mut parts = Vec::new();
+        heading_parts(&el.children, &mut parts);
+        self.headings.push((level, parts));
+    }
+
+    fn on_complete(&mut self, ir: &ZenIR, output: &mut ObserverOutput) {
+        let outline = std::mem::take(&mut self.headings)
+            .into_iter()
+            .map(|(level, parts)| {
+                let text: String = parts
+                    .iter()
+                    .map(|part| match part {
+                        HeadingPart::Text(text) => text.clone(),
+                        HeadingPart::Expression(id) => {
+                            let code = ir
+                                .template
+                                .expressions
+                                .iter()
+                                .find(|e| &e.id == id)
+                                .map_or(id.as_str(), |e| e.code.trim().trim_end_matches(';'));
+                            format!("{{{}}}", code)
+                        }
+                    })
+                    .collect();
+                serde_json::json!({
+                    "level": level,
+                    "text": text.split_whitespace().collect::<Vec<_>>().join(" "),
+                })
+            })
+            .collect();
+        output.insert("headingOutline", serde_json::Value::Array(outline));
+    }
+}
+
+fn heading_parts(nodes: &[TemplateNode], parts: &mut Vec<HeadingPart>) {
+    for node in nodes {
+        match node {
+            TemplateNode::Text(t) => parts.push(HeadingPart::Text(t.value.clone())),
+            TemplateNode::Expression(e) => {
+                parts.push(HeadingPart::Expression(e.expression.clone()))
+            }
+            TemplateNode::Element(el) => heading_parts(&el.children, parts),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::ComponentIR;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    /// Records a label and the context of every node
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<(String, NodeContext)>>>);
+
+    impl IrObserver for Recorder {
+        fn on_node(&mut self, node: &TemplateNode, ctx: &NodeContext) {
+            let label = match node {
+                TemplateNode::Element(el) => el.tag.clone(),
+                TemplateNode::Text(t) => format!("'{}'", t.value.trim()),
+                TemplateNode::Expression(_) => "{expr}".to_string(),
+                TemplateNode::ConditionalFragment(_) => "?:".to_string(),
+                TemplateNode::OptionalFragment(_) => "&&".to_string(),
+                TemplateNode::LoopFragment(_) => "map".to_string(),
+                _ => "other".to_string(),
+            };
+            self.0.lock().unwrap().push((label, ctx.clone()));
+        }
+    }
+
+    struct Panics;
+
+    impl IrObserver for Panics {
+        fn name(&self) -> &str {
+            "panics"
+        }
+
+        fn on_node(&mut self, node: &TemplateNode, _ctx: &NodeContext) {
+            if matches!(node, TemplateNode::Element(el) if el.tag == "h2") {
+                panic!("no h2 please");
+            }
+        }
+    }
+
+    fn compile(page: &str, observers: Observers) -> CompileResult {
+        let card = ComponentIR::from_source(
+            "Card",
+            "components/Card.zen",
+            "<script>\nprop title\n</script>\n<article><h3>{title}</h3></article>",
+        )
+        .unwrap();
+        let options = CompileOptions {
+            ast_classifier: true,
+            components: [("Card".to_string(), serde_json::to_value(card).unwrap())].into(),
+            observers,
+            ..Default::default()
+        };
+        compile_zen_internal(page, "page.zen", options).unwrap()
+    }
+
+    const PAGE: &str = "<script>\nstate items = [\"a\"]\nstate open = true\nstate title = \"Hi\"\n</script>\n<main><h1>Welcome {title}</h1>{open ? <section><h2>News</h2></section> : <p>Closed</p>}<ul>{items.map((item, i) => <li>{item}</li>)}</ul><Card title={title} /></main>";
+
+    fn recorded(page: &str) -> Vec<(String, NodeContext)> {
+        let recorder = Recorder::default();
+        let mut observers = Observers::new();
+        observers.push(recorder.clone());
+        compile(page, observers);
+        let nodes = recorder.0.lock().unwrap().clone();
+        nodes
+    }
+
+    #[test]
+    fn test_nodes_arrive_in_document_order() {
+        let labels: Vec<String> = recorded(PAGE)
+            .into_iter()
+            .map(|(label, _)| label)
+            .filter(|label| label != "''")
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "main",
+                "h1",
+                "'Welcome'",
+                "{expr}",
+                "?:",
+                "section",
+                "h2",
+                "'News'",
+                "p",
+                "'Closed'",
+                "ul",
+                "map",
+                "li",
+                "{expr}",
+                "article",
+                "h3",
+                "{expr}"
+            ],
+        );
+    }
+
+    #[test]
+    fn test_context_describes_position() {
+        let nodes = recorded(PAGE);
+        let find = |label: &str| nodes.iter().find(|(l, _)| l == label).unwrap().1.clone();
+
+        let main = find("main");
+        assert_eq!((main.depth, main.parent_tag.clone()), (0, None));
+
+        let h2 = find("h2");
+        assert_eq!(h2.depth, 3);
+        assert_eq!(h2.parent_tag.as_deref(), Some("section"));
+        assert_eq!(h2.conditions, ["open"]);
+
+        let li = find("li");
+        assert_eq!(li.loop_variables, ["item", "i"]);
+        assert!(li.conditions.is_empty());
+
+        let h3 = find("h3");
+        assert_eq!(h3.component_instance.as_deref(), Some("inst0"));
+        assert_eq!(find("article").component_instance.as_deref(), Some("inst0"));
+        assert_eq!(main.component_instance, None);
+        assert!(!main.in_head);
+    }
+
+    #[test]
+    fn test_panicking_observer_is_isolated() {
+        let mut observers = Observers::new();
+        observers.push(Panics);
+        observers.push(HeadingOutline::default());
+        let result = compile(PAGE, observers);
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(
+            result.warnings.iter().any(|w| w.starts_with(
+                "Z-WARN-OBSERVER-PANIC: observer `panics` panicked in on_node on page.zen: no h2 please"
+            )),
+            "{:?}",
+            result.warnings
+        );
+        // Later observers still run
+        let observations = result.manifest.unwrap().observations;
+        assert!(observations.contains_key("headingOutline"));
+    }
+
+    #[test]
+    fn test_heading_outline() {
+        let mut observers = Observers::new();
+        observers.push(HeadingOutline::default());
+        let manifest = compile(PAGE, observers).manifest.unwrap();
+        assert_eq!(
+            manifest.observations["headingOutline"],
+            serde_json::json!([
+                { "level": 1, "text": "Welcome {title}" },
+                { "level": 2, "text": "News" },
+                { "level": 3, "text": "{window.__ZENITH_SCOPES__[\"inst0\"].props.title}" }
+            ])
+        );
+    }
+}
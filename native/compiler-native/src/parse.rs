@@ -13,147 +13,15 @@ use regex::Regex;
 
 #[cfg(feature = "napi")]
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::validate::{
     AttributeIR, CompilerError, ComponentNode, DoctypeNode, ElementNode, ExpressionIR,
     ExpressionNode, LoopContext, ScriptIR, SourceLocation, TemplateIR, TemplateNode, TextNode,
 };
 
-// ═══════════════════════════════════════════════════════════════════════════════
-// SVG ATTRIBUTE CASE MAPPING
-// ═══════════════════════════════════════════════════════════════════════════════
-
 lazy_static! {
-    /// SVG attribute case mapping - parse5/html5ever lowercases all attributes,
-    /// but SVG requires specific casing for these attributes.
-    static ref SVG_ATTR_CASE_MAP: HashMap<&'static str, &'static str> = {
-        let mut m = HashMap::new();
-        // Core SVG attributes with camelCase
-        m.insert("viewbox", "viewBox");
-        m.insert("preserveaspectratio", "preserveAspectRatio");
-        m.insert("basefrequency", "baseFrequency");
-        m.insert("baseprofile", "baseProfile");
-        m.insert("clippathunits", "clipPathUnits");
-        m.insert("diffuseconstant", "diffuseConstant");
-        m.insert("edgemode", "edgeMode");
-        m.insert("filterunits", "filterUnits");
-        m.insert("glyphref", "glyphRef");
-        m.insert("gradienttransform", "gradientTransform");
-        m.insert("gradientunits", "gradientUnits");
-        m.insert("kernelmatrix", "kernelMatrix");
-        m.insert("kernelunitlength", "kernelUnitLength");
-        m.insert("keypoints", "keyPoints");
-        m.insert("keysplines", "keySplines");
-        m.insert("keytimes", "keyTimes");
-        m.insert("lengthadjust", "lengthAdjust");
-        m.insert("limitingconeangle", "limitingConeAngle");
-        m.insert("markerheight", "markerHeight");
-        m.insert("markerunits", "markerUnits");
-        m.insert("markerwidth", "markerWidth");
-        m.insert("maskcontentunits", "maskContentUnits");
-        m.insert("maskunits", "maskUnits");
-        m.insert("numoctaves", "numOctaves");
-        m.insert("pathlength", "pathLength");
-        m.insert("patterncontentunits", "patternContentUnits");
-        m.insert("patterntransform", "patternTransform");
-        m.insert("patternunits", "patternUnits");
-        m.insert("pointsatx", "pointsAtX");
-        m.insert("pointsaty", "pointsAtY");
-        m.insert("pointsatz", "pointsAtZ");
-        m.insert("primitiveunits", "primitiveUnits");
-        m.insert("refx", "refX");
-        m.insert("refy", "refY");
-        m.insert("repeatcount", "repeatCount");
-        m.insert("repeatdur", "repeatDur");
-        m.insert("requiredextensions", "requiredExtensions");
-        m.insert("requiredfeatures", "requiredFeatures");
-        m.insert("specularconstant", "specularConstant");
-        m.insert("specularexponent", "specularExponent");
-        m.insert("spreadmethod", "spreadMethod");
-        m.insert("startoffset", "startOffset");
-        m.insert("stddeviation", "stdDeviation");
-        m.insert("stitchtiles", "stitchTiles");
-        m.insert("surfacescale", "surfaceScale");
-        m.insert("systemlanguage", "systemLanguage");
-        m.insert("tablevalues", "tableValues");
-        m.insert("targetx", "targetX");
-        m.insert("targety", "targetY");
-        m.insert("textlength", "textLength");
-        m.insert("xchannelselector", "xChannelSelector");
-        m.insert("ychannelselector", "yChannelSelector");
-        m.insert("zoomandpan", "zoomAndPan");
-        m.insert("attributename", "attributeName");
-        m.insert("attributetype", "attributeType");
-        m.insert("calcmode", "calcMode");
-        m.insert("clippath", "clipPath");
-        m
-    };
-
-    /// SVG tags set
-    static ref SVG_TAGS: std::collections::HashSet<&'static str> = {
-        let mut s = std::collections::HashSet::new();
-        s.insert("svg");
-        s.insert("path");
-        s.insert("circle");
-        s.insert("ellipse");
-        s.insert("line");
-        s.insert("polyline");
-        s.insert("polygon");
-        s.insert("rect");
-        s.insert("g");
-        s.insert("defs");
-        s.insert("use");
-        s.insert("symbol");
-        s.insert("clippath");
-        s.insert("mask");
-        s.insert("pattern");
-        s.insert("marker");
-        s.insert("lineargradient");
-        s.insert("radialgradient");
-        s.insert("stop");
-        s.insert("filter");
-        s.insert("feblend");
-        s.insert("fecolormatrix");
-        s.insert("fecomponenttransfer");
-        s.insert("fecomposite");
-        s.insert("feconvolvematrix");
-        s.insert("fediffuselighting");
-        s.insert("fedisplacementmap");
-        s.insert("fedropshadow");
-        s.insert("feflood");
-        s.insert("fefunca");
-        s.insert("fefuncb");
-        s.insert("fefuncg");
-        s.insert("fefuncr");
-        s.insert("fegaussianblur");
-        s.insert("feimage");
-        s.insert("femerge");
-        s.insert("femergenode");
-        s.insert("femorphology");
-        s.insert("feoffset");
-        s.insert("fespecularlighting");
-        s.insert("fetile");
-        s.insert("feturbulence");
-        s.insert("foreignobject");
-        s.insert("image");
-        s.insert("switch");
-        s.insert("text");
-        s.insert("tspan");
-        s.insert("textpath");
-        s.insert("title");
-        s.insert("desc");
-        s.insert("metadata");
-        s.insert("a");
-        s.insert("view");
-        s.insert("animate");
-        s.insert("animatemotion");
-        s.insert("animatetransform");
-        s.insert("set");
-        s.insert("mpath");
-        s
-    };
-
     /// Expression placeholder pattern for normalization
     static ref EXPR_PLACEHOLDER_RE: Regex = Regex::new(r"__ZENITH_EXPR_(\d+)__").unwrap();
 
@@ -164,12 +32,19 @@ lazy_static! {
     static ref ATTR_REGEX: Regex = Regex::new(r#"(?i)([a-z0-9-]+)(?:=(?:"([^"]*)"|'([^']*)'|([^>\s]+)))?"#).unwrap();
 
     /// Regex for extracting props: prop x = y
-    static ref PROP_RE: Regex = Regex::new(r"(?m)^\s*prop\s+([a-zA-Z_$][a-zA-Z0-9_$]*)(?:\s*=\s*([^;\n]+))?").unwrap();
+    static ref PROP_RE: Regex = Regex::new(r"(?m)^\s*prop\s+([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)(?:\s*=\s*([^;\n]+))?").unwrap();
 
     /// Regex for extracting state: state x = y
-    static ref STATE_RE: Regex = Regex::new(r"(?m)^\s*state\s+([a-zA-Z_$][a-zA-Z0-9_$]*)(?:\s*=\s*([^;\n]+))?").unwrap();
+    static ref STATE_RE: Regex = Regex::new(r"(?m)^\s*state\s+([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)(?:\s*=\s*([^;\n]+))?").unwrap();
+
+    /// Component opening tags: <HeroSection, <ui.Button, <ui:Button
+    static ref COMPONENT_OPEN_RE: Regex =
+        Regex::new(r"<((?:[a-z][a-zA-Z0-9-]*[.:])?[A-Z][a-zA-Z0-9.]+)").unwrap();
 }
 
+/// Carries the source position of a component tag (`line:column`) through parsing
+const LOCATION_ATTR: &str = "data-zen-loc";
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // TEMPLATE IR TYPES
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -182,28 +57,30 @@ use std::sync::atomic::{AtomicU64, Ordering};
 
 static EXPRESSION_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
 
+/// Provisional id while a template is converted; `parse_markup` replaces it with
+/// one anchored to the expression's place in the template (see `expr_ids.rs`)
 fn generate_expression_id() -> String {
     let id = EXPRESSION_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
     format!("expr_{}", id)
 }
 
+static NODE_ORDINAL_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Process-unique identity for a parsed element or text node, so it can be
+/// followed through restructuring (moved vs dropped, see `content_audit.rs`).
+/// Clones keep it; nodes built by the compiler or read from JSON have 0.
+pub(crate) fn next_node_ordinal() -> u64 {
+    NODE_ORDINAL_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // SVG ATTRIBUTE CORRECTION
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Correct SVG attribute casing - restores camelCase for SVG attributes
-fn correct_svg_attribute_name(attr_name: &str, tag_name: &str) -> String {
-    let lower_tag = tag_name.to_lowercase();
-    let lower_attr = attr_name.to_lowercase();
-
-    // Only apply SVG corrections for SVG elements
-    if SVG_TAGS.contains(lower_tag.as_str()) {
-        if let Some(&corrected) = SVG_ATTR_CASE_MAP.get(lower_attr.as_str()) {
-            return corrected.to_string();
-        }
-    }
-
-    attr_name.to_string()
+/// Correct SVG attribute casing - restores camelCase for SVG attributes, using the
+/// built-in tables (see `html_tables.rs`)
+pub(crate) fn correct_svg_attribute_name(attr_name: &str, tag_name: &str) -> String {
+    crate::html_tables::HtmlTables::default().svg_attribute_name(attr_name, tag_name)
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -211,30 +88,29 @@ fn correct_svg_attribute_name(attr_name: &str, tag_name: &str) -> String {
 // ═══════════════════════════════════════════════════════════════════════════════
 
 /// Find the end of a balanced brace expression, handling strings and template literals.
-/// Returns the index after the closing brace, or None if unbalanced.
+/// `start_index` is a byte offset; the result is the byte offset just past the closing
+/// brace (always a char boundary). Returns None when the construct is unterminated
+/// (including an open string or template literal at EOF) or `start_index` does not
+/// fall on a char boundary.
 fn find_balanced_brace_end(html: &str, start_index: usize) -> Option<usize> {
-    let chars: Vec<char> = html.chars().collect();
+    let mut chars = html.get(start_index..)?.char_indices().peekable();
     let mut depth = 0;
-    let mut i = start_index;
     let mut in_string: Option<char> = None;
     let mut in_template_literal = false;
     let mut template_brace_depth = 0;
 
-    while i < chars.len() {
-        let c = chars[i];
-
-        // Handle escape sequences
-        if c == '\\' && i + 1 < chars.len() {
-            i += 2;
+    while let Some((offset, c)) = chars.next() {
+        // Handle escape sequences (the escaped char may be multi-byte)
+        if c == '\\' {
+            chars.next();
             continue;
         }
 
         // Handle strings
-        if in_string.is_some() {
-            if Some(c) == in_string {
+        if let Some(quote) = in_string {
+            if c == quote {
                 in_string = None;
             }
-            i += 1;
             continue;
         }
 
@@ -242,42 +118,27 @@ fn find_balanced_brace_end(html: &str, start_index: usize) -> Option<usize> {
         if in_template_literal {
             if c == '`' && template_brace_depth == 0 {
                 in_template_literal = false;
-            } else if c == '$' && i + 1 < chars.len() && chars[i + 1] == '{' {
+            } else if c == '$' && chars.peek().map(|&(_, next)| next) == Some('{') {
                 template_brace_depth += 1;
-                i += 2;
-                continue;
+                chars.next();
             } else if c == '}' && template_brace_depth > 0 {
                 template_brace_depth -= 1;
             }
-            i += 1;
-            continue;
-        }
-
-        // Check for string delimiters
-        if c == '"' || c == '\'' {
-            in_string = Some(c);
-            i += 1;
-            continue;
-        }
-
-        // Check for template literal
-        if c == '`' {
-            in_template_literal = true;
-            i += 1;
             continue;
         }
 
-        // Track brace depth
-        if c == '{' {
-            depth += 1;
-        } else if c == '}' {
-            depth -= 1;
-            if depth == 0 {
-                return Some(i + 1);
+        match c {
+            '"' | '\'' => in_string = Some(c),
+            '`' => in_template_literal = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(start_index + offset + 1);
+                }
             }
+            _ => {}
         }
-
-        i += 1;
     }
 
     None
@@ -285,21 +146,18 @@ fn find_balanced_brace_end(html: &str, start_index: usize) -> Option<usize> {
 
 /// Normalize expressions before parsing.
 /// Replaces both attr={expr} and {textExpr} with placeholders so html5ever can parse correctly.
+/// A `{` that never closes is kept as literal text (see `unterminated_expression_warnings`).
 fn normalize_all_expressions(html: &str) -> (String, HashMap<String, String>) {
-    let mut normalized = String::new();
+    let mut normalized = String::with_capacity(html.len());
     let mut expressions = HashMap::new();
     let mut expr_counter = 0;
-    let chars: Vec<char> = html.chars().collect();
     let mut i = 0;
 
-    while i < chars.len() {
-        let c = chars[i];
-
+    while let Some(c) = html[i..].chars().next() {
         // Check for opening brace that starts an expression
         if c == '{' {
-            // Make sure it's not an escaped brace or inside a string
             if let Some(end) = find_balanced_brace_end(html, i) {
-                let mut expr_content: String = chars[i + 1..end - 1].iter().collect();
+                let mut expr_content = html[i + 1..end - 1].to_string();
 
                 // STRIP HTML COMMENTS: Expressions like { items.map(i => ( <!-- comment --> <div/> )) }
                 // contain HTML comments which are invalid in JS context.
@@ -318,19 +176,232 @@ fn normalize_all_expressions(html: &str) -> (String, HashMap<String, String>) {
         }
 
         normalized.push(c);
-        i += 1;
+        i += c.len_utf8();
     }
 
     (normalized, expressions)
 }
 
+/// Every balanced `{...}` has been replaced by a placeholder, so a `{` left in the
+/// normalized template opens an expression that never closes.
+fn unterminated_expression_warnings(normalized: &str, file_path: &str) -> Vec<String> {
+    normalized
+        .match_indices('{')
+        .map(|(offset, _)| {
+            let excerpt: String = normalized[offset..]
+                .chars()
+                .take_while(|c| *c != '\n')
+                .take(24)
+                .collect();
+            format!(
+                "Z-WARN-UNTERMINATED-EXPRESSION: `{}` in {} opens an expression that is never closed; it is rendered as literal text.",
+                EXPR_PLACEHOLDER_RE.replace_all(&excerpt, "{…}"),
+                file_path
+            )
+        })
+        .collect()
+}
+
+/// Attributes whose duplicates on one element are merged rather than dropped
+const MERGED_ATTRS: &[&str] = &["class", "style"];
+
+/// Carries the static part of a `class` / `style` that also has a dynamic value
+const MERGE_ATTR_PREFIX: &str = "data-zen-merge-";
+
+/// html5ever keeps only the first of duplicate attributes, so repeated `class` and
+/// `style` attributes on one element are combined before parsing:
+///
+/// - static + static: joined into one attribute, with a warning
+/// - static + dynamic: the dynamic value stays on the attribute and the static part
+///   moves to `data-zen-merge-<name>`; a `class` expression that folds to a constant
+///   is joined statically instead
+/// - several dynamic values: combined into a single expression
+///
+/// Markup lowered from a JSX expression (`lowered_context` describes where it came
+/// from) also keeps the last of any other duplicated attribute, with a warning.
+fn merge_duplicate_attributes(
+    normalized: &str,
+    exprs: &mut HashMap<String, String>,
+    file_path: &str,
+    lowered_context: Option<&str>,
+) -> (String, Vec<String>) {
+    lazy_static! {
+        static ref START_TAG_RE: Regex = Regex::new(
+            r#"<([A-Za-z][^\s/>]*)((?:\s+[^\s"'=<>/]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*)\s*(/?)>"#
+        )
+        .unwrap();
+        static ref ATTR_TOKEN_RE: Regex = Regex::new(
+            r#"([^\s"'=<>/]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#
+        )
+        .unwrap();
+    }
+
+    let mut warnings = Vec::new();
+    let merged = START_TAG_RE.replace_all(normalized, |caps: &regex::Captures| {
+        let tokens: Vec<(String, &str, &str)> = ATTR_TOKEN_RE
+            .captures_iter(caps.get(2).map_or("", |m| m.as_str()))
+            .map(|c| {
+                let value = c
+                    .get(2)
+                    .or(c.get(3))
+                    .or(c.get(4))
+                    .map_or("", |m| m.as_str());
+                (c[1].to_lowercase(), c.get(0).unwrap().as_str(), value)
+            })
+            .collect();
+        let count = |name: &str| tokens.iter().filter(|(n, _, _)| n == name).count();
+        let duplicated: Vec<&str> = MERGED_ATTRS
+            .iter()
+            .copied()
+            .filter(|name| count(name) > 1)
+            .collect();
+        let keep_last = lowered_context.is_some()
+            && tokens
+                .iter()
+                .any(|(n, _, _)| count(n) > 1 && !MERGED_ATTRS.contains(&n.as_str()));
+        if duplicated.is_empty() && !keep_last {
+            return caps[0].to_string();
+        }
+
+        let tag = &caps[1];
+        let mut out = Vec::new();
+        let mut emitted = Vec::new();
+        for (i, (name, raw, _)) in tokens.iter().enumerate() {
+            let later = tokens[i + 1..].iter().filter(|(n, _, _)| n == name).count();
+            if let (Some(context), false) = (lowered_context, MERGED_ATTRS.contains(&name.as_str()))
+            {
+                if later > 0 {
+                    continue;
+                }
+                let total = count(name);
+                if total > 1 {
+                    let shown = EXPR_PLACEHOLDER_RE.replace_all(raw, |c: &regex::Captures| {
+                        format!("{{{}}}", exprs.get(&c[0]).map_or("", String::as_str))
+                    });
+                    warnings.push(format!(
+                        "Z-WARN-DUPLICATE-ATTRIBUTE: <{}> in {} in {} has {} `{}` attributes; only the last one ({}) is kept.",
+                        tag, context, file_path, total, name, shown
+                    ));
+                }
+                out.push(raw.to_string());
+            } else if !duplicated.contains(&name.as_str()) {
+                out.push(raw.to_string());
+            } else if !emitted.contains(name) {
+                emitted.push(name.clone());
+                let values: Vec<&str> = tokens
+                    .iter()
+                    .filter(|(n, _, _)| n == name)
+                    .map(|(_, _, v)| *v)
+                    .collect();
+                out.extend(merged_attribute(
+                    tag,
+                    name,
+                    &values,
+                    exprs,
+                    file_path,
+                    &mut warnings,
+                ));
+            }
+        }
+        format!("<{} {}{}>", tag, out.join(" "), &caps[3])
+    });
+
+    (merged.into_owned(), warnings)
+}
+
+/// One `name="..."` (plus a `data-zen-merge-` marker when a static part rides along)
+/// for the values of a duplicated attribute
+fn merged_attribute(
+    tag: &str,
+    name: &str,
+    values: &[&str],
+    exprs: &mut HashMap<String, String>,
+    file_path: &str,
+    warnings: &mut Vec<String>,
+) -> Vec<String> {
+    let separator = crate::transform::merged_attr_separator(name);
+    let mut statics = Vec::new();
+    let mut dynamics = Vec::new();
+    let mut literal_count = 0;
+    for value in values {
+        let code = EXPR_PLACEHOLDER_RE
+            .find(value)
+            .and_then(|m| exprs.get(m.as_str()))
+            .cloned();
+        match code {
+            // A constant class expression is as good as a static value
+            Some(code) if name == "class" => {
+                match crate::static_eval::static_eval(&code, &HashMap::new()) {
+                    Some(v) if !matches!(v.as_str(), "null" | "undefined" | "false") => {
+                        statics.push(v)
+                    }
+                    Some(_) => {}
+                    None => dynamics.push(code),
+                }
+            }
+            Some(code) => dynamics.push(code),
+            None => {
+                literal_count += 1;
+                statics.push(value.to_string());
+            }
+        }
+    }
+
+    let static_value = statics
+        .iter()
+        .map(|v| v.trim().trim_end_matches(';').trim())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>()
+        .join(separator);
+    let quoted = |v: &str| format!("\"{}\"", v.replace('"', "&quot;"));
+
+    if dynamics.is_empty() {
+        if literal_count == values.len() {
+            warnings.push(format!(
+                "Z-WARN-DUPLICATE-ATTRIBUTE: <{}> in {} has {} static `{}` attributes; they were merged into {}={}.",
+                tag,
+                file_path,
+                values.len(),
+                name,
+                name,
+                quoted(&static_value)
+            ));
+        }
+        return vec![format!("{}={}", name, quoted(&static_value))];
+    }
+
+    let code = if dynamics.len() == 1 {
+        dynamics.remove(0)
+    } else {
+        let parts: Vec<String> = dynamics.iter().map(|c| format!("({})", c)).collect();
+        format!(
+            "[{}].filter(Boolean).join(\"{}\")",
+            parts.join(", "),
+            separator
+        )
+    };
+    let placeholder = format!("__ZENITH_EXPR_{}__", exprs.len());
+    exprs.insert(placeholder.clone(), code);
+
+    let mut attrs = vec![format!("{}=\"{}\"", name, placeholder)];
+    if !static_value.is_empty() {
+        attrs.push(format!(
+            "{}{}={}",
+            MERGE_ATTR_PREFIX,
+            name,
+            quoted(&static_value)
+        ));
+    }
+    attrs
+}
+
 /// Convert self-closing component tags to properly closed tags.
 /// HTML5/html5ever treats `<ComponentName />` as an opening tag,
 /// causing following siblings to be incorrectly nested as children.
 fn convert_self_closing_components(html: &str) -> String {
     lazy_static! {
         static ref SELF_CLOSING_RE: Regex =
-            Regex::new(r"<([A-Z][a-zA-Z0-9]*)\s*([^>]*?)\s*/>").unwrap();
+            Regex::new(r"<((?:[a-z][a-zA-Z0-9-]*[.:])?[A-Z][a-zA-Z0-9]*)\s*([^>]*?)\s*/>").unwrap();
     }
 
     SELF_CLOSING_RE
@@ -394,30 +465,124 @@ fn strip_comments(html: &str) -> String {
 /// because html5ever lowercases all tag names.
 fn mark_component_tags(html: &str) -> String {
     lazy_static! {
-        static ref TAG_OPEN_RE: Regex = Regex::new(r"<([A-Z][a-zA-Z0-9.]+)(\s|>)").unwrap();
-        // Closing tags: </HeroSection>
-        static ref TAG_CLOSE_RE: Regex = Regex::new(r"</([A-Z][a-zA-Z0-9.]*)>").unwrap();
+        // Closing tags: </HeroSection>, </ui.Button>
+        static ref TAG_CLOSE_RE: Regex =
+            Regex::new(r"</((?:[a-z][a-zA-Z0-9-]*[.:])?[A-Z][a-zA-Z0-9.]*)>").unwrap();
     }
 
-    let marked = TAG_OPEN_RE.replace_all(html, |caps: &regex::Captures| {
-        let name = &caps[1];
-        let sep = &caps[2];
-        format!("<{} data-zen-orig-name=\"{}\"{}", name, name, sep)
-    });
+    // The marker goes right after the name; the separator that follows (a space,
+    // a line break, `>` or `/`) is left as written
+    let mut marked = String::with_capacity(html.len());
+    let mut last = 0;
+    for caps in COMPONENT_OPEN_RE.captures_iter(html) {
+        let (whole, name) = (caps.get(0).unwrap(), caps.get(1).unwrap());
+        if !ends_tag_name(html, whole.end()) {
+            continue;
+        }
+        marked.push_str(&html[last..whole.start()]);
+        marked.push_str(&format!(
+            "<{} data-zen-orig-name=\"{}\"",
+            parsed_component_tag(name.as_str()),
+            name.as_str()
+        ));
+        last = whole.end();
+    }
+    marked.push_str(&html[last..]);
 
-    TAG_CLOSE_RE.replace_all(&marked, "</$1>").to_string()
+    TAG_CLOSE_RE
+        .replace_all(&marked, |caps: &regex::Captures| {
+            format!("</{}>", parsed_component_tag(&caps[1]))
+        })
+        .to_string()
 }
 
-/// Check if a tag name represents a component (starts with uppercase)
+fn ends_tag_name(html: &str, at: usize) -> bool {
+    html[at..]
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_whitespace() || c == '>' || c == '/')
+}
+
+/// Pre-pass recording where each component tag starts, as `data-zen-loc`: the
+/// later passes rewrite the source, so positions are taken before them. Tags in
+/// script and style blocks, comments and `{...}` expressions are not marked.
+fn mark_component_locations(html: &str) -> String {
+    lazy_static! {
+        static ref SKIPPED_RE: Regex =
+            Regex::new(r"(?is)<script\b[^>]*>.*?</script>|<style\b[^>]*>.*?</style>|<!--.*?-->")
+                .unwrap();
+    }
+
+    let mut skipped: Vec<(usize, usize)> = SKIPPED_RE
+        .find_iter(html)
+        .map(|m| (m.start(), m.end()))
+        .collect();
+    let mut blocks = skipped.clone().into_iter().peekable();
+    let mut i = 0;
+    while let Some(c) = html[i..].chars().next() {
+        if let Some(&(start, end)) = blocks.peek() {
+            if i >= start {
+                i = i.max(end);
+                blocks.next();
+                continue;
+            }
+        }
+        if c == '{' {
+            if let Some(end) = find_balanced_brace_end(html, i) {
+                skipped.push((i, end));
+                i = end;
+                continue;
+            }
+        }
+        i += c.len_utf8();
+    }
+
+    let mut marked = String::with_capacity(html.len());
+    let mut last = 0;
+    for m in COMPONENT_OPEN_RE.find_iter(html) {
+        if !ends_tag_name(html, m.end())
+            || skipped
+                .iter()
+                .any(|&(start, end)| (start..end).contains(&m.start()))
+        {
+            continue;
+        }
+        let (line, column) = crate::content_audit::line_column(html, m.start());
+        marked.push_str(&html[last..m.end()]);
+        marked.push_str(&format!(" {}=\"{}:{}\"", LOCATION_ATTR, line, column));
+        last = m.end();
+    }
+    marked.push_str(&html[last..]);
+    marked
+}
+
+/// Tag name a component is parsed under. html5ever lets block-level elements
+/// close an open paragraph (`<p>See <Nav>…` would end the `p` before `Nav`), so
+/// components named like one are parsed under a neutral name;
+/// `data-zen-orig-name` restores it.
+fn parsed_component_tag(name: &str) -> String {
+    let lower = name.to_ascii_lowercase();
+    if crate::minify::is_block_tag(&lower) {
+        format!("zen-{}", lower)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Check if a tag name represents a component (starts with uppercase,
+/// optionally behind a namespace as in `ui.Button` / `ui:Button`)
 pub fn is_component_tag(tag_name: &str) -> bool {
-    tag_name
+    let (_, local) = crate::component::split_component_name(tag_name);
+    local
         .chars()
         .next()
         .map(|c| c.is_uppercase())
         .unwrap_or(false)
 }
 
-/// Parse DOM node to TemplateNode
+/// Parse DOM node to TemplateNode. Iterative, since generated markup can nest
+/// thousands of levels deep (see `depth.rs`).
+#[allow(clippy::too_many_arguments)]
 fn parse_dom_node(
     handle: &Handle,
     expressions: &mut Vec<ExpressionIR>,
@@ -425,52 +590,118 @@ fn parse_dom_node(
     inline_scripts: &HashMap<String, String>,
     parent_loop_context: Option<&LoopContext>,
     file_path: &str,
+    tables: &crate::html_tables::HtmlTables,
     is_in_head: bool,
-) -> Result<Vec<TemplateNode>, CompilerError> {
-    let node = handle;
-
-    match &node.data {
-        NodeData::Document => {
-            // Process children of document
-            let children = node.children.borrow();
-            let mut nodes = Vec::new();
-            for child in children.iter() {
-                nodes.extend(parse_dom_node(
-                    child,
+) -> Result<Vec<TemplateNode>, Box<CompilerError>> {
+    let mut stack = vec![OpenNode {
+        element: None,
+        children_in_head: is_in_head,
+        pending: vec![handle.clone()].into_iter(),
+        children: Vec::new(),
+    }];
+    while let Some(top) = stack.last_mut() {
+        match top.pending.next() {
+            Some(child) => {
+                match open_dom_node(
+                    &child,
                     expressions,
                     normalized_exprs,
                     inline_scripts,
                     parent_loop_context,
                     file_path,
-                    is_in_head,
-                )?);
+                    tables,
+                    top.children_in_head,
+                )? {
+                    DomNode::Parsed(nodes) => top.children.extend(nodes),
+                    DomNode::Open(node) => stack.push(node),
+                }
+            }
+            None => {
+                let parsed = stack.pop().unwrap().close(parent_loop_context);
+                match stack.last_mut() {
+                    Some(parent) => parent.children.extend(parsed),
+                    None => return Ok(parsed),
+                }
             }
-            Ok(nodes)
         }
+    }
+    unreachable!("the outermost node returns when it closes")
+}
+
+/// A DOM node as `open_dom_node` leaves it
+enum DomNode {
+    /// Converted completely
+    Parsed(Vec<TemplateNode>),
+    /// Waiting for its children
+    Open(OpenNode),
+}
+
+/// A document or element whose children are still being converted
+struct OpenNode {
+    /// None for the document, which contributes just its children
+    element: Option<OpenElement>,
+    children_in_head: bool,
+    pending: std::vec::IntoIter<Handle>,
+    children: Vec<TemplateNode>,
+}
+
+struct OpenElement {
+    tag_name: String,
+    attributes: Vec<AttributeIR>,
+    /// Restored body of an inline script, which replaces its children
+    script_content: Option<String>,
+    /// Position of a component tag in the source (see `mark_component_locations`)
+    location: Option<SourceLocation>,
+}
+
+/// Convert a node up to its children: attribute expressions are registered
+/// before anything inside the element, as the recursive conversion did.
+#[allow(clippy::too_many_arguments)]
+fn open_dom_node(
+    handle: &Handle,
+    expressions: &mut Vec<ExpressionIR>,
+    normalized_exprs: &HashMap<String, String>,
+    inline_scripts: &HashMap<String, String>,
+    parent_loop_context: Option<&LoopContext>,
+    file_path: &str,
+    tables: &crate::html_tables::HtmlTables,
+    is_in_head: bool,
+) -> Result<DomNode, Box<CompilerError>> {
+    let node = handle;
+
+    match &node.data {
+        NodeData::Document => Ok(DomNode::Open(OpenNode {
+            element: None,
+            children_in_head: is_in_head,
+            pending: node.children.borrow().clone().into_iter(),
+            children: Vec::new(),
+        })),
 
         NodeData::Doctype {
             name,
             public_id,
             system_id,
-        } => Ok(vec![TemplateNode::Doctype(DoctypeNode {
+        } => Ok(DomNode::Parsed(vec![TemplateNode::Doctype(DoctypeNode {
             name: name.to_string(),
             public_id: public_id.to_string(),
             system_id: system_id.to_string(),
             location: SourceLocation { line: 1, column: 1 },
-        })]),
+            extra: Default::default(),
+        })])),
 
         NodeData::Text { contents } => {
             let text = contents.borrow().to_string();
 
             // Process text that may contain multiple expressions.
             // process_text_with_expressions handles plain text, single expressions, and mixed content.
-            Ok(process_text_with_expressions(
+            Ok(DomNode::Parsed(process_text_with_expressions(
                 &text,
                 expressions,
                 normalized_exprs,
                 parent_loop_context,
                 is_in_head,
-            ))
+                &TextContext::of(handle),
+            )))
         }
 
         NodeData::Element { name, attrs, .. } => {
@@ -487,7 +718,7 @@ fn parse_dom_node(
 
             // INVARIANT: Rejects <template> tag (INV005)
             if tag_name.to_lowercase() == "template" {
-                return Err(CompilerError::with_details(
+                return Err(Box::new(CompilerError::with_details(
                     "INV005",
                     "The <template> tag is not supported in Zenith. Use structural fragments or components instead.",
                     file_path,
@@ -495,7 +726,7 @@ fn parse_dom_node(
                     1,
                     Some("<template>".to_string()),
                     vec![],
-                ));
+                )));
             }
             // INLINE SCRIPT RESTORATION
             let mut script_content = None;
@@ -512,10 +743,46 @@ fn parse_dom_node(
 
             // Parse attributes
             let mut parsed_attrs = Vec::new();
+            let mut location = None;
             for attr in attributes.iter() {
-                let attr_name = correct_svg_attribute_name(&attr.name.local.to_string(), &tag_name);
+                if &*attr.name.local == LOCATION_ATTR {
+                    location = attr.value.split_once(':').and_then(|(line, column)| {
+                        Some(SourceLocation {
+                            line: line.parse().ok()?,
+                            column: column.parse().ok()?,
+                        })
+                    });
+                    continue;
+                }
+                let attr_name = tables.svg_attribute_name(&attr.name.local, &tag_name);
                 let attr_value = attr.value.to_string();
 
+                // Conditional component props: `<Tooltip text?={maybeText}>`
+                let (attr_name, optional) = match attr_name.strip_suffix('?') {
+                    Some(prop) if is_component_tag(&tag_name) => (prop.to_string(), true),
+                    _ => (attr_name, false),
+                };
+
+                // Static half of a merged class/style goes just before its dynamic half
+                if let Some(merged) = attr_name.strip_prefix(MERGE_ATTR_PREFIX) {
+                    let at = parsed_attrs
+                        .iter()
+                        .position(|a: &AttributeIR| a.name == merged)
+                        .unwrap_or(parsed_attrs.len());
+                    parsed_attrs.insert(
+                        at,
+                        AttributeIR {
+                            name: merged.to_string(),
+                            value: crate::validate::AttributeValue::Static(attr_value),
+                            location: SourceLocation { line: 1, column: 1 },
+                            loop_context: parent_loop_context.cloned(),
+                            optional: false,
+                            extra: Default::default(),
+                        },
+                    );
+                    continue;
+                }
+
                 // Check if attribute value contains an expression
                 if let Some(caps) = EXPR_PLACEHOLDER_RE.captures(&attr_value) {
                     let placeholder = caps.get(0).unwrap().as_str();
@@ -526,13 +793,36 @@ fn parse_dom_node(
                             code: expr_code.clone(),
                             location: SourceLocation { line: 1, column: 1 },
                             loop_context: parent_loop_context.cloned(),
+                            origin: None,
+                            extra: Default::default(),
                         };
                         expressions.push(expr_ir.clone());
+                        if attr_name == BIND_VALUE_ATTR && !is_component_tag(&tag_name) {
+                            let target = expr_ir.code.trim().to_string();
+                            let bound = bind_value(&tag_name, expr_ir, expressions);
+                            parsed_attrs.extend(bound.ok_or_else(|| {
+                                CompilerError::with_details(
+                                    "Z-ERR-BIND-VALUE",
+                                    &format!(
+                                        "Z-ERR-BIND-VALUE: `bind:value={{{}}}` on <{}>: bind:value is supported on <select> with a state name. Use `prop:value` with an `onchange` handler instead.",
+                                        target, tag_name
+                                    ),
+                                    file_path,
+                                    1,
+                                    1,
+                                    Some(format!("<{} bind:value={{{}}}>", tag_name, target)),
+                                    vec![],
+                                )
+                            })?);
+                            continue;
+                        }
                         parsed_attrs.push(AttributeIR {
                             name: attr_name,
                             value: crate::validate::AttributeValue::Dynamic(expr_ir),
                             location: SourceLocation { line: 1, column: 1 },
                             loop_context: parent_loop_context.cloned(),
+                            optional,
+                            extra: Default::default(),
                         });
                         continue;
                     }
@@ -543,71 +833,233 @@ fn parse_dom_node(
                     value: crate::validate::AttributeValue::Static(attr_value),
                     location: SourceLocation { line: 1, column: 1 },
                     loop_context: parent_loop_context.cloned(),
+                    optional,
+                    extra: Default::default(),
                 });
             }
 
-            // Parse children
-            let children_handles = node.children.borrow();
-            let mut children = Vec::new();
+            // Children are converted by the caller. Detect if we're entering <head> element
+            let children_in_head = is_in_head || tag_name.to_lowercase() == "head";
+            Ok(DomNode::Open(OpenNode {
+                element: Some(OpenElement {
+                    tag_name,
+                    attributes: parsed_attrs,
+                    script_content,
+                    location,
+                }),
+                children_in_head,
+                pending: node.children.borrow().clone().into_iter(),
+                children: Vec::new(),
+            }))
+        }
 
-            // Detect if we're entering <head> element
-            let child_is_in_head = is_in_head || tag_name.to_lowercase() == "head";
+        NodeData::Comment { .. } => Ok(DomNode::Parsed(vec![])),
+        NodeData::ProcessingInstruction { .. } => Ok(DomNode::Parsed(vec![])),
+    }
+}
 
-            for child in children_handles.iter() {
-                children.extend(parse_dom_node(
-                    child,
-                    expressions,
-                    normalized_exprs,
-                    inline_scripts,
-                    parent_loop_context,
-                    file_path,
-                    child_is_in_head,
-                )?);
-            }
+impl OpenNode {
+    /// The finished node, once all children are converted
+    fn close(self, parent_loop_context: Option<&LoopContext>) -> Vec<TemplateNode> {
+        let Some(OpenElement {
+            tag_name,
+            attributes: parsed_attrs,
+            script_content,
+            location,
+        }) = self.element
+        else {
+            return self.children;
+        };
+        let children = self.children;
+
+        // Check if this is a component (uppercase first letter)
+        if is_component_tag(&tag_name) {
+            vec![TemplateNode::Component(ComponentNode {
+                name: tag_name,
+                attributes: parsed_attrs,
+                children,
+                location: location.unwrap_or_default(),
+                loop_context: parent_loop_context.cloned(),
+                extra: Default::default(),
+            })]
+        } else {
+            vec![TemplateNode::Element(ElementNode {
+                tag: tag_name,
+                attributes: parsed_attrs
+                    .into_iter()
+                    .filter(|a| a.name != "data-zen-inline-id")
+                    .collect(),
+                children: if let Some(content) = script_content {
+                    vec![TemplateNode::Text(TextNode {
+                        value: content,
+                        location: SourceLocation { line: 1, column: 1 },
+                        loop_context: parent_loop_context.cloned(),
+                        ordinal: next_node_ordinal(),
+                        extra: Default::default(),
+                    })]
+                } else {
+                    children
+                },
+                location: SourceLocation { line: 1, column: 1 },
+                loop_context: parent_loop_context.cloned(),
+                ordinal: next_node_ordinal(),
+                extra: Default::default(),
+            })]
+        }
+    }
+}
 
-            // Check if this is a component (uppercase first letter)
-            if is_component_tag(&tag_name) {
-                Ok(vec![TemplateNode::Component(ComponentNode {
-                    name: tag_name,
-                    attributes: parsed_attrs,
-                    children,
-                    location: SourceLocation { line: 1, column: 1 },
-                    loop_context: parent_loop_context.cloned(),
-                })])
-            } else {
-                Ok(vec![TemplateNode::Element(ElementNode {
-                    tag: tag_name,
-                    attributes: parsed_attrs
-                        .into_iter()
-                        .filter(|a| a.name != "data-zen-inline-id")
-                        .collect(),
-                    children: if let Some(content) = script_content {
-                        vec![TemplateNode::Text(TextNode {
-                            value: content,
-                            location: SourceLocation { line: 1, column: 1 },
-                            loop_context: parent_loop_context.cloned(),
-                        })]
-                    } else {
-                        children
-                    },
-                    location: SourceLocation { line: 1, column: 1 },
-                    loop_context: parent_loop_context.cloned(),
-                })])
+/// `<select bind:value={current}>`: two-way binding of the selected option
+const BIND_VALUE_ATTR: &str = "bind:value";
+
+/// `bind:value={current}` as `prop:value={current}` plus an `onchange` handler that
+/// assigns the chosen option's value back to the state. None unless the element
+/// is a `<select>` and the value a plain state name.
+fn bind_value(
+    tag: &str,
+    expr: ExpressionIR,
+    expressions: &mut Vec<ExpressionIR>,
+) -> Option<[AttributeIR; 2]> {
+    let target = expr.code.trim();
+    if !tag.eq_ignore_ascii_case("select") || !crate::static_eval::is_valid_identifier(target) {
+        return None;
+    }
+    let handler = ExpressionIR {
+        id: generate_expression_id(),
+        code: format!("(event) => {} = event.target.value", target),
+        ..expr.clone()
+    };
+    expressions.push(handler.clone());
+    let attribute = |name: &str, expr: ExpressionIR| AttributeIR {
+        name: name.to_string(),
+        location: expr.location.clone(),
+        loop_context: expr.loop_context.clone(),
+        value: crate::validate::AttributeValue::Dynamic(expr),
+        optional: false,
+        extra: Default::default(),
+    };
+    Some([
+        attribute("prop:value", expr),
+        attribute("onchange", handler),
+    ])
+}
+
+/// Where a text node sits, deciding whether its whitespace renders: a
+/// whitespace-only node, or whitespace next to an expression placeholder.
+/// Between inline content a whitespace run is one space; at the edge of a
+/// block-level parent or of a component's children, beside a block-level
+/// sibling, or between SVG shapes it is nothing (see `minify::BLOCK_ELEMENTS`).
+/// What a component renders is not known here, so whitespace beside one renders
+/// when the component sits in running text: its parent is inline, holds
+/// phrasing content (`PHRASING_PARENTS`), or has visible text of its own.
+struct TextContext {
+    /// Whitespace before the text's first placeholder renders
+    leading: bool,
+    /// Whitespace after the text's last placeholder renders
+    trailing: bool,
+    /// Inside `pre`, `textarea` and the like whitespace is kept as written
+    preserve: bool,
+}
+
+impl TextContext {
+    fn of(handle: &Handle) -> Self {
+        let parent = handle.parent.take();
+        handle.parent.set(parent.clone());
+        let dropped = Self {
+            leading: false,
+            trailing: false,
+            preserve: false,
+        };
+        let Some(parent) = parent.and_then(|p| p.upgrade()) else {
+            return dropped;
+        };
+        let (parent_is_block, holds_phrasing, preserve) = match &parent.data {
+            // Inside SVG only text content elements render whitespace
+            NodeData::Element { name, .. }
+                if &*name.ns == "http://www.w3.org/2000/svg"
+                    && !SVG_TEXT_ELEMENTS.contains(&&*name.local) =>
+            {
+                return dropped;
+            }
+            // Components and fragments render as `display: contents`
+            NodeData::Element { name, .. } => {
+                let tag = name.local.to_string();
+                (
+                    !is_component_element(&parent) && crate::minify::is_block_tag(&tag),
+                    PHRASING_PARENTS.contains(&tag.as_str()),
+                    crate::minify::is_whitespace_sensitive_tag(&tag),
+                )
+            }
+            _ => (true, false, false),
+        };
+        let siblings = parent.children.borrow();
+        let index = siblings.iter().position(|s| std::rc::Rc::ptr_eq(s, handle));
+        let in_running_text = !parent_is_block
+            || holds_phrasing
+            || siblings.iter().any(|sibling| match &sibling.data {
+                NodeData::Text { contents } => !EXPR_PLACEHOLDER_RE
+                    .replace_all(&contents.borrow(), "")
+                    .trim()
+                    .is_empty(),
+                _ => false,
+            });
+        let renders_beside = |sibling: Option<&Handle>| match sibling {
+            Some(sibling) if is_component_element(sibling) => in_running_text,
+            Some(sibling) => {
+                !matches!(&sibling.data, NodeData::Element { name, .. } if crate::minify::is_block_tag(&name.local))
             }
+            // Whitespace at the edges of a component's children is formatting
+            None => !parent_is_block && !is_component_element(&parent),
+        };
+        Self {
+            leading: preserve
+                || renders_beside(
+                    index
+                        .and_then(|i| i.checked_sub(1))
+                        .and_then(|i| siblings.get(i)),
+                ),
+            trailing: preserve || renders_beside(index.and_then(|i| siblings.get(i + 1))),
+            preserve,
         }
+    }
 
-        NodeData::Comment { .. } => Ok(vec![]),
-        NodeData::ProcessingInstruction { .. } => Ok(vec![]),
+    /// What a whitespace-only segment renders as
+    fn whitespace(&self, segment: &str, renders: bool) -> Option<String> {
+        match (renders, self.preserve) {
+            (false, _) => None,
+            (true, true) => Some(segment.to_string()),
+            (true, false) => Some(" ".to_string()),
+        }
     }
 }
 
-/// Process text that may contain multiple expression placeholders
+/// SVG elements whose children are rendered text
+const SVG_TEXT_ELEMENTS: &[&str] = &["text", "tspan", "textPath", "title", "desc"];
+
+/// Block-level elements whose content is phrasing content, so a component in
+/// them is inline
+const PHRASING_PARENTS: &[&str] = &[
+    "p", "h1", "h2", "h3", "h4", "h5", "h6", "dt", "caption", "summary",
+];
+
+/// Whether a parsed element is a component tag (marked by `mark_component_tags`)
+fn is_component_element(handle: &Handle) -> bool {
+    matches!(&handle.data, NodeData::Element { attrs, .. } if attrs
+        .borrow()
+        .iter()
+        .any(|a| &*a.name.local == "data-zen-orig-name"))
+}
+
+/// Process text that may contain multiple expression placeholders.
+/// Segments with visible text are kept verbatim; whitespace-only segments are
+/// kept as a single space where they render (see `TextContext`).
 fn process_text_with_expressions(
     text: &str,
     expressions: &mut Vec<ExpressionIR>,
     normalized_exprs: &HashMap<String, String>,
     loop_context: Option<&LoopContext>,
     is_in_head: bool,
+    context: &TextContext,
 ) -> Vec<TemplateNode> {
     let mut nodes = Vec::new();
     let mut last_end = 0;
@@ -618,11 +1070,19 @@ fn process_text_with_expressions(
         // Add text before this expression
         if m.start() > last_end {
             let before_text = &text[last_end..m.start()];
-            if !before_text.trim().is_empty() {
+            let value = if before_text.trim().is_empty() {
+                // Between two placeholders whitespace is always inline
+                context.whitespace(before_text, last_end > 0 || context.leading)
+            } else {
+                Some(before_text.to_string())
+            };
+            if let Some(value) = value {
                 nodes.push(TemplateNode::Text(TextNode {
-                    value: before_text.to_string(),
+                    value,
                     location: SourceLocation { line: 1, column: 1 },
                     loop_context: loop_context.cloned(),
+                    ordinal: next_node_ordinal(),
+                    extra: Default::default(),
                 }));
             }
         }
@@ -636,12 +1096,15 @@ fn process_text_with_expressions(
                 code: expr_code.clone(),
                 location: SourceLocation { line: 1, column: 1 },
                 loop_context: loop_context.cloned(),
+                origin: None,
+                extra: Default::default(),
             });
             nodes.push(TemplateNode::Expression(ExpressionNode {
                 expression: expr_id,
                 location: SourceLocation { line: 1, column: 1 },
                 loop_context: loop_context.cloned(),
                 is_in_head,
+                extra: Default::default(),
             }));
         }
 
@@ -651,11 +1114,24 @@ fn process_text_with_expressions(
     // Add remaining text
     if last_end < text.len() {
         let after_text = &text[last_end..];
-        if !after_text.trim().is_empty() {
+        let value = if after_text.trim().is_empty() {
+            // Without placeholders the text renders only between two inline neighbours
+            let renders = if last_end > 0 {
+                context.trailing
+            } else {
+                context.leading && context.trailing
+            };
+            context.whitespace(after_text, renders)
+        } else {
+            Some(after_text.to_string())
+        };
+        if let Some(value) = value {
             nodes.push(TemplateNode::Text(TextNode {
-                value: after_text.to_string(),
+                value,
                 location: SourceLocation { line: 1, column: 1 },
                 loop_context: loop_context.cloned(),
+                ordinal: next_node_ordinal(),
+                extra: Default::default(),
             }));
         }
     }
@@ -667,10 +1143,90 @@ fn process_text_with_expressions(
 // MAIN PARSING FUNCTIONS
 // ═══════════════════════════════════════════════════════════════════════════════
 
+/// Strip a leading UTF-8 BOM and normalize CRLF (and lone CR) line endings to LF.
+/// Every regex, index-based scanner, and Oxc span downstream sees this text.
+pub fn normalize_source(source: &str) -> Cow<'_, str> {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    if source.contains('\r') {
+        Cow::Owned(source.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(source)
+    }
+}
+
+/// Whether `source` uses CRLF line endings (checked before normalization)
+fn uses_crlf(source: &str) -> bool {
+    source.contains("\r\n")
+}
+
+/// `Z-ERR-SCRIPT-SYNTAX` for the page's own script and template expressions
+/// (components are checked during resolution, see `script_syntax.rs`)
+fn page_syntax_errors(
+    template_ir: &TemplateIR,
+    script_ir: Option<&ScriptIR>,
+    source: &str,
+    file_path: &str,
+) -> Vec<String> {
+    let mut errors = script_ir.map_or_else(Vec::new, |script| {
+        crate::script_syntax::check_script(&script.raw, source, file_path)
+    });
+    errors.extend(crate::script_syntax::check_expressions(
+        &template_ir.expressions,
+        source,
+        file_path,
+    ));
+    errors
+}
+
 /// Parse template from HTML string
 pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, CompilerError> {
+    parse_markup(
+        html,
+        file_path,
+        None,
+        crate::depth::DEFAULT_MAX_DEPTH,
+        &Default::default(),
+    )
+    .map_err(|e| *e)
+}
+
+/// Parse markup lowered from a JSX expression (see `classify.rs`); `context`
+/// describes the fragment it renders in, for duplicate attribute warnings, and
+/// its expression ids are anchored below `anchor` (see `expr_ids.rs`)
+pub(crate) fn parse_lowered_markup(
+    markup: &str,
+    file_path: &str,
+    context: &str,
+    anchor: &str,
+) -> Result<TemplateIR, Box<CompilerError>> {
+    parse_markup(
+        markup,
+        file_path,
+        Some((context, anchor)),
+        crate::depth::DEFAULT_MAX_DEPTH,
+        &Default::default(),
+    )
+}
+
+fn parse_markup(
+    html: &str,
+    file_path: &str,
+    lowered: Option<(&str, &str)>,
+    max_depth: usize,
+    tables: &crate::html_tables::HtmlTables,
+) -> Result<TemplateIR, Box<CompilerError>> {
+    let lowered_context = lowered.map(|(context, _)| context);
+    let html = normalize_source(html);
+
+    // Step 0: Component positions, before the source is rewritten (lowered markup
+    // has none in the page)
+    let located = match lowered {
+        Some(_) => html.to_string(),
+        None => mark_component_locations(&html),
+    };
+
     // Step 1: Convert self-closing components
-    let html_self = convert_self_closing_components(html);
+    let html_self = convert_self_closing_components(&located);
 
     // Step 2: Strip script and style blocks
     let (html_strip, inline_scripts) = strip_blocks(&html_self);
@@ -679,11 +1235,17 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
     let casing_preserved = mark_component_tags(&html_strip);
 
     // Step 4: Normalize expressions to placeholders
-    let (normalized, normalized_exprs) = normalize_all_expressions(&casing_preserved);
+    let (normalized, mut normalized_exprs) = normalize_all_expressions(&casing_preserved);
+    let (normalized, mut warnings) = merge_duplicate_attributes(
+        &normalized,
+        &mut normalized_exprs,
+        file_path,
+        lowered_context,
+    );
 
     // INVARIANT: Rejects <template> tag (INV005) - Pre-parse check for safety
     if normalized.to_lowercase().contains("<template") {
-        return Err(CompilerError::with_details(
+        return Err(Box::new(CompilerError::with_details(
             "INV005",
             "The <template> tag is not supported in Zenith. Use structural fragments or components instead.",
             file_path,
@@ -691,7 +1253,7 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
             1,
             Some("<template>".to_string()),
             vec![],
-        ));
+        )));
     }
 
     // Step 5: Parse with html5ever
@@ -707,6 +1269,16 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
                 0,
             )
         })?;
+    // Conversion and the passes after it recurse per level of nesting
+    if let Some((depth, path)) = crate::depth::check_dom(&dom.document, max_depth) {
+        return Err(Box::new(CompilerError::new(
+            crate::depth::MAX_DEPTH_ERROR,
+            &crate::depth::too_deep(depth, max_depth, &path, file_path),
+            file_path,
+            1,
+            1,
+        )));
+    }
 
     // Step 5: Convert DOM to TemplateNodes
     let mut expressions = Vec::new();
@@ -717,7 +1289,13 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
     // leading to Double Head issues.
     let html_no_comments = strip_comments(&html_strip);
     let has_html_in_src = html_no_comments.to_lowercase().contains("<html");
+    if has_html_in_src && lowered_context.is_none() {
+        warnings.extend(crate::content_audit::check_outside_document(
+            &html, file_path,
+        ));
+    }
 
+    #[allow(clippy::too_many_arguments)]
     fn collect_body_content(
         handle: &Handle,
         nodes: &mut Vec<TemplateNode>,
@@ -725,8 +1303,9 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
         normalized_exprs: &HashMap<String, String>,
         inline_scripts: &HashMap<String, String>,
         file_path: &str,
+        tables: &crate::html_tables::HtmlTables,
         has_html_in_src: bool,
-    ) -> Result<(), CompilerError> {
+    ) -> Result<(), Box<CompilerError>> {
         let node = handle;
         match &node.data {
             NodeData::Document => {
@@ -738,6 +1317,7 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
                         normalized_exprs,
                         inline_scripts,
                         file_path,
+                        tables,
                         has_html_in_src,
                     )?;
                 }
@@ -758,6 +1338,7 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
                             normalized_exprs,
                             inline_scripts,
                             file_path,
+                            tables,
                             has_html_in_src,
                         )?;
                     }
@@ -772,6 +1353,7 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
                         inline_scripts,
                         None,
                         file_path,
+                        tables,
                         false,
                     )?);
                 } else {
@@ -782,6 +1364,7 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
                         inline_scripts,
                         None,
                         file_path,
+                        tables,
                         false,
                     )?);
                 }
@@ -795,6 +1378,7 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
                         inline_scripts,
                         None,
                         file_path,
+                        tables,
                         false,
                     )?);
                 }
@@ -809,6 +1393,7 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
                     inline_scripts,
                     None,
                     file_path,
+                    tables,
                     false,
                 )?);
             }
@@ -816,6 +1401,7 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
         Ok(())
     }
 
+    warnings.extend(unterminated_expression_warnings(&normalized, file_path));
     collect_body_content(
         &dom.document,
         &mut nodes,
@@ -823,18 +1409,25 @@ pub fn parse_template(html: &str, file_path: &str) -> Result<TemplateIR, Compile
         &normalized_exprs,
         &inline_scripts,
         file_path,
+        tables,
         has_html_in_src,
     )?;
+    // Ids by position in the template, not parse order
+    let seed = lowered.map(|(_, anchor)| anchor).unwrap_or_default();
+    crate::expr_ids::anchor_ids(&mut nodes, &mut expressions, seed);
 
     Ok(TemplateIR {
         raw: html.to_string(),
         nodes,
         expressions,
+        warnings,
     })
 }
 
 /// Parse script block from HTML string
 pub fn parse_script(html: &str) -> Option<ScriptIR> {
+    let html = normalize_source(html);
+    let html = html.as_ref();
     let mut scripts = Vec::new();
     let mut attributes = HashMap::new();
 
@@ -945,29 +1538,42 @@ fn extract_script_block(source: &str) -> Option<String> {
         .and_then(|cap| cap.get(1).map(|m| m.as_str().to_string()))
 }
 
-/// Extract static props passed to layout components (e.g., <DefaultLayout title="Home">)
-/// This is used for document compilation to get compile-time values.
-fn extract_static_layout_props(source: &str) -> std::collections::HashMap<String, String> {
-    let mut props = std::collections::HashMap::new();
-
-    // Match layout-like components with static attributes
-    // Pattern: <ComponentName attr="value" attr2="value2">
-    lazy_static! {
-        static ref LAYOUT_RE: Regex = Regex::new(r#"<([A-Z][a-zA-Z]*Layout)\s+([^>]*?)>"#).unwrap();
-        static ref ATTR_RE: Regex = Regex::new(r#"(\w+)="([^"]*)""#).unwrap();
-    }
+/// Static props passed along the layout chain (e.g. `<DocsLayout title="Home">`),
+/// merged outermost-first so the layout closest to the page wins.
+fn extract_static_layout_props(chain: &[crate::validate::LayoutLevel]) -> HashMap<String, String> {
+    chain.iter().flat_map(|level| level.props.clone()).collect()
+}
 
-    if let Some(layout_cap) = LAYOUT_RE.captures(source) {
-        if let Some(attrs_str) = layout_cap.get(2) {
-            for attr_cap in ATTR_RE.captures_iter(attrs_str.as_str()) {
-                if let (Some(name), Some(value)) = (attr_cap.get(1), attr_cap.get(2)) {
-                    props.insert(name.as_str().to_string(), value.as_str().to_string());
-                }
-            }
-        }
-    }
+/// Compile-time scope of a document page: route props, overridden by the static
+/// layout props, with the layout scripts run outermost-first. A page that is
+/// its own document (rooted at `<html>`) runs its own script instead.
+fn build_document_scope(
+    zen_ir: &crate::validate::ZenIR,
+    source: &str,
+    route_props: HashMap<String, String>,
+    components: &HashMap<String, serde_json::Value>,
+) -> Option<crate::document::DocumentScope> {
+    let mut props_map = route_props;
+    props_map.extend(extract_static_layout_props(&zen_ir.layout_chain));
+
+    let scripts: Vec<String> = if zen_ir.layout_chain.is_empty() {
+        extract_script_block(source).into_iter().collect()
+    } else {
+        zen_ir
+            .layout_chain
+            .iter()
+            .filter_map(|level| {
+                let comp = components.get(&level.component)?;
+                serde_json::from_value::<crate::component::ComponentIR>(comp.clone())
+                    .ok()?
+                    .script
+            })
+            .collect()
+    };
+    let scripts: Vec<&str> = scripts.iter().map(String::as_str).collect();
 
-    props
+    // Don't fail hard - fall back to no scope (will show compile error in output)
+    crate::document::execute_document_chain(&scripts, &props_map).ok()
 }
 
 /// Full Zenith compilation entry point - the "One True Syscall"
@@ -975,14 +1581,136 @@ fn extract_static_layout_props(source: &str) -> std::collections::HashMap<String
 /// Combines: parse_template + parse_script → ZenIR → component resolution →
 /// transform → finalize → FinalizedOutput
 #[cfg(feature = "napi")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[napi(object)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParseFullOptions {
     pub mode: Option<String>,
     pub use_cache: Option<bool>,
     pub components: Option<serde_json::Value>,
     pub layout: Option<serde_json::Value>,
     pub props: Option<serde_json::Value>,
+    /// Optional result sections to keep ("ir", "manifest"). All are included when absent.
+    pub include: Option<Vec<String>>,
+    /// Hydration payload budgets; exceeded limits fail the compile
+    pub budgets: Option<crate::finalize::Budgets>,
+    /// Expression complexity thresholds per kind; unset ones use the defaults
+    /// (see `expr_complexity.rs`)
+    pub expression_complexity: Option<crate::expr_complexity::ComplexityLimits>,
+    /// Translation key extraction and build-time locale inlining
+    pub i18n: Option<crate::i18n::I18nOptions>,
+    /// Filters available at runtime for pipe syntax (`{value | name}`)
+    pub filters: Option<Vec<String>>,
+    /// Additions to the SVG attribute case, SVG element and void element tables
+    pub html_extensions: Option<crate::html_tables::HtmlExtensions>,
+    /// Register <head> text expressions for post-hydration updates
+    pub reactive_head: Option<bool>,
+    /// Emit CRLF line endings in the HTML when the source used them
+    pub preserve_crlf: Option<bool>,
+    /// Maximum `<link rel="preload">` hints (default 4, 0 disables them)
+    pub preload_limit: Option<u32>,
+    /// Pass untyped static props that are exactly `true`/`false` or an integer as literals
+    pub coerce_static_props: Option<bool>,
+    /// Lower `.map()`, ternary, and `&&` markup expressions to template fragments
+    pub ast_classifier: Option<bool>,
+    /// Hydrate only `zen:island` subtrees and interactive components
+    pub islands: Option<bool>,
+    /// Inline component usages that need nothing at runtime as plain markup
+    pub inline_components: Option<bool>,
+    /// Content for a slot the component does not declare is an error, not a warning
+    pub strict_slots: Option<bool>,
+    /// Unknown IR fields are errors instead of being carried through (debugging)
+    pub strict_ir: Option<bool>,
+    /// Invalid component registry entries fail the compile instead of staying unresolved
+    pub strict_registry: Option<bool>,
+    /// Production output: compact HTML and bundle
+    pub minify: Option<bool>,
+    /// Remove `console.*` calls and `debugger` statements from the bundle
+    pub strip_debug: Option<crate::strip_debug::StripDebug>,
+    /// Extra or overriding `@zen-screen` breakpoints (name → media condition or min-width)
+    pub breakpoints: Option<HashMap<String, String>>,
+    /// Bundle hydrates inside an iframe `srcdoc` preview
+    pub embed_mode: Option<bool>,
+    /// Add `<link rel="modulepreload">` for the runtime and npm imports
+    pub modulepreload: Option<bool>,
+    /// Import specifier → URL used for modulepreload links
+    pub import_url_map: Option<HashMap<String, String>>,
+    /// Add `<link rel="prefetch">` for the assets of the routes the page navigates to
+    pub prefetch_links: Option<bool>,
+    /// Route → asset URL used for prefetch links
+    pub prefetch_routes: Option<HashMap<String, String>>,
+    /// Share repeated inline SVGs through one hidden sprite
+    pub svg_sprite: Option<bool>,
+    /// Build environment for `zen:if-env` / `zen:env` regions
+    pub env: Option<String>,
+    /// `"es2017"`, `"es2020"` or `"esnext"` (default): syntax level of the bundle
+    pub target_es: Option<String>,
+    /// Warn for template classes without a style rule and for unused class rules
+    pub warn_unstyled_classes: Option<bool>,
+    /// Regex of class names the unstyled-class check skips (e.g. `^js-`)
+    pub unstyled_class_ignore: Option<String>,
+    /// Warn for member access on state that may start undefined or null
+    pub strict_nullish: Option<bool>,
+    /// Deepest markup nesting accepted before `Z-ERR-MAX-DEPTH` (default 5000)
+    pub max_depth: Option<u32>,
+    /// Re-check the compiled output against the ground-truth rules (default: debug builds)
+    pub verify_invariants: Option<bool>,
+    /// Scope component styles: `"class-suffix"`, `"where-wrapper"` or `"attribute-selector"`
+    pub css_scope_strategy: Option<String>,
+    /// Prefix of the class names the compiler generates (default `zen-`)
+    pub class_prefix: Option<String>,
+    /// Component paths whose styles go into their own shared chunk
+    pub shared_components: Option<Vec<String>>,
+}
+
+/// Result of `parse_full_zen`. Each shape serializes to the object the JS side
+/// has always received; the variants only keep the encoders off `serde_json::Value`.
+#[cfg(feature = "napi")]
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub(crate) enum ParseFullOutput {
+    /// `mode: "metadata"`: the IR before component resolution
+    Metadata(Box<crate::validate::ZenIR>),
+    /// The template did not parse
+    TemplateError(CompilerError),
+    /// Script-only module: no page to build
+    #[serde(rename_all = "camelCase")]
+    Module {
+        html: String,
+        has_errors: bool,
+        errors: Vec<String>,
+        warnings: Vec<String>,
+        module_code: Option<String>,
+    },
+    Page(Box<ParsedPage>),
+}
+
+#[cfg(feature = "napi")]
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ParsedPage {
+    html: String,
+    has_errors: bool,
+    errors: Vec<String>,
+    warnings: Vec<String>,
+    bindings: Vec<crate::transform::Binding>,
+    i18n_keys: Vec<(String, crate::validate::SourceLocation)>,
+    preload_hints: Vec<crate::preload::PreloadHint>,
+    navigation_targets: Vec<String>,
+    dep_graph: crate::dep_graph::DepGraph,
+    style_chunks: Vec<crate::style_chunks::StyleChunk>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ir: Option<crate::validate::ZenIR>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    js: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    npm_imports: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    styles: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    manifest: Option<crate::finalize::ZenManifestExport>,
 }
 
 #[cfg(feature = "napi")]
@@ -992,53 +1720,127 @@ pub fn parse_full_zen_native(
     file_path: String,
     options_json: String,
 ) -> napi::Result<serde_json::Value> {
-    /*
-    eprintln!("[Zenith PARSE_FULL] ENTRY - file: {}", file_path);
-    eprintln!(
-        "[Zenith PARSE_FULL] Options JSON: {}",
-        &options_json[..options_json.len().min(200)]
+    zen_debug!(
+        file = %file_path,
+        options_len = options_json.len(),
+        "parse_full_zen_native entry"
     );
-    */
-
-    use crate::component::resolve_components;
-    use crate::finalize::{finalize_output_internal, CompiledTemplate};
-    use crate::validate::ZenIR;
 
     // Parse options from JSON string to avoid napi undefined issues
     let options: ParseFullOptions = serde_json::from_str(&options_json)
         .map_err(|e| napi::Error::from_reason(format!("Options parse error: {}", e)))?;
+    let output = parse_full_zen(&source, &file_path, options).map_err(napi::Error::from_reason)?;
+    serde_json::to_value(&output)
+        .map_err(|e| napi::Error::from_reason(format!("Serialize error: {}", e)))
+}
 
-    let mode = options.mode.unwrap_or_else(|| "full".to_string());
-
-    // Parse template
-    let template_ir = match parse_template(&source, &file_path) {
-        Ok(ir) => ir,
-        Err(e) => {
-            return Ok(serde_json::json!({
-                "code": e.code,
-                "errorType": e.error_type,
-                "message": e.message,
-                "guarantee": e.guarantee,
-                "file": e.file,
-                "line": e.line,
-                "column": e.column,
-                "context": e.context,
-                "hints": e.hints
-            }))
-        }
-    };
+/// `parse_full_zen_native` without the JSON boundary (shared with `wire.rs`)
+#[cfg(feature = "napi")]
+pub(crate) fn parse_full_zen(
+    source: &str,
+    file_path: &str,
+    options: ParseFullOptions,
+) -> Result<ParseFullOutput, String> {
+    let max_depth = options
+        .max_depth
+        .map_or(crate::depth::DEFAULT_MAX_DEPTH, |d| d as usize);
+    crate::depth::with_stack(max_depth, || parse_full_page(source, file_path, options))?
+}
 
-    // Step 2: Parse script
-    let script_ir = parse_script(&source);
+/// `parse_full_zen` on the thread `depth::with_stack` provides
+#[cfg(feature = "napi")]
+fn parse_full_page(
+    source: &str,
+    file_path: &str,
+    options: ParseFullOptions,
+) -> Result<ParseFullOutput, String> {
+    use crate::component::{resolve_components_with_options, ResolveOptions};
+    use crate::finalize::{
+        finalize_output_with_options, CompiledTemplate, FinalizeOptions, Finalized,
+    };
+    use crate::validate::ZenIR;
 
-    // Step 3: Build initial ZenIR
+    let file_path = file_path.to_string();
+    let mode = options.mode.clone().unwrap_or_else(|| "full".to_string());
+    zen_span!(DEBUG, "compile", file_path = %file_path, mode = %mode);
+    let reactive_head = options.reactive_head.unwrap_or(false);
+    let target_es = options
+        .target_es
+        .as_deref()
+        .map(crate::es_target::EsTarget::from_name)
+        .transpose()?
+        .unwrap_or_default();
+    let css_scope = options
+        .css_scope_strategy
+        .as_deref()
+        .map(crate::css_scope::ScopeStrategy::from_name)
+        .transpose()?;
+    let restore_crlf = options.preserve_crlf.unwrap_or(false) && uses_crlf(source);
+    let source = normalize_source(source).into_owned();
+    let (source, stripped_env_regions) = match crate::env::resolve_env_regions(
+        &source,
+        &file_path,
+        options.env.as_deref().unwrap_or_default(),
+    ) {
+        Ok((resolved, regions)) => (resolved.into_owned(), regions),
+        Err(e) => return Ok(ParseFullOutput::TemplateError(*e)),
+    };
+    let include_section = |section: &str| {
+        options
+            .include
+            .as_ref()
+            .is_none_or(|sections| sections.iter().any(|s| s == section))
+    };
+
+    let (html_tables, html_warnings) =
+        crate::html_tables::HtmlTables::new(&options.html_extensions.clone().unwrap_or_default());
+    let (template_ir, script_ir) = {
+        zen_span!(DEBUG, "parse");
+
+        // Parse template
+        let max_depth = options
+            .max_depth
+            .map_or(crate::depth::DEFAULT_MAX_DEPTH, |d| d as usize);
+        let template_ir = match parse_markup(&source, &file_path, None, max_depth, &html_tables) {
+            Ok(ir) => ir,
+            Err(e) => return Ok(ParseFullOutput::TemplateError(*e)),
+        };
+
+        // Step 2: Parse script
+        (template_ir, parse_script(&source))
+    };
+    let syntax_errors = page_syntax_errors(&template_ir, script_ir.as_ref(), &source, &file_path);
+
+    // Script-only module: no page to build
+    if let Some(script) = script_ir
+        .as_ref()
+        .filter(|_| crate::module::is_script_only(&template_ir))
+    {
+        let (module_code, errors) = match crate::module::compile_module(script, &file_path) {
+            Ok(code) => (Some(code), vec![]),
+            Err(errors) => (None, errors),
+        };
+        return Ok(ParseFullOutput::Module {
+            html: String::new(),
+            has_errors: !errors.is_empty(),
+            errors,
+            warnings: Vec::new(),
+            module_code,
+        });
+    }
+
+    // Step 3: Build initial ZenIR
     let mut zen_ir = ZenIR {
         file_path: file_path.clone(),
         template: template_ir,
         script: script_ir.clone(),
-        styles: crate::discovery::extract_styles_native(source.clone())
+        styles: crate::discovery::extract_style_blocks(&source)
             .into_iter()
-            .map(|raw| crate::validate::StyleIR { raw })
+            .map(|(raw, global)| crate::validate::StyleIR {
+                raw,
+                global,
+                source_component: String::new(),
+            })
             .collect(),
         props: script_ir
             .as_ref()
@@ -1058,22 +1860,42 @@ pub fn parse_full_zen_native(
         uses_state: false,
         has_events: false,
         css_classes: vec![],
+        warnings: vec![],
+        used_filters: vec![],
+        unused_props: BTreeMap::new(),
+        component_usages: Vec::new(),
+        inlined_components: 0,
+        conditional_props: BTreeMap::new(),
+        coerced_props: BTreeMap::new(),
+        component_events: BTreeMap::new(),
+        dep_graph: Default::default(),
+        layout_chain: Vec::new(),
+        island_props: HashMap::new(),
+        hydration: Vec::new(),
+        loop_item_keys: Vec::new(),
+        generated_classes: BTreeMap::new(),
+        islands: None,
+        extra: Default::default(),
     };
+    zen_ir.warnings = zen_ir.template.warnings.clone();
+    zen_ir.warnings.extend(html_warnings);
 
     // For metadata mode, return early with just IR
     if mode == "metadata" {
-        let result = serde_json::to_value(&zen_ir)
-            .map_err(|e| napi::Error::from_reason(format!("Serialize error: {}", e)))?;
-        return Ok(result);
+        return Ok(ParseFullOutput::Metadata(Box::new(zen_ir)));
     }
 
+    // Page-only dependency graph; resolution replaces it with the full one
+    zen_ir.dep_graph = crate::component::page_dep_graph(&zen_ir);
+
+    // Step 3.9: `zenAssert` in the page script (component scripts are prepared on resolution)
+    let assert_errors = crate::zen_assert::prepare_page_script(&mut zen_ir, &source);
+
     // Step 4: Resolve components if provided
-    /*
-    eprintln!(
-        "[Zenith PARSE] Checking components option: {:?}",
-        options.components.is_some()
+    zen_trace!(
+        has_components = options.components.is_some(),
+        "checking components option"
     );
-    */
     let mut components_map: std::collections::HashMap<String, serde_json::Value> =
         std::collections::HashMap::new();
     if let Some(components) = &options.components {
@@ -1081,8 +1903,25 @@ pub fn parse_full_zen_native(
             components_map = serde_json::from_value(components.clone()).unwrap_or_default();
             if !components_map.is_empty() {
                 // Component resolution handled internally
-                zen_ir = resolve_components(zen_ir, components_map.clone())
-                    .map_err(|e| napi::Error::from_reason(e))?;
+                zen_span!(DEBUG, "resolve", components = components_map.len());
+                let resolve_options = ResolveOptions {
+                    coerce_static_props: options.coerce_static_props.unwrap_or(false),
+                    islands: options.islands.unwrap_or(false),
+                    inline_components: options.inline_components.unwrap_or(false),
+                    strict_slots: options.strict_slots.unwrap_or(false),
+                    cancel: None,
+                    strict_ir: options.strict_ir.unwrap_or(false),
+                    strict_registry: options.strict_registry.unwrap_or(false),
+                    css_scope,
+                    class_prefix: options.class_prefix.clone(),
+                    max_depth: options.max_depth.map(|d| d as usize),
+                    script_cache: None,
+                };
+                zen_ir = resolve_components_with_options(
+                    zen_ir,
+                    components_map.clone(),
+                    &resolve_options,
+                )?;
             } else {
             }
         } else {
@@ -1090,107 +1929,251 @@ pub fn parse_full_zen_native(
     } else {
     }
 
-    // Step 5: Transform template
-    // Check if this is a document module and build scope if so
-    let is_document = crate::document::is_document_module(&zen_ir.template.nodes);
+    // Step 4.4: Lower markup-rendering expressions to fragments
+    let lowering_errors = if options.ast_classifier.unwrap_or(false) {
+        crate::classify::lower_fragments(&mut zen_ir.template, &file_path, &mut zen_ir.warnings)
+    } else {
+        Vec::new()
+    };
 
-    let document_scope = if is_document {
-        // For document modules, we need to:
-        // 1. Get props that were passed to the layout (from page_props)
-        // 2. Build a scope from those props
-
-        // Build props map from page_props (these are the props passed to the layout)
-        let mut props_map: std::collections::HashMap<String, String> =
-            std::collections::HashMap::new();
-
-        // If this is a layout-wrapped document, the props are in the ZenIR
-        // Check options first, then fall back to extracting from page
-        if let Some(opts_props) = &options.props {
-            if let Some(obj) = opts_props.as_object() {
-                for (k, v) in obj {
-                    if let Some(s) = v.as_str() {
-                        props_map.insert(k.clone(), s.to_string());
-                    }
-                }
-            }
-        }
+    // Step 4.5: Extract / inline translation calls
+    let i18n_keys = match &options.i18n {
+        Some(i18n) => crate::i18n::process_i18n(&mut zen_ir, &source, i18n),
+        None => Vec::new(),
+    };
 
-        // Also extract props that were statically passed in the original source
-        // Parse the original template to find static attribute values on layout component
-        let static_props = extract_static_layout_props(&source);
-        for (k, v) in static_props {
-            props_map.insert(k, v);
-        }
+    // Step 4.6: Lower filter pipes to zenFilter calls
+    crate::filters::lower_filter_pipes(&mut zen_ir);
 
-        // Extract script source from the ORIGINAL source of the document-providing component
-        // We look for a component in components_map that has <html> as its root.
-        let mut script_source = extract_script_block(&source).unwrap_or_default();
+    // Step 4.7: Reconcile node expression ids with the registry
+    let mut reconcile_errors = lowering_errors;
+    reconcile_errors.extend(syntax_errors);
+    reconcile_errors.extend(
+        crate::reconcile::reconcile_expressions(&mut zen_ir)
+            .into_iter()
+            .map(|e| format_invariant_error(&e)),
+    );
+    reconcile_errors.extend(assert_errors);
+    reconcile_errors.extend(crate::zen_assert::prepare_expressions(&mut zen_ir));
+
+    // Step 4.72: Expand `@zen-screen` / `@zen-container` in page and component styles
+    reconcile_errors.extend(crate::breakpoints::expand_style_queries(
+        &mut zen_ir,
+        &options.breakpoints.clone().unwrap_or_default(),
+    ));
+
+    // Step 4.75: Production output drops insignificant whitespace
+    if options.minify.unwrap_or(false) {
+        crate::minify::collapse_whitespace(&mut zen_ir.template.nodes);
+    }
 
-        for (_name, comp_val) in &components_map {
-            if let Ok(comp) =
-                serde_json::from_value::<crate::component::ComponentIR>(comp_val.clone())
-            {
-                if crate::document::is_document_module(&comp.nodes) {
-                    if let Some(s) = comp.script {
-                        script_source = s;
-                        break;
-                    }
-                }
-            }
+    // Step 4.76: Classes the template can carry, for CSS pruning
+    zen_ir.css_classes = crate::finalize::collect_css_classes(&zen_ir.template.nodes);
+    if options.warn_unstyled_classes.unwrap_or(false) {
+        match crate::css_usage::check_class_usage(
+            &zen_ir.css_classes,
+            crate::finalize::has_dynamic_classes(&zen_ir.template.nodes),
+            &zen_ir.styles,
+            options.unstyled_class_ignore.as_deref(),
+            &file_path,
+        ) {
+            Ok(warnings) => zen_ir.warnings.extend(warnings),
+            Err(e) => reconcile_errors.push(e),
         }
+    }
 
-        // Execute document script at compile time
-        match crate::document::execute_document_script(&script_source, &props_map) {
-            Ok(scope) => Some(scope),
-            Err(_e) => {
-                // Don't fail hard - fall back to no scope (will show compile error in output)
-                None
-            }
-        }
+    // Step 4.8: Islands mode folds everything outside islands to static HTML
+    if options.islands.unwrap_or(false) {
+        reconcile_errors.extend(crate::islands::apply_islands(&mut zen_ir));
+    }
+
+    // Step 5: Transform template
+    // Check if this is a document module and build scope if so
+    let is_document = crate::document::is_document_module(&zen_ir.template.nodes);
+
+    // Props passed to the page (route props) as strings
+    let route_props: HashMap<String, String> = options
+        .props
+        .as_ref()
+        .and_then(|p| p.as_object())
+        .map(|obj| {
+            obj.iter()
+                .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+    let document_scope = if is_document {
+        build_document_scope(&zen_ir, &source, route_props.clone(), &components_map)
     } else {
         None
     };
 
-    let transform_output = crate::transform::transform_template_with_scope(
-        &zen_ir.template.nodes,
-        &zen_ir.template.expressions,
-        document_scope.as_ref(),
-    );
+    // Step 5.1: Evaluate `define:vars` into the is:inline scripts
+    let static_values = crate::define_vars::static_values(document_scope.as_ref(), &route_props);
+    reconcile_errors.extend(crate::define_vars::apply_define_vars(
+        &mut zen_ir,
+        &static_values,
+    ));
+    // Step 5.2: Head and zen:static expressions must not need a browser
+    reconcile_errors.extend(crate::ssr_safety::check_compile_time_expressions(
+        &mut zen_ir,
+    ));
+    // Step 5.25: Conditions and reflected attributes that need a browser to decide
+    crate::hydration_mismatch::check_hydration_mismatch(&mut zen_ir);
+    // Step 5.26: `onclick|lazy` becomes `onclick` plus a `zen:lazy` marker
+    crate::lazy::normalize_lazy_modifiers(&mut zen_ir.template.nodes);
+    // Step 5.27: `zen:pending` targets are boolean states, handlers are functions
+    reconcile_errors.extend(crate::pending::check_pending_handlers(&mut zen_ir));
+    // Step 5.28: `zen:portal` subtrees render inert in place, mounted at their target
+    reconcile_errors.extend(crate::portal::wrap_portals(&mut zen_ir, is_document));
+    // Step 5.29: Member access on state that may not have a value yet
+    if options.strict_nullish.unwrap_or(false) {
+        crate::nullish::check_nullish_access(&mut zen_ir);
+    }
+
+    // Step 5.3: Server-render only the first chunk of zen:virtualize loops
+    let virtual_loops = crate::virtualize::plan_virtual_loops(&mut zen_ir);
+    // Step 5.31: Option state in loops binds as properties; static options SSR
+    let select_loops = crate::select_options::plan_select_options(&mut zen_ir, &virtual_loops);
+    // Step 5.32: Item member access checked against static loop sources
+    crate::loop_shapes::check_loop_item_keys(&mut zen_ir, &static_values);
+    // Step 5.33: Fragments that restructure aria-live regions
+    crate::live_regions::check_live_regions(&mut zen_ir);
+
+    let head_options = crate::transform::HeadOptions {
+        reactive: reactive_head,
+        initial_values: crate::static_eval::initial_state_values(&zen_ir.all_states),
+        initial_objects: crate::attr_coerce::initial_object_values(&zen_ir.all_states),
+        minify: options.minify.unwrap_or(false),
+        virtual_loops,
+        select_loops,
+        html: html_tables,
+    };
+    let transform_output = {
+        zen_span!(DEBUG, "transform");
+        crate::transform::transform_template_with_options(
+            &zen_ir.template.nodes,
+            &zen_ir.template.expressions,
+            document_scope.as_ref(),
+            &head_options,
+        )
+    };
 
     let compiled = CompiledTemplate {
         html: transform_output.html,
         styles: vec![],
+        binding_count: transform_output.bindings.len(),
     };
 
     // Step 6: Finalize output
-    let finalized = finalize_output_internal(zen_ir.clone(), compiled)
-        .map_err(|e| napi::Error::from_reason(e))?;
+    let finalize_options = FinalizeOptions {
+        budgets: options.budgets.clone(),
+        filters: options.filters.clone().unwrap_or_default(),
+        reactive_head,
+        preload_limit: options.preload_limit,
+        minify: options.minify.unwrap_or(false),
+        strip_debug: options.strip_debug.clone().unwrap_or_default(),
+        cancel: None,
+        embed: options.embed_mode.unwrap_or(false),
+        modulepreload: options.modulepreload.unwrap_or(false),
+        import_url_map: options.import_url_map.clone().unwrap_or_default(),
+        prefetch_links: options.prefetch_links.unwrap_or(false),
+        prefetch_routes: options.prefetch_routes.clone().unwrap_or_default(),
+        svg_sprite: options.svg_sprite.unwrap_or(false),
+        target_es,
+        expression_complexity: options.expression_complexity.clone(),
+        plan_filter: None,
+    };
+    let finalized = {
+        zen_span!(DEBUG, "finalize");
+        match finalize_output_with_options(zen_ir.clone(), compiled, &finalize_options)? {
+            Finalized::Page(finalized) => finalized,
+            Finalized::ForceIslands => unreachable!("no plan filter is set"),
+        }
+    };
 
     // Step 7: Build result with all fields
-    let mut result = serde_json::json!({
-        "ir": zen_ir,
-        "html": finalized.html,
-        "hasErrors": finalized.has_errors,
-        "errors": finalized.errors,
-        "bindings": transform_output.bindings,
-    });
-
-    if let Some(manifest) = finalized.manifest {
-        if let Some(obj) = result.as_object_mut() {
-            obj.insert("js".to_string(), serde_json::json!(manifest.bundle));
-            obj.insert(
-                "npmImports".to_string(),
-                serde_json::json!(manifest.npm_imports),
-            );
-            obj.insert("styles".to_string(), serde_json::json!(manifest.styles));
-            obj.insert(
-                "manifest".to_string(),
-                serde_json::to_value(&manifest).unwrap_or(serde_json::Value::Null),
-            );
-        }
+    let html = if restore_crlf {
+        finalized.html.replace('\n', "\r\n")
+    } else {
+        finalized.html
+    };
+    let mut errors = reconcile_errors;
+    errors.extend(finalized.errors);
+    // Step 7.1: Check the output against the ground-truth rules
+    if errors.is_empty() && options.verify_invariants.unwrap_or(cfg!(debug_assertions)) {
+        errors.extend(verify_output_invariants(
+            &zen_ir,
+            &html,
+            finalized.manifest.as_ref(),
+            &transform_output.bindings,
+        ));
+    }
+    let errors = crate::diagnostics::normalize(errors);
+    let mut manifest = finalized.manifest;
+    if let Some(manifest) = manifest.as_mut() {
+        manifest.stripped_env_regions = stripped_env_regions;
     }
+    // Step 7.2: Split the styles into cacheable chunks
+    let style_chunks = crate::style_chunks::chunk_styles(
+        &crate::global_styles::document_order(zen_ir.styles.clone()),
+        &file_path,
+        &options
+            .shared_components
+            .iter()
+            .flatten()
+            .cloned()
+            .collect(),
+    );
+    Ok(ParseFullOutput::Page(Box::new(ParsedPage {
+        html,
+        has_errors: !errors.is_empty(),
+        errors,
+        warnings: finalized.warnings,
+        bindings: transform_output.bindings,
+        i18n_keys,
+        preload_hints: finalized.preload_hints,
+        navigation_targets: manifest
+            .as_ref()
+            .map(|m| m.navigation.targets.clone())
+            .unwrap_or_default(),
+        dep_graph: zen_ir.dep_graph.clone(),
+        style_chunks,
+        ir: include_section("ir").then_some(zen_ir),
+        js: manifest.as_ref().map(|m| m.bundle.clone()),
+        npm_imports: manifest.as_ref().map(|m| m.npm_imports.clone()),
+        styles: manifest.as_ref().map(|m| m.styles.clone()),
+        manifest: manifest.filter(|_| include_section("manifest")),
+    })))
+}
+
+/// Borrowed view of the template IR so the node tree is serialized without cloning.
+#[cfg(feature = "napi")]
+#[derive(Serialize)]
+struct TemplateSlice<'a> {
+    nodes: &'a [TemplateNode],
+    expressions: &'a [ExpressionIR],
+}
 
-    Ok(result)
+/// Parse-only compilation: returns `{nodes, expressions}` as JSON.
+/// Skips script parsing, component resolution, transform, and finalize.
+/// Template parse failures are errors, as in the other entry points.
+#[cfg(feature = "napi")]
+pub fn parse_zen_template_json(source: &str, file_path: &str) -> Result<String, String> {
+    let ir = parse_template(source, file_path)
+        .map_err(|e| format!("Template parse error: {}", e.message))?;
+    serde_json::to_string(&TemplateSlice {
+        nodes: &ir.nodes,
+        expressions: &ir.expressions,
+    })
+    .map_err(|e| format!("Serialize error: {}", e))
+}
+
+/// Editor-facing parse entry point (template node tree and expressions only)
+#[cfg(feature = "napi")]
+#[napi]
+pub fn parse_zen_template_native(source: String, file_path: String) -> napi::Result<String> {
+    parse_zen_template_json(&source, &file_path).map_err(napi::Error::from_reason)
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -1200,10 +2183,121 @@ pub fn parse_full_zen_native(
 /// Options for internal compilation (Rust structs, no JSON)
 #[derive(Debug, Clone, Default)]
 pub struct CompileOptions {
+    /// "full" (default), "metadata", or "component" (standalone component fragment)
     pub mode: String,
     pub components: std::collections::HashMap<String, serde_json::Value>,
     pub layout: Option<serde_json::Value>,
     pub props: std::collections::HashMap<String, String>,
+    /// Hydration payload budgets; exceeded limits fail the compile
+    pub budgets: Option<crate::finalize::Budgets>,
+    /// Expression complexity thresholds per kind; unset ones use the defaults
+    /// (see `expr_complexity.rs`)
+    pub expression_complexity: Option<crate::expr_complexity::ComplexityLimits>,
+    /// Translation key extraction and build-time locale inlining
+    pub i18n: Option<crate::i18n::I18nOptions>,
+    /// Filters available at runtime for pipe syntax (`{value | name}`)
+    pub filters: Vec<String>,
+    /// Additions to and overrides of the SVG attribute case, SVG element and void
+    /// element tables (see `html_tables.rs`)
+    pub html_extensions: crate::html_tables::HtmlExtensions,
+    /// Register <head> text expressions for post-hydration updates
+    pub reactive_head: bool,
+    /// Emit CRLF line endings in the HTML when the source used them
+    pub preserve_crlf: bool,
+    /// Maximum `<link rel="preload">` hints (default 4, 0 disables them)
+    pub preload_limit: Option<u32>,
+    /// Pass untyped static props that are exactly `true`/`false` or an integer as literals.
+    /// Props declared as `number`/`boolean` are always coerced.
+    pub coerce_static_props: bool,
+    /// Lower `.map()`, ternary, and `&&` markup expressions to loop, conditional,
+    /// and optional fragments (see `classify.rs`). Opt-in until it has baked.
+    pub ast_classifier: bool,
+    /// Hydrate only `zen:island` subtrees and interactive components; the rest of the
+    /// page is emitted as static HTML (see `islands.rs`)
+    pub islands: bool,
+    /// Sees the page's codegen plan before any code is generated and proceeds, skips
+    /// the bundle, or recompiles the page in islands mode (see `codegen.rs`)
+    pub plan_filter: Option<crate::codegen::PlanFilter>,
+    /// Substitute usages of components that need nothing at runtime (no state, script
+    /// logic, events, or dynamic props) as plain markup, without a scope. Usages marked
+    /// `zen:inline-always` are always tried and warn when they cannot be inlined.
+    pub inline_components: bool,
+    /// Compound children (`<Card.Footer>`) for slots the component does not declare
+    /// fail the compile instead of warning
+    pub strict_slots: bool,
+    /// Fields of the component registry's IR that this compiler does not know fail
+    /// the compile instead of being carried through (see `ir_compat.rs`); for
+    /// debugging a toolchain version mismatch
+    pub strict_ir: bool,
+    /// Component registry entries that fail the integrity checks (dangling expression
+    /// ids, malformed attributes or slots) fail the compile instead of being left out
+    /// with a warning, their usages unresolved (see `registry.rs`)
+    pub strict_registry: bool,
+    /// Production output: drop insignificant whitespace from the HTML (see `minify.rs`),
+    /// banner comments from the bundle, and compact its expressions and template IR
+    pub minify: bool,
+    /// Remove `console.<method>(...)` calls (except `keep`) and `debugger` statements
+    /// from the page and component scripts and from template expressions (see `strip_debug.rs`)
+    pub strip_debug: crate::strip_debug::StripDebug,
+    /// Abort the compile cooperatively (see `cancel.rs`); a cancelled compile
+    /// returns `Err(COMPILE_CANCELLED)`
+    pub cancel: Option<crate::cancel::CancellationToken>,
+    /// Breakpoints for `@zen-screen` / `@zen-container` on top of the defaults
+    /// (`sm`, `md`, `lg`, `xl`; see `breakpoints.rs`). A value is a media condition
+    /// or a bare length, read as a min-width.
+    pub breakpoints: HashMap<String, String>,
+    /// The output is embedded with `to_srcdoc` (see `embed.rs`): the bundle hydrates
+    /// immediately if the iframe document is already interactive, and only once
+    pub embed_mode: bool,
+    /// Add `<link rel="modulepreload">` to the head for the runtime package and each
+    /// runtime import, in import order (see `preload.rs`)
+    pub modulepreload: bool,
+    /// Where the browser loads each import specifier from; imports without an
+    /// entry are not preloaded and are listed in a `Z-INFO-MODULEPRELOAD-UNMAPPED` diagnostic
+    pub import_url_map: HashMap<String, String>,
+    /// Add `<link rel="prefetch">` to the head for the asset of each route the page
+    /// navigates to (see `navigation.rs`)
+    pub prefetch_links: bool,
+    /// Route → URL of the asset to prefetch for it; routes without an entry are
+    /// not prefetched
+    pub prefetch_routes: HashMap<String, String>,
+    /// Move static inline SVGs that repeat on the page into one hidden sprite and
+    /// render each occurrence as a `<use>` reference (see `svg_sprite.rs`)
+    pub svg_sprite: bool,
+    /// Build environment (`staging`, `production`, ...). Template regions marked for
+    /// other environments are removed before parsing (see `env.rs`).
+    pub env: String,
+    /// Syntax level of the bundle; below `EsNext` newer syntax is lowered for older
+    /// browsers (see `es_target.rs`)
+    pub target_es: Option<crate::es_target::EsTarget>,
+    /// Cross-check template classes against style selectors (see `css_usage.rs`)
+    pub warn_unstyled_classes: bool,
+    /// Regex of class names the cross-check skips (`^js-`, utility prefixes)
+    pub unstyled_class_ignore: Option<String>,
+    /// Warn for template member access on state that starts `undefined` or `null`,
+    /// or with a value unknown at compile time, without `?.` or a guard (see `nullish.rs`)
+    pub strict_nullish: bool,
+    /// Deepest markup nesting, page and components expanded, before the compile
+    /// fails with `Z-ERR-MAX-DEPTH` (see `depth.rs`). Unset: `DEFAULT_MAX_DEPTH`.
+    pub max_depth: Option<usize>,
+    /// Read-only visitors over the final node tree; their outputs go into the
+    /// manifest's `observations` (see `observers.rs`)
+    pub observers: crate::observers::Observers,
+    /// Parse the compiled expressions and bundle and check them against the ground-truth
+    /// rules (see `invariants.rs`). Unset: on in debug builds, off in release builds.
+    pub verify_invariants: Option<bool>,
+    /// Limit component `<style>` blocks to the component's own markup, with the given
+    /// selector strategy (see `css_scope.rs`). Unset: styles apply page-wide.
+    pub css_scope_strategy: Option<crate::css_scope::ScopeStrategy>,
+    /// Prefix of the class names the compiler generates (see `class_names.rs`).
+    /// Unset: `zen-`.
+    pub class_prefix: Option<String>,
+    /// Component paths whose styles go into their own shared chunk (see `style_chunks.rs`)
+    pub shared_components: Vec<String>,
+    /// Component script summaries shared across compiles (see `script_cache.rs`).
+    /// Unset: each compile parses the scripts of the components it uses; a batch
+    /// shares one cache.
+    pub script_cache: Option<crate::script_cache::ScriptCache>,
 }
 
 /// Result of internal compilation (Rust structs, no JSON serialization)
@@ -1212,8 +2306,59 @@ pub struct CompileResult {
     pub html: String,
     pub has_errors: bool,
     pub errors: Vec<String>,
+    pub warnings: Vec<String>,
     pub manifest: Option<crate::finalize::ZenManifestExport>,
     pub bindings: Vec<crate::transform::Binding>,
+    /// Static `t('key')` message keys with their source locations (when extraction is enabled)
+    pub i18n_keys: Vec<(String, crate::validate::SourceLocation)>,
+    /// Critical images and fonts worth preloading (see `preload.rs`)
+    pub preload_hints: Vec<crate::preload::PreloadHint>,
+    /// Routes the page navigates to via `zenLink()` or internal links, sorted
+    /// (see `navigation.rs`)
+    pub navigation_targets: Vec<String>,
+    /// Layouts, components, npm packages and external scripts this page depends on
+    pub dep_graph: crate::dep_graph::DepGraph,
+    /// ESM output of a script-only module (see `module.rs`); `html` is empty then
+    pub module_code: Option<String>,
+    /// The page CSS split into cacheable chunks, in cascade order (see `style_chunks.rs`)
+    pub style_chunks: Vec<crate::style_chunks::StyleChunk>,
+}
+
+/// One-line form of an invariant violation for the `errors` list
+fn format_invariant_error(e: &CompilerError) -> String {
+    format!(
+        "{}: {} ({}:{}:{})",
+        e.code, e.message, e.file, e.line, e.column
+    )
+}
+
+/// INV010 errors for a finished page that breaks the ground-truth rules
+fn verify_output_invariants(
+    ir: &crate::validate::ZenIR,
+    html: &str,
+    manifest: Option<&crate::finalize::ZenManifestExport>,
+    bindings: &[crate::transform::Binding],
+) -> Vec<String> {
+    let Some(manifest) = manifest else {
+        return Vec::new();
+    };
+    let output = crate::invariants::CompiledOutput {
+        html,
+        expressions: &manifest.expressions,
+        bundle: &manifest.bundle,
+        bindings,
+        reactive_names: ir
+            .all_states
+            .keys()
+            .chain(&ir.page_bindings)
+            .chain(&ir.page_props)
+            .map(String::as_str)
+            .collect(),
+    };
+    crate::invariants::verify_output(&output, &ir.file_path)
+        .iter()
+        .map(format_invariant_error)
+        .collect()
 }
 
 /// Internal Zenith compilation entry point for Rolldown plugin.
@@ -1223,8 +2368,22 @@ pub fn compile_zen_internal(
     file_path: &str,
     options: CompileOptions,
 ) -> Result<CompileResult, String> {
-    use crate::component::resolve_components;
-    use crate::finalize::{finalize_output_internal, CompiledTemplate};
+    let max_depth = options.max_depth.unwrap_or(crate::depth::DEFAULT_MAX_DEPTH);
+    crate::depth::with_stack(max_depth, || compile_page(source, file_path, options))?
+        .map(|(result, _)| result)
+}
+
+/// `compile_zen_internal` plus the page's styles in cascade order, so a batch can
+/// re-chunk them once it knows which components its pages share
+fn compile_page(
+    source: &str,
+    file_path: &str,
+    options: CompileOptions,
+) -> Result<(CompileResult, Vec<crate::validate::StyleIR>), String> {
+    use crate::component::{resolve_components_with_options, ResolveOptions};
+    use crate::finalize::{
+        finalize_output_with_options, CompiledTemplate, FinalizeOptions, Finalized,
+    };
     use crate::validate::ZenIR;
 
     let mode = if options.mode.is_empty() {
@@ -1232,22 +2391,83 @@ pub fn compile_zen_internal(
     } else {
         options.mode.clone()
     };
+    zen_span!(DEBUG, "compile", file_path = %file_path, mode = %mode);
+    let reactive_head = options.reactive_head;
+    let restore_crlf = options.preserve_crlf && uses_crlf(source);
+    let normalized = normalize_source(source);
+    let (resolved, stripped_env_regions) =
+        crate::env::resolve_env_regions(normalized.as_ref(), file_path, &options.env)
+            .map_err(|e| format!("Template parse error: {}", e.message))?;
+    let source = resolved.as_ref();
+
+    // Component mode: no page, layout, or document context
+    if mode == "component" {
+        let mut result = compile_component_internal(source, file_path)?;
+        if let Some(manifest) = result.manifest.as_mut() {
+            manifest.stripped_env_regions = stripped_env_regions;
+        }
+        if restore_crlf {
+            result.html = result.html.replace('\n', "\r\n");
+        }
+        return Ok((result, Vec::new()));
+    }
 
-    // Step 1: Parse template
-    let template_ir = parse_template(source, file_path)
-        .map_err(|e| format!("Template parse error: {}", e.message))?;
+    let (html_tables, html_warnings) =
+        crate::html_tables::HtmlTables::new(&options.html_extensions);
+    let (template_ir, script_ir) = {
+        zen_span!(DEBUG, "parse");
 
-    // Step 2: Parse script
-    let script_ir = parse_script(source);
+        // Step 1: Parse template
+        let max_depth = options.max_depth.unwrap_or(crate::depth::DEFAULT_MAX_DEPTH);
+        let template_ir = parse_markup(source, file_path, None, max_depth, &html_tables)
+            .map_err(|e| format!("Template parse error: {}", e.message))?;
+
+        // Step 2: Parse script
+        (template_ir, parse_script(source))
+    };
+    let syntax_errors = page_syntax_errors(&template_ir, script_ir.as_ref(), source, file_path);
+    crate::cancel::check(options.cancel.as_ref())?;
+
+    // Script-only module: no page to build
+    if let Some(script) = script_ir
+        .as_ref()
+        .filter(|_| crate::module::is_script_only(&template_ir))
+    {
+        let (module_code, errors) = match crate::module::compile_module(script, file_path) {
+            Ok(code) => (Some(code), vec![]),
+            Err(errors) => (None, errors),
+        };
+        return Ok((
+            CompileResult {
+                html: String::new(),
+                has_errors: !errors.is_empty(),
+                errors,
+                warnings: vec![],
+                manifest: None,
+                bindings: Vec::new(),
+                i18n_keys: Vec::new(),
+                preload_hints: Vec::new(),
+                navigation_targets: Vec::new(),
+                dep_graph: Default::default(),
+                module_code,
+                style_chunks: Vec::new(),
+            },
+            Vec::new(),
+        ));
+    }
 
     // Step 3: Build initial ZenIR
     let mut zen_ir = ZenIR {
         file_path: file_path.to_string(),
         template: template_ir,
         script: script_ir.clone(),
-        styles: crate::discovery::extract_styles_native(source.to_string())
+        styles: crate::discovery::extract_style_blocks(source)
             .into_iter()
-            .map(|raw| crate::validate::StyleIR { raw })
+            .map(|(raw, global)| crate::validate::StyleIR {
+                raw,
+                global,
+                source_component: String::new(),
+            })
             .collect(),
         props: script_ir
             .as_ref()
@@ -1266,76 +2486,379 @@ pub fn compile_zen_internal(
         uses_state: false,
         has_events: false,
         css_classes: vec![],
+        warnings: vec![],
+        used_filters: vec![],
+        unused_props: BTreeMap::new(),
+        component_usages: Vec::new(),
+        inlined_components: 0,
+        conditional_props: BTreeMap::new(),
+        coerced_props: BTreeMap::new(),
+        component_events: BTreeMap::new(),
+        dep_graph: Default::default(),
+        layout_chain: Vec::new(),
+        island_props: HashMap::new(),
+        hydration: Vec::new(),
+        loop_item_keys: Vec::new(),
+        generated_classes: BTreeMap::new(),
+        islands: None,
+        extra: Default::default(),
     };
+    zen_ir.warnings = zen_ir.template.warnings.clone();
+    zen_ir.warnings.extend(html_warnings);
 
     // For metadata mode, return early
     if mode == "metadata" {
-        return Ok(CompileResult {
-            html: String::new(),
-            has_errors: false,
-            errors: vec![],
-            manifest: None,
-            bindings: Vec::new(),
-        });
+        return Ok((
+            CompileResult {
+                html: String::new(),
+                has_errors: false,
+                errors: vec![],
+                warnings: vec![],
+                manifest: None,
+                bindings: Vec::new(),
+                i18n_keys: Vec::new(),
+                preload_hints: Vec::new(),
+                navigation_targets: Vec::new(),
+                dep_graph: Default::default(),
+                module_code: None,
+                style_chunks: Vec::new(),
+            },
+            Vec::new(),
+        ));
     }
 
+    // Page-only dependency graph; resolution replaces it with the full one
+    zen_ir.dep_graph = crate::component::page_dep_graph(&zen_ir);
+
+    // Step 3.9: `zenAssert` in the page script (component scripts are prepared on resolution)
+    let assert_errors = crate::zen_assert::prepare_page_script(&mut zen_ir, source);
+
     // Step 4: Resolve components if provided
     if !options.components.is_empty() {
-        zen_ir = resolve_components(zen_ir, options.components.clone())?;
+        zen_span!(DEBUG, "resolve", components = options.components.len());
+        let resolve_options = ResolveOptions {
+            coerce_static_props: options.coerce_static_props,
+            islands: options.islands,
+            inline_components: options.inline_components,
+            strict_slots: options.strict_slots,
+            cancel: options.cancel.clone(),
+            strict_ir: options.strict_ir,
+            strict_registry: options.strict_registry,
+            css_scope: options.css_scope_strategy,
+            class_prefix: options.class_prefix.clone(),
+            max_depth: options.max_depth,
+            script_cache: options.script_cache.clone(),
+        };
+        zen_ir =
+            resolve_components_with_options(zen_ir, options.components.clone(), &resolve_options)?;
     }
 
-    // Step 5: Transform template
-    let is_document = crate::document::is_document_module(&zen_ir.template.nodes);
+    // Step 4.4: Lower markup-rendering expressions to fragments
+    let lowering_errors = if options.ast_classifier {
+        crate::classify::lower_fragments(&mut zen_ir.template, file_path, &mut zen_ir.warnings)
+    } else {
+        Vec::new()
+    };
 
-    let document_scope = if is_document {
-        let mut props_map: std::collections::HashMap<String, String> = options.props.clone();
-        let static_props = extract_static_layout_props(source);
-        for (k, v) in static_props {
-            props_map.insert(k, v);
-        }
+    // Step 4.5: Extract / inline translation calls
+    let i18n_keys = match &options.i18n {
+        Some(i18n) => crate::i18n::process_i18n(&mut zen_ir, source, i18n),
+        None => Vec::new(),
+    };
 
-        let mut script_source = extract_script_block(source).unwrap_or_default();
-        for (_name, comp_val) in &options.components {
-            if let Ok(comp) =
-                serde_json::from_value::<crate::component::ComponentIR>(comp_val.clone())
-            {
-                if crate::document::is_document_module(&comp.nodes) {
-                    if let Some(s) = comp.script {
-                        script_source = s;
-                        break;
-                    }
-                }
-            }
-        }
+    // Step 4.6: Lower filter pipes to zenFilter calls
+    crate::filters::lower_filter_pipes(&mut zen_ir);
 
-        match crate::document::execute_document_script(&script_source, &props_map) {
-            Ok(scope) => Some(scope),
-            Err(_) => None,
+    // Step 4.7: Reconcile node expression ids with the registry
+    let mut reconcile_errors = lowering_errors;
+    reconcile_errors.extend(syntax_errors);
+    reconcile_errors.extend(
+        crate::reconcile::reconcile_expressions(&mut zen_ir)
+            .into_iter()
+            .map(|e| format_invariant_error(&e)),
+    );
+    reconcile_errors.extend(assert_errors);
+    reconcile_errors.extend(crate::zen_assert::prepare_expressions(&mut zen_ir));
+
+    // Step 4.72: Expand `@zen-screen` / `@zen-container` in page and component styles
+    reconcile_errors.extend(crate::breakpoints::expand_style_queries(
+        &mut zen_ir,
+        &options.breakpoints,
+    ));
+
+    crate::cancel::check(options.cancel.as_ref())?;
+
+    // Step 4.75: Production output drops insignificant whitespace
+    if options.minify {
+        crate::minify::collapse_whitespace(&mut zen_ir.template.nodes);
+    }
+
+    // Step 4.76: Classes the template can carry, for CSS pruning
+    zen_ir.css_classes = crate::finalize::collect_css_classes(&zen_ir.template.nodes);
+    if options.warn_unstyled_classes {
+        match crate::css_usage::check_class_usage(
+            &zen_ir.css_classes,
+            crate::finalize::has_dynamic_classes(&zen_ir.template.nodes),
+            &zen_ir.styles,
+            options.unstyled_class_ignore.as_deref(),
+            file_path,
+        ) {
+            Ok(warnings) => zen_ir.warnings.extend(warnings),
+            Err(e) => reconcile_errors.push(e),
         }
+    }
+
+    // Step 4.8: Islands mode folds everything outside islands to static HTML
+    if options.islands {
+        reconcile_errors.extend(crate::islands::apply_islands(&mut zen_ir));
+    }
+
+    // Step 5: Transform template
+    let is_document = crate::document::is_document_module(&zen_ir.template.nodes);
+
+    let document_scope = if is_document {
+        build_document_scope(&zen_ir, source, options.props.clone(), &options.components)
     } else {
         None
     };
 
-    let transform_output = crate::transform::transform_template_with_scope(
-        &zen_ir.template.nodes,
-        &zen_ir.template.expressions,
-        document_scope.as_ref(),
-    );
+    // Step 5.1: Evaluate `define:vars` into the is:inline scripts
+    let static_values = crate::define_vars::static_values(document_scope.as_ref(), &options.props);
+    reconcile_errors.extend(crate::define_vars::apply_define_vars(
+        &mut zen_ir,
+        &static_values,
+    ));
+    // Step 5.2: Head and zen:static expressions must not need a browser
+    reconcile_errors.extend(crate::ssr_safety::check_compile_time_expressions(
+        &mut zen_ir,
+    ));
+    // Step 5.25: Conditions and reflected attributes that need a browser to decide
+    crate::hydration_mismatch::check_hydration_mismatch(&mut zen_ir);
+    // Step 5.26: `onclick|lazy` becomes `onclick` plus a `zen:lazy` marker
+    crate::lazy::normalize_lazy_modifiers(&mut zen_ir.template.nodes);
+    // Step 5.27: `zen:pending` targets are boolean states, handlers are functions
+    reconcile_errors.extend(crate::pending::check_pending_handlers(&mut zen_ir));
+    // Step 5.28: `zen:portal` subtrees render inert in place, mounted at their target
+    reconcile_errors.extend(crate::portal::wrap_portals(&mut zen_ir, is_document));
+    // Step 5.29: Member access on state that may not have a value yet
+    if options.strict_nullish {
+        crate::nullish::check_nullish_access(&mut zen_ir);
+    }
+
+    // Step 5.3: Server-render only the first chunk of zen:virtualize loops
+    let virtual_loops = crate::virtualize::plan_virtual_loops(&mut zen_ir);
+    // Step 5.31: Option state in loops binds as properties; static options SSR
+    let select_loops = crate::select_options::plan_select_options(&mut zen_ir, &virtual_loops);
+    // Step 5.32: Item member access checked against static loop sources
+    crate::loop_shapes::check_loop_item_keys(&mut zen_ir, &static_values);
+    // Step 5.33: Fragments that restructure aria-live regions
+    crate::live_regions::check_live_regions(&mut zen_ir);
+    // Step 5.4: Custom observers see the final node tree
+    let (observations, observer_warnings) =
+        crate::observers::run_observers(&options.observers, &zen_ir);
+    zen_ir.warnings.extend(observer_warnings);
+
+    let head_options = crate::transform::HeadOptions {
+        reactive: reactive_head,
+        initial_values: crate::static_eval::initial_state_values(&zen_ir.all_states),
+        initial_objects: crate::attr_coerce::initial_object_values(&zen_ir.all_states),
+        minify: options.minify,
+        virtual_loops,
+        select_loops,
+        html: html_tables,
+    };
+    let transform_output = {
+        zen_span!(DEBUG, "transform");
+        crate::transform::transform_template_cancellable(
+            &zen_ir.template.nodes,
+            &zen_ir.template.expressions,
+            document_scope.as_ref(),
+            &head_options,
+            options.cancel.as_ref(),
+        )
+    };
+    crate::cancel::check(options.cancel.as_ref())?;
 
     let compiled = CompiledTemplate {
         html: transform_output.html,
         styles: vec![],
+        binding_count: transform_output.bindings.len(),
     };
 
     // Step 6: Finalize output
-    let finalized = finalize_output_internal(zen_ir.clone(), compiled)?;
+    let finalize_options = FinalizeOptions {
+        budgets: options.budgets.clone(),
+        filters: options.filters.clone(),
+        reactive_head,
+        preload_limit: options.preload_limit,
+        minify: options.minify,
+        strip_debug: options.strip_debug.clone(),
+        cancel: options.cancel.clone(),
+        embed: options.embed_mode,
+        modulepreload: options.modulepreload,
+        import_url_map: options.import_url_map.clone(),
+        prefetch_links: options.prefetch_links,
+        prefetch_routes: options.prefetch_routes.clone(),
+        svg_sprite: options.svg_sprite,
+        target_es: options.target_es.unwrap_or_default(),
+        expression_complexity: options.expression_complexity.clone(),
+        plan_filter: options.plan_filter,
+    };
+    let finalized = {
+        zen_span!(DEBUG, "finalize");
+        match finalize_output_with_options(zen_ir.clone(), compiled, &finalize_options)? {
+            Finalized::Page(finalized) => finalized,
+            // Step 6.1: The plan filter asked for islands mode
+            Finalized::ForceIslands => {
+                let options = CompileOptions {
+                    islands: true,
+                    ..options
+                };
+                return compile_page(source, file_path, options);
+            }
+        }
+    };
+    let html = if restore_crlf {
+        finalized.html.replace('\n', "\r\n")
+    } else {
+        finalized.html
+    };
+
+    let mut errors = reconcile_errors;
+    errors.extend(finalized.errors);
+    // Step 6.5: Check the output against the ground-truth rules
+    if errors.is_empty() && options.verify_invariants.unwrap_or(cfg!(debug_assertions)) {
+        errors.extend(verify_output_invariants(
+            &zen_ir,
+            &html,
+            finalized.manifest.as_ref(),
+            &transform_output.bindings,
+        ));
+    }
+    let errors = crate::diagnostics::normalize(errors);
+    let mut manifest = finalized.manifest;
+    if let Some(manifest) = manifest.as_mut() {
+        manifest.stripped_env_regions = stripped_env_regions;
+        manifest.observations = observations;
+    }
+    let navigation_targets = manifest
+        .as_ref()
+        .map(|m| m.navigation.targets.clone())
+        .unwrap_or_default();
+
+    // Step 6.6: Split the styles into cacheable chunks
+    let styles = crate::global_styles::document_order(zen_ir.styles);
+    let shared = options.shared_components.iter().cloned().collect();
+    let style_chunks = crate::style_chunks::chunk_styles(&styles, file_path, &shared);
+
+    Ok((
+        CompileResult {
+            html,
+            has_errors: !errors.is_empty(),
+            errors,
+            warnings: finalized.warnings,
+            manifest,
+            bindings: transform_output.bindings,
+            i18n_keys,
+            preload_hints: finalized.preload_hints,
+            navigation_targets,
+            dep_graph: zen_ir.dep_graph,
+            module_code: None,
+            style_chunks,
+        },
+        styles,
+    ))
+}
+
+/// Compile several `(source, file_path)` pairs with the same options. Each file
+/// keeps its own result; a cancelled `options.cancel` aborts the whole batch with
+/// `Err(COMPILE_CANCELLED)` and drops the results already finished. Components
+/// whose styles several pages carry are chunked as shared, and component scripts
+/// are parsed once for the whole batch.
+pub fn compile_zen_batch_internal(
+    files: &[(&str, &str)],
+    mut options: CompileOptions,
+) -> Result<Vec<Result<CompileResult, String>>, String> {
+    options.script_cache.get_or_insert_with(Default::default);
+    let mut pages = Vec::with_capacity(files.len());
+    for (source, file_path) in files {
+        let max_depth = options.max_depth.unwrap_or(crate::depth::DEFAULT_MAX_DEPTH);
+        let page = crate::depth::with_stack(max_depth, || {
+            compile_page(source, file_path, options.clone())
+        })
+        .and_then(|page| page);
+        match page {
+            Err(e) if crate::cancel::is_cancelled_error(&e) => return Err(e),
+            page => pages.push(page),
+        }
+    }
+
+    // Components more than one page uses get their own shared style chunk
+    let page_styles: Vec<Vec<crate::validate::StyleIR>> = pages
+        .iter()
+        .filter_map(|page| page.as_ref().ok().map(|(_, styles)| styles.clone()))
+        .collect();
+    let mut shared = crate::style_chunks::shared_across_pages(&page_styles);
+    shared.extend(options.shared_components.iter().cloned());
+
+    Ok(pages
+        .into_iter()
+        .zip(files)
+        .map(|(page, (_, file_path))| {
+            page.map(|(mut result, styles)| {
+                result.style_chunks =
+                    crate::style_chunks::chunk_styles(&styles, file_path, &shared);
+                result
+            })
+        })
+        .collect())
+}
+
+/// Compile a single component to its HTML fragment and standalone manifest
+/// (used to publish precompiled components to a registry).
+fn compile_component_internal(source: &str, file_path: &str) -> Result<CompileResult, String> {
+    let name = std::path::Path::new(file_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Component");
+    let component = crate::component::ComponentIR::from_source(name, file_path, source)?;
+
+    let head_options = crate::transform::HeadOptions {
+        reactive: false,
+        initial_values: crate::static_eval::initial_state_values(&component.states),
+        initial_objects: crate::attr_coerce::initial_object_values(&component.states),
+        minify: false,
+        virtual_loops: HashMap::new(),
+        select_loops: HashMap::new(),
+        html: Default::default(),
+    };
+    let transform_output = crate::transform::transform_template_with_options(
+        &component.nodes,
+        &component.expressions,
+        None,
+        &head_options,
+    );
+
+    let compiled = crate::finalize::CompiledTemplate {
+        html: transform_output.html.clone(),
+        styles: vec![],
+        binding_count: transform_output.bindings.len(),
+    };
+    let manifest = crate::finalize::finalize_component(&component, compiled);
 
     Ok(CompileResult {
-        html: finalized.html,
-        has_errors: finalized.has_errors,
-        errors: finalized.errors,
-        manifest: finalized.manifest,
+        html: transform_output.html,
+        has_errors: false,
+        errors: vec![],
+        warnings: vec![],
+        manifest: Some(manifest),
         bindings: transform_output.bindings,
+        i18n_keys: Vec::new(),
+        preload_hints: Vec::new(),
+        navigation_targets: Vec::new(),
+        dep_graph: Default::default(),
+        module_code: None,
+        style_chunks: Vec::new(),
     })
 }
 
@@ -1347,7 +2870,7 @@ pub fn compile_zen_internal(
 /// Matches patterns like:
 /// - interface Props { title: string; description: string; }
 /// - interface Props {\n    title: string;\n    number: number;\n}
-fn extract_props_from_interface(script: &str) -> Vec<String> {
+pub(crate) fn extract_props_from_interface(script: &str) -> Vec<String> {
     let mut props = Vec::new();
 
     // Match `interface Props { ... }` block
@@ -1357,7 +2880,9 @@ fn extract_props_from_interface(script: &str) -> Vec<String> {
         if let Some(body) = cap.get(1) {
             let body_str = body.as_str();
             // Match property definitions: name: type or name?: type
-            let prop_re = Regex::new(r"([a-zA-Z_$][a-zA-Z0-9_$]*)\s*\??\s*:").unwrap();
+            let prop_re =
+                Regex::new(r"([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)\s*\??\s*:")
+                    .unwrap();
             for prop_cap in prop_re.captures_iter(body_str) {
                 if let Some(m) = prop_cap.get(1) {
                     props.push(m.as_str().to_string());
@@ -1366,12 +2891,7 @@ fn extract_props_from_interface(script: &str) -> Vec<String> {
         }
     }
 
-    if !props.is_empty() {
-        // eprintln!(
-        //     "[Zenith PARSE_SCRIPT] Interface Props extracted: {:?}",
-        //     props
-        // );
-    }
+    zen_trace!(props = ?props, "interface props extracted");
     props
 }
 
@@ -1421,6 +2941,57 @@ mod tests {
         assert!(exprs.values().any(|v| v == "count"));
     }
 
+    #[test]
+    fn test_find_balanced_brace_multibyte() {
+        // Byte offsets, not char counts: "🎉" is four bytes
+        assert_eq!(find_balanced_brace_end("{'🎉'}", 0), Some(8));
+        assert_eq!(find_balanced_brace_end("שלום {x}", 9), Some(12));
+        // Not a char boundary
+        assert_eq!(find_balanced_brace_end("ש{x}", 1), None);
+        // Template literal still open at EOF
+        assert_eq!(find_balanced_brace_end("{`abc ${x}", 0), None);
+    }
+
+    #[test]
+    fn test_unterminated_expression_warning() {
+        let result =
+            compile_zen_internal("<p>{a} {broken</p>", "page.zen", CompileOptions::default())
+                .unwrap();
+        let warning = result
+            .warnings
+            .iter()
+            .find(|w| w.starts_with("Z-WARN-UNTERMINATED-EXPRESSION"))
+            .expect("unterminated brace should be reported");
+        assert!(warning.contains("`{broken</p>`"), "{}", warning);
+        assert!(warning.contains("page.zen"));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_find_balanced_brace_end_is_in_bounds(
+            s in r#"[a-z{}'"`$\\ \nשל🎉😀]{0,48}"#,
+            start in 0usize..64,
+        ) {
+            if let Some(end) = find_balanced_brace_end(&s, start) {
+                proptest::prop_assert!(end > start && end <= s.len());
+                proptest::prop_assert!(s.is_char_boundary(end));
+                proptest::prop_assert_eq!(&s[end - 1..end], "}");
+            }
+        }
+
+        #[test]
+        fn prop_normalize_round_trips(s in r#"[a-z{}'"`$\\ \nשל🎉😀]{0,48}"#) {
+            let (normalized, exprs) = normalize_all_expressions(&s);
+            for key in exprs.keys() {
+                proptest::prop_assert_eq!(normalized.matches(key.as_str()).count(), 1);
+            }
+            let restored = EXPR_PLACEHOLDER_RE.replace_all(&normalized, |caps: &regex::Captures| {
+                format!("{{{}}}", exprs[&caps[0]])
+            });
+            proptest::prop_assert_eq!(restored, s);
+        }
+    }
+
     #[test]
     fn test_convert_self_closing() {
         let result = convert_self_closing_components("<Button />");
@@ -1431,6 +3002,214 @@ mod tests {
         assert!(result.contains("</Card>"));
     }
 
+    #[cfg(feature = "napi")]
+    fn normalize_expr_ids(s: &str) -> String {
+        let re = Regex::new(r"expr_\d+").unwrap();
+        re.replace_all(s, "expr_N").to_string()
+    }
+
+    #[cfg(feature = "napi")]
+    #[test]
+    fn test_lightweight_entry_points_match_full_pipeline() {
+        let source = r#"<div class={theme}><p>{count}</p></div>"#;
+
+        let template_json = parse_zen_template_json(source, "page.zen").unwrap();
+        let slice: serde_json::Value = serde_json::from_str(&template_json).unwrap();
+        assert!(slice.get("nodes").is_some());
+        assert_eq!(slice["expressions"].as_array().unwrap().len(), 2);
+
+        let transformed = crate::transform::transform_nodes_json(
+            &slice["nodes"].to_string(),
+            &slice["expressions"].to_string(),
+            None,
+        )
+        .unwrap();
+        let transformed: serde_json::Value = serde_json::from_str(&transformed).unwrap();
+
+        let full = compile_zen_internal(source, "page.zen", CompileOptions::default()).unwrap();
+        assert_eq!(
+            normalize_expr_ids(transformed["html"].as_str().unwrap()),
+            normalize_expr_ids(&full.html)
+        );
+        assert_eq!(
+            normalize_expr_ids(&transformed["bindings"].to_string()),
+            normalize_expr_ids(&serde_json::to_value(&full.bindings).unwrap().to_string())
+        );
+    }
+
+    #[cfg(feature = "napi")]
+    #[test]
+    fn test_parse_template_json_reports_errors() {
+        let err = parse_zen_template_json("<template><p>x</p></template>", "page.zen").unwrap_err();
+        assert!(err.starts_with("Template parse error: "), "{}", err);
+        assert!(err.contains("<template> tag is not supported"), "{}", err);
+    }
+
+    const BUDGET_FIXTURE: &str = r#"<script>
+state count = 0
+state label = "x"
+</script>
+<div class={label}><p>{count}</p><span>{count + 1}</span></div>"#;
+
+    #[test]
+    fn test_budget_exceeded_fails_with_code() {
+        let options = CompileOptions {
+            budgets: Some(crate::finalize::Budgets {
+                max_expressions: Some(2),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let result = compile_zen_internal(BUDGET_FIXTURE, "page.zen", options).unwrap();
+        assert!(result.has_errors);
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].starts_with("Z-ERR-BUDGET-EXPRESSIONS"));
+    }
+
+    #[test]
+    fn test_budget_metrics_reported_without_budgets() {
+        let plain =
+            compile_zen_internal(BUDGET_FIXTURE, "page.zen", CompileOptions::default()).unwrap();
+        assert!(!plain.has_errors);
+
+        let generous = CompileOptions {
+            budgets: Some(crate::finalize::Budgets {
+                max_expressions: Some(100),
+                max_bundle_bytes: Some(10_000_000),
+                max_state_keys: Some(100),
+                max_bindings: Some(100),
+            }),
+            ..Default::default()
+        };
+        let budgeted = compile_zen_internal(BUDGET_FIXTURE, "page.zen", generous).unwrap();
+        assert!(!budgeted.has_errors);
+
+        for result in [&plain, &budgeted] {
+            let metrics = &result.manifest.as_ref().unwrap().metrics;
+            assert_eq!(metrics.expressions, 3);
+            assert_eq!(metrics.state_keys, 2);
+            assert_eq!(metrics.bindings as usize, result.bindings.len());
+            assert!(metrics.bundle_bytes > 0);
+        }
+    }
+
+    fn compile_fragment_fixture(template: &str) -> (String, String) {
+        let source = format!(
+            "<script>\nstate items = []\nstate show = true\n</script>\n{}",
+            template
+        );
+        let result = compile_zen_internal(&source, "page.zen", CompileOptions::default()).unwrap();
+        let expressions = result.manifest.map(|m| m.expressions).unwrap_or_default();
+        (result.html, expressions)
+    }
+
+    #[test]
+    fn test_nested_fragments_two_deep() {
+        let (html, expressions) = compile_fragment_fixture(
+            "<ul>{items.map(item => <><><li>{item}</li></><li>{item}</li></>)}</ul>",
+        );
+        assert!(!html.contains("<>") && !html.contains("</>"));
+        assert!(!expressions.contains("</>"));
+        assert!(expressions.matches("window.__zenith.fragment(").count() >= 2);
+    }
+
+    #[test]
+    fn test_fragment_as_ternary_consequent() {
+        let (html, expressions) =
+            compile_fragment_fixture("<div>{show ? <><a>x</a><b>y</b></> : null}</div>");
+        assert!(!html.contains("</>"));
+        assert!(!expressions.contains("</>"));
+        assert!(expressions.contains("window.__zenith.fragment("));
+    }
+
+    #[test]
+    fn test_fragment_containing_loop() {
+        let (html, expressions) = compile_fragment_fixture(
+            "<div>{show && <><h2>List</h2>{items.map(i => <p>{i}</p>)}</>}</div>",
+        );
+        assert!(!html.contains("</>"));
+        assert!(!expressions.contains("</>"));
+        assert!(expressions.contains("window.__zenith.fragment("));
+        assert!(expressions.contains(".map("));
+    }
+
+    const PANEL: &str = r#"<script>
+prop label
+state open = false
+</script>
+<div class="panel shadow"><span class="label">{label}</span><p>{open}</p><slot /></div>"#;
+
+    #[test]
+    fn test_component_mode_matches_inlined_contribution() {
+        let standalone = compile_zen_internal(
+            PANEL,
+            "components/Panel.zen",
+            CompileOptions {
+                mode: "component".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let component_manifest = standalone.manifest.unwrap();
+        assert!(component_manifest.uses_state);
+        assert_eq!(
+            component_manifest.css_classes,
+            vec!["label", "panel", "shadow"]
+        );
+        assert_eq!(component_manifest.props, vec!["label"]);
+        assert_eq!(component_manifest.slots, vec!["default"]);
+        assert!(standalone.html.contains("class=\"panel shadow\""));
+
+        let panel =
+            crate::component::ComponentIR::from_source("Panel", "components/Panel.zen", PANEL)
+                .unwrap();
+        let mut components = HashMap::new();
+        components.insert("Panel".to_string(), serde_json::to_value(panel).unwrap());
+        let page = compile_zen_internal(
+            "<main><Panel label=\"Hi\" /></main>",
+            "page.zen",
+            CompileOptions {
+                components,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let page_manifest = page.manifest.unwrap();
+        assert_eq!(page_manifest.uses_state, component_manifest.uses_state);
+        assert_eq!(page_manifest.css_classes, component_manifest.css_classes);
+    }
+
+    fn compile_fallback_fixture(template: &str) -> String {
+        let source = format!(
+            "<script>\nstate user = null\nconst extra = () => <em>more</em>\n</script>\n{}",
+            template
+        );
+        let result = compile_zen_internal(&source, "page.zen", CompileOptions::default()).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        result.manifest.map(|m| m.expressions).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_nullish_with_jsx_fallback() {
+        let expressions = compile_fallback_fixture("<div>{user ?? <p>Guest</p>}</div>");
+        assert!(expressions.contains("scope.state.user ??"));
+        assert!(expressions.contains("window.__zenith.h(\"p\""));
+        assert!(!expressions.contains("<p>"));
+    }
+
+    #[test]
+    fn test_logical_or_with_jsx_fallback() {
+        let expressions = compile_fallback_fixture("<div>{user || <p>Guest</p>}</div>");
+        assert!(expressions.contains("scope.state.user ||"));
+        assert!(expressions.contains("window.__zenith.h(\"p\""));
+    }
+
+    #[test]
+    fn test_optional_call_returning_jsx() {
+        let expressions = compile_fallback_fixture("<div>{extra?.()}</div>");
+        assert!(expressions.contains("extra?.()"));
+    }
+
     #[test]
     fn test_parse_script() {
         let html = r#"<script setup lang="ts">const x = 1;</script>"#;
@@ -1441,4 +3220,291 @@ mod tests {
         assert_eq!(script.attributes.get("setup"), Some(&"true".to_string()));
         assert_eq!(script.attributes.get("lang"), Some(&"ts".to_string()));
     }
+
+    #[test]
+    fn test_conditional_prop_attribute() {
+        let template = parse_template("<Badge size?={chosen} class?=\"x\" />", "page.zen").unwrap();
+        let TemplateNode::Component(badge) = &template.nodes[0] else {
+            panic!("expected a component node");
+        };
+        let size = badge.attributes.iter().find(|a| a.name == "size").unwrap();
+        assert!(size.optional);
+        assert!(matches!(
+            size.value,
+            crate::validate::AttributeValue::Dynamic(_)
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_static_class_merges_with_warning() {
+        let template = parse_template(
+            "<p class=\"lead\" id=\"intro\" class=\"muted\">Hi</p>",
+            "page.zen",
+        )
+        .unwrap();
+        let TemplateNode::Element(p) = &template.nodes[0] else {
+            panic!("expected <p>, got {:?}", template.nodes[0]);
+        };
+        let classes: Vec<&crate::validate::AttributeValue> = p
+            .attributes
+            .iter()
+            .filter(|a| a.name == "class")
+            .map(|a| &a.value)
+            .collect();
+        assert!(matches!(
+            classes.as_slice(),
+            [crate::validate::AttributeValue::Static(v)] if v == "lead muted"
+        ));
+        assert!(p.attributes.iter().any(|a| a.name == "id"));
+
+        assert_eq!(template.warnings.len(), 1);
+        assert!(template.warnings[0].starts_with("Z-WARN-DUPLICATE-ATTRIBUTE: <p> in page.zen"));
+        assert!(template.warnings[0].contains("class=\"lead muted\""));
+    }
+
+    #[test]
+    fn test_two_dynamic_styles_combine() {
+        let template = parse_template("<div style={a} style={b}></div>", "page.zen").unwrap();
+        assert_eq!(template.expressions.len(), 1);
+        assert_eq!(
+            template.expressions[0].code,
+            "[(a), (b)].filter(Boolean).join(\"; \")"
+        );
+        assert!(template.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_normalize_source_strips_bom_and_crlf() {
+        assert_eq!(
+            normalize_source("\u{feff}<p>a</p>\r\n<p>b</p>\r"),
+            "<p>a</p>\n<p>b</p>\n"
+        );
+        assert!(matches!(normalize_source("<p>a</p>\n"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_parse_script_with_bom_and_crlf() {
+        let source =
+            "\u{feff}<script>\r\nstate count = 1\r\nprop label\r\n</script>\r\n<p>{count}</p>";
+        let script = parse_script(source).unwrap();
+        assert_eq!(script.states.get("count"), Some(&"1".to_string()));
+        assert_eq!(script.props, vec!["label".to_string()]);
+        assert!(!script.raw.contains('\r'));
+    }
+
+    #[test]
+    fn test_preserve_crlf_restores_line_endings() {
+        let source =
+            "<div>\r\n  <p>{count}</p>\r\n</div>\r\n<script>\r\nstate count = 1\r\n</script>";
+        let compile = |preserve_crlf| {
+            compile_zen_internal(
+                source,
+                "page.zen",
+                CompileOptions {
+                    preserve_crlf,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .html
+        };
+        let lf = compile(false);
+        let crlf = compile(true);
+        assert!(!lf.contains('\r'));
+        assert_eq!(crlf, lf.replace('\n', "\r\n"));
+    }
+
+    fn compile_islands(source: &str) -> CompileResult {
+        compile_zen_internal(
+            source,
+            "page.zen",
+            CompileOptions {
+                islands: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_islands_hydrate_only_marked_subtree() {
+        let source = "<script>\nstate count = 1\nstate title = \"Shop\"\nfunction increment() { count++ }\n</script>\n<main>\n  <h1>{title}</h1>\n  <p class={title}>Seen {count} times</p>\n  <div zen:island>\n    <button onclick={increment}>{count}</button>\n  </div>\n</main>";
+        let result = compile_islands(source);
+        assert!(!result.has_errors, "{:?}", result.errors);
+
+        let (static_html, island_html) = result.html.split_once("<zen-island").unwrap();
+        assert!(static_html.contains("<h1>Shop</h1>"));
+        assert!(static_html.contains("<p class=\"Shop\">Seen 1 times</p>"));
+        assert!(!static_html.contains("<!--zen:"));
+        assert!(!static_html.contains("data-zen-"));
+        assert!(island_html.starts_with(" data-zen-island=\"island0\""));
+        assert!(island_html.contains("<!--zen:"));
+
+        let manifest = result.manifest.unwrap();
+        assert_eq!(manifest.islands.len(), 1);
+        let island = &manifest.islands[0];
+        assert_eq!(island.id, "island0");
+        assert_eq!(island.expressions, 2);
+        assert_eq!(island.deps, vec!["count".to_string()]);
+        assert_eq!(manifest.metrics.expressions, 2);
+        assert_eq!(manifest.bundle.matches("function _expr_").count(), 2);
+        assert!(manifest
+            .bundle
+            .contains("document.querySelector('[data-zen-island=\"island0\"]')"));
+        assert!(!manifest
+            .bundle
+            .contains("window.zenithHydrate(state, document, locals)"));
+    }
+
+    #[test]
+    fn test_islands_reject_unfoldable_static_expression() {
+        let source = "<div><p>{Math.random()}</p><div zen:island><p>ok</p></div></div>";
+        let result = compile_islands(source);
+        assert!(result.has_errors);
+        assert!(
+            result.errors[0].starts_with("Z-ERR-ISLAND-DYNAMIC: `Math.random()` in page.zen"),
+            "{:?}",
+            result.errors
+        );
+        assert!(result.errors[0].contains("Add `zen:island`"));
+    }
+
+    fn text_html(template: &str) -> String {
+        let source = format!(
+            "<script>\nstate name = 'Ada'\nstate title = 'Dr'\n</script>\n{}",
+            template
+        );
+        let result = compile_zen_internal(&source, "page.zen", CompileOptions::default()).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        Regex::new(r"<!--zen:expr_\d+-->")
+            .unwrap()
+            .replace_all(&result.html, "{}")
+            .to_string()
+    }
+
+    #[test]
+    fn test_punctuation_around_expression_is_exact() {
+        assert!(text_html("<p>Hello, {name}!</p>").contains("<p>Hello, {}!</p>"));
+        // Whitespace between placeholders and beside inline elements renders
+        assert!(text_html("<p>{title} {name}</p>").contains("<p>{} {}</p>"));
+        assert!(text_html("<p>Hi <b>there</b>\n  {name}</p>").contains("<p>Hi <b>there</b> {}</p>"));
+    }
+
+    #[test]
+    fn test_whitespace_between_inline_elements_renders() {
+        assert!(text_html("<p><b>a</b> <i>b</i></p>").contains("<p><b>a</b> <i>b</i></p>"));
+        let html = text_html("<p>\n  <b>a</b>\n  <i>b</i>\n</p>");
+        assert!(html.contains("<p><b>a</b> <i>b</i></p>"), "{}", html);
+        // Between SVG shapes whitespace is formatting
+        let html = text_html("<svg>\n  <circle r=\"1\" />\n  <rect />\n</svg>");
+        assert!(!html.contains("> <"), "{}", html);
+    }
+
+    #[test]
+    fn test_whitespace_between_blocks_collapses() {
+        let html = text_html("<main>\n  <div>a</div>\n  <div>{name}</div>\n</main>");
+        assert!(
+            html.contains("<main><div>a</div><div>{}</div></main>"),
+            "{}",
+            html
+        );
+        let html = text_html("<main>\n  <div>a</div>\n  {name}\n  <div>b</div>\n</main>");
+        assert!(
+            html.contains("<main><div>a</div>{}<div>b</div></main>"),
+            "{}",
+            html
+        );
+    }
+
+    /// `text_html` with `Link` and `Nav` components (an anchor around their slot)
+    fn component_html(template: &str) -> String {
+        let link = crate::component::ComponentIR::from_source(
+            "Link",
+            "components/Link.zen",
+            "<a class=\"link\"><slot /></a>",
+        )
+        .unwrap();
+        let link = serde_json::to_value(link).unwrap();
+        let source = format!("<script>\nstate name = 'Ada'\n</script>\n{}", template);
+        let options = CompileOptions {
+            components: [
+                ("Link".to_string(), link.clone()),
+                ("Nav".to_string(), link),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let result = compile_zen_internal(&source, "page.zen", options).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        Regex::new(r"<!--zen:expr_\d+-->")
+            .unwrap()
+            .replace_all(&result.html, "{}")
+            .to_string()
+    }
+
+    #[test]
+    fn test_inline_component_keeps_adjacent_spaces() {
+        let sentence = "<p>Read the <a class=\"link\">docs</a> for details.</p>";
+        for template in [
+            "<p>Read the <Link href=\"/docs\">docs</Link> for details.</p>",
+            "<p>Read the <Link\n  href=\"/docs\"\n>docs</Link> for details.</p>",
+            // Named like a block element, it still does not close the paragraph
+            "<p>Read the <Nav\n  href=\"/docs\">docs</Nav> for details.</p>",
+        ] {
+            let html = component_html(template);
+            assert!(html.contains(sentence), "{}: {}", template, html);
+        }
+        let html = component_html("<p>Ask {name} <Nav>here</Nav> {name}.</p>");
+        assert!(
+            html.contains("<p>Ask {} <a class=\"link\">here</a> {}.</p>"),
+            "{}",
+            html
+        );
+        let html = component_html("<main><div>Hi {name}\n  <Link>there</Link></div></main>");
+        assert!(
+            html.contains("<div>Hi {} <a class=\"link\">there</a></div>"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn test_block_component_does_not_gather_whitespace() {
+        let html = component_html("<main>\n  <Nav>a</Nav>\n  {name}\n  <div>b</div>\n</main>");
+        assert!(
+            html.contains("<main><a class=\"link\">a</a>{}<div>b</div></main>"),
+            "{}",
+            html
+        );
+        let html = component_html("<section>\n  {name}\n  <Link>a</Link>\n</section>");
+        assert!(
+            html.contains("<section>{}<a class=\"link\">a</a></section>"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn test_component_marker_leaves_separator() {
+        assert_eq!(
+            mark_component_tags("<Link\n  href=\"/docs\">docs</Link> for"),
+            "<zen-link data-zen-orig-name=\"Link\"\n  href=\"/docs\">docs</zen-link> for"
+        );
+        assert_eq!(
+            mark_component_tags("<Card/><Card title=\"a\">"),
+            "<Card data-zen-orig-name=\"Card\"/><Card data-zen-orig-name=\"Card\" title=\"a\">"
+        );
+        // Not a tag name boundary
+        assert_eq!(mark_component_tags("a <Bc-d"), "a <Bc-d");
+    }
+
+    #[test]
+    fn test_expression_at_paragraph_edges() {
+        // The edges of a block-level parent are not rendered
+        assert!(text_html("<p> {name} </p>").contains("<p>{}</p>"));
+        // Inside an inline parent they are
+        assert!(text_html("<p><span> {name} </span></p>").contains("<span> {} </span>"));
+        // Whitespace is kept as written inside `pre`
+        assert!(text_html("<pre>{title}\n  {name}</pre>").contains("<pre>{}\n  {}</pre>"));
+    }
 }
@@ -0,0 +1,334 @@
+//! Pending State for Async Handlers
+//!
+//! `<button onclick={saveAndRefresh} zen:pending="saving">` keeps the boolean state
+//! `saving` true while the handler's promise is unsettled. Every `on*` handler of
+//! the element is wrapped before renaming:
+//!
+//! ```js
+//! (async function (...__args) {
+//!   saving = true; window.zenithNotify(scope, 'state', 'saving');
+//!   try { return await (saveAndRefresh).apply(this, __args); }
+//!   finally { saving = false; window.zenithNotify(scope, 'state', 'saving'); }
+//! })
+//! ```
+//!
+//! so the assignments are renamed and tracked like any handler mutation, and the
+//! notifications reach dependent expressions after each change rather than when the
+//! handler expression is evaluated. The binding of each wrapped handler records the
+//! target as `pending`.
+//!
+//! Before transform, the target must be a state initialized to `true` or `false`,
+//! and the handler a function expression or the name of a function the script
+//! declares or imports (`Z-ERR-PENDING-TARGET`, `Z-ERR-PENDING-HANDLER`). A handler
+//! that is not `async` settles synchronously, so the state never shows as pending:
+//! `Z-WARN-PENDING-SYNC-HANDLER`.
+
+use std::collections::{HashMap, HashSet};
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{BindingPattern, Expression, ImportDeclarationSpecifier, Statement};
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use crate::transform::PENDING_ATTR;
+use crate::validate::{AttributeValue, ExpressionIR, TemplateNode, ZenIR};
+
+/// Handler expression ids of `zen:pending` elements, with their pending state
+pub(crate) fn pending_targets(nodes: &[TemplateNode]) -> HashMap<String, String> {
+    let mut targets = HashMap::new();
+    collect(nodes, &mut |expr, state| {
+        targets.insert(expr.id.clone(), state.to_string());
+    });
+    targets
+}
+
+fn collect<'n>(nodes: &'n [TemplateNode], f: &mut impl FnMut(&'n ExpressionIR, &'n str)) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                let pending = el.attributes.iter().find_map(|a| match &a.value {
+                    AttributeValue::Static(state) if a.name == PENDING_ATTR => Some(state.trim()),
+                    _ => None,
+                });
+                if let Some(state) = pending {
+                    for attr in &el.attributes {
+                        if let AttributeValue::Dynamic(expr) = &attr.value {
+                            if is_event_attr(&attr.name) {
+                                f(expr, state);
+                            }
+                        }
+                    }
+                }
+                collect(&el.children, f);
+            }
+            TemplateNode::Component(c) => collect(&c.children, f),
+            TemplateNode::ConditionalFragment(cf) => {
+                collect(&cf.consequent, f);
+                collect(&cf.alternate, f);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    collect(nodes, f);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => collect(&of.fragment, f),
+            TemplateNode::LoopFragment(lf) => collect(&lf.body, f),
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn is_event_attr(name: &str) -> bool {
+    name.len() > 2 && name.starts_with("on")
+}
+
+/// Handler source wrapped to set `state` while it runs
+pub(crate) fn wrap_handler(code: &str, state: &str) -> String {
+    format!(
+        "(async function (...__args) {{ {state} = true; window.zenithNotify(scope, 'state', '{state}'); try {{ return await ({code}).apply(this, __args); }} finally {{ {state} = false; window.zenithNotify(scope, 'state', '{state}'); }} }})",
+        state = state,
+        code = code.trim()
+    )
+}
+
+/// What the script declares that a handler may name
+#[derive(Default)]
+struct ScriptFunctions {
+    /// Declared functions and function-valued bindings, with whether they are async
+    functions: HashMap<String, bool>,
+    /// Imported names; whether they are async is unknown
+    imports: HashSet<String>,
+}
+
+fn script_functions(script: &str) -> ScriptFunctions {
+    let mut found = ScriptFunctions::default();
+    let script = crate::component::zen_keywords_to_let(script);
+    let allocator = Allocator::default();
+    let parsed = Parser::new(&allocator, &script, SourceType::tsx().with_module(true)).parse();
+    for stmt in &parsed.program.body {
+        match stmt {
+            Statement::FunctionDeclaration(decl) => {
+                if let Some(id) = &decl.id {
+                    found.functions.insert(id.name.to_string(), decl.r#async);
+                }
+            }
+            Statement::VariableDeclaration(decl) => {
+                for d in &decl.declarations {
+                    let BindingPattern::BindingIdentifier(id) = &d.id else {
+                        continue;
+                    };
+                    if let Some(is_async) = d.init.as_ref().and_then(function_async) {
+                        found.functions.insert(id.name.to_string(), is_async);
+                    }
+                }
+            }
+            Statement::ImportDeclaration(decl) => {
+                for spec in decl.specifiers.iter().flatten() {
+                    let local = match spec {
+                        ImportDeclarationSpecifier::ImportSpecifier(s) => &s.local,
+                        ImportDeclarationSpecifier::ImportDefaultSpecifier(s) => &s.local,
+                        ImportDeclarationSpecifier::ImportNamespaceSpecifier(s) => &s.local,
+                    };
+                    found.imports.insert(local.name.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Whether a function expression is async; `None` when it is not a function
+fn function_async(expr: &Expression) -> Option<bool> {
+    match expr.without_parentheses() {
+        Expression::ArrowFunctionExpression(f) => Some(f.r#async),
+        Expression::FunctionExpression(f) => Some(f.r#async),
+        _ => None,
+    }
+}
+
+/// Check every `zen:pending` target and handler; returns errors, warnings go to
+/// `ir.warnings`
+pub(crate) fn check_pending_handlers(ir: &mut ZenIR) -> Vec<String> {
+    let mut handlers = Vec::new();
+    collect(&ir.template.nodes, &mut |expr, state| {
+        handlers.push((expr.clone(), state.to_string()));
+    });
+    if handlers.is_empty() {
+        return Vec::new();
+    }
+    let script = script_functions(ir.script.as_ref().map_or("", |s| s.raw.as_str()));
+
+    let mut errors = Vec::new();
+    for (expr, state) in handlers {
+        let at = format!(
+            "at {} (line {}, column {})",
+            ir.file_path, expr.location.line, expr.location.column
+        );
+        match ir.all_states.get(&state).map(|init| init.trim()) {
+            Some("true" | "false") => {}
+            Some(_) => errors.push(format!(
+                "Z-ERR-PENDING-TARGET: `zen:pending=\"{}\"` {} targets a state that is not a boolean; initialize `{}` to `false`.",
+                state, at, state
+            )),
+            None => errors.push(format!(
+                "Z-ERR-PENDING-TARGET: `zen:pending=\"{}\"` {} does not name a state; declare `state {} = false` in the script.",
+                state, at, state
+            )),
+        }
+
+        let allocator = Allocator::default();
+        let Ok(handler) = Parser::new(&allocator, &expr.code, SourceType::tsx()).parse_expression()
+        else {
+            continue;
+        };
+        let is_async = match handler.without_parentheses() {
+            Expression::Identifier(ident) => {
+                let name = ident.name.as_str();
+                if script.imports.contains(name) {
+                    continue;
+                }
+                match script.functions.get(name) {
+                    Some(&is_async) => is_async,
+                    None => {
+                        errors.push(format!(
+                            "Z-ERR-PENDING-HANDLER: handler `{{{}}}` {} does not resolve to a function declared in the script; `zen:pending` needs a callable handler.",
+                            expr.code.trim(),
+                            at
+                        ));
+                        continue;
+                    }
+                }
+            }
+            other => match function_async(other) {
+                Some(is_async) => is_async,
+                None => {
+                    errors.push(format!(
+                        "Z-ERR-PENDING-HANDLER: handler `{{{}}}` {} is neither a function nor the name of one; `zen:pending` needs a callable handler.",
+                        expr.code.trim(),
+                        at
+                    ));
+                    continue;
+                }
+            },
+        };
+        if !is_async {
+            ir.warnings.push(format!(
+                "Z-WARN-PENDING-SYNC-HANDLER: handler `{{{}}}` {} is not async, so `{}` is reset before the page can show it as pending.",
+                expr.code.trim(),
+                at,
+                state
+            ));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(page: &str) -> CompileResult {
+        compile_zen_internal(page, "page.zen", CompileOptions::default()).unwrap()
+    }
+
+    fn errors(page: &str) -> String {
+        let result = compile(page);
+        assert!(result.has_errors);
+        result.errors.join("\n")
+    }
+
+    const SCRIPT: &str = "<script>\nstate saving = false\nstate label = 'Save'\nasync function saveAndRefresh() { await fetch('/save') }\nfunction reset() { label = 'Save' }\n</script>\n";
+
+    #[test]
+    fn test_wrapper_sets_and_notifies_pending_state() {
+        let result = compile(&format!(
+            "{}<button onclick={{saveAndRefresh}} zen:pending=\"saving\" disabled={{saving}}>{{label}}</button>",
+            SCRIPT
+        ));
+        assert!(!result.html.contains("zen:pending"), "{}", result.html);
+        let binding = result
+            .bindings
+            .iter()
+            .find(|b| b.target == "onclick")
+            .unwrap();
+        assert_eq!(binding.pending.as_deref(), Some("saving"));
+
+        let bundle = result.manifest.unwrap().bundle;
+        let wrapper = bundle
+            .find("async function(...__args)")
+            .map(|at| &bundle[at..])
+            .unwrap_or_else(|| panic!("{}", bundle));
+        let set = wrapper.find("scope.state.saving = true").unwrap();
+        let call = wrapper.find("await scope.locals.saveAndRefresh.apply(this, __args)");
+        let reset = wrapper.find("scope.state.saving = false").unwrap();
+        assert!(
+            call.is_some_and(|call| set < call && call < reset),
+            "{}",
+            wrapper
+        );
+        assert!(wrapper.contains("finally"), "{}", wrapper);
+        assert_eq!(
+            wrapper
+                .matches("window.zenithNotify(scope, \"state\", \"saving\")")
+                .count(),
+            2,
+            "{}",
+            wrapper
+        );
+    }
+
+    #[test]
+    fn test_pending_target_must_be_boolean_state() {
+        let err = errors(&format!(
+            "{}<button onclick={{saveAndRefresh}} zen:pending=\"busy\">Save</button>",
+            SCRIPT
+        ));
+        assert!(
+            err.contains("Z-ERR-PENDING-TARGET: `zen:pending=\"busy\"` at page.zen")
+                && err.contains("does not name a state"),
+            "{}",
+            err
+        );
+        let err = errors(&format!(
+            "{}<button onclick={{saveAndRefresh}} zen:pending=\"label\">Save</button>",
+            SCRIPT
+        ));
+        assert!(err.contains("is not a boolean"), "{}", err);
+    }
+
+    #[test]
+    fn test_handler_checks() {
+        let result = compile(&format!(
+            "{}<button onclick={{reset}} zen:pending=\"saving\">Reset</button>",
+            SCRIPT
+        ));
+        assert!(
+            result
+                .warnings
+                .iter()
+                .any(|w| w.starts_with("Z-WARN-PENDING-SYNC-HANDLER: handler `{reset}`")),
+            "{:?}",
+            result.warnings
+        );
+
+        let result = compile(&format!(
+            "{}<button onclick={{async () => await saveAndRefresh()}} zen:pending=\"saving\">Save</button>",
+            SCRIPT
+        ));
+        assert!(result
+            .warnings
+            .iter()
+            .all(|w| !w.starts_with("Z-WARN-PENDING")));
+
+        let err = errors(&format!(
+            "{}<button onclick={{label}} zen:pending=\"saving\">Save</button>",
+            SCRIPT
+        ));
+        assert!(
+            err.contains("Z-ERR-PENDING-HANDLER: handler `{label}`"),
+            "{}",
+            err
+        );
+    }
+}
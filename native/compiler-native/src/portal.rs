@@ -0,0 +1,282 @@
+//! Portals
+//!
+//! `<div zen:portal="#modal-root">` renders the subtree into another DOM target,
+//! while it is declared (and its bindings resolve) where it is written. Before
+//! transform, each portal element is wrapped in
+//! `<template data-zen-portal="#modal-root" data-zen-portal-id="portal0">`: the
+//! server-rendered content stays at its declared location but is inert, and the
+//! bindings inside register normally with their loop and conditional contexts.
+//! Codegen emits the wrapper as `{ portal: target, id, children: [...] }` in the
+//! canonical IR, for the runtime to mount at the target after hydration.
+//!
+//! A portal in a loop body gets a per-item id: the wrapper lists the index
+//! variables of the enclosing loops (in `data-zen-portal-index`, never rendered)
+//! and the IR appends them, `"portal0-" + i`. A loop without an index variable
+//! is given one.
+//!
+//! The target is static: a CSS selector or `body`. In document-mode pages it
+//! must look like one (`#id`, `.class` or `body`).
+
+use crate::validate::{
+    AttributeIR, AttributeValue, ElementNode, SourceLocation, TemplateNode, ZenIR,
+};
+
+pub const PORTAL_ATTR: &str = "zen:portal";
+pub const PORTAL_TARGET_ATTR: &str = "data-zen-portal";
+pub const PORTAL_ID_ATTR: &str = "data-zen-portal-id";
+/// Index variables of the loops around the portal; read by codegen only
+pub const PORTAL_INDEX_ATTR: &str = "data-zen-portal-index";
+
+/// Wrap every `zen:portal` element of the page (see module docs).
+/// Returns `Z-ERR-PORTAL` errors for dynamic or malformed targets.
+pub(crate) fn wrap_portals(ir: &mut ZenIR, is_document: bool) -> Vec<String> {
+    let mut portals = Portals {
+        file_path: &ir.file_path,
+        is_document,
+        next_id: 0,
+        loop_indices: Vec::new(),
+        errors: Vec::new(),
+        removed: Vec::new(),
+    };
+    portals.visit(&mut ir.template.nodes);
+    let Portals {
+        errors, removed, ..
+    } = portals;
+    ir.template.expressions.retain(|e| !removed.contains(&e.id));
+    errors
+}
+
+struct Portals<'a> {
+    file_path: &'a str,
+    is_document: bool,
+    next_id: usize,
+    /// Index variables of the enclosing loops, outermost first
+    loop_indices: Vec<String>,
+    errors: Vec<String>,
+    /// Expression ids of dynamic targets, which are dropped
+    removed: Vec<String>,
+}
+
+impl Portals<'_> {
+    /// Returns whether a portal was found
+    fn visit(&mut self, nodes: &mut [TemplateNode]) -> bool {
+        let mut found = false;
+        for node in nodes.iter_mut() {
+            found |= match node {
+                TemplateNode::Element(el) => {
+                    let inner = self.visit(&mut el.children);
+                    match self.take_target(el) {
+                        Some(target) => {
+                            let wrapper = self.wrapper(el, target);
+                            let original = std::mem::replace(node, wrapper);
+                            if let TemplateNode::Element(wrapper) = node {
+                                wrapper.children.push(original);
+                            }
+                            true
+                        }
+                        None => inner,
+                    }
+                }
+                TemplateNode::Component(c) => self.visit(&mut c.children),
+                TemplateNode::ConditionalFragment(cf) => {
+                    let consequent = self.visit(&mut cf.consequent);
+                    self.visit(&mut cf.alternate) | consequent
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    let mut any = false;
+                    for nodes in mb.children_mut() {
+                        any |= self.visit(nodes);
+                    }
+                    any
+                }
+                TemplateNode::OptionalFragment(of) => self.visit(&mut of.fragment),
+                TemplateNode::LoopFragment(lf) => {
+                    let named = lf.index_var.is_some();
+                    let fallback = format!("__zen_portal_i{}", self.loop_indices.len());
+                    let index = lf.index_var.get_or_insert(fallback).clone();
+                    self.loop_indices.push(index);
+                    let inner = self.visit(&mut lf.body);
+                    self.loop_indices.pop();
+                    if !inner && !named {
+                        lf.index_var = None;
+                    }
+                    inner
+                }
+                TemplateNode::Text(_) | TemplateNode::Expression(_) | TemplateNode::Doctype(_) => {
+                    false
+                }
+            };
+        }
+        found
+    }
+
+    /// Remove `zen:portal` from the element; its target when it is usable
+    fn take_target(&mut self, el: &mut ElementNode) -> Option<String> {
+        let at = el.attributes.iter().position(|a| a.name == PORTAL_ATTR)?;
+        let attr = el.attributes.remove(at);
+        let location = format!(
+            "{} (line {}, column {})",
+            self.file_path, el.location.line, el.location.column
+        );
+        match attr.value {
+            AttributeValue::Dynamic(expr) => {
+                self.errors.push(format!(
+                    "Z-ERR-PORTAL: <{}> in {} has a dynamic `zen:portal` target `{}`; portal targets are static. Use a selector such as `zen:portal=\"#modal-root\"` or `zen:portal=\"body\"`.",
+                    el.tag, location, expr.code
+                ));
+                self.removed.push(expr.id);
+                None
+            }
+            AttributeValue::Static(target) => {
+                let target = target.trim().to_string();
+                if target.is_empty()
+                    || (self.is_document
+                        && !(target == "body"
+                            || target.starts_with('#')
+                            || target.starts_with('.')))
+                {
+                    self.errors.push(format!(
+                        "Z-ERR-PORTAL: <{}> in {} has `zen:portal=\"{}\"`, which is not a portal target. Use an id or class selector (`#modal-root`, `.toasts`) or `body`.",
+                        el.tag, location, target
+                    ));
+                    return None;
+                }
+                Some(target)
+            }
+        }
+    }
+
+    fn wrapper(&mut self, el: &ElementNode, target: String) -> TemplateNode {
+        let id = format!("portal{}", self.next_id);
+        self.next_id += 1;
+        let mut attributes = vec![
+            static_attr(PORTAL_TARGET_ATTR, &target, el),
+            static_attr(PORTAL_ID_ATTR, &id, el),
+        ];
+        if !self.loop_indices.is_empty() {
+            attributes.push(static_attr(
+                PORTAL_INDEX_ATTR,
+                &self.loop_indices.join(" "),
+                el,
+            ));
+        }
+        TemplateNode::Element(ElementNode {
+            tag: "template".to_string(),
+            attributes,
+            children: Vec::new(),
+            location: el.location.clone(),
+            loop_context: el.loop_context.clone(),
+            ordinal: 0,
+            extra: Default::default(),
+        })
+    }
+}
+
+fn static_attr(name: &str, value: &str, el: &ElementNode) -> AttributeIR {
+    AttributeIR {
+        name: name.to_string(),
+        value: AttributeValue::Static(value.to_string()),
+        location: SourceLocation::default(),
+        loop_context: el.loop_context.clone(),
+        optional: false,
+        extra: Default::default(),
+    }
+}
+
+/// The wrapper's target, id, and loop index variables, when it wraps a portal
+pub(crate) fn portal_wrapper(el: &ElementNode) -> Option<(String, String, Vec<String>)> {
+    if el.tag != "template" {
+        return None;
+    }
+    let value = |name: &str| {
+        el.attributes.iter().find_map(|a| match &a.value {
+            AttributeValue::Static(v) if a.name == name => Some(v.clone()),
+            _ => None,
+        })
+    };
+    let indices = value(PORTAL_INDEX_ATTR)
+        .map(|v| v.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    Some((value(PORTAL_TARGET_ATTR)?, value(PORTAL_ID_ATTR)?, indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(source: &str) -> Result<CompileResult, String> {
+        let options = CompileOptions {
+            ast_classifier: true,
+            ..Default::default()
+        };
+        compile_zen_internal(source, "page.zen", options)
+    }
+
+    #[test]
+    fn test_ssr_wraps_portal_in_inert_template() {
+        let source = "<script>\nstate open = true\nstate title = \"Hi\"\n</script>\n<main><div class=\"modal\" zen:portal=\"#modal-root\"><h2>{title}</h2></div></main>";
+        let result = compile(source).unwrap();
+        assert!(
+            result.html.contains(
+                "<main><template data-zen-portal=\"#modal-root\" data-zen-portal-id=\"portal0\"><div class=\"modal\"><h2>"
+            ),
+            "{}",
+            result.html
+        );
+        assert!(!result.html.contains("zen:portal"));
+        // The binding inside registers as usual
+        assert!(result.bindings.iter().any(|b| b.expression == "title"));
+    }
+
+    #[test]
+    fn test_ir_marks_portal_subtree() {
+        let source = "<main><aside zen:portal=\"body\"><p>Saved</p></aside></main>";
+        let result = compile(source).unwrap();
+        let bundle = result.manifest.unwrap().bundle;
+        assert!(
+            bundle.contains(
+                "{ portal: \"body\", id: \"portal0\", children: [window.__zenith.h(\"aside\""
+            ),
+            "{}",
+            bundle
+        );
+    }
+
+    #[test]
+    fn test_loop_portals_get_per_item_ids() {
+        let source = "<script>\nstate toasts = [\"a\", \"b\"]\n</script>\n<ul>{toasts.map(toast => <li zen:portal=\"#toasts\">{toast}</li>)}</ul>\n<ol>{toasts.map((toast, i) => <li zen:portal=\".log\">{i}</li>)}</ol>";
+        let result = compile(source).unwrap();
+        let bundle = result.manifest.unwrap().bundle;
+        assert!(
+            bundle.contains("id: \"portal0-\" + __zen_portal_i0"),
+            "{}",
+            bundle
+        );
+        assert!(bundle.contains("(toast, __zen_portal_i0) =>"));
+        assert!(bundle.contains("id: \"portal1-\" + i"));
+        // The index list is for codegen only
+        assert!(!result.html.contains(PORTAL_INDEX_ATTR));
+    }
+
+    #[test]
+    fn test_dynamic_target_errors() {
+        let source = "<script>\nstate target = \"#a\"\n</script>\n<div zen:portal={target}>x</div>";
+        let error = match compile(source) {
+            Ok(result) => result.errors.join("\n"),
+            Err(e) => e,
+        };
+        assert!(error.contains("Z-ERR-PORTAL"), "{}", error);
+        assert!(error.contains("dynamic `zen:portal` target `target`"));
+    }
+
+    #[test]
+    fn test_document_targets_must_be_selectors() {
+        let source = "<html><head><title>x</title></head><body><div zen:portal=\"modal-root\">x</div></body></html>";
+        let error = match compile(source) {
+            Ok(result) => result.errors.join("\n"),
+            Err(e) => e,
+        };
+        assert!(error.contains("`zen:portal=\"modal-root\"`"), "{}", error);
+    }
+}
@@ -0,0 +1,580 @@
+//! Critical Resource Preload Hints
+//!
+//! Collects statically known resources worth preloading: hero images and
+//! `<video poster>` urls near the top of the page, and `@font-face` sources in
+//! the collected styles. Finalize reports them as `preload_hints` and, when the
+//! document has a real `<head>`, injects matching `<link rel="preload">` tags.
+//!
+//! Elements opt out with `data-zen-no-preload`. `data-zen-preload` forces an
+//! element in regardless of position; a non-empty value is used as the hint's
+//! `media` query.
+//!
+//! With `modulepreload`, finalize also adds `<link rel="modulepreload">` for the
+//! runtime package and the bundle's imports, so the browser fetches them in
+//! parallel with the bundle instead of after parsing it. The compiler cannot
+//! resolve `node_modules`, so bare specifiers are looked up in the caller's
+//! `import_url_map`; unmapped ones are listed in a `Z-INFO-MODULEPRELOAD-UNMAPPED`
+//! diagnostic.
+
+use html5ever::parse_document;
+use html5ever::tendril::TendrilSink;
+use markup5ever_rcdom::{NodeData, RcDom};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+use crate::codegen::{ScriptImport, RUNTIME_PACKAGE};
+use crate::transform::escape_html;
+use crate::validate::{AttributeValue, ElementNode, StyleIR, TemplateNode};
+
+/// Opt-out marker for a single element
+pub const NO_PRELOAD_ATTR: &str = "data-zen-no-preload";
+
+/// Force-include marker; its value (if any) becomes the hint's media query
+pub const FORCE_PRELOAD_ATTR: &str = "data-zen-preload";
+
+/// Hints emitted when the caller does not configure a limit
+pub const DEFAULT_PRELOAD_LIMIT: u32 = 4;
+
+/// Elements after this many (in document order) are considered below the fold
+const FOLD_ELEMENT_LIMIT: usize = 50;
+
+lazy_static::lazy_static! {
+    static ref FONT_FACE_RE: Regex = Regex::new(r"(?is)@font-face\s*\{([^}]*)\}").unwrap();
+    static ref CSS_URL_RE: Regex =
+        Regex::new(r#"url\(\s*['"]?([^'")]+?)['"]?\s*\)"#).unwrap();
+    pub(crate) static ref HEAD_OPEN_RE: Regex = Regex::new(r"(?i)<head(?:\s[^>]*)?>").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi(object))]
+pub struct PreloadHint {
+    pub href: String,
+    /// Value of the link's `as` attribute (`image` or `font`)
+    pub as_type: String,
+    pub media: Option<String>,
+}
+
+/// Hints for a page: forced elements first, then above-the-fold images and
+/// posters in document order, then font-face sources. Deduplicated by href and
+/// capped at `limit`.
+pub fn collect_preload_hints(
+    nodes: &[TemplateNode],
+    styles: &[StyleIR],
+    limit: usize,
+) -> Vec<PreloadHint> {
+    let mut forced = Vec::new();
+    let mut fold = Vec::new();
+    let mut seen_elements = 0;
+    walk(nodes, true, &mut seen_elements, &mut forced, &mut fold);
+
+    let fonts = styles
+        .iter()
+        .flat_map(|s| font_face_urls(&s.raw))
+        .map(|href| PreloadHint {
+            href,
+            as_type: "font".to_string(),
+            media: None,
+        });
+
+    let mut seen = HashSet::new();
+    forced
+        .into_iter()
+        .chain(fold)
+        .chain(fonts)
+        .filter(|hint| is_preloadable(&hint.href) && seen.insert(hint.href.clone()))
+        .take(limit)
+        .collect()
+}
+
+/// `certain` is false inside conditionals and loops, whose content may not render
+fn walk(
+    nodes: &[TemplateNode],
+    certain: bool,
+    seen_elements: &mut usize,
+    forced: &mut Vec<PreloadHint>,
+    fold: &mut Vec<PreloadHint>,
+) {
+    for node in nodes {
+        match node {
+            TemplateNode::Element(el) => {
+                *seen_elements += 1;
+                if let Some(hint) = element_hint(el) {
+                    if has_attr(el, FORCE_PRELOAD_ATTR) {
+                        forced.push(hint);
+                    } else if certain
+                        && *seen_elements <= FOLD_ELEMENT_LIMIT
+                        && !has_attr(el, NO_PRELOAD_ATTR)
+                    {
+                        fold.push(hint);
+                    }
+                }
+                walk(&el.children, certain, seen_elements, forced, fold);
+            }
+            TemplateNode::Component(c) => walk(&c.children, certain, seen_elements, forced, fold),
+            TemplateNode::ConditionalFragment(cf) => {
+                walk(&cf.consequent, false, seen_elements, forced, fold);
+                walk(&cf.alternate, false, seen_elements, forced, fold);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    walk(nodes, false, seen_elements, forced, fold);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => {
+                walk(&of.fragment, false, seen_elements, forced, fold)
+            }
+            TemplateNode::LoopFragment(lf) => walk(&lf.body, false, seen_elements, forced, fold),
+            _ => {}
+        }
+    }
+}
+
+/// Static image url of an `<img src>` or `<video poster>`
+fn element_hint(el: &ElementNode) -> Option<PreloadHint> {
+    let url_attr = match el.tag.to_ascii_lowercase().as_str() {
+        "img" => "src",
+        "video" => "poster",
+        _ => return None,
+    };
+    let href = static_attr(el, url_attr)?;
+    let media = static_attr(el, FORCE_PRELOAD_ATTR).filter(|m| !m.trim().is_empty());
+    Some(PreloadHint {
+        href,
+        as_type: "image".to_string(),
+        media,
+    })
+}
+
+fn has_attr(el: &ElementNode, name: &str) -> bool {
+    el.attributes.iter().any(|a| a.name == name)
+}
+
+/// Only static values are known at compile time; dynamic expressions never produce hints
+fn static_attr(el: &ElementNode, name: &str) -> Option<String> {
+    el.attributes
+        .iter()
+        .find(|a| a.name == name)
+        .and_then(|a| match &a.value {
+            AttributeValue::Static(v) => Some(v.trim().to_string()),
+            AttributeValue::Dynamic(_) => None,
+        })
+}
+
+fn is_preloadable(href: &str) -> bool {
+    !href.is_empty() && !href.starts_with("data:") && !href.contains("__ZENITH_EXPR_")
+}
+
+/// First `url()` of each `@font-face` block (the preferred format comes first in `src`)
+pub fn font_face_urls(css: &str) -> Vec<String> {
+    FONT_FACE_RE
+        .captures_iter(css)
+        .filter_map(|block| {
+            let body = block.get(1)?.as_str();
+            let src = body.find("src")?;
+            CSS_URL_RE
+                .captures(&body[src..])
+                .map(|c| c[1].trim().to_string())
+        })
+        .collect()
+}
+
+/// Insert `<link rel="preload">` tags right after the opening `<head>` tag.
+/// Documents without a `<head>` are returned unchanged, as are hrefs the author
+/// already preloads.
+pub fn inject_preload_links(html: &str, hints: &[PreloadHint]) -> String {
+    let Some(head) = HEAD_OPEN_RE.find(html) else {
+        return html.to_string();
+    };
+
+    let existing = link_hrefs(html, "preload");
+    let links: String = hints
+        .iter()
+        .filter(|hint| !existing.contains(&hint.href))
+        .map(|hint| {
+            let media = hint
+                .media
+                .as_ref()
+                .map(|m| format!(" media=\"{}\"", escape_html(m)))
+                .unwrap_or_default();
+            // Fonts are always fetched in CORS mode; without `crossorigin` the preload is wasted
+            let cors = if hint.as_type == "font" {
+                " crossorigin"
+            } else {
+                ""
+            };
+            format!(
+                "<link rel=\"preload\" href=\"{}\" as=\"{}\"{}{}>",
+                escape_html(&hint.href),
+                hint.as_type,
+                media,
+                cors
+            )
+        })
+        .collect();
+
+    let mut out = String::with_capacity(html.len() + links.len());
+    out.push_str(&html[..head.end()]);
+    out.push_str(&links);
+    out.push_str(&html[head.end()..]);
+    out
+}
+
+/// Insert `<link rel="modulepreload">` tags for the runtime package and each
+/// runtime import (type-only imports are erased), in import order, right after
+/// the opening `<head>` tag. Returns the HTML and the specifiers with no URL.
+/// URLs the author already preloads are skipped.
+pub fn inject_modulepreload_links(
+    html: &str,
+    imports: &[ScriptImport],
+    url_map: &HashMap<String, String>,
+) -> (String, Vec<String>) {
+    let Some(head) = HEAD_OPEN_RE.find(html) else {
+        return (html.to_string(), Vec::new());
+    };
+
+    let mut seen = HashSet::new();
+    let mut existing = link_hrefs(html, "modulepreload");
+    let mut unmapped = Vec::new();
+    let mut links = String::new();
+    let sources = std::iter::once(RUNTIME_PACKAGE).chain(
+        imports
+            .iter()
+            .filter(|i| !i.type_only)
+            .map(|i| i.source.as_str()),
+    );
+    for source in sources {
+        if !seen.insert(source) {
+            continue;
+        }
+        let url = match url_map.get(source) {
+            Some(url) => url.as_str(),
+            // Already a URL the browser can fetch
+            None if source.starts_with('/') || source.contains("://") => source,
+            None => {
+                unmapped.push(source.to_string());
+                continue;
+            }
+        };
+        if existing.insert(url.to_string()) {
+            links.push_str(&format!(
+                "<link rel=\"modulepreload\" href=\"{}\">",
+                escape_html(url)
+            ));
+        }
+    }
+
+    let mut out = String::with_capacity(html.len() + links.len());
+    out.push_str(&html[..head.end()]);
+    out.push_str(&links);
+    out.push_str(&html[head.end()..]);
+    (out, unmapped)
+}
+
+/// `href`s of the document's `<link>` tags whose `rel` includes `rel`, in any
+/// attribute order or quoting, with character references decoded
+fn link_hrefs(html: &str, rel: &str) -> HashSet<String> {
+    let dom = parse_document(RcDom::default(), Default::default()).one(html);
+    let mut hrefs = HashSet::new();
+    // Dropping the last handle to a node empties its subtree; `dom` keeps the root
+    let mut stack = vec![dom.document.clone()];
+    while let Some(node) = stack.pop() {
+        if let NodeData::Element { name, attrs, .. } = &node.data {
+            let attrs = attrs.borrow();
+            let attr = |key: &str| {
+                attrs
+                    .iter()
+                    .find(|a| &*a.name.local == key)
+                    .map(|a| a.value.to_string())
+            };
+            let is_rel = attr("rel").is_some_and(|value| {
+                value
+                    .split_ascii_whitespace()
+                    .any(|token| token.eq_ignore_ascii_case(rel))
+            });
+            if &*name.local == "link" && is_rel {
+                hrefs.extend(attr("href"));
+            }
+        }
+        stack.extend(node.children.borrow().iter().cloned());
+    }
+    hrefs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions};
+
+    const HERO_PAGE: &str = r#"<script>
+state avatarUrl = "/me.png"
+</script>
+<html>
+<head><title>Home</title></head>
+<body>
+  <img src="/hero.jpg" alt="Hero" />
+  <img src="/tracking.gif" data-zen-no-preload alt="" />
+  <img src="data:image/png;base64,AAAA" alt="" />
+  <img src={avatarUrl} alt="Avatar" />
+  <video poster="/intro.jpg"></video>
+</body>
+</html>"#;
+
+    fn compile(source: &str, preload_limit: Option<u32>) -> crate::parse::CompileResult {
+        compile_zen_internal(
+            source,
+            "page.zen",
+            CompileOptions {
+                preload_limit,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn hrefs(hints: &[PreloadHint]) -> Vec<&str> {
+        hints.iter().map(|h| h.href.as_str()).collect()
+    }
+
+    #[test]
+    fn test_hero_image_hint_and_link() {
+        let result = compile(HERO_PAGE, None);
+        assert_eq!(
+            hrefs(&result.preload_hints),
+            vec!["/hero.jpg", "/intro.jpg"]
+        );
+        assert_eq!(result.preload_hints[0].as_type, "image");
+        assert!(result
+            .html
+            .contains(r#"<link rel="preload" href="/hero.jpg" as="image">"#));
+        assert!(!result.html.contains("href=\"/tracking.gif\""));
+    }
+
+    #[test]
+    fn test_font_face_url_from_component_css() {
+        let css = "@font-face { font-family: Inter; src: url('/fonts/inter.woff2') format('woff2'), url(/fonts/inter.woff) format('woff'); }";
+        assert_eq!(font_face_urls(css), vec!["/fonts/inter.woff2"]);
+
+        let mut components = std::collections::HashMap::new();
+        components.insert(
+            "Brand".to_string(),
+            serde_json::json!({
+                "name": "Brand",
+                "styles": [css],
+                "nodes": [{ "type": "element", "tag": "span", "attributes": [], "children": [] }]
+            }),
+        );
+        let result = compile_zen_internal(
+            "<html><head></head><body><Brand /></body></html>",
+            "page.zen",
+            CompileOptions {
+                components,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(hrefs(&result.preload_hints), vec!["/fonts/inter.woff2"]);
+        assert!(result
+            .html
+            .contains(r#"<link rel="preload" href="/fonts/inter.woff2" as="font" crossorigin>"#));
+    }
+
+    #[test]
+    fn test_forced_hint_outside_fold_with_media() {
+        let mut source = String::from("<div>");
+        for _ in 0..60 {
+            source.push_str("<span>x</span>");
+        }
+        source.push_str(r#"<img src="/late.jpg" alt="" /><img src="/wide.jpg" data-zen-preload="(min-width: 800px)" alt="" /></div>"#);
+
+        let result = compile(&source, None);
+        assert_eq!(hrefs(&result.preload_hints), vec!["/wide.jpg"]);
+        assert_eq!(
+            result.preload_hints[0].media.as_deref(),
+            Some("(min-width: 800px)")
+        );
+    }
+
+    #[test]
+    fn test_limit_caps_hints() {
+        let result = compile(HERO_PAGE, Some(1));
+        assert_eq!(hrefs(&result.preload_hints), vec!["/hero.jpg"]);
+        assert_eq!(result.html.matches("rel=\"preload\"").count(), 1);
+
+        let disabled = compile(HERO_PAGE, Some(0));
+        assert!(disabled.preload_hints.is_empty());
+        assert!(!disabled.html.contains("rel=\"preload\""));
+    }
+
+    #[test]
+    fn test_no_head_no_injection() {
+        let html = "<div><img src=\"/a.jpg\"></div>";
+        let hints = vec![PreloadHint {
+            href: "/a.jpg".to_string(),
+            as_type: "image".to_string(),
+            media: None,
+        }];
+        assert_eq!(inject_preload_links(html, &hints), html);
+    }
+
+    #[test]
+    fn test_preload_link_values_are_escaped() {
+        let hints = vec![PreloadHint {
+            href: "/a.jpg?w=1&h=\"2\"".to_string(),
+            as_type: "image".to_string(),
+            media: Some("(min-width: 800px)\"><script>".to_string()),
+        }];
+        let out = inject_preload_links("<head></head>", &hints);
+        assert_eq!(
+            out,
+            "<head><link rel=\"preload\" href=\"/a.jpg?w=1&amp;h=&quot;2&quot;\" as=\"image\" media=\"(min-width: 800px)&quot;&gt;&lt;script&gt;\"></head>"
+        );
+        // The author's link is compared by its decoded href
+        assert_eq!(inject_preload_links(&out, &hints), out);
+    }
+
+    const MODULE_PAGE: &str = r#"<script>
+import confetti from 'canvas-confetti'
+import { format } from 'date-fns'
+import type { Options } from 'canvas-confetti'
+import 'some-polyfill'
+state count = 0
+function celebrate() { confetti(); count++ }
+</script>
+<html>
+<head><title>Party</title><link rel="modulepreload" href="https://cdn.example/date-fns.js"></head>
+<body><button onclick={celebrate}>{format(new Date(), 'yyyy')} {count}</button></body>
+</html>"#;
+
+    fn compile_modules(import_url_map: &[(&str, &str)]) -> crate::parse::CompileResult {
+        compile_zen_internal(
+            MODULE_PAGE,
+            "page.zen",
+            CompileOptions {
+                modulepreload: true,
+                import_url_map: import_url_map
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn modulepreloads(html: &str) -> Vec<&str> {
+        html.match_indices("<link rel=\"modulepreload\" href=\"")
+            .map(|(at, m)| {
+                let rest = &html[at + m.len()..];
+                &rest[..rest.find('"').unwrap()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mapped_imports_are_preloaded_in_import_order() {
+        let result = compile_modules(&[
+            ("@zenithbuild/runtime", "/_zen/runtime.js"),
+            ("canvas-confetti", "https://cdn.example/confetti.js"),
+            ("some-polyfill", "/vendor/polyfill.js"),
+            ("date-fns", "https://cdn.example/date-fns.js"),
+        ]);
+        // The runtime first, then imports as written; the author's own link is kept once
+        assert_eq!(
+            modulepreloads(&result.html),
+            vec![
+                "/_zen/runtime.js",
+                "https://cdn.example/confetti.js",
+                "/vendor/polyfill.js",
+                "https://cdn.example/date-fns.js",
+            ]
+        );
+        assert!(result
+            .warnings
+            .iter()
+            .all(|w| !w.starts_with("Z-INFO-MODULEPRELOAD-UNMAPPED")));
+    }
+
+    #[test]
+    fn test_unmapped_imports_are_listed() {
+        let result = compile_modules(&[("canvas-confetti", "/vendor/confetti.js")]);
+        assert_eq!(
+            modulepreloads(&result.html),
+            vec!["/vendor/confetti.js", "https://cdn.example/date-fns.js"]
+        );
+        let info = result
+            .warnings
+            .iter()
+            .find(|w| w.starts_with("Z-INFO-MODULEPRELOAD-UNMAPPED"))
+            .unwrap();
+        assert!(
+            info.contains("`@zenithbuild/runtime`, `date-fns`, `some-polyfill`"),
+            "{}",
+            info
+        );
+        // Off by default
+        let result = compile(MODULE_PAGE, None);
+        assert_eq!(modulepreloads(&result.html).len(), 1);
+    }
+
+    #[test]
+    fn test_modulepreload_links_deduplicate() {
+        let imports = vec![
+            ScriptImport {
+                source: "lit".to_string(),
+                specifiers: "{ html }".to_string(),
+                type_only: false,
+                side_effect: false,
+            },
+            ScriptImport {
+                source: "lit".to_string(),
+                specifiers: "{ css }".to_string(),
+                type_only: false,
+                side_effect: false,
+            },
+            ScriptImport {
+                source: "lit-html".to_string(),
+                specifiers: "{ render }".to_string(),
+                type_only: false,
+                side_effect: false,
+            },
+        ];
+        let map: HashMap<String, String> = [
+            ("lit", "/lit.js"),
+            ("lit-html", "/lit.js"),
+            ("@zenithbuild/runtime", "/runtime.js"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let html = "<html><head><link rel=\"modulepreload\" href=\"/runtime.js\"></head></html>";
+        let (out, unmapped) = inject_modulepreload_links(html, &imports, &map);
+        assert_eq!(modulepreloads(&out), vec!["/lit.js", "/runtime.js"]);
+        assert!(unmapped.is_empty());
+        // The author's link is found whatever its attribute order and quoting
+        let html =
+            "<html><head><link href='/runtime.js' crossorigin rel='MODULEPRELOAD'></head></html>";
+        let (out, _) = inject_modulepreload_links(html, &imports, &map);
+        assert_eq!(modulepreloads(&out), vec!["/lit.js"]);
+        // Mapped URLs are attribute-escaped
+        let map: HashMap<String, String> = [("lit", "/lit.js?v=\"1\"&x=<y>")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let (out, _) = inject_modulepreload_links("<head></head>", &imports[..1], &map);
+        assert_eq!(
+            modulepreloads(&out),
+            vec!["/lit.js?v=&quot;1&quot;&amp;x=&lt;y&gt;"]
+        );
+        // No head, nothing to do
+        let (out, unmapped) = inject_modulepreload_links("<p>Hi</p>", &imports, &map);
+        assert_eq!(out, "<p>Hi</p>");
+        assert!(unmapped.is_empty());
+    }
+}
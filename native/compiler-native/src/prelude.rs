@@ -0,0 +1,75 @@
+//! Supported Public API
+//!
+//! `use compiler_native::prelude::*;` brings in everything downstream Rust
+//! consumers (the SSG, the Rolldown plugin) are meant to depend on. Items are
+//! added here deliberately; anything not listed is internal and may change
+//! between releases. The list is checked by `tests/public_api.rs`.
+
+// Entry points
+pub use crate::parse::{compile_zen_batch_internal, CompileOptions, CompileResult};
+pub use crate::parse::{compile_zen_internal, parse_script, parse_template};
+
+// Element tables
+pub use crate::html_tables::{
+    default_svg_attr_case_map, default_svg_tags, default_void_elements, HtmlExtensions,
+};
+
+// Cancellation
+pub use crate::cancel::{is_cancelled_error, CancellationToken, COMPILE_CANCELLED};
+
+// Nesting limit (`CompileOptions.max_depth`)
+pub use crate::depth::{DEFAULT_MAX_DEPTH, MAX_DEPTH_ERROR};
+
+// Caching
+pub use crate::script_cache::ScriptCache;
+
+// Observers
+pub use crate::observers::{HeadingOutline, IrObserver, NodeContext, ObserverOutput, Observers};
+
+// Codegen plan
+pub use crate::codegen::{
+    CodegenPlan, ExpressionPlan, PlanDecision, PlanFilter, ScriptImport, SizeEstimate,
+    StateDeclaration,
+};
+
+// Diagnostics
+pub use crate::validate::{CompilerError, SourceLocation};
+
+// IR
+pub use crate::component::ComponentIR;
+pub use crate::validate::{
+    AttributeIR, AttributeValue, ComponentNode, ConditionalBranch, ConditionalFragmentNode,
+    DoctypeNode, ElementNode, ExpressionIR, ExpressionNode, ExpressionOrigin, LayoutLevel,
+    LoopContext, LoopFragmentNode, MultiBranchFragmentNode, OptionalFragmentNode, ScopeBindings,
+    ScriptIR, StyleIR, TemplateIR, TemplateNode, TextNode, UnknownFields, ZenIR,
+};
+
+// Output
+pub use crate::client_directives::InstanceHydration;
+pub use crate::css_scope::ScopeStrategy;
+pub use crate::dep_graph::{DepGraph, DepKind, DepNode};
+pub use crate::env::StrippedEnvRegion;
+pub use crate::es_target::EsTarget;
+pub use crate::expr_complexity::{ComplexityLimits, ComplexityThresholds, ExpressionMetrics};
+pub use crate::finalize::{Budgets, PayloadMetrics, ZenManifestExport};
+pub use crate::i18n::I18nOptions;
+pub use crate::islands::{IslandIR, IslandManifest};
+pub use crate::lazy::BundleChunk;
+pub use crate::loop_shapes::LoopItemKeys;
+pub use crate::navigation::Navigation;
+pub use crate::preload::PreloadHint;
+pub use crate::strip_debug::StripDebug;
+pub use crate::style_chunks::StyleChunk;
+pub use crate::svg_sprite::SvgSprite;
+pub use crate::transform::Binding;
+pub use crate::validate::ComponentUsage;
+
+// Boundary schemas
+#[cfg(feature = "schema")]
+pub use crate::schema::json_schemas;
+
+// Embedding
+pub use crate::embed::to_srcdoc;
+
+// Upgrade review
+pub use crate::compat::{compare_outputs, CompatBucket, CompatFinding, CompatReport};
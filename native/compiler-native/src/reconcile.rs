@@ -0,0 +1,285 @@
+//! Expression Registry Reconciliation
+//!
+//! Runs after component resolution and before transform. Every expression id a
+//! node refers to must exist in `template.expressions`, and every registered
+//! expression should be referenced by some node:
+//!
+//! - A missing id is reported as INV009 at the referencing node. Attributes carry
+//!   their own code, so the entry is rebuilt from it; bare expression nodes and
+//!   fragment conditions only carry the id and get an `undefined` placeholder.
+//!   Either way transform and codegen see a consistent registry.
+//! - Registered but unreferenced expressions are pruned (with one info
+//!   diagnostic) so codegen does not emit functions nothing calls. Props passed
+//!   to component instances were already dropped by resolution, which copies
+//!   their code into the instance script.
+
+use std::collections::HashSet;
+
+use crate::validate::{
+    AttributeIR, AttributeValue, CompilerError, ExpressionIR, LoopContext, SourceLocation,
+    TemplateNode, ZenIR, INV_EXPRESSION_MISSING,
+};
+
+/// A node's reference to an expression id
+struct ExpressionRef {
+    id: String,
+    /// What refers to it, for the diagnostic ("text expression", "attribute `href`", ...)
+    site: String,
+    /// Code stored on the node itself (attributes only)
+    code: Option<String>,
+    location: SourceLocation,
+    loop_context: Option<LoopContext>,
+}
+
+/// Reconcile node references with the expression registry.
+/// Returns INV009 errors for references that had to be repaired.
+pub fn reconcile_expressions(ir: &mut ZenIR) -> Vec<CompilerError> {
+    let mut refs = Vec::new();
+    collect_references(&ir.template.nodes, &mut refs);
+
+    let mut registered: HashSet<String> = ir
+        .template
+        .expressions
+        .iter()
+        .map(|e| e.id.clone())
+        .collect();
+
+    let mut errors = Vec::new();
+    for r in &refs {
+        if !registered.insert(r.id.clone()) {
+            continue;
+        }
+        let repair = if r.code.is_some() {
+            "it was rebuilt from the code stored on the node"
+        } else {
+            "a placeholder rendering `undefined` was emitted in its place"
+        };
+        errors.push(CompilerError::with_details(
+            INV_EXPRESSION_MISSING,
+            &format!(
+                "Expression `{}` referenced by {} is not registered; {}.",
+                r.id, r.site, repair
+            ),
+            &ir.file_path,
+            r.location.line,
+            r.location.column,
+            r.code.clone(),
+            vec![
+                "This usually means component resolution dropped the expression (for example slot content replaced by fallback content).".to_string(),
+            ],
+        ));
+        ir.template.expressions.push(ExpressionIR {
+            id: r.id.clone(),
+            code: r.code.clone().unwrap_or_else(|| "undefined".to_string()),
+            location: r.location.clone(),
+            loop_context: r.loop_context.clone(),
+            origin: None,
+            extra: Default::default(),
+        });
+    }
+
+    let referenced: HashSet<&str> = refs.iter().map(|r| r.id.as_str()).collect();
+    let mut pruned = Vec::new();
+    ir.template.expressions.retain(|e| {
+        let keep = referenced.contains(e.id.as_str());
+        if !keep {
+            pruned.push(e.id.clone());
+        }
+        keep
+    });
+    if !pruned.is_empty() {
+        ir.warnings.push(format!(
+            "Z-INFO-ORPHAN-EXPRESSIONS: {} expression(s) in {} are not referenced by any node and were excluded from codegen: {}",
+            pruned.len(),
+            ir.file_path,
+            pruned.join(", ")
+        ));
+    }
+
+    errors
+}
+
+/// Expression ids referenced by `nodes`, in document order (may repeat)
+pub(crate) fn referenced_ids(nodes: &[TemplateNode]) -> Vec<String> {
+    let mut refs = Vec::new();
+    collect_references(nodes, &mut refs);
+    refs.into_iter().map(|r| r.id).collect()
+}
+
+fn collect_references(nodes: &[TemplateNode], refs: &mut Vec<ExpressionRef>) {
+    let id_ref =
+        |id: &str, site: &str, location: &SourceLocation, lc: &Option<LoopContext>| ExpressionRef {
+            id: id.to_string(),
+            site: site.to_string(),
+            code: None,
+            location: location.clone(),
+            loop_context: lc.clone(),
+        };
+
+    for node in nodes {
+        match node {
+            TemplateNode::Expression(e) => refs.push(id_ref(
+                &e.expression,
+                "a text expression",
+                &e.location,
+                &e.loop_context,
+            )),
+            TemplateNode::Element(el) => {
+                collect_attribute_references(&el.attributes, refs);
+                collect_references(&el.children, refs);
+            }
+            TemplateNode::Component(c) => {
+                collect_attribute_references(&c.attributes, refs);
+                collect_references(&c.children, refs);
+            }
+            TemplateNode::ConditionalFragment(cf) => {
+                refs.push(id_ref(
+                    &cf.condition,
+                    "a conditional",
+                    &cf.location,
+                    &cf.loop_context,
+                ));
+                collect_references(&cf.consequent, refs);
+                collect_references(&cf.alternate, refs);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for branch in &mb.branches {
+                    refs.push(id_ref(
+                        &branch.condition,
+                        "a conditional branch",
+                        &mb.location,
+                        &mb.loop_context,
+                    ));
+                }
+                for nodes in mb.children() {
+                    collect_references(nodes, refs);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => {
+                refs.push(id_ref(
+                    &of.condition,
+                    "an optional fragment",
+                    &of.location,
+                    &of.loop_context,
+                ));
+                collect_references(&of.fragment, refs);
+            }
+            TemplateNode::LoopFragment(lf) => {
+                refs.push(id_ref(
+                    &lf.source,
+                    "a loop source",
+                    &lf.location,
+                    &lf.loop_context,
+                ));
+                collect_references(&lf.body, refs);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_attribute_references(attributes: &[AttributeIR], refs: &mut Vec<ExpressionRef>) {
+    for attr in attributes {
+        if let AttributeValue::Dynamic(expr) = &attr.value {
+            refs.push(ExpressionRef {
+                id: expr.id.clone(),
+                site: format!("attribute `{}`", attr.name),
+                code: Some(expr.code.clone()),
+                location: attr.location.clone(),
+                loop_context: expr.loop_context.clone().or(attr.loop_context.clone()),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(nodes: serde_json::Value, expressions: serde_json::Value) -> ZenIR {
+        serde_json::from_value(serde_json::json!({
+            "filePath": "page.zen",
+            "template": { "raw": "", "nodes": nodes, "expressions": expressions },
+            "script": null,
+            "styles": []
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_missing_text_expression_gets_placeholder() {
+        let mut ir = page(
+            serde_json::json!([{
+                "type": "element",
+                "tag": "p",
+                "attributes": [],
+                "children": [{
+                    "type": "expression",
+                    "expression": "expr_7",
+                    "location": { "line": 3, "column": 5 }
+                }]
+            }]),
+            serde_json::json!([]),
+        );
+
+        let errors = reconcile_expressions(&mut ir);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, "INV009");
+        assert_eq!((errors[0].line, errors[0].column), (3, 5));
+        assert!(errors[0].message.contains("`expr_7`"));
+
+        assert_eq!(ir.template.expressions.len(), 1);
+        assert_eq!(ir.template.expressions[0].code, "undefined");
+
+        // Transform no longer has a missing id to trip over
+        let output = crate::transform::transform_template_with_scope(
+            &ir.template.nodes,
+            &ir.template.expressions,
+            None,
+        );
+        assert!(output.html.contains("<!--zen:expr_7-->"));
+    }
+
+    #[test]
+    fn test_missing_attribute_expression_is_rebuilt() {
+        let mut ir = page(
+            serde_json::json!([{
+                "type": "element",
+                "tag": "a",
+                "attributes": [{ "name": "href", "value": { "id": "expr_2", "code": "link" } }],
+                "children": []
+            }]),
+            serde_json::json!([]),
+        );
+
+        let errors = reconcile_expressions(&mut ir);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].context.as_deref(), Some("link"));
+        assert_eq!(ir.template.expressions[0].id, "expr_2");
+        assert_eq!(ir.template.expressions[0].code, "link");
+    }
+
+    #[test]
+    fn test_orphaned_expressions_are_pruned() {
+        let mut ir = page(
+            serde_json::json!([{ "type": "expression", "expression": "expr_0" }]),
+            serde_json::json!([
+                { "id": "expr_0", "code": "count" },
+                { "id": "expr_1", "code": "stale" }
+            ]),
+        );
+
+        let errors = reconcile_expressions(&mut ir);
+        assert!(errors.is_empty());
+        let ids: Vec<&str> = ir
+            .template
+            .expressions
+            .iter()
+            .map(|e| e.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["expr_0"]);
+        assert_eq!(ir.warnings.len(), 1);
+        assert!(ir.warnings[0].starts_with("Z-INFO-ORPHAN-EXPRESSIONS"));
+        assert!(ir.warnings[0].contains("expr_1"));
+    }
+}
@@ -0,0 +1,282 @@
+//! Component Registry Intake
+//!
+//! The registry comes from the JS side as one JSON value per component, and a
+//! half-written cache file or a toolchain version skew can hand over entries whose
+//! parts do not fit together. Every entry is checked before resolution uses it:
+//!
+//! - it deserializes as a `ComponentIR`
+//! - every expression id its nodes reference (expression nodes, dynamic attributes,
+//!   fragment conditions and loop sources given as ids) is in its `expressions`
+//! - every dynamic attribute has an id and code
+//! - its slots are declared once each, and named slots have a name
+//!
+//! An entry that fails is left out of the registry with `Z-WARN-REGISTRY-INVALID`
+//! naming the component, its path and the first violation, so its usages stay
+//! unresolved like any unknown tag instead of failing deep in resolution or
+//! producing a broken instance. With `strict_registry` it is `Z-ERR-REGISTRY-INVALID`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::component::ComponentIR;
+use crate::validate::{AttributeValue, TemplateNode};
+
+/// Entries that pass the checks, and a diagnostic for each one that does not
+pub(crate) fn take_in(
+    components: HashMap<String, serde_json::Value>,
+    strict: bool,
+) -> (HashMap<String, ComponentIR>, Vec<String>) {
+    let mut accepted = HashMap::new();
+    let mut diagnostics = Vec::new();
+    let mut entries: Vec<(String, serde_json::Value)> = components.into_iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    for (key, value) in entries {
+        let path = value
+            .get("path")
+            .and_then(|p| p.as_str())
+            .filter(|p| !p.is_empty())
+            .unwrap_or("an unknown path")
+            .to_string();
+        let violation = match serde_json::from_value::<ComponentIR>(value) {
+            Ok(comp) => match check_component(&comp) {
+                None => {
+                    accepted.insert(key, comp);
+                    continue;
+                }
+                Some(violation) => violation,
+            },
+            Err(e) => format!("the entry is not a component IR ({})", e),
+        };
+        diagnostics.push(if strict {
+            format!(
+                "Z-ERR-REGISTRY-INVALID: registry entry `{}` ({}) is invalid: {}.",
+                key, path, violation
+            )
+        } else {
+            format!(
+                "Z-WARN-REGISTRY-INVALID: registry entry `{}` ({}) is invalid: {}. It is left out of the registry, so <{}> stays unresolved; rebuild the component registry.",
+                key, path, violation, key
+            )
+        });
+    }
+    (accepted, diagnostics)
+}
+
+/// The first referential integrity violation of a component entry
+pub(crate) fn check_component(comp: &ComponentIR) -> Option<String> {
+    let ids: HashSet<&str> = comp.expressions.iter().map(|e| e.id.as_str()).collect();
+    if let Some(violation) = check_nodes(&comp.nodes, &ids) {
+        return Some(violation);
+    }
+    let mut declared = HashSet::new();
+    for slot in &comp.slots {
+        match slot.name.as_deref() {
+            Some("") => {
+                return Some(format!(
+                    "a named slot at line {} has an empty name",
+                    slot.location.line
+                ))
+            }
+            name if !declared.insert(name) => {
+                return Some(match name {
+                    Some(name) => format!("slot `{}` is declared twice", name),
+                    None => "the default slot is declared twice".to_string(),
+                })
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn check_nodes(nodes: &[TemplateNode], ids: &HashSet<&str>) -> Option<String> {
+    let missing = |id: &str, what: &str| {
+        format!(
+            "{} references expression `{}`, which is not in its expressions",
+            what, id
+        )
+    };
+    for node in nodes {
+        let violation = match node {
+            TemplateNode::Expression(e) => (!ids.contains(e.expression.as_str()))
+                .then(|| missing(&e.expression, "an expression node")),
+            TemplateNode::Element(el) => check_attributes(&el.attributes, &el.tag, ids)
+                .or_else(|| check_nodes(&el.children, ids)),
+            TemplateNode::Component(c) => check_attributes(&c.attributes, &c.name, ids)
+                .or_else(|| check_nodes(&c.children, ids)),
+            TemplateNode::ConditionalFragment(cf) => {
+                referenced_id(&cf.condition, ids, "a conditional fragment")
+                    .or_else(|| check_nodes(&cf.consequent, ids))
+                    .or_else(|| check_nodes(&cf.alternate, ids))
+            }
+            TemplateNode::MultiBranchFragment(mb) => mb
+                .branches
+                .iter()
+                .find_map(|b| referenced_id(&b.condition, ids, "a branch"))
+                .or_else(|| mb.children().find_map(|nodes| check_nodes(nodes, ids))),
+            TemplateNode::OptionalFragment(of) => {
+                referenced_id(&of.condition, ids, "an optional fragment")
+                    .or_else(|| check_nodes(&of.fragment, ids))
+            }
+            TemplateNode::LoopFragment(lf) => {
+                referenced_id(&lf.source, ids, "a loop").or_else(|| check_nodes(&lf.body, ids))
+            }
+            TemplateNode::Text(_) | TemplateNode::Doctype(_) => None,
+        };
+        if violation.is_some() {
+            return violation;
+        }
+    }
+    None
+}
+
+fn check_attributes(
+    attributes: &[crate::validate::AttributeIR],
+    tag: &str,
+    ids: &HashSet<&str>,
+) -> Option<String> {
+    attributes.iter().find_map(|attr| {
+        let AttributeValue::Dynamic(expr) = &attr.value else {
+            return None;
+        };
+        if expr.id.is_empty() {
+            Some(format!(
+                "dynamic attribute `{}` of <{}> has no expression id",
+                attr.name, tag
+            ))
+        } else if expr.code.trim().is_empty() {
+            Some(format!(
+                "dynamic attribute `{}` of <{}> has no code",
+                attr.name, tag
+            ))
+        } else if !ids.contains(expr.id.as_str()) {
+            Some(format!(
+                "dynamic attribute `{}` of <{}> references expression `{}`, which is not in its expressions",
+                attr.name, tag, expr.id
+            ))
+        } else {
+            None
+        }
+    })
+}
+
+/// Fragment conditions and loop sources are ids or, for sources, code; only ids
+/// are checked
+fn referenced_id(value: &str, ids: &HashSet<&str>, what: &str) -> Option<String> {
+    let is_id = value.strip_prefix("expr_").is_some_and(|rest| {
+        !rest.is_empty() && rest.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    });
+    (is_id && !ids.contains(value)).then(|| {
+        format!(
+            "{} references expression `{}`, which is not in its expressions",
+            what, value
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    const CARD: &str = "<div class=\"card\" title={heading}><h2>{heading}</h2><slot /></div>\n<script>\nprop heading\n</script>";
+
+    fn card() -> serde_json::Value {
+        let comp = ComponentIR::from_source("Card", "components/Card.zen", CARD).unwrap();
+        serde_json::to_value(comp).unwrap()
+    }
+
+    /// The Card entry with the expression node's id pointing nowhere
+    fn dangling_card() -> serde_json::Value {
+        let mut card = card();
+        let h2 = &mut card["nodes"][0]["children"][0];
+        assert_eq!(h2["tag"], "h2");
+        h2["children"][0]["expression"] = serde_json::json!("expr_404");
+        card
+    }
+
+    fn compile(card: serde_json::Value, strict_registry: bool) -> Result<CompileResult, String> {
+        let options = CompileOptions {
+            components: [("Card".to_string(), card)].into(),
+            strict_registry,
+            ..Default::default()
+        };
+        compile_zen_internal(
+            "<main><Card heading=\"Hi\"><p>Body</p></Card></main>",
+            "page.zen",
+            options,
+        )
+    }
+
+    #[test]
+    fn test_well_formed_entry_is_accepted() {
+        let result = compile(card(), false).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(result.html.contains("<h2>"), "{}", result.html);
+        assert!(
+            !result
+                .warnings
+                .iter()
+                .any(|w| w.contains("REGISTRY-INVALID")),
+            "{:?}",
+            result.warnings
+        );
+    }
+
+    #[test]
+    fn test_dangling_expression_is_rejected_and_left_unresolved() {
+        let result = compile(dangling_card(), false).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let warning = result
+            .warnings
+            .iter()
+            .find(|w| w.starts_with("Z-WARN-REGISTRY-INVALID"))
+            .unwrap_or_else(|| panic!("{:?}", result.warnings));
+        assert!(
+            warning.starts_with("Z-WARN-REGISTRY-INVALID: registry entry `Card` (components/Card.zen) is invalid: an expression node references expression `expr_404`, which is not in its expressions."),
+            "{}",
+            warning
+        );
+        // Unresolved: the usage's children are kept, the card's markup is not
+        assert!(result.html.contains("<p>Body</p>"), "{}", result.html);
+        assert!(!result.html.contains("<h2>"), "{}", result.html);
+    }
+
+    #[test]
+    fn test_malformed_attributes_and_entries_are_named() {
+        let mut card = card();
+        card["nodes"][0]["attributes"][1]["value"]["id"] = serde_json::json!("");
+        let (accepted, diagnostics) = take_in(
+            [
+                ("Card".to_string(), card),
+                (
+                    "Broken".to_string(),
+                    serde_json::json!({ "path": "components/Broken.zen", "nodes": 3 }),
+                ),
+            ]
+            .into(),
+            false,
+        );
+        assert!(accepted.is_empty());
+        assert_eq!(diagnostics.len(), 2, "{:?}", diagnostics);
+        assert!(
+            diagnostics[0].starts_with("Z-WARN-REGISTRY-INVALID: registry entry `Broken` (components/Broken.zen) is invalid: the entry is not a component IR"),
+            "{}",
+            diagnostics[0]
+        );
+        assert!(
+            diagnostics[1].contains("dynamic attribute `title` of <div> has no expression id"),
+            "{}",
+            diagnostics[1]
+        );
+    }
+
+    #[test]
+    fn test_strict_registry_fails_the_compile() {
+        let err = compile(dangling_card(), true).unwrap_err();
+        assert!(
+            err.contains("Z-ERR-REGISTRY-INVALID: registry entry `Card` (components/Card.zen) is invalid: an expression node references expression `expr_404`"),
+            "{}",
+            err
+        );
+    }
+}
@@ -49,6 +49,11 @@ mod tests {
                     collect_orphan_slots(n, orphans);
                 }
             }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for n in mb.children().flatten() {
+                    collect_orphan_slots(n, orphans);
+                }
+            }
             TemplateNode::OptionalFragment(of) => {
                 for n in &of.fragment {
                     collect_orphan_slots(n, orphans);
@@ -71,6 +76,8 @@ mod tests {
             children: vec![],
             location: mock_loc(),
             loop_context: None,
+            ordinal: 0,
+            extra: Default::default(),
         })];
 
         let orphans = find_orphan_slots(&nodes);
@@ -86,6 +93,8 @@ mod tests {
             children: vec![],
             location: mock_loc(),
             loop_context: None,
+            ordinal: 0,
+            extra: Default::default(),
         })];
 
         let orphans = find_orphan_slots(&nodes);
@@ -104,9 +113,13 @@ mod tests {
                 children: vec![],
                 location: mock_loc(),
                 loop_context: None,
+                ordinal: 0,
+                extra: Default::default(),
             })],
             location: mock_loc(),
             loop_context: None,
+            ordinal: 0,
+            extra: Default::default(),
         })];
 
         let orphans = find_orphan_slots(&nodes);
@@ -122,9 +135,13 @@ mod tests {
                 value: "Hello".to_string(),
                 location: mock_loc(),
                 loop_context: None,
+                ordinal: 0,
+                extra: Default::default(),
             })],
             location: mock_loc(),
             loop_context: None,
+            ordinal: 0,
+            extra: Default::default(),
         })];
 
         let orphans = find_orphan_slots(&nodes);
@@ -145,10 +162,14 @@ mod tests {
                     value: AttributeValue::Static("header".to_string()),
                     location: mock_loc(),
                     loop_context: None,
+                    optional: false,
+                    extra: Default::default(),
                 }],
                 children: vec![],
                 location: mock_loc(),
                 loop_context: None,
+                ordinal: 0,
+                extra: Default::default(),
             }),
             TemplateNode::Element(ElementNode {
                 tag: "main".to_string(),
@@ -156,6 +177,8 @@ mod tests {
                 children: vec![],
                 location: mock_loc(),
                 loop_context: None,
+                ordinal: 0,
+                extra: Default::default(),
             }),
             TemplateNode::Element(ElementNode {
                 tag: "slot".to_string(),
@@ -164,10 +187,14 @@ mod tests {
                     value: AttributeValue::Static("footer".to_string()),
                     location: mock_loc(),
                     loop_context: None,
+                    optional: false,
+                    extra: Default::default(),
                 }],
                 children: vec![],
                 location: mock_loc(),
                 loop_context: None,
+                ordinal: 0,
+                extra: Default::default(),
             }),
         ];
 
@@ -188,10 +215,14 @@ mod tests {
                 value: AttributeValue::Static("btn-primary".to_string()),
                 location: mock_loc(),
                 loop_context: None,
+                optional: false,
+                extra: Default::default(),
             }],
             children: vec![],
             location: mock_loc(),
             loop_context: None,
+            ordinal: 0,
+            extra: Default::default(),
         });
 
         if let TemplateNode::Element(el) = node {
@@ -208,6 +239,8 @@ mod tests {
             code: "dynamicClass".to_string(),
             location: mock_loc(),
             loop_context: None,
+            origin: None,
+            extra: Default::default(),
         };
 
         let attr = AttributeIR {
@@ -215,6 +248,8 @@ mod tests {
             value: AttributeValue::Dynamic(expr),
             location: mock_loc(),
             loop_context: None,
+            optional: false,
+            extra: Default::default(),
         };
 
         match &attr.value {
@@ -233,6 +268,8 @@ mod tests {
                 value: AttributeValue::Static("my-id".to_string()),
                 location: mock_loc(),
                 loop_context: None,
+                optional: false,
+                extra: Default::default(),
             },
             AttributeIR {
                 name: "class".to_string(),
@@ -241,9 +278,13 @@ mod tests {
                     code: "className".to_string(),
                     location: mock_loc(),
                     loop_context: None,
+                    origin: None,
+                    extra: Default::default(),
                 }),
                 location: mock_loc(),
                 loop_context: None,
+                optional: false,
+                extra: Default::default(),
             },
         ];
 
@@ -273,9 +314,13 @@ mod tests {
                 value: "test".to_string(),
                 location: mock_loc(),
                 loop_context: None,
+                ordinal: 0,
+                extra: Default::default(),
             })],
             location: mock_loc(),
             loop_context: None,
+            ordinal: 0,
+            extra: Default::default(),
         });
 
         let json = serde_json::to_string(&node).expect("Should serialize");
@@ -479,4 +524,129 @@ mod tests {
             errors
         );
     }
+
+    #[test]
+    fn test_tagged_template_interpolation_is_qualified_and_tracked() {
+        let allocator = Allocator::default();
+        let code = "css`color: ${theme}; ${(...parts) => parts.length}`";
+        let mut ret = Parser::new(&allocator, code, SourceType::default()).parse();
+        let mut renamer = ScriptRenamer::with_categories(
+            &allocator,
+            HashSet::from(["theme".to_string()]),
+            HashSet::new(),
+            HashSet::from(["css".to_string()]),
+            HashSet::new(),
+        );
+        renamer.visit_program(&mut ret.program);
+        let result = Codegen::new().build(&ret.program).code;
+
+        assert!(renamer.errors.is_empty(), "{:?}", renamer.errors);
+        assert!(
+            result.contains("scope.locals.css`color: ${scope.state.theme};"),
+            "got: {}",
+            result
+        );
+        assert!(renamer.state_deps.contains("theme"));
+    }
+
+    #[test]
+    fn test_unresolved_template_tag_names_its_role() {
+        let state = HashSet::from(["theme".to_string()]);
+        let (result, errors) =
+            transform_code_with_guards("css`color: ${theme}`", &state, false, false);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].starts_with(
+            "Z-ERR-SCOPE-002: Identifier `css` is used as a template tag but is not declared"
+        ));
+        // The interpolation is still rewritten
+        assert!(result.contains("${scope.state.theme}"), "got: {}", result);
+    }
+
+    #[test]
+    fn test_string_raw_is_a_global_tag() {
+        let state = HashSet::from(["theme".to_string()]);
+        let (result, errors) =
+            transform_code_with_guards("String.raw`a\\n${theme}`", &state, false, false);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(
+            result.contains("String.raw`a\\n${scope.state.theme}`"),
+            "got: {}",
+            result
+        );
+    }
+
+    /// Renamer after visiting `code` with `user` as state and `profile`, `tags` as props
+    fn visit_with_props<'a>(
+        allocator: &'a Allocator,
+        code: &'a str,
+        is_event_handler: bool,
+    ) -> ScriptRenamer<'a> {
+        let mut ret = Parser::new(allocator, code, SourceType::default()).parse();
+        let mut renamer = ScriptRenamer::with_categories(
+            allocator,
+            HashSet::from(["user".to_string()]),
+            HashSet::from(["profile".to_string(), "tags".to_string()]),
+            HashSet::new(),
+            HashSet::new(),
+        );
+        renamer.is_event_handler = is_event_handler;
+        renamer.visit_program(&mut ret.program);
+        renamer
+    }
+
+    #[test]
+    fn test_nested_prop_mutation_in_expression_errors() {
+        let allocator = Allocator::default();
+        for code in [
+            "profile.name = 'x'",
+            "(profile?.address).city = 'x'",
+            "props.profile.name = 'x'",
+            "tags.push(1)",
+            "tags?.splice(0, 1)",
+        ] {
+            let renamer = visit_with_props(&allocator, code, false);
+            assert!(
+                renamer
+                    .errors
+                    .iter()
+                    .any(|e| e.starts_with("Z-ERR-REACTIVITY-BOUNDARY: Prop")),
+                "{}: {:?}",
+                code,
+                renamer.errors
+            );
+        }
+        // Reads and non-mutating calls are fine
+        let renamer = visit_with_props(
+            &allocator,
+            "tags.map((t) => t.name).join(profile.sep)",
+            false,
+        );
+        assert!(renamer.errors.is_empty(), "{:?}", renamer.errors);
+    }
+
+    #[test]
+    fn test_nested_prop_mutation_in_handler_warns() {
+        let allocator = Allocator::default();
+        let renamer = visit_with_props(
+            &allocator,
+            "() => { profile.name = 'x'; tags.push(1) }",
+            true,
+        );
+        assert!(renamer.errors.is_empty(), "{:?}", renamer.errors);
+        assert_eq!(renamer.warnings.len(), 2, "{:?}", renamer.warnings);
+        assert!(renamer.warnings[0].starts_with(
+            "Z-WARN-PROP-MUTATION: Event handler mutates prop `profile` (field assignment)"
+        ));
+        assert!(renamer.warnings[1].contains("prop `tags` (`.push()`)"));
+    }
+
+    #[test]
+    fn test_nested_state_write_in_handler_is_a_mutation() {
+        let allocator = Allocator::default();
+        let renamer = visit_with_props(&allocator, "() => { user.name = 'x' }", true);
+        assert!(renamer.mutated_state_deps.contains("user"));
+        let renamer = visit_with_props(&allocator, "() => state.user.roles.add('admin')", true);
+        assert!(renamer.mutated_state_deps.contains("user"));
+        assert!(renamer.errors.is_empty() && renamer.warnings.is_empty());
+    }
 }
@@ -28,27 +28,47 @@ fn phase_0_hard_stop_sanity_check() {
             id: "expr_handler_1".to_string(),
             code: "incrementParent".to_string(),
             loop_context: None,
+            origin: None,
         },
         // {parentCount}
         ExpressionInput {
             id: "expr_text_1".to_string(),
             code: "parentCount".to_string(),
             loop_context: None,
+            origin: None,
         },
         // {showExtra ? 'ON' : 'OFF'}
         ExpressionInput {
             id: "expr_text_2".to_string(),
             code: "showExtra ? 'ON' : 'OFF'".to_string(),
             loop_context: None,
+            origin: None,
         },
         // Inline handler: () => parentCount += 1
         ExpressionInput {
             id: "expr_inline_handler".to_string(),
             code: "() => parentCount += 1".to_string(),
             loop_context: None,
+            origin: None,
         },
     ];
 
+    // The template that references them (unreferenced expressions are not emitted)
+    let nodes = serde_json::from_value(serde_json::json!([{
+        "type": "element",
+        "tag": "div",
+        "attributes": [
+            { "name": "onclick", "value": { "id": "expr_handler_1", "code": "incrementParent" } },
+            { "name": "oninput", "value": { "id": "expr_inline_handler", "code": "() => parentCount += 1" } }
+        ],
+        "children": [
+            { "type": "expression", "expression": "expr_text_1", "loopContext": null },
+            { "type": "expression", "expression": "expr_text_2", "loopContext": null }
+        ],
+        "loopContext": null
+    }]))
+    .unwrap();
+
     let input = CodegenInput {
         file_path: "verify-phase-2.zen".to_string(),
         script_content: script_content.to_string(),
@@ -56,7 +76,7 @@ fn phase_0_hard_stop_sanity_check() {
         styles: vec![],
         template_bindings: vec![],
         location: "test".to_string(),
-        nodes: vec![],
+        nodes,
         page_bindings: vec![
             "parentCount".to_string(),
             "showExtra".to_string(),
@@ -71,6 +91,12 @@ fn phase_0_hard_stop_sanity_check() {
         .into_iter()
         .collect(),
         locals: vec![],
+        islands: None,
+        minify: false,
+        embed: false,
+        strip_debug: Default::default(),
+        cancel: None,
+        target_es: Default::default(),
     };
 
     let result = generate_runtime_code_internal(input);
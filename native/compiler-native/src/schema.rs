@@ -0,0 +1,161 @@
+//! JSON Schemas
+//!
+//! Schemas of the payloads that cross the JS boundary, generated from the same
+//! types serde encodes them with, so the TypeScript plugin can validate what it
+//! receives in dev mode and generate its types instead of keeping a hand-written
+//! copy in sync. Built with the `schema` feature.
+//!
+//! | Name                | Payload                                      |
+//! |---------------------|----------------------------------------------|
+//! | `ParseFullOptions`  | options of `parse_full_zen_native`           |
+//! | `ParseFullOutput`   | what `parse_full_zen_native` returns         |
+//! | `ComponentIR`       | entries of `components`                      |
+//! | `ZenIR`             | the IR (`mode: "metadata"`, `includeIr`)     |
+//! | `TemplateNode`      | template nodes                               |
+//! | `ZenManifestExport` | the manifest                                 |
+//!
+//! `ParseFullOutput` is also the JSON projection of `CompileResult`: the page
+//! shape is the same fields under their camelCase names.
+
+use std::collections::HashMap;
+
+use napi_derive::napi;
+use schemars::JsonSchema;
+
+fn schema_of<T: JsonSchema>() -> serde_json::Value {
+    schemars::schema_for!(T).to_value()
+}
+
+/// Schema of every boundary payload, by name
+pub fn json_schemas() -> HashMap<String, serde_json::Value> {
+    [
+        (
+            "ParseFullOptions",
+            schema_of::<crate::parse::ParseFullOptions>(),
+        ),
+        (
+            "ParseFullOutput",
+            schema_of::<crate::parse::ParseFullOutput>(),
+        ),
+        ("ComponentIR", schema_of::<crate::component::ComponentIR>()),
+        ("ZenIR", schema_of::<crate::validate::ZenIR>()),
+        ("TemplateNode", schema_of::<crate::validate::TemplateNode>()),
+        (
+            "ZenManifestExport",
+            schema_of::<crate::finalize::ZenManifestExport>(),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, schema)| (name.to_string(), schema))
+    .collect()
+}
+
+/// `json_schemas` for the JS side
+#[napi]
+pub fn get_json_schemas_native() -> serde_json::Value {
+    serde_json::to_value(json_schemas()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, parse_full_zen, CompileOptions, ParseFullOptions};
+
+    const PAGE: &str = include_str!("../tests/fixtures/loop_conditional/page.zen");
+    /// Expressions with one dependency each, so two compiles emit the same bundle
+    const COUNTER: &str = "<script>\nstate count = 0\n</script>\n<main><p>{count}</p><button onclick={() => count++}>+</button></main>";
+
+    fn page_json(source: &str) -> serde_json::Value {
+        let options = ParseFullOptions {
+            include: Some(vec!["manifest".to_string()]),
+            ..Default::default()
+        };
+        serde_json::to_value(parse_full_zen(source, "page.zen", options).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_every_payload_has_a_schema() {
+        let schemas = json_schemas();
+        let mut names: Vec<&str> = schemas.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "ComponentIR",
+                "ParseFullOptions",
+                "ParseFullOutput",
+                "TemplateNode",
+                "ZenIR",
+                "ZenManifestExport"
+            ]
+        );
+        for (name, schema) in &schemas {
+            assert_eq!(schema["title"], serde_json::json!(name), "{}", name);
+            assert!(
+                jsonschema::meta::is_valid(schema),
+                "{} is not a valid schema",
+                name
+            );
+        }
+        // Field names are the serde ones
+        let manifest = &schemas["ZenManifestExport"]["properties"];
+        assert!(manifest.get("npmImports").is_some() && manifest.get("npm_imports").is_none());
+    }
+
+    #[test]
+    fn test_page_result_matches_the_legacy_shape() {
+        let result = compile_zen_internal(COUNTER, "page.zen", CompileOptions::default()).unwrap();
+        let manifest = result.manifest.unwrap();
+        // The object `parse_full_zen_native` used to assemble with `json!`
+        let legacy = serde_json::json!({
+            "html": result.html,
+            "hasErrors": result.has_errors,
+            "errors": result.errors,
+            "warnings": result.warnings,
+            "bindings": result.bindings,
+            "i18nKeys": result.i18n_keys,
+            "preloadHints": result.preload_hints,
+            "navigationTargets": result.navigation_targets,
+            "depGraph": result.dep_graph,
+            "styleChunks": result.style_chunks,
+            "js": manifest.bundle,
+            "npmImports": manifest.npm_imports,
+            "styles": manifest.styles,
+            "manifest": manifest,
+        });
+        assert_eq!(page_json(COUNTER), legacy);
+    }
+
+    #[test]
+    fn test_payloads_validate_against_their_schemas() {
+        let schemas = json_schemas();
+        let validate = |name: &str, payload: &serde_json::Value| {
+            let validator = jsonschema::validator_for(&schemas[name]).unwrap();
+            let errors: Vec<String> = validator
+                .iter_errors(payload)
+                .map(|e| format!("{} at {}", e, e.instance_path()))
+                .collect();
+            assert!(errors.is_empty(), "{}: {:?}", name, errors);
+        };
+
+        let page = page_json(PAGE);
+        validate("ParseFullOutput", &page);
+        validate("ZenManifestExport", &page["manifest"]);
+        validate(
+            "ParseFullOptions",
+            &serde_json::json!({ "mode": "full", "astClassifier": true }),
+        );
+        let component =
+            crate::component::ComponentIR::from_source("Card", "components/Card.zen", PAGE)
+                .unwrap();
+        validate("ComponentIR", &serde_json::to_value(component).unwrap());
+
+        // A renamed field is caught
+        let mut manifest = page["manifest"].clone();
+        let fields = manifest.as_object_mut().unwrap();
+        let bundle = fields.remove("bundle").unwrap();
+        fields.insert("jsBundle".to_string(), bundle);
+        let validator = jsonschema::validator_for(&schemas["ZenManifestExport"]).unwrap();
+        assert!(!validator.is_valid(&manifest));
+    }
+}
@@ -0,0 +1,188 @@
+//! Component Script Cache
+//!
+//! A site build compiles many pages over the same components, and resolution of
+//! every page used to parse every component script again. What resolution needs
+//! from a script depends on the registry entry alone: the names it declares, the
+//! script rewritten for the component's `__run()` thunk, the imports lifted out of
+//! it, and its diagnostics. A `ParsedScript` keeps those as owned data (oxc ASTs
+//! borrow their allocator, so no AST is kept), built once per component.
+//!
+//! A `ScriptCache` shares them across compiles, keyed by component path and
+//! content hash (see `ComponentIR::compute_content_hash`), so an edited component
+//! is parsed again and an unchanged one is not. `compile_zen_batch_internal` uses
+//! one cache for the whole batch; a dev server compiling pages one at a time can
+//! keep its own in `CompileOptions.script_cache` and drop it to free the entries.
+//! Template expressions are still renamed per instance: that needs their AST.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::component::ComponentIR;
+
+#[cfg(test)]
+thread_local! {
+    /// Component scripts parsed for analysis on this thread
+    pub(crate) static SCRIPT_PARSES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// What resolution learns from one parse of a component script
+#[derive(Debug)]
+pub struct ParsedScript {
+    /// Top-level declarations that are neither props nor state
+    pub(crate) locals: HashSet<String>,
+    /// The script rewritten for the `__run()` thunk (as written if it does not parse)
+    pub(crate) renamed: String,
+    /// `import` statements lifted out of the script, hoisted into the page script
+    pub(crate) imports: Vec<String>,
+    /// Syntax errors, or the scope errors of the rewrite
+    pub(crate) errors: Vec<String>,
+}
+
+/// Summaries by (component path, content hash)
+type Entries = HashMap<(String, String), Arc<ParsedScript>>;
+
+/// Shared handle: clones see the same entries, so one cache can cover a batch
+/// or a whole dev server session
+#[derive(Debug, Clone, Default)]
+pub struct ScriptCache {
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl ScriptCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Component scripts summarized so far
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Summary of the component's script, parsed on first use; None without a script
+    pub(crate) fn get(&self, comp: &ComponentIR) -> Option<Arc<ParsedScript>> {
+        let script = comp.script.as_deref()?;
+        let key = (
+            comp.path.clone(),
+            comp.content_hash
+                .clone()
+                .unwrap_or_else(|| comp.compute_content_hash()),
+        );
+        if let Some(parsed) = self.lock().get(&key) {
+            return Some(Arc::clone(parsed));
+        }
+        // Parsed without the lock held: concurrent compiles may race on one entry,
+        // and both results are the same
+        let parsed = Arc::new(crate::component::analyze_component_script(comp, script));
+        self.lock().insert(key, Arc::clone(&parsed));
+        Some(parsed)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_batch_internal, compile_zen_internal, CompileOptions};
+
+    fn registry(components: usize) -> HashMap<String, serde_json::Value> {
+        (0..components)
+            .map(|i| {
+                let name = format!("Widget{}", i);
+                let source = format!(
+                    "<script>\nstate count = {}\nprop label\nfunction show(n) {{ return label + ': ' + n }}\n</script>\n<p class=\"w{}\">{{show(count)}}</p>",
+                    i, i
+                );
+                let path = format!("components/{}.zen", name);
+                let comp = ComponentIR::from_source(&name, &path, &source).unwrap();
+                (name, serde_json::to_value(comp).unwrap())
+            })
+            .collect()
+    }
+
+    fn pages(pages: usize, components: usize) -> Vec<(String, String)> {
+        (0..pages)
+            .map(|p| {
+                let usages: String = (0..components)
+                    .map(|i| format!("<Widget{} label=\"page {}\" />", i, p))
+                    .collect();
+                (format!("<main>{}</main>", usages), format!("page{}.zen", p))
+            })
+            .collect()
+    }
+
+    fn parses<T>(compile: impl FnOnce() -> T) -> (usize, T) {
+        SCRIPT_PARSES.with(|count| count.set(0));
+        let result = compile();
+        (SCRIPT_PARSES.with(std::cell::Cell::get), result)
+    }
+
+    #[test]
+    fn test_batch_parses_each_component_script_once() {
+        let components = registry(30);
+        let pages = pages(50, 30);
+        let files: Vec<(&str, &str)> = pages
+            .iter()
+            .map(|(source, path)| (source.as_str(), path.as_str()))
+            .collect();
+        let options = CompileOptions {
+            components: components.clone(),
+            verify_invariants: Some(false),
+            ..Default::default()
+        };
+
+        let (batch_parses, batch) = parses(|| compile_zen_batch_internal(&files, options.clone()));
+        assert_eq!(batch_parses, 30);
+
+        // Each page on its own parses every component again, with the same output
+        let (single_parses, single) = parses(|| {
+            files
+                .iter()
+                .step_by(10)
+                .map(|(source, path)| compile_zen_internal(source, path, options.clone()))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(single_parses, 5 * 30);
+        for (batch, single) in batch.unwrap().into_iter().step_by(10).zip(single) {
+            let (batch, single) = (batch.unwrap(), single.unwrap());
+            assert!(batch.errors.is_empty(), "{:?}", batch.errors);
+            assert_eq!(batch.html, single.html);
+            assert_eq!(batch.errors, single.errors);
+            assert_eq!(batch.bindings.len(), single.bindings.len());
+        }
+    }
+
+    #[test]
+    fn test_shared_cache_reparses_only_edited_components() {
+        let cache = ScriptCache::new();
+        let mut components = registry(3);
+        let options = |components: &HashMap<String, serde_json::Value>| CompileOptions {
+            components: components.clone(),
+            script_cache: Some(cache.clone()),
+            ..Default::default()
+        };
+        let (source, path) = pages(1, 3).remove(0);
+
+        let (first, _) = parses(|| compile_zen_internal(&source, &path, options(&components)));
+        assert_eq!((first, cache.len()), (3, 3));
+        let (again, _) = parses(|| compile_zen_internal(&source, &path, options(&components)));
+        assert_eq!(again, 0);
+
+        let edited = ComponentIR::from_source(
+            "Widget1",
+            "components/Widget1.zen",
+            "<script>\nstate count = 10\nprop label\n</script>\n<p class=\"edited\">{label} {count}</p>",
+        )
+        .unwrap();
+        components.insert("Widget1".to_string(), serde_json::to_value(edited).unwrap());
+        let (edit, result) = parses(|| compile_zen_internal(&source, &path, options(&components)));
+        assert_eq!(edit, 1);
+        assert!(result.unwrap().html.contains("class=\"edited\""));
+    }
+}
@@ -0,0 +1,463 @@
+//! Top-level Script Collisions
+//!
+//! Component scripts run inside their instance's `__run()` thunk, but their
+//! imports are hoisted to the top of the page's merged script, next to the page's
+//! own declarations. Two top-level declarations of one name make the bundle throw
+//! "Identifier has already been declared" at load, so every hoisted import is
+//! checked against what the merged script already declares:
+//!
+//! - the same binding (same module and export) is hoisted once;
+//! - a name the page declares itself (`function formatDate()`, `state today`) is
+//!   renamed in the component: the import becomes `formatDate as formatDate_inst0`
+//!   and that instance's script refers to the new name;
+//! - an import of a different binding (`slugify` from two packages) cannot be
+//!   renamed safely on either side and is a `Z-ERR-SCRIPT-COLLISION` naming both
+//!   declaration sites.
+
+use std::collections::HashMap;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{
+    BindingPattern, ImportDeclarationSpecifier, ModuleExportName, ObjectProperty, Statement,
+};
+use oxc_ast_visit::Visit;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use crate::component::ComponentIR;
+
+pub const SCRIPT_COLLISION_ERROR: &str = "Z-ERR-SCRIPT-COLLISION";
+
+/// What an import binds: the module and its export (`default`, `*`, or a name)
+#[derive(Debug, Clone, PartialEq)]
+struct Import {
+    source: String,
+    imported: String,
+}
+
+impl Import {
+    fn describe(&self) -> String {
+        match self.imported.as_str() {
+            "default" => format!("the default export of \"{}\"", self.source),
+            "*" => format!("the namespace of \"{}\"", self.source),
+            name => format!("`{}` from \"{}\"", name, self.source),
+        }
+    }
+}
+
+/// A top-level declaration and its offset in the script
+struct Declared {
+    name: String,
+    import: Option<Import>,
+    offset: usize,
+}
+
+struct Binding {
+    import: Option<Import>,
+    /// `file (line L, column C)`
+    site: String,
+}
+
+/// Top-level names of the merged script so far
+#[derive(Default)]
+pub(crate) struct TopLevelBindings {
+    bindings: HashMap<String, Binding>,
+}
+
+/// One instance's hoisted imports
+#[derive(Default)]
+pub(crate) struct Hoisted {
+    /// Import statements to add to the merged script
+    pub statements: Vec<String>,
+    /// Renamed import bindings, for the instance's script
+    pub renames: HashMap<String, String>,
+    pub errors: Vec<String>,
+}
+
+impl TopLevelBindings {
+    /// The declarations of the page script; `source` is the page as written
+    pub(crate) fn from_page(script: &str, source: &str, file: &str) -> Self {
+        let mut top_level = Self::default();
+        for decl in declarations(&crate::component::zen_keywords_to_let_in_place(script)) {
+            let site = site(source, script, decl.offset, file);
+            top_level.bindings.entry(decl.name).or_insert(Binding {
+                import: decl.import,
+                site,
+            });
+        }
+        top_level
+    }
+
+    /// Check the imports of an instance of `comp` against the merged script
+    /// (see module docs)
+    pub(crate) fn hoist(
+        &mut self,
+        imports: &[String],
+        comp: &ComponentIR,
+        suffix: &str,
+    ) -> Hoisted {
+        let mut hoisted = Hoisted::default();
+        let mut sites: Option<Vec<Declared>> = None;
+        for statement in imports {
+            let declared = declarations(statement);
+            // Side-effect and type-only imports bind nothing at runtime
+            if declared.is_empty() {
+                if !hoisted.statements.contains(statement) {
+                    hoisted.statements.push(statement.clone());
+                }
+                continue;
+            }
+            let mut kept = Vec::new();
+            let mut changed = false;
+            for decl in declared {
+                let import = decl.import.expect("import statements declare imports");
+                let Some(existing) = self.bindings.get(&decl.name) else {
+                    kept.push((decl.name, import));
+                    continue;
+                };
+                changed = true;
+                match &existing.import {
+                    // Already bound to the same export
+                    Some(bound) if *bound == import => {}
+                    Some(bound) => {
+                        let sites = sites.get_or_insert_with(|| component_declarations(comp));
+                        hoisted.errors.push(format!(
+                            "{}: `{}` is imported as {} at {} and as {} at {}; both are hoisted to the top of the page script. Give one of the imports another local name.",
+                            SCRIPT_COLLISION_ERROR,
+                            decl.name,
+                            import.describe(),
+                            component_site(comp, sites, &decl.name),
+                            bound.describe(),
+                            existing.site,
+                        ));
+                    }
+                    // A page declaration: the component's binding takes the instance suffix,
+                    // reusing an earlier instance's import of the same export
+                    None => {
+                        let renamed = self
+                            .bindings
+                            .iter()
+                            .find(|(_, b)| b.import.as_ref() == Some(&import))
+                            .map(|(name, _)| name.clone());
+                        let renamed = match renamed {
+                            Some(name) => name,
+                            None => {
+                                let name = format!("{}_{}", decl.name, suffix);
+                                kept.push((name.clone(), import));
+                                name
+                            }
+                        };
+                        hoisted.renames.insert(decl.name, renamed);
+                    }
+                }
+            }
+            for (name, import) in &kept {
+                let site = {
+                    let sites = sites.get_or_insert_with(|| component_declarations(comp));
+                    let original = hoisted
+                        .renames
+                        .iter()
+                        .find(|(_, renamed)| *renamed == name)
+                        .map_or(name.as_str(), |(original, _)| original.as_str());
+                    component_site(comp, sites, original)
+                };
+                self.bindings.insert(
+                    name.clone(),
+                    Binding {
+                        import: Some(import.clone()),
+                        site,
+                    },
+                );
+            }
+            if !changed {
+                hoisted.statements.push(statement.clone());
+            } else {
+                hoisted.statements.extend(
+                    kept.iter()
+                        .map(|(name, import)| import_statement(name, import)),
+                );
+            }
+        }
+        hoisted
+    }
+}
+
+/// One import of one binding
+fn import_statement(local: &str, import: &Import) -> String {
+    match import.imported.as_str() {
+        "default" => format!("import {} from {:?};", local, import.source),
+        "*" => format!("import * as {} from {:?};", local, import.source),
+        name if name == local => format!("import {{ {} }} from {:?};", local, import.source),
+        name => format!(
+            "import {{ {} as {} }} from {:?};",
+            name, local, import.source
+        ),
+    }
+}
+
+fn source_type() -> SourceType {
+    SourceType::default()
+        .with_module(true)
+        .with_typescript(true)
+        .with_jsx(true)
+}
+
+/// Runtime top-level declarations of `code`; empty when it does not parse
+fn declarations(code: &str) -> Vec<Declared> {
+    let allocator = Allocator::default();
+    let ret = Parser::new(&allocator, code, source_type()).parse();
+    if !ret.errors.is_empty() {
+        return Vec::new();
+    }
+    let mut declared = Vec::new();
+    let mut local = |name: &str, offset: u32, import: Option<Import>| {
+        declared.push(Declared {
+            name: name.to_string(),
+            import,
+            offset: offset as usize,
+        })
+    };
+    for stmt in &ret.program.body {
+        match stmt {
+            Statement::ImportDeclaration(decl) if !decl.import_kind.is_type() => {
+                let source = decl.source.value.to_string();
+                for spec in decl.specifiers.iter().flatten() {
+                    let (imported, id) = match spec {
+                        ImportDeclarationSpecifier::ImportSpecifier(s) => {
+                            if s.import_kind.is_type() {
+                                continue;
+                            }
+                            let imported = match &s.imported {
+                                ModuleExportName::IdentifierName(n) => n.name.to_string(),
+                                ModuleExportName::IdentifierReference(n) => n.name.to_string(),
+                                ModuleExportName::StringLiteral(l) => l.value.to_string(),
+                            };
+                            (imported, &s.local)
+                        }
+                        ImportDeclarationSpecifier::ImportDefaultSpecifier(s) => {
+                            ("default".to_string(), &s.local)
+                        }
+                        ImportDeclarationSpecifier::ImportNamespaceSpecifier(s) => {
+                            ("*".to_string(), &s.local)
+                        }
+                    };
+                    let import = Import {
+                        source: source.clone(),
+                        imported,
+                    };
+                    local(&id.name, id.span.start, Some(import));
+                }
+            }
+            Statement::VariableDeclaration(var_decl) => {
+                for decl in &var_decl.declarations {
+                    let mut names = Vec::new();
+                    binding_names(&decl.id, &mut names);
+                    for (name, offset) in names {
+                        local(&name, offset, None);
+                    }
+                }
+            }
+            Statement::FunctionDeclaration(func) => {
+                if let Some(id) = &func.id {
+                    local(&id.name, id.span.start, None);
+                }
+            }
+            Statement::ClassDeclaration(class) => {
+                if let Some(id) = &class.id {
+                    local(&id.name, id.span.start, None);
+                }
+            }
+            _ => {}
+        }
+    }
+    declared
+}
+
+fn binding_names(pattern: &BindingPattern, names: &mut Vec<(String, u32)>) {
+    match pattern {
+        BindingPattern::BindingIdentifier(id) => names.push((id.name.to_string(), id.span.start)),
+        BindingPattern::ObjectPattern(obj) => {
+            for prop in &obj.properties {
+                binding_names(&prop.value, names);
+            }
+            if let Some(rest) = &obj.rest {
+                binding_names(&rest.argument, names);
+            }
+        }
+        BindingPattern::ArrayPattern(arr) => {
+            for pattern in arr.elements.iter().flatten() {
+                binding_names(pattern, names);
+            }
+            if let Some(rest) = &arr.rest {
+                binding_names(&rest.argument, names);
+            }
+        }
+        BindingPattern::AssignmentPattern(assign) => binding_names(&assign.left, names),
+    }
+}
+
+fn component_declarations(comp: &ComponentIR) -> Vec<Declared> {
+    let script = comp.script.as_deref().unwrap_or("");
+    declarations(&crate::component::zen_keywords_to_let_in_place(script))
+}
+
+fn component_site(comp: &ComponentIR, declared: &[Declared], name: &str) -> String {
+    let script = comp.script.as_deref().unwrap_or("");
+    match declared.iter().find(|d| d.name == name) {
+        Some(decl) => site(&comp.template, script, decl.offset, &comp.path),
+        None => comp.path.clone(),
+    }
+}
+
+/// `file (line L, column C)` of `offset` into `script`, as written in `source`
+fn site(source: &str, script: &str, offset: usize, file: &str) -> String {
+    match source.find(script) {
+        Some(base) => {
+            let (line, column) = crate::script_syntax::position(source, base, script, offset);
+            format!("{} (line {}, column {})", file, line, column)
+        }
+        None => file.to_string(),
+    }
+}
+
+/// `code` with references to the renamed bindings updated; unchanged when it
+/// does not parse
+pub(crate) fn rename_references(code: &str, renames: &HashMap<String, String>) -> String {
+    if renames.is_empty() {
+        return code.to_string();
+    }
+    let allocator = Allocator::default();
+    let ret = Parser::new(&allocator, code, source_type()).parse();
+    if !ret.errors.is_empty() {
+        return code.to_string();
+    }
+    let mut references = References {
+        renames,
+        replacements: Vec::new(),
+    };
+    references.visit_program(&ret.program);
+    let mut replacements = references.replacements;
+    replacements.sort_by_key(|(start, _, _)| std::cmp::Reverse(*start));
+    let mut out = code.to_string();
+    for (start, end, text) in replacements {
+        out.replace_range(start as usize..end as usize, &text);
+    }
+    out
+}
+
+struct References<'r> {
+    renames: &'r HashMap<String, String>,
+    replacements: Vec<(u32, u32, String)>,
+}
+
+impl<'a> Visit<'a> for References<'_> {
+    fn visit_identifier_reference(&mut self, ident: &oxc_ast::ast::IdentifierReference<'a>) {
+        if let Some(renamed) = self.renames.get(ident.name.as_str()) {
+            self.replacements
+                .push((ident.span.start, ident.span.end, renamed.clone()));
+        }
+    }
+
+    fn visit_object_property(&mut self, prop: &ObjectProperty<'a>) {
+        // `{ formatDate }` keeps its key
+        if prop.shorthand {
+            if let Some(name) = prop.key.static_name() {
+                if let Some(renamed) = self.renames.get(name.as_ref()) {
+                    self.replacements.push((
+                        prop.span.start,
+                        prop.span.end,
+                        format!("{}: {}", name, renamed),
+                    ));
+                    return;
+                }
+            }
+        }
+        oxc_ast_visit::walk::walk_object_property(self, prop);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions};
+
+    fn component(name: &str, path: &str, source: &str) -> serde_json::Value {
+        serde_json::to_value(ComponentIR::from_source(name, path, source).unwrap()).unwrap()
+    }
+
+    fn compile(page: &str, components: &[(&str, serde_json::Value)]) -> Result<String, String> {
+        let options = CompileOptions {
+            components: components
+                .iter()
+                .map(|(name, comp)| (name.to_string(), comp.clone()))
+                .collect(),
+            ..Default::default()
+        };
+        compile_zen_internal(page, "page.zen", options)
+            .map(|result| result.manifest.unwrap().bundle)
+    }
+
+    const STAMP: &str = "<script>\nimport formatDate from \"date-fns/format\"\nprop when\nfunction label() { return formatDate(when, { formatDate }) }\n</script>\n<time onclick={label}>{when}</time>";
+
+    #[test]
+    fn test_page_local_collision_renames_component_import() {
+        let page = "<script>\nfunction formatDate(d) { return String(d) }\nstate today = \"2024-01-01\"\n</script>\n<main><p>{formatDate(today)}</p><Stamp when={today} /><Stamp when=\"x\" /></main>";
+        let bundle = compile(
+            page,
+            &[("Stamp", component("Stamp", "components/Stamp.zen", STAMP))],
+        )
+        .unwrap();
+        assert!(
+            bundle.contains("import formatDate_inst0 from \"date-fns/format\";"),
+            "{}",
+            bundle
+        );
+        assert!(!bundle.contains("import formatDate from"));
+        // Both instances call the import; the page keeps its own function
+        assert_eq!(
+            bundle
+                .matches(
+                    "return formatDate_inst0(scope.props.when, { formatDate: formatDate_inst0 })"
+                )
+                .count(),
+            2,
+            "{}",
+            bundle
+        );
+        assert!(bundle.contains("scope.locals.formatDate = function formatDate(d)"));
+    }
+
+    #[test]
+    fn test_same_binding_is_hoisted_once() {
+        let other = "<script>\nimport { slugify, kebab } from \"slug-lib\"\nfunction go() { return slugify(kebab(\"a\")) }\n</script>\n<b onclick={go}>b</b>";
+        let tag = "<script>\nimport { slugify } from \"slug-lib\"\nfunction go() { return slugify(\"a\") }\n</script>\n<i onclick={go}>i</i>";
+        let page = "<main><Tag /><Other /></main>";
+        let bundle = compile(
+            page,
+            &[
+                ("Tag", component("Tag", "components/Tag.zen", tag)),
+                ("Other", component("Other", "components/Other.zen", other)),
+            ],
+        )
+        .unwrap();
+        assert_eq!(bundle.matches("slugify } from").count(), 1, "{}", bundle);
+        assert!(bundle.contains("import { kebab } from \"slug-lib\";"));
+    }
+
+    #[test]
+    fn test_colliding_imports_error_with_both_sites() {
+        let badge = "<script>\nimport format from \"date-fns/format\"\nfunction go() { return format(1) }\n</script>\n<b onclick={go}>b</b>";
+        let page = "<script>\nimport format from \"pretty-format\"\nstate n = 1\n</script>\n<main>{format(n)}<Badge /></main>";
+        let err = compile(
+            page,
+            &[("Badge", component("Badge", "components/Badge.zen", badge))],
+        )
+        .unwrap_err();
+        assert!(err.contains(SCRIPT_COLLISION_ERROR), "{}", err);
+        assert!(
+            err.contains("`format` is imported as the default export of \"date-fns/format\" at components/Badge.zen (line 2, column 8) and as the default export of \"pretty-format\" at page.zen (line 2, column 8)"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Script Syntax Diagnostics
+//!
+//! oxc recovers from syntax errors and hands back a partial program, so the
+//! analysis that follows (state extraction, local collection, renaming) quietly
+//! runs on an incomplete AST. Names declared after the error then surface as
+//! `Z-ERR-SCOPE-002` for identifiers that are perfectly fine, burying the one real
+//! problem. The parser's own errors are therefore reported as `Z-ERR-SCRIPT-SYNTAX`
+//! with oxc's message and the position in the `.zen` file, and a component whose
+//! script does not parse has its unresolved-identifier errors suppressed (noted on
+//! the syntax error) until it does.
+
+use oxc_allocator::Allocator;
+use oxc_parser::{Parser, ParserReturn};
+use oxc_span::SourceType;
+
+use crate::content_audit::line_column;
+use crate::validate::ExpressionIR;
+
+pub(crate) const SCRIPT_SYNTAX_ERROR: &str = "Z-ERR-SCRIPT-SYNTAX";
+
+/// Appended to a component's first syntax error when its scope errors are dropped
+const SCOPE_SUPPRESSED_NOTE: &str =
+    "Z-ERR-SCOPE-002 is not reported for this file until its script parses";
+
+/// Source type of `<script>` blocks (after `state`/`prop` become `let`)
+fn script_source_type() -> SourceType {
+    SourceType::default()
+        .with_module(true)
+        .with_typescript(true)
+        .with_jsx(true)
+}
+
+/// Source type of template expressions, as codegen parses them
+fn expression_source_type() -> SourceType {
+    SourceType::default().with_jsx(true).with_typescript(true)
+}
+
+/// Syntax errors of a parsed script. `parsable` is the text that was parsed;
+/// `script` is the same script as written in `source` (they differ only in the
+/// `state`/`prop` keywords, so line numbers agree).
+pub(crate) fn script_errors(
+    ret: &ParserReturn,
+    parsable: &str,
+    script: &str,
+    source: &str,
+    file: &str,
+    suppresses_scope: bool,
+) -> Vec<String> {
+    let base = source.find(script);
+    ret.errors
+        .iter()
+        .enumerate()
+        .map(|(i, err)| {
+            let offset = err
+                .labels
+                .as_ref()
+                .and_then(|labels| labels.first())
+                .map(|label| label.offset().min(parsable.len()));
+            let mut message = format!(
+                "{}: {}{}",
+                SCRIPT_SYNTAX_ERROR,
+                err.message,
+                location(source, file, base, parsable, offset)
+            );
+            if i == 0 && suppresses_scope {
+                message.push_str("; ");
+                message.push_str(SCOPE_SUPPRESSED_NOTE);
+            }
+            message
+        })
+        .collect()
+}
+
+/// Syntax errors of a page script (see `check_expressions` for its template)
+pub(crate) fn check_script(script: &str, source: &str, file: &str) -> Vec<String> {
+    let parsable = crate::component::zen_keywords_to_let(script);
+    let allocator = Allocator::default();
+    let ret = Parser::new(&allocator, &parsable, script_source_type()).parse();
+    script_errors(&ret, &parsable, script, source, file, false)
+}
+
+/// Syntax errors of template expressions, located in `source` where the
+/// expression text can be found there
+pub(crate) fn check_expressions(
+    expressions: &[ExpressionIR],
+    source: &str,
+    file: &str,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    for expr in expressions {
+        let allocator = Allocator::default();
+        let ret = Parser::new(&allocator, &expr.code, expression_source_type()).parse();
+        let Some(err) = ret.errors.first() else {
+            continue;
+        };
+        // `{{ a, b: 1 }}` is an object literal, not a block
+        if Parser::new(&allocator, &expr.code, expression_source_type())
+            .parse_expression()
+            .is_ok()
+        {
+            continue;
+        }
+        let offset = err
+            .labels
+            .as_ref()
+            .and_then(|labels| labels.first())
+            .map(|label| label.offset().min(expr.code.len()));
+        errors.push(format!(
+            "{}: {} in expression `{{{}}}`{}",
+            SCRIPT_SYNTAX_ERROR,
+            err.message,
+            expr.code.trim(),
+            location(source, file, source.find(&expr.code), &expr.code, offset)
+        ));
+    }
+    errors
+}
+
+/// ` at file (line L, column C)` for `offset` into `code`, which starts at `base`
+/// in `source`; ` in file` when either is unknown
+fn location(
+    source: &str,
+    file: &str,
+    base: Option<usize>,
+    code: &str,
+    offset: Option<usize>,
+) -> String {
+    let (Some(base), Some(offset)) = (base, offset) else {
+        return format!(" in {}", file);
+    };
+    let (line, column) = position(source, base, code, offset);
+    format!(" at {} (line {}, column {})", file, line, column)
+}
+
+/// Line and column in `source` of `offset` into `code`, which starts at `base`
+pub(crate) fn position(source: &str, base: usize, code: &str, offset: usize) -> (usize, usize) {
+    let (base_line, base_column) = line_column(source, base);
+    let (line, column) = line_column(code, offset);
+    if line == 1 {
+        (base_line, base_column + column - 1)
+    } else {
+        (base_line + line - 1, column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::ComponentIR;
+    use crate::parse::{compile_zen_internal, CompileOptions};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_missing_brace_reports_syntax_error_without_scope_avalanche() {
+        let card = ComponentIR::from_source(
+            "Card",
+            "components/Card.zen",
+            "<script>\nprop title\nstate open = false\nfunction toggle() {\n  open = !open\n\nconst label = 'Card'\nconst count = 3\n</script>\n<div onclick={toggle}>{label} {count} {title} {open}</div>",
+        )
+        .unwrap();
+        let mut components = HashMap::new();
+        components.insert("Card".to_string(), serde_json::to_value(card).unwrap());
+        let err = compile_zen_internal(
+            "<main><Card title=\"a\" /><Card title=\"b\" /></main>",
+            "page.zen",
+            CompileOptions {
+                components,
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        let errors: Vec<&str> = err.lines().filter(|l| l.starts_with("Z-")).collect();
+        assert_eq!(errors.len(), 1, "{}", err);
+        assert!(
+            errors[0].starts_with("Z-ERR-SCRIPT-SYNTAX: Expected `}` but found `EOF`"),
+            "{}",
+            err
+        );
+        assert!(errors[0].contains(SCOPE_SUPPRESSED_NOTE), "{}", err);
+        // The end of the script, where the brace was expected
+        assert!(
+            errors[0].contains("`EOF` at components/Card.zen (line 8, column 16);"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_expression_syntax_error_has_message_and_location() {
+        let result = compile_zen_internal(
+            "<script>\nstate count = 0\n</script>\n<main>\n  <p>{count + * 2}</p>\n</main>",
+            "page.zen",
+            CompileOptions::default(),
+        )
+        .unwrap();
+        assert!(result.has_errors);
+        assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+        assert_eq!(
+            result.errors[0],
+            "Z-ERR-SCRIPT-SYNTAX: Unexpected token in expression `{count + * 2}` at page.zen (line 5, column 15)"
+        );
+    }
+
+    #[test]
+    fn test_page_script_syntax_error_is_located() {
+        let source = "<script>\nstate count = 0\nconst items = [1, 2\n</script>\n<p>{count}</p>";
+        let script = crate::parse::parse_script(source).unwrap();
+        let errors = check_script(&script.raw, source, "page.zen");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0].starts_with("Z-ERR-SCRIPT-SYNTAX: "),
+            "{:?}",
+            errors
+        );
+        assert!(errors[0].contains(" at page.zen (line "), "{:?}", errors);
+        assert!(!errors[0].contains(SCOPE_SUPPRESSED_NOTE));
+    }
+}
@@ -0,0 +1,235 @@
+//! Select Options
+//!
+//! Option lists generated from state arrays:
+//!
+//! ```text
+//! <select bind:value={current}>
+//!   {options.map(o => <option value={o.id} selected={o.id === current}>{o.label}</option>)}
+//! </select>
+//! ```
+//!
+//! - `selected`, `checked` and `disabled` on an `<option>` or `<input>` in a loop
+//!   body bind as DOM properties (`prop:selected`): the attribute only sets the
+//!   default, and once the user has picked an option it no longer moves the selection
+//! - a loop directly in a `<select>` or `<optgroup>` over statically known data (see
+//!   `virtualize::StaticLoops`) has its options rendered at build time, after the
+//!   loop template, so the first paint shows the list with the right option selected
+//! - `bind:value` on the `<select>` is lowered when parsing (see `parse::bind_value`);
+//!   the transform marks the option matching the initial value as `selected`
+
+use std::collections::HashMap;
+
+use crate::transform::PROP_PREFIX;
+use crate::validate::{AttributeValue, TemplateNode, ZenIR};
+use crate::virtualize::{StaticLoops, VirtualLoop};
+
+/// Option and checkbox state that a loop item decides
+const ITEM_STATE_ATTRS: &[&str] = &["selected", "checked", "disabled"];
+
+/// Lowers option state in loops to property bindings, then renders the options of
+/// static `<select>` loops, keyed by loop source id. Virtualized loops render their
+/// own first chunk and are left alone.
+pub(crate) fn plan_select_options(
+    ir: &mut ZenIR,
+    virtual_loops: &HashMap<String, VirtualLoop>,
+) -> HashMap<String, Vec<Vec<TemplateNode>>> {
+    bind_item_state(&mut ir.template.nodes, false);
+
+    let loops = StaticLoops::new(ir);
+    let mut plans = HashMap::new();
+    plan(&loops, &ir.template.nodes, false, virtual_loops, &mut plans);
+    plans
+}
+
+fn bind_item_state(nodes: &mut [TemplateNode], in_loop: bool) {
+    for node in nodes {
+        match node {
+            TemplateNode::LoopFragment(lf) => bind_item_state(&mut lf.body, true),
+            TemplateNode::Element(el) => {
+                if in_loop && matches!(el.tag.to_lowercase().as_str(), "option" | "input") {
+                    for attr in &mut el.attributes {
+                        if matches!(attr.value, AttributeValue::Dynamic(_))
+                            && ITEM_STATE_ATTRS.contains(&attr.name.as_str())
+                        {
+                            attr.name = format!("{}{}", PROP_PREFIX, attr.name);
+                        }
+                    }
+                }
+                bind_item_state(&mut el.children, in_loop);
+            }
+            TemplateNode::Component(c) => bind_item_state(&mut c.children, in_loop),
+            TemplateNode::ConditionalFragment(cf) => {
+                bind_item_state(&mut cf.consequent, in_loop);
+                bind_item_state(&mut cf.alternate, in_loop);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children_mut() {
+                    bind_item_state(nodes, in_loop);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => bind_item_state(&mut of.fragment, in_loop),
+            _ => {}
+        }
+    }
+}
+
+fn plan(
+    loops: &StaticLoops,
+    nodes: &[TemplateNode],
+    in_select: bool,
+    virtual_loops: &HashMap<String, VirtualLoop>,
+    plans: &mut HashMap<String, Vec<Vec<TemplateNode>>>,
+) {
+    for node in nodes {
+        match node {
+            TemplateNode::LoopFragment(lf) => {
+                if in_select && !virtual_loops.contains_key(&lf.source) {
+                    let rendered = loops
+                        .static_items(&loops.code(&lf.source))
+                        .and_then(|items| {
+                            items
+                                .iter()
+                                .enumerate()
+                                .map(|(index, item)| loops.render_item(&lf.body, lf, item, index))
+                                .collect::<Option<Vec<_>>>()
+                        });
+                    if let Some(rendered) = rendered {
+                        plans.insert(lf.source.clone(), rendered);
+                    }
+                }
+                plan(loops, &lf.body, false, virtual_loops, plans);
+            }
+            TemplateNode::Element(el) => {
+                let options = matches!(el.tag.to_lowercase().as_str(), "select" | "optgroup");
+                plan(loops, &el.children, options, virtual_loops, plans);
+            }
+            // Fragments and components render wrapper elements, which a select drops
+            TemplateNode::Component(c) => plan(loops, &c.children, false, virtual_loops, plans),
+            TemplateNode::ConditionalFragment(cf) => {
+                plan(loops, &cf.consequent, false, virtual_loops, plans);
+                plan(loops, &cf.alternate, false, virtual_loops, plans);
+            }
+            TemplateNode::MultiBranchFragment(mb) => {
+                for nodes in mb.children() {
+                    plan(loops, nodes, false, virtual_loops, plans);
+                }
+            }
+            TemplateNode::OptionalFragment(of) => {
+                plan(loops, &of.fragment, false, virtual_loops, plans)
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(page: &str) -> Result<CompileResult, String> {
+        let options = CompileOptions {
+            ast_classifier: true,
+            ..Default::default()
+        };
+        compile_zen_internal(page, "page.zen", options)
+    }
+
+    const FRUIT: &str = "<script>\nstate current = \"b\"\nconst options = [{ id: \"a\", label: \"Apple\" }, { id: \"b\", label: \"Banana\" }]\n</script>\n";
+
+    #[test]
+    fn test_static_options_render_with_selection() {
+        let result = compile(&format!(
+            "{}<main><select>{{options.map(o => <option value={{o.id}} selected={{o.id === current}}>{{o.label}}</option>)}}</select></main>",
+            FRUIT
+        ))
+        .unwrap();
+        let html = &result.html;
+        assert!(
+            html.contains("</template><option value=\"a\">Apple</option><option value=\"b\" selected>Banana</option></select>"),
+            "{}",
+            html
+        );
+        // The template stays in place for hydration
+        assert!(
+            html.contains("<select><template data-zen-loop="),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn test_dynamic_options_bind_selected_as_property() {
+        let result = compile(
+            "<script>\nstate current = \"b\"\nfunction load() { return [] }\nstate options = load()\n</script>\n<main><select>{options.map(o => <option value={o.id} selected={o.id === current}>{o.label}</option>)}</select></main>",
+        )
+        .unwrap();
+        assert!(!result.html.contains("<option value"), "{}", result.html);
+        let binding = result
+            .bindings
+            .iter()
+            .find(|b| b.expression == "o.id === current")
+            .unwrap();
+        assert_eq!(binding.r#type, "property");
+        assert_eq!(binding.target, "selected");
+        assert!(binding.loop_context.is_some());
+    }
+
+    #[test]
+    fn test_bind_value_wires_property_and_change_handler() {
+        let result = compile(&format!(
+            "{}<main><select bind:value={{current}}>{{options.map(o => <option value={{o.id}}>{{o.label}}</option>)}}</select></main>",
+            FRUIT
+        ))
+        .unwrap();
+        let value = result
+            .bindings
+            .iter()
+            .find(|b| b.target == "value")
+            .unwrap();
+        assert_eq!(value.r#type, "property");
+        assert_eq!(value.expression, "current");
+        let change = result
+            .bindings
+            .iter()
+            .find(|b| b.target == "onchange")
+            .unwrap();
+        assert_eq!(change.expression, "(event) => current = event.target.value");
+
+        // The select has no value attribute; its initial option is marked instead
+        let html = &result.html;
+        assert!(!html.contains("<select value="), "{}", html);
+        assert!(
+            html.contains(
+                "<option value=\"a\">Apple</option><option value=\"b\" selected>Banana</option>"
+            ),
+            "{}",
+            html
+        );
+        let bundle = result.manifest.unwrap().bundle;
+        assert!(
+            bundle.contains("scope.state.current = event.target.value"),
+            "{}",
+            bundle
+        );
+    }
+
+    #[test]
+    fn test_bind_value_needs_a_select_and_a_state() {
+        let err = compile(
+            "<script>\nstate name = \"\"\n</script>\n<main><input bind:value={name} /></main>",
+        )
+        .unwrap_err();
+        assert!(err.contains("Z-ERR-BIND-VALUE"), "{}", err);
+
+        let err = compile(&format!(
+            "{}<main><select bind:value={{current.trim()}}></select></main>",
+            FRUIT
+        ))
+        .unwrap_err();
+        assert!(
+            err.contains("bind:value is supported on <select>"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! SSR Safety of Compile-Time Expressions
+//!
+//! `<head>` expressions and expressions under `zen:static` are evaluated at
+//! build time, where browser globals (`window`, `localStorage`, ...) do not
+//! exist. Such code used to fail evaluation quietly and render nothing. Before
+//! transform, every expression bound for compile-time evaluation is checked:
+//!
+//! - in `<head>`, reading a browser-only global is `Z-ERR-SSR-GLOBAL` and markup
+//!   (JSX) is `Z-ERR-SSR-JSX`; head content has no runtime fallback
+//! - under `zen:static`, reading a browser-only global is `Z-WARN-SSR-GLOBAL` and
+//!   the expression renders through its regular runtime binding instead
+//!
+//! The unsafe globals are the browser-only part of the renamer's `GLOBALS`.
+//! Component scope access (`window.__ZENITH_SCOPES__["inst0"]`) is resolved by
+//! static evaluation itself and is not a hazard.
+
+use lazy_static::lazy_static;
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{IdentifierReference, JSXElement, JSXFragment};
+use oxc_ast_visit::Visit;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+use regex::Regex;
+
+use crate::jsx_lowerer::SSR_UNSAFE_GLOBALS;
+use crate::validate::{ExpressionIR, TemplateNode, ZenIR};
+
+lazy_static! {
+    /// Scope access of a promoted component expression
+    static ref INSTANCE_SCOPE: Regex =
+        Regex::new(r#"window\.__ZENITH_SCOPES__\[[^\]]*\]"#).unwrap();
+}
+
+/// What keeps an expression from being evaluated at compile time
+#[derive(Debug, Default, PartialEq)]
+struct Hazards {
+    /// Browser-only globals read, in first-seen order
+    globals: Vec<String>,
+    jsx: bool,
+}
+
+impl<'a> Visit<'a> for Hazards {
+    fn visit_identifier_reference(&mut self, ident: &IdentifierReference<'a>) {
+        let name = ident.name.as_str();
+        if SSR_UNSAFE_GLOBALS.contains(&name) && !self.globals.iter().any(|g| g == name) {
+            self.globals.push(name.to_string());
+        }
+    }
+
+    fn visit_jsx_element(&mut self, _element: &JSXElement<'a>) {
+        self.jsx = true;
+    }
+
+    fn visit_jsx_fragment(&mut self, _fragment: &JSXFragment<'a>) {
+        self.jsx = true;
+    }
+}
+
+fn hazards(code: &str) -> Hazards {
+    let code = INSTANCE_SCOPE.replace_all(code, "scope");
+    let allocator = Allocator::default();
+    let mut hazards = Hazards::default();
+    // Code oxc rejects is reported elsewhere
+    if let Ok(expr) = Parser::new(&allocator, &code, SourceType::tsx()).parse_expression() {
+        hazards.visit_expression(&expr);
+    }
+    hazards
+}
+
+/// Whether `code` can be evaluated at compile time without a browser
+pub(crate) fn is_ssr_safe(code: &str) -> bool {
+    hazards(code) == Hazards::default()
+}
+
+/// Browser-only globals `code` reads, in first-seen order
+pub(crate) fn browser_globals(code: &str) -> Vec<String> {
+    hazards(code).globals
+}
+
+/// Check the expressions that are evaluated at compile time. Head errors are
+/// returned; `zen:static` warnings are added to `ir.warnings`.
+pub(crate) fn check_compile_time_expressions(ir: &mut ZenIR) -> Vec<String> {
+    let mut check = Check {
+        expressions: &ir.template.expressions,
+        file_path: &ir.file_path,
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    };
+    check.visit(&ir.template.nodes, false, false);
+    let Check {
+        errors, warnings, ..
+    } = check;
+    ir.warnings.extend(warnings);
+    errors
+}
+
+struct Check<'a> {
+    expressions: &'a [ExpressionIR],
+    file_path: &'a str,
+    errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl Check<'_> {
+    fn visit(&mut self, nodes: &[TemplateNode], in_head: bool, frozen: bool) {
+        for node in nodes {
+            match node {
+                TemplateNode::Expression(e) => {
+                    if let Some(expr) = self.expressions.iter().find(|x| x.id == e.expression) {
+                        self.check(expr, in_head || e.is_in_head, frozen);
+                    }
+                }
+                TemplateNode::Element(el) => {
+                    let in_head = in_head || el.tag.eq_ignore_ascii_case("head");
+                    let frozen = frozen
+                        || el
+                            .attributes
+                            .iter()
+                            .any(|a| a.name == crate::transform::STATIC_ATTR);
+                    self.visit(&el.children, in_head, frozen);
+                }
+                TemplateNode::Component(c) => self.visit(&c.children, in_head, frozen),
+                TemplateNode::ConditionalFragment(cf) => {
+                    self.visit(&cf.consequent, in_head, frozen);
+                    self.visit(&cf.alternate, in_head, frozen);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    for nodes in mb.children() {
+                        self.visit(nodes, in_head, frozen);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => self.visit(&of.fragment, in_head, frozen),
+                TemplateNode::LoopFragment(lf) => self.visit(&lf.body, in_head, frozen),
+                _ => {}
+            }
+        }
+    }
+
+    fn check(&mut self, expr: &ExpressionIR, in_head: bool, frozen: bool) {
+        if !in_head && !frozen {
+            return;
+        }
+        let hazards = hazards(&expr.code);
+        let place = match &expr.origin {
+            Some(origin) => format!("in {}", origin),
+            None => format!(
+                "at {} (line {}, column {})",
+                self.file_path, expr.location.line, expr.location.column
+            ),
+        };
+        let globals = hazards
+            .globals
+            .iter()
+            .map(|g| format!("`{}`", g))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        if in_head {
+            if !hazards.globals.is_empty() {
+                self.errors.push(format!(
+                    "Z-ERR-SSR-GLOBAL: <head> expression `{{{}}}` {} reads {}, which only exists in the browser. Head content is rendered at build time; compute the value from props or the page script.",
+                    expr.code.trim(), place, globals
+                ));
+            }
+            if hazards.jsx {
+                self.errors.push(format!(
+                    "Z-ERR-SSR-JSX: <head> expression `{{{}}}` {} renders markup. Head content is rendered at build time and must be text.",
+                    expr.code.trim(), place
+                ));
+            }
+        } else if !hazards.globals.is_empty() {
+            self.warnings.push(format!(
+                "Z-WARN-SSR-GLOBAL: zen:static expression `{{{}}}` {} reads {}, which only exists in the browser; it renders at runtime instead of being folded at build time.",
+                expr.code.trim(), place, globals
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(page: &str) -> CompileResult {
+        compile_zen_internal(page, "page.zen", CompileOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_head_expression_reading_browser_global_errors() {
+        let result = compile(
+            "<html><head><title>{localStorage.getItem('title')}</title></head><body><p>Hi</p></body></html>",
+        );
+        assert!(result.has_errors);
+        let error = result
+            .errors
+            .iter()
+            .find(|e| e.starts_with("Z-ERR-SSR-GLOBAL"))
+            .unwrap_or_else(|| panic!("{:?}", result.errors));
+        assert!(
+            error.contains("`{localStorage.getItem('title')}` at page.zen (line")
+                && error.contains("reads `localStorage`, which only exists in the browser"),
+            "{}",
+            error
+        );
+        // Not evaluated: no compile-error marker in the rendered title
+        assert!(result.html.contains("<title></title>"), "{}", result.html);
+    }
+
+    #[test]
+    fn test_static_body_expression_falls_back_to_binding() {
+        let result = compile("<main zen:static><p>{window.innerWidth}</p></main>");
+        assert!(
+            result.errors.iter().all(|e| !e.contains("SSR")),
+            "{:?}",
+            result.errors
+        );
+        let warning = result
+            .warnings
+            .iter()
+            .find(|w| w.starts_with("Z-WARN-SSR-GLOBAL"))
+            .unwrap_or_else(|| panic!("{:?}", result.warnings));
+        assert!(warning.contains("reads `window`"), "{}", warning);
+        assert!(result.html.contains("<!--zen:"), "{}", result.html);
+    }
+
+    #[test]
+    fn test_hazard_detection() {
+        assert!(!is_ssr_safe("navigator.language || 'en'"));
+        assert!(!is_ssr_safe("<b>{title}</b>"));
+        assert!(is_ssr_safe("`${title} | Docs`"));
+        // Promoted component expressions reach their scope through `window`
+        assert!(is_ssr_safe(
+            "window.__ZENITH_SCOPES__[\"inst0\"].props.title"
+        ));
+        // Object keys are not references
+        assert!(is_ssr_safe("({ document: 1 }).document"));
+        assert!(SSR_UNSAFE_GLOBALS
+            .iter()
+            .all(|g| crate::jsx_lowerer::GLOBALS.contains(g)));
+
+        let result = compile("<html><head><title>{<b>x</b>}</title></head><body></body></html>");
+        assert!(
+            result.errors.iter().any(|e| e.starts_with("Z-ERR-SSR-JSX")),
+            "{:?}",
+            result.errors
+        );
+    }
+}
@@ -5,6 +5,42 @@
 
 use std::collections::HashMap;
 
+/// Functions known to be free of side effects when called during render
+pub const PURE_FUNCTIONS: &[&str] = &[
+    "String",
+    "Number",
+    "Boolean",
+    "BigInt",
+    "Symbol",
+    "parseInt",
+    "parseFloat",
+    "isNaN",
+    "isFinite",
+    "encodeURI",
+    "encodeURIComponent",
+    "decodeURI",
+    "decodeURIComponent",
+    "zenFilter",
+    "t",
+    "$t",
+    "cn",
+    "clsx",
+    "classNames",
+];
+
+/// Naming conventions treated as pure (`formatDate`, `getTotal`, `isActive`, `toLabel`, ...)
+const PURE_PREFIXES: &[&str] = &["format", "get", "is", "has", "to", "compute", "calc"];
+
+/// Whether a bare function call may safely run on every render
+pub fn is_pure_function(name: &str) -> bool {
+    PURE_FUNCTIONS.contains(&name)
+        || PURE_PREFIXES.iter().any(|prefix| {
+            name.strip_prefix(prefix)
+                .and_then(|rest| rest.chars().next())
+                .is_some_and(|c| c.is_ascii_uppercase())
+        })
+}
+
 /// Try to evaluate an expression to a static string value.
 /// Returns Some(resolved_string) if successful, None if the expression
 /// cannot be statically resolved.
@@ -96,6 +132,16 @@ pub fn static_eval(expr: &str, props: &HashMap<String, String>) -> Option<String
     None
 }
 
+/// Static string values of initial state declarations (raw JS initializers).
+/// Used to evaluate head expressions against the value they render with.
+pub fn initial_state_values(all_states: &HashMap<String, String>) -> HashMap<String, String> {
+    let empty = HashMap::new();
+    all_states
+        .iter()
+        .filter_map(|(name, init)| static_eval(init, &empty).map(|v| (name.clone(), v)))
+        .collect()
+}
+
 /// Try to parse a string literal (single, double, or backtick quoted)
 fn try_parse_string_literal(s: &str) -> Option<String> {
     let trimmed = s.trim();
@@ -105,13 +151,17 @@ fn try_parse_string_literal(s: &str) -> Option<String> {
     {
         // Remove quotes and unescape basic sequences
         let inner = &trimmed[1..trimmed.len() - 1];
+        // `'a' + 'b'` starts and ends with a quote but is not one literal
+        if closes_early(inner, trimmed.as_bytes()[0]) {
+            return None;
+        }
         return Some(unescape_string(inner));
     }
 
     // Template literal without interpolations
     if trimmed.starts_with('`') && trimmed.ends_with('`') {
         let inner = &trimmed[1..trimmed.len() - 1];
-        if !inner.contains("${") {
+        if !inner.contains("${") && !closes_early(inner, b'`') {
             return Some(unescape_string(inner));
         }
     }
@@ -119,6 +169,20 @@ fn try_parse_string_literal(s: &str) -> Option<String> {
     None
 }
 
+/// Whether the body of a quoted literal contains its unescaped closing quote
+fn closes_early(inner: &str, quote: u8) -> bool {
+    let mut escaped = false;
+    for byte in inner.bytes() {
+        match byte {
+            _ if escaped => escaped = false,
+            b'\\' => escaped = true,
+            b if b == quote => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
 /// Unescape basic string escape sequences
 fn unescape_string(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
@@ -283,25 +347,8 @@ fn try_resolve_template_literal(expr: &str, props: &HashMap<String, String>) ->
 }
 
 /// Check if a string is a valid JavaScript identifier
-fn is_valid_identifier(s: &str) -> bool {
-    if s.is_empty() {
-        return false;
-    }
-
-    let mut chars = s.chars();
-
-    match chars.next() {
-        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {}
-        _ => return false,
-    }
-
-    for c in chars {
-        if !c.is_ascii_alphanumeric() && c != '_' && c != '$' {
-            return false;
-        }
-    }
-
-    true
+pub(crate) fn is_valid_identifier(s: &str) -> bool {
+    oxc_syntax::identifier::is_identifier_name(s)
 }
 
 #[cfg(test)]
@@ -0,0 +1,343 @@
+//! Debug Statement Stripping
+//!
+//! With `CompileOptions.strip_debug`, codegen removes debugging leftovers from the
+//! page script (component scripts are merged into it) and from every template
+//! expression, after renaming:
+//!
+//! - `console.<method>(...)` statements, unless the method is in `keep`
+//! - `debugger` statements
+//!
+//! A console call in expression position evaluates to `undefined`, so it becomes
+//! `void 0` and the surrounding expression is simplified: `console.log(x) || value`
+//! is `value`, `console.log(x) && value` is `void 0`, and `(console.log(x), value)`
+//! is `value`. Arguments are dropped with the call, as with any minifier's
+//! `drop_console`. `zenAssert(...)` calls are removed the same way when `asserts`
+//! is set. The number of removed calls and statements is reported as
+//! `manifest.strippedDebugStatements`.
+
+use oxc_allocator::Vec as ArenaVec;
+use oxc_ast::ast::{Expression, Program, Statement};
+use oxc_ast::AstBuilder;
+use oxc_ast_visit::{walk_mut, VisitMut};
+use oxc_span::{GetSpan, SPAN};
+use oxc_syntax::number::NumberBase;
+use oxc_syntax::operator::{LogicalOperator, UnaryOperator};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+/// What `strip_debug` removes. The default removes nothing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(default, rename_all = "camelCase")]
+pub struct StripDebug {
+    /// Remove `console.<method>(...)` calls
+    pub console: bool,
+    /// Remove `debugger` statements
+    pub debugger: bool,
+    /// Console methods to keep (e.g. `["error", "warn"]`)
+    pub keep: Vec<String>,
+    /// Remove `zenAssert(...)` calls (see `zen_assert.rs`)
+    pub asserts: bool,
+}
+
+impl StripDebug {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.console || self.debugger || self.asserts
+    }
+}
+
+pub(crate) struct DebugStripper<'a, 'o> {
+    ast: AstBuilder<'a>,
+    options: &'o StripDebug,
+    /// Console calls and `debugger` statements removed so far
+    pub stripped: u32,
+}
+
+impl<'a, 'o> DebugStripper<'a, 'o> {
+    pub(crate) fn new(ast: AstBuilder<'a>, options: &'o StripDebug) -> Self {
+        Self {
+            ast,
+            options,
+            stripped: 0,
+        }
+    }
+
+    /// Strip a template expression parsed as a program: its top-level expression
+    /// is replaced rather than removed, so the expression keeps a value
+    pub(crate) fn strip_expression(&mut self, program: &mut Program<'a>) {
+        for stmt in program.body.iter_mut() {
+            match stmt {
+                Statement::ExpressionStatement(s) => self.visit_expression(&mut s.expression),
+                _ => self.visit_statement(stmt),
+            }
+        }
+    }
+
+    fn is_console_call(&self, expr: &Expression<'a>) -> bool {
+        let Expression::CallExpression(call) = expr.without_parentheses() else {
+            return false;
+        };
+        if self.options.asserts
+            && matches!(&call.callee, Expression::Identifier(f) if f.name == crate::zen_assert::ASSERT_FN)
+        {
+            return true;
+        }
+        if !self.options.console {
+            return false;
+        }
+        let Expression::StaticMemberExpression(member) = call.callee.without_parentheses() else {
+            return false;
+        };
+        matches!(&member.object, Expression::Identifier(object) if object.name == "console")
+            && !self
+                .options
+                .keep
+                .iter()
+                .any(|m| m == member.property.name.as_str())
+    }
+
+    fn is_strippable(&self, stmt: &Statement<'a>) -> bool {
+        match stmt {
+            Statement::DebuggerStatement(_) => self.options.debugger,
+            Statement::ExpressionStatement(s) => self.is_console_call(&s.expression),
+            _ => false,
+        }
+    }
+
+    fn undefined(&self) -> Expression<'a> {
+        self.ast.expression_unary(
+            SPAN,
+            UnaryOperator::Void,
+            self.ast
+                .expression_numeric_literal(SPAN, 0.0, None, NumberBase::Decimal),
+        )
+    }
+}
+
+/// Strip a parsed script; returns the number of removed calls and statements
+pub(crate) fn strip_program<'a>(
+    allocator: &'a oxc_allocator::Allocator,
+    program: &mut Program<'a>,
+    options: &StripDebug,
+) -> u32 {
+    if !options.is_enabled() {
+        return 0;
+    }
+    let mut stripper = DebugStripper::new(AstBuilder::new(allocator), options);
+    stripper.visit_program(program);
+    stripper.stripped
+}
+
+/// Template expression source with debugging removed, and the number of removals.
+/// `None` when there is nothing to strip, so untouched expressions keep their source.
+pub(crate) fn strip_expression_code(code: &str, options: &StripDebug) -> Option<(String, u32)> {
+    if !options.is_enabled()
+        || !(code.contains("console")
+            || code.contains("debugger")
+            || code.contains(crate::zen_assert::ASSERT_FN))
+    {
+        return None;
+    }
+    let allocator = oxc_allocator::Allocator::default();
+    let parsed = oxc_parser::Parser::new(&allocator, code, oxc_span::SourceType::tsx()).parse();
+    if !parsed.errors.is_empty() {
+        return None;
+    }
+    let mut program = parsed.program;
+    let mut stripper = DebugStripper::new(AstBuilder::new(&allocator), options);
+    stripper.strip_expression(&mut program);
+    (stripper.stripped > 0).then(|| {
+        let code = oxc_codegen::Codegen::new().build(&program).code;
+        (
+            code.trim().trim_end_matches(';').to_string(),
+            stripper.stripped,
+        )
+    })
+}
+
+fn is_undefined(expr: &Expression) -> bool {
+    matches!(expr, Expression::UnaryExpression(u)
+        if u.operator == UnaryOperator::Void && matches!(u.argument, Expression::NumericLiteral(_)))
+}
+
+impl<'a> VisitMut<'a> for DebugStripper<'a, '_> {
+    fn visit_statements(&mut self, stmts: &mut ArenaVec<'a, Statement<'a>>) {
+        let before = stmts.len();
+        stmts.retain(|stmt| !self.is_strippable(stmt));
+        self.stripped += (before - stmts.len()) as u32;
+        walk_mut::walk_statements(self, stmts);
+    }
+
+    /// Single-statement positions (`if (x) console.log(x)`) keep an empty statement
+    fn visit_statement(&mut self, stmt: &mut Statement<'a>) {
+        if self.is_strippable(stmt) {
+            *stmt = self.ast.statement_empty(stmt.span());
+            self.stripped += 1;
+            return;
+        }
+        walk_mut::walk_statement(self, stmt);
+    }
+
+    fn visit_expression(&mut self, expr: &mut Expression<'a>) {
+        if self.is_console_call(expr) {
+            *expr = self.undefined();
+            self.stripped += 1;
+            return;
+        }
+        walk_mut::walk_expression(self, expr);
+
+        let simplified = match expr {
+            Expression::LogicalExpression(logical) if is_undefined(&logical.left) => {
+                Some(match logical.operator {
+                    LogicalOperator::Or | LogicalOperator::Coalesce => {
+                        std::mem::replace(&mut logical.right, self.undefined())
+                    }
+                    LogicalOperator::And => self.undefined(),
+                })
+            }
+            Expression::SequenceExpression(seq) => {
+                let last = seq.expressions.len().saturating_sub(1);
+                let mut index = 0;
+                seq.expressions.retain(|e| {
+                    index += 1;
+                    index - 1 == last || !is_undefined(e)
+                });
+                (seq.expressions.len() == 1)
+                    .then(|| std::mem::replace(&mut seq.expressions[0], self.undefined()))
+            }
+            _ => None,
+        };
+        if let Some(simplified) = simplified {
+            *expr = simplified;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxc_allocator::Allocator;
+    use oxc_codegen::Codegen;
+    use oxc_parser::Parser;
+    use oxc_span::SourceType;
+
+    fn strip(code: &str, options: &StripDebug, expression: bool) -> (String, u32) {
+        let allocator = Allocator::default();
+        let mut program = Parser::new(&allocator, code, SourceType::tsx())
+            .parse()
+            .program;
+        let mut stripper = DebugStripper::new(AstBuilder::new(&allocator), options);
+        if expression {
+            stripper.strip_expression(&mut program);
+        } else {
+            stripper.visit_program(&mut program);
+        }
+        (Codegen::new().build(&program).code, stripper.stripped)
+    }
+
+    fn all() -> StripDebug {
+        StripDebug {
+            console: true,
+            debugger: true,
+            keep: vec![],
+            asserts: true,
+        }
+    }
+
+    #[test]
+    fn test_statements_are_removed() {
+        let (code, stripped) = strip(
+            "console.log('start');\nfunction save() { console.info(data); debugger; send(data); }\nif (ready) console.debug(ready);",
+            &all(),
+            false,
+        );
+        assert!(
+            !code.contains("console") && !code.contains("debugger"),
+            "{}",
+            code
+        );
+        assert!(
+            code.contains("send(data)") && code.contains("if (ready)"),
+            "{}",
+            code
+        );
+        assert_eq!(stripped, 4);
+    }
+
+    #[test]
+    fn test_expression_position_keeps_value() {
+        let (code, _) = strip("console.log(x) || value", &all(), true);
+        assert_eq!(code.trim(), "value;");
+        let (code, _) = strip("(console.log(x), value)", &all(), true);
+        assert_eq!(code.trim(), "value;");
+        let (code, _) = strip("console.log(x)", &all(), true);
+        assert_eq!(code.trim(), "void 0;");
+        // Handlers keep their other statements
+        let (code, stripped) = strip("() => { console.log(x); count++; }", &all(), true);
+        assert!(
+            code.contains("count++") && !code.contains("console"),
+            "{}",
+            code
+        );
+        assert_eq!(stripped, 1);
+    }
+
+    #[test]
+    fn test_keep_list_and_disabled_kinds() {
+        let options = StripDebug {
+            keep: vec!["error".to_string(), "warn".to_string()],
+            ..all()
+        };
+        let (code, stripped) = strip(
+            "console.error(e); console.warn(w); console.log(l);",
+            &options,
+            false,
+        );
+        assert!(code.contains("console.error(e)") && code.contains("console.warn(w)"));
+        assert!(!code.contains("console.log"), "{}", code);
+        assert_eq!(stripped, 1);
+
+        let debugger_only = StripDebug {
+            debugger: true,
+            ..Default::default()
+        };
+        let (code, stripped) = strip("debugger; console.log(l);", &debugger_only, false);
+        assert!(!code.contains("debugger") && code.contains("console.log(l)"));
+        assert_eq!(stripped, 1);
+    }
+
+    #[test]
+    fn test_compile_strips_bundle_and_counts() {
+        let page = "<script>\nstate count = 0\nconsole.log('boot')\n</script>\n<button onclick={() => { console.log(count); count++ }}>{console.log(count) || count}</button>";
+        let compile = |strip_debug: StripDebug| {
+            crate::parse::compile_zen_internal(
+                page,
+                "page.zen",
+                crate::parse::CompileOptions {
+                    strip_debug,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .manifest
+            .unwrap()
+        };
+
+        let manifest = compile(all());
+        // The bundle scaffold logs with console.error/warn only
+        assert!(
+            !manifest.bundle.contains("console.log"),
+            "{}",
+            manifest.bundle
+        );
+        assert!(manifest.bundle.contains("count++"), "{}", manifest.bundle);
+        assert_eq!(manifest.stripped_debug_statements, 3);
+
+        let manifest = compile(StripDebug::default());
+        assert!(manifest.bundle.contains("console.log"));
+        assert_eq!(manifest.stripped_debug_statements, 0);
+    }
+}
@@ -0,0 +1,285 @@
+//! Style Chunks
+//!
+//! The page CSS split so shared component styles can be cached site-wide. Styles
+//! are walked in cascade order (`global_styles::document_order`) and contiguous
+//! runs with the same owner form one chunk:
+//!
+//! - a component listed as shared (`CompileOptions.shared_components`, or used by
+//!   more than one page of a `compile_zen_batch_internal` batch) gets its own
+//!   chunk, `shared: true`, named after the component file. Its content, and so
+//!   its hash, is the same on every page.
+//! - everything else (the page's own styles and components only this page uses)
+//!   goes into page chunks named after the page file.
+//!
+//! Chunks are listed in `order`. Concatenating them in that order, joined by a
+//! newline, is exactly the page's single-file CSS (`ZenManifestExport.styles`),
+//! so a bundler may link them as separate stylesheets or inline them without
+//! changing the cascade.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+use crate::validate::StyleIR;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi(object))]
+pub struct StyleChunk {
+    /// Component file stem for shared chunks (`.global` for its global blocks),
+    /// page file stem otherwise (`-2`, `-3`, ... when a shared chunk splits the page's)
+    pub name: String,
+    /// First 8 hex characters of the SHA-256 of `css`
+    pub hash: String,
+    pub css: String,
+    /// Source component paths of the styles in the chunk (the page's own have none)
+    pub components: Vec<String>,
+    /// Emit once for the site and reference it from every page
+    pub shared: bool,
+    /// Position in the cascade: chunks apply in ascending order
+    pub order: u32,
+}
+
+/// Who a run of styles belongs to
+#[derive(PartialEq)]
+enum Owner<'s> {
+    Shared { component: &'s str, global: bool },
+    Page,
+}
+
+/// Split cascade-ordered styles into chunks (see module docs)
+pub(crate) fn chunk_styles(
+    styles: &[StyleIR],
+    page_path: &str,
+    shared: &HashSet<String>,
+) -> Vec<StyleChunk> {
+    let mut runs: Vec<(Owner, Vec<&StyleIR>)> = Vec::new();
+    for style in styles {
+        let owner = if shared.contains(&style.source_component) {
+            Owner::Shared {
+                component: &style.source_component,
+                global: style.global,
+            }
+        } else {
+            Owner::Page
+        };
+        match runs.last_mut() {
+            Some((last, members)) if *last == owner => members.push(style),
+            _ => runs.push((owner, vec![style])),
+        }
+    }
+
+    let mut page_runs = 0;
+    runs.into_iter()
+        .enumerate()
+        .map(|(order, (owner, members))| {
+            let (name, is_shared) = match owner {
+                Owner::Shared { component, global } => {
+                    let stem = file_stem(component);
+                    if global {
+                        (format!("{}.global", stem), true)
+                    } else {
+                        (stem.to_string(), true)
+                    }
+                }
+                Owner::Page => {
+                    page_runs += 1;
+                    let stem = file_stem(page_path);
+                    if page_runs == 1 {
+                        (stem.to_string(), false)
+                    } else {
+                        (format!("{}-{}", stem, page_runs), false)
+                    }
+                }
+            };
+            let css = members
+                .iter()
+                .map(|s| s.raw.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let mut components: Vec<String> = Vec::new();
+            for member in &members {
+                if !member.source_component.is_empty()
+                    && !components.contains(&member.source_component)
+                {
+                    components.push(member.source_component.clone());
+                }
+            }
+            StyleChunk {
+                name,
+                hash: content_hash(&css),
+                css,
+                components,
+                shared: is_shared,
+                order: order as u32,
+            }
+        })
+        .collect()
+}
+
+/// Components whose styles more than one page of a batch carries
+pub(crate) fn shared_across_pages(pages: &[Vec<StyleIR>]) -> HashSet<String> {
+    let mut seen = HashSet::new();
+    let mut shared = HashSet::new();
+    for styles in pages {
+        let components: HashSet<&String> = styles
+            .iter()
+            .map(|s| &s.source_component)
+            .filter(|c| !c.is_empty())
+            .collect();
+        for component in components {
+            if !seen.insert(component.clone()) {
+                shared.insert(component.clone());
+            }
+        }
+    }
+    shared
+}
+
+fn content_hash(css: &str) -> String {
+    let digest = Sha256::digest(css.as_bytes());
+    format!("{:x}", digest)[..8].to_string()
+}
+
+fn file_stem(path: &str) -> &str {
+    std::path::Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(raw: &str, component: &str, global: bool) -> StyleIR {
+        StyleIR {
+            raw: raw.to_string(),
+            global,
+            source_component: component.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_unshared_styles_form_one_page_chunk() {
+        let styles = vec![
+            style("body { margin: 0; }", "", false),
+            style(".card { padding: 1rem; }", "components/Card.zen", false),
+        ];
+        let chunks = chunk_styles(&styles, "pages/index.zen", &HashSet::new());
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].name, "index");
+        assert!(!chunks[0].shared);
+        assert_eq!(chunks[0].components, vec!["components/Card.zen"]);
+        assert_eq!(
+            chunks[0].css,
+            "body { margin: 0; }\n.card { padding: 1rem; }"
+        );
+    }
+
+    #[test]
+    fn test_shared_component_splits_page_runs_in_order() {
+        let styles = vec![
+            style(
+                "* { box-sizing: border-box; }",
+                "components/Button.zen",
+                true,
+            ),
+            style("h1 { color: red; }", "", false),
+            style(".btn { color: blue; }", "components/Button.zen", false),
+            style(".card { padding: 1rem; }", "components/Card.zen", false),
+        ];
+        let shared = HashSet::from(["components/Button.zen".to_string()]);
+        let chunks = chunk_styles(&styles, "about.zen", &shared);
+        let names: Vec<&str> = chunks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Button.global", "about", "Button", "about-2"]);
+        assert_eq!(
+            chunks.iter().map(|c| c.order).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+        assert!(chunks[0].shared && chunks[2].shared);
+        assert!(!chunks[1].shared && !chunks[3].shared);
+
+        let cascade: Vec<&str> = chunks.iter().map(|c| c.css.as_str()).collect();
+        let single: Vec<&str> = styles.iter().map(|s| s.raw.as_str()).collect();
+        assert_eq!(cascade.join("\n"), single.join("\n"));
+    }
+
+    #[test]
+    fn test_shared_across_pages_counts_each_page_once() {
+        let button = style(".btn {}", "components/Button.zen", false);
+        let card = style(".card {}", "components/Card.zen", false);
+        let pages = vec![
+            vec![button.clone(), button.clone(), card],
+            vec![button, style("p {}", "", false)],
+        ];
+        assert_eq!(
+            shared_across_pages(&pages),
+            HashSet::from(["components/Button.zen".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_batch_pages_share_one_button_chunk() {
+        use crate::component::ComponentIR;
+        use crate::parse::{compile_zen_batch_internal, CompileOptions};
+
+        let mut components = std::collections::HashMap::new();
+        for (name, path, source) in [
+            (
+                "Button",
+                "components/Button.zen",
+                "<button class=\"btn\">Go</button>\n<style>\n.btn { color: blue; }\n</style>",
+            ),
+            (
+                "Hero",
+                "components/Hero.zen",
+                "<header class=\"hero\">Hi</header>\n<style>\n.hero { height: 50vh; }\n</style>",
+            ),
+        ] {
+            let component = ComponentIR::from_source(name, path, source).unwrap();
+            components.insert(name.to_string(), serde_json::to_value(component).unwrap());
+        }
+        let options = CompileOptions {
+            components,
+            ..Default::default()
+        };
+        let home = "<main><Hero /><Button /></main>\n<style>\nmain { padding: 1rem; }\n</style>";
+        let about = "<section><Button /></section>\n<style>\nsection { margin: 0; }\n</style>";
+        let results =
+            compile_zen_batch_internal(&[(home, "index.zen"), (about, "about.zen")], options)
+                .unwrap();
+        let (home, about) = (results[0].as_ref().unwrap(), results[1].as_ref().unwrap());
+
+        let shared = |chunks: &[StyleChunk]| -> Vec<StyleChunk> {
+            chunks.iter().filter(|c| c.shared).cloned().collect()
+        };
+        assert_eq!(shared(&home.style_chunks).len(), 1);
+        assert_eq!(shared(&home.style_chunks), shared(&about.style_chunks));
+        assert_eq!(shared(&home.style_chunks)[0].name, "Button");
+
+        // Hero is only on the home page, so it stays in a page chunk
+        let page_css = |chunks: &[StyleChunk]| -> String {
+            chunks
+                .iter()
+                .filter(|c| !c.shared)
+                .map(|c| c.css.clone())
+                .collect()
+        };
+        assert!(page_css(&home.style_chunks).contains(".hero"));
+        assert!(!page_css(&about.style_chunks).contains(".hero"));
+        assert_ne!(page_css(&home.style_chunks), page_css(&about.style_chunks));
+
+        // The documented order reproduces the single-file stylesheet
+        for result in [home, about] {
+            let mut chunks = result.style_chunks.clone();
+            chunks.sort_by_key(|c| c.order);
+            let css: Vec<&str> = chunks.iter().map(|c| c.css.as_str()).collect();
+            assert_eq!(css.join("\n"), result.manifest.as_ref().unwrap().styles);
+        }
+    }
+}
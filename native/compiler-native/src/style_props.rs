@@ -0,0 +1,447 @@
+//! Prop Interpolation in Component Styles
+//!
+//! A component's `<style>` may read its props in declaration values:
+//!
+//! ```text
+//! prop color = "teal"
+//! <style> .badge { background: {color}; } </style>
+//! ```
+//!
+//! Each `{prop}` becomes a custom property namespaced by the component's scope
+//! id (`css_scope::scope_id`), with the prop's literal default as the fallback:
+//! `background: var(--zen-<id>-color, teal)`. Every instance sets the custom
+//! properties on the root elements of its template: a static prop is written
+//! into their `style` attribute, a dynamic one becomes a `style` binding that
+//! reads the instance's props, with the value it starts with server-rendered
+//! when that is known at compile time. Props the usage does not pass are left
+//! unset, so the fallback applies.
+//!
+//! An interpolation must name a declared prop and sit in a declaration value;
+//! anything else (selectors, at-rule preludes, property names) is
+//! `Z-ERR-STYLE-INTERPOLATION`. So is one joined to adjacent text (`{size}px`):
+//! `var()` substitutes a whole token, so the unit belongs in the prop value or
+//! in `calc({size} * 1px)`.
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::component::ComponentIR;
+use crate::validate::{
+    AttributeIR, AttributeValue, ExpressionIR, ExpressionOrigin, SourceLocation, TemplateNode,
+};
+
+pub const STYLE_INTERPOLATION_ERROR: &str = "Z-ERR-STYLE-INTERPOLATION";
+
+lazy_static! {
+    static ref INTERPOLATION: Regex = Regex::new(r"\{\s*([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)\s*\}").unwrap();
+    /// `prop name = <literal>` (group 2 is the default)
+    static ref PROP_DEFAULT: Regex = Regex::new(
+        r#"(?m)^\s*prop\s+([\p{ID_Start}_$][\p{ID_Continue}$\x{200C}\x{200D}]*)\s*(?::[^=;\n]+)?=\s*("[^"\n]*"|'[^'\n]*'|-?\d+(?:\.\d+)?)\s*;?\s*$"#
+    )
+    .unwrap();
+}
+
+/// Custom property carrying `prop` for the component's styles
+fn custom_property(comp: &ComponentIR, prop: &str) -> String {
+    format!("--zen-{}-{}", crate::css_scope::scope_id(&comp.path), prop)
+}
+
+/// Props the component's styles interpolate, in first-seen order
+pub(crate) fn interpolated_props(comp: &ComponentIR) -> Vec<String> {
+    let mut props: Vec<String> = Vec::new();
+    for style in &comp.styles {
+        for caps in INTERPOLATION.captures_iter(style) {
+            if !props.iter().any(|p| p == &caps[1]) {
+                props.push(caps[1].to_string());
+            }
+        }
+    }
+    props
+}
+
+/// The component's styles with each interpolation replaced by its custom property,
+/// plus `Z-ERR-STYLE-INTERPOLATION` errors
+pub(crate) fn rewrite_styles(comp: &ComponentIR) -> (Vec<String>, Vec<String>) {
+    let declared = crate::component::declared_props(comp);
+    let defaults = prop_defaults(comp.script.as_deref().unwrap_or(""));
+    let mut errors = Vec::new();
+    let styles = comp
+        .styles
+        .iter()
+        .map(|css| {
+            // Interpolations masked, so their braces do not read as blocks
+            let masked: String = INTERPOLATION
+                .replace_all(css, |caps: &regex::Captures| "_".repeat(caps[0].len()))
+                .into_owned();
+            INTERPOLATION
+                .replace_all(css, |caps: &regex::Captures| {
+                    let whole = caps.get(0).unwrap();
+                    let prop = &caps[1];
+                    if let Some(place) = outside_value(&masked, whole.start(), whole.end()) {
+                        errors.push(format!(
+                            "{}: `{}` in {} of a <style> block in {}; style interpolation is only supported in declaration values.",
+                            STYLE_INTERPOLATION_ERROR,
+                            whole.as_str(),
+                            place,
+                            comp.path
+                        ));
+                        return whole.as_str().to_string();
+                    }
+                    if !declared.contains(prop) {
+                        let mut props: Vec<&String> = declared.iter().collect();
+                        props.sort();
+                        let list = if props.is_empty() {
+                            "none".to_string()
+                        } else {
+                            props.iter().map(|p| format!("`{}`", p)).collect::<Vec<_>>().join(", ")
+                        };
+                        errors.push(format!(
+                            "{}: `{}` in a <style> block of {} is not a declared prop (declared props: {}).",
+                            STYLE_INTERPOLATION_ERROR,
+                            whole.as_str(),
+                            comp.path,
+                            list
+                        ));
+                        return whole.as_str().to_string();
+                    }
+                    if let Some(joined) = joined_token(css, whole.start(), whole.end()) {
+                        let fix = match joined.strip_prefix(whole.as_str()) {
+                            Some(unit) => format!(
+                                "Put the unit in the prop value or write `calc({} * 1{})`",
+                                whole.as_str(),
+                                unit
+                            ),
+                            None => "Pass the whole value as the prop".to_string(),
+                        };
+                        errors.push(format!(
+                            "{}: `{}` in a <style> block of {} joins the prop to adjacent text, but a custom property is substituted as a whole token. {}.",
+                            STYLE_INTERPOLATION_ERROR,
+                            joined,
+                            comp.path,
+                            fix
+                        ));
+                        return whole.as_str().to_string();
+                    }
+                    match defaults.iter().find(|(name, _)| name == prop) {
+                        Some((_, fallback)) => {
+                            format!("var({}, {})", custom_property(comp, prop), fallback)
+                        }
+                        None => format!("var({})", custom_property(comp, prop)),
+                    }
+                })
+                .into_owned()
+        })
+        .collect();
+    (styles, errors)
+}
+
+/// Where an interpolation at `start..end` sits when it is not in a declaration value
+fn outside_value(masked: &str, start: usize, end: usize) -> Option<&'static str> {
+    // A selector or prelude is followed by its block; a value ends at `;` or `}`
+    let next = masked[end..]
+        .find(['{', '}', ';'])
+        .map(|i| &masked[end + i..end + i + 1]);
+    let statement_start = masked[..start].rfind(['{', '}', ';']).map_or(0, |i| i + 1);
+    let statement = masked[statement_start..start].trim_start();
+    if next == Some("{") {
+        return Some(if statement.starts_with('@') {
+            "an at-rule prelude"
+        } else {
+            "a selector"
+        });
+    }
+    if statement.starts_with('@') {
+        return Some("an at-rule prelude");
+    }
+    if !statement.contains(':') {
+        return Some("a property name");
+    }
+    None
+}
+
+/// The token an interpolation at `start..end` forms with the text right next to
+/// it (`{size}px`, `#{hex}`), if any
+fn joined_token(css: &str, start: usize, end: usize) -> Option<&str> {
+    let joins = |c: char| c.is_alphanumeric() || matches!(c, '%' | '_' | '-' | '#' | '.');
+    let before = css[..start]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| joins(*c))
+        .last()
+        .map_or(start, |(i, _)| i);
+    let after = css[end..]
+        .char_indices()
+        .take_while(|(_, c)| joins(*c))
+        .last()
+        .map_or(end, |(i, c)| end + i + c.len_utf8());
+    (before < start || after > end).then(|| &css[before..after])
+}
+
+/// Literal defaults of `prop name = ...` declarations, unquoted for CSS
+fn prop_defaults(script: &str) -> Vec<(String, String)> {
+    PROP_DEFAULT
+        .captures_iter(script)
+        .map(|caps| {
+            let value = caps[2].trim_matches(|c| c == '"' || c == '\'');
+            (caps[1].to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// Set the custom properties of an instance on the root elements of its template.
+/// Returns the `style` binding expression when a prop is passed dynamically;
+/// its initial value is set statically when it folds with `initial_values`
+/// (see `static_eval::initial_state_values`).
+pub(crate) fn apply_instance_vars(
+    nodes: &mut [TemplateNode],
+    comp: &ComponentIR,
+    attributes: &[AttributeIR],
+    origin: Option<ExpressionOrigin>,
+    initial_values: &HashMap<String, String>,
+) -> Option<ExpressionIR> {
+    let props = interpolated_props(comp);
+    if props.is_empty() {
+        return None;
+    }
+    let mut static_decls = Vec::new();
+    let mut dynamic_parts = Vec::new();
+    for prop in &props {
+        // Attribute names may arrive lowercased from the HTML parser
+        let Some(attr) = attributes
+            .iter()
+            .find(|a| a.name.eq_ignore_ascii_case(prop))
+        else {
+            continue;
+        };
+        let property = custom_property(comp, prop);
+        match (&attr.value, &origin) {
+            (AttributeValue::Static(value), _) => {
+                static_decls.push(format!("{}: {}", property, value))
+            }
+            (AttributeValue::Dynamic(expr), Some(origin)) => {
+                // Server-rendered until the binding takes over, which appends after it
+                let initial = crate::static_eval::static_eval(&expr.code, initial_values)
+                    .filter(|v| !matches!(v.as_str(), "" | "null" | "undefined"));
+                if let Some(initial) = initial {
+                    static_decls.push(format!("{}: {}", property, initial));
+                }
+                dynamic_parts.push(format!(
+                    "\"{}: \" + (window.__ZENITH_SCOPES__[\"{}\"].props.{} ?? \"\")",
+                    property, origin.instance_id, prop
+                ))
+            }
+            // Without an instance scope there is nothing to bind to
+            (AttributeValue::Dynamic(_), None) => {}
+        }
+    }
+
+    let expr = match &origin {
+        Some(origin) if !dynamic_parts.is_empty() => Some(ExpressionIR {
+            id: format!("style_vars_{}", origin.instance_id),
+            code: dynamic_parts.join(" + \"; \" + "),
+            location: origin.original_location.clone(),
+            loop_context: None,
+            origin: Some(origin.clone()),
+            extra: Default::default(),
+        }),
+        _ => None,
+    };
+    let static_style = (!static_decls.is_empty()).then(|| static_decls.join("; "));
+
+    for node in nodes.iter_mut() {
+        if let TemplateNode::Element(el) = node {
+            set_vars(
+                &mut el.attributes,
+                static_style.as_deref(),
+                expr.as_ref(),
+                el.loop_context.as_ref(),
+            );
+        }
+    }
+    expr
+}
+
+fn set_vars(
+    attributes: &mut Vec<AttributeIR>,
+    static_style: Option<&str>,
+    expr: Option<&ExpressionIR>,
+    loop_context: Option<&crate::validate::LoopContext>,
+) {
+    let style_attr = |value: AttributeValue| AttributeIR {
+        name: "style".to_string(),
+        value,
+        location: SourceLocation::default(),
+        loop_context: loop_context.cloned(),
+        optional: false,
+        extra: Default::default(),
+    };
+    let is_style = |a: &AttributeIR| a.name.eq_ignore_ascii_case("style");
+
+    if let Some(decls) = static_style {
+        match attributes
+            .iter_mut()
+            .find(|a| is_style(a) && matches!(a.value, AttributeValue::Static(_)))
+        {
+            Some(AttributeIR {
+                value: AttributeValue::Static(value),
+                ..
+            }) => {
+                let own = value.trim().trim_end_matches(';');
+                *value = if own.is_empty() {
+                    decls.to_string()
+                } else {
+                    format!("{}; {}", own, decls)
+                };
+            }
+            _ => {
+                // The static half of a merged style goes before its dynamic half
+                let at = attributes
+                    .iter()
+                    .position(is_style)
+                    .unwrap_or(attributes.len());
+                attributes.insert(at, style_attr(AttributeValue::Static(decls.to_string())));
+            }
+        }
+    }
+
+    if let Some(expr) = expr {
+        match attributes
+            .iter_mut()
+            .find(|a| is_style(a) && matches!(a.value, AttributeValue::Dynamic(_)))
+        {
+            Some(AttributeIR {
+                value: AttributeValue::Dynamic(own),
+                ..
+            }) => {
+                own.code = format!("({}) + \"; \" + {}", own.code, expr.code);
+            }
+            _ => attributes.push(style_attr(AttributeValue::Dynamic(expr.clone()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    const BADGE: &str = "<script>\nprop color = \"teal\"\nprop label\n</script>\n<span class=\"badge\" style=\"padding: 2px\">{label}</span>\n<style>\n.badge { background: {color}; border: 1px solid {color}; }\n</style>";
+
+    fn compile(page: &str, badge: &str) -> Result<CompileResult, String> {
+        let component = ComponentIR::from_source("Badge", "components/Badge.zen", badge).unwrap();
+        let options = CompileOptions {
+            components: std::collections::HashMap::from([(
+                "Badge".to_string(),
+                serde_json::to_value(component).unwrap(),
+            )]),
+            ..Default::default()
+        };
+        compile_zen_internal(page, "page.zen", options)
+    }
+
+    fn property(prop: &str) -> String {
+        format!(
+            "--zen-{}-{}",
+            crate::css_scope::scope_id("components/Badge.zen"),
+            prop
+        )
+    }
+
+    #[test]
+    fn test_static_prop_sets_custom_property_on_root() {
+        let result = compile("<main><Badge color=\"red\" label=\"New\" /></main>", BADGE).unwrap();
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert!(
+            result.html.contains(&format!(
+                "style=\"padding: 2px; {}: red\"",
+                property("color")
+            )),
+            "{}",
+            result.html
+        );
+        let css = &result.manifest.unwrap().styles;
+        assert!(css.contains(&format!("background: var({}, teal)", property("color"))));
+        assert!(css.contains(&format!("solid var({}, teal)", property("color"))));
+    }
+
+    #[test]
+    fn test_dynamic_prop_produces_style_binding() {
+        let page = "<script>\nstate tone = \"red\"\n</script>\n<main><Badge color={tone} label=\"New\" /></main>";
+        let result = compile(page, BADGE).unwrap();
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        let binding = result
+            .bindings
+            .iter()
+            .find(|b| b.id == "style_vars_inst0")
+            .expect("no style binding");
+        assert_eq!(
+            (binding.r#type.as_str(), binding.target.as_str()),
+            ("attribute", "style")
+        );
+        // Appended to the root's own static style
+        assert!(binding.merge_with_static);
+        let manifest = result.manifest.unwrap();
+        assert!(manifest.expressions.contains(&format!(
+            "\"{}: \" + (window.__ZENITH_SCOPES__[\"inst0\"].props.color",
+            property("color")
+        )));
+    }
+
+    #[test]
+    fn test_dynamic_prop_initial_value_is_server_rendered() {
+        let page = "<script>\nstate tone = \"red\"\n</script>\n<main><Badge color={tone} label=\"New\" /></main>";
+        let result = compile(page, BADGE).unwrap();
+        assert!(!result.has_errors, "{:?}", result.errors);
+        assert!(
+            result.html.contains(&format!(
+                "style=\"padding: 2px; {}: red\"",
+                property("color")
+            )),
+            "{}",
+            result.html
+        );
+    }
+
+    #[test]
+    fn test_interpolation_joined_to_a_unit_errors() {
+        let badge = BADGE
+            .replace("prop label", "prop label\nprop size = 4")
+            .replace("border: 1px", "padding: {size}px; border: 1px");
+        let error = compile("<main><Badge label=\"New\" /></main>", &badge).unwrap_err();
+        assert!(
+            error.contains("`{size}px` in a <style> block of components/Badge.zen joins the prop to adjacent text")
+                && error.contains("write `calc({size} * 1px)`"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_undeclared_interpolation_errors_with_prop_list() {
+        let badge = BADGE.replace("{color}; border", "{colour}; border");
+        let error = compile("<main><Badge label=\"New\" /></main>", &badge).unwrap_err();
+        assert!(error.contains(STYLE_INTERPOLATION_ERROR));
+        assert!(error.contains("`{colour}`"));
+        assert!(error.contains("declared props: `color`, `label`"));
+    }
+
+    #[test]
+    fn test_selector_interpolation_errors() {
+        let badge = BADGE.replace(".badge {", ".badge-{color} {");
+        let error = compile("<main><Badge label=\"New\" /></main>", &badge).unwrap_err();
+        assert!(
+            error.contains("`{color}` in a selector of a <style> block"),
+            "{}",
+            error
+        );
+
+        let comp = ComponentIR {
+            styles: vec!["@media (min-width: {size}) { a { color: red; } }".to_string()],
+            ..ComponentIR::from_source("Badge", "components/Badge.zen", BADGE).unwrap()
+        };
+        let (_, errors) = rewrite_styles(&comp);
+        assert!(errors[0].contains("an at-rule prelude"), "{:?}", errors);
+    }
+}
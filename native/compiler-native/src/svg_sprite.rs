@@ -0,0 +1,340 @@
+//! Inline SVG Sprites
+//!
+//! Icons inlined through a component repeat the same path data at every use. With
+//! `svg_sprite`, finalize keeps one copy of each repeated `<svg>` as a `<symbol>`
+//! in a hidden sprite at the top of the `<body>` and renders every occurrence as
+//!
+//! ```text
+//! <svg class="icon" aria-label="Close"><use href="#zen-svg-1a2b3c4d"></use></svg>
+//! ```
+//!
+//! The occurrence keeps its own `<svg>` tag with all of its attributes (class, id,
+//! aria-*, size, fill); the symbol gets the content and the `viewBox` and
+//! `preserveAspectRatio`, which is all two occurrences must share. SVGs with
+//! bindings, expressions or event handlers (`data-zen-*`, hydration markers) and
+//! SVGs with `<style>` or `<script>` children are left inline, as are groups where
+//! the sprite would not save bytes.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+#[cfg(feature = "napi")]
+use napi_derive::napi;
+
+/// Prefix of the generated symbol ids
+pub const SYMBOL_PREFIX: &str = "zen-svg-";
+
+/// Root attributes a symbol takes over; the rest stay on each occurrence
+const SYMBOL_ATTRS: &[&str] = &["viewbox", "preserveaspectratio"];
+
+/// Markup that keeps an SVG inline: bindings, hydration markers and raw text
+const DYNAMIC_MARKERS: &[&str] = &["data-zen-", "<!--zen:", "__ZENITH_", "<style", "<script"];
+
+lazy_static::lazy_static! {
+    static ref SVG_TAG_RE: Regex = Regex::new(r"(?i)<svg\b|</svg\s*>").unwrap();
+    static ref RAW_TEXT_RE: Regex =
+        Regex::new(r"(?is)<script\b[^>]*>.*?</script>|<style\b[^>]*>.*?</style>").unwrap();
+    static ref BODY_OPEN_RE: Regex = Regex::new(r"(?i)<body(?:\s[^>]*)?>").unwrap();
+    static ref ATTRIBUTE_RE: Regex =
+        Regex::new(r#"([^\s=/>]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+)))?"#).unwrap();
+}
+
+/// Manifest entry: what the sprite replaced
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct SvgSprite {
+    /// Symbols in the sprite
+    pub symbols: u32,
+    /// Occurrences rendered as `<use>`
+    pub uses: u32,
+    /// HTML bytes saved, net of the sprite itself
+    pub bytes_saved: u32,
+}
+
+/// One top-level inline `<svg>` of the page
+struct InlineSvg<'h> {
+    start: usize,
+    end: usize,
+    /// The opening tag, without its `>`
+    open: &'h str,
+    /// Identity of the content: shared attributes and the markup inside
+    key: String,
+    symbol_attrs: String,
+    inner: &'h str,
+}
+
+/// Byte ranges of top-level `<svg>` elements outside script and style text
+fn svg_ranges(html: &str) -> Vec<(usize, usize)> {
+    let raw: Vec<(usize, usize)> = RAW_TEXT_RE
+        .find_iter(html)
+        .map(|m| (m.start(), m.end()))
+        .collect();
+    let mut ranges = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for tag in SVG_TAG_RE.find_iter(html) {
+        if raw
+            .iter()
+            .any(|(s, e)| *s < tag.start() && tag.start() < *e)
+        {
+            continue;
+        }
+        if tag.as_str().starts_with("</") {
+            match depth {
+                0 => {}
+                1 => {
+                    depth = 0;
+                    ranges.push((start, tag.end()));
+                }
+                _ => depth -= 1,
+            }
+        } else {
+            if depth == 0 {
+                start = tag.start();
+            }
+            depth += 1;
+        }
+    }
+    ranges
+}
+
+/// End of the opening tag starting at `start` (the index of its `>`), past quoted values
+fn open_tag_end(html: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in html[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(start + i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn inline_svg(html: &str, (start, end): (usize, usize)) -> Option<InlineSvg<'_>> {
+    let markup = &html[start..end];
+    let lower = markup.to_ascii_lowercase();
+    if DYNAMIC_MARKERS.iter().any(|marker| lower.contains(marker)) {
+        return None;
+    }
+    let open_end = open_tag_end(html, start)?;
+    let open = &html[start..open_end];
+    if open.ends_with('/') {
+        return None;
+    }
+    let close_start = start + lower.rfind("</svg")?;
+    let inner = &html[open_end + 1..close_start];
+    if inner.trim().is_empty() {
+        return None;
+    }
+
+    let mut symbol_attrs = String::new();
+    for caps in ATTRIBUTE_RE.captures_iter(&open["<svg".len()..]) {
+        let name = caps[1].to_ascii_lowercase();
+        if !SYMBOL_ATTRS.contains(&name.as_str()) {
+            continue;
+        }
+        let value = caps
+            .get(2)
+            .or_else(|| caps.get(3))
+            .or_else(|| caps.get(4))
+            .map_or("", |m| m.as_str());
+        symbol_attrs.push_str(&format!(
+            " {}=\"{}\"",
+            crate::parse::correct_svg_attribute_name(&name, "symbol"),
+            value.split_whitespace().collect::<Vec<_>>().join(" ")
+        ));
+    }
+    Some(InlineSvg {
+        start,
+        end,
+        open,
+        key: format!("{}\n{}", symbol_attrs, inner.trim()),
+        symbol_attrs,
+        inner,
+    })
+}
+
+fn symbol_id(key: &str) -> String {
+    let digest = Sha256::digest(key.as_bytes());
+    let hex: String = digest[..4].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", SYMBOL_PREFIX, hex)
+}
+
+fn use_site(svg: &InlineSvg, id: &str) -> String {
+    format!("{}><use href=\"#{}\"></use></svg>", svg.open, id)
+}
+
+/// Replace repeated static inline SVGs with `<use>` references to a hidden sprite
+/// injected after the opening `<body>` tag (at the start of a document without one).
+/// Returns the HTML unchanged when nothing repeats or nothing would be saved.
+pub(crate) fn dedupe_inline_svgs(html: &str) -> (String, SvgSprite) {
+    let svgs: Vec<InlineSvg> = svg_ranges(html)
+        .into_iter()
+        .filter_map(|range| inline_svg(html, range))
+        .collect();
+    let mut groups: HashMap<&str, Vec<&InlineSvg>> = HashMap::new();
+    let mut order: Vec<&str> = Vec::new();
+    for svg in &svgs {
+        let group = groups.entry(svg.key.as_str()).or_default();
+        if group.is_empty() {
+            order.push(svg.key.as_str());
+        }
+        group.push(svg);
+    }
+
+    let mut symbols = String::new();
+    let mut replaced: HashMap<usize, String> = HashMap::new();
+    let mut report = SvgSprite::default();
+    let mut saved: i64 = 0;
+    for key in order {
+        let group = &groups[key];
+        if group.len() < 2 {
+            continue;
+        }
+        let id = symbol_id(key);
+        let symbol = format!(
+            "<symbol id=\"{}\"{}>{}</symbol>",
+            id, group[0].symbol_attrs, group[0].inner
+        );
+        let uses: Vec<String> = group.iter().map(|svg| use_site(svg, &id)).collect();
+        let before: usize = group.iter().map(|svg| svg.end - svg.start).sum();
+        let after = symbol.len() + uses.iter().map(String::len).sum::<usize>();
+        if after >= before {
+            continue;
+        }
+        saved += (before - after) as i64;
+        symbols.push_str(&symbol);
+        report.symbols += 1;
+        report.uses += group.len() as u32;
+        for (svg, use_site) in group.iter().zip(uses) {
+            replaced.insert(svg.start, use_site);
+        }
+    }
+
+    let sprite = format!(
+        "<svg style=\"display:none\" aria-hidden=\"true\"><defs>{}</defs></svg>",
+        symbols
+    );
+    saved -= (sprite.len() - symbols.len()) as i64;
+    if report.symbols == 0 || saved <= 0 {
+        return (html.to_string(), SvgSprite::default());
+    }
+    report.bytes_saved = saved as u32;
+
+    let mut out = String::with_capacity(html.len());
+    let mut last = 0;
+    for svg in &svgs {
+        if let Some(use_site) = replaced.get(&svg.start) {
+            out.push_str(&html[last..svg.start]);
+            out.push_str(use_site);
+            last = svg.end;
+        }
+    }
+    out.push_str(&html[last..]);
+    let at = BODY_OPEN_RE.find(&out).map_or(0, |body| body.end());
+    out.insert_str(at, &sprite);
+    (out, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    const ICON: &str = "<svg viewBox=\"0 0 24 24\" class=\"icon\"><path d=\"M12 2L2 7l10 5 10-5-10-5zM2 17l10 5 10-5M2 12l10 5 10-5\" fill=\"none\" stroke=\"currentColor\"/></svg>";
+
+    fn compile(page: &str, svg_sprite: bool) -> CompileResult {
+        let icon = crate::component::ComponentIR::from_source("Icon", "Icon.zen", ICON).unwrap();
+        let options = CompileOptions {
+            components: [("Icon".to_string(), serde_json::to_value(&icon).unwrap())].into(),
+            svg_sprite,
+            ..Default::default()
+        };
+        let result = compile_zen_internal(page, "page.zen", options).unwrap();
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        result
+    }
+
+    fn page(body: &str) -> String {
+        format!(
+            "<script>\nstate size = 24\n</script>\n<html><head><title>Icons</title></head><body><main>{}</main></body></html>",
+            body
+        )
+    }
+
+    #[test]
+    fn test_repeated_icons_share_one_symbol() {
+        let source = page(&"<Icon />".repeat(10));
+        let result = compile(&source, true);
+        let html = &result.html;
+        assert_eq!(html.matches("<symbol id=\"zen-svg-").count(), 1, "{}", html);
+        assert_eq!(
+            html.matches("<use href=\"#zen-svg-").count(),
+            10,
+            "{}",
+            html
+        );
+        assert_eq!(html.matches("M12 2L2 7").count(), 1, "{}", html);
+        assert!(
+            html.contains("<body><svg style=\"display:none\" aria-hidden=\"true\"><defs><symbol id=\"zen-svg-"),
+            "{}",
+            html
+        );
+        assert!(html.contains(" viewBox=\"0 0 24 24\"><path"), "{}", html);
+
+        let sprite = result.manifest.unwrap().svg_sprite;
+        assert_eq!((sprite.symbols, sprite.uses), (1, 10));
+        let plain = compile(&source, false);
+        assert_eq!(
+            sprite.bytes_saved as usize,
+            plain.html.len() - result.html.len()
+        );
+        assert_eq!(plain.manifest.unwrap().svg_sprite, SvgSprite::default());
+    }
+
+    #[test]
+    fn test_use_sites_keep_their_attributes() {
+        let path = "<path d=\"M18.36 6.64a9 9 0 1 1-12.73 0M12 2v10M4.93 4.93l1.41 1.41M17.66 6.34l1.41-1.41M2 12h2M20 12h2\" stroke=\"currentColor\" stroke-width=\"2\" stroke-linecap=\"round\"></path>";
+        let source = page(&format!(
+            "<svg viewBox=\"0 0 24 24\" class=\"close\" aria-label=\"Close\">{p}</svg><svg id=\"dismiss\" viewBox=\"0 0 24 24\" width=\"16\">{p}</svg><svg viewBox=\"0 0 24 24\">{p}</svg><svg viewBox=\"0 0 24 24\">{p}</svg>",
+            p = path
+        ));
+        let html = compile(&source, true).html;
+        let id = &html[html.find("<symbol id=\"").unwrap() + 12..][..16];
+        for open in [
+            "<svg viewBox=\"0 0 24 24\" class=\"close\" aria-label=\"Close\">",
+            "<svg id=\"dismiss\" viewBox=\"0 0 24 24\" width=\"16\">",
+        ] {
+            assert!(
+                html.contains(&format!("{}<use href=\"#{}\"></use></svg>", open, id)),
+                "{}",
+                html
+            );
+        }
+    }
+
+    #[test]
+    fn test_dynamic_svgs_stay_inline() {
+        let dynamic = "<svg viewBox=\"0 0 24 24\" width={size}><path d=\"M12 2L2 7l10 5 10-5-10-5zM2 17l10 5 10-5M2 12l10 5 10-5\" fill=\"none\" stroke=\"currentColor\"></path></svg>";
+        let source = page(&format!("{d}{d}{i}", d = dynamic, i = "<Icon />".repeat(4)));
+        let result = compile(&source, true);
+        let html = &result.html;
+        assert_eq!(html.matches("<use href=").count(), 4, "{}", html);
+        // Both dynamic copies and the symbol
+        assert_eq!(html.matches("M12 2L2 7").count(), 3, "{}", html);
+        assert_eq!(html.matches("data-zen-attr-width=").count(), 2, "{}", html);
+        assert_eq!(result.manifest.unwrap().svg_sprite.uses, 4);
+
+        // Style children apply to the document, not the symbol
+        let styled = "<svg viewBox=\"0 0 24 24\"><style>path { fill: red }</style><path d=\"M12 2L2 7l10 5 10-5-10-5zM2 17l10 5 10-5M2 12l10 5 10-5\"></path></svg>";
+        let html = format!("<body>{}</body>", styled.repeat(3));
+        assert_eq!(dedupe_inline_svgs(&html), (html, SvgSprite::default()));
+    }
+}
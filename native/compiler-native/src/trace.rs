@@ -0,0 +1,45 @@
+//! Compiler Tracing
+//!
+//! Thin wrappers over `tracing` so instrumentation can stay in the code
+//! permanently. With the `tracing` feature off every macro expands to nothing
+//! and the arguments are never evaluated, so the default build pays nothing.
+//!
+//! - `zen_span!(LEVEL, "name", fields...)` enters a span until the end of the
+//!   enclosing block.
+//! - `zen_debug!` / `zen_trace!` emit events with `tracing`'s field syntax.
+
+#[cfg(feature = "tracing")]
+macro_rules! zen_span {
+    ($level:ident, $name:expr $(, $($fields:tt)*)?) => {
+        let _zen_span = tracing::span!(tracing::Level::$level, $name $(, $($fields)*)?).entered();
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! zen_span {
+    ($($args:tt)*) => {};
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! zen_debug {
+    ($($args:tt)*) => {
+        tracing::debug!($($args)*)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! zen_debug {
+    ($($args:tt)*) => {};
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! zen_trace {
+    ($($args:tt)*) => {
+        tracing::trace!($($args)*)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! zen_trace {
+    ($($args:tt)*) => {};
+}
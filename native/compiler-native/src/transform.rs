@@ -1,22 +1,52 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 use crate::document::DocumentScope;
-use crate::validate::{AttributeValue, ExpressionIR, LoopContext, SourceLocation, TemplateNode};
+use crate::validate::{
+    AttributeIR, AttributeValue, ExpressionIR, ExpressionOrigin, LoopContext, SourceLocation,
+    TemplateNode,
+};
 
 #[cfg(feature = "napi")]
 use napi_derive::napi;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 #[cfg_attr(feature = "napi", napi(object))]
 pub struct Binding {
     pub id: String,
-    pub r#type: String, // 'text' | 'attribute' | 'conditional' | 'optional' | 'loop'
+    pub r#type: String, // 'text' | 'attribute' | 'property' | 'conditional' | 'optional' | 'loop' | 'head-text' | 'form-action' | 'effect'
     pub target: String,
     pub expression: String,
     pub location: Option<SourceLocation>,
     pub loop_context: Option<LoopContext>,
+    /// The element also has a static value for this attribute (duplicate `class`/`style`):
+    /// the runtime appends the dynamic value to `static_prefix` instead of replacing it
+    #[serde(default)]
+    pub merge_with_static: bool,
+    #[serde(default)]
+    pub static_prefix: Option<String>,
+    /// Component instance the expression was promoted from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<ExpressionOrigin>,
+    /// `"aria"` or `"data"`: the value is stringified before it is set (see `attr_coerce.rs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coerce: Option<String>,
+    /// Loop over a long static list: only the first `chunk_size` items are
+    /// server-rendered, the rest are in the loop's `application/zen-data` block
+    #[serde(default)]
+    pub virtualized: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<u32>,
+    /// Boolean state held `true` while this event handler runs (`zen:pending`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending: Option<String>,
+    /// Text inside an `aria-live` region: the text after the marker is server-rendered
+    /// and updated in place (see `live_regions.rs`)
+    #[serde(default)]
+    pub live_region: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,11 +57,162 @@ pub struct TransformOutput {
     pub bindings: Vec<Binding>,
 }
 
+/// `<form zen:action={handler}>`: enhanced submit handler receiving `(formData, event)`
+pub const FORM_ACTION_ATTR: &str = "zen:action";
+
+/// `<div zen:static>`: expressions in the subtree are evaluated once and never updated
+pub const STATIC_ATTR: &str = "zen:static";
+
+/// `<div zen:island>`: in islands mode, the subtree hydrates and the rest of the page is static
+pub const ISLAND_ATTR: &str = "zen:island";
+
+/// `<Icon zen:inline-always />`: warn when the usage cannot be substituted in place
+/// (see `component::inline_template`)
+pub const INLINE_ALWAYS_ATTR: &str = "zen:inline-always";
+
+/// `<li zen:virtualize="200">` on a loop body root: server-render at most 200 items
+/// of a static list (see `virtualize.rs`)
+pub const VIRTUALIZE_ATTR: &str = "zen:virtualize";
+
+/// `<div data-zen-client-only>`: nothing in the subtree is folded to its initial
+/// value on the server; its markup and hydration markers are emitted as
+/// placeholders the client fills (see `hydration_mismatch.rs`)
+pub const CLIENT_ONLY_ATTR: &str = "data-zen-client-only";
+
+/// `<button onclick={save} zen:pending="saving">`: `saving` is true while the
+/// handler runs (see `pending.rs`)
+pub const PENDING_ATTR: &str = "zen:pending";
+
+/// `<button onclick={exportCsv} zen:lazy>`: imports only this handler needs load on
+/// first use (see `lazy.rs`)
+pub const LAZY_ATTR: &str = "zen:lazy";
+
+/// `<my-chart prop:data={chartData}>`: assigned as a DOM property, not an attribute
+pub const PROP_PREFIX: &str = "prop:";
+
+/// Properties whose initial value SSRs as an equivalent attribute
+const REFLECTED_PROPS: &[&str] = &["value", "checked", "selected", "disabled"];
+
+/// Separator between the static and dynamic parts of a merged `class` / `style`
+pub(crate) fn merged_attr_separator(name: &str) -> &'static str {
+    if name.eq_ignore_ascii_case("style") {
+        "; "
+    } else {
+        " "
+    }
+}
+
+/// Static half of an attribute that also has a dynamic value on the same element
+/// (duplicate `class` / `style`, see `parse::merge_duplicate_attributes`)
+pub(crate) fn merged_static_value(attributes: &[AttributeIR], name: &str) -> Option<String> {
+    attributes.iter().find_map(|a| match &a.value {
+        AttributeValue::Static(v) if a.name == name => Some(v.clone()),
+        _ => None,
+    })
+}
+
+/// DOM property for a `prop:` name. Attribute names arrive lowercased, so dashes
+/// mark word boundaries (`chart-data` → `chartData`); a few common properties
+/// are recognised in their lowercased form (`contenteditable` → `contentEditable`).
+pub fn dom_property_name(name: &str) -> String {
+    let known = match name {
+        "contenteditable" => Some("contentEditable"),
+        "textcontent" => Some("textContent"),
+        "innertext" => Some("innerText"),
+        "readonly" => Some("readOnly"),
+        "tabindex" => Some("tabIndex"),
+        "maxlength" => Some("maxLength"),
+        "selectedindex" => Some("selectedIndex"),
+        "defaultvalue" => Some("defaultValue"),
+        "defaultchecked" => Some("defaultChecked"),
+        _ => None,
+    };
+    if let Some(known) = known {
+        return known.to_string();
+    }
+
+    let mut out = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '-' {
+            upper = !out.is_empty();
+        } else if upper {
+            out.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Best-effort SSR attribute for a property's initial value: only `value` and the
+/// boolean `checked`, `selected` and `disabled` have attribute equivalents; anything
+/// else (objects included) SSRs nothing.
+fn reflected_attribute(property: &str, value: Option<String>) -> Option<String> {
+    if !REFLECTED_PROPS.contains(&property) {
+        return None;
+    }
+    match (property, value?.as_str()) {
+        (_, "null" | "undefined") => None,
+        ("checked" | "selected" | "disabled", "true") => Some(property.to_string()),
+        ("checked" | "selected" | "disabled", _) => None,
+        (_, v) => Some(format!("{}=\"{}\"", property, escape_html(v))),
+    }
+}
+
+/// `{void expr}` runs as an effect on dependency changes and renders nothing
+pub fn is_effect_expression(code: &str) -> bool {
+    let code = code.trim_start();
+    code.starts_with("void ") || code.starts_with("void(")
+}
+
+/// How expressions inside <head> are rendered
+#[derive(Debug, Clone, Default)]
+pub struct HeadOptions {
+    /// Register head text expressions as `head-text` bindings for post-hydration updates
+    pub reactive: bool,
+    /// Initial state values used to statically evaluate head expressions
+    pub initial_values: HashMap<String, String>,
+    /// JSON of states initialized with an object or array literal (for `data-*` attributes)
+    pub initial_objects: HashMap<String, String>,
+    /// Production output: no cosmetic newlines between generated wrapper elements
+    pub minify: bool,
+    /// Planned `zen:virtualize` loops, by loop source id
+    pub virtual_loops: HashMap<String, crate::virtualize::VirtualLoop>,
+    /// Options of `<select>` loops over static data, by loop source id
+    pub select_loops: HashMap<String, Vec<Vec<TemplateNode>>>,
+    /// Void elements, with the compile's `html_extensions` merged in
+    pub html: crate::html_tables::HtmlTables,
+}
+
 /// Transform template with optional document scope for document modules
 pub fn transform_template_with_scope(
     nodes: &[TemplateNode],
     expressions: &[ExpressionIR],
     document_scope: Option<&DocumentScope>,
+) -> TransformOutput {
+    transform_template_with_options(nodes, expressions, document_scope, &HeadOptions::default())
+}
+
+/// Transform template with document scope and head handling options
+pub fn transform_template_with_options(
+    nodes: &[TemplateNode],
+    expressions: &[ExpressionIR],
+    document_scope: Option<&DocumentScope>,
+    head: &HeadOptions,
+) -> TransformOutput {
+    transform_template_cancellable(nodes, expressions, document_scope, head, None)
+}
+
+/// `transform_template_with_options` that stops between top-level nodes once
+/// `cancel` is set; the caller discards the partial output
+pub(crate) fn transform_template_cancellable(
+    nodes: &[TemplateNode],
+    expressions: &[ExpressionIR],
+    document_scope: Option<&DocumentScope>,
+    head: &HeadOptions,
+    cancel: Option<&crate::cancel::CancellationToken>,
 ) -> TransformOutput {
     let mut html = String::new();
     let mut bindings = Vec::new();
@@ -40,16 +221,27 @@ pub fn transform_template_with_scope(
     let is_document = crate::document::is_document_module(nodes);
 
     for node in nodes.iter() {
+        if cancel.is_some_and(|c| c.is_cancelled()) {
+            break;
+        }
         let (node_html, node_bindings) = transform_node_internal(
             node,
             expressions,
             &None,
             false,
             if is_document { document_scope } else { None },
+            head,
+            false,
         );
         html.push_str(&node_html);
         bindings.extend(node_bindings);
     }
+    for binding in &mut bindings {
+        binding.origin = expressions
+            .iter()
+            .find(|e| e.id == binding.id)
+            .and_then(|e| e.origin.clone());
+    }
 
     TransformOutput { html, bindings }
 }
@@ -68,292 +260,747 @@ pub fn transform_template_native(
     Ok(transform_template_with_scope(&nodes, &expressions, None))
 }
 
+/// Transform-only compilation over serialized nodes/expressions, returning
+/// `{html, bindings}` as JSON. Does not run component resolution or finalize.
+/// `scope_json` optionally supplies a document scope (`{props, locals}`).
+#[cfg(feature = "napi")]
+pub fn transform_nodes_json(
+    nodes_json: &str,
+    expressions_json: &str,
+    scope_json: Option<&str>,
+) -> Result<String, String> {
+    let nodes: Vec<TemplateNode> =
+        serde_json::from_str(nodes_json).map_err(|e| format!("Nodes parse error: {}", e))?;
+    let expressions: Vec<ExpressionIR> = serde_json::from_str(expressions_json)
+        .map_err(|e| format!("Expressions parse error: {}", e))?;
+    let scope: Option<DocumentScope> = match scope_json {
+        Some(json) if !json.trim().is_empty() => {
+            Some(serde_json::from_str(json).map_err(|e| format!("Scope parse error: {}", e))?)
+        }
+        _ => None,
+    };
+
+    let output = transform_template_with_scope(&nodes, &expressions, scope.as_ref());
+    serde_json::to_string(&output).map_err(|e| format!("Serialize error: {}", e))
+}
+
+#[cfg(feature = "napi")]
+#[napi]
+pub fn transform_nodes_native(
+    nodes_json: String,
+    expressions_json: String,
+    scope_json: Option<String>,
+) -> napi::Result<String> {
+    transform_nodes_json(&nodes_json, &expressions_json, scope_json.as_deref())
+        .map_err(napi::Error::from_reason)
+}
+
+/// Inherited down the tree: the nearest loop context, `<head>`, `zen:static`,
+/// and the initial value of the enclosing `<select>`
+#[derive(Clone)]
+struct WalkContext {
+    loop_context: Rc<Option<LoopContext>>,
+    in_head: bool,
+    frozen: bool,
+    select_value: Option<Rc<str>>,
+    /// Inside an `aria-live` region (see `live_regions.rs`)
+    live_region: bool,
+    /// Inside a `data-zen-client-only` subtree: no server-side folding
+    client_only: bool,
+}
+
+impl WalkContext {
+    /// The same context under a fragment, which carries its own loop context
+    fn under(&self, loop_context: &Option<LoopContext>) -> Self {
+        WalkContext {
+            loop_context: Rc::new(loop_context.clone()),
+            ..self.clone()
+        }
+    }
+}
+
+/// One unit of work on the transform's explicit stack
+enum Step<'a> {
+    /// Render a node: leaves push their html, containers schedule their children
+    Enter(&'a TemplateNode, WalkContext),
+    /// `<script>`/`<style>` content, emitted as written
+    Raw(&'a str),
+    /// A binding that belongs between two groups of children
+    Bind(Box<Binding>),
+    /// Assemble a container from its rendered children
+    Exit(Close<'a>),
+}
+
+/// What a container needs once its children are rendered; the counts say how
+/// many rendered children (in order) each part takes
+enum Close<'a> {
+    Element {
+        tag: &'a str,
+        attrs: String,
+        children: usize,
+    },
+    Conditional {
+        id: &'a str,
+        consequent: usize,
+        alternate: usize,
+    },
+    Switch {
+        ids: Vec<&'a str>,
+        cases: Vec<(&'a str, usize)>,
+    },
+    Optional {
+        id: &'a str,
+        fragment: usize,
+    },
+    Loop {
+        node: &'a crate::validate::LoopFragmentNode,
+        id: &'a str,
+        body: usize,
+        context: WalkContext,
+    },
+    Component {
+        name: &'a str,
+        children: usize,
+    },
+}
+
+/// Renders one node with an explicit stack instead of recursion, so deep trees
+/// are bounded by the heap rather than the thread's stack. Html and bindings come
+/// out in the same order as a depth-first walk.
 fn transform_node_internal(
     node: &TemplateNode,
     expressions: &[ExpressionIR],
     parent_loop_context: &Option<LoopContext>,
     is_inside_head: bool,
     document_scope: Option<&DocumentScope>,
+    head: &HeadOptions,
+    frozen: bool,
 ) -> (String, Vec<Binding>) {
-    let mut bindings = Vec::new();
+    let context = WalkContext {
+        loop_context: Rc::new(parent_loop_context.clone()),
+        in_head: is_inside_head,
+        frozen,
+        select_value: None,
+        live_region: false,
+        client_only: false,
+    };
+    TransformWalk {
+        expressions,
+        document_scope,
+        head,
+        stack: vec![Step::Enter(node, context)],
+        rendered: Vec::new(),
+        bindings: Vec::new(),
+    }
+    .run()
+}
 
-    let html = match node {
-        TemplateNode::Text(t) => escape_html(&t.value),
+struct TransformWalk<'a> {
+    expressions: &'a [ExpressionIR],
+    document_scope: Option<&'a DocumentScope>,
+    head: &'a HeadOptions,
+    stack: Vec<Step<'a>>,
+    /// Html of nodes whose container has not been assembled yet
+    rendered: Vec<String>,
+    bindings: Vec<Binding>,
+}
 
-        TemplateNode::Doctype(doc) => {
-            let mut content = format!("<!DOCTYPE {}", doc.name);
-            if !doc.public_id.is_empty() {
-                content.push_str(&format!(" PUBLIC \"{}\"", doc.public_id));
-            }
-            if !doc.system_id.is_empty() {
-                content.push_str(&format!(" \"{}\"", doc.system_id));
+impl<'a> TransformWalk<'a> {
+    /// Works the stack down, returning the html and bindings of everything on it
+    fn run(mut self) -> (String, Vec<Binding>) {
+        while let Some(step) = self.stack.pop() {
+            match step {
+                Step::Enter(node, context) => self.enter(node, context),
+                Step::Raw(text) => self.rendered.push(text.to_string()),
+                Step::Bind(binding) => self.bindings.push(*binding),
+                Step::Exit(close) => {
+                    let html = self.exit(close);
+                    self.rendered.push(html);
+                }
             }
-            content.push('>');
-            content
         }
+        (self.rendered.concat(), self.bindings)
+    }
 
-        TemplateNode::Expression(expr_node) => {
-            let expr = expressions
-                .iter()
-                .find(|e| e.id == expr_node.expression)
-                .expect("Expression not found");
-
-            // PHASE 3: Compile-time Head Resolution
-            // When inside <head>, we emit the expression code directly as a placeholder
-            // that will be resolved during the final emission pass (not runtime).
-            // This prevents <!--zen:expr--> comments from appearing in <head>.
-            if is_inside_head {
-                // STRICT HEAD ENFORCEMENT
-                // Expressions in head MUST be statically resolvable at compile time.
-                // If we have a document scope, use it for resolution
-                if let Some(scope) = document_scope {
-                    match crate::document::resolve_document_expression(&expr.code, scope) {
-                        Ok(resolved) => resolved,
-                        Err(e) => {
-                            format!("ZENITH_COMPILE_ERROR: {}", e)
-                        }
-                    }
-                } else {
-                    // Fallback to static_eval with empty props
-                    let empty_props = std::collections::HashMap::new();
-                    match crate::static_eval::static_eval(&expr.code, &empty_props) {
-                        Some(resolved) => resolved,
-                        None => {
-                            format!(
-                                "ZENITH_COMPILE_ERROR: Dynamic expression '{}' not allowed in <head>",
-                                expr.code
-                            )
-                        }
-                    }
-                }
-            } else {
-                let active_loop_context = expr_node
-                    .loop_context
-                    .clone()
-                    .or(parent_loop_context.clone());
+    /// Schedules `nodes` to render next, first node first
+    fn schedule(&mut self, nodes: &'a [TemplateNode], context: &WalkContext) {
+        for node in nodes.iter().rev() {
+            self.stack.push(Step::Enter(node, context.clone()));
+        }
+    }
 
-                bindings.push(Binding {
-                    id: expr.id.clone(),
-                    r#type: "text".to_string(),
-                    target: "data-zen-text".to_string(),
-                    expression: expr.code.clone(),
-                    location: Some(expr.location.clone()),
-                    loop_context: active_loop_context,
-                });
+    /// The html of the last `count` rendered nodes, concatenated
+    fn take(&mut self, count: usize) -> String {
+        let start = self.rendered.len() - count;
+        self.rendered.drain(start..).collect()
+    }
+
+    fn enter(&mut self, node: &'a TemplateNode, context: WalkContext) {
+        let (expressions, document_scope, head) =
+            (self.expressions, self.document_scope, self.head);
+        let parent_loop_context: &Option<LoopContext> = &context.loop_context;
+        let (is_inside_head, frozen) = (context.in_head, context.frozen && !context.client_only);
+        let bindings = &mut self.bindings;
+
+        match node {
+            TemplateNode::Text(t) => self.rendered.push(escape_html(&t.value)),
 
-                format!("<!--zen:{}-->", expr.id)
+            TemplateNode::Doctype(doc) => {
+                let mut content = format!("<!DOCTYPE {}", doc.name);
+                if !doc.public_id.is_empty() {
+                    content.push_str(&format!(" PUBLIC \"{}\"", doc.public_id));
+                }
+                if !doc.system_id.is_empty() {
+                    content.push_str(&format!(" \"{}\"", doc.system_id));
+                }
+                content.push('>');
+                self.rendered.push(content);
             }
-        }
 
-        TemplateNode::Element(el) => {
-            let tag = &el.tag;
-            let mut attrs = Vec::new();
+            TemplateNode::Expression(expr_node) => {
+                let html = {
+                    let expr = expressions
+                        .iter()
+                        .find(|e| e.id == expr_node.expression)
+                        .expect("Expression not found");
+
+                    // PHASE 3: Compile-time Head Resolution
+                    // When inside <head>, we emit the expression code directly as a placeholder
+                    // that will be resolved during the final emission pass (not runtime).
+                    // This prevents <!--zen:expr--> comments from appearing in <head>.
+                    if is_inside_head {
+                        // STRICT HEAD ENFORCEMENT
+                        // Expressions in head MUST be statically resolvable at compile time.
+                        // If we have a document scope, use it for resolution
+                        let resolved = if !crate::ssr_safety::is_ssr_safe(&expr.code) {
+                            // Reported as Z-ERR-SSR-GLOBAL / Z-ERR-SSR-JSX; never evaluated
+                            String::new()
+                        } else if let Some(scope) = document_scope {
+                            match crate::document::resolve_document_expression(&expr.code, scope) {
+                                Ok(resolved) => resolved,
+                                Err(e) => {
+                                    format!("ZENITH_COMPILE_ERROR: {}", e)
+                                }
+                            }
+                        } else {
+                            // Fallback to static_eval against initial state values
+                            match crate::static_eval::static_eval(&expr.code, &head.initial_values)
+                            {
+                                Some(resolved) => resolved,
+                                None => {
+                                    format!(
+                                        "ZENITH_COMPILE_ERROR: Dynamic expression '{}' not allowed in <head>",
+                                        expr.code
+                                    )
+                                }
+                            }
+                        };
+
+                        // Reactive head: the enclosing element is marked with data-zen-head-text
+                        if head.reactive {
+                            bindings.push(Binding {
+                                id: expr.id.clone(),
+                                r#type: "head-text".to_string(),
+                                target: "data-zen-head-text".to_string(),
+                                expression: expr.code.clone(),
+                                location: Some(expr.location.clone()),
+                                loop_context: None,
+                                ..Default::default()
+                            });
+                        }
 
-            for attr in &el.attributes {
-                match &attr.value {
-                    AttributeValue::Static(v) => {
-                        attrs.push(format!("{}=\"{}\"", attr.name, escape_html(v)));
-                    }
-                    AttributeValue::Dynamic(expr) => {
-                        let active_loop_context =
-                            attr.loop_context.clone().or(parent_loop_context.clone());
+                        resolved
+                    } else if let Some(value) = frozen
+                        .then(|| crate::static_eval::static_eval(&expr.code, &head.initial_values))
+                        .flatten()
+                        .filter(|_| crate::ssr_safety::is_ssr_safe(&expr.code))
+                    {
+                        // zen:static: fold to the initial value at compile time, no binding needed
+                        escape_html(&value)
+                    } else if is_effect_expression(&expr.code) {
+                        bindings.push(Binding {
+                            id: expr.id.clone(),
+                            r#type: "effect".to_string(),
+                            target: "data-zen-effect".to_string(),
+                            expression: expr.code.clone(),
+                            location: Some(expr.location.clone()),
+                            loop_context: expr_node
+                                .loop_context
+                                .clone()
+                                .or(parent_loop_context.clone()),
+                            ..Default::default()
+                        });
+
+                        String::new()
+                    } else {
+                        let active_loop_context = expr_node
+                            .loop_context
+                            .clone()
+                            .or(parent_loop_context.clone());
 
                         bindings.push(Binding {
                             id: expr.id.clone(),
-                            r#type: "attribute".to_string(),
-                            target: attr.name.clone(),
+                            r#type: "text".to_string(),
+                            target: "data-zen-text".to_string(),
                             expression: expr.code.clone(),
                             location: Some(expr.location.clone()),
                             loop_context: active_loop_context,
+                            live_region: context.live_region,
+                            ..Default::default()
                         });
 
-                        attrs.push(format!("data-zen-attr-{}={}", attr.name, expr.id));
+                        // Live region: the initial text is already there at hydration
+                        let initial = (context.live_region && !context.client_only)
+                            .then(|| {
+                                crate::static_eval::static_eval(&expr.code, &head.initial_values)
+                            })
+                            .flatten()
+                            .filter(|_| crate::ssr_safety::is_ssr_safe(&expr.code));
+                        match initial {
+                            Some(value) => format!("<!--zen:{}-->{}", expr.id, escape_html(&value)),
+                            None => format!("<!--zen:{}-->", expr.id),
+                        }
                     }
-                }
+                };
+                self.rendered.push(html);
             }
 
-            let attr_str = if attrs.is_empty() {
-                "".to_string()
-            } else {
-                format!(" {}", attrs.join(" "))
-            };
-
-            let active_loop_context = el.loop_context.clone().or(parent_loop_context.clone());
-            let next_in_head = is_inside_head || tag.to_lowercase() == "head";
-
-            let mut children_html = String::new();
-            for child in &el.children {
-                let (c_html, c_bindings) = transform_node_internal(
-                    child,
-                    expressions,
-                    &active_loop_context,
-                    next_in_head,
-                    document_scope,
-                );
-                children_html.push_str(&c_html);
-                bindings.extend(c_bindings);
+            TemplateNode::Element(el) => {
+                let tag = &el.tag;
+                let is_select = tag.eq_ignore_ascii_case("select");
+                let mut select_value = None;
+                let mut attrs = Vec::new();
+                let client_only =
+                    context.client_only || el.attributes.iter().any(|a| a.name == CLIENT_ONLY_ATTR);
+                let pending = el.attributes.iter().find_map(|a| match &a.value {
+                    AttributeValue::Static(state) if a.name == PENDING_ATTR => Some(state.trim()),
+                    _ => None,
+                });
+
+                for attr in &el.attributes {
+                    match &attr.value {
+                        AttributeValue::Static(_) if attr.name == STATIC_ATTR => {
+                            attrs.push("data-zen-static".to_string());
+                        }
+                        AttributeValue::Static(_) if attr.name == ISLAND_ATTR => {}
+                        AttributeValue::Static(_) if attr.name == VIRTUALIZE_ATTR => {}
+                        AttributeValue::Static(_) if attr.name == PENDING_ATTR => {}
+                        AttributeValue::Static(_) if attr.name == LAZY_ATTR => {}
+                        AttributeValue::Static(_)
+                            if attr.name == crate::portal::PORTAL_INDEX_ATTR => {}
+                        AttributeValue::Static(v) if attr.name.starts_with(PROP_PREFIX) => {
+                            let property = dom_property_name(&attr.name[PROP_PREFIX.len()..]);
+                            attrs.extend(reflected_attribute(&property, Some(v.clone())));
+                        }
+                        AttributeValue::Static(v) => {
+                            attrs.push(format!("{}=\"{}\"", attr.name, escape_html(v)));
+                        }
+                        AttributeValue::Dynamic(expr) if attr.name == FORM_ACTION_ATTR => {
+                            // Progressive enhancement: the static action/method stay in the SSR
+                            // output; the handler takes over submission once hydrated
+                            bindings.push(Binding {
+                                id: expr.id.clone(),
+                                r#type: "form-action".to_string(),
+                                target: "submit".to_string(),
+                                expression: expr.code.clone(),
+                                location: Some(expr.location.clone()),
+                                loop_context: attr
+                                    .loop_context
+                                    .clone()
+                                    .or(parent_loop_context.clone()),
+                                ..Default::default()
+                            });
+
+                            attrs.push(format!("data-zen-enhanced-form=\"{}\"", expr.id));
+                        }
+                        AttributeValue::Dynamic(expr) if attr.name.starts_with(PROP_PREFIX) => {
+                            let name = &attr.name[PROP_PREFIX.len()..];
+                            let property = dom_property_name(name);
+                            let initial =
+                                crate::static_eval::static_eval(&expr.code, &head.initial_values)
+                                    .filter(|_| !client_only);
+                            // A select has no value attribute: its matching option is marked
+                            if is_select && property == "value" {
+                                select_value = initial.map(Rc::from);
+                            } else {
+                                attrs.extend(reflected_attribute(&property, initial));
+                            }
+
+                            bindings.push(Binding {
+                                id: expr.id.clone(),
+                                r#type: "property".to_string(),
+                                target: property,
+                                expression: expr.code.clone(),
+                                location: Some(expr.location.clone()),
+                                loop_context: attr
+                                    .loop_context
+                                    .clone()
+                                    .or(parent_loop_context.clone()),
+                                ..Default::default()
+                            });
+
+                            attrs.push(format!("data-zen-prop-{}=\"{}\"", name, expr.id));
+                        }
+                        AttributeValue::Dynamic(expr) => {
+                            let active_loop_context =
+                                attr.loop_context.clone().or(parent_loop_context.clone());
+
+                            let static_prefix = merged_static_value(&el.attributes, &attr.name);
+                            let coercion =
+                                crate::attr_coerce::AttrCoercion::for_attribute(&attr.name);
+                            // A merged static half is already rendered as the SSR value
+                            if let Some(value) = coercion
+                                .filter(|_| static_prefix.is_none() && !client_only)
+                                .and_then(|c| {
+                                    c.fold(&expr.code, &head.initial_values, &head.initial_objects)
+                                })
+                            {
+                                attrs.push(format!("{}=\"{}\"", attr.name, escape_html(&value)));
+                            }
+                            bindings.push(Binding {
+                                id: expr.id.clone(),
+                                r#type: "attribute".to_string(),
+                                target: attr.name.clone(),
+                                expression: expr.code.clone(),
+                                location: Some(expr.location.clone()),
+                                loop_context: active_loop_context,
+                                merge_with_static: static_prefix.is_some(),
+                                static_prefix,
+                                coerce: coercion.map(|c| c.marker().to_string()),
+                                pending: pending
+                                    .filter(|_| crate::pending::is_event_attr(&attr.name))
+                                    .map(str::to_string),
+                                ..Default::default()
+                            });
+
+                            attrs.push(format!("data-zen-attr-{}={}", attr.name, expr.id));
+                        }
+                    }
+                }
+
+                if tag.eq_ignore_ascii_case("option") {
+                    let chosen = context.select_value.as_deref().is_some_and(|selected| {
+                        el.attributes.iter().any(|a| {
+                            a.name == "value"
+                                && matches!(&a.value, AttributeValue::Static(v) if v == selected)
+                        })
+                    });
+                    let marked = el.attributes.iter().any(|a| {
+                        a.name == "selected" || a.name == format!("{}selected", PROP_PREFIX)
+                    });
+                    if chosen && !marked {
+                        attrs.push("selected".to_string());
+                    }
+                }
+
+                let active_loop_context = el.loop_context.clone().or(parent_loop_context.clone());
+                let next_frozen = frozen || el.attributes.iter().any(|a| a.name == STATIC_ATTR);
+                let next_in_head = is_inside_head || tag.to_lowercase() == "head";
+
+                if head.reactive && next_in_head {
+                    let head_text_ids: Vec<&str> = el
+                        .children
+                        .iter()
+                        .filter_map(|c| match c {
+                            TemplateNode::Expression(e) => Some(e.expression.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    if !head_text_ids.is_empty() {
+                        attrs.push(format!(
+                            "data-zen-head-text=\"{}\"",
+                            head_text_ids.join(" ")
+                        ));
+                    }
+                }
+
+                let attr_str = if attrs.is_empty() {
+                    "".to_string()
+                } else {
+                    format!(" {}", attrs.join(" "))
+                };
+
+                self.stack.push(Step::Exit(Close::Element {
+                    tag,
+                    attrs: attr_str,
+                    children: el.children.len(),
+                }));
+                let children = WalkContext {
+                    loop_context: Rc::new(active_loop_context),
+                    in_head: next_in_head,
+                    frozen: next_frozen,
+                    // Options sit in the select or in one of its optgroups
+                    select_value: match tag.to_lowercase().as_str() {
+                        "select" => select_value,
+                        "optgroup" => context.select_value.clone(),
+                        _ => None,
+                    },
+                    live_region: crate::live_regions::live_region_attr(&el.attributes)
+                        .unwrap_or(context.live_region),
+                    client_only,
+                };
+                // Script and style content is raw text: restored inline scripts are emitted as written
+                let raw_text = matches!(tag.to_lowercase().as_str(), "script" | "style");
+                for child in el.children.iter().rev() {
+                    self.stack.push(match (raw_text, child) {
+                        (true, TemplateNode::Text(text)) => Step::Raw(&text.value),
+                        _ => Step::Enter(child, children.clone()),
+                    });
+                }
             }
 
-            let void_elements: HashSet<&str> = [
-                "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta",
-                "param", "source", "track", "wbr",
-            ]
-            .iter()
-            .cloned()
-            .collect();
+            TemplateNode::ConditionalFragment(cond) => {
+                let expr = expressions
+                    .iter()
+                    .find(|e| e.id == cond.condition)
+                    .expect("Condition expression not found");
+
+                bindings.push(Binding {
+                    id: expr.id.clone(),
+                    r#type: "conditional".to_string(),
+                    target: "data-zen-conditional".to_string(),
+                    expression: expr.code.clone(),
+                    location: Some(expr.location.clone()),
+                    loop_context: cond.loop_context.clone(),
+                    ..Default::default()
+                });
 
-            if void_elements.contains(tag.to_lowercase().as_str()) && children_html.is_empty() {
-                format!("<{}{} />", tag, attr_str)
-            } else {
-                format!("<{}{}>{}</{}>", tag, attr_str, children_html, tag)
+                self.stack.push(Step::Exit(Close::Conditional {
+                    id: &expr.id,
+                    consequent: cond.consequent.len(),
+                    alternate: cond.alternate.len(),
+                }));
+                let children = context.under(&cond.loop_context);
+                self.schedule(&cond.alternate, &children);
+                self.schedule(&cond.consequent, &children);
             }
-        }
 
-        TemplateNode::ConditionalFragment(cond) => {
-            let expr = expressions
-                .iter()
-                .find(|e| e.id == cond.condition)
-                .expect("Condition expression not found");
-
-            bindings.push(Binding {
-                id: expr.id.clone(),
-                r#type: "conditional".to_string(),
-                target: "data-zen-conditional".to_string(),
-                expression: expr.code.clone(),
-                location: Some(expr.location.clone()),
-                loop_context: cond.loop_context.clone(),
-            });
-
-            let mut cons_html = String::new();
-            for child in &cond.consequent {
-                let (c_html, c_bindings) = transform_node_internal(
-                    child,
-                    expressions,
-                    &cond.loop_context,
-                    is_inside_head,
-                    document_scope,
-                );
-                cons_html.push_str(&c_html);
-                bindings.extend(c_bindings);
+            // One wrapper for a whole else-if chain; conditions are bound in order so the
+            // runtime can stop at the first one that holds
+            TemplateNode::MultiBranchFragment(multi) => {
+                let mut ids = Vec::with_capacity(multi.branches.len());
+                let mut cases = Vec::with_capacity(multi.branches.len() + 1);
+                let mut case_bindings = Vec::with_capacity(multi.branches.len());
+                for branch in &multi.branches {
+                    let expr = expressions
+                        .iter()
+                        .find(|e| e.id == branch.condition)
+                        .expect("Branch condition expression not found");
+                    case_bindings.push(Binding {
+                        id: expr.id.clone(),
+                        r#type: "case".to_string(),
+                        target: "data-zen-case".to_string(),
+                        expression: expr.code.clone(),
+                        location: Some(expr.location.clone()),
+                        loop_context: multi.loop_context.clone(),
+                        ..Default::default()
+                    });
+                    ids.push(expr.id.as_str());
+                    cases.push((expr.id.as_str(), branch.nodes.len()));
+                }
+                cases.push(("default", multi.default.len()));
+
+                self.stack.push(Step::Exit(Close::Switch { ids, cases }));
+                // Each case's binding comes right before the bindings of its content
+                let children = context.under(&multi.loop_context);
+                self.schedule(&multi.default, &children);
+                for (branch, binding) in multi.branches.iter().zip(case_bindings).rev() {
+                    self.schedule(&branch.nodes, &children);
+                    self.stack.push(Step::Bind(Box::new(binding)));
+                }
             }
 
-            let mut alt_html = String::new();
-            for child in &cond.alternate {
-                let (a_html, a_bindings) = transform_node_internal(
-                    child,
-                    expressions,
-                    &cond.loop_context,
-                    is_inside_head,
-                    document_scope,
-                );
-                alt_html.push_str(&a_html);
-                bindings.extend(a_bindings);
+            TemplateNode::OptionalFragment(opt) => {
+                let expr = expressions
+                    .iter()
+                    .find(|e| e.id == opt.condition)
+                    .expect("Optional condition expression not found");
+
+                bindings.push(Binding {
+                    id: expr.id.clone(),
+                    r#type: "optional".to_string(),
+                    target: "data-zen-optional".to_string(),
+                    expression: expr.code.clone(),
+                    location: Some(expr.location.clone()),
+                    loop_context: opt.loop_context.clone(),
+                    ..Default::default()
+                });
+
+                self.stack.push(Step::Exit(Close::Optional {
+                    id: &expr.id,
+                    fragment: opt.fragment.len(),
+                }));
+                self.schedule(&opt.fragment, &context.under(&opt.loop_context));
             }
 
-            format!(
-                "<div data-zen-conditional=\"{}\" style=\"display: contents;\">\n<div data-zen-branch=\"true\" style=\"display: contents;\">{}</div>\n<div data-zen-branch=\"false\" style=\"display: contents;\">{}</div>\n</div>",
-                expr.id, cons_html, alt_html
-            )
-        }
+            TemplateNode::LoopFragment(lp) => {
+                let expr = expressions
+                    .iter()
+                    .find(|e| e.id == lp.source)
+                    .expect("Loop source expression not found");
 
-        TemplateNode::OptionalFragment(opt) => {
-            let expr = expressions
-                .iter()
-                .find(|e| e.id == opt.condition)
-                .expect("Optional condition expression not found");
-
-            bindings.push(Binding {
-                id: expr.id.clone(),
-                r#type: "optional".to_string(),
-                target: "data-zen-optional".to_string(),
-                expression: expr.code.clone(),
-                location: Some(expr.location.clone()),
-                loop_context: opt.loop_context.clone(),
-            });
-
-            let mut frag_html = String::new();
-            for child in &opt.fragment {
-                let (c_html, c_bindings) = transform_node_internal(
-                    child,
-                    expressions,
-                    &opt.loop_context,
-                    is_inside_head,
-                    document_scope,
-                );
-                frag_html.push_str(&c_html);
-                bindings.extend(c_bindings);
+                let virtual_loop = head.virtual_loops.get(&lp.source);
+                bindings.push(Binding {
+                    id: expr.id.clone(),
+                    r#type: "loop".to_string(),
+                    target: "data-zen-loop".to_string(),
+                    expression: expr.code.clone(),
+                    location: Some(expr.location.clone()),
+                    loop_context: lp.loop_context.clone(),
+                    virtualized: virtual_loop.is_some(),
+                    chunk_size: virtual_loop.map(|v| v.chunk_size),
+                    ..Default::default()
+                });
+
+                let children = context.under(&lp.loop_context);
+                self.stack.push(Step::Exit(Close::Loop {
+                    node: lp,
+                    id: &expr.id,
+                    body: lp.body.len(),
+                    context: children.clone(),
+                }));
+                self.schedule(&lp.body, &children);
             }
 
-            format!(
-                "<div data-zen-optional=\"{}\" style=\"display: contents;\">{}</div>",
-                expr.id, frag_html
-            )
+            TemplateNode::Component(comp) => {
+                self.stack.push(Step::Exit(Close::Component {
+                    name: &comp.name,
+                    children: comp.children.len(),
+                }));
+                self.schedule(&comp.children, &context.under(&comp.loop_context));
+            }
         }
+    }
 
-        TemplateNode::LoopFragment(lp) => {
-            let expr = expressions
-                .iter()
-                .find(|e| e.id == lp.source)
-                .expect("Loop source expression not found");
-
-            bindings.push(Binding {
-                id: expr.id.clone(),
-                r#type: "loop".to_string(),
-                target: "data-zen-loop".to_string(),
-                expression: expr.code.clone(),
-                location: Some(expr.location.clone()),
-                loop_context: lp.loop_context.clone(),
-            });
-
-            let mut body_html = String::new();
-            for child in &lp.body {
-                let (b_html, b_bindings) = transform_node_internal(
-                    child,
-                    expressions,
-                    &lp.loop_context,
-                    is_inside_head,
-                    document_scope,
-                );
-                body_html.push_str(&b_html);
-                bindings.extend(b_bindings);
+    fn exit(&mut self, close: Close<'a>) -> String {
+        let newline = if self.head.minify { "" } else { "\n" };
+        match close {
+            Close::Element {
+                tag,
+                attrs,
+                children,
+            } => {
+                let children_html = self.take(children);
+
+                if self.head.html.is_void(tag) && children_html.is_empty() {
+                    format!("<{}{} />", tag, attrs)
+                } else {
+                    format!("<{}{}>{}</{}>", tag, attrs, children_html, tag)
+                }
             }
 
-            let index_attr = if let Some(ref idx) = lp.index_var {
-                format!(" data-zen-index=\"{}\"", idx)
-            } else {
-                "".to_string()
-            };
+            Close::Conditional {
+                id,
+                consequent,
+                alternate,
+            } => {
+                let alt_html = self.take(alternate);
+                let cons_html = self.take(consequent);
+                format!(
+                    "<div data-zen-conditional=\"{}\" style=\"display: contents;\">{nl}<div data-zen-branch=\"true\" style=\"display: contents;\">{}</div>{nl}<div data-zen-branch=\"false\" style=\"display: contents;\">{}</div>{nl}</div>",
+                    id, cons_html, alt_html, nl = newline
+                )
+            }
 
-            format!(
-                "<template data-zen-loop=\"{}\" data-zen-item=\"{}\"{}>{}</template>",
-                expr.id, lp.item_var, index_attr, body_html
-            )
-        }
+            Close::Switch { ids, cases } => {
+                let total: usize = cases.iter().map(|(_, count)| count).sum();
+                let start = self.rendered.len() - total;
+                let mut rendered = self.rendered.drain(start..);
+                let cases: String = cases
+                    .iter()
+                    .map(|(case, count)| {
+                        format!(
+                            "{nl}<div data-zen-case=\"{}\" style=\"display: contents;\">{}</div>",
+                            case,
+                            rendered.by_ref().take(*count).collect::<String>(),
+                            nl = newline
+                        )
+                    })
+                    .collect();
+                format!(
+                    "<div data-zen-switch=\"{}\" style=\"display: contents;\">{}{}</div>",
+                    ids.join(" "),
+                    cases,
+                    newline
+                )
+            }
 
-        TemplateNode::Component(comp) => {
-            let mut children_html = String::new();
-            for child in &comp.children {
-                let (c_html, c_bindings) = transform_node_internal(
-                    child,
-                    expressions,
-                    &comp.loop_context,
-                    is_inside_head,
-                    document_scope,
+            Close::Optional { id, fragment } => format!(
+                "<div data-zen-optional=\"{}\" style=\"display: contents;\">{}</div>",
+                id,
+                self.take(fragment)
+            ),
+
+            Close::Loop {
+                node: lp,
+                id,
+                body,
+                context,
+            } => {
+                let body_html = self.take(body);
+                let index_attr = if let Some(ref idx) = lp.index_var {
+                    format!(" data-zen-index=\"{}\"", idx)
+                } else {
+                    "".to_string()
+                };
+
+                let mut html = format!(
+                    "<template data-zen-loop=\"{}\" data-zen-item=\"{}\"{}>{}</template>",
+                    id, lp.item_var, index_attr, body_html
                 );
-                children_html.push_str(&c_html);
-                bindings.extend(c_bindings);
+                // Options for first paint; hydration renders the loop from its template
+                for node in self
+                    .head
+                    .select_loops
+                    .get(&lp.source)
+                    .filter(|_| !context.client_only)
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                {
+                    html.push_str(&self.render_folded(node, &context));
+                }
+                if let Some(virtual_loop) = self.head.virtual_loops.get(&lp.source) {
+                    html.insert_str(
+                        "<template".len(),
+                        &format!(" data-zen-virtualize=\"{}\"", virtual_loop.chunk_size),
+                    );
+                    for node in virtual_loop.rendered.iter().flatten() {
+                        html.push_str(&self.render_folded(node, &context));
+                    }
+                    html.push_str(&format!(
+                        "<script type=\"application/zen-data\" data-zen-loop-data=\"{}\">{}</script>",
+                        id, virtual_loop.rest_json
+                    ));
+                }
+                html
             }
-            format!(
+
+            Close::Component { name, children } => format!(
                 "<div data-zen-component=\"{}\" style=\"display: contents;\">{}</div>",
-                comp.name, children_html
-            )
+                name,
+                self.take(children)
+            ),
         }
-    };
+    }
 
-    (html, bindings)
+    /// Html of a loop item rendered at build time. It is plain markup, every
+    /// expression already folded, so it registers no bindings.
+    fn render_folded(&self, node: &TemplateNode, context: &WalkContext) -> String {
+        TransformWalk {
+            expressions: self.expressions,
+            document_scope: self.document_scope,
+            head: self.head,
+            stack: vec![Step::Enter(node, context.clone())],
+            rendered: Vec::new(),
+            bindings: Vec::new(),
+        }
+        .run()
+        .0
+    }
 }
 
-fn escape_html(text: &str) -> String {
+pub(crate) fn escape_html(text: &str) -> String {
     text.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
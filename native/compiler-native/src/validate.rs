@@ -1,7 +1,7 @@
 #[cfg(feature = "napi")]
 use napi_derive::napi;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // INVARIANT CODES
@@ -10,10 +10,19 @@ use std::collections::{HashMap, HashSet};
 pub const INV_LOOP_CONTEXT_LOST: &str = "INV001";
 pub const INV_ATTRIBUTE_NOT_FORWARDED: &str = "INV002";
 pub const INV_REACTIVE_BOUNDARY: &str = "INV004";
+pub const INV_ORPHAN_SLOT_MARKER: &str = "INV007";
+pub const INV_BINDING_AFTER_FREEZE: &str = "INV008";
+pub const INV_EXPRESSION_MISSING: &str = "INV009";
+pub const INV_OUTPUT_INVARIANT: &str = "INV010";
 pub const INV_UNRESOLVED_IDENTIFIER: &str = "Z-ERR-SCOPE-002";
 pub const INV_RUN_REACTIVE: &str = "Z-ERR-RUN-REACTIVE";
 pub const INV_REACTIVITY_BOUNDARY: &str = "Z-ERR-REACTIVITY-BOUNDARY";
 
+/// Unknown fields of an IR struct, in key order. The TypeScript toolchain may run a
+/// newer IR producer than this crate: whatever it adds is carried through
+/// deserialize → resolve → serialize instead of being dropped (see `ir_compat.rs`).
+pub type UnknownFields = std::collections::BTreeMap<String, serde_json::Value>;
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // SCOPE BINDINGS (Phase 1: Identifier Inventory)
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -88,6 +97,16 @@ fn get_guarantee(code: &str) -> &'static str {
             "Attributes passed to components are forwarded to the semantic root element."
         }
         INV_REACTIVE_BOUNDARY => "Components are purely structural transforms.",
+        INV_ORPHAN_SLOT_MARKER => {
+            "Compound slot tags fill a slot of the component they are directly inside."
+        }
+        INV_BINDING_AFTER_FREEZE => {
+            "Every expression in a file classifies identifiers against the same bindings."
+        }
+        INV_EXPRESSION_MISSING => "Every expression a node references is registered exactly once.",
+        INV_OUTPUT_INVARIANT => {
+            "Compiled output keeps the ground-truth scope, qualification, and registration rules."
+        }
         INV_RUN_REACTIVE => "Component __run() must not reference reactive state or props. Use effects or expressions for reactive behavior.",
         INV_REACTIVITY_BOUNDARY => "Reactive state may only be read inside expressions. Reactive state may only be written inside event handlers.",
         _ => "Unknown invariant.",
@@ -99,6 +118,7 @@ fn get_guarantee(code: &str) -> &'static str {
 // ═══════════════════════════════════════════════════════════════════════════════
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "napi", napi(object))]
 #[serde(rename_all = "camelCase")]
 pub struct CompilerError {
@@ -145,7 +165,8 @@ impl CompilerError {
 // IR TYPES
 // ═══════════════════════════════════════════════════════════════════════════════
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "napi", napi(object))]
 #[serde(rename_all = "camelCase")]
 pub struct SourceLocation {
@@ -154,6 +175,7 @@ pub struct SourceLocation {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "napi", napi(object))]
 #[serde(rename_all = "camelCase")]
 pub struct LoopContext {
@@ -175,9 +197,44 @@ pub struct ExpressionInput {
     pub id: String,
     pub code: String,
     pub loop_context: Option<LoopContextInput>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<ExpressionOrigin>,
+}
+
+/// Where a promoted component expression came from (component resolution gives
+/// each instance its own copy, e.g. `expr_3` of `Card.zen` becomes `expr_3_inst7`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct ExpressionOrigin {
+    pub component_path: String,
+    pub component_name: String,
+    /// `instN`, the key of the instance's scope
+    pub instance_id: String,
+    /// Expression id within the component's own template
+    pub original_id: String,
+    /// Location within the component file
+    pub original_location: SourceLocation,
+}
+
+impl std::fmt::Display for ExpressionOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "component {} at {} (line {}, column {}), expression {}, instance {}",
+            self.component_name,
+            self.component_path,
+            self.original_location.line,
+            self.original_location.column,
+            self.original_id,
+            self.instance_id
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ExpressionIR {
     #[serde(default)]
@@ -186,9 +243,16 @@ pub struct ExpressionIR {
     #[serde(default)]
     pub location: SourceLocation,
     pub loop_context: Option<LoopContext>,
+    /// Set on expressions promoted out of a component instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<ExpressionOrigin>,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum TemplateNode {
     Element(ElementNode),
@@ -196,12 +260,14 @@ pub enum TemplateNode {
     Expression(ExpressionNode),
     Component(ComponentNode),
     ConditionalFragment(ConditionalFragmentNode),
+    MultiBranchFragment(MultiBranchFragmentNode),
     OptionalFragment(OptionalFragmentNode),
     LoopFragment(LoopFragmentNode),
     Doctype(DoctypeNode),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ElementNode {
     pub tag: String,
@@ -210,18 +276,32 @@ pub struct ElementNode {
     #[serde(default)]
     pub location: SourceLocation,
     pub loop_context: Option<LoopContext>,
+    /// Identity assigned at parse (see `parse::next_node_ordinal`); 0 when built elsewhere
+    #[serde(skip)]
+    pub ordinal: u64,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct TextNode {
     pub value: String,
     #[serde(default)]
     pub location: SourceLocation,
     pub loop_context: Option<LoopContext>,
+    /// Identity assigned at parse (see `parse::next_node_ordinal`); 0 when built elsewhere
+    #[serde(skip)]
+    pub ordinal: u64,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ExpressionNode {
     pub expression: String,
@@ -231,9 +311,13 @@ pub struct ExpressionNode {
     /// If true, this expression is inside <head> and must be statically resolvable
     #[serde(default)]
     pub is_in_head: bool,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ComponentNode {
     pub name: String,
@@ -242,9 +326,13 @@ pub struct ComponentNode {
     #[serde(default)]
     pub location: SourceLocation,
     pub loop_context: Option<LoopContext>,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ConditionalFragmentNode {
     pub condition: String,
@@ -253,9 +341,58 @@ pub struct ConditionalFragmentNode {
     #[serde(default)]
     pub location: SourceLocation,
     pub loop_context: Option<LoopContext>,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
 }
 
+/// A right-nested ternary chain (`a ? <A/> : b ? <B/> : <C/>`) as one fragment:
+/// the first branch whose condition holds renders, otherwise `default`
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MultiBranchFragmentNode {
+    pub branches: Vec<ConditionalBranch>,
+    pub default: Vec<TemplateNode>,
+    #[serde(default)]
+    pub location: SourceLocation,
+    pub loop_context: Option<LoopContext>,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionalBranch {
+    /// Expression id of the branch condition
+    pub condition: String,
+    pub nodes: Vec<TemplateNode>,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
+}
+
+impl MultiBranchFragmentNode {
+    /// Node lists of every branch, the default last
+    pub fn children(&self) -> impl Iterator<Item = &Vec<TemplateNode>> {
+        self.branches
+            .iter()
+            .map(|b| &b.nodes)
+            .chain(std::iter::once(&self.default))
+    }
+
+    pub fn children_mut(&mut self) -> impl Iterator<Item = &mut Vec<TemplateNode>> {
+        self.branches
+            .iter_mut()
+            .map(|b| &mut b.nodes)
+            .chain(std::iter::once(&mut self.default))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct OptionalFragmentNode {
     pub condition: String,
@@ -263,9 +400,13 @@ pub struct OptionalFragmentNode {
     #[serde(default)]
     pub location: SourceLocation,
     pub loop_context: Option<LoopContext>,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct LoopFragmentNode {
     pub source: String,
@@ -275,9 +416,13 @@ pub struct LoopFragmentNode {
     #[serde(default)]
     pub location: SourceLocation,
     pub loop_context: Option<LoopContext>,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct DoctypeNode {
     pub name: String,
@@ -285,16 +430,22 @@ pub struct DoctypeNode {
     pub system_id: String,
     #[serde(default)]
     pub location: SourceLocation,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum AttributeValue {
     Static(String),
     Dynamic(ExpressionIR),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct AttributeIR {
     pub name: String,
@@ -302,17 +453,28 @@ pub struct AttributeIR {
     #[serde(default)]
     pub location: SourceLocation,
     pub loop_context: Option<LoopContext>,
+    /// Conditional component prop (`name?={expr}`): omitted when the value is nullish
+    #[serde(default)]
+    pub optional: bool,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct TemplateIR {
     pub raw: String,
     pub nodes: Vec<TemplateNode>,
     pub expressions: Vec<ExpressionIR>,
+    /// Template-level diagnostics (e.g. an expression brace that never closes)
+    #[serde(default)]
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ScriptIR {
     pub raw: String,
@@ -324,13 +486,21 @@ pub struct ScriptIR {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct StyleIR {
     pub raw: String,
+    /// `<style global>`: document-level CSS, emitted once before the other styles
+    #[serde(default)]
+    pub global: bool,
+    /// Path of the component the block came from (empty for the page's own styles)
+    #[serde(default)]
+    pub source_component: String,
 }
 
 /// Meta tag for head directive
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct MetaTag {
     pub name: Option<String>,
@@ -340,6 +510,7 @@ pub struct MetaTag {
 
 /// Link tag for head directive
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct LinkTag {
     pub rel: String,
@@ -350,6 +521,7 @@ pub struct LinkTag {
 
 /// Head directive for compile-time head element injection
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct HeadDirective {
     pub title: Option<String>,
@@ -358,7 +530,59 @@ pub struct HeadDirective {
     pub links: Vec<LinkTag>,
 }
 
+impl HeadDirective {
+    /// Apply a more specific (inner) directive on top of this one. Title and
+    /// description are replaced; meta tags dedupe by name or property and links by
+    /// rel + href, with the inner entry winning.
+    pub fn merge(&mut self, inner: HeadDirective) {
+        if inner.title.is_some() {
+            self.title = inner.title;
+        }
+        if inner.description.is_some() {
+            self.description = inner.description;
+        }
+        for meta in inner.meta {
+            self.meta.retain(|m| {
+                !(meta.name.is_some() && m.name == meta.name
+                    || meta.property.is_some() && m.property == meta.property)
+            });
+            self.meta.push(meta);
+        }
+        for link in inner.links {
+            self.links
+                .retain(|l| !(l.rel == link.rel && l.href == link.href));
+            self.links.push(link);
+        }
+    }
+}
+
+/// A document-providing layout the page is wrapped in
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutLevel {
+    /// Registry key of the layout component
+    pub component: String,
+    /// Static attributes passed where the layout is used
+    pub props: HashMap<String, String>,
+}
+
+/// A component tag and the registry entry it resolved to
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "napi", napi(object))]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentUsage {
+    /// Tag as written in the template (e.g. `ui.Button`)
+    pub tag: String,
+    /// Registry key it resolved to
+    pub component: String,
+    /// Source path of the resolved component
+    pub path: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ZenIR {
     pub file_path: String,
@@ -385,6 +609,56 @@ pub struct ZenIR {
     /// CSS class names used (for pruning)
     #[serde(default)]
     pub css_classes: Vec<String>,
+    /// Non-fatal diagnostics collected during resolution (surfaced by finalize)
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Filter names used via pipe syntax (`{value | name}`), checked by finalize
+    #[serde(default)]
+    pub used_filters: Vec<String>,
+    /// Per component: declared props that no usage site passed in this compilation
+    #[serde(default)]
+    pub unused_props: BTreeMap<String, Vec<String>>,
+    /// Which registry entry (and path) each component tag resolved to
+    #[serde(default)]
+    pub component_usages: Vec<ComponentUsage>,
+    /// Component usages substituted in place without an instance
+    #[serde(default)]
+    pub inlined_components: u32,
+    /// Per component: props passed conditionally (`name?={expr}`) with a dynamic value
+    #[serde(default)]
+    pub conditional_props: BTreeMap<String, Vec<String>>,
+    /// Per component: static props passed as number/boolean literals (`"pageSize: number"`)
+    #[serde(default)]
+    pub coerced_props: BTreeMap<String, Vec<String>>,
+    /// Per component: events emitted via `zenDispatch()` (handled with `on:name`)
+    #[serde(default)]
+    pub component_events: BTreeMap<String, Vec<String>>,
+    /// File-level dependencies: layouts, components, npm packages, external scripts
+    #[serde(default)]
+    pub dep_graph: crate::dep_graph::DepGraph,
+    /// Layouts providing the document, outermost first (empty unless the page is
+    /// wrapped in one; a page rooted at `<html>` is its own document)
+    #[serde(default)]
+    pub layout_chain: Vec<LayoutLevel>,
+    /// Static props of component instances that do not hydrate, keyed `instN.name`
+    /// (islands mode folds their expressions to HTML)
+    #[serde(default)]
+    pub island_props: HashMap<String, String>,
+    /// Hydrated subtrees; `None` unless compiled in islands mode
+    #[serde(default)]
+    pub islands: Option<Vec<crate::islands::IslandIR>>,
+    /// Instances with a `client:*` loading directive, and those nested in a deferred one
+    #[serde(default)]
+    pub hydration: Vec<crate::client_directives::InstanceHydration>,
+    /// Item keys of loops over statically known objects (see `loop_shapes.rs`)
+    #[serde(default)]
+    pub loop_item_keys: Vec<crate::loop_shapes::LoopItemKeys>,
+    /// Class names the compiler generated, by purpose (see `class_names.rs`)
+    #[serde(default)]
+    pub generated_classes: BTreeMap<String, Vec<String>>,
+    /// Fields this compiler does not know (IR from a newer producer), kept as-is
+    #[serde(flatten, skip_serializing_if = "UnknownFields::is_empty")]
+    pub extra: UnknownFields,
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -0,0 +1,453 @@
+//! Loop Virtualization
+//!
+//! `zen:virtualize="200"` on the root element of a loop body caps how much of a
+//! long, statically known list is server-rendered. When the source has more
+//! items than the threshold:
+//!
+//! - the first N items are rendered into the HTML after the loop template
+//! - the remaining items are emitted as JSON in
+//!   `<script type="application/zen-data" data-zen-loop-data="{id}">`
+//! - the loop binding carries `virtualized` and `chunk_size`; the runtime appends
+//!   the rest from the data block with the unchanged loop template, when it chooses
+//!
+//! A source is statically known when it is an array literal, or a page `state` /
+//! `const` initialized with one. Other sources, and item templates that read more
+//! than the item and its index, render at runtime as before, with a warning.
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{BinaryOperator, Expression};
+use oxc_parser::Parser;
+use oxc_span::{GetSpan, SourceType};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::transform::VIRTUALIZE_ATTR;
+use crate::validate::{
+    AttributeIR, AttributeValue, ExpressionIR, LoopFragmentNode, TemplateNode, TextNode, ZenIR,
+};
+
+/// Server-rendered part of a virtualized loop
+#[derive(Debug, Clone)]
+pub struct VirtualLoop {
+    pub chunk_size: u32,
+    /// Loop body of each server-rendered item, with every expression folded
+    pub rendered: Vec<Vec<TemplateNode>>,
+    /// JSON array of the items left to the runtime
+    pub rest_json: String,
+}
+
+/// Plans every virtualized loop of the page, keyed by loop source id.
+/// Loops that cannot be virtualized get a warning in `ir.warnings`.
+pub(crate) fn plan_virtual_loops(ir: &mut ZenIR) -> HashMap<String, VirtualLoop> {
+    let mut planner = Planner {
+        loops: StaticLoops::new(ir),
+        plans: HashMap::new(),
+        warnings: Vec::new(),
+    };
+    planner.visit(&ir.template.nodes);
+    let Planner {
+        plans, warnings, ..
+    } = planner;
+    ir.warnings.extend(warnings);
+    plans
+}
+
+struct Planner<'a> {
+    loops: StaticLoops<'a>,
+    plans: HashMap<String, VirtualLoop>,
+    warnings: Vec<String>,
+}
+
+impl Planner<'_> {
+    fn visit(&mut self, nodes: &[TemplateNode]) {
+        for node in nodes {
+            match node {
+                TemplateNode::LoopFragment(lf) => {
+                    if let Some(threshold) = virtualize_threshold(lf) {
+                        self.plan(lf, threshold);
+                    }
+                    self.visit(&lf.body);
+                }
+                TemplateNode::Element(el) => self.visit(&el.children),
+                TemplateNode::Component(c) => self.visit(&c.children),
+                TemplateNode::ConditionalFragment(cf) => {
+                    self.visit(&cf.consequent);
+                    self.visit(&cf.alternate);
+                }
+                TemplateNode::MultiBranchFragment(mb) => {
+                    for nodes in mb.children() {
+                        self.visit(nodes);
+                    }
+                }
+                TemplateNode::OptionalFragment(of) => self.visit(&of.fragment),
+                _ => {}
+            }
+        }
+    }
+
+    fn plan(&mut self, lf: &LoopFragmentNode, threshold: &str) {
+        let code = self.loops.code(&lf.source);
+        let location = format!(
+            "{} (line {}, column {})",
+            self.loops.ir.file_path, lf.location.line, lf.location.column
+        );
+        let Some(chunk_size) = threshold.trim().parse::<u32>().ok().filter(|n| *n > 0) else {
+            self.warnings.push(format!(
+                "Z-WARN-VIRTUALIZE: zen:virtualize=\"{}\" on the loop over `{}` in {} is not a positive item count; the list renders at runtime as usual.",
+                threshold, code, location
+            ));
+            return;
+        };
+        let Some(items) = self.loops.static_items(&code) else {
+            self.warnings.push(format!(
+                "Z-WARN-VIRTUALIZE-DYNAMIC: zen:virtualize on the loop over `{}` in {} needs statically known data (an array literal, or a page `state` / `const` initialized with one); the list renders at runtime as usual.",
+                code, location
+            ));
+            return;
+        };
+        if items.len() <= chunk_size as usize {
+            return;
+        }
+
+        let (first, rest) = items.split_at(chunk_size as usize);
+        let rendered: Option<Vec<_>> = first
+            .iter()
+            .enumerate()
+            .map(|(index, item)| self.loops.render_item(&lf.body, lf, item, index))
+            .collect();
+        let Some(rendered) = rendered else {
+            self.warnings.push(format!(
+                "Z-WARN-VIRTUALIZE-TEMPLATE: zen:virtualize on the loop over `{}` in {}: the item template reads more than `{}`{} (or renders fragments or components), so items cannot be rendered at build time; the list renders at runtime as usual.",
+                code,
+                location,
+                lf.item_var.trim(),
+                lf.index_var
+                    .as_deref()
+                    .map_or(String::new(), |i| format!(" and `{}`", i.trim()))
+            ));
+            return;
+        };
+        self.plans.insert(
+            lf.source.clone(),
+            VirtualLoop {
+                chunk_size,
+                rendered,
+                rest_json: serde_json::to_string(rest)
+                    .unwrap_or_default()
+                    .replace("</", "<\\/"),
+            },
+        );
+    }
+}
+
+/// Loop items of a statically known list, rendered at build time
+/// (also used for the options of `<select>` loops, see `select_options.rs`)
+pub(crate) struct StaticLoops<'a> {
+    pub(crate) ir: &'a ZenIR,
+    /// The page's states and consts initialized with a literal
+    values: HashMap<String, Value>,
+}
+
+impl<'a> StaticLoops<'a> {
+    pub(crate) fn new(ir: &'a ZenIR) -> Self {
+        let consts = ir
+            .script
+            .as_ref()
+            .map(|s| crate::document::extract_const_declarations(&s.raw))
+            .unwrap_or_default();
+        let values = ir
+            .all_states
+            .iter()
+            .chain(consts.iter())
+            .filter_map(|(name, init)| Some((name.clone(), literal_value(init)?)))
+            .collect();
+        StaticLoops { ir, values }
+    }
+
+    pub(crate) fn code(&self, id: &str) -> String {
+        self.ir
+            .template
+            .expressions
+            .iter()
+            .find(|e| e.id == id)
+            .map_or(id, |e| e.code.as_str())
+            .trim()
+            .to_string()
+    }
+
+    /// Items of an array literal, or of a state / const initialized with one
+    pub(crate) fn static_items(&self, code: &str) -> Option<Vec<Value>> {
+        let items = match crate::attr_coerce::literal_json(code) {
+            Some(json) => serde_json::from_str(&json).ok()?,
+            None => self.values.get(code)?.clone(),
+        };
+        match items {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Loop body with every expression folded for one item; None if one cannot be
+    pub(crate) fn render_item(
+        &self,
+        nodes: &[TemplateNode],
+        lf: &LoopFragmentNode,
+        item: &Value,
+        index: usize,
+    ) -> Option<Vec<TemplateNode>> {
+        let mut rendered = Vec::new();
+        for node in nodes {
+            rendered.push(match node {
+                TemplateNode::Text(_) => node.clone(),
+                TemplateNode::Expression(e) => TemplateNode::Text(TextNode {
+                    value: self.item_value(
+                        &self.expression(&e.expression)?.code,
+                        lf,
+                        item,
+                        index,
+                    )?,
+                    location: e.location.clone(),
+                    loop_context: None,
+                    ordinal: 0,
+                    extra: Default::default(),
+                }),
+                TemplateNode::Element(el) => {
+                    let mut el = el.clone();
+                    let mut attributes = Vec::new();
+                    for attr in el.attributes {
+                        if attr.name == VIRTUALIZE_ATTR {
+                            continue;
+                        }
+                        let AttributeValue::Dynamic(expr) = &attr.value else {
+                            attributes.push(attr);
+                            continue;
+                        };
+                        let value = self.item_value(&expr.code, lf, item, index)?;
+                        // Like the runtime: `false` and `null` remove the attribute
+                        if value != "false" && value != "null" {
+                            attributes.push(AttributeIR {
+                                value: AttributeValue::Static(value),
+                                loop_context: None,
+                                ..attr
+                            });
+                        }
+                    }
+                    el.attributes = attributes;
+                    el.children = self.render_item(&el.children, lf, item, index)?;
+                    el.loop_context = None;
+                    TemplateNode::Element(el)
+                }
+                _ => return None,
+            });
+        }
+        Some(rendered)
+    }
+
+    fn expression(&self, id: &str) -> Option<&ExpressionIR> {
+        self.ir.template.expressions.iter().find(|e| e.id == id)
+    }
+
+    /// Text of `code` for one item: the item, a property path of it, the index, a
+    /// literal, or an equality check between those and the page's static values
+    fn item_value(
+        &self,
+        code: &str,
+        lf: &LoopFragmentNode,
+        item: &Value,
+        index: usize,
+    ) -> Option<String> {
+        let code = code.trim();
+        if let Some(equal) = self.item_equality(code, lf, item, index) {
+            return Some(equal.to_string());
+        }
+        if lf.index_var.as_deref().map(str::trim) == Some(code) {
+            return Some(index.to_string());
+        }
+        if let Some(value) = item_path(code, lf, item) {
+            return match value {
+                Value::String(s) => Some(s.clone()),
+                Value::Number(n) => Some(n.to_string()),
+                Value::Bool(b) => Some(b.to_string()),
+                Value::Null => Some("null".to_string()),
+                Value::Array(_) | Value::Object(_) => None,
+            };
+        }
+        crate::static_eval::static_eval(code, &HashMap::new())
+    }
+
+    /// `o.id === current` and the like, for `selected={...}` on loop-generated options
+    fn item_equality(
+        &self,
+        code: &str,
+        lf: &LoopFragmentNode,
+        item: &Value,
+        index: usize,
+    ) -> Option<bool> {
+        let allocator = Allocator::default();
+        let expr = Parser::new(&allocator, code, SourceType::default())
+            .parse_expression()
+            .ok()?;
+        let Expression::BinaryExpression(binary) = expr.without_parentheses() else {
+            return None;
+        };
+        let operand = |e: &Expression| {
+            let span = e.span();
+            let code = code[span.start as usize..span.end as usize].trim();
+            if lf.index_var.as_deref().map(str::trim) == Some(code) {
+                return Some(Value::from(index));
+            }
+            item_path(code, lf, item)
+                .cloned()
+                .or_else(|| self.values.get(code).cloned())
+                .or_else(|| literal_value(code))
+        };
+        let (left, right) = (operand(&binary.left)?, operand(&binary.right)?);
+        match binary.operator {
+            BinaryOperator::StrictEquality => Some(left == right),
+            BinaryOperator::StrictInequality => Some(left != right),
+            BinaryOperator::Equality => Some(loose_text(&left)? == loose_text(&right)?),
+            BinaryOperator::Inequality => Some(loose_text(&left)? != loose_text(&right)?),
+            _ => None,
+        }
+    }
+}
+
+/// `zen:virtualize` value on the root element of the loop body
+fn virtualize_threshold(lf: &LoopFragmentNode) -> Option<&str> {
+    lf.body.iter().find_map(|node| match node {
+        TemplateNode::Element(el) => el.attributes.iter().find_map(|a| match &a.value {
+            AttributeValue::Static(v) if a.name == VIRTUALIZE_ATTR => Some(v.as_str()),
+            _ => None,
+        }),
+        _ => None,
+    })
+}
+
+/// The item (`item`) or a property path of it (`item.author.name`)
+fn item_path<'v>(code: &str, lf: &LoopFragmentNode, item: &'v Value) -> Option<&'v Value> {
+    let mut path = code.split('.').map(str::trim);
+    if path.next() != Some(lf.item_var.trim()) {
+        return None;
+    }
+    let mut value = item;
+    for name in path {
+        value = value.get(name)?;
+    }
+    Some(value)
+}
+
+/// Value of a literal initializer: object or array literals made of literals, and
+/// string, number, boolean and null literals
+fn literal_value(code: &str) -> Option<Value> {
+    let code = code.trim();
+    if let Some(json) = crate::attr_coerce::literal_json(code) {
+        return serde_json::from_str(&json).ok();
+    }
+    if let Ok(value) = serde_json::from_str::<Value>(code) {
+        return Some(value);
+    }
+    let quoted = code.len() >= 2
+        && (code.starts_with('\'') && code.ends_with('\'')
+            || code.starts_with('`') && code.ends_with('`') && !code.contains("${"));
+    quoted
+        .then(|| crate::static_eval::static_eval(code, &HashMap::new()))
+        .flatten()
+        .map(Value::String)
+}
+
+/// Scalar as `==` compares it with a string or number
+fn loose_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Null | Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{compile_zen_internal, CompileOptions, CompileResult};
+
+    fn compile(page: &str) -> CompileResult {
+        compile_zen_internal(
+            page,
+            "page.zen",
+            CompileOptions {
+                ast_classifier: true,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn changelog(count: usize) -> String {
+        let entries: Vec<String> = (0..count)
+            .map(|i| format!("{{ id: {}, title: 'Release {}' }}", i, i))
+            .collect();
+        format!(
+            "<script>\nstate entries = [{}]\n</script>\n<ul>{{entries.map((entry) => <li zen:virtualize=\"200\" data-id={{entry.id}}>{{entry.title}}</li>)}}</ul>",
+            entries.join(", ")
+        )
+    }
+
+    #[test]
+    fn test_large_static_loop_renders_first_chunk_and_data_block() {
+        let result = compile(&changelog(1000));
+        assert!(!result.has_errors, "{:?}", result.errors);
+        let html = &result.html;
+        // 200 rendered items, plus the one in the loop template
+        assert_eq!(html.matches("</li>").count(), 201, "{}", html);
+        assert!(html.contains("<li data-id=\"199\">Release 199</li>"));
+        assert!(!html.contains(">Release 200<"));
+        assert!(!html.contains("zen:virtualize"));
+
+        let start = html
+            .find("<script type=\"application/zen-data\" data-zen-loop-data=\"")
+            .expect("data block");
+        let block = &html[start..];
+        let json = &block[block.find('>').unwrap() + 1..block.find("</script>").unwrap()];
+        let rest: Vec<serde_json::Value> = serde_json::from_str(json).unwrap();
+        assert_eq!(rest.len(), 800);
+        assert_eq!(rest[0]["title"], "Release 200");
+    }
+
+    #[test]
+    fn test_virtualized_loop_binding_is_flagged() {
+        let result = compile(&changelog(1000));
+        let binding = result
+            .bindings
+            .iter()
+            .find(|b| b.r#type == "loop")
+            .expect("loop binding");
+        assert!(binding.virtualized);
+        assert_eq!(binding.chunk_size, Some(200));
+        // The template still renders the item through its regular bindings
+        assert!(result
+            .bindings
+            .iter()
+            .any(|b| b.r#type == "text" && b.expression.contains("title")));
+
+        let short = compile(&changelog(50));
+        let binding = short.bindings.iter().find(|b| b.r#type == "loop").unwrap();
+        assert!(!binding.virtualized);
+        assert!(!short.html.contains("application/zen-data"));
+    }
+
+    #[test]
+    fn test_dynamic_source_warns() {
+        let result = compile(
+            "<script>\nstate entries = []\nfunction visible() { return entries }\n</script>\n<ul>{visible().map((entry) => <li zen:virtualize=\"200\">{entry}</li>)}</ul>",
+        );
+        assert!(
+            result
+                .warnings
+                .iter()
+                .any(|w| w.starts_with("Z-WARN-VIRTUALIZE-DYNAMIC") && w.contains("`visible()`")),
+            "{:?}",
+            result.warnings
+        );
+        let binding = result.bindings.iter().find(|b| b.r#type == "loop").unwrap();
+        assert!(!binding.virtualized);
+    }
+}
@@ -0,0 +1,185 @@
+//! MessagePack Entry Points
+//!
+//! `parse_full_zen_native` hands V8 a `serde_json::Value`, which for large pages
+//! costs more than the compile itself. The functions here take and return
+//! MessagePack buffers with the same schema: options are `ParseFullOptions`, each
+//! result is the object `parse_full_zen_native` returns, and the result structs
+//! are encoded directly without a `Value` in between.
+//!
+//! Every buffer, in either direction, starts with `WIRE_FORMAT_VERSION`. A request
+//! with another version is rejected, so a JS wrapper built for a different
+//! encoding fails loudly instead of decoding garbage. Built with the `napi` feature.
+
+use serde::{Deserialize, Serialize};
+
+use napi::bindgen_prelude::Buffer;
+use napi_derive::napi;
+
+use crate::parse::{parse_full_zen, ParseFullOptions, ParseFullOutput};
+
+/// First byte of every MessagePack request and result
+pub const WIRE_FORMAT_VERSION: u8 = 1;
+
+/// One file of a batch request
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchFile {
+    source: String,
+    file_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchRequest {
+    files: Vec<BatchFile>,
+    #[serde(default)]
+    options: ParseFullOptions,
+}
+
+/// A batch result: the file's output, or `{ error }` when it failed to compile
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum BatchEntry {
+    Output(ParseFullOutput),
+    Failed { error: String },
+}
+
+fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8], what: &str) -> Result<T, String> {
+    match bytes.split_first() {
+        Some((&WIRE_FORMAT_VERSION, payload)) => {
+            rmp_serde::from_slice(payload).map_err(|e| format!("{} decode error: {}", what, e))
+        }
+        Some((version, _)) => Err(format!(
+            "{} use wire format version {}, this compiler expects {}",
+            what, version, WIRE_FORMAT_VERSION
+        )),
+        None => Err(format!("{} are empty", what)),
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![WIRE_FORMAT_VERSION];
+    rmp_serde::encode::write_named(&mut bytes, value)
+        .map_err(|e| format!("Serialize error: {}", e))?;
+    Ok(bytes)
+}
+
+/// `parse_full_zen_native` over MessagePack: versioned `ParseFullOptions` in,
+/// versioned result out
+pub(crate) fn parse_full_zen_bytes(
+    source: &str,
+    file_path: &str,
+    options: &[u8],
+) -> Result<Vec<u8>, String> {
+    let options: ParseFullOptions = decode(options, "Options")?;
+    encode(&parse_full_zen(source, file_path, options)?)
+}
+
+/// Several files with the same options in one call. A request is
+/// `{ files: [{ source, filePath }], options }`; the result is an array with one
+/// entry per file, in order.
+pub(crate) fn parse_full_zen_batch_bytes(request: &[u8]) -> Result<Vec<u8>, String> {
+    let request: BatchRequest = decode(request, "Batch requests")?;
+    let entries: Vec<BatchEntry> = request
+        .files
+        .iter()
+        .map(
+            |file| match parse_full_zen(&file.source, &file.file_path, request.options.clone()) {
+                Ok(output) => BatchEntry::Output(output),
+                Err(error) => BatchEntry::Failed { error },
+            },
+        )
+        .collect();
+    encode(&entries)
+}
+
+#[napi]
+pub fn parse_full_zen_msgpack(
+    source: String,
+    file_path: String,
+    options_msgpack: Buffer,
+) -> napi::Result<Buffer> {
+    parse_full_zen_bytes(&source, &file_path, &options_msgpack)
+        .map(Buffer::from)
+        .map_err(napi::Error::from_reason)
+}
+
+#[napi]
+pub fn parse_full_zen_batch_msgpack(request_msgpack: Buffer) -> napi::Result<Buffer> {
+    parse_full_zen_batch_bytes(&request_msgpack)
+        .map(Buffer::from)
+        .map_err(napi::Error::from_reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = include_str!("../tests/fixtures/loop_conditional/page.zen");
+
+    fn options(options: &ParseFullOptions) -> Vec<u8> {
+        encode(options).unwrap()
+    }
+
+    fn as_value(bytes: &[u8]) -> serde_json::Value {
+        assert_eq!(bytes[0], WIRE_FORMAT_VERSION);
+        rmp_serde::from_slice(&bytes[1..]).unwrap()
+    }
+
+    #[test]
+    fn test_msgpack_result_matches_json() {
+        let opts = ParseFullOptions {
+            ast_classifier: Some(true),
+            ..Default::default()
+        };
+        let output = parse_full_zen(PAGE, "page.zen", opts.clone()).unwrap();
+        let json = serde_json::to_value(&output).unwrap();
+        let packed = encode(&output).unwrap();
+
+        let decoded = as_value(&packed);
+        assert!(decoded.get("ir").is_some() && decoded.get("manifest").is_some());
+        assert_eq!(decoded, json);
+        // Through the entry point, options included (ids differ between compiles)
+        let via_bytes = as_value(&parse_full_zen_bytes(PAGE, "page.zen", &options(&opts)).unwrap());
+        assert_eq!(
+            via_bytes["bindings"].as_array().map(Vec::len),
+            json["bindings"].as_array().map(Vec::len)
+        );
+        // The payload is the point: it must be smaller than the JSON it replaces
+        let json_len = serde_json::to_string(&json).unwrap().len();
+        assert!(packed.len() < json_len, "{} >= {}", packed.len(), json_len);
+    }
+
+    #[test]
+    fn test_batch_keeps_file_order() {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct File<'a> {
+            source: &'a str,
+            file_path: &'a str,
+        }
+        let request = encode(&serde_json::json!({
+            "files": [
+                File { source: PAGE, file_path: "a.zen" },
+                File { source: "<p>Second</p>", file_path: "b.zen" },
+            ],
+            "options": { "include": [] },
+        }))
+        .unwrap();
+        let decoded = as_value(&parse_full_zen_batch_bytes(&request).unwrap());
+        let entries = decoded.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0]["html"].as_str().unwrap().contains("todos"));
+        // `include: []` drops the optional sections
+        assert!(entries[0].get("ir").is_none() && entries[0].get("manifest").is_none());
+        assert!(entries[1]["html"].as_str().unwrap().contains("Second"));
+    }
+
+    #[test]
+    fn test_version_mismatch_is_rejected() {
+        let mut bytes = options(&ParseFullOptions::default());
+        bytes[0] = WIRE_FORMAT_VERSION + 1;
+        let err = parse_full_zen_bytes(PAGE, "page.zen", &bytes).unwrap_err();
+        assert!(err.contains("wire format version 2"), "{}", err);
+        assert!(parse_full_zen_bytes(PAGE, "page.zen", &[]).is_err());
+    }
+}
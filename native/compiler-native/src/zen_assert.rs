@@ -0,0 +1,358 @@
+//! Development Assertions
+//!
+//! `zenAssert(condition, message)` checks an invariant while developing and
+//! vanishes from production bundles:
+//!
+//! - A condition known at compile time is decided here: a false one fails the
+//!   compile with the assert's message (`Z-ERR-ASSERT`), a true one is removed.
+//! - Other asserts get their provenance as an injected third argument, which the
+//!   bundle's `zenAssert` helper logs on failure: component, file, line and column
+//!   for scripts; component, instance and expression id for template expressions.
+//! - With `strip_debug.asserts` every call is removed like a console call (see
+//!   `strip_debug.rs`): statements are dropped and expression-position asserts
+//!   become `undefined`.
+
+use std::collections::HashMap;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{Argument, CallExpression, Expression, ExpressionStatement, UnaryOperator};
+use oxc_ast_visit::{walk, Visit};
+use oxc_parser::Parser;
+use oxc_span::{GetSpan, SourceType, Span};
+
+use crate::validate::{ExpressionOrigin, ZenIR};
+
+pub(crate) const ASSERT_FN: &str = "zenAssert";
+
+/// Defined once in the bundle when a call survives to codegen
+pub(crate) const ASSERT_HELPER_JS: &str = "function zenAssert(condition, message, at) {
+    if (condition) return;
+    const where = at ? ' (' + Object.entries(at).map(([key, value]) => key + ': ' + value).join(', ') + ')' : '';
+    console.error('[Zenith] Assertion failed: ' + (message === undefined ? 'zenAssert' : message) + where, at);
+  }
+";
+
+/// `zenAssert` calls of the page script, before components merge into it
+pub(crate) fn prepare_page_script(ir: &mut ZenIR, source: &str) -> Vec<String> {
+    let Some(script) = ir.script.as_mut() else {
+        return Vec::new();
+    };
+    let (raw, errors) = prepare_script(&script.raw, source, &ir.file_path, None);
+    script.raw = raw;
+    errors
+}
+
+/// `script` with its asserts decided or given their provenance; `source` is the
+/// file the script was written in
+pub(crate) fn prepare_script(
+    script: &str,
+    source: &str,
+    file_path: &str,
+    component: Option<&str>,
+) -> (String, Vec<String>) {
+    if !script.contains(ASSERT_FN) {
+        return (script.to_string(), Vec::new());
+    }
+    let parsable = crate::component::zen_keywords_to_let_in_place(script);
+    let base = source.find(script);
+    rewrite(
+        &parsable,
+        script,
+        SourceType::default()
+            .with_module(true)
+            .with_typescript(true)
+            .with_jsx(true),
+        true,
+        |offset| {
+            let (line, column) = match base {
+                Some(base) => crate::script_syntax::position(source, base, script, offset),
+                None => crate::content_audit::line_column(script, offset),
+            };
+            let mut at = Vec::new();
+            if let Some(component) = component {
+                at.push(format!("component: {}", js_string(component)));
+            }
+            at.push(format!("file: {}", js_string(file_path)));
+            at.push(format!("line: {}", line));
+            at.push(format!("column: {}", column));
+            (
+                at,
+                format!(" at {} (line {}, column {})", file_path, line, column),
+            )
+        },
+    )
+}
+
+/// Asserts in template expressions, in the registry and in attribute copies
+pub(crate) fn prepare_expressions(ir: &mut ZenIR) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut rewritten = HashMap::new();
+    for expr in &mut ir.template.expressions {
+        if !expr.code.contains(ASSERT_FN) {
+            continue;
+        }
+        let origin = expr.origin.clone();
+        let id = expr.id.clone();
+        let file_path = ir.file_path.clone();
+        let (code, expr_errors) = rewrite(&expr.code, &expr.code, SourceType::tsx(), false, |_| {
+            expression_provenance(&file_path, &id, origin.as_ref())
+        });
+        errors.extend(expr_errors);
+        if code != expr.code {
+            expr.code = code.clone();
+            rewritten.insert(expr.id.clone(), code);
+        }
+    }
+    if !rewritten.is_empty() {
+        crate::i18n::update_attribute_code(&mut ir.template.nodes, &rewritten);
+    }
+    errors
+}
+
+fn expression_provenance(
+    file_path: &str,
+    id: &str,
+    origin: Option<&ExpressionOrigin>,
+) -> (Vec<String>, String) {
+    match origin {
+        Some(origin) => (
+            vec![
+                format!("component: {}", js_string(&origin.component_name)),
+                format!("instance: {}", js_string(&origin.instance_id)),
+                format!("file: {}", js_string(&origin.component_path)),
+                format!("expression: {}", js_string(&origin.original_id)),
+            ],
+            format!(
+                " in expression {} of {} (rendered in {})",
+                origin.original_id, origin.component_path, file_path
+            ),
+        ),
+        None => (
+            vec![
+                format!("file: {}", js_string(file_path)),
+                format!("expression: {}", js_string(id)),
+            ],
+            format!(" in expression {} of {}", id, file_path),
+        ),
+    }
+}
+
+/// Apply the assert edits to `code`; `parsable` has the same offsets.
+/// `provenance(offset)` gives the injected fields and the error location.
+fn rewrite(
+    parsable: &str,
+    code: &str,
+    source_type: SourceType,
+    removes_statements: bool,
+    provenance: impl Fn(usize) -> (Vec<String>, String),
+) -> (String, Vec<String>) {
+    let allocator = Allocator::default();
+    let ret = Parser::new(&allocator, parsable, source_type).parse();
+    if !ret.errors.is_empty() {
+        // Syntax errors are reported on their own (see `script_syntax.rs`)
+        return (code.to_string(), Vec::new());
+    }
+    let mut finder = AssertFinder::default();
+    finder.visit_program(&ret.program);
+
+    let mut errors = Vec::new();
+    let mut edits: Vec<(Span, String)> = Vec::new();
+    for call in finder.calls {
+        let (at, location) = provenance(call.span.start as usize);
+        match call.known {
+            Some(false) => errors.push(format!(
+                "Z-ERR-ASSERT: {}{}",
+                message(code, call.message),
+                location
+            )),
+            Some(true) => match call.statement.filter(|_| removes_statements) {
+                Some(statement) => edits.push((statement, String::new())),
+                None => edits.push((call.span, "void 0".to_string())),
+            },
+            None if call.args == 2 => edits.push((
+                Span::new(call.last_arg_end, call.last_arg_end),
+                format!(", {{ {} }}", at.join(", ")),
+            )),
+            // `zenAssert(x)` logs without a message; a third argument is the caller's
+            None if call.args == 1 => edits.push((
+                Span::new(call.last_arg_end, call.last_arg_end),
+                format!(", undefined, {{ {} }}", at.join(", ")),
+            )),
+            None => {}
+        }
+    }
+
+    let mut out = code.to_string();
+    edits.sort_by_key(|(span, _)| std::cmp::Reverse(span.start));
+    for (span, text) in edits {
+        out.replace_range(span.start as usize..span.end as usize, &text);
+    }
+    (out, errors)
+}
+
+/// The assert's message: a string's value, otherwise its source text
+fn message(code: &str, message: Option<(Span, Option<String>)>) -> String {
+    match message {
+        Some((_, Some(value))) => value,
+        Some((span, None)) => code[span.start as usize..span.end as usize].to_string(),
+        None => "assertion failed".to_string(),
+    }
+}
+
+fn js_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+struct AssertCall {
+    span: Span,
+    /// The expression statement that is only this call
+    statement: Option<Span>,
+    args: usize,
+    last_arg_end: u32,
+    known: Option<bool>,
+    /// Span of the message argument and its value when it is a string
+    message: Option<(Span, Option<String>)>,
+}
+
+#[derive(Default)]
+struct AssertFinder {
+    calls: Vec<AssertCall>,
+    /// Span of the expression statement being visited, when it is a bare call
+    statement: Option<Span>,
+}
+
+fn is_assert(call: &CallExpression) -> bool {
+    matches!(&call.callee, Expression::Identifier(id) if id.name == ASSERT_FN)
+}
+
+impl<'a> Visit<'a> for AssertFinder {
+    fn visit_expression_statement(&mut self, it: &ExpressionStatement<'a>) {
+        let bare = matches!(&it.expression, Expression::CallExpression(call) if is_assert(call));
+        self.statement = bare.then_some(it.span);
+        walk::walk_expression_statement(self, it);
+        self.statement = None;
+    }
+
+    fn visit_call_expression(&mut self, it: &CallExpression<'a>) {
+        if is_assert(it) {
+            let statement = self.statement.take();
+            self.calls.push(AssertCall {
+                span: it.span,
+                statement,
+                args: it.arguments.len(),
+                last_arg_end: it.arguments.last().map_or(it.span.end, |a| a.span().end),
+                known: it
+                    .arguments
+                    .first()
+                    .and_then(Argument::as_expression)
+                    .and_then(truthiness),
+                message: it.arguments.get(1).map(|arg| {
+                    let value = match arg.as_expression() {
+                        Some(Expression::StringLiteral(s)) => Some(s.value.to_string()),
+                        Some(Expression::TemplateLiteral(t)) if t.expressions.is_empty() => {
+                            t.quasis.first().map(|q| q.value.raw.to_string())
+                        }
+                        _ => None,
+                    };
+                    (arg.span(), value)
+                }),
+            });
+        }
+        walk::walk_call_expression(self, it);
+    }
+}
+
+/// Truthiness of a condition made of literals
+fn truthiness(expr: &Expression) -> Option<bool> {
+    match expr.without_parentheses() {
+        Expression::BooleanLiteral(b) => Some(b.value),
+        Expression::NumericLiteral(n) => Some(n.value != 0.0 && !n.value.is_nan()),
+        Expression::StringLiteral(s) => Some(!s.value.is_empty()),
+        Expression::NullLiteral(_) => Some(false),
+        Expression::Identifier(id) if id.name == "undefined" => Some(false),
+        Expression::UnaryExpression(u) => match u.operator {
+            UnaryOperator::LogicalNot => truthiness(&u.argument).map(|b| !b),
+            UnaryOperator::Void => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::component::ComponentIR;
+    use crate::parse::{compile_zen_internal, CompileOptions};
+    use crate::strip_debug::StripDebug;
+    use std::collections::HashMap;
+
+    const LIST: &str = "<script>\nprop items\nconst limit = 10\nzenAssert(limit > 0, 'List requires a limit')\n</script>\n<ul>{items.length}</ul>";
+
+    fn compile(list: &str, strip_debug: StripDebug) -> Result<String, String> {
+        let comp = ComponentIR::from_source("List", "components/List.zen", list).unwrap();
+        let mut components = HashMap::new();
+        components.insert("List".to_string(), serde_json::to_value(comp).unwrap());
+        let result = compile_zen_internal(
+            "<script>\nstate rows = [1]\n</script>\n<main><List items={rows} /></main>",
+            "page.zen",
+            CompileOptions {
+                components,
+                strip_debug,
+                ..Default::default()
+            },
+        )?;
+        assert!(!result.has_errors, "{:?}", result.errors);
+        Ok(result.manifest.unwrap().bundle)
+    }
+
+    #[test]
+    fn test_dev_keeps_call_with_injected_location() {
+        let bundle = compile(LIST, StripDebug::default()).unwrap();
+        // The component script is reprinted inside its `__run()` thunk
+        let compact: String = bundle.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert!(
+            compact.contains(
+                "zenAssert(scope.locals.limit > 0, \"List requires a limit\", { component: \"List\", file: \"components/List.zen\", line: 4, column: 1 });"
+            ),
+            "{}",
+            bundle
+        );
+        assert!(bundle.contains("function zenAssert(condition, message, at)"));
+    }
+
+    #[test]
+    fn test_prod_strips_asserts() {
+        let bundle = compile(
+            LIST,
+            StripDebug {
+                asserts: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(!bundle.contains("zenAssert"), "{}", bundle);
+    }
+
+    #[test]
+    fn test_statically_false_assert_fails_with_message() {
+        let err = compile(
+            "<script>\nprop items\nzenAssert(false, 'List is not ready yet')\n</script>\n<ul>{items.length}</ul>",
+            StripDebug::default(),
+        )
+        .unwrap_err();
+        assert!(
+            err.contains(
+                "Z-ERR-ASSERT: List is not ready yet at components/List.zen (line 3, column 1)"
+            ),
+            "{}",
+            err
+        );
+        // A statically true assert is removed even in development
+        let bundle = compile(
+            "<script>\nprop items\nzenAssert(!0, 'always')\n</script>\n<ul>{items.length}</ul>",
+            StripDebug::default(),
+        )
+        .unwrap();
+        assert!(!bundle.contains("always"), "{}", bundle);
+    }
+}
@@ -0,0 +1,187 @@
+//! The `zenith-compile` binary: output files, diagnostics and exit codes.
+
+#![cfg(feature = "cli")]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// A fresh output directory per test
+fn out_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("zenith-compile-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn zenith_compile(cwd: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_zenith-compile"))
+        .current_dir(cwd)
+        .args(args)
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_compiles_page_with_components() {
+    let out = out_dir("components");
+    let result = zenith_compile(
+        &fixture("component_events"),
+        &[
+            "page.zen",
+            "--components",
+            "./components",
+            "--out",
+            out.to_str().unwrap(),
+        ],
+    );
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+
+    let html = std::fs::read_to_string(out.join("page.html")).unwrap();
+    // Expressions of the second instance, with ids scoped to it
+    let instance_id = regex::Regex::new(r"expr_\d+_inst1").unwrap();
+    assert!(instance_id.is_match(&html), "{}", html);
+    let bundle = std::fs::read_to_string(out.join("page.js")).unwrap();
+    assert!(!bundle.is_empty());
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(out.join("page.manifest.json")).unwrap())
+            .unwrap();
+    assert!(manifest["expressions"].is_string());
+    let _ = std::fs::remove_dir_all(&out);
+}
+
+#[test]
+fn test_errors_exit_non_zero_with_json_diagnostics() {
+    let out = out_dir("errors");
+    let result = zenith_compile(
+        &fixture("intentional_errors"),
+        &[
+            "page.zen",
+            "--filter",
+            "upper",
+            "--json-diagnostics",
+            "-o",
+            out.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    let diagnostics: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+    let unknown = diagnostics
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|d| d["code"] == "Z-ERR-UNKNOWN-FILTER")
+        .unwrap_or_else(|| panic!("{}", diagnostics));
+    assert_eq!(unknown["severity"], "error");
+    assert_eq!(unknown["file"], "page.zen");
+    assert!(unknown["message"].as_str().unwrap().contains("currency"));
+    let _ = std::fs::remove_dir_all(&out);
+}
+
+#[test]
+fn test_human_diagnostics_are_located() {
+    let out = out_dir("human");
+    let result = zenith_compile(
+        &fixture("intentional_errors"),
+        &["page.zen", "--filter", "upper", "-o", out.to_str().unwrap()],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(
+        stderr.contains("page.zen:") && stderr.contains("error[Z-ERR-UNKNOWN-FILTER]"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("\x1b["));
+    let _ = std::fs::remove_dir_all(&out);
+}
+
+#[test]
+fn test_usage_errors_exit_two() {
+    let result = zenith_compile(&fixture("intentional_errors"), &["--mode", "static"]);
+    assert_eq!(result.status.code(), Some(2));
+    let result = zenith_compile(&fixture("intentional_errors"), &[]);
+    assert_eq!(result.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Usage: zenith-compile"));
+}
+
+#[test]
+fn test_each_failed_page_is_reported() {
+    let out = out_dir("failed-pages");
+    // The second page fails with an invariant error, after a line of context
+    let result = zenith_compile(
+        &fixture("cli_batch"),
+        &[
+            "broken_style.zen",
+            "orphan_slot.zen",
+            "-c",
+            "components",
+            "--json-diagnostics",
+            "-o",
+            out.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(result.status.code(), Some(1));
+    let diagnostics: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+    for file in ["broken_style.zen", "orphan_slot.zen"] {
+        assert!(
+            diagnostics
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|d| d["file"] == file && d["severity"] == "error"),
+            "{}: {}",
+            file,
+            diagnostics
+        );
+    }
+    // Invariant codes are codes too
+    let orphan = diagnostics
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|d| d["file"] == "orphan_slot.zen")
+        .unwrap();
+    assert_eq!(orphan["code"], "INV007", "{}", orphan);
+    assert_eq!(
+        (orphan["line"].as_u64(), orphan["column"].as_u64()),
+        (Some(2), Some(3))
+    );
+    let _ = std::fs::remove_dir_all(&out);
+}
+
+#[test]
+fn test_pages_with_one_stem_keep_their_directories() {
+    let out = out_dir("same-stem");
+    let result = zenith_compile(
+        &fixture("cli_batch"),
+        &["a/index.zen", "b/index.zen", "-o", out.to_str().unwrap()],
+    );
+    assert!(
+        result.status.success(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    );
+    let a = std::fs::read_to_string(out.join("a/index.html")).unwrap();
+    let b = std::fs::read_to_string(out.join("b/index.html")).unwrap();
+    assert!(a.contains("Section A") && b.contains("Section B"));
+    assert!(!out.join("index.html").exists());
+
+    // Two arguments for one page would write the same files
+    let result = zenith_compile(
+        &fixture("cli_batch"),
+        &["a/index.zen", "./a/index.zen", "-o", out.to_str().unwrap()],
+    );
+    assert_eq!(result.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&result.stderr).contains("same page"));
+    let _ = std::fs::remove_dir_all(&out);
+}
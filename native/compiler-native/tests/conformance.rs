@@ -0,0 +1,352 @@
+//! Conformance corpus: this crate against the TypeScript compiler it was ported
+//! from (parseTemplate.ts / parseScript.ts and the page pipeline).
+//!
+//! Each case lives in `tests/conformance/cases/<name>/`:
+//!
+//! - `page.zen`, the page
+//! - `registry.json` (optional), the component registry: tag → the path of a
+//!   component source in the case (built with `ComponentIR::from_source`), or a
+//!   `ComponentIR` object taken as-is
+//! - `case.json`: a `description` and the compile options (`props`, `filters`,
+//!   `astClassifier`)
+//! - `expected/`: the outputs of the reference TypeScript compiler, `ir.json`
+//!   (`{ template, script }`), `html.html`, `bindings.json` and
+//!   `diagnostics.json`, with `recorded-with.txt` naming its version. This crate
+//!   never writes them: outputs recorded with it would only compare it against
+//!   itself, so they are rejected. A case without them is compiled but not
+//!   compared, and REPORT.md lists it as awaiting a recording.
+//!
+//! Both sides become one JSON document, `{ ir, html, bindings, diagnostics }`
+//! with the HTML as one line per tag boundary, and are diffed field by field
+//! (see `conformance/diff.rs`). A difference fails the run unless
+//! `conformance/allowlist.toml` documents it for that case (see
+//! `conformance/allowlist.rs`); so does an allowlist entry that no longer
+//! matches anything, and a `conformance/REPORT.md` that is out of date
+//! (`UPDATE_SNAPSHOTS=1 cargo test --test conformance` rewrites it).
+
+#[path = "conformance/allowlist.rs"]
+mod allowlist;
+#[path = "conformance/diff.rs"]
+mod diff;
+#[path = "conformance/report.rs"]
+mod report;
+// Only the HTML and expression id normalization and the snapshot check of the
+// golden harness are used here
+#[allow(dead_code)]
+mod support;
+
+use compiler_native::{
+    compile_zen_internal, parse_script, parse_template, CompileOptions, ComponentIR,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use allowlist::Allowlist;
+use report::CaseOutcome;
+
+const EXPECTED_FILES: &[&str] = &[
+    "ir.json",
+    "html.html",
+    "bindings.json",
+    "diagnostics.json",
+    "recorded-with.txt",
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+struct CaseSpec {
+    description: String,
+    props: HashMap<String, String>,
+    filters: Vec<String>,
+    ast_classifier: bool,
+}
+
+fn conformance_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("conformance")
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))
+}
+
+fn read_json(path: &Path) -> Result<Value, String> {
+    serde_json::from_str(&read(path)?).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// The outputs of one case, in the form they are recorded in
+struct Outputs {
+    ir: Value,
+    html: String,
+    bindings: Value,
+    diagnostics: Value,
+}
+
+fn compile_case(name: &str, dir: &Path, spec: &CaseSpec) -> Result<Outputs, String> {
+    let page_path = format!("{}/page.zen", name);
+    let source = read(&dir.join("page.zen"))?;
+
+    let registry_path = dir.join("registry.json");
+    let registry: BTreeMap<String, Value> = if registry_path.exists() {
+        serde_json::from_value(read_json(&registry_path)?)
+            .map_err(|e| format!("{}: {}", registry_path.display(), e))?
+    } else {
+        BTreeMap::new()
+    };
+    let mut components = HashMap::new();
+    for (tag, entry) in registry {
+        let entry = match entry {
+            Value::String(rel_path) => {
+                let component_source = read(&dir.join(&rel_path))?;
+                let component = ComponentIR::from_source(&tag, &rel_path, &component_source)
+                    .map_err(|e| format!("component `{}`: {}", tag, e))?;
+                serde_json::to_value(component).unwrap()
+            }
+            object => object,
+        };
+        components.insert(tag, entry);
+    }
+
+    let ir = json!({
+        "template": match parse_template(&source, &page_path) {
+            Ok(template) => serde_json::to_value(template).unwrap(),
+            Err(e) => json!({ "error": serde_json::to_value(e).unwrap() }),
+        },
+        "script": parse_script(&source),
+    });
+
+    let options = CompileOptions {
+        components,
+        props: spec.props.clone(),
+        filters: spec.filters.clone(),
+        ast_classifier: spec.ast_classifier,
+        ..Default::default()
+    };
+    let (html, bindings, has_errors, mut errors, mut warnings) =
+        match compile_zen_internal(&source, &page_path, options) {
+            Ok(result) => (
+                result.html,
+                serde_json::to_value(result.bindings).unwrap(),
+                result.has_errors,
+                result.errors,
+                result.warnings,
+            ),
+            Err(e) => (String::new(), json!([]), true, vec![e], vec![]),
+        };
+    errors.sort();
+    warnings.sort();
+    Ok(Outputs {
+        ir,
+        html,
+        bindings,
+        diagnostics: json!({ "hasErrors": has_errors, "errors": errors, "warnings": warnings }),
+    })
+}
+
+/// The recorded reference outputs and what recorded them; `None` while the
+/// case has no recording
+fn load_expected(dir: &Path) -> Result<Option<(Outputs, String)>, String> {
+    let expected = dir.join("expected");
+    if !expected.exists() {
+        return Ok(None);
+    }
+    if let Some(missing) = EXPECTED_FILES
+        .iter()
+        .find(|file| !expected.join(file).exists())
+    {
+        return Err(format!("incomplete recording: no `expected/{}`", missing));
+    }
+    let recorded_with = read(&expected.join("recorded-with.txt"))?
+        .trim()
+        .to_string();
+    if recorded_with.starts_with(env!("CARGO_PKG_NAME")) {
+        return Err(format!(
+            "`expected/` was recorded with {}, not the reference compiler",
+            recorded_with
+        ));
+    }
+    let outputs = Outputs {
+        ir: read_json(&expected.join("ir.json"))?,
+        html: read(&expected.join("html.html"))?,
+        bindings: read_json(&expected.join("bindings.json"))?,
+        diagnostics: read_json(&expected.join("diagnostics.json"))?,
+    };
+    Ok(Some((outputs, recorded_with)))
+}
+
+/// The document the sides are compared as
+fn document(outputs: &Outputs, normalize_ids: bool) -> Value {
+    let lines: Vec<String> = support::normalize_html(&outputs.html)
+        .lines()
+        .map(String::from)
+        .collect();
+    let document = json!({
+        "ir": outputs.ir,
+        "html": lines,
+        "bindings": outputs.bindings,
+        "diagnostics": outputs.diagnostics,
+    });
+    if !normalize_ids {
+        return document;
+    }
+    let text = support::canonical_ids(&[document.to_string()]).remove(0);
+    serde_json::from_str(&text).unwrap()
+}
+
+fn run_case(name: &str, dir: &Path, allowlist: &Allowlist, outcome: &mut CaseOutcome) {
+    let spec: CaseSpec = match read_json(&dir.join("case.json"))
+        .and_then(|v| serde_json::from_value(v).map_err(|e| format!("case.json: {}", e)))
+    {
+        Ok(spec) => spec,
+        Err(e) => {
+            outcome.problem = Some(e);
+            return;
+        }
+    };
+    outcome.description = spec.description.clone();
+    let actual = match compile_case(name, dir, &spec) {
+        Ok(outputs) => outputs,
+        Err(e) => {
+            outcome.problem = Some(e);
+            return;
+        }
+    };
+    let (expected, recorded_with) = match load_expected(dir) {
+        Ok(Some(loaded)) => loaded,
+        Ok(None) => return,
+        Err(e) => {
+            outcome.problem = Some(e);
+            return;
+        }
+    };
+    outcome.recorded_with = Some(recorded_with);
+
+    let normalize_ids = allowlist.normalizes_ids(name);
+    let expected_document = document(&expected, normalize_ids);
+    let actual_document = document(&actual, normalize_ids);
+    outcome.fields = diff::count_fields(&expected_document);
+    let differences = diff::diff(&expected_document, &actual_document);
+    outcome.ids_normalized = normalize_ids
+        && diff::diff(&document(&expected, false), &document(&actual, false)).len()
+            != differences.len();
+    for difference in differences {
+        match allowlist.allowing(name, &difference.pointer) {
+            Some(entry) => outcome.allowed.push((difference, entry)),
+            None => outcome.unallowed.push(difference),
+        }
+    }
+}
+
+#[test]
+fn conformance_corpus() {
+    let root = conformance_dir();
+    let allowlist = Allowlist::parse(&read(&root.join("allowlist.toml")).unwrap())
+        .unwrap_or_else(|e| panic!("allowlist.toml: {}", e));
+
+    let mut names: Vec<String> = fs::read_dir(root.join("cases"))
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert!(!names.is_empty(), "no conformance cases");
+
+    let outcomes: Vec<CaseOutcome> = names
+        .iter()
+        .map(|name| {
+            let mut outcome = CaseOutcome {
+                name: name.clone(),
+                ..Default::default()
+            };
+            run_case(
+                name,
+                &root.join("cases").join(name),
+                &allowlist,
+                &mut outcome,
+            );
+            outcome
+        })
+        .collect();
+
+    let mut failures = Vec::new();
+    for case in &outcomes {
+        if let Some(problem) = &case.problem {
+            failures.push(format!("`{}`: {}", case.name, problem));
+        }
+        if !case.unallowed.is_empty() {
+            let listed: Vec<String> = case.unallowed.iter().map(|d| d.describe()).collect();
+            failures.push(format!(
+                "`{}` differs from its recorded outputs:\n  {}\nDocument intentional differences in tests/conformance/allowlist.toml:\n{}",
+                case.name,
+                listed.join("\n  "),
+                report::suggested_entries(&case.name, &case.unallowed)
+            ));
+        }
+    }
+    for case in allowlist.cases() {
+        if !names.iter().any(|name| name == case) {
+            failures.push(format!("the allowlist names the unknown case `{}`", case));
+        }
+    }
+    for entry in &allowlist.normalize {
+        if !outcomes
+            .iter()
+            .any(|case| case.name == entry.case && case.ids_normalized)
+        {
+            failures.push(format!(
+                "expression ids of `{}` match without renumbering any more; remove its [[normalize]] entry",
+                entry.case
+            ));
+        }
+    }
+    for (index, entry) in allowlist.ignore.iter().enumerate() {
+        if !outcomes
+            .iter()
+            .any(|case| case.allowed.iter().any(|(_, by)| *by == index))
+        {
+            failures.push(format!(
+                "allowlist entry `{}` for `{}` matches no difference any more; remove it",
+                entry.path, entry.case
+            ));
+        }
+    }
+
+    let report = report::render(&outcomes, &allowlist);
+    support::assert_snapshot_file(&root.join("REPORT.md"), "conformance/REPORT.md", &report);
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn recordings_of_this_crate_are_rejected() {
+    let dir = std::env::temp_dir().join(format!("zenith-conformance-{}", std::process::id()));
+    let expected = dir.join("expected");
+    fs::create_dir_all(&expected).unwrap();
+    for file in EXPECTED_FILES {
+        fs::write(expected.join(file), "{}").unwrap();
+    }
+    fs::write(
+        expected.join("recorded-with.txt"),
+        format!("{} {}\n", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+    )
+    .unwrap();
+    let err = load_expected(&dir).err();
+    fs::write(
+        expected.join("recorded-with.txt"),
+        "@zenithbuild/compiler 1.3.18\n",
+    )
+    .unwrap();
+    let reference = load_expected(&dir).map(|loaded| loaded.map(|(_, by)| by));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(err.unwrap().contains("not the reference compiler"));
+    assert_eq!(
+        reference.unwrap().as_deref(),
+        Some("@zenithbuild/compiler 1.3.18")
+    );
+}
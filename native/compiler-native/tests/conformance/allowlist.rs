@@ -0,0 +1,183 @@
+//! Documented intentional differences (`tests/conformance/allowlist.toml`).
+//!
+//! ```toml
+//! [[normalize]]
+//! case = "loops"
+//! # Renumber `expr_N` ids by first appearance on both sides
+//! expression_ids = "why the ids may differ"
+//!
+//! [[ignore]]
+//! case = "svg_icons"
+//! path = "/ir/template/nodes/**/location"
+//! reason = "why the values may differ"
+//! ```
+//!
+//! Every entry names the one case it documents. A `path` is a JSON pointer in
+//! which `*` stands for one segment and `**` for any number of them; it covers
+//! the value it names and everything below it. Every entry needs a reason, and
+//! an entry that no longer matches a difference fails the run, so the list only
+//! ever holds divergences that exist.
+
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Allowlist {
+    #[serde(default)]
+    pub normalize: Vec<Normalize>,
+    #[serde(default)]
+    pub ignore: Vec<Ignore>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Normalize {
+    pub case: String,
+    /// Reason the case's expression ids are compared by first appearance
+    pub expression_ids: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Ignore {
+    pub case: String,
+    pub path: String,
+    pub reason: String,
+}
+
+impl Ignore {
+    pub fn applies_to(&self, case: &str) -> bool {
+        self.case == case
+    }
+
+    /// Whether `pointer` is the value `path` names or below it
+    pub fn covers(&self, pointer: &str) -> bool {
+        let pattern: Vec<&str> = segments(&self.path);
+        let pointer: Vec<&str> = segments(pointer);
+        covers(&pattern, &pointer)
+    }
+}
+
+impl Allowlist {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let list: Allowlist = toml::from_str(source).map_err(|e| e.to_string())?;
+        for entry in &list.normalize {
+            check_case(&entry.case)?;
+            if entry.expression_ids.trim().is_empty() {
+                return Err(format!(
+                    "`expression_ids` for `{}` needs a reason",
+                    entry.case
+                ));
+            }
+        }
+        for entry in &list.ignore {
+            check_case(&entry.case)?;
+            if entry.reason.trim().is_empty() {
+                return Err(format!("the entry for `{}` needs a reason", entry.path));
+            }
+            if !entry.path.starts_with('/') {
+                return Err(format!(
+                    "`{}` is not a JSON pointer (it must start with `/`)",
+                    entry.path
+                ));
+            }
+        }
+        Ok(list)
+    }
+
+    /// Whether `case` compares expression ids by first appearance
+    pub fn normalizes_ids(&self, case: &str) -> bool {
+        self.normalize.iter().any(|entry| entry.case == case)
+    }
+
+    /// Cases the entries name
+    pub fn cases(&self) -> impl Iterator<Item = &str> {
+        self.normalize
+            .iter()
+            .map(|entry| entry.case.as_str())
+            .chain(self.ignore.iter().map(|entry| entry.case.as_str()))
+    }
+
+    /// The first entry that allows a difference at `pointer` in `case`
+    pub fn allowing(&self, case: &str, pointer: &str) -> Option<usize> {
+        self.ignore
+            .iter()
+            .position(|entry| entry.applies_to(case) && entry.covers(pointer))
+    }
+}
+
+/// Entries document one case each, never the whole corpus
+fn check_case(case: &str) -> Result<(), String> {
+    if case.is_empty() || case.contains(['*', '/']) {
+        return Err(format!(
+            "`{}` is not a case name; list the entry under each case it documents",
+            case
+        ));
+    }
+    Ok(())
+}
+
+fn segments(pointer: &str) -> Vec<&str> {
+    pointer.split('/').skip(1).collect()
+}
+
+fn covers(pattern: &[&str], pointer: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => true,
+        Some((&"**", rest)) => (0..=pointer.len()).any(|skip| covers(rest, &pointer[skip..])),
+        Some((segment, rest)) => match pointer.split_first() {
+            Some((head, tail)) => (*segment == "*" || segment == head) && covers(rest, tail),
+            None => false,
+        },
+    }
+}
+
+#[test]
+fn patterns_cover_their_subtree() {
+    let entry = Ignore {
+        case: "layout".to_string(),
+        path: "/ir/template/nodes/**/location".to_string(),
+        reason: "positions".to_string(),
+    };
+    assert!(entry.covers("/ir/template/nodes/0/location"));
+    assert!(entry.covers("/ir/template/nodes/0/children/3/location/line"));
+    assert!(!entry.covers("/ir/template/nodes/0/tag"));
+    assert!(!entry.covers("/ir/template/expressions/0/location"));
+
+    let entry = Ignore {
+        case: "loops".to_string(),
+        path: "/bindings/*/target".to_string(),
+        reason: "targets".to_string(),
+    };
+    assert!(entry.applies_to("loops") && !entry.applies_to("slots"));
+    assert!(entry.covers("/bindings/2/target"));
+    assert!(!entry.covers("/bindings/2"));
+}
+
+#[test]
+fn entries_need_a_reason() {
+    let err = Allowlist::parse("[[ignore]]\ncase = \"svg\"\npath = \"/html\"\nreason = \" \"\n")
+        .unwrap_err();
+    assert!(err.contains("needs a reason"), "{}", err);
+    let err = Allowlist::parse("[[ignore]]\ncase = \"svg\"\npath = \"html\"\nreason = \"x\"\n")
+        .unwrap_err();
+    assert!(err.contains("not a JSON pointer"), "{}", err);
+    assert!(Allowlist::parse("[[ignore]]\ncase = \"svg\"\npath = \"/html\"\n").is_err());
+    let err =
+        Allowlist::parse("[[normalize]]\ncase = \"svg\"\nexpression_ids = \"\"\n").unwrap_err();
+    assert!(err.contains("needs a reason"), "{}", err);
+}
+
+#[test]
+fn entries_name_one_case() {
+    let err = Allowlist::parse("[[ignore]]\ncase = \"*\"\npath = \"/html\"\nreason = \"x\"\n")
+        .unwrap_err();
+    assert!(err.contains("not a case name"), "{}", err);
+    let err =
+        Allowlist::parse("[[normalize]]\ncase = \"*\"\nexpression_ids = \"x\"\n").unwrap_err();
+    assert!(err.contains("not a case name"), "{}", err);
+
+    let list =
+        Allowlist::parse("[[normalize]]\ncase = \"loops\"\nexpression_ids = \"x\"\n").unwrap();
+    assert!(list.normalizes_ids("loops") && !list.normalizes_ids("svg"));
+}
@@ -0,0 +1,215 @@
+//! Field-level JSON differ.
+//!
+//! Objects are compared key by key and arrays of objects index by index, so a
+//! difference is reported at the deepest field that differs. Arrays of plain
+//! values (the HTML lines) are aligned by their longest common subsequence
+//! instead, so one inserted line is one difference and not a shift of every
+//! line after it. Pointers of missing values point into the expected document,
+//! pointers of unexpected values into the actual one.
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Changed { expected: Value, actual: Value },
+    Missing(Value),
+    Unexpected(Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    /// JSON pointer (RFC 6901) of the differing value
+    pub pointer: String,
+    pub change: Change,
+}
+
+impl Difference {
+    /// One line for failure messages and the report
+    pub fn describe(&self) -> String {
+        match &self.change {
+            Change::Changed { expected, actual } => format!(
+                "`{}` changed: {} → {}",
+                self.pointer,
+                excerpt(expected),
+                excerpt(actual)
+            ),
+            Change::Missing(value) => format!("`{}` missing: {}", self.pointer, excerpt(value)),
+            Change::Unexpected(value) => {
+                format!("`{}` unexpected: {}", self.pointer, excerpt(value))
+            }
+        }
+    }
+}
+
+/// Every difference between `expected` and `actual`, in document order
+pub fn diff(expected: &Value, actual: &Value) -> Vec<Difference> {
+    let mut out = Vec::new();
+    diff_at("", expected, actual, &mut out);
+    out
+}
+
+/// Number of leaf values in `value`, the fields a comparison covers
+pub fn count_fields(value: &Value) -> usize {
+    match value {
+        Value::Object(map) => map.values().map(count_fields).sum(),
+        Value::Array(items) => items.iter().map(count_fields).sum(),
+        _ => 1,
+    }
+}
+
+/// `segment` escaped for a JSON pointer
+pub fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn diff_at(pointer: &str, expected: &Value, actual: &Value, out: &mut Vec<Difference>) {
+    match (expected, actual) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let at = format!("{}/{}", pointer, escape(key));
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_at(&at, x, y, out),
+                    (Some(x), None) => out.push(Difference {
+                        pointer: at,
+                        change: Change::Missing(x.clone()),
+                    }),
+                    (None, Some(y)) => out.push(Difference {
+                        pointer: at,
+                        change: Change::Unexpected(y.clone()),
+                    }),
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if is_flat(a) && is_flat(b) => {
+            diff_flat(pointer, a, b, out)
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let at = format!("{}/{}", pointer, i);
+                match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => diff_at(&at, x, y, out),
+                    (Some(x), None) => out.push(Difference {
+                        pointer: at,
+                        change: Change::Missing(x.clone()),
+                    }),
+                    (None, Some(y)) => out.push(Difference {
+                        pointer: at,
+                        change: Change::Unexpected(y.clone()),
+                    }),
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        (a, b) if a != b => out.push(Difference {
+            pointer: pointer.to_string(),
+            change: Change::Changed {
+                expected: a.clone(),
+                actual: b.clone(),
+            },
+        }),
+        _ => {}
+    }
+}
+
+fn is_flat(items: &[Value]) -> bool {
+    items
+        .iter()
+        .all(|v| !matches!(v, Value::Object(_) | Value::Array(_)))
+}
+
+/// Align two arrays of plain values by their longest common subsequence; a
+/// removal directly followed by an insertion is one change at that position
+fn diff_flat(pointer: &str, a: &[Value], b: &[Value], out: &mut Vec<Difference>) {
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if i < a.len() && j < b.len() && lcs[i + 1][j + 1] == lcs[i][j] {
+            out.push(Difference {
+                pointer: format!("{}/{}", pointer, i),
+                change: Change::Changed {
+                    expected: a[i].clone(),
+                    actual: b[j].clone(),
+                },
+            });
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            out.push(Difference {
+                pointer: format!("{}/{}", pointer, j),
+                change: Change::Unexpected(b[j].clone()),
+            });
+            j += 1;
+        } else {
+            out.push(Difference {
+                pointer: format!("{}/{}", pointer, i),
+                change: Change::Missing(a[i].clone()),
+            });
+            i += 1;
+        }
+    }
+}
+
+/// Compact JSON of `value`, cut to one readable line
+fn excerpt(value: &Value) -> String {
+    let text = value.to_string();
+    if text.chars().count() > 80 {
+        format!("{}…", text.chars().take(80).collect::<String>())
+    } else {
+        text
+    }
+}
+
+#[test]
+fn reports_the_deepest_differing_field() {
+    let expected = serde_json::json!({ "a": { "b": [1, { "c": "x" }] }, "gone": true });
+    let actual = serde_json::json!({ "a": { "b": [1, { "c": "y" }] }, "new/key": 2 });
+    let pointers: Vec<String> = diff(&expected, &actual)
+        .into_iter()
+        .map(|d| d.describe())
+        .collect();
+    assert_eq!(
+        pointers,
+        [
+            "`/a/b/1/c` changed: \"x\" → \"y\"",
+            "`/gone` missing: true",
+            "`/new~1key` unexpected: 2",
+        ]
+    );
+}
+
+#[test]
+fn aligns_lines_around_an_insertion() {
+    let expected = serde_json::json!(["<main>", "<p>a</p>", "<p>b</p>", "</main>"]);
+    let actual = serde_json::json!(["<main>", "<p>a</p>", "<hr />", "<p>b</p>", "</main>"]);
+    let differences = diff(&expected, &actual);
+    assert_eq!(
+        differences,
+        [Difference {
+            pointer: "/2".to_string(),
+            change: Change::Unexpected(serde_json::json!("<hr />")),
+        }]
+    );
+
+    let actual = serde_json::json!(["<main>", "<p>A</p>", "<p>b</p>", "</main>"]);
+    assert_eq!(
+        diff(&expected, &actual)[0].describe(),
+        "`/1` changed: \"<p>a</p>\" → \"<p>A</p>\""
+    );
+}
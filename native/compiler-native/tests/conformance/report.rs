@@ -0,0 +1,132 @@
+//! `tests/conformance/REPORT.md`: per case what was compared and which
+//! differences the allowlist let through, so a drift review reads one file.
+
+use crate::allowlist::Allowlist;
+use crate::diff::Difference;
+
+/// What comparing one case found
+#[derive(Debug, Default)]
+pub struct CaseOutcome {
+    pub name: String,
+    pub description: String,
+    /// Implementation and version that recorded the expected outputs; unset
+    /// while the case awaits a recording of the reference compiler
+    pub recorded_with: Option<String>,
+    /// Leaf values in the expected document
+    pub fields: usize,
+    /// Differences with the index of the allowlist entry that allows each
+    pub allowed: Vec<(Difference, usize)>,
+    pub unallowed: Vec<Difference>,
+    /// Whether renumbering expression ids hid differences
+    pub ids_normalized: bool,
+    /// Why the case could not be compared at all
+    pub problem: Option<String>,
+}
+
+pub fn render(outcomes: &[CaseOutcome], allowlist: &Allowlist) -> String {
+    let mut out = String::from(
+        "# Conformance Report\n\n\
+         Generated by `UPDATE_SNAPSHOTS=1 cargo test --test conformance` from the\n\
+         cases in `cases/` and the documented differences in `allowlist.toml`.\n\n",
+    );
+
+    out.push_str("| Case | Recorded with | Fields | Allowed | Unallowed |\n");
+    out.push_str("|------|---------------|-------:|--------:|----------:|\n");
+    for case in outcomes {
+        let unallowed = match (&case.problem, &case.recorded_with) {
+            (None, Some(_)) => case.unallowed.len().to_string(),
+            _ => "not compared".to_string(),
+        };
+        out.push_str(&format!(
+            "| `{}` | {} | {} | {} | {} |\n",
+            case.name,
+            case.recorded_with
+                .as_deref()
+                .unwrap_or("awaiting reference recording"),
+            case.fields,
+            case.allowed.len(),
+            unallowed
+        ));
+    }
+
+    out.push_str("\n## Cases\n\n");
+    for case in outcomes {
+        out.push_str(&format!("- `{}`: {}\n", case.name, case.description));
+    }
+
+    out.push_str("\n## Normalizations\n\n");
+    if allowlist.normalize.is_empty() {
+        out.push_str("None.\n");
+    }
+    for entry in &allowlist.normalize {
+        out.push_str(&format!(
+            "- `{}` compares expression ids by first appearance: {}\n",
+            entry.case, entry.expression_ids
+        ));
+    }
+
+    out.push_str("\n## Allowlist\n\n");
+    if allowlist.ignore.is_empty() {
+        out.push_str("No entries.\n");
+    }
+    for (index, entry) in allowlist.ignore.iter().enumerate() {
+        let matched: Vec<(&str, &Difference)> = outcomes
+            .iter()
+            .flat_map(|case| {
+                case.allowed
+                    .iter()
+                    .filter(move |(_, by)| *by == index)
+                    .map(move |(d, _)| (case.name.as_str(), d))
+            })
+            .collect();
+        out.push_str(&format!(
+            "{}. `{}` in `{}`: {} ({} {})\n",
+            index + 1,
+            entry.path,
+            entry.case,
+            entry.reason,
+            matched.len(),
+            if matched.len() == 1 {
+                "difference"
+            } else {
+                "differences"
+            }
+        ));
+        for (case, difference) in matched {
+            out.push_str(&format!("   - `{}` {}\n", case, difference.describe()));
+        }
+    }
+
+    let failing: Vec<&CaseOutcome> = outcomes
+        .iter()
+        .filter(|c| c.problem.is_some() || !c.unallowed.is_empty())
+        .collect();
+    if !failing.is_empty() {
+        out.push_str("\n## Unallowed differences\n");
+        for case in failing {
+            out.push_str(&format!("\n### `{}`\n\n", case.name));
+            if let Some(problem) = &case.problem {
+                out.push_str(&format!("- {}\n", problem));
+            }
+            for difference in &case.unallowed {
+                out.push_str(&format!("- {}\n", difference.describe()));
+            }
+        }
+    }
+    out
+}
+
+/// Allowlist entries that would let `differences` of `case` through, for the
+/// failure message; the reason is left for the reviewer to write
+pub fn suggested_entries(case: &str, differences: &[Difference]) -> String {
+    differences
+        .iter()
+        .map(|d| {
+            format!(
+                "[[ignore]]\ncase = \"{}\"\npath = \"{}\"\nreason = \"\"\n",
+                case, d.pointer
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
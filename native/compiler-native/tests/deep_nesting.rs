@@ -0,0 +1,43 @@
+//! Markup nested just under the default limit compiles on the main thread,
+//! whose stack (8MB on Linux) is all a caller like Node gives the compiler.
+//! Runs without the test harness, which would move it to a worker thread.
+
+use compiler_native::{compile_zen_internal, DEFAULT_MAX_DEPTH, MAX_DEPTH_ERROR};
+
+/// A page whose deepest element is `depth` levels down, counting the
+/// `html > body > main` the parser wraps it in
+fn nested_page(depth: usize) -> String {
+    let divs = depth - 3;
+    format!(
+        "<script>\nstate label = \"deep\"\n</script>\n<main>{}<span>{{label}}</span>{}</main>",
+        "<div>".repeat(divs - 1),
+        "</div>".repeat(divs - 1)
+    )
+}
+
+fn main() {
+    assert_eq!(
+        std::thread::current().name(),
+        Some("main"),
+        "must run on the main thread"
+    );
+
+    let result = compile_zen_internal(
+        &nested_page(DEFAULT_MAX_DEPTH - 1),
+        "deep.zen",
+        Default::default(),
+    )
+    .unwrap_or_else(|e| panic!("compile failed: {}", e));
+    assert!(!result.has_errors, "{:?}", result.errors);
+    assert!(result.bindings.iter().any(|b| b.expression == "label"));
+
+    let err = compile_zen_internal(
+        &nested_page(DEFAULT_MAX_DEPTH + 1),
+        "deep.zen",
+        Default::default(),
+    )
+    .unwrap_err();
+    assert!(err.contains(MAX_DEPTH_ERROR), "{}", err);
+
+    println!("deep_nesting: ok");
+}
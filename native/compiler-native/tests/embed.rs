@@ -0,0 +1,112 @@
+//! `to_srcdoc` output survives a round trip through an HTML parser: the
+//! attribute decodes to the compiled document with the bundle inlined.
+
+use compiler_native::{compile_zen_internal, to_srcdoc, CompileOptions};
+use html5ever::tendril::TendrilSink;
+use html5ever::{parse_document, ParseOpts};
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+
+const COUNTER: &str = r#"<script>
+state count = 0
+const label = "Clicks <!-- so far --> & \"more\""
+function increment() { count++ }
+</script>
+<html>
+<head><title>Counter</title></head>
+<body>
+  <button onclick={increment} title="Say &quot;hi&quot;">{label} {count}</button>
+</body>
+</html>"#;
+
+fn parse(html: &str) -> RcDom {
+    parse_document(RcDom::default(), ParseOpts::default())
+        .from_utf8()
+        .read_from(&mut html.as_bytes())
+        .unwrap()
+}
+
+fn find_element(node: &Handle, tag: &str) -> Option<Handle> {
+    if let NodeData::Element { name, .. } = &node.data {
+        if &*name.local == tag {
+            return Some(node.clone());
+        }
+    }
+    node.children
+        .borrow()
+        .iter()
+        .find_map(|child| find_element(child, tag))
+}
+
+fn attribute(node: &Handle, attr: &str) -> Option<String> {
+    match &node.data {
+        NodeData::Element { attrs, .. } => attrs
+            .borrow()
+            .iter()
+            .find(|a| &*a.name.local == attr)
+            .map(|a| a.value.to_string()),
+        _ => None,
+    }
+}
+
+fn text(node: &Handle) -> String {
+    node.children
+        .borrow()
+        .iter()
+        .filter_map(|child| match &child.data {
+            NodeData::Text { contents } => Some(contents.borrow().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn srcdoc_round_trips_through_an_html_parser() {
+    let result = compile_zen_internal(
+        COUNTER,
+        "Counter.zen",
+        CompileOptions {
+            embed_mode: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(!result.has_errors, "{:?}", result.errors);
+    let bundle = result.manifest.as_ref().unwrap().bundle.clone();
+    assert!(bundle.contains("<!--"), "fixture should exercise escaping");
+    assert!(!bundle.contains("document.readyState"), "{}", bundle);
+
+    let srcdoc = to_srcdoc(&result);
+    assert!(!srcdoc.contains('"'), "{}", srcdoc);
+    assert!(!srcdoc.to_lowercase().contains("</script>"), "{}", srcdoc);
+
+    // The docs page embeds the value verbatim
+    let page = parse(&format!(
+        "<!DOCTYPE html><html><body><iframe srcdoc=\"{}\"></iframe><p id=\"after\">after</p></body></html>",
+        srcdoc
+    ));
+    let iframe = find_element(&page.document, "iframe").unwrap();
+    assert!(iframe.children.borrow().is_empty());
+    assert!(find_element(&page.document, "p")
+        .is_some_and(|p| attribute(&p, "id").as_deref() == Some("after")));
+
+    // The decoded value is the compiled page with the bundle as its last body child
+    let document = attribute(&iframe, "srcdoc").unwrap();
+    assert!(document.starts_with(result.html.split("</body>").next().unwrap()));
+    let inner = parse(&document);
+    let body = find_element(&inner.document, "body").unwrap();
+    let button = find_element(&body, "button").unwrap();
+    assert_eq!(attribute(&button, "title").as_deref(), Some("Say \"hi\""));
+    let script = body
+        .children
+        .borrow()
+        .iter()
+        .rev()
+        .find(|c| matches!(c.data, NodeData::Element { .. }))
+        .cloned()
+        .unwrap();
+    assert!(find_element(&script, "script").is_some());
+    assert_eq!(attribute(&script, "type").as_deref(), Some("module"));
+    let code = text(&script);
+    assert!(!code.contains("<!--"), "{}", code);
+    assert_eq!(code.replace(r"<\!--", "<!--"), bundle);
+}
@@ -0,0 +1,208 @@
+//! Golden end-to-end fixtures (parse → resolve → transform → finalize).
+//!
+//! Run with `UPDATE_SNAPSHOTS=1 cargo test --test golden` to accept output changes.
+
+mod support;
+
+use support::{
+    assert_fixture_snapshots, canonical_ids, compile_fixture, compile_fixture_with,
+    format_bindings, format_diagnostics, format_expressions, line_diff, normalize_html,
+};
+
+fn compile_clean(name: &str) -> compiler_native::CompileResult {
+    let result = compile_fixture(name);
+    assert!(
+        !result.has_errors,
+        "fixture `{}` failed to compile:\n{}",
+        name,
+        result.errors.join("\n")
+    );
+    assert_fixture_snapshots(name, &result);
+    result
+}
+
+#[test]
+fn loop_conditional() {
+    let result = compile_clean("loop_conditional");
+    assert!(result.bindings.iter().any(|b| b.r#type == "loop"));
+}
+
+#[test]
+fn slots_in_loop() {
+    let result = compile_clean("slots_in_loop");
+    assert!(result.html.contains("class=\"card\""));
+    assert!(!result.html.contains("<slot"));
+    // Default options: the `.map()` is not lowered to a loop
+    assert!(result
+        .bindings
+        .iter()
+        .any(|b| b.expression.starts_with("posts.map(")));
+}
+
+#[test]
+fn document_layout() {
+    let result = compile_clean("document_layout");
+    assert!(result.html.contains("<h1>Welcome</h1>"));
+    assert_eq!(result.html.matches("<body").count(), 1);
+}
+
+#[test]
+fn nested_layout() {
+    let result = compile_clean("nested_layout");
+    let html = &result.html;
+
+    // One document skeleton, with both layouts' content around the page
+    assert_eq!(html.matches("<html").count(), 1);
+    assert_eq!(html.matches("<body").count(), 1);
+    assert!(html.contains("<aside>Sidebar</aside>"));
+    assert!(html.contains("<h1>Getting Started</h1>"));
+
+    // The page's title prop overrides DocsLayout's; BaseLayout's script sees both levels
+    assert_eq!(html.matches("<title>").count(), 1);
+    assert!(html.contains("<title>Getting Started | Zenith Docs</title>"));
+
+    let styles = &result.manifest.as_ref().unwrap().styles;
+    assert_eq!(styles.matches(".site").count(), 1);
+    assert_eq!(styles.matches(".docs").count(), 1);
+
+    let layouts: Vec<&str> = result
+        .dep_graph
+        .nodes
+        .iter()
+        .filter(|n| n.kind == compiler_native::DepKind::Layout)
+        .map(|n| n.path.as_str())
+        .collect();
+    assert_eq!(
+        layouts,
+        vec!["components/BaseLayout.zen", "components/DocsLayout.zen"]
+    );
+}
+
+#[test]
+fn dep_graph() {
+    let result = compile_clean("dep_graph");
+    let graph = &result.dep_graph;
+
+    let ids: Vec<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "asset:/analytics.js",
+            "component:components/Button.zen",
+            "component:components/Card.zen",
+            "layout:components/Shell.zen",
+            "npm:date-fns",
+            "page:dep_graph/page.zen",
+        ]
+    );
+    // page → layout → Card → Button, plus Card's npm import and the layout's script
+    assert_eq!(graph.edges, vec![(2, 1), (2, 4), (3, 0), (3, 2), (5, 3)]);
+
+    assert_eq!(
+        graph.to_dot(),
+        r#"digraph deps {
+  "asset:/analytics.js" [label="/analytics.js", shape=note];
+  "component:components/Button.zen" [label="components/Button.zen", shape=box];
+  "component:components/Card.zen" [label="components/Card.zen", shape=box];
+  "layout:components/Shell.zen" [label="components/Shell.zen", shape=box];
+  "npm:date-fns" [label="date-fns", shape=ellipse];
+  "page:dep_graph/page.zen" [label="dep_graph/page.zen", shape=box];
+  "component:components/Card.zen" -> "component:components/Button.zen";
+  "component:components/Card.zen" -> "npm:date-fns";
+  "layout:components/Shell.zen" -> "asset:/analytics.js";
+  "layout:components/Shell.zen" -> "component:components/Card.zen";
+  "page:dep_graph/page.zen" -> "layout:components/Shell.zen";
+}
+"#
+    );
+}
+
+#[test]
+fn svg_page() {
+    let result = compile_clean("svg_page");
+    assert!(result.html.contains("viewBox"));
+    assert!(result.bindings.iter().any(|b| b.r#type == "attribute"));
+}
+
+#[test]
+fn intentional_errors() {
+    let result = compile_fixture("intentional_errors");
+    assert!(result.has_errors);
+    assert!(result
+        .errors
+        .iter()
+        .any(|e| e.starts_with("Z-ERR-UNKNOWN-FILTER") && e.contains("`currency`")));
+    assert_fixture_snapshots("intentional_errors", &result);
+}
+
+#[test]
+fn ts_component() {
+    let result = compile_clean("ts_component");
+    let manifest = result.manifest.as_ref().unwrap();
+    assert!(manifest.component_usages.iter().any(|u| u.tag == "Counter"));
+}
+
+#[test]
+fn component_events() {
+    let result = compile_clean("component_events");
+    let manifest = result.manifest.as_ref().unwrap();
+    assert_eq!(
+        manifest.component_events.get("ItemRow"),
+        Some(&vec!["select".to_string()])
+    );
+    assert!(!result
+        .warnings
+        .iter()
+        .any(|w| w.starts_with("Z-WARN-UNKNOWN-EVENT")));
+
+    // Each instance registers its parent's handler; the dispatcher looks it up by event name
+    let bundle = &manifest.bundle;
+    assert_eq!(bundle.matches("__locals.__events =").count(), 2);
+    assert!(bundle.contains("scope.locals.handleSelect"));
+    assert!(bundle.contains("scope.state.selected = item"));
+    assert!(bundle.contains("scope.locals.__events[event]"));
+}
+
+/// Everything a line-ending change could disturb, as one comparable string
+fn render_result(result: &compiler_native::CompileResult) -> String {
+    canonical_ids(&[
+        normalize_html(&result.html),
+        format_bindings(result),
+        format_diagnostics(result),
+        format_expressions(result),
+        result
+            .manifest
+            .as_ref()
+            .map_or(String::new(), |m| m.bundle.clone()),
+    ])
+    .concat()
+}
+
+#[test]
+fn line_endings_and_bom_do_not_change_output() {
+    for fixture in ["loop_conditional", "ts_component", "document_layout"] {
+        let lf = render_result(&compile_fixture(fixture));
+        let crlf = render_result(&compile_fixture_with(fixture, |s| s.replace('\n', "\r\n")));
+        let bom_crlf = render_result(&compile_fixture_with(fixture, |s| {
+            format!("\u{feff}{}", s.replace('\n', "\r\n"))
+        }));
+        assert!(
+            lf == crlf,
+            "CRLF changed `{}`:\n{}",
+            fixture,
+            line_diff(&lf, &crlf)
+        );
+        assert!(
+            lf == bom_crlf,
+            "BOM+CRLF changed `{}`:\n{}",
+            fixture,
+            line_diff(&lf, &bom_crlf)
+        );
+    }
+}
+
+#[test]
+fn diff_marks_changed_lines() {
+    let diff = line_diff("a\nb\nc\n", "a\nx\nc\n");
+    assert_eq!(diff, "  a\n+ x\n- b\n  c\n");
+}
@@ -0,0 +1,94 @@
+//! Public API surface check.
+//!
+//! The exported names of `src/prelude.rs` and the root re-exports in
+//! `src/lib.rs` are compared against the checked-in `tests/public_api.txt`.
+//! An intentional surface change must update that file in the same commit
+//! (`UPDATE_SNAPSHOTS=1 cargo test --test public_api` rewrites it).
+
+use std::fs;
+use std::path::Path;
+
+/// Names exported by `pub use` statements, tagged with their gating attribute
+fn exported_names(source: &str, module: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut tag: Option<&str> = None;
+    let mut statement: Option<String> = None;
+
+    for line in source.lines().map(str::trim) {
+        if let Some(stmt) = statement.as_mut() {
+            stmt.push_str(line);
+        } else if line == "#[cfg(feature = \"napi\")]" {
+            tag = Some("napi");
+            continue;
+        } else if line == "#[doc(hidden)]" {
+            tag = Some("compat");
+            continue;
+        } else if line.starts_with("pub use ") {
+            statement = Some(line.to_string());
+        } else {
+            if !line.is_empty() && !line.starts_with("//") {
+                tag = None;
+            }
+            continue;
+        }
+
+        let stmt = statement.as_deref().unwrap();
+        if !stmt.ends_with(';') {
+            continue;
+        }
+        let items = match (stmt.find('{'), stmt.rfind('}')) {
+            (Some(open), Some(close)) => &stmt[open + 1..close],
+            _ => stmt.trim_end_matches(';').rsplit("::").next().unwrap_or(""),
+        };
+        let label = match tag {
+            Some(tag) => format!("{}[{}]", module, tag),
+            None => module.to_string(),
+        };
+        for item in items.split(',').map(str::trim) {
+            if !item.is_empty() && item != "*" {
+                names.push(format!("{}: {}", label, item));
+            }
+        }
+        statement = None;
+        tag = None;
+    }
+    names
+}
+
+#[test]
+fn public_api_matches_snapshot() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let read = |p: &str| fs::read_to_string(root.join(p)).unwrap();
+
+    let mut names = exported_names(&read("src/prelude.rs"), "prelude");
+    names.extend(exported_names(&read("src/lib.rs"), "root"));
+    names.sort();
+    let actual = format!("{}\n", names.join("\n"));
+
+    let snapshot = root.join("tests").join("public_api.txt");
+    if std::env::var("UPDATE_SNAPSHOTS").is_ok_and(|v| !v.is_empty() && v != "0") {
+        fs::write(&snapshot, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&snapshot).unwrap_or_default();
+    assert!(
+        expected == actual,
+        "public API changed; update tests/public_api.txt if intentional.\nexpected:\n{}\nactual:\n{}",
+        expected,
+        actual
+    );
+}
+
+/// The prelude alone is enough to compile a page and inspect its IR types
+#[test]
+fn prelude_covers_compile_round_trip() {
+    use compiler_native::prelude::*;
+
+    let result: CompileResult =
+        compile_zen_internal("<p>Hello</p>", "page.zen", CompileOptions::default()).unwrap();
+    let manifest: ZenManifestExport = result.manifest.unwrap();
+    assert!(manifest.is_static);
+
+    let template: TemplateIR = parse_template("<p>{x}</p>", "page.zen").unwrap();
+    assert!(matches!(template.nodes[0], TemplateNode::Element(_)));
+}
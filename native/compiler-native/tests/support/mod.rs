@@ -0,0 +1,273 @@
+//! Golden Fixture Harness
+//!
+//! Each fixture lives in `tests/fixtures/<name>/` and is described by a
+//! `fixture.json` sidecar:
+//!
+//! ```json
+//! {
+//!   "page": "page.zen",
+//!   "components": { "Card": "components/Card.zen" },
+//!   "props": { "title": "Home" },
+//!   "filters": ["currency"],
+//!   "reactiveHead": false
+//! }
+//! ```
+//!
+//! Component sources are turned into registry entries with
+//! `ComponentIR::from_source`, so fixtures exercise the full
+//! parse → resolve → transform → finalize pipeline.
+//!
+//! Snapshots are stored in `tests/fixtures/<name>/snapshots/<section>.snap`.
+//! A missing or different snapshot fails the test; set `UPDATE_SNAPSHOTS=1` to
+//! record new snapshots or rewrite existing ones after an intentional output change.
+
+use compiler_native::{compile_zen_internal, CompileOptions, CompileResult, ComponentIR};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FixtureSpec {
+    page: Option<String>,
+    /// Tag name → component source path (relative to the fixture directory)
+    components: BTreeMap<String, String>,
+    props: HashMap<String, String>,
+    filters: Vec<String>,
+    reactive_head: bool,
+    ast_classifier: bool,
+}
+
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e))
+}
+
+/// Compile a fixture page with its component registry.
+/// Hard compile failures are folded into `errors` so they can be snapshotted too.
+pub fn compile_fixture(name: &str) -> CompileResult {
+    compile_fixture_with(name, |source| source.to_string())
+}
+
+/// Like `compile_fixture`, with `rewrite` applied to every source file
+/// (page and components) before compilation.
+pub fn compile_fixture_with(name: &str, rewrite: impl Fn(&str) -> String) -> CompileResult {
+    let dir = fixtures_dir().join(name);
+    let spec: FixtureSpec = serde_json::from_str(&read(&dir.join("fixture.json")))
+        .unwrap_or_else(|e| panic!("invalid fixture.json for `{}`: {}", name, e));
+
+    let mut components = HashMap::new();
+    for (tag, rel_path) in &spec.components {
+        let source = rewrite(&read(&dir.join(rel_path)));
+        let component = ComponentIR::from_source(tag, rel_path, &source)
+            .unwrap_or_else(|e| panic!("component `{}` in `{}`: {}", tag, name, e));
+        components.insert(tag.clone(), serde_json::to_value(component).unwrap());
+    }
+
+    let page = spec.page.unwrap_or_else(|| "page.zen".to_string());
+    let source = rewrite(&read(&dir.join(&page)));
+    let options = CompileOptions {
+        components,
+        props: spec.props,
+        filters: spec.filters,
+        reactive_head: spec.reactive_head,
+        ast_classifier: spec.ast_classifier,
+        ..Default::default()
+    };
+
+    match compile_zen_internal(&source, &format!("{}/{}", name, page), options) {
+        Ok(result) => result,
+        Err(e) => CompileResult {
+            html: String::new(),
+            has_errors: true,
+            errors: vec![e],
+            warnings: vec![],
+            manifest: None,
+            bindings: vec![],
+            i18n_keys: vec![],
+            preload_hints: vec![],
+            navigation_targets: vec![],
+            dep_graph: Default::default(),
+            module_code: None,
+            style_chunks: vec![],
+        },
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// NORMALIZATION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// One tag boundary per line, trimmed, without blank lines
+pub fn normalize_html(html: &str) -> String {
+    let mut out = String::new();
+    for line in html.replace("><", ">\n<").lines() {
+        let line = line.trim();
+        if !line.is_empty() {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Renumber `expr_N` ids by first appearance across `sections`, so golden
+/// files read `expr_0`, `expr_1`, ... instead of the ids' anchor hashes.
+pub fn canonical_ids(sections: &[String]) -> Vec<String> {
+    let id = Regex::new(r"expr_(\d+)").unwrap();
+    let mut renumbered: HashMap<String, usize> = HashMap::new();
+    for section in sections {
+        for caps in id.captures_iter(section) {
+            let next = renumbered.len();
+            renumbered.entry(caps[1].to_string()).or_insert(next);
+        }
+    }
+    sections
+        .iter()
+        .map(|section| {
+            id.replace_all(section, |caps: &regex::Captures| {
+                format!("expr_{}", renumbered[&caps[1]])
+            })
+            .into_owned()
+        })
+        .collect()
+}
+
+pub fn format_bindings(result: &CompileResult) -> String {
+    result
+        .bindings
+        .iter()
+        .map(|b| format!("{} {} {} = {}\n", b.id, b.r#type, b.target, b.expression))
+        .collect()
+}
+
+/// Errors then warnings, each group sorted so collection order doesn't matter
+pub fn format_diagnostics(result: &CompileResult) -> String {
+    let mut errors = result.errors.clone();
+    let mut warnings = result.warnings.clone();
+    errors.sort();
+    warnings.sort();
+
+    let mut out = format!("has_errors: {}\n", result.has_errors);
+    for e in errors {
+        out.push_str(&format!("error: {}\n", e));
+    }
+    for w in warnings {
+        out.push_str(&format!("warning: {}\n", w));
+    }
+    out
+}
+
+pub fn format_expressions(result: &CompileResult) -> String {
+    let expressions = result
+        .manifest
+        .as_ref()
+        .map(|m| m.expressions.as_str())
+        .unwrap_or("");
+    let mut out: String = expressions
+        .lines()
+        .map(|l| format!("{}\n", l.trim_end()))
+        .collect();
+    if out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SNAPSHOTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn update_requested() -> bool {
+    std::env::var("UPDATE_SNAPSHOTS").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Line diff based on the longest common subsequence
+pub fn line_diff(expected: &str, actual: &str) -> String {
+    let a: Vec<&str> = expected.lines().collect();
+    let b: Vec<&str> = actual.lines().collect();
+
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            out.push_str(&format!("  {}\n", a[i]));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            out.push_str(&format!("+ {}\n", b[j]));
+            j += 1;
+        } else {
+            out.push_str(&format!("- {}\n", a[i]));
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Compare `actual` against `snapshots/<section>.snap` of the fixture
+pub fn assert_snapshot(fixture: &str, section: &str, actual: &str) {
+    let path = fixtures_dir()
+        .join(fixture)
+        .join("snapshots")
+        .join(format!("{}.snap", section));
+    assert_snapshot_file(&path, &format!("{}/{}", fixture, section), actual);
+}
+
+/// Compare `actual` against the snapshot stored at `path`, called `name` in failures
+pub fn assert_snapshot_file(path: &Path, name: &str, actual: &str) {
+    if update_requested() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, actual).unwrap();
+        return;
+    }
+    if !path.exists() {
+        panic!(
+            "missing snapshot {} (run with UPDATE_SNAPSHOTS=1 to record it)",
+            name
+        );
+    }
+
+    let expected = read(path);
+    if expected != actual {
+        panic!(
+            "snapshot mismatch for {} (rerun with UPDATE_SNAPSHOTS=1 to accept):\n{}",
+            name,
+            line_diff(&expected, actual)
+        );
+    }
+}
+
+/// Snapshot the normalized HTML, bindings, diagnostics, and bundle expressions
+pub fn assert_fixture_snapshots(fixture: &str, result: &CompileResult) {
+    let sections = canonical_ids(&[
+        normalize_html(&result.html),
+        format_bindings(result),
+        format_diagnostics(result),
+        format_expressions(result),
+    ]);
+    for (name, actual) in ["html", "bindings", "diagnostics", "expressions"]
+        .iter()
+        .zip(&sections)
+    {
+        assert_snapshot(fixture, name, actual);
+    }
+}
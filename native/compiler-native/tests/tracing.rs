@@ -0,0 +1,104 @@
+//! Compile phases emit tracing spans (only built with `--features tracing`).
+
+#![cfg(feature = "tracing")]
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use compiler_native::{compile_zen_internal, CompileOptions};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// (span name, `file_path` field if present)
+type Spans = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+/// Records every span that is created; everything else is ignored
+struct SpanRecorder {
+    spans: Spans,
+}
+
+struct FilePathVisitor(Option<String>);
+
+impl Visit for FilePathVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "file_path" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let mut visitor = FilePathVisitor(None);
+        attrs.record(&mut visitor);
+        let mut spans = self.spans.lock().unwrap();
+        spans.push((attrs.metadata().name().to_string(), visitor.0));
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+    fn event(&self, _: &Event<'_>) {}
+    fn enter(&self, _: &Id) {}
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn compile_emits_phase_spans() {
+    let spans: Spans = Arc::default();
+    let recorder = SpanRecorder {
+        spans: spans.clone(),
+    };
+
+    let mut components = HashMap::new();
+    components.insert(
+        "Badge".to_string(),
+        serde_json::json!({
+            "name": "Badge",
+            "nodes": [{ "type": "element", "tag": "span", "attributes": [], "children": [] }]
+        }),
+    );
+    let source = "<script>\nstate count = 0\n</script>\n<div><Badge /><p>{count}</p></div>";
+
+    let result = tracing::subscriber::with_default(recorder, || {
+        compile_zen_internal(
+            source,
+            "page.zen",
+            CompileOptions {
+                components,
+                ..Default::default()
+            },
+        )
+    })
+    .unwrap();
+    assert!(!result.has_errors, "{:?}", result.errors);
+
+    let spans = spans.lock().unwrap();
+    let names: Vec<&str> = spans.iter().map(|(name, _)| name.as_str()).collect();
+    for phase in [
+        "compile",
+        "parse",
+        "resolve",
+        "resolve_component_node",
+        "transform",
+        "finalize",
+        "codegen",
+        "compute_expression_intent",
+    ] {
+        assert!(
+            names.contains(&phase),
+            "missing `{}` span in {:?}",
+            phase,
+            names
+        );
+    }
+
+    let compile = spans.iter().find(|(name, _)| name == "compile").unwrap();
+    assert_eq!(compile.1.as_deref(), Some("page.zen"));
+}